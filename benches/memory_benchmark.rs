@@ -1,7 +1,8 @@
 use criterion::{Criterion, black_box, criterion_group, criterion_main};
-use rudu::Args;
+use rudu::ScanOptions;
 use rudu::cli::SortKey;
 use rudu::scan::{scan_files_and_dirs, scan_files_and_dirs_incremental};
+use rudu::testing::TreeSpec;
 use rudu::thread_pool::ThreadPoolStrategy;
 use rudu::utils::build_exclude_matcher;
 use std::fs;
@@ -13,6 +14,7 @@ use procfs::process::Process;
 
 /// Memory usage tracker for benchmarks
 #[derive(Debug, Clone)]
+#[allow(dead_code)]
 struct MemoryUsage {
     peak_rss: u64,  // Peak resident set size in bytes
     peak_vms: u64,  // Peak virtual memory size in bytes
@@ -48,10 +50,10 @@ impl MemoryUsage {
 
 #[cfg(target_os = "linux")]
 fn get_memory_usage() -> (u64, u64) {
-    if let Ok(process) = Process::myself() {
-        if let Ok(stat) = process.stat() {
-            return (stat.rss * 4096, stat.vsize); // RSS is in pages, VMS is in bytes
-        }
+    if let Ok(process) = Process::myself()
+        && let Ok(stat) = process.stat()
+    {
+        return (stat.rss * 4096, stat.vsize); // RSS is in pages, VMS is in bytes
     }
     (0, 0)
 }
@@ -64,22 +66,13 @@ fn get_memory_usage() -> (u64, u64) {
 }
 
 fn create_test_directory_structure(dir: &Path, depth: usize, files_per_dir: usize) {
-    if depth == 0 {
-        return;
-    }
-
-    // Create files in current directory
-    for i in 0..files_per_dir {
-        let file_path = dir.join(format!("file_{}.txt", i));
-        fs::write(&file_path, format!("Content of file {}", i)).unwrap();
-    }
-
-    // Create subdirectories
-    for i in 0..3 {
-        let subdir_path = dir.join(format!("subdir_{}", i));
-        fs::create_dir_all(&subdir_path).unwrap();
-        create_test_directory_structure(&subdir_path, depth - 1, files_per_dir);
-    }
+    TreeSpec::new()
+        .depth(depth)
+        .fanout(3)
+        .files_per_dir(files_per_dir)
+        .file_size(20)
+        .generate(dir)
+        .unwrap();
 }
 
 fn create_large_directory_structure(dir: &Path, depth: usize, files_per_dir: usize) {
@@ -111,21 +104,7 @@ fn memory_benchmark_small_scan(c: &mut Criterion) {
     // Create a small directory structure
     create_test_directory_structure(root, 3, 10);
 
-    let args = Args {
-        path: root.to_path_buf(),
-        depth: None,
-        sort: rudu::cli::SortKey::Size,
-        show_files: false,
-        exclude: vec![],
-        show_owner: false,
-        output: None,
-        threads: None,
-        show_inodes: true,
-        threads_strategy: ThreadPoolStrategy::Default,
-        no_cache: false,
-        cache_ttl: 604800, // 7 days
-        profile: false,
-    };
+    let options = ScanOptions::new().show_files(false).show_inodes(true);
 
     let exclude_matcher = build_exclude_matcher(&[]).unwrap();
 
@@ -139,9 +118,9 @@ fn memory_benchmark_small_scan(c: &mut Criterion) {
 
                 let _result = scan_files_and_dirs(
                     black_box(root),
-                    black_box(&args),
+                    black_box(&options),
                     black_box(&exclude_matcher),
-                    black_box(SortKey::Size),
+                    black_box(&[SortKey::Size][..]),
                 )
                 .unwrap();
 
@@ -166,21 +145,7 @@ fn memory_benchmark_large_scan(c: &mut Criterion) {
     // Create a large directory structure to stress memory
     create_large_directory_structure(root, 5, 20);
 
-    let args = Args {
-        path: root.to_path_buf(),
-        depth: None,
-        sort: rudu::cli::SortKey::Size,
-        show_files: false,
-        exclude: vec![],
-        show_owner: false,
-        output: None,
-        threads: None,
-        show_inodes: true,
-        threads_strategy: ThreadPoolStrategy::Default,
-        no_cache: false,
-        cache_ttl: 604800, // 7 days
-        profile: false,
-    };
+    let options = ScanOptions::new().show_files(false).show_inodes(true);
 
     let exclude_matcher = build_exclude_matcher(&[]).unwrap();
 
@@ -194,9 +159,9 @@ fn memory_benchmark_large_scan(c: &mut Criterion) {
 
                 let _result = scan_files_and_dirs(
                     black_box(root),
-                    black_box(&args),
+                    black_box(&options),
                     black_box(&exclude_matcher),
-                    black_box(SortKey::Size),
+                    black_box(&[SortKey::Size][..]),
                 )
                 .unwrap();
 
@@ -221,21 +186,7 @@ fn memory_benchmark_cache_operations(c: &mut Criterion) {
     // Create a moderate directory structure
     create_test_directory_structure(root, 4, 15);
 
-    let args = Args {
-        path: root.to_path_buf(),
-        depth: None,
-        sort: rudu::cli::SortKey::Size,
-        show_files: false,
-        exclude: vec![],
-        show_owner: false,
-        output: None,
-        threads: None,
-        show_inodes: true,
-        threads_strategy: ThreadPoolStrategy::Default,
-        no_cache: false,
-        cache_ttl: 604800, // 7 days
-        profile: false,
-    };
+    let options = ScanOptions::new().show_files(false).show_inodes(true);
 
     let exclude_matcher = build_exclude_matcher(&[]).unwrap();
 
@@ -250,9 +201,9 @@ fn memory_benchmark_cache_operations(c: &mut Criterion) {
                 // First scan to create cache
                 let _result = scan_files_and_dirs(
                     black_box(root),
-                    black_box(&args),
+                    black_box(&options),
                     black_box(&exclude_matcher),
-                    black_box(SortKey::Size),
+                    black_box(&[SortKey::Size][..]),
                 )
                 .unwrap();
 
@@ -261,9 +212,9 @@ fn memory_benchmark_cache_operations(c: &mut Criterion) {
                 // Second scan using cache
                 let _result = scan_files_and_dirs_incremental(
                     black_box(root),
-                    black_box(&args),
+                    black_box(&options),
                     black_box(&exclude_matcher),
-                    black_box(SortKey::Size),
+                    black_box(&[SortKey::Size][..]),
                 )
                 .unwrap();
 
@@ -291,21 +242,11 @@ fn memory_benchmark_threaded_scan(c: &mut Criterion) {
     // Create a large directory structure
     create_large_directory_structure(root, 4, 25);
 
-    let args = Args {
-        path: root.to_path_buf(),
-        depth: None,
-        sort: rudu::cli::SortKey::Size,
-        show_files: false,
-        exclude: vec![],
-        show_owner: true, // Enable owner info to stress memory more
-        output: None,
-        threads: Some(num_cpus::get()),
-        show_inodes: true,
-        threads_strategy: ThreadPoolStrategy::WorkStealingUneven,
-        no_cache: false,
-        cache_ttl: 604800, // 7 days
-        profile: false,
-    };
+    let options = ScanOptions::new()
+        .show_files(false)
+        .show_owner(true)
+        .show_inodes(true)
+        .threads_strategy(ThreadPoolStrategy::WorkStealingUneven);
 
     let exclude_matcher = build_exclude_matcher(&[]).unwrap();
 
@@ -319,9 +260,9 @@ fn memory_benchmark_threaded_scan(c: &mut Criterion) {
 
                 let _result = scan_files_and_dirs(
                     black_box(root),
-                    black_box(&args),
+                    black_box(&options),
                     black_box(&exclude_matcher),
-                    black_box(SortKey::Size),
+                    black_box(&[SortKey::Size][..]),
                 )
                 .unwrap();
 
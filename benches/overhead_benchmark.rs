@@ -1,9 +1,8 @@
 use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
-use rudu::Args;
+use rudu::ScanOptions;
 use rudu::cli::SortKey;
 use rudu::memory::MemoryMonitor;
 use rudu::scan::{scan_files_and_dirs, scan_files_and_dirs_with_memory_monitor};
-use rudu::thread_pool::ThreadPoolStrategy;
 use rudu::utils::build_exclude_matcher;
 use std::fs;
 use std::path::Path;
@@ -39,23 +38,10 @@ fn memory_monitoring_overhead_benchmark(c: &mut Criterion) {
     // This creates approximately 20,000+ files across multiple directory levels
     create_large_directory_structure(root, 6, 50);
 
-    let base_args = Args {
-        path: root.to_path_buf(),
-        depth: None,
-        sort: SortKey::Size,
-        show_files: false,
-        exclude: vec![],
-        show_owner: false,
-        output: None,
-        threads: None,
-        show_inodes: true,
-        threads_strategy: ThreadPoolStrategy::Default,
-        no_cache: true, // Disable cache to ensure consistent benchmark
-        cache_ttl: 604800,
-        profile: false,
-        memory_limit: Some(1000),      // 1GB limit (generous for this test)
-        memory_check_interval_ms: 200, // Default interval
-    };
+    let base_options = ScanOptions::new()
+        .show_files(false)
+        .show_inodes(true)
+        .no_cache(true);
 
     let exclude_matcher = build_exclude_matcher(&[]).unwrap();
 
@@ -64,8 +50,14 @@ fn memory_monitoring_overhead_benchmark(c: &mut Criterion) {
 
     // Test different memory check intervals to find optimal settings
     for &interval_ms in &[50, 100, 200, 500, 1000] {
-        let mut args_with_interval = base_args.clone();
-        args_with_interval.memory_check_interval_ms = interval_ms;
+        // Entries walked between checks, as the CLI derives them from the interval
+        let options_with_interval = base_options
+            .clone()
+            .memory_check_entries(match interval_ms {
+                0..=100 => 500,
+                101..=200 => 1000,
+                _ => 2000,
+            });
 
         group.bench_with_input(
             BenchmarkId::new("with_memory_monitor", interval_ms),
@@ -79,9 +71,9 @@ fn memory_monitoring_overhead_benchmark(c: &mut Criterion) {
 
                     scan_files_and_dirs_with_memory_monitor(
                         black_box(root),
-                        black_box(&args_with_interval),
+                        black_box(&options_with_interval),
                         black_box(&exclude_matcher),
-                        black_box(SortKey::Size),
+                        black_box(&[SortKey::Size][..]),
                         Some(monitor),
                     )
                     .unwrap()
@@ -95,9 +87,9 @@ fn memory_monitoring_overhead_benchmark(c: &mut Criterion) {
         b.iter(|| {
             scan_files_and_dirs(
                 black_box(root),
-                black_box(&base_args),
+                black_box(&base_options),
                 black_box(&exclude_matcher),
-                black_box(SortKey::Size),
+                black_box(&[SortKey::Size][..]),
             )
             .unwrap()
         })
@@ -113,23 +105,11 @@ fn memory_monitoring_accuracy_benchmark(c: &mut Criterion) {
     // Create a moderate directory structure
     create_large_directory_structure(root, 5, 30);
 
-    let args = Args {
-        path: root.to_path_buf(),
-        depth: None,
-        sort: SortKey::Size,
-        show_files: false,
-        exclude: vec![],
-        show_owner: false,
-        output: None,
-        threads: None,
-        show_inodes: true,
-        threads_strategy: ThreadPoolStrategy::Default,
-        no_cache: true,
-        cache_ttl: 604800,
-        profile: false,
-        memory_limit: Some(50), // Very low limit to test monitoring accuracy
-        memory_check_interval_ms: 100, // Frequent checks for accuracy
-    };
+    let options = ScanOptions::new()
+        .show_files(false)
+        .show_inodes(true)
+        .no_cache(true)
+        .memory_check_entries(500);
 
     let exclude_matcher = build_exclude_matcher(&[]).unwrap();
 
@@ -139,9 +119,9 @@ fn memory_monitoring_accuracy_benchmark(c: &mut Criterion) {
 
             let result = scan_files_and_dirs_with_memory_monitor(
                 black_box(root),
-                black_box(&args),
+                black_box(&options),
                 black_box(&exclude_matcher),
-                black_box(SortKey::Size),
+                black_box(&[SortKey::Size][..]),
                 Some(monitor),
             )
             .unwrap();
@@ -160,23 +140,10 @@ fn memory_check_interval_tuning_benchmark(c: &mut Criterion) {
     // Create a very large directory structure to maximize memory monitoring calls
     create_large_directory_structure(root, 7, 100);
 
-    let base_args = Args {
-        path: root.to_path_buf(),
-        depth: None,
-        sort: SortKey::Size,
-        show_files: false,
-        exclude: vec![],
-        show_owner: false,
-        output: None,
-        threads: None,
-        show_inodes: true,
-        threads_strategy: ThreadPoolStrategy::Default,
-        no_cache: true,
-        cache_ttl: 604800,
-        profile: false,
-        memory_limit: Some(2000), // Large enough to not interfere
-        memory_check_interval_ms: 200,
-    };
+    let base_options = ScanOptions::new()
+        .show_files(false)
+        .show_inodes(true)
+        .no_cache(true);
 
     let exclude_matcher = build_exclude_matcher(&[]).unwrap();
 
@@ -184,8 +151,14 @@ fn memory_check_interval_tuning_benchmark(c: &mut Criterion) {
 
     // Test a wide range of intervals to find the sweet spot
     for &interval_ms in &[25, 50, 100, 200, 400, 800] {
-        let mut args_with_interval = base_args.clone();
-        args_with_interval.memory_check_interval_ms = interval_ms;
+        // Entries walked between checks, as the CLI derives them from the interval
+        let options_with_interval = base_options
+            .clone()
+            .memory_check_entries(match interval_ms {
+                0..=100 => 500,
+                101..=200 => 1000,
+                _ => 2000,
+            });
 
         group.bench_with_input(
             BenchmarkId::new("interval_ms", interval_ms),
@@ -199,9 +172,9 @@ fn memory_check_interval_tuning_benchmark(c: &mut Criterion) {
 
                     scan_files_and_dirs_with_memory_monitor(
                         black_box(root),
-                        black_box(&args_with_interval),
+                        black_box(&options_with_interval),
                         black_box(&exclude_matcher),
-                        black_box(SortKey::Size),
+                        black_box(&[SortKey::Size][..]),
                         Some(monitor),
                     )
                     .unwrap()
@@ -222,23 +195,10 @@ fn one_percent_overhead_validation_benchmark(c: &mut Criterion) {
     // This represents a typical large scan scenario where overhead matters
     create_large_directory_structure(root, 6, 75);
 
-    let args = Args {
-        path: root.to_path_buf(),
-        depth: None,
-        sort: SortKey::Size,
-        show_files: false,
-        exclude: vec![],
-        show_owner: false,
-        output: None,
-        threads: None,
-        show_inodes: true,
-        threads_strategy: ThreadPoolStrategy::Default,
-        no_cache: true, // Disable cache for consistent measurements
-        cache_ttl: 604800,
-        profile: false,
-        memory_limit: Some(4000), // High limit to avoid triggering limits
-        memory_check_interval_ms: 200, // Default interval
-    };
+    let options = ScanOptions::new()
+        .show_files(false)
+        .show_inodes(true)
+        .no_cache(true);
 
     let exclude_matcher = build_exclude_matcher(&[]).unwrap();
 
@@ -253,9 +213,9 @@ fn one_percent_overhead_validation_benchmark(c: &mut Criterion) {
         b.iter(|| {
             scan_files_and_dirs(
                 black_box(root),
-                black_box(&args),
+                black_box(&options),
                 black_box(&exclude_matcher),
-                black_box(SortKey::Size),
+                black_box(&[SortKey::Size][..]),
             )
             .unwrap()
         })
@@ -268,9 +228,9 @@ fn one_percent_overhead_validation_benchmark(c: &mut Criterion) {
 
             scan_files_and_dirs_with_memory_monitor(
                 black_box(root),
-                black_box(&args),
+                black_box(&options),
                 black_box(&exclude_matcher),
-                black_box(SortKey::Size),
+                black_box(&[SortKey::Size][..]),
                 Some(monitor),
             )
             .unwrap()
@@ -284,9 +244,9 @@ fn one_percent_overhead_validation_benchmark(c: &mut Criterion) {
 
             scan_files_and_dirs_with_memory_monitor(
                 black_box(root),
-                black_box(&args),
+                black_box(&options),
                 black_box(&exclude_matcher),
-                black_box(SortKey::Size),
+                black_box(&[SortKey::Size][..]),
                 Some(monitor),
             )
             .unwrap()
@@ -1,21 +1,17 @@
 use criterion::{BenchmarkId, Criterion, Throughput, black_box, criterion_group, criterion_main};
-use rudu::Args;
+use rudu::ScanOptions;
 use rudu::cli::SortKey;
 use rudu::scan::scan_files_and_dirs;
-use rudu::thread_pool::ThreadPoolStrategy;
+use rudu::testing::TreeSpec;
 use rudu::utils::build_exclude_matcher;
 use std::env;
-use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 use std::time::Instant;
 use tempfile::TempDir;
-use walkdir;
 
 #[cfg(target_os = "linux")]
 use procfs::process::Process;
 
-use std::process::Command;
-
 /// Memory usage tracker for profiling
 struct MemoryTracker {
     initial_rss: f64,
@@ -31,11 +27,11 @@ impl MemoryTracker {
     fn current_rss_mb() -> f64 {
         #[cfg(target_os = "linux")]
         {
-            if let Ok(process) = Process::myself() {
-                if let Ok(stat) = process.stat() {
-                    // RSS is in pages, convert to MB
-                    return stat.rss as f64 * 4.0 / 1024.0; // Assuming 4KB pages
-                }
+            if let Ok(process) = Process::myself()
+                && let Ok(stat) = process.stat()
+            {
+                // RSS is in pages, convert to MB
+                return stat.rss as f64 * 4.0 / 1024.0; // Assuming 4KB pages
             }
             0.0
         }
@@ -67,25 +63,15 @@ impl MemoryTracker {
 }
 
 /// Create a synthetic directory structure for testing
-/// Reused from existing scan_benchmark.rs with modifications
 fn create_synthetic_tree(dir: &Path, depth: usize, width: usize, files_per_dir: usize) {
-    if depth == 0 {
-        return;
-    }
-
-    // Create files in current directory
-    for i in 0..files_per_dir {
-        let file_path = dir.join(format!("file_{}.txt", i));
-        let content = format!("Content of file {} - {}", i, "x".repeat(100 * i));
-        fs::write(&file_path, content).unwrap();
-    }
-
-    // Create subdirectories
-    for i in 0..width {
-        let subdir_path = dir.join(format!("subdir_{}", i));
-        fs::create_dir_all(&subdir_path).unwrap();
-        create_synthetic_tree(&subdir_path, depth - 1, width, files_per_dir);
-    }
+    TreeSpec::new()
+        .depth(depth)
+        .fanout(width)
+        .files_per_dir(files_per_dir)
+        .file_size(100 * files_per_dir as u64)
+        .skew(1.0)
+        .generate(dir)
+        .unwrap();
 }
 
 /// Create a small tree (low depth, few files) - baseline
@@ -103,23 +89,9 @@ fn create_deep_tree(dir: &Path) {
     create_synthetic_tree(dir, 8, 2, 5);
 }
 
-/// Helper function to create Args with common settings
-fn create_args(path: PathBuf) -> Args {
-    Args {
-        path,
-        depth: None,
-        sort: SortKey::Size,
-        show_files: false,
-        exclude: vec![],
-        show_owner: false,
-        output: None,
-        threads: None,
-        show_inodes: true,
-        threads_strategy: ThreadPoolStrategy::Default,
-        no_cache: false,
-        cache_ttl: 604800, // 7 days
-        profile: false,
-    }
+/// Helper function to create scan options with common settings
+fn create_options() -> ScanOptions {
+    ScanOptions::new().show_files(false).show_inodes(true)
 }
 
 /// Benchmark function with profiling
@@ -146,9 +118,9 @@ fn profile_scan_function<F>(
 
     group.throughput(Throughput::Elements(total_entries as u64));
 
-    let mut args = create_args(root.to_path_buf());
-    args.show_owner = show_owner;
-    args.show_inodes = show_inodes;
+    let options = create_options()
+        .show_owner(show_owner)
+        .show_inodes(show_inodes);
 
     let exclude_matcher = build_exclude_matcher(&[]).unwrap();
 
@@ -163,9 +135,9 @@ fn profile_scan_function<F>(
 
                 let result = scan_files_and_dirs(
                     black_box(root),
-                    black_box(&args),
+                    black_box(&options),
                     black_box(&exclude_matcher),
-                    black_box(SortKey::Size),
+                    black_box(&[SortKey::Size][..]),
                 );
 
                 let duration = start.elapsed();
@@ -272,8 +244,7 @@ fn benchmark_real_world_sample(c: &mut Criterion) {
 
         group.throughput(Throughput::Elements(total_entries as u64));
 
-        let mut args = create_args(path.to_path_buf());
-        args.depth = Some(3); // Limit depth for real-world benchmark
+        let options = create_options().depth(3); // Limit depth for real-world benchmark
 
         let exclude_matcher = build_exclude_matcher(&[]).unwrap();
 
@@ -288,9 +259,9 @@ fn benchmark_real_world_sample(c: &mut Criterion) {
 
                     let result = scan_files_and_dirs(
                         black_box(path),
-                        black_box(&args),
+                        black_box(&options),
                         black_box(&exclude_matcher),
-                        black_box(SortKey::Size),
+                        black_box(&[SortKey::Size][..]),
                     );
 
                     let duration = start.elapsed();
@@ -340,15 +311,15 @@ fn benchmark_scaling(c: &mut Criterion) {
             // Create a structure with 'size' files
             create_synthetic_tree(root, 3, 3, size / 9);
 
-            let args = create_args(root.to_path_buf());
+            let options = create_options();
             let exclude_matcher = build_exclude_matcher(&[]).unwrap();
 
             b.iter(|| {
                 let result = scan_files_and_dirs(
                     black_box(root),
-                    black_box(&args),
+                    black_box(&options),
                     black_box(&exclude_matcher),
-                    black_box(SortKey::Size),
+                    black_box(&[SortKey::Size][..]),
                 );
                 black_box(result.unwrap());
             });
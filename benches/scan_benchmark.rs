@@ -1,9 +1,9 @@
 use criterion::{Criterion, black_box, criterion_group, criterion_main};
-use rudu::Args;
+use rudu::ScanOptions;
 use rudu::cache::save_cache;
 use rudu::cli::SortKey;
 use rudu::scan::{scan_files_and_dirs, scan_files_and_dirs_incremental};
-use rudu::thread_pool::ThreadPoolStrategy;
+use rudu::testing::TreeSpec;
 use rudu::utils::build_exclude_matcher;
 use std::collections::HashMap;
 use std::fs;
@@ -12,22 +12,13 @@ use tempfile::TempDir;
 use walkdir::WalkDir;
 
 fn create_test_directory_structure(dir: &Path, depth: usize, files_per_dir: usize) {
-    if depth == 0 {
-        return;
-    }
-
-    // Create files in current directory
-    for i in 0..files_per_dir {
-        let file_path = dir.join(format!("file_{}.txt", i));
-        fs::write(&file_path, format!("Content of file {}", i)).unwrap();
-    }
-
-    // Create subdirectories
-    for i in 0..3 {
-        let subdir_path = dir.join(format!("subdir_{}", i));
-        fs::create_dir_all(&subdir_path).unwrap();
-        create_test_directory_structure(&subdir_path, depth - 1, files_per_dir);
-    }
+    TreeSpec::new()
+        .depth(depth)
+        .fanout(3)
+        .files_per_dir(files_per_dir)
+        .file_size(20)
+        .generate(dir)
+        .unwrap();
 }
 
 /// Create a deep tree structure optimized for incremental scanning tests
@@ -59,32 +50,32 @@ fn create_deep_tree_structure(dir: &Path, depth: usize, files_per_dir: usize) {
 /// Create a cache for the given directory structure
 fn create_cache_for_structure(
     root: &Path,
-    args: &Args,
+    options: &ScanOptions,
 ) -> HashMap<std::path::PathBuf, rudu::cache::CacheEntry> {
     // First scan to populate cache
     let exclude_matcher = build_exclude_matcher(&[]).unwrap();
-    let entries = scan_files_and_dirs(root, args, &exclude_matcher, SortKey::Size).unwrap();
+    let entries = scan_files_and_dirs(root, options, &exclude_matcher, &[SortKey::Size]).unwrap();
 
     // Convert entries to cache format
     let mut cache = HashMap::new();
     for entry in entries.entries {
         let metadata = fs::metadata(&entry.path).unwrap();
         let owner_u32 = entry.owner.as_ref().and_then(|s| s.parse::<u32>().ok());
-        let cache_entry = rudu::cache::CacheEntry::new(
-            rudu::utils::path_hash(&entry.path),
-            entry.path.clone(),
-            entry.size,
-            metadata
+        let cache_entry = rudu::cache::CacheEntry::new(rudu::cache::CacheEntryParams {
+            path: entry.path.clone(),
+            size: entry.size,
+            mtime: metadata
                 .modified()
                 .unwrap()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_secs(),
-            1, // nlink - simplified for benchmark
-            entry.inodes,
-            owner_u32,
-            entry.entry_type,
-        );
+            nlink: 1, // simplified for benchmark
+            inode_cnt: entry.inodes,
+            owner: owner_u32,
+            entry_type: entry.entry_type,
+            ctime: None,
+        });
         cache.insert(entry.path, cache_entry);
     }
 
@@ -124,23 +115,7 @@ fn benchmark_scan_small_directory(c: &mut Criterion) {
     // Create a small directory structure: 3 levels deep, 5 files per directory
     create_test_directory_structure(root, 3, 5);
 
-    let args = Args {
-        path: root.to_path_buf(),
-        depth: None,
-        sort: rudu::cli::SortKey::Size,
-        show_files: false,
-        exclude: vec![],
-        show_owner: false,
-        output: None,
-        threads: None,
-        show_inodes: true,
-        threads_strategy: ThreadPoolStrategy::Default,
-        no_cache: false,
-        cache_ttl: 604800, // 7 days
-        profile: false,
-        memory_limit: None,
-        memory_check_interval_ms: 200,
-    };
+    let options = ScanOptions::new().show_files(false).show_inodes(true);
 
     let exclude_matcher = build_exclude_matcher(&[]).unwrap();
 
@@ -148,9 +123,9 @@ fn benchmark_scan_small_directory(c: &mut Criterion) {
         b.iter(|| {
             scan_files_and_dirs(
                 black_box(root),
-                black_box(&args),
+                black_box(&options),
                 black_box(&exclude_matcher),
-                black_box(SortKey::Size),
+                black_box(&[SortKey::Size][..]),
             )
             .unwrap()
         })
@@ -164,23 +139,7 @@ fn benchmark_scan_deep_directory(c: &mut Criterion) {
     // Create a deeper directory structure: 5 levels deep, 10 files per directory
     create_test_directory_structure(root, 5, 10);
 
-    let args = Args {
-        path: root.to_path_buf(),
-        depth: None,
-        sort: rudu::cli::SortKey::Size,
-        show_files: false,
-        exclude: vec![],
-        show_owner: false,
-        output: None,
-        threads: None,
-        show_inodes: true,
-        threads_strategy: ThreadPoolStrategy::Default,
-        no_cache: false,
-        cache_ttl: 604800, // 7 days
-        profile: false,
-        memory_limit: None,
-        memory_check_interval_ms: 200,
-    };
+    let options = ScanOptions::new().show_files(false).show_inodes(true);
 
     let exclude_matcher = build_exclude_matcher(&[]).unwrap();
 
@@ -188,9 +147,9 @@ fn benchmark_scan_deep_directory(c: &mut Criterion) {
         b.iter(|| {
             scan_files_and_dirs(
                 black_box(root),
-                black_box(&args),
+                black_box(&options),
                 black_box(&exclude_matcher),
-                black_box(SortKey::Size),
+                black_box(&[SortKey::Size][..]),
             )
             .unwrap()
         })
@@ -204,23 +163,10 @@ fn benchmark_scan_with_owner_info(c: &mut Criterion) {
     // Create a directory structure with owner info enabled
     create_test_directory_structure(root, 4, 8);
 
-    let args = Args {
-        path: root.to_path_buf(),
-        depth: None,
-        sort: rudu::cli::SortKey::Size,
-        show_files: false,
-        exclude: vec![],
-        show_owner: true,
-        output: None,
-        threads: None,
-        show_inodes: true,
-        threads_strategy: ThreadPoolStrategy::Default,
-        no_cache: false,
-        cache_ttl: 604800, // 7 days
-        profile: false,
-        memory_limit: None,
-        memory_check_interval_ms: 200,
-    };
+    let options = ScanOptions::new()
+        .show_files(false)
+        .show_owner(true)
+        .show_inodes(true);
 
     let exclude_matcher = build_exclude_matcher(&[]).unwrap();
 
@@ -228,9 +174,9 @@ fn benchmark_scan_with_owner_info(c: &mut Criterion) {
         b.iter(|| {
             scan_files_and_dirs(
                 black_box(root),
-                black_box(&args),
+                black_box(&options),
                 black_box(&exclude_matcher),
-                black_box(SortKey::Size),
+                black_box(&[SortKey::Size][..]),
             )
             .unwrap()
         })
@@ -245,26 +191,10 @@ fn benchmark_scan_with_cache_hit(c: &mut Criterion) {
     // Create a moderate directory structure
     create_test_directory_structure(root, 4, 8);
 
-    let args = Args {
-        path: root.to_path_buf(),
-        depth: None,
-        sort: rudu::cli::SortKey::Size,
-        show_files: false,
-        exclude: vec![],
-        show_owner: false,
-        output: None,
-        threads: None,
-        show_inodes: true,
-        threads_strategy: ThreadPoolStrategy::Default,
-        no_cache: false,
-        cache_ttl: 604800, // 7 days
-        profile: false,
-        memory_limit: None,
-        memory_check_interval_ms: 200,
-    };
+    let options = ScanOptions::new().show_files(false).show_inodes(true);
 
     // Create and populate cache
-    create_cache_for_structure(root, &args);
+    create_cache_for_structure(root, &options);
 
     let exclude_matcher = build_exclude_matcher(&[]).unwrap();
 
@@ -272,9 +202,9 @@ fn benchmark_scan_with_cache_hit(c: &mut Criterion) {
         b.iter(|| {
             scan_files_and_dirs_incremental(
                 black_box(root),
-                black_box(&args),
+                black_box(&options),
                 black_box(&exclude_matcher),
-                black_box(SortKey::Size),
+                black_box(&[SortKey::Size][..]),
             )
             .unwrap()
         })
@@ -289,26 +219,10 @@ fn benchmark_scan_with_cache_miss(c: &mut Criterion) {
     // Create a moderate directory structure
     create_test_directory_structure(root, 4, 8);
 
-    let args = Args {
-        path: root.to_path_buf(),
-        depth: None,
-        sort: rudu::cli::SortKey::Size,
-        show_files: false,
-        exclude: vec![],
-        show_owner: false,
-        output: None,
-        threads: None,
-        show_inodes: true,
-        threads_strategy: ThreadPoolStrategy::Default,
-        no_cache: false,
-        cache_ttl: 604800, // 7 days
-        profile: false,
-        memory_limit: None,
-        memory_check_interval_ms: 200,
-    };
+    let options = ScanOptions::new().show_files(false).show_inodes(true);
 
     // Create and populate cache
-    create_cache_for_structure(root, &args);
+    create_cache_for_structure(root, &options);
 
     let exclude_matcher = build_exclude_matcher(&[]).unwrap();
 
@@ -321,9 +235,9 @@ fn benchmark_scan_with_cache_miss(c: &mut Criterion) {
             |_| {
                 scan_files_and_dirs_incremental(
                     black_box(root),
-                    black_box(&args),
+                    black_box(&options),
                     black_box(&exclude_matcher),
-                    black_box(SortKey::Size),
+                    black_box(&[SortKey::Size][..]),
                 )
                 .unwrap()
             },
@@ -340,26 +254,10 @@ fn benchmark_scan_incremental_deep(c: &mut Criterion) {
     // Create a deep directory structure: 8 levels deep, 6 files per directory
     create_deep_tree_structure(root, 8, 6);
 
-    let args = Args {
-        path: root.to_path_buf(),
-        depth: None,
-        sort: rudu::cli::SortKey::Size,
-        show_files: false,
-        exclude: vec![],
-        show_owner: false,
-        output: None,
-        threads: None,
-        show_inodes: true,
-        threads_strategy: ThreadPoolStrategy::Default,
-        no_cache: false,
-        cache_ttl: 604800, // 7 days
-        profile: false,
-        memory_limit: None,
-        memory_check_interval_ms: 200,
-    };
+    let options = ScanOptions::new().show_files(false).show_inodes(true);
 
     // Create and populate cache
-    create_cache_for_structure(root, &args);
+    create_cache_for_structure(root, &options);
 
     let exclude_matcher = build_exclude_matcher(&[]).unwrap();
 
@@ -372,9 +270,9 @@ fn benchmark_scan_incremental_deep(c: &mut Criterion) {
             |_| {
                 scan_files_and_dirs_incremental(
                     black_box(root),
-                    black_box(&args),
+                    black_box(&options),
                     black_box(&exclude_matcher),
-                    black_box(SortKey::Size),
+                    black_box(&[SortKey::Size][..]),
                 )
                 .unwrap()
             },
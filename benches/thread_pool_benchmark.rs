@@ -1,7 +1,8 @@
 use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
-use rudu::Args;
+use rudu::ScanOptions;
 use rudu::cli::SortKey;
 use rudu::scan::scan_files_and_dirs;
+use rudu::testing::TreeSpec;
 use rudu::thread_pool::ThreadPoolStrategy;
 use rudu::utils::build_exclude_matcher;
 use std::fs;
@@ -9,22 +10,13 @@ use std::path::Path;
 use tempfile::TempDir;
 
 fn create_test_directory_structure(dir: &Path, depth: usize, files_per_dir: usize) {
-    if depth == 0 {
-        return;
-    }
-
-    // Create files in current directory
-    for i in 0..files_per_dir {
-        let file_path = dir.join(format!("file_{}.txt", i));
-        fs::write(&file_path, format!("Content of file {}", i)).unwrap();
-    }
-
-    // Create subdirectories
-    for i in 0..3 {
-        let subdir_path = dir.join(format!("subdir_{}", i));
-        fs::create_dir_all(&subdir_path).unwrap();
-        create_test_directory_structure(&subdir_path, depth - 1, files_per_dir);
-    }
+    TreeSpec::new()
+        .depth(depth)
+        .fanout(3)
+        .files_per_dir(files_per_dir)
+        .file_size(20)
+        .generate(dir)
+        .unwrap();
 }
 
 fn create_io_heavy_structure(dir: &Path, depth: usize, files_per_dir: usize) {
@@ -128,60 +120,27 @@ fn thread_pool_benchmark(c: &mut Criterion) {
             ];
 
             for param in params {
-                let (test_root, test_args) = match param.workload {
+                let (test_root, test_options) = match param.workload {
                     "small" => (
                         small_root,
-                        Args {
-                            path: small_root.to_path_buf(),
-                            depth: None,
-                            sort: SortKey::Size,
-                            show_files: false,
-                            exclude: vec![],
-                            show_owner: false,
-                            output: None,
-                            threads: Some(param.n_threads),
-                            show_inodes: true,
-                            threads_strategy: param.strategy,
-                            no_cache: false,
-                            cache_ttl: 604800,
-                            profile: false,
-                        },
+                        ScanOptions::new()
+                            .show_files(false)
+                            .show_inodes(true)
+                            .threads_strategy(param.strategy),
                     ),
                     "io_heavy" => (
                         io_heavy_root,
-                        Args {
-                            path: io_heavy_root.to_path_buf(),
-                            depth: None,
-                            sort: SortKey::Size,
-                            show_files: false,
-                            exclude: vec![],
-                            show_owner: true, // Enable owner info for I/O heavy workload
-                            output: None,
-                            threads: Some(param.n_threads),
-                            show_inodes: true,
-                            threads_strategy: param.strategy,
-                            no_cache: false,
-                            cache_ttl: 604800,
-                            profile: false,
-                        },
+                        ScanOptions::new()
+                            .show_files(false)
+                            .show_owner(true)
+                            .show_inodes(true)
+                            .threads_strategy(param.strategy),
                     ),
                     "deep" => (
                         deep_root,
-                        Args {
-                            path: deep_root.to_path_buf(),
-                            depth: None,
-                            sort: SortKey::Size,
-                            show_files: true,
-                            exclude: vec![],
-                            show_owner: false,
-                            output: None,
-                            threads: Some(param.n_threads),
-                            show_inodes: true,
-                            threads_strategy: param.strategy,
-                            no_cache: false,
-                            cache_ttl: 604800,
-                            profile: false,
-                        },
+                        ScanOptions::new()
+                            .show_inodes(true)
+                            .threads_strategy(param.strategy),
                     ),
                     _ => unreachable!(),
                 };
@@ -198,9 +157,9 @@ fn thread_pool_benchmark(c: &mut Criterion) {
                             // comparative results for different strategies
                             scan_files_and_dirs(
                                 black_box(test_root),
-                                black_box(&test_args),
+                                black_box(&test_options),
                                 black_box(&exclude_matcher),
-                                black_box(SortKey::Size),
+                                black_box(&[SortKey::Size][..]),
                             )
                             .unwrap()
                         })
@@ -235,22 +194,11 @@ fn strategy_comparison_benchmark(c: &mut Criterion) {
 
     let mut group = c.benchmark_group("strategy_comparison");
 
-    for (strategy, threads) in strategies_with_threads {
-        let args = Args {
-            path: root.to_path_buf(),
-            depth: None,
-            sort: SortKey::Size,
-            show_files: false,
-            exclude: vec![],
-            show_owner: false,
-            output: None,
-            threads: Some(threads),
-            show_inodes: true,
-            threads_strategy: strategy,
-            no_cache: false,
-            cache_ttl: 604800,
-            profile: false,
-        };
+    for (strategy, _) in strategies_with_threads {
+        let options = ScanOptions::new()
+            .show_files(false)
+            .show_inodes(true)
+            .threads_strategy(strategy);
 
         let exclude_matcher = build_exclude_matcher(&[]).unwrap();
 
@@ -261,9 +209,9 @@ fn strategy_comparison_benchmark(c: &mut Criterion) {
                 b.iter(|| {
                     scan_files_and_dirs(
                         black_box(root),
-                        black_box(&args),
+                        black_box(&options),
                         black_box(&exclude_matcher),
-                        black_box(SortKey::Size),
+                        black_box(&[SortKey::Size][..]),
                     )
                     .unwrap()
                 })
@@ -1,5 +1,5 @@
 use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
-use rudu::Args;
+use rudu::ScanOptions;
 use rudu::cli::SortKey;
 use rudu::scan::scan_files_and_dirs;
 use rudu::thread_pool::ThreadPoolStrategy;
@@ -82,8 +82,6 @@ fn create_uneven_directory_structure(dir: &Path) {
 }
 
 fn work_stealing_benchmark(c: &mut Criterion) {
-    let num_cpus = num_cpus::get();
-
     // Create test directories
     let large_temp_dir = TempDir::new().unwrap();
     let large_root = large_temp_dir.path();
@@ -107,30 +105,11 @@ fn work_stealing_benchmark(c: &mut Criterion) {
     group.measurement_time(std::time::Duration::from_secs(60));
 
     for strategy in strategies {
-        let threads = match strategy {
-            ThreadPoolStrategy::Default => num_cpus,
-            ThreadPoolStrategy::Fixed => num_cpus,
-            ThreadPoolStrategy::IOHeavy => num_cpus * 2,
-            ThreadPoolStrategy::WorkStealingUneven => num_cpus,
-            _ => num_cpus,
-        };
-
         // Test with large directories
-        let large_args = Args {
-            path: large_root.to_path_buf(),
-            depth: None,
-            sort: SortKey::Size,
-            show_files: false,
-            exclude: vec![],
-            show_owner: false,
-            output: None,
-            threads: Some(threads),
-            show_inodes: true,
-            threads_strategy: strategy,
-            no_cache: false,
-            cache_ttl: 604800,
-            profile: false,
-        };
+        let large_options = ScanOptions::new()
+            .show_files(false)
+            .show_inodes(true)
+            .threads_strategy(strategy);
 
         let exclude_matcher = build_exclude_matcher(&[]).unwrap();
 
@@ -141,9 +120,9 @@ fn work_stealing_benchmark(c: &mut Criterion) {
                 b.iter(|| {
                     scan_files_and_dirs(
                         black_box(large_root),
-                        black_box(&large_args),
+                        black_box(&large_options),
                         black_box(&exclude_matcher),
-                        black_box(SortKey::Size),
+                        black_box(&[SortKey::Size][..]),
                     )
                     .unwrap()
                 })
@@ -151,21 +130,10 @@ fn work_stealing_benchmark(c: &mut Criterion) {
         );
 
         // Test with uneven directories
-        let uneven_args = Args {
-            path: uneven_root.to_path_buf(),
-            depth: None,
-            sort: SortKey::Size,
-            show_files: false,
-            exclude: vec![],
-            show_owner: false,
-            output: None,
-            threads: Some(threads),
-            show_inodes: true,
-            threads_strategy: strategy,
-            no_cache: false,
-            cache_ttl: 604800,
-            profile: false,
-        };
+        let uneven_options = ScanOptions::new()
+            .show_files(false)
+            .show_inodes(true)
+            .threads_strategy(strategy);
 
         group.bench_with_input(
             BenchmarkId::new("uneven_dirs", strategy.as_str()),
@@ -174,9 +142,9 @@ fn work_stealing_benchmark(c: &mut Criterion) {
                 b.iter(|| {
                     scan_files_and_dirs(
                         black_box(uneven_root),
-                        black_box(&uneven_args),
+                        black_box(&uneven_options),
                         black_box(&exclude_matcher),
-                        black_box(SortKey::Size),
+                        black_box(&[SortKey::Size][..]),
                     )
                     .unwrap()
                 })
@@ -203,38 +171,13 @@ fn work_stealing_scalability_benchmark(c: &mut Criterion) {
 
     for threads in thread_counts {
         // Test default strategy
-        let default_args = Args {
-            path: root.to_path_buf(),
-            depth: None,
-            sort: SortKey::Size,
-            show_files: false,
-            exclude: vec![],
-            show_owner: false,
-            output: None,
-            threads: Some(threads),
-            show_inodes: true,
-            threads_strategy: ThreadPoolStrategy::Default,
-            no_cache: false,
-            cache_ttl: 604800,
-            profile: false,
-        };
+        let default_options = ScanOptions::new().show_files(false).show_inodes(true);
 
         // Test work-stealing strategy
-        let work_stealing_args = Args {
-            path: root.to_path_buf(),
-            depth: None,
-            sort: SortKey::Size,
-            show_files: false,
-            exclude: vec![],
-            show_owner: false,
-            output: None,
-            threads: Some(threads),
-            show_inodes: true,
-            threads_strategy: ThreadPoolStrategy::WorkStealingUneven,
-            no_cache: false,
-            cache_ttl: 604800,
-            profile: false,
-        };
+        let work_stealing_options = ScanOptions::new()
+            .show_files(false)
+            .show_inodes(true)
+            .threads_strategy(ThreadPoolStrategy::WorkStealingUneven);
 
         let exclude_matcher = build_exclude_matcher(&[]).unwrap();
 
@@ -245,9 +188,9 @@ fn work_stealing_scalability_benchmark(c: &mut Criterion) {
                 b.iter(|| {
                     scan_files_and_dirs(
                         black_box(root),
-                        black_box(&default_args),
+                        black_box(&default_options),
                         black_box(&exclude_matcher),
-                        black_box(SortKey::Size),
+                        black_box(&[SortKey::Size][..]),
                     )
                     .unwrap()
                 })
@@ -261,9 +204,9 @@ fn work_stealing_scalability_benchmark(c: &mut Criterion) {
                 b.iter(|| {
                     scan_files_and_dirs(
                         black_box(root),
-                        black_box(&work_stealing_args),
+                        black_box(&work_stealing_options),
                         black_box(&exclude_matcher),
-                        black_box(SortKey::Size),
+                        black_box(&[SortKey::Size][..]),
                     )
                     .unwrap()
                 })
@@ -1,4 +1,5 @@
-use rudu::cli::{Args, SortKey};
+use rudu::ScanOptions;
+use rudu::cli::SortKey;
 use rudu::memory::MemoryMonitor;
 use rudu::scan::{MemoryLimitStatus, scan_files_and_dirs_with_memory_monitor};
 use std::path::Path;
@@ -8,35 +9,19 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Create a memory monitor with a 100MB limit
     let memory_monitor = Arc::new(Mutex::new(MemoryMonitor::new(100)));
 
-    // Set up basic scanning args (this would typically come from CLI)
-    let args = Args {
-        path: Path::new(".").to_path_buf(),
-        depth: Some(3),
-        exclude: vec![],
-        show_files: false,
-        show_owner: false,
-        show_inodes: false,
-        sort: SortKey::Size,
-        output: None,
-        no_cache: false,
-        cache_ttl: 24,
-        profile: false,
-        threads: None,
-        threads_strategy: rudu::thread_pool::ThreadPoolStrategy::Default,
-        memory_limit: Some(100),
-        memory_check_interval_ms: 200,
-    };
+    // Set up the scan options; the CLI builds these from its arguments
+    let options = ScanOptions::new().depth(3).show_files(false).cache_ttl(24);
 
     // Create a simple exclude matcher (empty in this demo)
-    let exclude_matcher = globset::GlobSetBuilder::new().build()?;
+    let exclude_matcher = rudu::utils::PathMatcher::default();
 
     // Run the scan with memory monitoring
     println!("🔍 Starting scan with memory monitoring...");
     let result = scan_files_and_dirs_with_memory_monitor(
-        &args.path,
-        &args,
+        Path::new("."),
+        &options,
         &exclude_matcher,
-        args.sort,
+        &[SortKey::Size],
         Some(memory_monitor.clone()),
     )?;
 
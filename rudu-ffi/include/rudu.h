@@ -0,0 +1,102 @@
+/* C bindings for the rudu disk usage scanner. */
+
+#ifndef RUDU_H
+#define RUDU_H
+
+/* Generated by cbindgen from rudu-ffi/src/lib.rs; do not edit. */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+// Kind of a scanned entry, mirroring `rudu::EntryType`
+typedef enum RuduEntryType {
+  RUDU_ENTRY_TYPE_FILE = 0,
+  RUDU_ENTRY_TYPE_DIR = 1,
+  RUDU_ENTRY_TYPE_SYMLINK = 2,
+  RUDU_ENTRY_TYPE_SOCKET = 3,
+  RUDU_ENTRY_TYPE_FIFO = 4,
+  RUDU_ENTRY_TYPE_DEVICE = 5,
+  RUDU_ENTRY_TYPE_UNKNOWN = 6,
+} RuduEntryType;
+
+// Entries of a finished scan, returned by `rudu_scan`
+typedef struct RuduScan RuduScan;
+
+// Options for `rudu_scan`; start from `rudu_options_default`.
+typedef struct RuduOptions {
+  // Resolve the owner name of every entry
+  bool show_owner;
+  // Count the entries below every directory
+  bool show_inodes;
+  // Neither read nor write the scan cache
+  bool no_cache;
+  // Glob patterns or names to exclude, `exclude_len` of them; may be NULL
+  // when `exclude_len` is 0
+  const char *const *exclude;
+  size_t exclude_len;
+} RuduOptions;
+
+// One entry of a scan. Strings are owned by the scan they came from.
+typedef struct RuduEntry {
+  // Full path, NUL-terminated
+  const char *path;
+  // Size in bytes; for directories the total of everything below them
+  uint64_t size;
+  enum RuduEntryType entry_type;
+  // Modification time in seconds since the Unix epoch, or -1 if unknown
+  int64_t mtime;
+  // Entries below a directory, or -1 unless `show_inodes` was set
+  int64_t inodes;
+  // Owner name, or NULL unless `show_owner` was set
+  const char *owner;
+} RuduEntry;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Options matching a plain `rudu PATH` run: no owners or inode counts, the
+// cache enabled and nothing excluded.
+struct RuduOptions rudu_options_default(void);
+
+// Scans the tree at `root`, with default options if `options` is NULL.
+//
+// Returns NULL if the scan failed; `rudu_last_error` then describes why.
+// The result must be released with `rudu_scan_free`.
+//
+// # Safety
+// `root` must be a valid C string, and `options`, if not NULL, must point
+// to a valid `RuduOptions`.
+struct RuduScan *rudu_scan(const char *root, const struct RuduOptions *options);
+
+// Number of entries in `scan`, or 0 if it is NULL.
+//
+// # Safety
+// `scan` must be NULL or a scan returned by `rudu_scan` and not yet freed.
+size_t rudu_scan_len(const struct RuduScan *scan);
+
+// The entry at `index` of `scan`, or NULL if `index` is out of range.
+//
+// Entries are sorted by path, with every directory's size covering the
+// entries below it. The pointer is valid until the scan is freed.
+//
+// # Safety
+// `scan` must be NULL or a scan returned by `rudu_scan` and not yet freed.
+const struct RuduEntry *rudu_scan_entry(const struct RuduScan *scan, size_t index);
+
+// Releases a scan and every string of its entries. NULL is ignored.
+//
+// # Safety
+// `scan` must be NULL or a scan returned by `rudu_scan` and not yet freed.
+void rudu_scan_free(struct RuduScan *scan);
+
+// Message describing the last failed call on this thread, or NULL if none
+// failed. Valid until the next failing call on the same thread.
+const char *rudu_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* RUDU_H */
@@ -0,0 +1,382 @@
+//! Size and growth alerts by webhook or email (`alerts` feature).
+//!
+//! `--alert-threshold` and `--alert-growth` turn a scan, typically a
+//! `rudu daemon` round, into a check: [`check`] compares every listed
+//! directory with a size limit and with its size in the previous snapshot,
+//! and the directories breaking a rule are sent as one JSON document to an
+//! `--alert-url` webhook ([`post_webhook`]) and/or as a plain-text email
+//! through an SMTP relay ([`send_email`]).
+//!
+//! Email is sent without TLS or authentication, as to the local mail hub
+//! of a cluster; webhooks may be `http` or `https`.
+
+use crate::data::{FileEntry, ScanMeta};
+use crate::snapshot::Snapshot;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Time allowed for connecting to and each exchange with a webhook or
+/// SMTP server
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// When a directory raises an alert
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AlertRules {
+    /// Bytes a directory may hold
+    pub threshold: Option<u64>,
+    /// Percent a directory may grow by since the previous snapshot
+    pub growth_percent: Option<f64>,
+}
+
+impl AlertRules {
+    /// Whether any rule is set
+    pub fn is_empty(&self) -> bool {
+        self.threshold.is_none() && self.growth_percent.is_none()
+    }
+}
+
+/// A directory breaking one or more [`AlertRules`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Alert {
+    /// The directory
+    pub path: PathBuf,
+    /// Its size in bytes
+    pub size: u64,
+    /// Its size in the previous snapshot, if it was in one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_size: Option<u64>,
+    /// Growth since the previous snapshot in percent, if it had any size
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub growth_percent: Option<f64>,
+    /// Whether the size is over the threshold
+    pub over_threshold: bool,
+    /// Whether the growth is over the growth limit
+    pub over_growth: bool,
+}
+
+/// Everything sent for one scan
+#[derive(Debug, Clone, Serialize)]
+pub struct AlertPayload<'a> {
+    /// Host the scan ran on
+    pub host: String,
+    /// Root of the scan
+    pub root: &'a Path,
+    /// Run ID of the scan, as in `stats.json`
+    pub run_id: &'a str,
+    /// Start of the scan (RFC 3339)
+    pub started_at: String,
+    /// When the previous snapshot was taken, if growth was checked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_snapshot: Option<String>,
+    /// The threshold in bytes, if set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<u64>,
+    /// The growth limit in percent, if set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub growth_percent: Option<f64>,
+    /// The directories breaking a rule, largest first
+    pub alerts: &'a [Alert],
+}
+
+impl<'a> AlertPayload<'a> {
+    /// The payload for `alerts` raised by the scan described by `meta`
+    pub fn new(
+        meta: &'a ScanMeta,
+        rules: &AlertRules,
+        previous: Option<&Snapshot>,
+        alerts: &'a [Alert],
+    ) -> Self {
+        Self {
+            host: hostname(),
+            root: &meta.root,
+            run_id: &meta.run_id,
+            started_at: meta.started_at.to_rfc3339(),
+            previous_snapshot: previous.map(|s| s.taken_at.to_rfc3339()),
+            threshold: rules.threshold,
+            growth_percent: rules.growth_percent,
+            alerts,
+        }
+    }
+
+    /// Subject line for email alerts
+    pub fn subject(&self) -> String {
+        format!(
+            "[rudu] {} {} over limits on {}:{}",
+            self.alerts.len(),
+            if self.alerts.len() == 1 {
+                "directory"
+            } else {
+                "directories"
+            },
+            self.host,
+            self.root.display()
+        )
+    }
+
+    /// Plain-text body for email alerts, one line per directory
+    pub fn text(&self) -> String {
+        let mut text = format!(
+            "Scan {} of {} on {} started {}\n\n",
+            self.run_id,
+            self.root.display(),
+            self.host,
+            self.started_at
+        );
+        for alert in self.alerts {
+            let mut reasons = Vec::new();
+            if alert.over_threshold {
+                reasons.push(format!(
+                    "over the threshold of {} bytes",
+                    self.threshold.unwrap_or(0)
+                ));
+            }
+            if let (true, Some(growth), Some(previous)) =
+                (alert.over_growth, alert.growth_percent, alert.previous_size)
+            {
+                reasons.push(format!(
+                    "grew {:.1}% from {} bytes since {}",
+                    growth,
+                    previous,
+                    self.previous_snapshot
+                        .as_deref()
+                        .unwrap_or("the last snapshot")
+                ));
+            }
+            text.push_str(&format!(
+                "{}: {} bytes, {}\n",
+                alert.path.display(),
+                alert.size,
+                reasons.join(", ")
+            ));
+        }
+        text
+    }
+}
+
+/// The directories among `entries` that break `rules`, largest first.
+///
+/// Growth is measured against `previous`, a snapshot of the same root;
+/// directories missing from it, or empty in it, are only checked against
+/// the threshold.
+pub fn check(
+    entries: &[FileEntry],
+    root: &Path,
+    previous: Option<&Snapshot>,
+    rules: &AlertRules,
+) -> Vec<Alert> {
+    let mut alerts: Vec<Alert> = entries
+        .iter()
+        .filter(|e| e.entry_type.is_dir())
+        .filter_map(|e| {
+            let previous_size = previous.and_then(|snapshot| {
+                let relative = e.path.strip_prefix(root).ok()?;
+                snapshot.dirs.get(relative).copied()
+            });
+            let growth_percent = previous_size
+                .filter(|&previous| previous > 0)
+                .map(|previous| (e.size as f64 - previous as f64) * 100.0 / previous as f64);
+            let over_threshold = rules.threshold.is_some_and(|limit| e.size > limit);
+            let over_growth = rules
+                .growth_percent
+                .zip(growth_percent)
+                .is_some_and(|(limit, growth)| growth > limit);
+            (over_threshold || over_growth).then(|| Alert {
+                path: e.path.clone(),
+                size: e.size,
+                previous_size,
+                growth_percent,
+                over_threshold,
+                over_growth,
+            })
+        })
+        .collect();
+    alerts.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+    alerts
+}
+
+/// POSTs `payload` as JSON to `url`.
+///
+/// # Errors
+/// Returns an error if the server cannot be reached or does not answer
+/// with a 2xx status.
+pub fn post_webhook(url: &str, payload: &AlertPayload) -> Result<()> {
+    let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+    match agent
+        .post(url)
+        .set("Content-Type", "application/json")
+        .send_string(&serde_json::to_string(payload)?)
+    {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::Status(status, _)) => {
+            anyhow::bail!("Webhook {} answered HTTP {}", url, status)
+        }
+        Err(e) => Err(e).with_context(|| format!("Failed to reach webhook {}", url)),
+    }
+}
+
+/// Where and how to send email alerts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmtpConfig {
+    /// Relay as `host` or `host:port`; port 25 if not given
+    pub server: String,
+    /// Sender address
+    pub from: String,
+    /// Recipient addresses
+    pub to: Vec<String>,
+}
+
+/// Sends `payload` as a plain-text email through `config.server`.
+///
+/// # Errors
+/// Returns an error if the relay cannot be reached or refuses a command.
+pub fn send_email(config: &SmtpConfig, payload: &AlertPayload) -> Result<()> {
+    let server = if config.server.contains(':') {
+        config.server.clone()
+    } else {
+        format!("{}:25", config.server)
+    };
+    let stream = TcpStream::connect(&server)
+        .with_context(|| format!("Failed to reach SMTP server {}", server))?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut session = SmtpSession {
+        reader: BufReader::new(stream.try_clone()?),
+        writer: stream,
+    };
+    session.expect(220)?;
+    session.command(&format!("EHLO {}", hostname()), 250)?;
+    session.command(&format!("MAIL FROM:<{}>", config.from), 250)?;
+    for to in &config.to {
+        session.command(&format!("RCPT TO:<{}>", to), 250)?;
+    }
+    session.command("DATA", 354)?;
+    let message = message(config, &payload.subject(), &payload.text());
+    session.writer.write_all(message.as_bytes())?;
+    session.command(".", 250)?;
+    session.command("QUIT", 221)
+}
+
+/// The message for `DATA`: headers, then `body` with CRLF line endings and
+/// lines starting with `.` escaped
+pub fn message(config: &SmtpConfig, subject: &str, body: &str) -> String {
+    let mut message = format!(
+        "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMIME-Version: 1.0\r\n\
+         Content-Type: text/plain; charset=utf-8\r\n\r\n",
+        config.from,
+        config.to.join(", "),
+        subject,
+        chrono::Utc::now().to_rfc2822()
+    );
+    for line in body.lines() {
+        if line.starts_with('.') {
+            message.push('.');
+        }
+        message.push_str(line);
+        message.push_str("\r\n");
+    }
+    message
+}
+
+/// One connection to an SMTP relay
+struct SmtpSession {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl SmtpSession {
+    /// Sends `line` and checks the reply code
+    fn command(&mut self, line: &str, code: u16) -> Result<()> {
+        write!(self.writer, "{}\r\n", line)?;
+        self.expect(code)
+    }
+
+    /// Reads a (possibly multi-line) reply and checks its code
+    fn expect(&mut self, code: u16) -> Result<()> {
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                anyhow::bail!("SMTP server closed the connection");
+            }
+            // "250-..." continues a reply, "250 ..." ends it
+            if line.as_bytes().get(3) == Some(&b'-') {
+                continue;
+            }
+            let reply = line.trim_end();
+            return match reply.get(..3).and_then(|c| c.parse::<u16>().ok()) {
+                Some(got) if got == code => Ok(()),
+                _ => anyhow::bail!("SMTP server replied: {}", reply),
+            };
+        }
+    }
+}
+
+/// This machine's host name, or `localhost` if it cannot be read
+pub fn hostname() -> String {
+    let mut buf = [0u8; 256];
+    if unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) } != 0 {
+        return "localhost".to_string();
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::EntryType;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_check_and_message() {
+        let entries = vec![
+            FileEntry::for_test("/data", 5000, EntryType::Dir),
+            FileEntry::for_test("/data/big", 3000, EntryType::Dir),
+            FileEntry::for_test("/data/growing", 1500, EntryType::Dir),
+            FileEntry::for_test("/data/new", 400, EntryType::Dir),
+            FileEntry::for_test("/data/steady", 100, EntryType::Dir),
+        ];
+        let previous = Snapshot {
+            root: PathBuf::from("/data"),
+            taken_at: chrono::Utc::now(),
+            run_id: "r1".to_string(),
+            dirs: HashMap::from([
+                (PathBuf::new(), 4000),
+                (PathBuf::from("big"), 3000),
+                (PathBuf::from("growing"), 1000),
+                (PathBuf::from("steady"), 100),
+            ]),
+        };
+        let rules = AlertRules {
+            threshold: Some(2500),
+            growth_percent: Some(20.0),
+        };
+        let alerts = check(&entries, Path::new("/data"), Some(&previous), &rules);
+        let summary: Vec<(&str, bool, bool)> = alerts
+            .iter()
+            .map(|a| (a.path.to_str().unwrap(), a.over_threshold, a.over_growth))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("/data", true, true),
+                ("/data/big", true, false),
+                ("/data/growing", false, true)
+            ]
+        );
+        assert_eq!(alerts[2].growth_percent, Some(50.0));
+        assert!(check(&entries, Path::new("/data"), None, &rules).len() == 2);
+
+        let config = SmtpConfig {
+            server: "localhost".to_string(),
+            from: "rudu@example.org".to_string(),
+            to: vec!["ops@example.org".to_string()],
+        };
+        let message = message(&config, "Subject", "line\n.dot\n");
+        assert!(message.contains("To: ops@example.org\r\n"));
+        assert!(message.ends_with("\r\n\r\nline\r\n..dot\r\n"));
+    }
+}
@@ -0,0 +1,332 @@
+//! Archive members as a virtual subtree (`--scan-archives`).
+//!
+//! A multi-gigabyte tarball answers "where is the space?" with a single
+//! line. With `--scan-archives`, [`expand_archives`] lists the members of
+//! the tar, gzipped tar and zip files found by the scan and adds them below
+//! the archive as entries of their own, so the archive lists, sorts and
+//! reports like a directory.
+//!
+//! Members get no space of their own: the archive's size is shared out
+//! between its files in proportion to what each stores in it (its length in
+//! a tar, its compressed size in a zip), so members always add up to the
+//! archive and the totals above it do not change. Directories inside the
+//! archive, listed or implied by member paths, total the files below them.
+
+use crate::data::{EntryType, FileEntry};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::{Component, Path, PathBuf};
+
+/// Archive formats whose members can be listed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    /// Uncompressed tar, listed by seeking from header to header
+    Tar,
+    /// Gzipped tar, which has to be decompressed to be listed
+    TarGz,
+    /// Zip (and jar), listed from its central directory
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// The format of the archive at `path`, by file name extension
+    pub fn of(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        if name.ends_with(".tar") {
+            Some(ArchiveFormat::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else if name.ends_with(".zip") || name.ends_with(".jar") {
+            Some(ArchiveFormat::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+/// One member of an archive
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Member {
+    /// Path inside the archive, without `.`, `..` or a leading `/`
+    pub path: PathBuf,
+    /// Bytes the member takes up in the archive, which weights its share
+    /// of the archive's size
+    pub stored: u64,
+    /// Modification time in seconds since the epoch
+    pub mtime: Option<u64>,
+    /// Whether the member is a directory
+    pub is_dir: bool,
+}
+
+/// Lists the members of the archive at `path`.
+///
+/// # Errors
+/// Returns an error if the file cannot be read or is not a valid archive
+/// of `format`.
+pub fn list_members(path: &Path, format: ArchiveFormat) -> io::Result<Vec<Member>> {
+    let file = BufReader::new(File::open(path)?);
+    match format {
+        ArchiveFormat::Tar => tar_members(tar::Archive::new(file).entries_with_seek()?),
+        ArchiveFormat::TarGz => {
+            let decoder = flate2::read::GzDecoder::new(file);
+            tar_members(tar::Archive::new(decoder).entries()?)
+        }
+        ArchiveFormat::Zip => zip_members(file),
+    }
+}
+
+fn tar_members<R: io::Read>(entries: tar::Entries<'_, R>) -> io::Result<Vec<Member>> {
+    let mut members = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let header = entry.header();
+        let Some(path) = member_path(&entry.path()?) else {
+            continue;
+        };
+        members.push(Member {
+            path,
+            stored: header.size()?,
+            mtime: header.mtime().ok(),
+            is_dir: header.entry_type().is_dir(),
+        });
+    }
+    Ok(members)
+}
+
+fn zip_members(file: BufReader<File>) -> io::Result<Vec<Member>> {
+    let mut archive = zip::ZipArchive::new(file).map_err(io::Error::other)?;
+    let mut members = Vec::with_capacity(archive.len());
+    for i in 0..archive.len() {
+        let member = archive.by_index_raw(i).map_err(io::Error::other)?;
+        let Some(path) = member.enclosed_name().and_then(|p| member_path(&p)) else {
+            continue;
+        };
+        members.push(Member {
+            path,
+            stored: member.compressed_size(),
+            mtime: member.last_modified().and_then(dos_time_to_unix),
+            is_dir: member.is_dir(),
+        });
+    }
+    Ok(members)
+}
+
+/// The normal components of a member path; `None` if there are none
+fn member_path(path: &Path) -> Option<PathBuf> {
+    let path: PathBuf = path
+        .components()
+        .filter(|c| matches!(c, Component::Normal(_)))
+        .collect();
+    (!path.as_os_str().is_empty()).then_some(path)
+}
+
+/// Seconds since the epoch of a zip timestamp. Zip records local time
+/// without a zone, so this reads it as UTC.
+fn dos_time_to_unix(time: zip::DateTime) -> Option<u64> {
+    let timestamp = chrono::NaiveDate::from_ymd_opt(
+        i32::from(time.year()),
+        u32::from(time.month()),
+        u32::from(time.day()),
+    )?
+    .and_hms_opt(
+        u32::from(time.hour()),
+        u32::from(time.minute()),
+        u32::from(time.second()),
+    )?
+    .and_utc()
+    .timestamp();
+    u64::try_from(timestamp).ok()
+}
+
+/// Adds the members of the archives among `entries` below them and marks
+/// the archives as [`EntryType::Archive`].
+///
+/// Archives are listed in parallel; one that cannot be listed is logged and
+/// kept as a plain file. Members inherit the archive's owner, and with
+/// `show_inodes` the archive and its directories count their direct
+/// children.
+pub fn expand_archives(entries: &mut Vec<FileEntry>, show_inodes: bool) {
+    let listed: Vec<(usize, Vec<Member>)> = entries
+        .par_iter()
+        .enumerate()
+        .filter(|(_, e)| e.entry_type == EntryType::File)
+        .filter_map(|(i, e)| {
+            let format = ArchiveFormat::of(&e.path)?;
+            match list_members(&e.path, format) {
+                Ok(members) => Some((i, members)),
+                Err(err) => {
+                    log::warn!("Cannot list archive {}: {}", e.path.display(), err);
+                    None
+                }
+            }
+        })
+        .collect();
+    for (i, members) in listed {
+        let added = member_entries(&entries[i], &members, show_inodes);
+        let archive = &mut entries[i];
+        archive.entry_type = EntryType::Archive;
+        if show_inodes {
+            archive.inodes = Some(
+                added
+                    .iter()
+                    .filter(|e| e.path.parent() == Some(&archive.path))
+                    .count() as u64,
+            );
+        }
+        entries.extend(added);
+    }
+}
+
+/// The entries of the members of `archive`, files sharing out its size and
+/// directories totalling the files below them.
+fn member_entries(archive: &FileEntry, members: &[Member], show_inodes: bool) -> Vec<FileEntry> {
+    let mut dirs: BTreeSet<PathBuf> = members
+        .iter()
+        .filter(|m| m.is_dir)
+        .map(|m| m.path.clone())
+        .collect();
+    // A member appended twice takes up space twice
+    let mut files: BTreeMap<&Path, (u64, Option<u64>)> = BTreeMap::new();
+    for member in members.iter().filter(|m| !m.is_dir) {
+        let file = files.entry(&member.path).or_default();
+        file.0 += member.stored;
+        file.1 = file.1.max(member.mtime);
+        dirs.extend(member.path.ancestors().skip(1).map(Path::to_path_buf));
+    }
+    dirs.remove(Path::new(""));
+    files.retain(|path, _| !dirs.contains(*path));
+
+    // Shares from running totals, so rounding never loses a byte
+    let total_stored: u64 = files.values().map(|(stored, _)| stored).sum();
+    let mut running = 0u64;
+    let mut shared = 0u64;
+    let mut dir_totals: BTreeMap<&Path, (u64, Option<u64>, u64)> =
+        dirs.iter().map(|d| (d.as_path(), (0, None, 0))).collect();
+    let mut entries = Vec::with_capacity(files.len() + dirs.len());
+    for (path, (stored, mtime)) in &files {
+        running += stored;
+        let upto = match total_stored {
+            0 => 0,
+            total => (u128::from(archive.size) * u128::from(running) / u128::from(total)) as u64,
+        };
+        let size = upto - shared;
+        shared = upto;
+        for ancestor in path.ancestors().skip(1) {
+            if let Some(dir) = dir_totals.get_mut(ancestor) {
+                dir.0 += size;
+                dir.1 = dir.1.max(*mtime);
+            }
+        }
+        entries.push(member_entry(archive, path, size, *mtime, EntryType::File));
+    }
+    for path in files
+        .keys()
+        .copied()
+        .chain(dirs.iter().map(PathBuf::as_path))
+    {
+        if let Some(dir) = path.parent().and_then(|p| dir_totals.get_mut(p)) {
+            dir.2 += 1;
+        }
+    }
+    for (path, (size, mtime, children)) in dir_totals {
+        let mut entry = member_entry(archive, path, size, mtime, EntryType::Dir);
+        entry.inodes = show_inodes.then_some(children);
+        entries.push(entry);
+    }
+    entries
+}
+
+fn member_entry(
+    archive: &FileEntry,
+    path: &Path,
+    size: u64,
+    mtime: Option<u64>,
+    entry_type: EntryType,
+) -> FileEntry {
+    FileEntry {
+        path: archive.path.join(path),
+        size,
+        owner: archive.owner.clone(),
+        inodes: None,
+        entry_type,
+        previous_size: None,
+        mtime,
+        stat: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn test_expand_archives() {
+        let dir = tempfile::tempdir().unwrap();
+        let tar_path = dir.path().join("backup.tar");
+        let mut builder = tar::Builder::new(File::create(&tar_path).unwrap());
+        for (name, len) in [
+            ("./data/a.bin", 3000),
+            ("data/sub/b.bin", 1000),
+            ("c.txt", 0),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(len);
+            header.set_mtime(1_700_000_000);
+            builder
+                .append_data(&mut header, name, io::repeat(0).take(len))
+                .unwrap();
+        }
+        builder.finish().unwrap();
+        drop(builder);
+
+        let zip_path = dir.path().join("Site.ZIP");
+        let mut writer = zip::ZipWriter::new(File::create(&zip_path).unwrap());
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        writer.add_directory("empty/", options).unwrap();
+        writer.start_file("index.html", options).unwrap();
+        writer.write_all(b"<html></html>").unwrap();
+        writer.finish().unwrap();
+
+        let entry = |path: &Path, size| FileEntry {
+            path: path.to_path_buf(),
+            size,
+            owner: Some("alice".to_string()),
+            inodes: None,
+            entry_type: EntryType::File,
+            previous_size: None,
+            mtime: None,
+            stat: None,
+        };
+        let not_tar = dir.path().join("broken.tar");
+        std::fs::write(&not_tar, b"not a tar").unwrap();
+        let mut entries = vec![
+            entry(&tar_path, 4001),
+            entry(&zip_path, 500),
+            entry(&not_tar, 9),
+        ];
+        expand_archives(&mut entries, true);
+
+        let find = |path: PathBuf| entries.iter().find(|e| e.path == path).unwrap();
+        let tar = find(tar_path.clone());
+        assert_eq!((tar.entry_type, tar.inodes), (EntryType::Archive, Some(2)));
+        // 4001 bytes shared 3000:1000:0, rounding down along the way
+        assert_eq!(find(tar_path.join("data/a.bin")).size, 3000);
+        assert_eq!(find(tar_path.join("data/sub/b.bin")).size, 1001);
+        assert_eq!(find(tar_path.join("c.txt")).size, 0);
+        let data = find(tar_path.join("data"));
+        assert_eq!(
+            (data.entry_type, data.size, data.inodes, data.mtime),
+            (EntryType::Dir, 4001, Some(2), Some(1_700_000_000))
+        );
+        assert_eq!(data.owner.as_deref(), Some("alice"));
+
+        assert_eq!(find(zip_path.join("index.html")).size, 500);
+        assert_eq!(find(zip_path.join("empty")).size, 0);
+        assert_eq!(find(not_tar.clone()).entry_type, EntryType::File);
+        assert_eq!(entries.len(), 3 + 5 + 2);
+    }
+}
@@ -0,0 +1,284 @@
+//! Per-directory size and inode budgets (`--budgets`).
+//!
+//! A budgets file maps directory patterns to the most bytes and inodes the
+//! matching directories may hold, such as one line per project of a shared
+//! filesystem. After the scan, [`Budgets::check`] compares every matching
+//! directory with its budget; the directories over theirs are reported and
+//! make rudu exit with an error, so CI jobs and cron scripts can gatekeep
+//! the space.
+//!
+//! ```text
+//! # PATTERN        SIZE   INODES
+//! projects/*       500G   1000000
+//! projects/climate 2T     -
+//! /scratch/*/tmp   -      50000
+//! ```
+//!
+//! Patterns are globs whose `*` stays within one path component. Relative
+//! patterns are matched below the scan root and absolute ones against the
+//! full path. A directory matched by several lines gets the budget of the
+//! last one, so general rules go first and exceptions after them. `-`
+//! leaves a limit unset, and inodes count a directory and everything below
+//! it.
+
+use crate::data::FileEntry;
+use crate::output::units::parse_size;
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobMatcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// One line of a budgets file
+#[derive(Debug, Clone)]
+pub struct Budget {
+    /// The pattern as written
+    pub pattern: String,
+    /// Bytes a matching directory may hold
+    pub max_size: Option<u64>,
+    /// Inodes a matching directory may hold, counting itself
+    pub max_inodes: Option<u64>,
+    matcher: GlobMatcher,
+}
+
+impl Budget {
+    /// Parses a `PATTERN SIZE [INODES]` line.
+    fn parse(line: &str) -> Result<Self> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let (pattern, size, inodes) = match fields[..] {
+            [pattern, size] => (pattern, size, "-"),
+            [pattern, size, inodes] => (pattern, size, inodes),
+            _ => anyhow::bail!("expected PATTERN SIZE [INODES]"),
+        };
+        let max_size = match size {
+            "-" => None,
+            size => Some(parse_size(size).map_err(anyhow::Error::msg)?),
+        };
+        let max_inodes = match inodes {
+            "-" => None,
+            inodes => Some(
+                inodes
+                    .parse()
+                    .with_context(|| format!("invalid inode count '{}'", inodes))?,
+            ),
+        };
+        if max_size.is_none() && max_inodes.is_none() {
+            anyhow::bail!("no limit for {}", pattern);
+        }
+        let glob = pattern.trim_start_matches("./").trim_end_matches('/');
+        let matcher = GlobBuilder::new(glob)
+            .literal_separator(true)
+            .build()
+            .with_context(|| format!("invalid pattern '{}'", pattern))?
+            .compile_matcher();
+        Ok(Self {
+            pattern: pattern.to_string(),
+            max_size,
+            max_inodes,
+            matcher,
+        })
+    }
+
+    /// Whether the budget covers the directory at `path` of a scan of `root`
+    fn matches(&self, path: &Path, root: &Path) -> bool {
+        if Path::new(&self.pattern).is_absolute() {
+            self.matcher.is_match(path)
+        } else {
+            path.strip_prefix(root)
+                .is_ok_and(|relative| self.matcher.is_match(relative))
+        }
+    }
+}
+
+/// The budgets of a budgets file, in file order
+#[derive(Debug, Clone, Default)]
+pub struct Budgets {
+    budgets: Vec<Budget>,
+}
+
+impl Budgets {
+    /// Reads a budgets file.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or a line is invalid.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read budgets file {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid budgets file {}", path.display()))
+    }
+
+    /// Parses budgets from lines of `PATTERN SIZE [INODES]`; blank lines
+    /// and `#` comments are skipped.
+    ///
+    /// # Errors
+    /// Returns an error naming the first invalid line, or if there are no
+    /// budgets at all.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut budgets = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            budgets.push(Budget::parse(line).with_context(|| format!("line {}", number + 1))?);
+        }
+        if budgets.is_empty() {
+            anyhow::bail!("no budgets found");
+        }
+        Ok(Self { budgets })
+    }
+
+    /// The budget of the directory at `path` of a scan of `root`: that of
+    /// the last line matching it
+    pub fn lookup(&self, path: &Path, root: &Path) -> Option<&Budget> {
+        self.budgets.iter().rev().find(|b| b.matches(path, root))
+    }
+
+    /// Number of budgets
+    pub fn len(&self) -> usize {
+        self.budgets.len()
+    }
+
+    /// Whether there are no budgets
+    #[allow(dead_code)] // the CLI only needs len(); kept for library users
+    pub fn is_empty(&self) -> bool {
+        self.budgets.is_empty()
+    }
+
+    /// The directories among the scan `entries` of `root` that are over
+    /// their budget, in path order.
+    ///
+    /// Inodes are counted from `entries`, so they need to hold every file
+    /// and directory the scan walked.
+    pub fn check(&self, entries: &[FileEntry], root: &Path) -> Vec<Violation> {
+        let mut budgeted: HashMap<&Path, (&Budget, u64, u64)> = entries
+            .iter()
+            .filter(|e| e.entry_type.is_dir())
+            .filter_map(|e| Some((e.path.as_path(), (self.lookup(&e.path, root)?, e.size, 0))))
+            .collect();
+        for entry in entries {
+            for ancestor in entry.path.ancestors() {
+                if let Some(dir) = budgeted.get_mut(ancestor) {
+                    dir.2 += 1;
+                }
+                if ancestor == root {
+                    break;
+                }
+            }
+        }
+
+        let mut violations: Vec<Violation> = budgeted
+            .into_iter()
+            .map(|(path, (budget, size, inodes))| Violation {
+                path: path.to_path_buf(),
+                pattern: budget.pattern.clone(),
+                size,
+                max_size: budget.max_size,
+                inodes,
+                max_inodes: budget.max_inodes,
+            })
+            .filter(|v| v.over_size() || v.over_inodes())
+            .collect();
+        violations.sort_by(|a, b| a.path.cmp(&b.path));
+        violations
+    }
+}
+
+/// A directory over its budget
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// The directory
+    pub path: PathBuf,
+    /// Pattern of the budget line that applies to it
+    pub pattern: String,
+    /// Its size in bytes
+    pub size: u64,
+    /// Bytes it may hold
+    pub max_size: Option<u64>,
+    /// Its inodes, counting itself
+    pub inodes: u64,
+    /// Inodes it may hold
+    pub max_inodes: Option<u64>,
+}
+
+impl Violation {
+    /// Whether the directory holds more bytes than its budget
+    pub fn over_size(&self) -> bool {
+        self.max_size.is_some_and(|max| self.size > max)
+    }
+
+    /// Whether the directory holds more inodes than its budget
+    pub fn over_inodes(&self) -> bool {
+        self.max_inodes.is_some_and(|max| self.inodes > max)
+    }
+
+    /// Which limits are broken, e.g. `over size`
+    pub fn status(&self) -> &'static str {
+        match (self.over_size(), self.over_inodes()) {
+            (true, true) => "over size and inodes",
+            (true, false) => "over size",
+            _ => "over inodes",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::EntryType;
+
+    #[test]
+    fn test_check_budgets() {
+        let budgets = Budgets::parse(
+            "# PATTERN SIZE INODES\n\
+             \n\
+             projects/*     1K  -\n\
+             ./projects/big 10K 2\n\
+             /data/scratch  -   2\n",
+        )
+        .unwrap();
+        assert_eq!(budgets.len(), 3);
+
+        let root = Path::new("/data");
+        let entries = vec![
+            FileEntry::for_test("/data", 9000, EntryType::Dir),
+            FileEntry::for_test("/data/projects", 8000, EntryType::Dir),
+            FileEntry::for_test("/data/projects/small", 1000, EntryType::Dir),
+            FileEntry::for_test("/data/projects/small/a", 1000, EntryType::File),
+            FileEntry::for_test("/data/projects/big", 5000, EntryType::Dir),
+            FileEntry::for_test("/data/projects/big/a", 2500, EntryType::File),
+            FileEntry::for_test("/data/projects/big/b", 2500, EntryType::File),
+            FileEntry::for_test("/data/projects/huge", 2000, EntryType::Dir),
+            FileEntry::for_test("/data/scratch", 1000, EntryType::Dir),
+            FileEntry::for_test("/data/scratch/x", 500, EntryType::File),
+            FileEntry::for_test("/data/scratch/y", 500, EntryType::File),
+        ];
+
+        let violations = budgets.check(&entries, root);
+        let summary: Vec<(&str, &str, &str, u64)> = violations
+            .iter()
+            .map(|v| {
+                (
+                    v.path.to_str().unwrap(),
+                    v.pattern.as_str(),
+                    v.status(),
+                    v.inodes,
+                )
+            })
+            .collect();
+        // `projects/big` overrides `projects/*`, and `*` does not reach into
+        // project subdirectories
+        assert_eq!(
+            summary,
+            vec![
+                ("/data/projects/big", "./projects/big", "over inodes", 3),
+                ("/data/projects/huge", "projects/*", "over size", 1),
+                ("/data/scratch", "/data/scratch", "over inodes", 3),
+            ]
+        );
+
+        assert!(Budgets::parse("# nothing\n").is_err());
+        assert!(Budgets::parse("projects/* - -\n").is_err());
+        let err = Budgets::parse("a 1G\nb 1X\n").unwrap_err();
+        assert_eq!(format!("{:#}", err).split(':').next(), Some("line 2"));
+    }
+}
@@ -0,0 +1,194 @@
+//! Optional encryption of cache files.
+//!
+//! Cache files record every scanned directory path, and on shared systems the
+//! default cache directory may be readable by other users. When a key is
+//! configured, cache files are written sealed with AES-256-GCM instead of in
+//! the plain [`mapped`](super::mapped) layout:
+//!
+//! ```text
+//! magic "RUDUENC1" | schema version u32 | nonce (12 bytes) | ciphertext + tag
+//! ```
+//!
+//! The schema version stays readable so [`migrate`](super::migrate) can tell
+//! whether a sealed file is current without decrypting it; it is
+//! authenticated together with the magic as associated data.
+//!
+//! The key is derived with SHA-256 from the `RUDU_CACHE_KEY` environment
+//! variable or from the contents of the file given to `--cache-key-file`.
+//! Sealed caches cannot be read without the key and are treated as missing;
+//! plain caches are still read while a key is set and are sealed on the next
+//! save.
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{Context, Result, anyhow};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// Environment variable holding the cache passphrase
+pub const KEY_ENV_VAR: &str = "RUDU_CACHE_KEY";
+
+/// Magic bytes identifying a sealed cache file
+const MAGIC: &[u8; 8] = b"RUDUENC1";
+
+/// Length of the authenticated, unencrypted prefix: magic and schema version
+const AAD_LEN: usize = 12;
+
+/// Length of the AES-GCM nonce
+const NONCE_LEN: usize = 12;
+
+/// The active key, if cache encryption is enabled
+static KEY: Lazy<RwLock<Option<[u8; 32]>>> = Lazy::new(|| RwLock::new(None));
+
+/// Installs the key derived from `material`, or disables encryption.
+pub fn set_key(material: Option<&[u8]>) {
+    *KEY.write() = material.map(|m| Sha256::digest(m).into());
+}
+
+/// Check whether cache files are written encrypted
+pub fn is_enabled() -> bool {
+    KEY.read().is_some()
+}
+
+/// Installs the key from `key_file`, or else from [`KEY_ENV_VAR`].
+///
+/// Surrounding whitespace, such as a trailing newline, is ignored.
+///
+/// # Errors
+/// Returns an error if the key file cannot be read or the key is empty.
+pub fn configure(key_file: Option<&Path>) -> Result<()> {
+    let material = match key_file {
+        Some(path) => Some(
+            std::fs::read(path)
+                .with_context(|| format!("Failed to read cache key file: {}", path.display()))?,
+        ),
+        None => std::env::var_os(KEY_ENV_VAR).map(|v| v.into_encoded_bytes()),
+    };
+
+    let material = material.map(|m| m.trim_ascii().to_vec());
+    if material.as_ref().is_some_and(|m| m.is_empty()) {
+        return Err(anyhow!("Cache encryption key is empty"));
+    }
+    set_key(material.as_deref());
+    Ok(())
+}
+
+/// Check whether `bytes` start like a sealed cache file
+pub fn is_sealed(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// The schema version recorded in a sealed file's preamble
+pub fn format_version(bytes: &[u8]) -> Option<u32> {
+    (bytes.len() >= AAD_LEN && is_sealed(bytes))
+        .then(|| u32::from_le_bytes(bytes[8..12].try_into().unwrap()))
+}
+
+/// Encrypt an encoded cache with the active key
+///
+/// # Arguments
+/// * `plain` - The encoded cache; its mapped-format version is recorded in
+///   the sealed preamble
+///
+/// # Errors
+/// Fails if no key is configured.
+pub fn seal(plain: &[u8]) -> Result<Vec<u8>> {
+    seal_with(&cipher()?, plain)
+}
+
+/// Decrypt a sealed cache file with the active key
+///
+/// # Errors
+/// Fails if no key is configured, the file is not sealed, or it was sealed
+/// with a different key or has been modified.
+pub fn open(sealed: &[u8]) -> Result<Vec<u8>> {
+    if !is_sealed(sealed) || sealed.len() < AAD_LEN + NONCE_LEN {
+        return Err(anyhow!("Not an encrypted cache file"));
+    }
+    open_with(&cipher()?, sealed)
+}
+
+fn cipher() -> Result<Aes256Gcm> {
+    let key = KEY
+        .read()
+        .ok_or_else(|| anyhow!("Cache is encrypted but no key is configured"))?;
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+}
+
+fn seal_with(cipher: &Aes256Gcm, plain: &[u8]) -> Result<Vec<u8>> {
+    let version = super::migrate::schema_version(plain);
+
+    let mut out = Vec::with_capacity(AAD_LEN + NONCE_LEN + plain.len() + 16);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&version.to_le_bytes());
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plain,
+                aad: &out[..AAD_LEN],
+            },
+        )
+        .map_err(|_| anyhow!("Failed to encrypt cache"))?;
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+fn open_with(cipher: &Aes256Gcm, sealed: &[u8]) -> Result<Vec<u8>> {
+    let (aad, rest) = sealed.split_at(AAD_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    cipher
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| anyhow!("Failed to decrypt cache: wrong key or corrupt file"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::{CacheHeader, mapped, migrate};
+
+    fn cipher_for(material: &[u8]) -> Aes256Gcm {
+        let key: [u8; 32] = Sha256::digest(material).into();
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+    }
+
+    #[test]
+    fn test_seal_round_trip() {
+        let plain = mapped::encode(
+            &CacheHeader::new_with_mtime("/data".into(), None),
+            &Default::default(),
+        )
+        .unwrap();
+        let cipher = cipher_for(b"secret");
+
+        let sealed = seal_with(&cipher, &plain).unwrap();
+        assert!(is_sealed(&sealed));
+        assert_eq!(format_version(&sealed), Some(migrate::SCHEMA_VERSION));
+        assert!(!sealed.windows(5).any(|w| w == b"/data"));
+        assert_eq!(open_with(&cipher, &sealed).unwrap(), plain);
+
+        // A fresh nonce per write
+        assert_ne!(seal_with(&cipher, &plain).unwrap(), sealed);
+    }
+
+    #[test]
+    fn test_open_rejects_wrong_key_and_tampering() {
+        let sealed = seal_with(&cipher_for(b"secret"), b"payload").unwrap();
+        assert!(open_with(&cipher_for(b"other"), &sealed).is_err());
+
+        // The schema version is authenticated
+        let mut tampered = sealed.clone();
+        tampered[8] ^= 1;
+        assert!(open_with(&cipher_for(b"secret"), &tampered).is_err());
+    }
+}
@@ -0,0 +1,131 @@
+//! Per-filesystem cache location policy.
+//!
+//! By default every cache file lives under [`cache_root`](super::cache_root).
+//! For network home directories that is a poor choice: the cache counts
+//! against the home quota and is slow to read back. A [`CacheDirRule`] maps a
+//! scanned path prefix to a different cache directory, e.g. caches for scans
+//! under `/g/data` can be kept on `/scratch`.
+//!
+//! Rules come from `--cache-dir-for PREFIX=DIR` and from the optional config
+//! file `$XDG_CONFIG_HOME/rudu/cache-dirs` (one `PREFIX=DIR` per line, `#`
+//! starts a comment). The rule with the longest matching prefix wins; on equal
+//! prefixes, command-line rules take precedence over the config file.
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Name of the rules file inside the rudu config directory
+pub const CONFIG_FILE_NAME: &str = "cache-dirs";
+
+/// The active rules, in precedence order
+static RULES: Lazy<RwLock<Vec<CacheDirRule>>> = Lazy::new(|| RwLock::new(Vec::new()));
+
+/// Maps scanned paths under `prefix` to caches stored in `dir`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheDirRule {
+    /// Scanned roots at or below this path use `dir`
+    pub prefix: PathBuf,
+    /// Cache root used instead of the default; caches go to `dir/rudu/`
+    pub dir: PathBuf,
+}
+
+impl FromStr for CacheDirRule {
+    type Err = String;
+
+    /// Parses `PREFIX=DIR`; a relative `PREFIX` is resolved against the
+    /// current directory.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (prefix, dir) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected PREFIX=DIR, got '{}'", s))?;
+        let (prefix, dir) = (prefix.trim(), dir.trim());
+        if prefix.is_empty() || dir.is_empty() {
+            return Err(format!("expected PREFIX=DIR, got '{}'", s));
+        }
+
+        Ok(Self {
+            prefix: std::path::absolute(prefix).map_err(|e| e.to_string())?,
+            dir: PathBuf::from(dir),
+        })
+    }
+}
+
+/// Replaces the active rules.
+///
+/// # Arguments
+/// * `rules` - Rules in precedence order; earlier rules win ties on prefix length
+pub fn set_rules(rules: Vec<CacheDirRule>) {
+    *RULES.write() = rules;
+}
+
+/// Returns a copy of the active rules.
+pub fn rules() -> Vec<CacheDirRule> {
+    RULES.read().clone()
+}
+
+/// Installs the command-line rules followed by those from the config file.
+///
+/// # Errors
+/// Returns an error if the config file exists but cannot be read or parsed.
+pub fn configure(cli_rules: &[CacheDirRule]) -> Result<()> {
+    let mut rules = cli_rules.to_vec();
+    if let Some(path) = config_path()
+        && path.exists()
+    {
+        rules.extend(load_rules_file(&path)?);
+    }
+    set_rules(rules);
+    Ok(())
+}
+
+/// Reads `PREFIX=DIR` rules from a file, ignoring blank lines and `#` comments.
+pub fn load_rules_file(path: &Path) -> Result<Vec<CacheDirRule>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read cache location rules: {}", path.display()))?;
+
+    content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i, line.split('#').next().unwrap_or("").trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(i, line)| {
+            line.parse()
+                .map_err(|e| anyhow::anyhow!("{}:{}: {}", path.display(), i + 1, e))
+        })
+        .collect()
+}
+
+/// Returns the cache root for scans of `root`.
+///
+/// Falls back to [`cache_root`](super::cache_root) when no rule matches.
+pub fn cache_root_for(root: &Path) -> PathBuf {
+    let rules = RULES.read();
+    if rules.is_empty() {
+        return super::cache_root();
+    }
+
+    let root = std::path::absolute(root).unwrap_or_else(|_| root.to_path_buf());
+    let mut best: Option<&CacheDirRule> = None;
+    for rule in rules.iter().filter(|r| root.starts_with(&r.prefix)) {
+        let longer =
+            best.is_none_or(|b| rule.prefix.components().count() > b.prefix.components().count());
+        if longer {
+            best = Some(rule);
+        }
+    }
+
+    best.map(|rule| rule.dir.clone())
+        .unwrap_or_else(super::cache_root)
+}
+
+/// Location of the rules file: `$XDG_CONFIG_HOME/rudu/cache-dirs`, or
+/// `~/.config/rudu/cache-dirs`.
+fn config_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join("rudu").join(CONFIG_FILE_NAME))
+}
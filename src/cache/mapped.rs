@@ -0,0 +1,1005 @@
+//! Fixed-record, hash-indexed on-disk cache format
+//!
+//! The bincode format has to be deserialized in full before any lookup can be
+//! made, so a cache describing millions of directories costs millions of heap
+//! allocations on every run. This format instead lays entries out as
+//! fixed-size records in an open-addressing hash table so that a single
+//! directory lookup touches one or two records straight from the memory map.
+//! Each record also points at the slot indices of its children, letting the
+//! scanner restore a cached subtree without building a parent→children index.
+//!
+//! Layout (all integers little-endian):
+//!
+//! ```text
+//! offset 0   magic "RUDUMAP1"                       8 bytes
+//!        8   format version (u32), reserved (u32)
+//!       16   slot count (u64, power of two)
+//!       24   entry count (u64)
+//!       32   encoded header length (u64)
+//!       40   child index count (u64)
+//!       48   path string bytes (u64)
+//!       56   CacheHeader (bincode), padded to 8 bytes
+//!            slots: slot count × RECORD_LEN bytes
+//!            child index: child index count × u32, padded to 8 bytes
+//!            path strings, padded to 8 bytes
+//!            bloom filter: bloom_len(entry count) bytes
+//! ```
+//!
+//! The bloom filter holds every record's path hash, so [`MappedCache::lookup`]
+//! rejects most paths that are not cached by testing a few bits of a bitmap
+//! roughly a tenth the size of the slot table, without touching the slots.
+//!
+//! Record layout (`RECORD_LEN` = 96 bytes):
+//!
+//! ```text
+//!  0 path_hash u64    8 size u64       16 mtime u64        24 ctime u64
+//! 32 nlink u64       40 inode_cnt u64  48 path_offset u64  56 children_offset u64
+//! 64 path_len u32    68 children u32   72 owner u32        76 flags u32
+//! 80 last_hit u64    88 entry_type u8  89..96 padding
+//! ```
+
+use anyhow::{Context, Result, anyhow};
+use memmap2::Mmap;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+use super::crypt;
+use super::model::{CacheEntry, CacheHeader, CacheValidation};
+use crate::data::EntryType;
+
+/// Magic bytes identifying a mapped cache file
+pub const MAGIC: &[u8; 8] = b"RUDUMAP1";
+
+/// On-disk layout version of the mapped format
+pub const FORMAT_VERSION: u32 = 3;
+
+/// Size in bytes of one fixed record
+pub const RECORD_LEN: usize = 96;
+
+const PREAMBLE_LEN: usize = 56;
+
+/// Bloom filter bits allotted per entry, before rounding up to a power of two
+const BLOOM_BITS_PER_ENTRY: usize = 10;
+
+/// Number of bloom filter bits set per path hash
+const BLOOM_HASHES: u64 = 7;
+
+const FLAG_OCCUPIED: u32 = 1;
+const FLAG_HAS_CTIME: u32 = 1 << 1;
+const FLAG_HAS_INODE_CNT: u32 = 1 << 2;
+const FLAG_HAS_OWNER: u32 = 1 << 3;
+
+/// Returns true if `bytes` start with the mapped-format magic
+pub fn is_mapped_format(bytes: &[u8]) -> bool {
+    bytes.len() >= MAGIC.len() && &bytes[..MAGIC.len()] == MAGIC
+}
+
+/// The layout version recorded in a mapped-format preamble
+///
+/// # Returns
+/// * `Option<u32>` - The version, or `None` if `bytes` are not a mapped cache
+pub fn format_version(bytes: &[u8]) -> Option<u32> {
+    (bytes.len() >= MAGIC.len() + 4 && is_mapped_format(bytes)).then(|| get_u32(bytes, 8))
+}
+
+/// Rewrite a version 1 file (88-byte records, no `last_hit`) as version 2
+///
+/// Version 2 inserted `last_hit` at record offset 80 and moved the entry type
+/// to offset 88. Slot positions, the child index and the path strings are
+/// unchanged, so they are copied as-is; `last_hit` is set to the cache's
+/// creation time.
+pub(crate) fn upgrade_v1(bytes: &[u8]) -> Result<Vec<u8>> {
+    const V1_RECORD_LEN: usize = 88;
+
+    if format_version(bytes) != Some(1) || bytes.len() < PREAMBLE_LEN {
+        return Err(anyhow!("Not a version 1 mapped cache"));
+    }
+    let slot_count = get_u64(bytes, 16) as usize;
+    let header_len = get_u64(bytes, 32) as usize;
+    // As in `MappedCache::from_map`, the sizes come from the file
+    let layout = || {
+        let slots_start = PREAMBLE_LEN.checked_add(checked_pad8(header_len)?)?;
+        let slots_end = slots_start.checked_add(slot_count.checked_mul(V1_RECORD_LEN)?)?;
+        Some((slots_start, slots_end))
+    };
+    let (slots_start, slots_end) =
+        layout().ok_or_else(|| anyhow!("Corrupt mapped cache: section sizes overflow"))?;
+    if bytes.len() < slots_end {
+        return Err(anyhow!("Truncated mapped cache file"));
+    }
+    let header: CacheHeader = bincode::deserialize(&bytes[PREAMBLE_LEN..PREAMBLE_LEN + header_len])
+        .context("Failed to deserialize mapped cache header")?;
+
+    let mut out = Vec::with_capacity(bytes.len() + slot_count * (RECORD_LEN - V1_RECORD_LEN));
+    out.extend_from_slice(&bytes[..slots_start]);
+    put_u32(&mut out, 8, 2);
+
+    for old in bytes[slots_start..slots_end].chunks_exact(V1_RECORD_LEN) {
+        let mut record = [0u8; RECORD_LEN];
+        if get_u32(old, 76) & FLAG_OCCUPIED != 0 {
+            record[..80].copy_from_slice(&old[..80]);
+            put_u64(&mut record, 80, header.creation_time);
+            record[88] = old[80];
+        }
+        out.extend_from_slice(&record);
+    }
+
+    // The child index starts right after the slots and is followed by
+    // padding to 8 bytes; the new slots keep that alignment.
+    out.extend_from_slice(&bytes[slots_end..]);
+    Ok(out)
+}
+
+/// Rewrite a version 2 file (no bloom filter) as version 3
+///
+/// The bloom filter is built from the path hashes already stored in the
+/// records and appended after the path strings; nothing else moves.
+pub(crate) fn upgrade_v2(bytes: &[u8]) -> Result<Vec<u8>> {
+    if format_version(bytes) != Some(2) || bytes.len() < PREAMBLE_LEN {
+        return Err(anyhow!("Not a version 2 mapped cache"));
+    }
+    let slot_count = get_u64(bytes, 16) as usize;
+    let entry_count = get_u64(bytes, 24) as usize;
+    let header_len = get_u64(bytes, 32) as usize;
+    let children_len = get_u64(bytes, 40) as usize;
+    let strings_len = get_u64(bytes, 48) as usize;
+    // The bloom filter is sized from the entry count, so it must be bounded
+    // by the slot table like in `MappedCache::from_map`
+    if entry_count > slot_count {
+        return Err(anyhow!("Corrupt mapped cache slot table"));
+    }
+    let layout = || {
+        let slots_start = PREAMBLE_LEN.checked_add(checked_pad8(header_len)?)?;
+        let slots_end = slots_start.checked_add(slot_count.checked_mul(RECORD_LEN)?)?;
+        let children_end = slots_end.checked_add(children_len.checked_mul(4)?)?;
+        let strings_end = checked_pad8(children_end)?.checked_add(strings_len)?;
+        Some((slots_start, slots_end, strings_end))
+    };
+    let (slots_start, slots_end, strings_end) =
+        layout().ok_or_else(|| anyhow!("Corrupt mapped cache: section sizes overflow"))?;
+    if bytes.len() < strings_end {
+        return Err(anyhow!("Truncated mapped cache file"));
+    }
+
+    let hashes = bytes[slots_start..slots_end]
+        .chunks_exact(RECORD_LEN)
+        .filter(|record| get_u32(record, 76) & FLAG_OCCUPIED != 0)
+        .map(|record| get_u64(record, 0));
+
+    let mut out = bytes[..strings_end].to_vec();
+    put_u32(&mut out, 8, 3);
+    append_bloom(&mut out, hashes, entry_count);
+    Ok(out)
+}
+
+/// Encode a header and its entries into the mapped format
+///
+/// Entries are keyed by path; the record's path hash is recomputed from the key
+/// so it always matches what [`MappedCache::lookup`] will probe for.
+pub fn encode(header: &CacheHeader, entries: &HashMap<PathBuf, CacheEntry>) -> Result<Vec<u8>> {
+    let header_bytes = bincode::serialize(header).context("Failed to serialize cache header")?;
+    let slot_count = (entries.len() * 2).next_power_of_two().max(16);
+    let mask = slot_count - 1;
+
+    // Assign each entry to a slot with linear probing
+    let mut slots: Vec<Option<(&PathBuf, &CacheEntry, u64)>> = vec![None; slot_count];
+    let mut slot_of: HashMap<&Path, u32> = HashMap::with_capacity(entries.len());
+    for (path, entry) in entries {
+        let hash = crate::utils::path_hash(path);
+        let mut slot = hash as usize & mask;
+        while slots[slot].is_some() {
+            slot = (slot + 1) & mask;
+        }
+        slots[slot] = Some((path, entry, hash));
+        slot_of.insert(path.as_path(), slot as u32);
+    }
+
+    // Group child slots under their parent's slot
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    for (path, slot) in &slot_of {
+        if let Some(parent_slot) = path.parent().and_then(|p| slot_of.get(p)) {
+            children.entry(*parent_slot).or_default().push(*slot);
+        }
+    }
+    for list in children.values_mut() {
+        list.sort_unstable();
+    }
+
+    let child_count: usize = children.values().map(Vec::len).sum();
+    let strings_len: usize = entries.keys().map(|p| p.as_os_str().len()).sum();
+
+    let mut out = Vec::with_capacity(
+        PREAMBLE_LEN + pad8(header_bytes.len()) + slot_count * RECORD_LEN + pad8(child_count * 4),
+    );
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&(slot_count as u64).to_le_bytes());
+    out.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+    out.extend_from_slice(&(header_bytes.len() as u64).to_le_bytes());
+    out.extend_from_slice(&(child_count as u64).to_le_bytes());
+    out.extend_from_slice(&(strings_len as u64).to_le_bytes());
+    out.extend_from_slice(&header_bytes);
+    out.resize(PREAMBLE_LEN + pad8(header_bytes.len()), 0);
+
+    let mut child_index: Vec<u32> = Vec::with_capacity(child_count);
+    let mut strings: Vec<u8> = Vec::with_capacity(strings_len);
+
+    for (slot, occupant) in slots.iter().enumerate() {
+        let mut record = [0u8; RECORD_LEN];
+        if let Some((path, entry, hash)) = occupant {
+            let path_bytes = path.as_os_str().as_bytes();
+            let kids = children.get(&(slot as u32));
+
+            let mut flags = FLAG_OCCUPIED;
+            if entry.ctime.is_some() {
+                flags |= FLAG_HAS_CTIME;
+            }
+            if entry.inode_cnt.is_some() {
+                flags |= FLAG_HAS_INODE_CNT;
+            }
+            if entry.owner.is_some() {
+                flags |= FLAG_HAS_OWNER;
+            }
+
+            put_u64(&mut record, 0, *hash);
+            put_u64(&mut record, 8, entry.size);
+            put_u64(&mut record, 16, entry.mtime);
+            put_u64(&mut record, 24, entry.ctime.unwrap_or(0));
+            put_u64(&mut record, 32, entry.nlink);
+            put_u64(&mut record, 40, entry.inode_cnt.unwrap_or(0));
+            put_u64(&mut record, 48, strings.len() as u64);
+            put_u64(&mut record, 56, child_index.len() as u64);
+            put_u32(&mut record, 64, path_bytes.len() as u32);
+            put_u32(&mut record, 68, kids.map(Vec::len).unwrap_or(0) as u32);
+            put_u32(&mut record, 72, entry.owner.unwrap_or(0));
+            put_u32(&mut record, 76, flags);
+            put_u64(&mut record, 80, entry.last_hit);
+            record[88] = entry_type_to_byte(entry.entry_type);
+
+            strings.extend_from_slice(path_bytes);
+            if let Some(kids) = kids {
+                child_index.extend_from_slice(kids);
+            }
+        }
+        out.extend_from_slice(&record);
+    }
+
+    for child in &child_index {
+        out.extend_from_slice(&child.to_le_bytes());
+    }
+    out.resize(pad8(out.len()), 0);
+    out.extend_from_slice(&strings);
+    append_bloom(&mut out, slot_hashes(&slots), entries.len());
+
+    Ok(out)
+}
+
+fn slot_hashes<'a>(
+    slots: &'a [Option<(&PathBuf, &CacheEntry, u64)>],
+) -> impl Iterator<Item = u64> + 'a {
+    slots.iter().flatten().map(|(_, _, hash)| *hash)
+}
+
+/// Size in bytes of the bloom filter for a cache of `entry_count` entries
+pub fn bloom_len(entry_count: usize) -> usize {
+    (entry_count * BLOOM_BITS_PER_ENTRY)
+        .next_power_of_two()
+        .max(64)
+        / 8
+}
+
+/// Bit positions for `hash` in a bloom filter of `bits` bits (a power of two)
+fn bloom_positions(hash: u64, bits: usize) -> impl Iterator<Item = usize> {
+    // Double hashing; the second hash is a splitmix64 finalization of the first
+    let mut h2 = hash;
+    h2 = (h2 ^ (h2 >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h2 = (h2 ^ (h2 >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    h2 = (h2 ^ (h2 >> 31)) | 1;
+    (0..BLOOM_HASHES).map(move |i| hash.wrapping_add(i.wrapping_mul(h2)) as usize & (bits - 1))
+}
+
+/// Pad `out` to 8 bytes and append a bloom filter over `hashes`
+fn append_bloom(out: &mut Vec<u8>, hashes: impl Iterator<Item = u64>, entry_count: usize) {
+    let len = bloom_len(entry_count);
+    let mut bloom = vec![0u8; len];
+    for hash in hashes {
+        for bit in bloom_positions(hash, len * 8) {
+            bloom[bit / 8] |= 1 << (bit % 8);
+        }
+    }
+    out.resize(pad8(out.len()), 0);
+    out.extend_from_slice(&bloom);
+}
+
+/// Record new timestamps for the scanned root in a mapped cache file in place
+///
+/// Updates both the header's root mtime and the root directory's own record.
+/// The file is overwritten without being truncated or renamed, so the
+/// directory containing it keeps its mtime. A sealed file is re-sealed whole;
+/// its length does not change.
+///
+/// # Returns
+/// * `Result<bool>` - False if the file has no record for `root` or the
+///   re-encoded header no longer fits in place
+pub(crate) fn set_root_times(path: &Path, root: &Path, mtime: u64, ctime: u64) -> Result<bool> {
+    let (patches, decrypted) = {
+        let cache = MappedCache::open(path)?;
+        let Some(record) = cache.lookup(root) else {
+            return Ok(false);
+        };
+        let mut header = cache.header().clone();
+        header.root_mtime = Some(mtime);
+        let header_bytes =
+            bincode::serialize(&header).context("Failed to serialize cache header")?;
+        if header_bytes.len() != get_u64(&cache.map, 32) as usize {
+            return Ok(false);
+        }
+
+        let mut times = [0u8; 16];
+        put_u64(&mut times, 0, mtime);
+        put_u64(&mut times, 8, ctime);
+        let flags = record.u32_at(76) | FLAG_HAS_CTIME;
+        let patches = vec![
+            (PREAMBLE_LEN, header_bytes),
+            (record.base() + 16, times.to_vec()),
+            (record.base() + 76, flags.to_le_bytes().to_vec()),
+        ];
+        let decrypted = match &cache.map {
+            Backing::Decrypted(bytes) => Some(bytes.clone()),
+            Backing::Mapped(_) => None,
+        };
+        (patches, decrypted)
+    };
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(path)
+        .with_context(|| format!("Failed to open cache file: {}", path.display()))?;
+    match decrypted {
+        Some(mut plain) => {
+            for (offset, bytes) in &patches {
+                plain[*offset..*offset + bytes.len()].copy_from_slice(bytes);
+            }
+            file.write_all(&crypt::seal(&plain)?)?;
+        }
+        None => {
+            for (offset, bytes) in &patches {
+                file.seek(SeekFrom::Start(*offset as u64))?;
+                file.write_all(bytes)?;
+            }
+        }
+    }
+    Ok(true)
+}
+
+/// Approximate number of bytes one entry for `path` adds to an encoded cache
+///
+/// Accounts for the path string, one child-index slot and the record itself at
+/// the table's maximum load factor of one half.
+pub fn estimated_entry_len(path: &Path) -> u64 {
+    (2 * RECORD_LEN + 4 + path.as_os_str().len()) as u64
+}
+
+/// Bytes of an opened cache: the file's memory mapping, or the decrypted
+/// contents of a sealed file (see [`crypt`])
+enum Backing {
+    Mapped(Mmap),
+    Decrypted(Vec<u8>),
+}
+
+impl std::ops::Deref for Backing {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Backing::Mapped(map) => map,
+            Backing::Decrypted(bytes) => bytes,
+        }
+    }
+}
+
+/// A cache file opened for direct lookups from its memory mapping
+///
+/// Sealed files are decrypted into memory when opened instead.
+pub struct MappedCache {
+    map: Backing,
+    header: CacheHeader,
+    slot_count: usize,
+    entry_count: usize,
+    slots_start: usize,
+    children_start: usize,
+    children_len: usize,
+    strings_start: usize,
+    bloom_start: usize,
+    bloom_bits: usize,
+}
+
+impl std::fmt::Debug for MappedCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MappedCache")
+            .field("header", &self.header)
+            .field("entries", &self.entry_count)
+            .field("slots", &self.slot_count)
+            .finish()
+    }
+}
+
+impl MappedCache {
+    /// Memory-map and validate a mapped-format cache file
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open cache file: {}", path.display()))?;
+        let map = unsafe {
+            Mmap::map(&file)
+                .with_context(|| format!("Failed to memory-map cache file: {}", path.display()))?
+        };
+        Self::from_map(map)
+    }
+
+    pub(crate) fn from_map(map: Mmap) -> Result<Self> {
+        let map = if crypt::is_sealed(&map) {
+            Backing::Decrypted(crypt::open(&map)?)
+        } else {
+            Backing::Mapped(map)
+        };
+        let bytes: &[u8] = &map;
+        if bytes.len() < PREAMBLE_LEN || !is_mapped_format(bytes) {
+            return Err(anyhow!("Not a mapped cache file"));
+        }
+        let version = get_u32(bytes, 8);
+        if version != FORMAT_VERSION {
+            return Err(anyhow!("Unsupported mapped cache version {}", version));
+        }
+
+        let slot_count = get_u64(bytes, 16) as usize;
+        let entry_count = get_u64(bytes, 24) as usize;
+        let header_len = get_u64(bytes, 32) as usize;
+        let children_len = get_u64(bytes, 40) as usize;
+        let strings_len = get_u64(bytes, 48) as usize;
+
+        if !slot_count.is_power_of_two() || entry_count > slot_count {
+            return Err(anyhow!("Corrupt mapped cache slot table"));
+        }
+
+        // Section offsets come from the file, so a damaged preamble must not
+        // overflow them into a seemingly valid layout
+        let layout = || {
+            let slots_start = PREAMBLE_LEN.checked_add(checked_pad8(header_len)?)?;
+            let children_start = slots_start.checked_add(slot_count.checked_mul(RECORD_LEN)?)?;
+            let strings_start =
+                checked_pad8(children_start.checked_add(children_len.checked_mul(4)?)?)?;
+            let bloom_start = checked_pad8(strings_start.checked_add(strings_len)?)?;
+            // Cannot overflow: entries fit the slot table, whose size did not
+            let bloom_len = bloom_len(entry_count);
+            let end = bloom_start.checked_add(bloom_len)?;
+            Some((
+                slots_start,
+                children_start,
+                strings_start,
+                bloom_start,
+                bloom_len,
+                end,
+            ))
+        };
+        let (slots_start, children_start, strings_start, bloom_start, bloom_len, end) =
+            layout().ok_or_else(|| anyhow!("Corrupt mapped cache: section sizes overflow"))?;
+        if bytes.len() < end {
+            return Err(anyhow!("Truncated mapped cache file"));
+        }
+
+        let header: CacheHeader =
+            bincode::deserialize(&bytes[PREAMBLE_LEN..PREAMBLE_LEN + header_len])
+                .context("Failed to deserialize mapped cache header")?;
+
+        Ok(Self {
+            map,
+            header,
+            slot_count,
+            entry_count,
+            slots_start,
+            children_start,
+            children_len,
+            strings_start,
+            bloom_start,
+            bloom_bits: bloom_len * 8,
+        })
+    }
+
+    /// The cache header stored in the file
+    pub fn header(&self) -> &CacheHeader {
+        &self.header
+    }
+
+    /// Number of entries in the cache
+    pub fn len(&self) -> usize {
+        self.entry_count
+    }
+
+    /// Check if the cache holds no entries
+    pub fn is_empty(&self) -> bool {
+        self.entry_count == 0
+    }
+
+    /// Lower bound on the number of files and directories below the cached
+    /// root: the sum of the recorded inode counts, with directories scanned
+    /// without `--show-inodes` counting only themselves
+    pub fn estimated_entries(&self) -> u64 {
+        self.iter()
+            .map(|record| record.inode_cnt().unwrap_or(1))
+            .sum()
+    }
+
+    /// Find the record for `path` without allocating
+    pub fn lookup(&self, path: &Path) -> Option<CachedRecord<'_>> {
+        let wanted = path.as_os_str().as_bytes();
+        let hash = crate::utils::path_hash(path);
+        if !self.may_contain(hash) {
+            return None;
+        }
+        let mask = self.slot_count - 1;
+        let mut slot = hash as usize & mask;
+
+        for _ in 0..self.slot_count {
+            let record = CachedRecord { cache: self, slot };
+            if !record.occupied() {
+                return None;
+            }
+            if record.path_hash() == hash && record.path_bytes() == wanted {
+                return Some(record);
+            }
+            slot = (slot + 1) & mask;
+        }
+        None
+    }
+
+    /// Bloom filter test: false means no record has this path hash
+    fn may_contain(&self, hash: u64) -> bool {
+        let bloom = &self.map[self.bloom_start..self.bloom_start + self.bloom_bits / 8];
+        bloom_positions(hash, self.bloom_bits).all(|bit| bloom[bit / 8] & (1 << (bit % 8)) != 0)
+    }
+
+    /// Find and materialize the entry for `path`
+    pub fn get(&self, path: &Path) -> Option<CacheEntry> {
+        self.lookup(path).map(|record| record.to_entry())
+    }
+
+    /// Iterate over every stored record in slot order
+    pub fn iter(&self) -> impl Iterator<Item = CachedRecord<'_>> {
+        (0..self.slot_count)
+            .map(move |slot| CachedRecord { cache: self, slot })
+            .filter(|record| record.occupied())
+    }
+
+    /// Iterate over the records of a record's direct children
+    pub fn children<'a>(
+        &'a self,
+        record: &CachedRecord<'a>,
+    ) -> impl Iterator<Item = CachedRecord<'a>> + 'a {
+        let start = record.u64_at(56) as usize;
+        let count = record.u32_at(68) as usize;
+        // Both come from the file; a range outside the child index is
+        // corrupt, so the record is treated as having no children
+        let range = match start.checked_add(count) {
+            Some(end) if end <= self.children_len => start..end,
+            _ => 0..0,
+        };
+        range.filter_map(move |i| {
+            let slot = get_u32(&self.map, self.children_start + i * 4) as usize;
+            (slot < self.slot_count).then_some(CachedRecord { cache: self, slot })
+        })
+    }
+
+    /// Materialize every entry into a path-keyed map
+    pub fn to_hash_map(&self) -> HashMap<PathBuf, CacheEntry> {
+        self.iter()
+            .map(|record| (record.path(), record.to_entry()))
+            .collect()
+    }
+}
+
+/// A borrowed view of one record inside a [`MappedCache`]
+#[derive(Clone, Copy)]
+pub struct CachedRecord<'a> {
+    cache: &'a MappedCache,
+    slot: usize,
+}
+
+impl<'a> CachedRecord<'a> {
+    fn base(&self) -> usize {
+        self.cache.slots_start + self.slot * RECORD_LEN
+    }
+
+    fn u64_at(&self, offset: usize) -> u64 {
+        get_u64(&self.cache.map, self.base() + offset)
+    }
+
+    fn u32_at(&self, offset: usize) -> u32 {
+        get_u32(&self.cache.map, self.base() + offset)
+    }
+
+    fn flags(&self) -> u32 {
+        self.u32_at(76)
+    }
+
+    fn occupied(&self) -> bool {
+        self.flags() & FLAG_OCCUPIED != 0
+    }
+
+    /// Stable FNV-1a hash of the path
+    pub fn path_hash(&self) -> u64 {
+        self.u64_at(0)
+    }
+
+    /// Raw path bytes, borrowed from the mapping
+    pub fn path_bytes(&self) -> &'a [u8] {
+        let start = self.cache.strings_start + self.u64_at(48) as usize;
+        let len = self.u32_at(64) as usize;
+        self.cache.map.get(start..start + len).unwrap_or(&[])
+    }
+
+    /// The record's path as an owned `PathBuf`
+    pub fn path(&self) -> PathBuf {
+        PathBuf::from(std::ffi::OsStr::from_bytes(self.path_bytes()))
+    }
+
+    /// Size in bytes
+    pub fn size(&self) -> u64 {
+        self.u64_at(8)
+    }
+
+    /// Cached inode count, if recorded
+    pub fn inode_cnt(&self) -> Option<u64> {
+        (self.flags() & FLAG_HAS_INODE_CNT != 0).then(|| self.u64_at(40))
+    }
+
+    /// Entry type
+    pub fn entry_type(&self) -> EntryType {
+        entry_type_from_byte(self.cache.map[self.base() + 88])
+    }
+
+    /// Check validity under the given validation mode without materializing
+    pub fn is_valid_with(
+        &self,
+        mode: CacheValidation,
+        current_mtime: u64,
+        current_nlink: u64,
+        current_ctime: u64,
+    ) -> bool {
+        if self.u64_at(16) != current_mtime || self.u64_at(32) != current_nlink {
+            return false;
+        }
+        match mode {
+            CacheValidation::Fast => true,
+            CacheValidation::Strict => {
+                self.flags() & FLAG_HAS_CTIME != 0 && self.u64_at(24) == current_ctime
+            }
+        }
+    }
+
+    /// Materialize an owned [`CacheEntry`]
+    pub fn to_entry(&self) -> CacheEntry {
+        let flags = self.flags();
+        CacheEntry {
+            path_hash: self.path_hash(),
+            path: self.path(),
+            size: self.size(),
+            mtime: self.u64_at(16),
+            ctime: (flags & FLAG_HAS_CTIME != 0).then(|| self.u64_at(24)),
+            nlink: self.u64_at(32),
+            inode_cnt: self.inode_cnt(),
+            owner: (flags & FLAG_HAS_OWNER != 0).then(|| self.u32_at(72)),
+            entry_type: self.entry_type(),
+            last_hit: self.u64_at(80),
+        }
+    }
+}
+
+fn entry_type_to_byte(entry_type: EntryType) -> u8 {
+    match entry_type {
+        EntryType::File => 0,
+        EntryType::Dir => 1,
+        EntryType::Symlink => 2,
+        EntryType::Socket => 3,
+        EntryType::Fifo => 4,
+        EntryType::Device => 5,
+        EntryType::Unknown => 6,
+        EntryType::Archive => 7,
+    }
+}
+
+fn entry_type_from_byte(byte: u8) -> EntryType {
+    match byte {
+        1 => EntryType::Dir,
+        2 => EntryType::Symlink,
+        3 => EntryType::Socket,
+        4 => EntryType::Fifo,
+        5 => EntryType::Device,
+        6 => EntryType::Unknown,
+        7 => EntryType::Archive,
+        _ => EntryType::File,
+    }
+}
+
+fn pad8(n: usize) -> usize {
+    (n + 7) & !7
+}
+
+fn checked_pad8(n: usize) -> Option<usize> {
+    Some(n.checked_add(7)? & !7)
+}
+
+fn put_u64(buf: &mut [u8], offset: usize, value: u64) {
+    buf[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buf: &mut [u8], offset: usize, value: u32) {
+    buf[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn get_u64(buf: &[u8], offset: usize) -> u64 {
+    let mut raw = [0u8; 8];
+    raw.copy_from_slice(&buf[offset..offset + 8]);
+    u64::from_le_bytes(raw)
+}
+
+fn get_u32(buf: &[u8], offset: usize) -> u32 {
+    let mut raw = [0u8; 4];
+    raw.copy_from_slice(&buf[offset..offset + 4]);
+    u32::from_le_bytes(raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::model::CacheEntryParams;
+    use tempfile::tempdir;
+
+    fn dir_entry(path: &str, size: u64) -> (PathBuf, CacheEntry) {
+        let path = PathBuf::from(path);
+        let entry = CacheEntry::new(CacheEntryParams {
+            path: path.clone(),
+            size,
+            mtime: 1_700_000_000,
+            ctime: Some(1_700_000_100),
+            nlink: 2,
+            inode_cnt: Some(3),
+            owner: Some(1000),
+            entry_type: EntryType::Dir,
+        });
+        (path, entry)
+    }
+
+    fn write_and_open(entries: &HashMap<PathBuf, CacheEntry>) -> (tempfile::TempDir, MappedCache) {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("cache.bin");
+        let header = CacheHeader::new_with_mtime(PathBuf::from("/root"), Some(1));
+        std::fs::write(&file, encode(&header, entries).unwrap()).unwrap();
+        let cache = MappedCache::open(&file).unwrap();
+        (dir, cache)
+    }
+
+    #[test]
+    fn test_round_trip_lookup() {
+        let entries: HashMap<PathBuf, CacheEntry> = [
+            dir_entry("/root", 300),
+            dir_entry("/root/a", 100),
+            dir_entry("/root/b", 200),
+        ]
+        .into_iter()
+        .collect();
+
+        let (_dir, cache) = write_and_open(&entries);
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.header().root_path, PathBuf::from("/root"));
+
+        let b = cache.get(Path::new("/root/b")).unwrap();
+        assert_eq!(b.size, 200);
+        assert_eq!(b.ctime, Some(1_700_000_100));
+        assert_eq!(b.inode_cnt, Some(3));
+        assert_eq!(b.owner, Some(1000));
+        assert_eq!(b.entry_type, EntryType::Dir);
+        assert_eq!(b.path_hash, crate::utils::path_hash(Path::new("/root/b")));
+
+        assert!(cache.lookup(Path::new("/root/missing")).is_none());
+        assert_eq!(cache.to_hash_map().len(), 3);
+        // Three directories recorded with three children each
+        assert_eq!(cache.estimated_entries(), 9);
+    }
+
+    #[test]
+    fn test_children_index() {
+        let entries: HashMap<PathBuf, CacheEntry> = [
+            dir_entry("/root", 300),
+            dir_entry("/root/a", 100),
+            dir_entry("/root/b", 200),
+            dir_entry("/root/b/c", 50),
+        ]
+        .into_iter()
+        .collect();
+
+        let (_dir, cache) = write_and_open(&entries);
+        let root = cache.lookup(Path::new("/root")).unwrap();
+        let mut kids: Vec<PathBuf> = cache.children(&root).map(|r| r.path()).collect();
+        kids.sort();
+        assert_eq!(
+            kids,
+            vec![PathBuf::from("/root/a"), PathBuf::from("/root/b")]
+        );
+
+        let leaf = cache.lookup(Path::new("/root/a")).unwrap();
+        assert_eq!(cache.children(&leaf).count(), 0);
+    }
+
+    #[test]
+    fn test_children_out_of_index_are_ignored() {
+        let entries: HashMap<PathBuf, CacheEntry> =
+            [dir_entry("/root", 300), dir_entry("/root/a", 100)]
+                .into_iter()
+                .collect();
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("cache.bin");
+        let header = CacheHeader::new_with_mtime(PathBuf::from("/root"), Some(1));
+        let mut bytes = encode(&header, &entries).unwrap();
+        std::fs::write(&file, &bytes).unwrap();
+        let cache = MappedCache::open(&file).unwrap();
+        let root = cache.lookup(Path::new("/root")).unwrap();
+        let children_offset = root.base() + 56;
+        assert_eq!(cache.children(&root).count(), 1);
+
+        for offset in [u64::MAX, 1] {
+            put_u64(&mut bytes, children_offset, offset);
+            std::fs::write(&file, &bytes).unwrap();
+            let cache = MappedCache::open(&file).unwrap();
+            let root = cache.lookup(Path::new("/root")).unwrap();
+            assert_eq!(cache.children(&root).count(), 0);
+        }
+    }
+
+    #[test]
+    fn test_validity_modes() {
+        let entries: HashMap<PathBuf, CacheEntry> = [dir_entry("/root", 1)].into_iter().collect();
+        let (_dir, cache) = write_and_open(&entries);
+        let record = cache.lookup(Path::new("/root")).unwrap();
+
+        assert!(record.is_valid_with(CacheValidation::Fast, 1_700_000_000, 2, 0));
+        assert!(!record.is_valid_with(CacheValidation::Fast, 1_700_000_001, 2, 0));
+        assert!(record.is_valid_with(CacheValidation::Strict, 1_700_000_000, 2, 1_700_000_100));
+        assert!(!record.is_valid_with(CacheValidation::Strict, 1_700_000_000, 2, 0));
+    }
+
+    #[test]
+    fn test_many_entries_with_probing() {
+        let entries: HashMap<PathBuf, CacheEntry> = (0..5000)
+            .map(|i| dir_entry(&format!("/root/d{}", i), i))
+            .collect();
+        let (_dir, cache) = write_and_open(&entries);
+
+        for i in (0..5000).step_by(97) {
+            let entry = cache.get(Path::new(&format!("/root/d{}", i))).unwrap();
+            assert_eq!(entry.size, i);
+        }
+    }
+
+    #[test]
+    fn test_bloom_filter() {
+        let entries: HashMap<PathBuf, CacheEntry> = (0..1000)
+            .map(|i| dir_entry(&format!("/root/d{}", i), i))
+            .collect();
+        let (_dir, cache) = write_and_open(&entries);
+
+        // No false negatives
+        for path in entries.keys() {
+            assert!(cache.may_contain(crate::utils::path_hash(path)));
+        }
+
+        // At 10 bits per entry the false positive rate is around 1%
+        let false_positives = (0..10_000)
+            .filter(|i| cache.may_contain(crate::utils::path_hash(Path::new(&format!("/x/{}", i)))))
+            .count();
+        assert!(false_positives < 500, "{} false positives", false_positives);
+    }
+
+    #[test]
+    fn test_upgrade_v2_appends_bloom() {
+        let header = CacheHeader::new_with_mtime(PathBuf::from("/root"), Some(1));
+        let entries: HashMap<PathBuf, CacheEntry> = [
+            dir_entry("/root", 3),
+            dir_entry("/root/a", 1),
+            dir_entry("/root/b", 2),
+        ]
+        .into_iter()
+        .collect();
+        let current = encode(&header, &entries).unwrap();
+
+        let mut v2 = current[..current.len() - bloom_len(entries.len())].to_vec();
+        put_u32(&mut v2, 8, 2);
+        assert_eq!(upgrade_v2(&v2).unwrap(), current);
+        assert!(upgrade_v2(&current).is_err());
+    }
+
+    #[test]
+    fn test_upgrade_v2_rejects_truncated_files() {
+        assert!(upgrade_v2(b"RUDUMAP1\x02\0\0\0").is_err());
+
+        let header = CacheHeader::new_with_mtime(PathBuf::from("/root"), None);
+        let entries: HashMap<PathBuf, CacheEntry> = [dir_entry("/root", 1)].into_iter().collect();
+        let current = encode(&header, &entries).unwrap();
+        let mut v2 = current[..current.len() - bloom_len(entries.len())].to_vec();
+        put_u32(&mut v2, 8, 2);
+
+        let err = upgrade_v2(&v2[..v2.len() - 8]).unwrap_err();
+        assert!(err.to_string().contains("Truncated"), "{err}");
+        for (offset, value) in [(16, 1 << 62), (32, u64::MAX), (40, u64::MAX / 2)] {
+            let mut corrupt = v2.clone();
+            put_u64(&mut corrupt, offset, value);
+            let err = upgrade_v2(&corrupt).unwrap_err();
+            assert!(err.to_string().contains("Corrupt"), "{offset}: {err}");
+        }
+        let mut corrupt = v2;
+        put_u64(&mut corrupt, 24, u64::MAX);
+        assert!(upgrade_v2(&corrupt).is_err());
+    }
+
+    #[test]
+    fn test_upgrade_v1_rejects_truncated_files() {
+        // Just the magic and version, as left by an interrupted write
+        assert!(upgrade_v1(b"RUDUMAP1\x01\0\0\0").is_err());
+
+        let mut v1 = vec![0u8; PREAMBLE_LEN];
+        v1[..8].copy_from_slice(MAGIC);
+        put_u32(&mut v1, 8, 1);
+        put_u64(&mut v1, 16, 1 << 62);
+        let err = upgrade_v1(&v1).unwrap_err();
+        assert!(err.to_string().contains("Corrupt"), "{err}");
+        put_u64(&mut v1, 16, 16);
+        put_u64(&mut v1, 32, u64::MAX);
+        assert!(upgrade_v1(&v1).is_err());
+        put_u64(&mut v1, 32, 8);
+        let err = upgrade_v1(&v1).unwrap_err();
+        assert!(err.to_string().contains("Truncated"), "{err}");
+    }
+
+    #[test]
+    fn test_rejects_other_formats() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("cache.bin");
+        std::fs::write(
+            &file,
+            b"definitely not a cache file at all......................",
+        )
+        .unwrap();
+        assert!(MappedCache::open(&file).is_err());
+
+        // Truncated after the preamble
+        let header = CacheHeader::new_with_mtime(PathBuf::from("/root"), None);
+        let entries: HashMap<PathBuf, CacheEntry> = [dir_entry("/root", 1)].into_iter().collect();
+        let bytes = encode(&header, &entries).unwrap();
+        std::fs::write(&file, &bytes[..bytes.len() / 2]).unwrap();
+        assert!(MappedCache::open(&file).is_err());
+    }
+
+    #[test]
+    fn test_rejects_overflowing_section_sizes() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("cache.bin");
+        let header = CacheHeader::new_with_mtime(PathBuf::from("/root"), None);
+        let entries: HashMap<PathBuf, CacheEntry> = [dir_entry("/root", 1)].into_iter().collect();
+        let bytes = encode(&header, &entries).unwrap();
+
+        // A power-of-two slot count whose table size wraps around
+        let mut oversized = bytes.clone();
+        put_u64(&mut oversized, 16, 1 << 62);
+        std::fs::write(&file, &oversized).unwrap();
+        let err = MappedCache::open(&file).unwrap_err();
+        assert!(err.to_string().contains("Corrupt"), "{err}");
+
+        let mut oversized = bytes;
+        put_u64(&mut oversized, 32, u64::MAX);
+        std::fs::write(&file, &oversized).unwrap();
+        assert!(MappedCache::open(&file).is_err());
+    }
+}
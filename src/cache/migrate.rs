@@ -0,0 +1,282 @@
+//! Cache schema versioning and migration
+//!
+//! A cache file records the schema it was written with: mapped-format files
+//! carry their layout version in the preamble (see [`mapped`](super::mapped)),
+//! as do encrypted files (see [`crypt`](super::crypt)), and files without the mapped magic are the bincode caches written by
+//! rudu 1.4.x and earlier, treated as schema 0.
+//!
+//! Caches used to be discarded whenever the rudu version string changed.
+//! Validity is now tied to [`SCHEMA_VERSION`] instead: files written with an
+//! older schema are upgraded step by step, and only files with an unknown
+//! (newer) schema are discarded. Bump the schema, and add a step to
+//! [`migrate`], whenever the meaning or layout of cached data changes.
+
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use super::model::{CacheEntry, CacheEntryParams, CacheHeader};
+use super::{crypt, mapped};
+use crate::data::EntryType;
+
+/// Schema version written by this build
+pub const SCHEMA_VERSION: u32 = mapped::FORMAT_VERSION;
+
+/// Entry layout of the bincode caches written by rudu 1.4.x (schema 0)
+#[derive(Deserialize)]
+struct EntryV0 {
+    #[allow(dead_code)] // recomputed from `path` on migration
+    path_hash: u64,
+    path: PathBuf,
+    size: u64,
+    mtime: u64,
+    nlink: u64,
+    inode_cnt: Option<u64>,
+    owner: Option<u32>,
+    entry_type: EntryType,
+}
+
+/// Whole-file layout of schema 0
+#[derive(Deserialize)]
+struct CacheV0 {
+    header: CacheHeader,
+    entries: HashMap<u64, EntryV0>,
+}
+
+/// The schema version of an encoded cache file
+///
+/// # Returns
+/// * `u32` - The mapped layout version, or 0 for a file without the mapped
+///   or encrypted magic
+pub fn schema_version(bytes: &[u8]) -> u32 {
+    mapped::format_version(bytes)
+        .or_else(|| crypt::format_version(bytes))
+        .unwrap_or(0)
+}
+
+/// Upgrade an encoded cache to [`SCHEMA_VERSION`]
+///
+/// # Arguments
+/// * `bytes` - The encoded cache file contents
+///
+/// # Returns
+/// * `Result<Option<Vec<u8>>>` - The upgraded file, or `None` if it is already current
+///
+/// # Errors
+/// Fails if the file has a newer schema than this build understands or
+/// cannot be decoded as its schema.
+pub fn migrate(bytes: &[u8]) -> Result<Option<Vec<u8>>> {
+    let mut version = schema_version(bytes);
+    if version == SCHEMA_VERSION {
+        return Ok(None);
+    }
+    if version > SCHEMA_VERSION {
+        return Err(anyhow!(
+            "Cache schema {} is newer than supported schema {}",
+            version,
+            SCHEMA_VERSION
+        ));
+    }
+
+    let mut current = bytes.to_vec();
+    while version < SCHEMA_VERSION {
+        current = match version {
+            0 => from_v0(&current)?,
+            1 => mapped::upgrade_v1(&current)?,
+            2 => mapped::upgrade_v2(&current)?,
+            other => return Err(anyhow!("No migration from cache schema {}", other)),
+        };
+        version = schema_version(&current);
+    }
+    Ok(Some(current))
+}
+
+/// Upgrade a cache file on disk in place if it uses an older schema
+///
+/// # Arguments
+/// * `path` - The cache file to check
+///
+/// # Returns
+/// * `Result<bool>` - True if the file was rewritten
+pub fn migrate_file(path: &Path) -> Result<bool> {
+    // Current files are recognised from their preamble without reading them
+    let mut preamble = [0u8; 16];
+    let read = File::open(path)
+        .and_then(|mut file| file.read(&mut preamble))
+        .with_context(|| format!("Failed to read cache file: {}", path.display()))?;
+    if schema_version(&preamble[..read]) == SCHEMA_VERSION {
+        return Ok(false);
+    }
+
+    let mut bytes = std::fs::read(path)
+        .with_context(|| format!("Failed to read cache file: {}", path.display()))?;
+    if crypt::is_sealed(&bytes) {
+        bytes = crypt::open(&bytes)
+            .with_context(|| format!("Failed to decrypt cache file: {}", path.display()))?;
+    }
+    match migrate(&bytes)
+        .with_context(|| format!("Failed to migrate cache file: {}", path.display()))?
+    {
+        Some(upgraded) => {
+            super::save_cache_to_file(path, &upgraded)?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Schema 0 → current: decode the 1.4.x bincode cache and re-encode it
+///
+/// Entries are stamped with the cache's creation time as `last_hit`; `ctime`
+/// was not recorded, so strict validation rescans them once.
+fn from_v0(bytes: &[u8]) -> Result<Vec<u8>> {
+    let cache: CacheV0 = bincode::deserialize(bytes).context("Failed to decode schema 0 cache")?;
+    let entries: HashMap<PathBuf, CacheEntry> = cache
+        .entries
+        .into_values()
+        .map(|old| {
+            let mut entry = CacheEntry::new(CacheEntryParams {
+                path: old.path.clone(),
+                size: old.size,
+                mtime: old.mtime,
+                ctime: None,
+                nlink: old.nlink,
+                inode_cnt: old.inode_cnt,
+                owner: old.owner,
+                entry_type: old.entry_type,
+            });
+            entry.last_hit = cache.header.creation_time;
+            (old.path, entry)
+        })
+        .collect();
+    mapped::encode(&cache.header, &entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    struct WriteEntryV0 {
+        path_hash: u64,
+        path: PathBuf,
+        size: u64,
+        mtime: u64,
+        nlink: u64,
+        inode_cnt: Option<u64>,
+        owner: Option<u32>,
+        entry_type: EntryType,
+    }
+
+    #[derive(Serialize)]
+    struct WriteCacheV0 {
+        header: CacheHeader,
+        entries: HashMap<u64, WriteEntryV0>,
+    }
+
+    fn header() -> CacheHeader {
+        let mut header = CacheHeader::new_with_mtime(PathBuf::from("/data"), Some(7));
+        header.rudu_version = "1.4.9".to_string();
+        header
+    }
+
+    #[test]
+    fn test_migrate_v0_bincode() {
+        let path = PathBuf::from("/data/sub");
+        let v0 = WriteCacheV0 {
+            header: header(),
+            entries: HashMap::from([(
+                1,
+                WriteEntryV0 {
+                    path_hash: 1,
+                    path: path.clone(),
+                    size: 4096,
+                    mtime: 100,
+                    nlink: 2,
+                    inode_cnt: Some(3),
+                    owner: Some(1000),
+                    entry_type: EntryType::Dir,
+                },
+            )]),
+        };
+        let bytes = bincode::serialize(&v0).unwrap();
+        assert_eq!(schema_version(&bytes), 0);
+
+        let upgraded = migrate(&bytes).unwrap().unwrap();
+        assert_eq!(schema_version(&upgraded), SCHEMA_VERSION);
+        assert!(migrate(&upgraded).unwrap().is_none());
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("cache.bin");
+        std::fs::write(&file, &upgraded).unwrap();
+        let cache = mapped::MappedCache::open(&file).unwrap();
+        assert_eq!(cache.header().rudu_version, "1.4.9");
+        let record = cache.lookup(&path).unwrap();
+        assert_eq!(record.size(), 4096);
+        assert_eq!(record.inode_cnt(), Some(3));
+        assert_eq!(record.to_entry().last_hit, cache.header().creation_time);
+    }
+
+    #[test]
+    fn test_migrate_v1_mapped() {
+        let entries: HashMap<PathBuf, CacheEntry> = ["/data", "/data/a", "/data/a/b"]
+            .into_iter()
+            .map(|p| {
+                let mut entry = CacheEntry::new(CacheEntryParams {
+                    path: PathBuf::from(p),
+                    size: 10,
+                    mtime: 20,
+                    ctime: Some(30),
+                    nlink: 2,
+                    inode_cnt: Some(1),
+                    owner: None,
+                    entry_type: EntryType::Dir,
+                });
+                entry.last_hit = 0;
+                (PathBuf::from(p), entry)
+            })
+            .collect();
+        let header = header();
+        let current = mapped::encode(&header, &entries).unwrap();
+
+        // Rebuild the same file in the version 1 layout: 88-byte records
+        // without `last_hit`, and no bloom filter
+        let header_len = u64::from_le_bytes(current[32..40].try_into().unwrap()) as usize;
+        let slots_start = 56 + header_len.div_ceil(8) * 8;
+        let slot_count = u64::from_le_bytes(current[16..24].try_into().unwrap()) as usize;
+        let slots_end = slots_start + slot_count * mapped::RECORD_LEN;
+        let bloom_start = current.len() - mapped::bloom_len(entries.len());
+        let mut v1 = current[..slots_start].to_vec();
+        v1[8..12].copy_from_slice(&1u32.to_le_bytes());
+        for record in current[slots_start..slots_end].chunks_exact(mapped::RECORD_LEN) {
+            v1.extend_from_slice(&record[..80]);
+            v1.push(record[88]);
+            v1.extend_from_slice(&[0u8; 7]);
+        }
+        v1.extend_from_slice(&current[slots_end..bloom_start]);
+
+        let upgraded = migrate(&v1).unwrap().unwrap();
+        let mut expected = current.clone();
+        for slot in 0..slot_count {
+            let base = slots_start + slot * mapped::RECORD_LEN;
+            if expected[base..base + mapped::RECORD_LEN]
+                .iter()
+                .any(|b| *b != 0)
+            {
+                expected[base + 80..base + 88].copy_from_slice(&header.creation_time.to_le_bytes());
+            }
+        }
+        assert_eq!(upgraded, expected);
+    }
+
+    #[test]
+    fn test_newer_schema_is_rejected() {
+        let mut bytes = mapped::encode(&header(), &HashMap::new()).unwrap();
+        bytes[8..12].copy_from_slice(&(SCHEMA_VERSION + 1).to_le_bytes());
+        assert!(migrate(&bytes).is_err());
+    }
+}
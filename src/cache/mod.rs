@@ -3,31 +3,45 @@
 //! This module provides disk-based caching functionality for rudu to improve
 //! performance on subsequent runs by storing metadata about scanned directories.
 //!
-//! The cache uses bincode for efficient serialization and stores cache files
-//! either in the scanned directory (as `.rudu-cache.bin`) or in the system
-//! cache directory as a fallback.
-
+//! Cache files are written in the fixed-record format from [`mapped`], which
+//! allows individual directory lookups straight from a memory mapping. Older
+//! bincode-encoded cache files are still read and are rewritten in the new
+//! format on the next save.
+
+pub mod crypt;
+pub mod location;
+pub mod mapped;
+pub mod migrate;
 pub mod model;
 
 #[cfg(test)]
 mod tests;
 
+use crate::error::Error;
 use anyhow::{Context, Result, anyhow};
 use memmap2::{Mmap, MmapMut};
 use once_cell::sync::Lazy;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::fs::{File, OpenOptions};
 use std::io::{Seek, SeekFrom};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 
 // Thread-safe file lock for atomic cache operations
 static FILE_LOCK: Lazy<parking_lot::Mutex<()>> = Lazy::new(|| parking_lot::Mutex::new(()));
 
+// Cache files list scanned paths, so they are only readable by their owner;
+// in-tree caches exist to be shared and only list paths inside the tree
+const CACHE_FILE_MODE: u32 = 0o600;
+const IN_TREE_CACHE_FILE_MODE: u32 = 0o644;
+
 // Global cache enabled flag - can be disabled dynamically when nearing memory limits
 static CACHE_ENABLED: AtomicBool = AtomicBool::new(true);
 
-pub use model::{CacheEntry, CacheEntryParams, CacheHeader};
+pub use location::CacheDirRule;
+pub use mapped::{CachedRecord, MappedCache};
+pub use model::{CacheEntry, CacheEntryParams, CacheHeader, CacheLocation, CacheValidation};
 
 /// Enable or disable caching dynamically
 ///
@@ -56,6 +70,9 @@ pub fn is_enabled() -> bool {
 /// 1. If `RUDU_CACHE_DIR` environment variable is set, use that
 /// 2. Otherwise, fall back to XDG cache directory logic
 ///
+/// Cache files are placed through [`location::cache_root_for`], which can
+/// override this per scanned root.
+///
 /// # Returns
 /// * `PathBuf` - The cache root directory path
 pub fn cache_root() -> PathBuf {
@@ -99,14 +116,13 @@ pub fn load_cache(root: &Path, ttl_seconds: u64) -> HashMap<PathBuf, CacheEntry>
     if !cache_path.exists() {
         return HashMap::new();
     }
+    upgrade_cache_file(&cache_path);
 
     match load_cache_from_file(&cache_path) {
         Ok(cache) => {
             // Check if cache should be invalidated
             if cache.header.should_invalidate(root, ttl_seconds) {
-                eprintln!(
-                    "🗑️  Cache invalidated (version mismatch, TTL expired, or root mtime changed)"
-                );
+                log::info!("🗑️  Cache invalidated (TTL expired, or root moved or modified)");
                 // Optionally remove the invalidated cache file
                 let _ = std::fs::remove_file(&cache_path);
                 return HashMap::new();
@@ -125,6 +141,74 @@ pub fn load_cache(root: &Path, ttl_seconds: u64) -> HashMap<PathBuf, CacheEntry>
     }
 }
 
+/// Open the cache for `root` for direct lookups without materializing it
+///
+/// Unlike [`load_cache`], entries stay in the memory mapping and are only
+/// decoded when looked up, so memory use does not grow with cache size.
+/// Returns `None` if caching is disabled, no cache exists, the file is in a
+/// legacy format, or the cache should be invalidated.
+///
+/// # Arguments
+/// * `root` - The root path to determine the cache file location
+/// * `ttl_seconds` - Time to live in seconds for cache invalidation
+/// * `location` - Where the cache for `root` is kept
+///
+/// # Returns
+/// * `Option<MappedCache>` - The opened cache, or `None` if unavailable
+pub fn open_cache(root: &Path, ttl_seconds: u64, location: CacheLocation) -> Option<MappedCache> {
+    if !is_enabled() {
+        return None;
+    }
+
+    let cache_path = model::Cache::get_cache_path_for(root, location).ok()?;
+    if !cache_path.exists() {
+        return None;
+    }
+    upgrade_cache_file(&cache_path);
+
+    let cache = {
+        let _g = FILE_LOCK.lock();
+        MappedCache::open(&cache_path).ok()?
+    };
+
+    if cache.header().should_invalidate(root, ttl_seconds) {
+        log::info!("🗑️  Cache invalidated (TTL expired, or root moved or modified)");
+        drop(cache);
+        let _ = std::fs::remove_file(&cache_path);
+        return None;
+    }
+
+    Some(cache)
+}
+
+/// Open the existing cache for `root` without validating it
+///
+/// Used to compare against sizes from a previous run, which is meaningful even
+/// when the cache is too stale to be used for skipping directories. Unlike
+/// [`open_cache`], an invalidated cache file is left in place.
+///
+/// # Arguments
+/// * `root` - The root path to determine the cache file location
+/// * `location` - Where the cache for `root` is kept
+///
+/// # Returns
+/// * `Option<MappedCache>` - The previous cache, or `None` if there is none
+pub fn open_previous_cache(root: &Path, location: CacheLocation) -> Option<MappedCache> {
+    if !is_enabled() {
+        return None;
+    }
+
+    let cache_path = model::Cache::get_cache_path_for(root, location).ok()?;
+    if !cache_path.exists() {
+        return None;
+    }
+    upgrade_cache_file(&cache_path);
+
+    let _g = FILE_LOCK.lock();
+    let cache = MappedCache::open(&cache_path).ok()?;
+    (cache.header().root_path == root).then_some(cache)
+}
+
 /// Save cache to disk using efficient serialization
 ///
 /// This function saves the cache entries to disk in a format that can be
@@ -136,7 +220,7 @@ pub fn load_cache(root: &Path, ttl_seconds: u64) -> HashMap<PathBuf, CacheEntry>
 ///
 /// # Returns
 /// * `Result<()>` - Success or error information
-pub fn save_cache(root: &Path, cache: &HashMap<PathBuf, CacheEntry>) -> Result<()> {
+pub fn save_cache(root: &Path, cache: &HashMap<PathBuf, CacheEntry>) -> crate::error::Result<()> {
     // Capture root mtime before any directory modifications
     let root_mtime = model::get_root_mtime(root);
     save_cache_with_mtime(root, cache, root_mtime)
@@ -152,19 +236,157 @@ pub fn save_cache(root: &Path, cache: &HashMap<PathBuf, CacheEntry>) -> Result<(
 ///
 /// # Returns
 /// * `Result<bool>` - True if a cache file was removed, false if none existed
-pub fn invalidate_cache(root: &Path) -> Result<bool> {
+pub fn invalidate_cache(root: &Path) -> crate::error::Result<bool> {
     let cache_path = model::Cache::get_cache_path_without_write_test(root)
-        .context("Failed to determine cache file path")?;
+        .context("Failed to determine cache file path")
+        .map_err(Error::cache)?;
 
     if cache_path.exists() {
-        std::fs::remove_file(&cache_path)
-            .with_context(|| format!("Failed to remove cache file: {}", cache_path.display()))?;
+        std::fs::remove_file(&cache_path).map_err(|source| Error::Io {
+            path: cache_path,
+            source,
+        })?;
         Ok(true)
     } else {
         Ok(false)
     }
 }
 
+/// Summary of a [`merge_cache_files`] run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeSummary {
+    /// Root path recorded in the merged cache (common ancestor of the inputs)
+    pub root_path: PathBuf,
+    /// Where the merged cache was written
+    pub output: PathBuf,
+    /// Number of entries in the merged cache
+    pub entries: usize,
+    /// Number of paths present in more than one input
+    pub conflicts: usize,
+}
+
+/// Merge several caches into one
+///
+/// Entries are unioned by path. When a path appears in more than one cache the
+/// entry with the newer mtime wins, since it reflects the more recent state of
+/// that directory. The merged header's root is the deepest common ancestor of
+/// the input roots and its creation time is the oldest input's, so merging
+/// never extends the TTL of stale data.
+///
+/// # Arguments
+/// * `caches` - The caches to merge
+///
+/// # Returns
+/// * `Option<(model::Cache, usize)>` - The merged cache and number of conflicting
+///   paths, or `None` if no caches were given
+pub fn merge_caches(
+    caches: impl IntoIterator<Item = model::Cache>,
+) -> Option<(model::Cache, usize)> {
+    let mut caches = caches.into_iter();
+    let first = caches.next()?;
+
+    let mut root = first.header.root_path.clone();
+    let mut creation_time = first.header.creation_time;
+    let mut entries: HashMap<u64, CacheEntry> = first.entries;
+    let mut conflicts = 0;
+
+    for cache in caches {
+        root = common_ancestor(&root, &cache.header.root_path);
+        creation_time = creation_time.min(cache.header.creation_time);
+        for (hash, entry) in cache.entries {
+            match entries.get(&hash) {
+                Some(existing) => {
+                    conflicts += 1;
+                    if entry.mtime > existing.mtime {
+                        entries.insert(hash, entry);
+                    }
+                }
+                None => {
+                    entries.insert(hash, entry);
+                }
+            }
+        }
+    }
+
+    // Prefer the root's own cached mtime so the merged cache is invalidated
+    // exactly when a fresh scan of that root would have been
+    let root_hash = crate::utils::path_hash(&root);
+    let root_mtime = entries
+        .get(&root_hash)
+        .map(|entry| entry.mtime)
+        .or_else(|| model::get_root_mtime(&root));
+
+    let mut header = model::CacheHeader::new_with_mtime(root, root_mtime);
+    header.creation_time = creation_time;
+
+    Some((model::Cache { header, entries }, conflicts))
+}
+
+/// Merge cache files produced by partial scans into a single cache file
+///
+/// # Arguments
+/// * `inputs` - Cache files to merge (mapped or legacy bincode format)
+/// * `output` - Where to write the result; defaults to the cache location for
+///   the merged root so the next scan of that root picks it up
+///
+/// # Returns
+/// * `Result<MergeSummary>` - Details of the merged cache, or an error if an
+///   input cannot be read or the output cannot be written
+pub fn merge_cache_files(
+    inputs: &[PathBuf],
+    output: Option<&Path>,
+) -> crate::error::Result<MergeSummary> {
+    merge_files(inputs, output).map_err(Error::cache)
+}
+
+fn merge_files(inputs: &[PathBuf], output: Option<&Path>) -> Result<MergeSummary> {
+    let caches = inputs
+        .iter()
+        .map(|input| {
+            load_cache_from_file(input)
+                .with_context(|| format!("Failed to read cache file: {}", input.display()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let (merged, conflicts) =
+        merge_caches(caches).ok_or_else(|| anyhow!("No cache files to merge"))?;
+
+    let output = match output {
+        Some(path) => path.to_path_buf(),
+        None => model::Cache::get_cache_path_without_write_test(&merged.header.root_path)
+            .context("Failed to determine cache file path")?,
+    };
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+
+    let entries: HashMap<PathBuf, CacheEntry> = merged
+        .entries
+        .into_values()
+        .map(|entry| (entry.path.clone(), entry))
+        .collect();
+    let data = mapped::encode(&merged.header, &entries).context("Failed to encode cache data")?;
+    save_cache_to_file(&output, &data)
+        .with_context(|| format!("Failed to save cache to: {}", output.display()))?;
+
+    Ok(MergeSummary {
+        root_path: merged.header.root_path,
+        output,
+        entries: entries.len(),
+        conflicts,
+    })
+}
+
+/// Deepest path that is an ancestor of (or equal to) both `a` and `b`
+fn common_ancestor(a: &Path, b: &Path) -> PathBuf {
+    a.components()
+        .zip(b.components())
+        .take_while(|(x, y)| x == y)
+        .map(|(x, _)| x)
+        .collect()
+}
+
 /// Save cache to disk using efficient serialization with a specific root mtime
 ///
 /// This function saves the cache entries to disk in a format that can be
@@ -181,13 +403,53 @@ pub fn save_cache_with_mtime(
     root: &Path,
     cache: &HashMap<PathBuf, CacheEntry>,
     root_mtime: Option<u64>,
+) -> crate::error::Result<()> {
+    save_cache_with_limit(root, cache, root_mtime, None, CacheLocation::Xdg)
+}
+
+/// Save cache to disk, evicting entries to stay within a size budget
+///
+/// When the encoded cache would exceed `max_bytes`, the least-recently-validated
+/// subtrees are dropped (see [`evict_lru`]) until it fits.
+///
+/// An in-tree cache is written through a temporary file and a rename, which
+/// bumps the root's mtime and ctime. Unless the root had already changed since
+/// it was scanned, the root's recorded times are then updated in place so the
+/// cache's own write does not invalidate it.
+///
+/// # Arguments
+/// * `root` - The root path to determine the cache file location
+/// * `cache` - The cache entries to save
+/// * `root_mtime` - The root directory's mtime to use for the cache header
+/// * `max_bytes` - Maximum size of the cache file, or `None` for no limit
+/// * `location` - Where the cache for `root` is kept
+///
+/// # Returns
+/// * `Result<()>` - Success or error information
+pub fn save_cache_with_limit(
+    root: &Path,
+    cache: &HashMap<PathBuf, CacheEntry>,
+    root_mtime: Option<u64>,
+    max_bytes: Option<u64>,
+    location: CacheLocation,
+) -> crate::error::Result<()> {
+    write_cache(root, cache, root_mtime, max_bytes, location).map_err(Error::cache)
+}
+
+fn write_cache(
+    root: &Path,
+    cache: &HashMap<PathBuf, CacheEntry>,
+    root_mtime: Option<u64>,
+    max_bytes: Option<u64>,
+    location: CacheLocation,
 ) -> Result<()> {
     // Check if caching is disabled dynamically
     if !is_enabled() {
         return Ok(()); // Silently skip cache saving when disabled
     }
 
-    let cache_path = model::Cache::get_cache_path_without_write_test(root)
+    let in_tree = location.resolve(root) == CacheLocation::InTree;
+    let cache_path = model::Cache::get_cache_path_for(root, location)
         .context("Failed to determine cache file path")?;
 
     // Ensure parent directory exists
@@ -196,22 +458,105 @@ pub fn save_cache_with_mtime(
             .with_context(|| format!("Failed to create cache directory: {}", parent.display()))?;
     }
 
-    // Create new cache structure with header using pre-captured root mtime
+    // Encode in the mapped format with a header using pre-captured root mtime
     let header = model::CacheHeader::new_with_mtime(root.to_path_buf(), root_mtime);
-    let entries: HashMap<u64, CacheEntry> = cache
+    let mut data = mapped::encode(&header, cache).context("Failed to encode cache data")?;
+
+    if let Some(max_bytes) = max_bytes
+        && data.len() as u64 > max_bytes
+    {
+        let mut bounded = cache.clone();
+        let mut evicted = 0;
+        // Slot tables grow in powers of two, so re-check after each round
+        while data.len() as u64 > max_bytes && !bounded.is_empty() {
+            let excess = data.len() as u64 - max_bytes;
+            evicted += evict_lru(&mut bounded, excess);
+            data = mapped::encode(&header, &bounded).context("Failed to encode cache data")?;
+        }
+        log::info!(
+            "🧹 Cache size limit reached, evicted {} least-recently-validated entries",
+            evicted
+        );
+    }
+
+    let root_before_save = if in_tree {
+        crate::utils::get_dir_metadata(root)
+    } else {
+        None
+    };
+    save_cache_to_file(&cache_path, &data)
+        .with_context(|| format!("Failed to save cache to: {}", cache_path.display()))?;
+
+    // Only carry the root forward if it is unchanged since it was scanned
+    if let Some(before) = root_before_save
+        && let Some(scanned) = cache.get(root)
+        && scanned.mtime == before.mtime
+        && scanned.nlink == before.nlink
+        && before.mtime == root_mtime.unwrap_or(before.mtime)
+        && let Some(after) = crate::utils::get_dir_metadata(root)
+    {
+        let _g = FILE_LOCK.lock();
+        mapped::set_root_times(&cache_path, root, after.mtime, after.ctime)?;
+    }
+    Ok(())
+}
+
+/// Evict least-recently-validated subtrees until about `bytes` have been freed
+///
+/// Directories are considered oldest `last_hit` first. Evicting a directory
+/// removes its whole cached subtree together with all of its cached ancestors:
+/// an ancestor left behind would still validate on the next run and restore
+/// its subtree from cache with the evicted part missing.
+///
+/// # Arguments
+/// * `entries` - Cache entries to evict from, modified in place
+/// * `bytes` - Approximate number of encoded bytes to free
+///
+/// # Returns
+/// * `usize` - Number of entries removed
+pub fn evict_lru(entries: &mut HashMap<PathBuf, CacheEntry>, bytes: u64) -> usize {
+    let before = entries.len();
+
+    let mut candidates: Vec<(u64, usize, PathBuf)> = entries
         .iter()
-        .map(|(path, entry)| {
-            let mut new_entry = entry.clone();
-            // Ensure path is set in the entry
-            new_entry.path = path.clone();
-            (crate::utils::path_hash(path), new_entry)
-        })
+        .map(|(path, entry)| (entry.last_hit, path.components().count(), path.clone()))
         .collect();
+    // Oldest first; among equally old entries prefer the deepest (smallest) subtree
+    candidates.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+
+    // Ordered by components, so every subtree is a contiguous range
+    let mut remaining: BTreeSet<PathBuf> = entries.keys().cloned().collect();
 
-    let full_cache = model::Cache { header, entries };
+    let mut freed = 0u64;
+    for (_, _, victim) in candidates {
+        if freed >= bytes {
+            break;
+        }
+        if !remaining.contains(&victim) {
+            continue; // Already removed with an earlier subtree or ancestor chain
+        }
+
+        let mut doomed: Vec<PathBuf> = remaining
+            .range(victim.clone()..)
+            .take_while(|path| path.starts_with(&victim))
+            .cloned()
+            .collect();
+        doomed.extend(
+            victim
+                .ancestors()
+                .skip(1)
+                .filter(|a| remaining.contains(*a))
+                .map(Path::to_path_buf),
+        );
+        for path in doomed {
+            remaining.remove(&path);
+            if entries.remove(&path).is_some() {
+                freed += mapped::estimated_entry_len(&path);
+            }
+        }
+    }
 
-    save_cache_to_file(&cache_path, &full_cache)
-        .with_context(|| format!("Failed to save cache to: {}", cache_path.display()))
+    before - entries.len()
 }
 
 /// Load cache from a specific file using memory-mapped IO
@@ -237,7 +582,20 @@ fn load_cache_from_file(path: &Path) -> Result<model::Cache> {
             .with_context(|| format!("Failed to memory-map cache file: {}", path.display()))?
     };
 
-    // Try to deserialize as new Cache format first
+    if mapped::is_mapped_format(&mmap) || crypt::is_sealed(&mmap) {
+        let cache = MappedCache::from_map(mmap)
+            .with_context(|| format!("Failed to read mapped cache: {}", path.display()))?;
+        let entries: HashMap<u64, CacheEntry> = cache
+            .iter()
+            .map(|record| (record.path_hash(), record.to_entry()))
+            .collect();
+        return Ok(model::Cache {
+            header: cache.header().clone(),
+            entries,
+        });
+    }
+
+    // Fall back to the bincode Cache format written by older versions
     match bincode::deserialize::<model::Cache>(&mmap) {
         Ok(cache) => Ok(cache),
         Err(_) => {
@@ -262,27 +620,56 @@ fn load_cache_from_file(path: &Path) -> Result<model::Cache> {
     }
 }
 
-/// Save cache to a specific file using efficient serialization with atomic writes
-fn save_cache_to_file(path: &Path, cache: &model::Cache) -> Result<()> {
+/// Upgrade a cache file written with an older schema in place
+///
+/// Failures are ignored: a file that cannot be migrated is simply not usable
+/// as a cache, which the caller finds out when opening it.
+fn upgrade_cache_file(path: &Path) {
+    if let Ok(true) = migrate::migrate_file(path) {
+        log::info!(
+            "📦 Upgraded cache to schema {}: {}",
+            migrate::SCHEMA_VERSION,
+            path.display()
+        );
+    }
+}
+
+/// Save encoded cache data to a specific file with atomic writes
+///
+/// The data is encrypted first if a cache key is configured. Except for
+/// in-tree caches, the file is only readable by its owner.
+fn save_cache_to_file(path: &Path, serialized_data: &[u8]) -> Result<()> {
+    let sealed;
+    let serialized_data = if crypt::is_enabled() {
+        sealed = crypt::seal(serialized_data)?;
+        &sealed[..]
+    } else {
+        serialized_data
+    };
+
     // Lock file access to prevent concurrent reads/writes
     let _g = FILE_LOCK.lock();
 
-    // First serialize to get the size
-    let serialized_data = bincode::serialize(cache).context("Failed to serialize cache data")?;
-
     // Create temporary file path
     let temp_path = path.with_extension("tmp");
+    let mode = match path.file_name() {
+        Some(name) if model::is_in_tree_cache_name(name) => IN_TREE_CACHE_FILE_MODE,
+        _ => CACHE_FILE_MODE,
+    };
 
     // Try memory-mapped IO first, fall back to regular file IO if it fails
-    if try_save_with_mmap(&temp_path, &serialized_data).is_err() {
+    if try_save_with_mmap(&temp_path, serialized_data, mode).is_err() {
         // Fallback to regular file IO
-        save_with_regular_io(&temp_path, &serialized_data).with_context(|| {
+        save_with_regular_io(&temp_path, serialized_data, mode).with_context(|| {
             format!(
                 "Failed to save cache to temporary file: {}",
                 temp_path.display()
             )
         })?;
     }
+    // A leftover temporary file keeps its old mode when truncated
+    std::fs::set_permissions(&temp_path, std::fs::Permissions::from_mode(mode))
+        .with_context(|| format!("Failed to set permissions on: {}", temp_path.display()))?;
 
     // Atomically move the temporary file to the final location
     std::fs::rename(&temp_path, path).with_context(|| {
@@ -297,7 +684,7 @@ fn save_cache_to_file(path: &Path, cache: &model::Cache) -> Result<()> {
 }
 
 /// Try to save using memory-mapped IO
-fn try_save_with_mmap(path: &Path, data: &[u8]) -> Result<()> {
+fn try_save_with_mmap(path: &Path, data: &[u8], mode: u32) -> Result<()> {
     let file_size = data.len() as u64;
 
     // Create or truncate the file
@@ -306,6 +693,7 @@ fn try_save_with_mmap(path: &Path, data: &[u8]) -> Result<()> {
         .write(true)
         .create(true)
         .truncate(true)
+        .mode(mode)
         .open(path)
         .with_context(|| format!("Failed to create cache file: {}", path.display()))?;
 
@@ -346,13 +734,14 @@ fn try_save_with_mmap(path: &Path, data: &[u8]) -> Result<()> {
 }
 
 /// Fallback to regular file IO
-fn save_with_regular_io(path: &Path, data: &[u8]) -> Result<()> {
+fn save_with_regular_io(path: &Path, data: &[u8], mode: u32) -> Result<()> {
     use std::io::Write;
 
     let mut file = OpenOptions::new()
         .write(true)
         .create(true)
         .truncate(true)
+        .mode(mode)
         .open(path)
         .with_context(|| format!("Failed to create cache file: {}", path.display()))?;
 
@@ -433,6 +822,7 @@ mod cache_root_tests {
             inode_cnt: Some(1),
             owner: Some(1000),
             entry_type: crate::data::EntryType::File,
+            ctime: None,
         });
         cache.insert(PathBuf::from("test.txt"), entry);
 
@@ -491,6 +881,7 @@ mod cache_root_tests {
             inode_cnt: Some(1),
             owner: Some(1000),
             entry_type: crate::data::EntryType::File,
+            ctime: None,
         });
         test_cache.insert(PathBuf::from("test.txt"), entry);
 
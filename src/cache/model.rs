@@ -13,6 +13,59 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::data::EntryType;
 
+/// How strictly cached directory entries are checked against the filesystem.
+///
+/// # Variants
+/// * `Fast` - Compare mtime and nlink only (default)
+/// * `Strict` - Additionally compare the directory's own ctime, catching a
+///   chown, chmod or rename of the directory itself, which leave its mtime
+///   and nlink untouched. As in `Fast` mode, changes to the files inside it
+///   (including their owner or mode) do not touch the directory and are not
+///   caught
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum CacheValidation {
+    #[default]
+    Fast,
+    Strict,
+}
+
+/// File name of a cache kept inside the scanned root
+pub const IN_TREE_CACHE_FILE: &str = ".rudu-cache.bin";
+
+/// Where the cache file for a scanned root is kept.
+///
+/// # Variants
+/// * `Xdg` - In the user's cache directory (default)
+/// * `InTree` - In the scanned root as [`IN_TREE_CACHE_FILE`], so everyone
+///   scanning that directory shares it
+/// * `Auto` - In-tree if an in-tree cache already exists, otherwise XDG
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum CacheLocation {
+    #[default]
+    Xdg,
+    InTree,
+    Auto,
+}
+
+impl CacheLocation {
+    /// Resolve `Auto` for `root` to either `InTree` or `Xdg`
+    pub fn resolve(self, root: &Path) -> Self {
+        match self {
+            Self::Auto if root.join(IN_TREE_CACHE_FILE).is_file() => Self::InTree,
+            Self::Auto => Self::Xdg,
+            other => other,
+        }
+    }
+}
+
+/// Returns true if `name` is an in-tree cache file or its temporary file
+pub fn is_in_tree_cache_name(name: &std::ffi::OsStr) -> bool {
+    let in_tree = Path::new(IN_TREE_CACHE_FILE);
+    name == in_tree.as_os_str() || name == in_tree.with_extension("tmp").as_os_str()
+}
+
 /// Cache header containing metadata about the cache file
 ///
 /// This structure stores global information about the cache including
@@ -24,7 +77,8 @@ pub struct CacheHeader {
     pub root_path: PathBuf,
     /// Unix timestamp when the cache was created
     pub creation_time: u64,
-    /// Version of rudu that created this cache
+    /// Version of rudu that created this cache (informational; compatibility
+    /// is decided by the schema version, see [`migrate`](super::migrate))
     pub rudu_version: String,
     /// Root directory's modification time when cache was created
     pub root_mtime: Option<u64>,
@@ -44,6 +98,8 @@ pub struct CacheEntry {
     pub size: u64,
     /// Last modification time (Unix timestamp)
     pub mtime: u64,
+    /// Last status change time (Unix timestamp), used by strict validation
+    pub ctime: Option<u64>,
     /// Number of hard links (for directories, indicates potential new children)
     pub nlink: u64,
     /// Number of inodes (for directories)
@@ -52,6 +108,9 @@ pub struct CacheEntry {
     pub owner: Option<u32>,
     /// Type of entry (file or directory)
     pub entry_type: EntryType,
+    /// Unix timestamp of when this entry was last validated against the
+    /// filesystem; used to pick eviction victims under `--cache-max-size`
+    pub last_hit: u64,
 }
 
 /// Named parameters for constructing a [`CacheEntry`].
@@ -67,6 +126,8 @@ pub struct CacheEntryParams {
     pub size: u64,
     /// Last modification time (Unix timestamp)
     pub mtime: u64,
+    /// Last status change time (Unix timestamp), if recorded
+    pub ctime: Option<u64>,
     /// Number of hard links
     pub nlink: u64,
     /// Number of inodes (directories only)
@@ -119,7 +180,11 @@ impl CacheHeader {
         }
     }
 
-    /// Check if the cache should be invalidated based on version, TTL, and root mtime
+    /// Check if the cache should be invalidated based on TTL, root path, and root mtime
+    ///
+    /// The rudu version that wrote the cache is not compared: compatibility is
+    /// governed by the schema version, and older schemas are upgraded by
+    /// [`migrate`](super::migrate) before the header is ever read.
     ///
     /// # Arguments
     /// * `root_path` - The root path being scanned
@@ -128,17 +193,11 @@ impl CacheHeader {
     /// # Returns
     /// * `bool` - true if cache should be invalidated, false if still valid
     pub fn should_invalidate(&self, root_path: &Path, ttl_seconds: u64) -> bool {
-        let current_version = env!("CARGO_PKG_VERSION");
         let current_time = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap_or_default()
             .as_secs();
 
-        // Check version mismatch
-        if self.rudu_version != current_version {
-            return true;
-        }
-
         // Check TTL
         if current_time.saturating_sub(self.creation_time) >= ttl_seconds {
             return true;
@@ -177,10 +236,15 @@ impl CacheEntry {
             path: params.path,
             size: params.size,
             mtime: params.mtime,
+            ctime: params.ctime,
             nlink: params.nlink,
             inode_cnt: params.inode_cnt,
             owner: params.owner,
             entry_type: params.entry_type,
+            last_hit: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
         }
     }
 
@@ -188,6 +252,26 @@ impl CacheEntry {
     pub fn is_valid(&self, current_mtime: u64, current_nlink: u64) -> bool {
         self.mtime == current_mtime && self.nlink == current_nlink
     }
+
+    /// Check validity under the given validation mode
+    ///
+    /// In [`CacheValidation::Strict`] mode the recorded ctime must also match;
+    /// entries written without a ctime are treated as stale.
+    pub fn is_valid_with(
+        &self,
+        mode: CacheValidation,
+        current_mtime: u64,
+        current_nlink: u64,
+        current_ctime: u64,
+    ) -> bool {
+        if !self.is_valid(current_mtime, current_nlink) {
+            return false;
+        }
+        match mode {
+            CacheValidation::Fast => true,
+            CacheValidation::Strict => self.ctime == Some(current_ctime),
+        }
+    }
 }
 
 impl Cache {
@@ -252,13 +336,25 @@ impl Cache {
         Ok(())
     }
 
+    /// Get the cache file path for a given root directory and cache location
+    ///
+    /// In-tree caches live directly in `root`; everything else goes through
+    /// [`get_cache_path_without_write_test`](Self::get_cache_path_without_write_test).
+    pub fn get_cache_path_for(root: &Path, location: CacheLocation) -> Result<PathBuf> {
+        match location.resolve(root) {
+            CacheLocation::InTree => Ok(root.join(IN_TREE_CACHE_FILE)),
+            _ => Self::get_cache_path_without_write_test(root),
+        }
+    }
+
     /// Get the cache file path for a given root directory without performing write test
     ///
     /// This function always uses the configurable cache directory to avoid changing the
-    /// directory's mtime during cache operations.
+    /// directory's mtime during cache operations. The directory is chosen per root by
+    /// the [`location`](super::location) rules.
     pub fn get_cache_path_without_write_test(root: &Path) -> Result<PathBuf> {
         // Always use configurable cache directory to avoid mtime issues
-        let cache_dir = super::cache_root();
+        let cache_dir = super::location::cache_root_for(root);
         let rudu_cache_dir = cache_dir.join("rudu");
 
         // Create the rudu cache directory if it doesn't exist
@@ -336,6 +432,7 @@ mod tests {
             inode_cnt: Some(42),
             owner: Some(1000),
             entry_type: EntryType::File,
+            ctime: None,
         });
 
         // path_hash is derived from path — verify it matches the stable FNV hash
@@ -359,6 +456,7 @@ mod tests {
             inode_cnt: Some(42),
             owner: Some(1000),
             entry_type: EntryType::File,
+            ctime: None,
         });
 
         // Valid case
@@ -369,6 +467,32 @@ mod tests {
         assert!(!entry.is_valid(1234567890, 3)); // Different nlink
     }
 
+    #[test]
+    fn test_cache_entry_strict_validation() {
+        let mut entry = CacheEntry::new(CacheEntryParams {
+            path: PathBuf::from("/test/dir"),
+            size: 4096,
+            mtime: 1234567890,
+            ctime: Some(1234567999),
+            nlink: 2,
+            inode_cnt: None,
+            owner: Some(1000),
+            entry_type: EntryType::Dir,
+        });
+
+        // Fast mode ignores ctime entirely
+        assert!(entry.is_valid_with(CacheValidation::Fast, 1234567890, 2, 1));
+
+        // Strict mode requires a matching ctime
+        assert!(entry.is_valid_with(CacheValidation::Strict, 1234567890, 2, 1234567999));
+        assert!(!entry.is_valid_with(CacheValidation::Strict, 1234567890, 2, 1234568000));
+        assert!(!entry.is_valid_with(CacheValidation::Strict, 1234567891, 2, 1234567999));
+
+        // Entries without a recorded ctime are stale under strict validation
+        entry.ctime = None;
+        assert!(!entry.is_valid_with(CacheValidation::Strict, 1234567890, 2, 1234567999));
+    }
+
     #[test]
     fn test_cache_operations() {
         let root = PathBuf::from("/test/root");
@@ -385,6 +509,7 @@ mod tests {
             inode_cnt: Some(42),
             owner: Some(1000),
             entry_type: EntryType::File,
+            ctime: None,
         });
 
         cache.add_entry(entry.clone());
@@ -418,6 +543,7 @@ mod tests {
             inode_cnt: Some(42),
             owner: Some(1000),
             entry_type: EntryType::File,
+            ctime: None,
         });
         let entry_hash = entry.path_hash;
         cache.add_entry(entry);
@@ -464,13 +590,13 @@ mod tests {
     }
 
     #[test]
-    fn test_cache_invalidation_version_mismatch() {
+    fn test_cache_survives_version_change() {
         let root = PathBuf::from("/test/root");
         let mut header = CacheHeader::new(root.clone());
 
-        // Test version mismatch
+        // A different rudu version alone no longer invalidates the cache
         header.rudu_version = "0.0.0".to_string();
-        assert!(header.should_invalidate(&root, 604800));
+        assert!(!header.should_invalidate(&root, 604800));
     }
 
     #[test]
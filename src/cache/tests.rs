@@ -28,6 +28,7 @@ pub fn safe_lock<T>(m: &std::sync::Mutex<T>) -> std::sync::MutexGuard<'_, T> {
 /// bumps the directory mtime, causing `load_cache` to see a mismatch and return empty.
 struct TestCacheGuard {
     /// Where RUDU_CACHE_DIR points — cache files land here.
+    #[allow(dead_code)] // held only so the directory lives as long as the guard
     cache_dir: tempfile::TempDir,
     /// The "scanned root" passed to save_cache / load_cache.
     root_dir: tempfile::TempDir,
@@ -131,6 +132,7 @@ fn test_save_and_load_cache_with_entries() {
         inode_cnt: Some(42),
         owner: Some(1000),
         entry_type: EntryType::File,
+        ctime: None,
     });
 
     let entry2 = CacheEntry::new(CacheEntryParams {
@@ -141,6 +143,7 @@ fn test_save_and_load_cache_with_entries() {
         inode_cnt: Some(100),
         owner: Some(1001),
         entry_type: EntryType::Dir,
+        ctime: None,
     });
 
     cache.insert(PathBuf::from("test1.txt"), entry1.clone());
@@ -191,6 +194,7 @@ fn test_save_and_load_large_cache() {
             } else {
                 EntryType::Dir
             },
+            ctime: None,
         });
         cache.insert(path, entry);
     }
@@ -226,6 +230,7 @@ fn test_memory_mapped_io_performance() {
             inode_cnt: Some(i),
             owner: Some(1000),
             entry_type: EntryType::File,
+            ctime: None,
         });
         cache.insert(path, entry);
     }
@@ -294,6 +299,7 @@ fn test_entry_validation() {
         inode_cnt: Some(42),
         owner: Some(1000),
         entry_type: EntryType::File,
+        ctime: None,
     });
 
     cache.insert(PathBuf::from("test.txt"), entry);
@@ -338,6 +344,7 @@ fn test_cache_with_complex_paths() {
             inode_cnt: Some(i as u64),
             owner: Some(1000),
             entry_type: EntryType::File,
+            ctime: None,
         });
         cache.insert(path.clone(), entry);
     }
@@ -364,7 +371,7 @@ fn test_cache_with_unicode_paths() {
     let mut cache = HashMap::new();
 
     // Test with Unicode path names
-    let unicode_paths = vec![
+    let unicode_paths = [
         PathBuf::from("файл.txt"),     // Russian
         PathBuf::from("文件.txt"),     // Chinese
         PathBuf::from("ファイル.txt"), // Japanese
@@ -383,6 +390,7 @@ fn test_cache_with_unicode_paths() {
             inode_cnt: Some(i as u64),
             owner: Some(1000),
             entry_type: EntryType::File,
+            ctime: None,
         });
         cache.insert(path.clone(), entry);
     }
@@ -417,6 +425,7 @@ fn test_cache_with_zero_size_files() {
         inode_cnt: Some(0),
         owner: Some(1000),
         entry_type: EntryType::File,
+        ctime: None,
     });
 
     cache.insert(PathBuf::from("empty.txt"), entry);
@@ -450,6 +459,7 @@ fn test_cache_concurrent_access() {
             inode_cnt: Some(i),
             owner: Some(1000),
             entry_type: EntryType::File,
+            ctime: None,
         });
         cache.insert(path, entry);
     }
@@ -494,6 +504,7 @@ fn test_cache_edge_cases() {
         inode_cnt: Some(42),
         owner: Some(1000),
         entry_type: EntryType::File,
+        ctime: None,
     });
     cache.insert(PathBuf::from(""), entry);
 
@@ -512,6 +523,7 @@ fn test_cache_edge_cases() {
         inode_cnt: Some(100),
         owner: Some(1001),
         entry_type: EntryType::File,
+        ctime: None,
     });
     cache.insert(long_path.clone(), entry);
 
@@ -539,6 +551,7 @@ fn test_cache_invalidation_integration() {
         inode_cnt: Some(42),
         owner: Some(1000),
         entry_type: EntryType::File,
+        ctime: None,
     });
     cache.insert(PathBuf::from("test.txt"), entry);
 
@@ -587,3 +600,251 @@ fn test_mutex_poison_recovery() {
     let normal_guard = safe_lock(&*test_mutex);
     assert_eq!(*normal_guard, 100);
 }
+
+fn dir_entry(path: PathBuf, size: u64, mtime: u64) -> CacheEntry {
+    CacheEntry::new(CacheEntryParams {
+        path,
+        size,
+        mtime,
+        ctime: None,
+        nlink: 2,
+        inode_cnt: None,
+        owner: None,
+        entry_type: EntryType::Dir,
+    })
+}
+
+#[test]
+fn test_merge_cache_files_from_sibling_scans() {
+    let _lock = safe_lock(&CACHE_TEST_LOCK);
+    let temp_dir = setup_temp_cache_dir().unwrap();
+    let root = temp_dir.path().to_path_buf();
+    let a = root.join("a");
+    let b = root.join("b");
+    std::fs::create_dir(&a).unwrap();
+    std::fs::create_dir(&b).unwrap();
+
+    let cache_a: HashMap<PathBuf, CacheEntry> = [(a.clone(), dir_entry(a.clone(), 10, 1))].into();
+    let cache_b: HashMap<PathBuf, CacheEntry> = [
+        (b.clone(), dir_entry(b.clone(), 20, 1)),
+        (b.join("c"), dir_entry(b.join("c"), 5, 1)),
+    ]
+    .into();
+    save_cache(&a, &cache_a).unwrap();
+    save_cache(&b, &cache_b).unwrap();
+
+    let inputs = vec![
+        model::Cache::get_cache_path_without_write_test(&a).unwrap(),
+        model::Cache::get_cache_path_without_write_test(&b).unwrap(),
+    ];
+    let summary = merge_cache_files(&inputs, None).unwrap();
+
+    assert_eq!(summary.root_path, root);
+    assert_eq!(summary.entries, 3);
+    assert_eq!(summary.conflicts, 0);
+    assert_eq!(
+        summary.output,
+        model::Cache::get_cache_path_without_write_test(&root).unwrap()
+    );
+
+    // The merged cache is picked up by a scan of the common root
+    let loaded = load_cache(&root, 604800);
+    assert_eq!(loaded.len(), 3);
+    assert_eq!(loaded.get(&b.join("c")).unwrap().size, 5);
+}
+
+#[test]
+fn test_merge_caches_prefers_newer_mtime() {
+    let root = PathBuf::from("/nonexistent/rudu-merge");
+    let shared = root.join("shared");
+
+    let older = model::Cache {
+        header: CacheHeader::new_with_mtime(root.join("x"), None),
+        entries: [(
+            crate::utils::path_hash(&shared),
+            dir_entry(shared.clone(), 100, 1_000),
+        )]
+        .into(),
+    };
+    let mut newer = model::Cache {
+        header: CacheHeader::new_with_mtime(root.join("y"), None),
+        entries: [(
+            crate::utils::path_hash(&shared),
+            dir_entry(shared.clone(), 200, 2_000),
+        )]
+        .into(),
+    };
+    newer.header.creation_time = 5;
+
+    let (merged, conflicts) = merge_caches([older, newer]).unwrap();
+    assert_eq!(conflicts, 1);
+    assert_eq!(merged.header.root_path, root);
+    assert_eq!(merged.header.creation_time, 5);
+    assert_eq!(merged.entries[&crate::utils::path_hash(&shared)].size, 200);
+
+    assert!(merge_caches(Vec::new()).is_none());
+}
+
+#[test]
+fn test_evict_lru_removes_oldest_subtree_and_ancestors() {
+    let root = PathBuf::from("/r");
+    let mut entries: HashMap<PathBuf, CacheEntry> = HashMap::new();
+    for (path, last_hit) in [
+        ("/r", 300),
+        ("/r/old", 100),
+        ("/r/old/deep", 250),
+        ("/r/new", 300),
+        ("/r/new/child", 300),
+    ] {
+        let mut entry = dir_entry(PathBuf::from(path), 1, 1);
+        entry.last_hit = last_hit;
+        entries.insert(PathBuf::from(path), entry);
+    }
+
+    let evicted = evict_lru(&mut entries, 1);
+
+    // /r/old is the oldest: it goes with its subtree and its ancestor /r
+    assert_eq!(evicted, 3);
+    assert!(!entries.contains_key(&root));
+    assert!(!entries.contains_key(&root.join("old")));
+    assert!(!entries.contains_key(&root.join("old/deep")));
+    assert!(entries.contains_key(&root.join("new")));
+    assert!(entries.contains_key(&root.join("new/child")));
+
+    // Nothing to free means nothing is evicted
+    assert_eq!(evict_lru(&mut entries, 0), 0);
+}
+
+#[test]
+fn test_save_cache_with_limit_stays_within_budget() {
+    let _lock = safe_lock(&CACHE_TEST_LOCK);
+    let temp_dir = setup_temp_cache_dir().unwrap();
+    let root = temp_dir.path().to_path_buf();
+
+    let cache: HashMap<PathBuf, CacheEntry> = (0..2000)
+        .map(|i| {
+            let path = root.join(format!("dir{}", i));
+            let mut entry = dir_entry(path.clone(), i, 1);
+            entry.last_hit = i;
+            (path, entry)
+        })
+        .collect();
+
+    let max_bytes = 64 * 1024;
+    save_cache_with_limit(
+        &root,
+        &cache,
+        model::get_root_mtime(&root),
+        Some(max_bytes),
+        CacheLocation::Xdg,
+    )
+    .unwrap();
+
+    let cache_path = model::Cache::get_cache_path_without_write_test(&root).unwrap();
+    assert!(std::fs::metadata(&cache_path).unwrap().len() <= max_bytes);
+
+    // The most recently validated entries survive
+    let loaded = load_cache(&root, 604800);
+    assert!(!loaded.is_empty() && loaded.len() < cache.len());
+    assert!(loaded.contains_key(&root.join("dir1999")));
+    assert!(!loaded.contains_key(&root.join("dir0")));
+}
+
+#[test]
+fn test_cache_dir_rules_choose_longest_prefix() {
+    let _lock = safe_lock(&CACHE_TEST_LOCK);
+    let guard = setup_temp_cache_dir().unwrap();
+    let root = guard.path();
+    let scratch = tempfile::tempdir().unwrap();
+    let project = scratch.path().join("project");
+
+    let rules_file = scratch.path().join("cache-dirs");
+    std::fs::write(
+        &rules_file,
+        format!(
+            "# network storage\n{}={}\n\n{}={}  # nested\n",
+            root.display(),
+            scratch.path().join("outer").display(),
+            root.join("sub").display(),
+            project.display(),
+        ),
+    )
+    .unwrap();
+    let rules = location::load_rules_file(&rules_file).unwrap();
+    assert_eq!(rules.len(), 2);
+    location::set_rules(rules);
+
+    assert_eq!(
+        location::cache_root_for(&root.join("sub").join("deeper")),
+        project
+    );
+    assert_eq!(
+        location::cache_root_for(&root.join("subway")),
+        scratch.path().join("outer")
+    );
+    assert_eq!(
+        model::Cache::get_cache_path_without_write_test(&root.join("sub"))
+            .unwrap()
+            .parent()
+            .unwrap(),
+        project.join("rudu")
+    );
+
+    // Unmatched roots keep the default location
+    assert_eq!(
+        location::cache_root_for(Path::new("/elsewhere")),
+        cache_root()
+    );
+
+    location::set_rules(Vec::new());
+}
+
+#[test]
+fn test_cache_dir_rule_parse_errors() {
+    assert!("no-separator".parse::<CacheDirRule>().is_err());
+    assert!("=/scratch".parse::<CacheDirRule>().is_err());
+    assert!("/data=".parse::<CacheDirRule>().is_err());
+
+    let dir = tempfile::tempdir().unwrap();
+    let rules_file = dir.path().join("cache-dirs");
+    std::fs::write(&rules_file, "/ok=/scratch\nbroken\n").unwrap();
+    let err = location::load_rules_file(&rules_file).unwrap_err();
+    assert!(err.to_string().contains(":2:"));
+}
+
+#[test]
+fn test_encrypted_cache_round_trip() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let _lock = safe_lock(&CACHE_TEST_LOCK);
+    let temp_dir = setup_temp_cache_dir().unwrap();
+    let root = temp_dir.path().to_path_buf();
+    let secret = root.join("secret-project");
+    let cache: HashMap<PathBuf, CacheEntry> = [
+        (root.clone(), dir_entry(root.clone(), 30, 3)),
+        (secret.clone(), dir_entry(secret.clone(), 20, 2)),
+    ]
+    .into_iter()
+    .collect();
+
+    crypt::set_key(Some(b"correct horse"));
+    save_cache(&root, &cache).unwrap();
+    let cache_path = model::Cache::get_cache_path_without_write_test(&root).unwrap();
+    let bytes = std::fs::read(&cache_path).unwrap();
+    let mode = std::fs::metadata(&cache_path).unwrap().permissions().mode();
+
+    assert!(crypt::is_sealed(&bytes));
+    assert!(!bytes.windows(14).any(|w| w == b"secret-project"));
+    assert_eq!(mode & 0o777, 0o600);
+    assert_eq!(load_cache(&root, 604800).len(), 2);
+    let root_mtime = model::get_root_mtime(&root).unwrap();
+    assert!(mapped::set_root_times(&cache_path, &root, root_mtime, 6).unwrap());
+    let reopened = open_cache(&root, 604800, CacheLocation::Xdg);
+    assert_eq!(reopened.unwrap().get(&root).unwrap().ctime, Some(6));
+
+    // Without the right key a sealed cache is unusable, not an error
+    crypt::set_key(Some(b"wrong"));
+    assert!(open_cache(&root, 604800, CacheLocation::Xdg).is_none());
+    crypt::set_key(None);
+    assert!(load_cache(&root, 604800).is_empty());
+}
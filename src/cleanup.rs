@@ -0,0 +1,292 @@
+//! Deleting entries selected from scan results (`--delete-selected`).
+//!
+//! The entries a run lists (after `--top`, `--include`, size and age
+//! filters) can be deleted in the same run, so finding what to clean up and
+//! cleaning it up happen in one tool. [`plan`] reduces the listing to the
+//! innermost selected paths, [`delete`] moves each to the trash or removes
+//! it, and [`apply_deletions`] takes the deleted entries out of the results
+//! and subtracts their sizes from their parents.
+//!
+//! The trash follows the freedesktop.org specification, so deleted entries
+//! can be restored from desktop file managers or with `gio trash --restore`.
+//! Only the home trash (`$XDG_DATA_HOME/Trash`) is used; entries on another
+//! filesystem cannot be moved there and have to be removed instead.
+
+use crate::data::{EntryType, FileEntry};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// How `--delete-selected` disposes of entries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum DeleteMode {
+    /// Move to the freedesktop.org trash, so entries can be restored
+    Trash,
+    /// Remove permanently
+    Remove,
+}
+
+/// The entries of a listing to delete: those strictly below `root` with no
+/// other listed entry below them, in their original order.
+///
+/// Directories that pass a size filter or rank in `--top` often do so only
+/// because of a large entry inside them; deleting the innermost entries
+/// removes what was selected without taking its neighbours along. With
+/// `files_only`, for selections made by file name or age, directories are
+/// never deleted, as they are only listed for the totals of the files
+/// below them.
+///
+/// Members of archives listed by `--scan-archives` only exist inside their
+/// archive, so they are skipped; the archive itself stays as well, as it
+/// is not the innermost entry.
+pub fn plan<'a>(entries: &'a [FileEntry], root: &Path, files_only: bool) -> Vec<&'a FileEntry> {
+    let mut listed: Vec<&Path> = entries
+        .iter()
+        .filter(|e| e.path != root && e.path.starts_with(root))
+        .map(|e| e.path.as_path())
+        .collect();
+    // Descendants sort right after their ancestors
+    listed.sort();
+    let innermost: Vec<&Path> = listed
+        .iter()
+        .enumerate()
+        .filter(|(i, path)| listed.get(i + 1).is_none_or(|next| !next.starts_with(path)))
+        .map(|(_, path)| *path)
+        .collect();
+    let archives: HashSet<&Path> = entries
+        .iter()
+        .filter(|e| e.entry_type == EntryType::Archive)
+        .map(|e| e.path.as_path())
+        .collect();
+    entries
+        .iter()
+        .filter(|e| innermost.binary_search(&e.path.as_path()).is_ok())
+        .filter(|e| !files_only || !e.entry_type.is_dir())
+        .filter(|e| !e.path.ancestors().skip(1).any(|a| archives.contains(a)))
+        .collect()
+}
+
+/// Deletes `path` with `mode`.
+///
+/// Returns where a trashed entry was moved to, or `None` when removed.
+///
+/// # Errors
+/// The error from moving or removing the entry; moving to the trash fails
+/// for entries on a different filesystem than the home directory.
+pub fn delete(path: &Path, mode: DeleteMode) -> io::Result<Option<PathBuf>> {
+    match mode {
+        DeleteMode::Trash => move_to_trash(path, &home_trash()?).map(Some),
+        DeleteMode::Remove => {
+            if fs::symlink_metadata(path)?.is_dir() {
+                fs::remove_dir_all(path)?;
+            } else {
+                fs::remove_file(path)?;
+            }
+            Ok(None)
+        }
+    }
+}
+
+/// Removes the entries at `deleted` and everything below them from
+/// `entries`, and subtracts their sizes from the remaining entries above
+/// them, so totals reflect the deletion.
+pub fn apply_deletions(entries: &mut Vec<FileEntry>, deleted: &[(PathBuf, u64)]) {
+    entries.retain(|e| !deleted.iter().any(|(path, _)| e.path.starts_with(path)));
+    for entry in entries.iter_mut() {
+        let freed: u64 = deleted
+            .iter()
+            .filter(|(path, _)| path.starts_with(&entry.path))
+            .map(|(_, size)| size)
+            .sum();
+        entry.size = entry.size.saturating_sub(freed);
+    }
+}
+
+/// The home trash: `$XDG_DATA_HOME/Trash`, or `~/.local/share/Trash`
+fn home_trash() -> io::Result<PathBuf> {
+    if let Some(data_home) = std::env::var_os("XDG_DATA_HOME").filter(|d| !d.is_empty()) {
+        return Ok(PathBuf::from(data_home).join("Trash"));
+    }
+    std::env::var_os("HOME")
+        .filter(|h| !h.is_empty())
+        .map(|home| PathBuf::from(home).join(".local/share/Trash"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "HOME is not set"))
+}
+
+/// Moves `path` into the `trash` directory with a `.trashinfo` record of
+/// where it came from, returning its new location.
+fn move_to_trash(path: &Path, trash: &Path) -> io::Result<PathBuf> {
+    let path = std::path::absolute(path)?;
+    let files = trash.join("files");
+    let info = trash.join("info");
+    fs::create_dir_all(&files)?;
+    fs::create_dir_all(&info)?;
+
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "cannot trash a root"))?
+        .to_string_lossy()
+        .into_owned();
+    let deletion_date = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S");
+    for n in 1.. {
+        let trashed_name = if n == 1 {
+            name.clone()
+        } else {
+            format!("{}.{}", name, n)
+        };
+        // Creating the info file first reserves the name, as the spec requires
+        let info_path = info.join(format!("{}.trashinfo", trashed_name));
+        let mut info_file = match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&info_path)
+        {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        };
+        io::Write::write_all(
+            &mut info_file,
+            format!(
+                "[Trash Info]\nPath={}\nDeletionDate={}\n",
+                escape_path(&path),
+                deletion_date
+            )
+            .as_bytes(),
+        )?;
+
+        let target = files.join(&trashed_name);
+        if let Err(e) = fs::rename(&path, &target) {
+            let _ = fs::remove_file(&info_path);
+            if e.raw_os_error() == Some(libc::EXDEV) {
+                return Err(io::Error::new(
+                    e.kind(),
+                    format!(
+                        "{} is on a different filesystem than the trash in {}; \
+                         use --delete-selected=remove to delete it",
+                        path.display(),
+                        trash.display()
+                    ),
+                ));
+            }
+            return Err(e);
+        }
+        return Ok(target);
+    }
+    unreachable!("the name counter never runs out")
+}
+
+/// Percent-encodes `path` for the `Path=` key of a `.trashinfo` file.
+fn escape_path(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut escaped = String::new();
+    for &byte in path.as_os_str().as_bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            escaped.push(byte as char);
+        } else {
+            escaped.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_selects_innermost_entries() {
+        let entries = [
+            FileEntry::for_test("/data/old/x.log", 100, EntryType::File),
+            FileEntry::for_test("/data", 1000, EntryType::Dir),
+            FileEntry::for_test("/data/old", 300, EntryType::Dir),
+            FileEntry::for_test("/data/old-2", 50, EntryType::Dir),
+            FileEntry::for_test("/data/old/sub", 200, EntryType::Dir),
+            FileEntry::for_test("/elsewhere", 5, EntryType::File),
+        ];
+        let planned = |files_only| -> Vec<&str> {
+            plan(&entries, Path::new("/data"), files_only)
+                .iter()
+                .map(|e| e.path.to_str().unwrap())
+                .collect()
+        };
+        assert_eq!(
+            planned(false),
+            ["/data/old/x.log", "/data/old-2", "/data/old/sub"]
+        );
+        assert_eq!(planned(true), ["/data/old/x.log"]);
+    }
+
+    #[test]
+    fn test_plan_skips_archive_members() {
+        let entries = [
+            FileEntry::for_test("/data", 1000, EntryType::Dir),
+            FileEntry::for_test("/data/backup.tar", 900, EntryType::Archive),
+            FileEntry::for_test("/data/backup.tar/big.bin", 800, EntryType::File),
+            FileEntry::for_test("/data/backup.tar/sub", 100, EntryType::Dir),
+            FileEntry::for_test("/data/plain.tar", 100, EntryType::Archive),
+        ];
+        let planned: Vec<&str> = plan(&entries, Path::new("/data"), false)
+            .iter()
+            .map(|e| e.path.to_str().unwrap())
+            .collect();
+        // An archive without listed members is a file like any other
+        assert_eq!(planned, ["/data/plain.tar"]);
+    }
+
+    #[test]
+    fn test_apply_deletions_updates_parent_totals() {
+        let mut entries = vec![
+            FileEntry::for_test("/data", 1000, EntryType::Dir),
+            FileEntry::for_test("/data/a", 600, EntryType::Dir),
+            FileEntry::for_test("/data/a/big", 500, EntryType::File),
+            FileEntry::for_test("/data/a/small", 100, EntryType::File),
+            FileEntry::for_test("/data/b", 400, EntryType::Dir),
+            FileEntry::for_test("/data/b/c", 400, EntryType::Dir),
+        ];
+        apply_deletions(
+            &mut entries,
+            &[
+                (PathBuf::from("/data/a/big"), 500),
+                (PathBuf::from("/data/b"), 400),
+            ],
+        );
+        let left: Vec<(&str, u64)> = entries
+            .iter()
+            .map(|e| (e.path.to_str().unwrap(), e.size))
+            .collect();
+        assert_eq!(
+            left,
+            [("/data", 100), ("/data/a", 100), ("/data/a/small", 100)]
+        );
+    }
+
+    #[test]
+    fn test_move_to_trash_records_origin() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let trash = temp_dir.path().join("Trash");
+        for _ in 0..2 {
+            fs::write(temp_dir.path().join("old.log"), "x").unwrap();
+            move_to_trash(&temp_dir.path().join("old.log"), &trash).unwrap();
+        }
+
+        assert!(!temp_dir.path().join("old.log").exists());
+        assert!(trash.join("files/old.log").exists());
+        let second = trash.join("files/old.log.2");
+        assert!(second.exists());
+        let info = fs::read_to_string(trash.join("info/old.log.2.trashinfo")).unwrap();
+        assert!(info.starts_with("[Trash Info]\nPath=/"));
+        assert!(info.contains("/old.log\nDeletionDate="));
+    }
+
+    #[test]
+    fn test_escape_path() {
+        assert_eq!(
+            escape_path(Path::new("/data/my file%.txt")),
+            "/data/my%20file%25.txt"
+        );
+    }
+}
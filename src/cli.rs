@@ -3,7 +3,9 @@
 //! This module defines command-line arguments using [`clap`] and exposes:
 //!
 //! - [`Args`]: the main struct parsed from CLI inputs
-//! - [`SortKey`]: an enum for sorting output by `size` or `name`
+//! - [`SortKey`]: an enum for sorting output by `size`, `name`, `mtime`, `owner` or
+//!   `inodes`, combined in `--sort size,name`
+//! - [`Command`]: optional subcommands such as `rudu cache merge`
 //!
 //! The `Args` struct is used in `main.rs` and other modules to control behavior
 //! such as filtering, depth limits, file visibility, and output formatting.
@@ -17,10 +19,22 @@
 //! # Dependencies
 //! - [`clap`] for argument parsing and help generation
 
+use crate::cache::{CacheDirRule, CacheLocation, CacheValidation};
+use crate::cleanup::DeleteMode;
+pub use crate::data::SortKey;
+use crate::output::columns::ColumnSpec;
+use crate::output::printf::PrintfFormat;
+use crate::output::template::ReportTemplate;
+use crate::output::time::TimeStyle;
+use crate::output::units::{SizeFormat, Threshold, parse_block_size, parse_size, parse_threshold};
 use crate::thread_pool::ThreadPoolStrategy;
-use clap::{Parser, ValueEnum};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum, ValueHint};
+use std::ffi::OsString;
 use std::path::PathBuf;
 
+/// Environment variable holding default arguments, parsed before the command line
+pub const OPTS_ENV: &str = "RUDU_OPTS";
+
 /// Command-line arguments for the `rudu` disk usage calculator.
 ///
 /// This struct defines all available command-line options and flags
@@ -28,47 +42,306 @@ use std::path::PathBuf;
 /// ```
 #[derive(Parser, Debug, Clone)]
 #[command(name = "rudu", author = "Sam Green", version = env!("CARGO_PKG_VERSION"), about)]
+// `-h` is du's --human-readable, so help is only available as --help. Repeated
+// options override earlier ones, so the command line can override RUDU_OPTS.
+#[command(disable_help_flag = true, args_override_self = true)]
 pub struct Args {
     /// Path to scan (defaults to current directory)
-    #[arg(default_value = ".")]
+    #[arg(default_value = ".", value_hint = ValueHint::DirPath)]
     pub path: PathBuf,
 
     /// Limit output to directories up to N levels deep
-    #[arg(long)]
+    #[arg(short = 'd', long, visible_alias = "max-depth")]
     pub depth: Option<usize>,
 
-    /// Sort output by name or size
-    #[arg(long, value_enum, default_value_t = SortKey::Name)]
-    pub sort: SortKey,
+    /// Sort output by name, size, mtime (newest first), owner or inodes (most first);
+    /// later keys break ties, e.g. size,name
+    #[arg(long, value_enum, value_name = "KEY[,KEY...]", value_delimiter = ',', default_values_t = [SortKey::Name])]
+    pub sort: Vec<SortKey>,
+
+    /// Reverse the sort order, e.g. smallest first with --sort size
+    #[arg(short = 'r', long)]
+    pub reverse: bool,
+
+    /// Break ties in the sort order by full path, so the output of an unchanged tree is
+    /// identical from run to run
+    #[arg(long)]
+    pub deterministic: bool,
 
     /// Show individual files at the target depth (default: true)
     #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
     pub show_files: bool,
 
+    /// Show individual files, like du -a; overrides --show-files false
+    #[arg(short = 'a', long)]
+    pub all: bool,
+
+    /// Only output files, e.g. for a pure file listing (implies --show-files)
+    #[arg(long, conflicts_with = "dirs_only")]
+    pub files_only: bool,
+
+    /// Only output directories, whatever --show-files says
+    #[arg(long)]
+    pub dirs_only: bool,
+
+    /// Only output entries of at least SIZE, e.g. 1G (K = 1024, KB = 1000)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub min_size: Option<u64>,
+
+    /// Only output entries of at most SIZE, e.g. 500M (K = 1024, KB = 1000)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub max_size: Option<u64>,
+
+    /// Hide entries smaller than SIZE, or with a negative SIZE larger than it, like du -t
+    #[arg(short = 't', long, value_name = "SIZE", value_parser = parse_threshold, allow_hyphen_values = true)]
+    pub threshold: Option<Threshold>,
+
     /// Exclude entries with matching names (e.g., '.git', 'node_modules')
     #[arg(long, value_name = "PATTERN", num_args = 1.., action = clap::ArgAction::Append)]
     pub exclude: Vec<String>,
 
+    /// Exclude paths matching these regular expressions (searched for in the full path)
+    #[arg(long, value_name = "REGEX", num_args = 1.., action = clap::ArgAction::Append)]
+    pub exclude_regex: Vec<String>,
+
+    /// Read exclude patterns from FILE, one per line ('#' starts a comment)
+    #[arg(short = 'X', long, value_name = "FILE", value_hint = ValueHint::FilePath, action = clap::ArgAction::Append)]
+    pub exclude_from: Vec<PathBuf>,
+
+    /// Skip filesystems mounted on directories with matching names or paths (e.g. '.snapshot');
+    /// mountpoints crossed are listed in the scan metadata either way
+    #[arg(long, value_name = "PATTERN", num_args = 1.., action = clap::ArgAction::Append)]
+    pub exclude_mounts: Vec<String>,
+
+    /// Only count files last modified more than AGE ago (e.g. 2y, 90d, 12h) or
+    /// before DATE (YYYY-MM-DD or RFC 3339)
+    #[arg(long, value_name = "AGE|DATE", value_parser = parse_time_cutoff)]
+    pub older_than: Option<u64>,
+
+    /// Only count files last modified less than AGE ago or on/after DATE
+    #[arg(long, value_name = "AGE|DATE", value_parser = parse_time_cutoff)]
+    pub newer_than: Option<u64>,
+
+    /// Only count files owned by group NAME (or a numeric group ID)
+    #[arg(long, value_name = "NAME", conflicts_with = "gid")]
+    pub group: Option<String>,
+
+    /// Only count files owned by the group with ID N
+    #[arg(long, value_name = "N")]
+    pub gid: Option<u32>,
+
+    /// Only count files matching these globs (or below matching directories),
+    /// e.g. '**/checkpoints/**'; --exclude takes precedence
+    #[arg(long, value_name = "PATTERN", num_args = 1.., action = clap::ArgAction::Append)]
+    pub include: Vec<String>,
+
+    /// Only count files whose path, or a directory's path above them, matches these
+    /// regular expressions; --exclude and --exclude-regex take precedence
+    #[arg(long, value_name = "REGEX", num_args = 1.., action = clap::ArgAction::Append)]
+    pub include_regex: Vec<String>,
+
     /// Show owner (username) of each file/directory
     #[arg(long, default_value_t = false)]
     pub show_owner: bool,
 
-    /// Write output to a CSV file instead of stdout
-    #[arg(long, value_name = "FILE")]
+    /// Write output to a file instead of stdout (CSV unless --format is given);
+    /// '-' writes that format to stdout
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
     pub output: Option<String>,
 
+    /// Field delimiter for CSV output: a single ASCII character, or 'tab'
+    /// (default: ',' or a tab with --format tsv)
+    #[arg(long, value_name = "CHAR", value_parser = parse_delimiter)]
+    pub csv_delimiter: Option<u8>,
+
+    /// When to quote CSV fields
+    #[arg(long, value_enum, default_value_t = CsvQuoting::Necessary)]
+    pub csv_quote: CsvQuoting,
+
+    /// Write a header row in CSV output (default: true)
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    pub csv_header: bool,
+
+    /// Columns to show, in order: any of type, size, owner, inodes, path, mtime (terminal and CSV)
+    #[arg(long, value_name = "COLS")]
+    pub columns: Option<ColumnSpec>,
+
+    /// Print each entry with a find-style format, e.g. '%s\t%u\t%p\n'
+    /// (%p path, %P relative path, %f name, %s bytes, %H human size, %u owner,
+    /// %i inodes, %y type, %t mtime, %% percent)
+    #[arg(long, value_name = "FORMAT", conflicts_with = "format")]
+    pub printf: Option<PrintfFormat>,
+
+    /// Render entries, totals and profile data through a Handlebars template file
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath, value_parser = parse_template, conflicts_with_all = ["format", "printf"])]
+    pub template: Option<ReportTemplate>,
+
+    /// Show sizes as a number of SIZE-byte blocks, e.g. 1K, 4KB, 1M (K = 1024, KB = 1000)
+    #[arg(short = 'B', long, value_name = "SIZE", value_parser = parse_block_size, group = "units")]
+    pub block_size: Option<u64>,
+
+    /// Show sizes in 1024-byte blocks, like --block-size 1K
+    #[arg(short = 'k', group = "units")]
+    pub kilobytes: bool,
+
+    /// Show sizes in 1 MiB blocks, like --block-size 1M
+    #[arg(short = 'm', group = "units")]
+    pub megabytes: bool,
+
+    /// Accepted for du compatibility; sizes are human-readable unless -k, -m or -B is given
+    #[arg(short = 'h', long)]
+    pub human_readable: bool,
+
+    /// Only print errors on stderr: no banner, progress or status messages
+    #[arg(short = 'q', long, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Print debug details on stderr; repeat (-vv) for trace output
+    #[arg(short = 'v', long, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Print help
+    #[arg(long, action = clap::ArgAction::Help)]
+    pub help: Option<bool>,
+
+    /// Show human-readable sizes in powers of 1000, e.g. 1.2 MB (default)
+    #[arg(long, group = "units")]
+    pub si: bool,
+
+    /// Show human-readable sizes in powers of 1024, e.g. 1.1 MiB
+    #[arg(long, group = "units")]
+    pub binary: bool,
+
+    /// End terminal and --printf records with NUL instead of a newline, for `xargs -0`
+    #[arg(short = '0', long)]
+    pub null: bool,
+
+    /// Write absolute paths, with symlinks in the root resolved, instead of paths relative to the
+    /// root (terminal, HTML) or as scanned (CSV, NDJSON, YAML, MessagePack, Parquet)
+    #[arg(long, conflicts_with = "relative_to")]
+    pub absolute_paths: bool,
+
+    /// Write paths relative to PATH instead, e.g. to match another inventory of the same
+    /// filesystem; paths outside PATH are written absolute
+    #[arg(long, value_name = "PATH", value_hint = ValueHint::DirPath)]
+    pub relative_to: Option<PathBuf>,
+
+    /// Only output the N largest directories, largest first
+    #[arg(long, value_name = "N")]
+    pub top: Option<usize>,
+
+    /// Rank files as well as directories with --top
+    #[arg(long, requires = "top")]
+    pub top_files: bool,
+
+    /// Only output the N largest files anywhere in the tree, largest first, whatever
+    /// --depth says; kept in a bounded heap during the scan, so it works with --summarize
+    #[arg(long, value_name = "N", conflicts_with_all = ["top", "report", "count_only", "tree", "include", "include_regex", "older_than", "newer_than", "group", "gid", "delete_selected"])]
+    pub largest_files: Option<usize>,
+
+    /// Output at most N entries, after sorting (and --top)
+    #[arg(long, value_name = "N")]
+    pub limit: Option<usize>,
+
+    /// Skip the first N entries of the output, after sorting (and --top)
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub offset: usize,
+
+    /// Print a usage report instead of the entry listing: by-owner (bytes, files and inodes
+    /// per owner), by-ext (bytes and files per extension and file type), age (bytes per
+    /// directory by file age; directories up to --depth, default 1), cold (directories
+    /// nothing in which was read or modified since --cold-after, as archive candidates),
+    /// project-quota (directories up to --depth against their XFS project quota), user-quota
+    /// (bytes per owner against the quotas in --user-quotas), hsm (resident and
+    /// HSM-migrated bytes per directory up to --depth, with totals), security
+    /// (world-writable directories, setuid/setgid files and unknown owners) or orphans
+    /// (bytes per user and group ID without a passwd or group entry); with the
+    /// lustre feature also lustre-pools (usage per OST pool) or lustre-files (poorly
+    /// striped large files)
+    #[arg(long, value_enum, value_name = "KIND", conflicts_with_all = ["top", "printf", "limit", "offset"])]
+    pub report: Option<ReportKind>,
+
+    /// Print the N largest sparse files (default 20), whose disk usage is at most half their
+    /// apparent size, with apparent and allocated totals of all files, instead of the listing
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "20", conflicts_with_all = ["report", "top", "printf", "limit", "offset", "largest_files", "summarize", "count_only", "tree", "delete_selected"])]
+    pub sparse_report: Option<usize>,
+
+    /// Count directories as cold in --report cold when nothing below them was accessed or
+    /// modified within AGE (e.g. 180d, 2y) or since DATE [default: 180d]
+    #[arg(long, value_name = "AGE|DATE", value_parser = parse_time_cutoff)]
+    pub cold_after: Option<u64>,
+
+    /// Quotas compared with each owner's usage in --report user-quota: the output of
+    /// repquota or lfs quota -u, or CSV lines of user,limit or user,soft,hard
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub user_quotas: Option<PathBuf>,
+
+    /// Only output the grand total of the scanned root, like du -s
+    #[arg(short = 's', long, conflicts_with_all = ["report", "top", "files_only", "dirs_only", "tree"])]
+    pub summarize: bool,
+
+    /// Show terminal output as an indented tree instead of a flat list of paths
+    #[arg(long, default_value_t = false)]
+    pub tree: bool,
+
+    /// Show a bar next to each directory in terminal output, scaled to its largest sibling
+    #[arg(long, default_value_t = false)]
+    pub bar: bool,
+
+    /// Color terminal output: auto (only on a terminal, unless NO_COLOR is set), always, or never
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    /// Output format (default: terminal, or csv with --output)
+    #[arg(long, value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// Write NDJSON records while scanning, in walk order (files as reached, each
+    /// directory after its contents) instead of sorted once the scan is done
+    #[arg(long, conflicts_with_all = ["top", "largest_files", "limit", "offset", "report", "sparse_report", "tree", "count_only", "estimate", "rpc", "include", "include_regex", "older_than", "newer_than", "group", "gid", "delete_selected", "snapshot", "scan_archives", "budgets", "alert_threshold", "alert_growth", "deleted_open", "memory_limit"])]
+    pub stream: bool,
+
     /// Limit the number of CPU threads used (default: use all available)
     #[arg(long, value_name = "N")]
     pub threads: Option<usize>,
 
+    /// Show each entry's last modification time in terminal and CSV output, like du --time
+    #[arg(long, default_value_t = false)]
+    pub time: bool,
+
+    /// Format of modification times: full-iso, long-iso (terminal default), iso,
+    /// rfc3339 (CSV default) or +FORMAT (strftime)
+    #[arg(long, value_name = "STYLE")]
+    pub time_style: Option<TimeStyle>,
+
     /// Show inode usage (i.e., number of files/subdirectories in each dir)
     #[arg(long, default_value_t = false)]
     pub show_inodes: bool,
 
+    /// Record atime, link count, uid/gid, mode, device, inode number and apparent
+    /// size of every entry, included in JSON, YAML and MessagePack output
+    #[arg(long, default_value_t = false)]
+    pub extended_metadata: bool,
+
     /// Thread pool strategy for performance optimization (hidden experimental flag)
     #[arg(long = "threads-strategy", value_enum, default_value_t = ThreadPoolStrategy::Default, hide = true)]
     pub threads_strategy: ThreadPoolStrategy,
 
+    /// Stat files in parallel batches of at least N files [default: chosen for the filesystem]
+    #[arg(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub stat_batch: Option<usize>,
+
+    /// Stat files from the client's attribute cache, filled by NFS READDIRPLUS while listing
+    /// directories, instead of asking the server again for each one; sizes may be up to
+    /// acregmax (60s) old [default: true on NFS mounts]
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub cached_attrs: Option<bool>,
+
+    /// Keep the default thread strategy, stat batching and attribute revalidation instead of
+    /// tuning them for the root's filesystem type (NFS, Lustre, ext4, ...)
+    #[arg(long)]
+    pub no_fs_tuning: bool,
+
     /// Disable caching and force a full rescan
     #[arg(long, default_value_t = false)]
     pub no_cache: bool,
@@ -77,6 +350,26 @@ pub struct Args {
     #[arg(long, default_value_t = 604800)]
     pub cache_ttl: u64,
 
+    /// Cache validation mode: fast (mtime + nlink) or strict (also the directory's own ctime)
+    #[arg(long, value_enum, default_value_t = CacheValidation::Fast)]
+    pub cache_validation: CacheValidation,
+
+    /// Maximum cache file size in megabytes; least-recently-validated subtrees are evicted beyond it
+    #[arg(long, value_name = "MB")]
+    pub cache_max_size: Option<u64>,
+
+    /// Where to keep the cache: xdg (user cache dir), in-tree (shared, inside the scanned root), or auto
+    #[arg(long, value_enum, default_value_t = CacheLocation::Xdg)]
+    pub cache_location: CacheLocation,
+
+    /// Store caches for roots under PREFIX in DIR instead of the default cache directory
+    #[arg(long, value_name = "PREFIX=DIR", action = clap::ArgAction::Append)]
+    pub cache_dir_for: Vec<CacheDirRule>,
+
+    /// Encrypt cache files with a key read from FILE (default: the RUDU_CACHE_KEY environment variable)
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub cache_key_file: Option<PathBuf>,
+
     /// Enable performance profiling and show timing summary
     #[arg(long, default_value_t = false)]
     pub profile: bool,
@@ -93,36 +386,563 @@ pub struct Args {
         hide = true
     )]
     pub memory_check_interval_ms: u64,
+
+    /// Scan directories marked with `.ruduignore` or `CACHEDIR.TAG` instead of excluding them
+    #[arg(long, default_value_t = false)]
+    pub no_exclude_markers: bool,
+
+    /// Report space held by deleted-but-open files on the scanned filesystem (Linux)
+    #[arg(long, default_value_t = false)]
+    pub deleted_open: bool,
+
+    /// Scan without asking first, even if the root is on a slow network filesystem or the
+    /// last scan found over 10 million entries
+    #[arg(short = 'y', long)]
+    pub yes: bool,
+
+    /// Delete the listed entries (narrowed by --top, --include, --older-than or size
+    /// filters), moving them to the trash or, with =remove, removing them; asks first
+    /// unless --yes is given
+    #[arg(long, value_name = "MODE", num_args = 0..=1, require_equals = true, default_missing_value = "trash", conflicts_with_all = ["report", "summarize", "rpc", "estimate"])]
+    pub delete_selected: Option<DeleteMode>,
+
+    /// With --delete-selected, only report what would be deleted
+    #[arg(long, requires = "delete_selected")]
+    pub dry_run: bool,
+
+    /// Print recursive file, directory and inode counts per directory (up to --depth)
+    /// instead of sizes, without stat'ing files
+    #[arg(long, conflicts_with_all = ["report", "estimate", "rpc", "top", "printf", "template", "summarize", "tree"])]
+    pub count_only: bool,
+
+    /// Only count entries without stat'ing files and estimate the size from the cache,
+    /// to gauge how long a full scan would take
+    #[arg(long, conflicts_with_all = ["report", "rpc", "output", "deleted_open"])]
+    pub estimate: bool,
+
+    /// Save the size of every directory as a snapshot for `rudu diff` (in
+    /// $RUDU_SNAPSHOT_DIR, default ~/.local/share/rudu/snapshots) and, with
+    /// the history feature, record totals up to --depth for `rudu report trend`
+    #[arg(long, conflicts_with_all = ["rpc", "estimate", "count_only"])]
+    pub snapshot: bool,
+
+    /// List the members of tar, tar.gz and zip files below them as a virtual subtree,
+    /// sharing each archive's size out between its files by what they store in it
+    #[arg(long, conflicts_with_all = ["rpc", "estimate", "count_only", "largest_files", "delete_selected"])]
+    pub scan_archives: bool,
+
+    /// Check directories against the budgets in FILE, lines of PATTERN SIZE [INODES] with
+    /// `-` for no limit; those over their budget are listed after the output and make
+    /// rudu exit with an error
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath, conflicts_with_all = ["rpc", "estimate", "count_only"])]
+    pub budgets: Option<PathBuf>,
+
+    /// Alert when a listed directory holds more than SIZE, by --alert-url and/or
+    /// --alert-email (alerts feature; use --depth to choose the directories watched)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub alert_threshold: Option<u64>,
+
+    /// Alert when a listed directory grew by more than PERCENT since the last snapshot
+    #[arg(long, value_name = "PERCENT")]
+    pub alert_growth: Option<f64>,
+
+    /// Send alerts to this webhook as a JSON POST
+    #[arg(long, value_name = "URL")]
+    pub alert_url: Option<String>,
+
+    /// Email alerts to these addresses (repeatable or comma-separated)
+    #[arg(long, value_name = "ADDR", value_delimiter = ',')]
+    pub alert_email: Vec<String>,
+
+    /// SMTP relay for --alert-email, as HOST or HOST:PORT (port 25 by default); mail is
+    /// sent without TLS or authentication
+    #[arg(long, value_name = "HOST[:PORT]", default_value = "localhost")]
+    pub smtp_server: String,
+
+    /// Sender address of alert emails [default: rudu@HOSTNAME]
+    #[arg(long, value_name = "ADDR")]
+    pub alert_from: Option<String>,
+
+    /// Serve JSON-RPC requests (scan, query, status, cancel) on stdin/stdout
+    #[arg(long, default_value_t = false)]
+    pub rpc: bool,
+
+    /// Optional subcommand; when absent, `path` is scanned
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Subcommands that run instead of a scan.
+///
+/// A directory whose name collides with a subcommand (e.g. `cache`) can still
+/// be scanned by passing it as `./cache`.
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Inspect and maintain scan caches
+    Cache {
+        #[command(subcommand)]
+        action: CacheCommand,
+    },
+    /// Scan directories on a schedule, writing a timestamped output per scan
+    /// (for systemd services instead of cron scripts)
+    Daemon {
+        /// Directories to scan each round
+        #[arg(value_hint = ValueHint::DirPath)]
+        paths: Vec<PathBuf>,
+
+        /// File listing more directories to scan, one per line ('#' starts a comment)
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        paths_from: Option<PathBuf>,
+
+        /// Time from the start of one round to the start of the next, e.g. 30m, 6h or 1d
+        #[arg(long, value_name = "AGE", default_value = "6h", value_parser = parse_interval)]
+        interval: std::time::Duration,
+
+        /// Directory for the outputs, named PATH-YYYYMMDDTHHMMSSZ.EXT after
+        /// the scanned path and the scan's start time (UTC)
+        #[arg(long, value_name = "DIR", default_value = ".", value_hint = ValueHint::DirPath)]
+        output_dir: PathBuf,
+
+        /// Keep only the newest N outputs of each path, deleting older ones
+        #[arg(long, value_name = "N")]
+        keep: Option<usize>,
+
+        /// Run a single round and exit
+        #[arg(long)]
+        once: bool,
+    },
+    /// Show per-directory growth and shrinkage between two snapshots saved
+    /// with --snapshot, or between a snapshot and a fresh scan with --since
+    Diff {
+        /// The older and newer snapshot files, or with --since the directory
+        /// to scan (default: .)
+        #[arg(value_name = "SNAPSHOT|PATH", num_args = 0..=2, value_hint = ValueHint::AnyPath)]
+        inputs: Vec<PathBuf>,
+
+        /// Compare a fresh scan of PATH with its newest saved snapshot
+        /// ("last") or with a snapshot FILE
+        #[arg(long, value_name = "last|FILE")]
+        since: Option<String>,
+
+        /// Leave out directories that changed by less than SIZE, e.g. 100M
+        #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+        min_change: Option<u64>,
+
+        /// Show only the N largest changes
+        #[arg(long, value_name = "N")]
+        top: Option<usize>,
+    },
+    /// Reports from the history of past runs
+    #[cfg(feature = "history")]
+    Report {
+        #[command(subcommand)]
+        action: ReportCommand,
+    },
+    /// Scan PATH and serve the results as JSON over HTTP (/stats, /tree,
+    /// /top, /by-owner) for dashboards
+    #[cfg(feature = "serve")]
+    Serve {
+        /// Directory to scan
+        #[arg(default_value = ".", value_hint = ValueHint::DirPath)]
+        path: PathBuf,
+
+        /// Address to listen on
+        #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8080")]
+        listen: String,
+
+        /// Rescan this often, e.g. 30m or 6h, replacing the served results
+        /// when each scan finishes (default: scan once)
+        #[arg(long, value_name = "AGE", value_parser = parse_interval)]
+        interval: Option<std::time::Duration>,
+    },
+    /// Print a shell completion script, e.g. `rudu completions bash > /etc/bash_completion.d/rudu`
+    Completions {
+        /// Shell to generate the script for
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+}
+
+/// Writes the completion script for `shell` to `out`.
+///
+/// Option values such as the `--sort` keys and `--format` names are part of
+/// the script, and paths complete as files or directories.
+pub fn write_completions<W: std::io::Write>(shell: clap_complete::Shell, out: &mut W) {
+    clap_complete::generate(shell, &mut Args::command(), "rudu", out);
+}
+
+/// Actions available under `rudu cache`.
+#[derive(Subcommand, Debug, Clone)]
+pub enum CacheCommand {
+    /// Merge cache files from partial scans of sibling subtrees into one cache
+    Merge {
+        /// Cache files to merge
+        #[arg(value_name = "CACHE", num_args = 2.., required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Where to write the merged cache (default: the cache location for
+        /// the merged root, so the next scan of that root uses it)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Scan PATH only to populate its cache, printing no entries (for cron jobs)
+    Warm {
+        /// Directory whose cache should be warmed
+        path: PathBuf,
+    },
+}
+
+/// Reports available under `rudu report`.
+#[cfg(feature = "history")]
+#[derive(Subcommand, Debug, Clone)]
+pub enum ReportCommand {
+    /// Show the growth of PATH and its subdirectories over the runs saved
+    /// with --snapshot, and when a quota will be full at that rate
+    Trend {
+        /// Directory to report on (default: .)
+        #[arg(default_value = ".", value_hint = ValueHint::DirPath)]
+        path: PathBuf,
+
+        /// Project when PATH reaches SIZE, e.g. 10T
+        #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+        quota: Option<u64>,
+
+        /// Only use runs since this age or date, e.g. 90d or 2024-01-01
+        #[arg(long, value_name = "AGE|DATE", value_parser = parse_time_cutoff)]
+        since: Option<u64>,
+    },
+}
+
+/// Enum for specifying the output format.
+///
+/// # Variants
+/// * `Terminal` - Human-readable table
+/// * `Csv` - Comma-separated values with a header row
+/// * `Tsv` - CSV with tab-separated fields
+/// * `Ndjson` - One JSON object per entry per line
+/// * `Yaml` - YAML sequence with the same fields as the JSON records
+/// * `Msgpack` - MessagePack array with the same fields as the JSON records
+/// * `Ncdu` - ncdu JSON export, for browsing with `ncdu -f`
+/// * `Folded` - Folded stacks weighted by bytes, for flamegraph tools
+/// * `Html` - Self-contained HTML report with a sortable table and pie chart
+/// * `Parquet` - Apache Parquet file (only with the `parquet` feature)
+/// * `Xlsx` - Excel workbook with summary and entries sheets (only with the `xlsx` feature)
+/// * `Printf` - Records laid out by `--printf` (selected by that option only)
+/// * `Template` - Text rendered by `--template` (selected by that option only)
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum OutputFormat {
+    Terminal,
+    Csv,
+    Tsv,
+    Ndjson,
+    Yaml,
+    Msgpack,
+    Ncdu,
+    Folded,
+    Html,
+    #[cfg(feature = "parquet")]
+    Parquet,
+    #[cfg(feature = "xlsx")]
+    Xlsx,
+    #[value(skip)]
+    Printf,
+    #[value(skip)]
+    Template,
+}
+
+impl OutputFormat {
+    /// File name extension for outputs in this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Terminal | OutputFormat::Printf | OutputFormat::Template => "txt",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Tsv => "tsv",
+            OutputFormat::Ndjson => "ndjson",
+            OutputFormat::Yaml => "yaml",
+            OutputFormat::Msgpack => "msgpack",
+            OutputFormat::Ncdu => "json",
+            OutputFormat::Folded => "folded",
+            OutputFormat::Html => "html",
+            #[cfg(feature = "parquet")]
+            OutputFormat::Parquet => "parquet",
+            #[cfg(feature = "xlsx")]
+            OutputFormat::Xlsx => "xlsx",
+        }
+    }
 }
 
-/// Enum for specifying how to sort scan results.
+/// Enum for specifying the aggregate report printed by `--report`.
 ///
 /// # Variants
-/// * `Name` - Sort entries alphabetically by path name
-/// * `Size` - Sort entries by size in descending order (largest first)
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
-pub enum SortKey {
-    Name,
-    Size,
+/// * `ByOwner` - Bytes, file counts and inode counts per owner
+/// * `ByExt` - Bytes and file counts per file extension and detected type
+/// * `Age` - Bytes per directory, bucketed by the age of each file's mtime
+/// * `Cold` - Directories nothing below which was read or modified since a cutoff
+/// * `ProjectQuota` - Directory sizes against the quota of their XFS project
+/// * `UserQuota` - Bytes per owner against the user quotas of `--user-quotas`
+/// * `Hsm` - Resident and HSM-migrated bytes per directory
+/// * `Security` - World-writable directories, setuid/setgid files and entries of unknown owners
+/// * `Orphans` - Bytes, files and directories per user and group ID that resolves to no name
+/// * `LustrePools` - Bytes, files and OSTs per Lustre OST pool (`lustre` feature)
+/// * `LustreFiles` - Large Lustre files on too few stripes (`lustre` feature)
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum ReportKind {
+    ByOwner,
+    ByExt,
+    Age,
+    Cold,
+    ProjectQuota,
+    UserQuota,
+    Hsm,
+    Security,
+    Orphans,
+    #[cfg(feature = "lustre")]
+    LustrePools,
+    #[cfg(feature = "lustre")]
+    LustreFiles,
 }
 
-/// A single record of output (used for CSV serialization).
+/// Enum for specifying when CSV fields are quoted.
 ///
-/// # Fields
-/// * `entry_type` - "DIR" or "FILE"
-/// * `size_bytes` - Size in bytes
-/// * `size_human` - Human-readable size (e.g., "1.2 MB")
-/// * `owner` - Optional owner username
-/// * `path` - Full path to the file or directory
-/// * `inodes` - Optional inode count for directories
-#[derive(Debug, serde::Serialize)]
-pub struct CsvEntry {
-    pub entry_type: String,
-    pub size_bytes: u64,
-    pub size_human: String,
-    pub owner: Option<String>,
-    pub path: String,
-    pub inodes: Option<u64>,
+/// # Variants
+/// * `Necessary` - Only fields containing delimiters, quotes or newlines
+/// * `Always` - Every field
+/// * `NonNumeric` - Every field that is not a number
+/// * `Never` - No field, even if the output becomes ambiguous
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum CsvQuoting {
+    Necessary,
+    Always,
+    NonNumeric,
+    Never,
+}
+
+/// Inserts the arguments in `opts` (a shell-quoted `RUDU_OPTS` value) after
+/// the program name in `argv`, so the command line can override them.
+///
+/// Values are attached to their option (`--exclude=.snapshot`), so that an
+/// option taking several values cannot swallow the path given on the command
+/// line.
+///
+/// # Errors
+/// Returns an error if `opts` has unbalanced quotes.
+pub fn with_env_opts(
+    argv: impl IntoIterator<Item = OsString>,
+    opts: Option<&str>,
+) -> Result<Vec<OsString>, String> {
+    let mut argv = argv.into_iter();
+    let mut out: Vec<OsString> = argv.next().into_iter().collect();
+    let Some(opts) = opts else {
+        out.extend(argv);
+        return Ok(out);
+    };
+    let tokens = shlex::split(opts)
+        .ok_or_else(|| format!("{} has unbalanced quotes: {}", OPTS_ENV, opts))?;
+
+    let mut command = Args::command();
+    command.build();
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        let (arg, separator) = match token.strip_prefix("--") {
+            Some(long) if !long.contains('=') => (
+                command.get_arguments().find(|a| {
+                    a.get_long() == Some(long)
+                        || a.get_all_aliases()
+                            .is_some_and(|aliases| aliases.contains(&long))
+                }),
+                "=",
+            ),
+            Some(_) => (None, ""),
+            None => {
+                let mut chars = token.chars();
+                let short = match (chars.next(), chars.next(), chars.next()) {
+                    (Some('-'), Some(c), None) => Some(c),
+                    _ => None,
+                };
+                let arg =
+                    short.and_then(|c| command.get_arguments().find(|a| a.get_short() == Some(c)));
+                (arg, "")
+            }
+        };
+        let max_values = arg
+            .and_then(|a| a.get_num_args())
+            .filter(|n| n.takes_values())
+            .map_or(0, |n| n.max_values());
+
+        let mut attached = 0;
+        while attached < max_values
+            && let Some(value) = tokens.next_if(|t| !t.starts_with('-'))
+        {
+            out.push(format!("{}{}{}", token, separator, value).into());
+            attached += 1;
+        }
+        if attached == 0 {
+            out.push(token.into());
+        }
+    }
+    out.extend(argv);
+    Ok(out)
+}
+
+impl Args {
+    /// Parses `RUDU_OPTS` followed by the process arguments, exiting with a
+    /// usage error like [`Parser::parse`] if they are invalid.
+    pub fn parse_with_env() -> Self {
+        let opts = std::env::var(OPTS_ENV).ok();
+        match with_env_opts(std::env::args_os(), opts.as_deref()) {
+            Ok(argv) => Args::parse_from(argv),
+            Err(e) => Args::command()
+                .error(clap::error::ErrorKind::InvalidValue, e)
+                .exit(),
+        }
+    }
+}
+
+/// Parses a `--csv-delimiter` value: one ASCII character, `tab` or `\t`.
+fn parse_delimiter(s: &str) -> Result<u8, String> {
+    match s {
+        "tab" | "\\t" => Ok(b'\t'),
+        _ if s.len() == 1 && s.is_ascii() => Ok(s.as_bytes()[0]),
+        _ => Err(format!(
+            "delimiter must be a single ASCII character or 'tab', got '{}'",
+            s
+        )),
+    }
+}
+
+/// Parses a `--template` value by reading and compiling the file it names.
+fn parse_template(s: &str) -> Result<ReportTemplate, String> {
+    ReportTemplate::load(std::path::Path::new(s)).map_err(|e| format!("{:#}", e))
+}
+
+/// Parses an `--older-than`/`--newer-than` value into a cutoff in seconds
+/// since the epoch.
+fn parse_time_cutoff(s: &str) -> Result<u64, String> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    time_cutoff(s, now)
+}
+
+/// Resolves an age (`90d`, `2y`, ...) relative to `now`, or a date, to
+/// seconds since the epoch.
+///
+/// Ages are a whole number followed by `s`, `m` (minutes), `h`, `d`, `w`,
+/// `mo` (30 days) or `y` (365 days). Dates are `YYYY-MM-DD`, taken as
+/// midnight UTC, or RFC 3339 timestamps.
+pub fn time_cutoff(s: &str, now: u64) -> Result<u64, String> {
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(date
+            .and_time(chrono::NaiveTime::MIN)
+            .and_utc()
+            .timestamp()
+            .max(0) as u64);
+    }
+    if let Ok(datetime) = chrono::DateTime::parse_from_rfc3339(s) {
+        return Ok(datetime.timestamp().max(0) as u64);
+    }
+
+    match age_seconds(s) {
+        Some(age) => Ok(now.saturating_sub(age)),
+        None => Err(format!(
+            "expected an age such as 90d or 2y, or a date such as 2024-01-31, got '{}'",
+            s
+        )),
+    }
+}
+
+/// Parses a `rudu daemon --interval` value, an age such as `30m` or `6h`.
+fn parse_interval(s: &str) -> Result<std::time::Duration, String> {
+    match age_seconds(s) {
+        Some(seconds) if seconds > 0 => Ok(std::time::Duration::from_secs(seconds)),
+        _ => Err(format!(
+            "expected an interval such as 30m, 6h or 1d, got '{}'",
+            s
+        )),
+    }
+}
+
+/// Seconds in an age: a whole number followed by `s`, `m` (minutes), `h`,
+/// `d`, `w`, `mo` (30 days) or `y` (365 days).
+fn age_seconds(s: &str) -> Option<u64> {
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let seconds: u64 = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        "mo" => 30 * 24 * 60 * 60,
+        "y" => 365 * 24 * 60 * 60,
+        _ => return None,
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .map(|n| n.saturating_mul(seconds))
+}
+
+/// Enum for specifying when terminal output is colored.
+///
+/// # Variants
+/// * `Auto` - Color when writing to a terminal and `NO_COLOR` is not set
+/// * `Always` - Always color, even when redirected
+/// * `Never` - Never color
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl Args {
+    /// The unit for human-facing sizes: `--block-size`, `-k`, `-m`,
+    /// `--binary`, or SI units by default (also selected by `--si`).
+    pub fn size_format(&self) -> SizeFormat {
+        if let Some(block) = self.block_size {
+            SizeFormat::Blocks(block)
+        } else if self.kilobytes {
+            SizeFormat::Blocks(1 << 10)
+        } else if self.megabytes {
+            SizeFormat::Blocks(1 << 20)
+        } else if self.binary {
+            SizeFormat::Binary
+        } else {
+            SizeFormat::Decimal
+        }
+    }
+
+    /// The output format to render with: `--printf`, `--template` or `--format`, or CSV
+    /// when only `--output` is given, or the terminal table.
+    pub fn output_format(&self) -> OutputFormat {
+        match (self.format, &self.output) {
+            _ if self.printf.is_some() => OutputFormat::Printf,
+            _ if self.template.is_some() => OutputFormat::Template,
+            (Some(format), _) => format,
+            (None, Some(_)) => OutputFormat::Csv,
+            (None, None) => OutputFormat::Terminal,
+        }
+    }
+
+    /// Resolves `--output -` to stdout: the format it implies (CSV unless
+    /// `--format` is given) is fixed and `output` is cleared, so renderers
+    /// write to stdout as usual.
+    pub fn resolve_stdout_output(&mut self) {
+        if self.output.as_deref() == Some("-") {
+            if self.printf.is_none() && self.template.is_none() {
+                self.format = Some(self.output_format());
+            }
+            self.output = None;
+        }
+    }
+
+    /// Whether stdout carries the human-readable terminal listing, which
+    /// notes such as the `--deleted-open` report may share. Otherwise stdout
+    /// is unused or holds machine-readable output, and they go to stderr.
+    pub fn stdout_is_terminal_listing(&self) -> bool {
+        self.output.is_none() && self.output_format() == OutputFormat::Terminal && !self.null
+    }
 }
 
 #[cfg(test)]
@@ -145,24 +965,64 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_cache_merge_subcommand_parsing() {
+        let args =
+            Args::try_parse_from(["rudu", "cache", "merge", "a.bin", "b.bin", "-o", "m.bin"])
+                .unwrap();
+        match args.command {
+            Some(Command::Cache {
+                action: CacheCommand::Merge { inputs, output },
+            }) => {
+                assert_eq!(inputs, vec![PathBuf::from("a.bin"), PathBuf::from("b.bin")]);
+                assert_eq!(output, Some(PathBuf::from("m.bin")));
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+
+        // A single input is rejected
+        assert!(Args::try_parse_from(["rudu", "cache", "merge", "a.bin"]).is_err());
+
+        let args =
+            Args::try_parse_from(["rudu", "--exclude=tmp", "cache", "warm", "/data"]).unwrap();
+        assert_eq!(args.exclude, vec!["tmp".to_string()]);
+        assert!(matches!(
+            args.command,
+            Some(Command::Cache {
+                action: CacheCommand::Warm { ref path }
+            }) if path == &PathBuf::from("/data")
+        ));
+
+        // Plain paths still scan
+        let args = Args::try_parse_from(["rudu", "/tmp"]).unwrap();
+        assert!(args.command.is_none());
+        assert_eq!(args.path, PathBuf::from("/tmp"));
+    }
+
     #[test]
     fn test_default_values() {
         let args = Args::try_parse_from(["rudu"]).unwrap();
 
         assert_eq!(args.path, PathBuf::from("."));
         assert_eq!(args.depth, None);
-        assert_eq!(args.sort, SortKey::Name);
-        assert_eq!(args.show_files, true);
+        assert_eq!(args.sort, [SortKey::Name]);
+        assert!(!args.reverse);
+        assert!(args.show_files);
         assert_eq!(args.exclude, Vec::<String>::new());
-        assert_eq!(args.show_owner, false);
+        assert_eq!(args.include, Vec::<String>::new());
+        assert!(args.exclude_from.is_empty());
+        assert!(args.exclude_regex.is_empty() && args.include_regex.is_empty());
+        assert!(!args.show_owner);
         assert_eq!(args.output, None);
         assert_eq!(args.threads, None);
-        assert_eq!(args.show_inodes, false);
-        assert_eq!(args.no_cache, false);
+        assert!(!args.show_inodes);
+        assert!(!args.no_cache);
         assert_eq!(args.cache_ttl, 604800);
-        assert_eq!(args.profile, false);
+        assert_eq!(args.cache_validation, CacheValidation::Fast);
+        assert!(!args.profile);
         assert_eq!(args.memory_limit, None);
         assert_eq!(args.memory_check_interval_ms, 200);
+        assert!(!args.deleted_open);
     }
 
     #[test]
@@ -185,6 +1045,434 @@ mod tests {
         assert_eq!(args.path, PathBuf::from("/some/path"));
     }
 
+    #[test]
+    fn test_cache_validation_parsing() {
+        let args = Args::try_parse_from(["rudu", "--cache-validation", "strict"]).unwrap();
+        assert_eq!(args.cache_validation, CacheValidation::Strict);
+
+        let result = Args::try_parse_from(["rudu", "--cache-validation", "paranoid"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_cache_location_parsing() {
+        let args = Args::try_parse_from(["rudu"]).unwrap();
+        assert_eq!(args.cache_location, CacheLocation::Xdg);
+
+        let args = Args::try_parse_from(["rudu", "--cache-location", "in-tree"]).unwrap();
+        assert_eq!(args.cache_location, CacheLocation::InTree);
+
+        let args = Args::try_parse_from(["rudu", "--cache-location", "auto"]).unwrap();
+        assert_eq!(args.cache_location, CacheLocation::Auto);
+    }
+
+    #[test]
+    fn test_output_format_defaults() {
+        let args = Args::try_parse_from(["rudu"]).unwrap();
+        assert_eq!(args.output_format(), OutputFormat::Terminal);
+
+        let args = Args::try_parse_from(["rudu", "--output", "out.csv"]).unwrap();
+        assert_eq!(args.output_format(), OutputFormat::Csv);
+
+        let args = Args::try_parse_from(["rudu", "--format", "ndjson"]).unwrap();
+        assert_eq!(args.output_format(), OutputFormat::Ndjson);
+
+        let args =
+            Args::try_parse_from(["rudu", "--format", "ndjson", "--output", "out.json"]).unwrap();
+        assert_eq!(args.output_format(), OutputFormat::Ndjson);
+
+        assert!(Args::try_parse_from(["rudu", "--format", "xml"]).is_err());
+    }
+
+    #[test]
+    fn test_cache_key_file_parsing() {
+        let args = Args::try_parse_from(["rudu"]).unwrap();
+        assert_eq!(args.cache_key_file, None);
+
+        let args = Args::try_parse_from(["rudu", "--cache-key-file", "/etc/rudu.key"]).unwrap();
+        assert_eq!(args.cache_key_file, Some(PathBuf::from("/etc/rudu.key")));
+    }
+
+    #[test]
+    fn test_columns_parsing() {
+        let args = Args::try_parse_from(["rudu"]).unwrap();
+        assert_eq!(args.columns, None);
+
+        let args = Args::try_parse_from(["rudu", "--columns", "size,path"]).unwrap();
+        assert_eq!(args.columns, Some("size,path".parse().unwrap()));
+
+        assert!(Args::try_parse_from(["rudu", "--columns", "size,colour"]).is_err());
+    }
+
+    #[test]
+    fn test_printf_parsing() {
+        let args = Args::try_parse_from(["rudu", "--printf", "%s %p\\n"]).unwrap();
+        assert_eq!(args.printf, Some("%s %p\\n".parse().unwrap()));
+        assert_eq!(args.output_format(), OutputFormat::Printf);
+
+        assert!(Args::try_parse_from(["rudu", "--printf", "%z"]).is_err());
+        assert!(Args::try_parse_from(["rudu", "--printf", "%p", "--format", "csv"]).is_err());
+    }
+
+    #[test]
+    fn test_csv_dialect_parsing() {
+        let args = Args::try_parse_from(["rudu"]).unwrap();
+        assert_eq!(args.csv_delimiter, None);
+        assert_eq!(args.csv_quote, CsvQuoting::Necessary);
+        assert!(args.csv_header);
+
+        let args = Args::try_parse_from([
+            "rudu",
+            "--csv-delimiter",
+            ";",
+            "--csv-quote",
+            "non-numeric",
+            "--csv-header",
+            "false",
+        ])
+        .unwrap();
+        assert_eq!(args.csv_delimiter, Some(b';'));
+        assert_eq!(args.csv_quote, CsvQuoting::NonNumeric);
+        assert!(!args.csv_header);
+
+        let args = Args::try_parse_from(["rudu", "--csv-delimiter", "tab"]).unwrap();
+        assert_eq!(args.csv_delimiter, Some(b'\t'));
+        assert!(Args::try_parse_from(["rudu", "--csv-delimiter", "::"]).is_err());
+        assert!(Args::try_parse_from(["rudu", "--csv-delimiter", "§"]).is_err());
+    }
+
+    #[test]
+    fn test_null_parsing() {
+        assert!(!Args::try_parse_from(["rudu"]).unwrap().null);
+        assert!(Args::try_parse_from(["rudu", "-0"]).unwrap().null);
+        assert!(Args::try_parse_from(["rudu", "--null"]).unwrap().null);
+    }
+
+    #[test]
+    fn test_size_format_options() {
+        let size_format = |argv: &[&str]| {
+            Args::try_parse_from(std::iter::once("rudu").chain(argv.iter().copied()))
+                .unwrap()
+                .size_format()
+        };
+        assert_eq!(size_format(&[]), SizeFormat::Decimal);
+        assert_eq!(size_format(&["--si"]), SizeFormat::Decimal);
+        assert_eq!(size_format(&["--binary"]), SizeFormat::Binary);
+        assert_eq!(size_format(&["-k"]), SizeFormat::Blocks(1024));
+        assert_eq!(size_format(&["-m"]), SizeFormat::Blocks(1 << 20));
+        assert_eq!(size_format(&["-B", "4KB"]), SizeFormat::Blocks(4000));
+        assert_eq!(
+            size_format(&["--block-size=1M"]),
+            SizeFormat::Blocks(1 << 20)
+        );
+
+        assert!(Args::try_parse_from(["rudu", "-k", "--binary"]).is_err());
+        assert!(Args::try_parse_from(["rudu", "-B", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_top_parsing() {
+        let args = Args::try_parse_from(["rudu", "--top", "20", "--top-files"]).unwrap();
+        assert_eq!(args.top, Some(20));
+        assert!(args.top_files);
+
+        assert!(Args::try_parse_from(["rudu", "--top-files"]).is_err());
+    }
+
+    #[test]
+    fn test_output_to_stdout() {
+        let parse = |argv: &[&str]| {
+            let mut args =
+                Args::try_parse_from(std::iter::once("rudu").chain(argv.iter().copied())).unwrap();
+            args.resolve_stdout_output();
+            args
+        };
+
+        let args = parse(&["--output", "-"]);
+        assert_eq!(args.output, None);
+        assert_eq!(args.output_format(), OutputFormat::Csv);
+        assert!(!args.stdout_is_terminal_listing());
+
+        let args = parse(&["--format", "ndjson", "--output", "-"]);
+        assert_eq!(args.output_format(), OutputFormat::Ndjson);
+
+        let args = parse(&["--printf", "%p\\n", "--output", "-"]);
+        assert_eq!(args.output_format(), OutputFormat::Printf);
+
+        let args = parse(&["--output", "out.csv"]);
+        assert_eq!(args.output.as_deref(), Some("out.csv"));
+        assert!(!args.stdout_is_terminal_listing());
+
+        assert!(parse(&[]).stdout_is_terminal_listing());
+    }
+
+    #[test]
+    fn test_time_cutoff() {
+        let now = 1_700_000_000;
+        assert_eq!(time_cutoff("90s", now), Ok(now - 90));
+        assert_eq!(time_cutoff("12h", now), Ok(now - 12 * 3600));
+        assert_eq!(time_cutoff("2y", now), Ok(now - 2 * 365 * 86400));
+        assert_eq!(time_cutoff("6mo", now), Ok(now - 6 * 30 * 86400));
+        assert_eq!(time_cutoff("2024-01-01", now), Ok(1_704_067_200));
+        assert_eq!(
+            time_cutoff("2024-01-01T01:00:00+01:00", now),
+            Ok(1_704_067_200)
+        );
+        for bad in ["", "d", "2", "2x", "-1d", "2024-13-01"] {
+            assert!(time_cutoff(bad, now).is_err(), "{bad}");
+        }
+        assert!(
+            Args::try_parse_from(["rudu", "--older-than", "2y"])
+                .unwrap()
+                .older_than
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_daemon_command() {
+        let args = Args::try_parse_from([
+            "rudu",
+            "--format",
+            "ndjson",
+            "daemon",
+            "--interval",
+            "30m",
+            "--paths-from",
+            "dirs.txt",
+            "--keep",
+            "7",
+            "/data",
+        ])
+        .unwrap();
+        match &args.command {
+            Some(Command::Daemon {
+                paths,
+                paths_from,
+                interval,
+                output_dir,
+                keep,
+                once,
+            }) => {
+                assert_eq!(*paths, [PathBuf::from("/data")]);
+                assert_eq!(*paths_from, Some(PathBuf::from("dirs.txt")));
+                assert_eq!(*interval, std::time::Duration::from_secs(30 * 60));
+                assert_eq!(*output_dir, PathBuf::from("."));
+                assert_eq!(*keep, Some(7));
+                assert!(!once);
+            }
+            other => panic!("expected the daemon command, got {other:?}"),
+        }
+        assert_eq!(args.output_format().extension(), "ndjson");
+        for bad in ["0h", "6", "soon"] {
+            assert!(Args::try_parse_from(["rudu", "daemon", "--interval", bad]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_delete_selected_mode() {
+        let parse = |argv: &[&str]| Args::try_parse_from(argv).map(|a| a.delete_selected);
+        assert_eq!(parse(&["rudu"]).unwrap(), None);
+        assert_eq!(
+            parse(&["rudu", "--delete-selected", "/data"]).unwrap(),
+            Some(DeleteMode::Trash)
+        );
+        assert_eq!(
+            parse(&["rudu", "--delete-selected=remove"]).unwrap(),
+            Some(DeleteMode::Remove)
+        );
+        assert!(parse(&["rudu", "--dry-run"]).is_err());
+        assert!(parse(&["rudu", "--delete-selected", "--report", "by-owner"]).is_err());
+    }
+
+    #[test]
+    fn test_files_only_conflicts_with_dirs_only() {
+        assert!(Args::try_parse_from(["rudu", "--files-only", "--dirs-only"]).is_err());
+        let args = Args::try_parse_from(["rudu", "--files-only"]).unwrap();
+        assert!(args.files_only && !args.dirs_only);
+    }
+
+    #[test]
+    fn test_summarize_parsing() {
+        assert!(Args::try_parse_from(["rudu", "-s"]).unwrap().summarize);
+        assert!(Args::try_parse_from(["rudu", "-s", "--top", "5"]).is_err());
+        assert!(Args::try_parse_from(["rudu", "--summarize", "--files-only"]).is_err());
+    }
+
+    #[test]
+    fn test_stream_conflicts_with_whole_listing_options() {
+        let args = Args::try_parse_from(["rudu", "--stream", "--format", "ndjson", "-d", "2"]);
+        assert!(args.unwrap().stream);
+        assert!(Args::try_parse_from(["rudu", "--stream", "--top", "5"]).is_err());
+        assert!(Args::try_parse_from(["rudu", "--stream", "--report", "by-owner"]).is_err());
+        assert!(Args::try_parse_from(["rudu", "--stream", "--memory-limit", "512"]).is_err());
+    }
+
+    #[test]
+    fn test_du_compatible_aliases() {
+        let args = Args::try_parse_from([
+            "rudu",
+            "-h",
+            "-a",
+            "--max-depth",
+            "2",
+            "-X",
+            "ex.txt",
+            "/data",
+        ])
+        .unwrap();
+        assert_eq!(args.depth, Some(2));
+        assert!(args.all && args.human_readable);
+        assert_eq!(args.exclude_from, [PathBuf::from("ex.txt")]);
+        assert_eq!(args.path, PathBuf::from("/data"));
+        assert_eq!(
+            Args::try_parse_from(["rudu", "-d1"]).unwrap().depth,
+            Some(1)
+        );
+        assert_eq!(
+            Args::try_parse_from(["rudu", "--help"]).unwrap_err().kind(),
+            clap::error::ErrorKind::DisplayHelp
+        );
+    }
+
+    #[test]
+    fn test_env_opts_come_first_and_keep_the_path() {
+        let argv = || ["rudu", "/data", "--threads", "4"].map(OsString::from);
+        let combined = with_env_opts(
+            argv(),
+            Some("--threads 8 --exclude .snapshot 'scratch dir' -d 2 --show-files false"),
+        )
+        .unwrap();
+        assert_eq!(
+            combined,
+            [
+                "rudu",
+                "--threads=8",
+                "--exclude=.snapshot",
+                "--exclude=scratch dir",
+                "-d2",
+                "--show-files=false",
+                "/data",
+                "--threads",
+                "4",
+            ]
+            .map(OsString::from)
+        );
+
+        let args = Args::try_parse_from(combined).unwrap();
+        assert_eq!(args.path, PathBuf::from("/data"));
+        assert_eq!(args.threads, Some(4));
+        assert_eq!(args.exclude, [".snapshot", "scratch dir"]);
+        assert_eq!(args.depth, Some(2));
+        assert!(!args.show_files);
+
+        assert_eq!(with_env_opts(argv(), None).unwrap(), argv());
+        assert!(with_env_opts(argv(), Some("--exclude 'open")).is_err());
+    }
+
+    #[test]
+    fn test_verbosity_parsing() {
+        assert_eq!(Args::try_parse_from(["rudu", "-vv"]).unwrap().verbose, 2);
+        assert!(Args::try_parse_from(["rudu", "--quiet"]).unwrap().quiet);
+        assert!(Args::try_parse_from(["rudu", "-q", "-v"]).is_err());
+    }
+
+    #[test]
+    fn test_completions() {
+        let args = Args::try_parse_from(["rudu", "completions", "zsh"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Some(Command::Completions {
+                shell: clap_complete::Shell::Zsh
+            })
+        ));
+
+        let mut out = Vec::new();
+        write_completions(clap_complete::Shell::Bash, &mut out);
+        let script = String::from_utf8(out).unwrap();
+        assert!(script.contains("--sort"));
+        assert!(script.contains("terminal csv tsv ndjson"));
+        assert!(script.contains("rudu__subcmd__cache__subcmd__warm"));
+    }
+
+    #[test]
+    fn test_compound_sort_keys() {
+        let args = Args::try_parse_from(["rudu", "--sort", "size,name", "-r"]).unwrap();
+        assert_eq!(args.sort, [SortKey::Size, SortKey::Name]);
+        assert!(args.reverse);
+        assert!(Args::try_parse_from(["rudu", "--sort", "size,bogus"]).is_err());
+        let args = Args::try_parse_from(["rudu", "--sort", "inodes,mtime,owner"]).unwrap();
+        assert_eq!(args.sort, [SortKey::Inodes, SortKey::Mtime, SortKey::Owner]);
+    }
+
+    #[test]
+    fn test_size_filter_parsing() {
+        let args = Args::try_parse_from(["rudu", "--min-size", "1G", "--max-size", "2TB"]).unwrap();
+        assert_eq!(args.min_size, Some(1 << 30));
+        assert_eq!(args.max_size, Some(2_000_000_000_000));
+
+        let args = Args::try_parse_from(["rudu", "-t", "-1K", "/data"]).unwrap();
+        assert_eq!(args.threshold, Some(Threshold::AtMost(1024)));
+        assert_eq!(args.path, PathBuf::from("/data"));
+        let args = Args::try_parse_from(["rudu", "--threshold", "1MB"]).unwrap();
+        assert_eq!(args.threshold, Some(Threshold::AtLeast(1_000_000)));
+
+        assert!(Args::try_parse_from(["rudu", "--min-size", "big"]).is_err());
+    }
+
+    #[test]
+    fn test_limit_parsing() {
+        let args = Args::try_parse_from(["rudu", "--limit", "50", "--offset", "100"]).unwrap();
+        assert_eq!(args.limit, Some(50));
+        assert_eq!(args.offset, 100);
+
+        let args = Args::try_parse_from(["rudu"]).unwrap();
+        assert_eq!(args.limit, None);
+        assert_eq!(args.offset, 0);
+    }
+
+    #[test]
+    fn test_report_parsing() {
+        let args = Args::try_parse_from(["rudu", "--report", "by-owner"]).unwrap();
+        assert_eq!(args.report, Some(ReportKind::ByOwner));
+        let args = Args::try_parse_from(["rudu", "--report", "by-ext"]).unwrap();
+        assert_eq!(args.report, Some(ReportKind::ByExt));
+        let args = Args::try_parse_from(["rudu", "--report", "age", "--depth", "2"]).unwrap();
+        assert_eq!(args.report, Some(ReportKind::Age));
+        let args = Args::try_parse_from(["rudu", "--report", "hsm"]).unwrap();
+        assert_eq!(args.report, Some(ReportKind::Hsm));
+
+        assert!(Args::try_parse_from(["rudu", "--report", "by-size"]).is_err());
+        assert!(Args::try_parse_from(["rudu", "--report", "by-owner", "--top", "5"]).is_err());
+    }
+
+    #[test]
+    fn test_cache_dir_for_parsing() {
+        let args = Args::try_parse_from([
+            "rudu",
+            "--cache-dir-for",
+            "/g/data=/scratch/cache",
+            "--cache-dir-for=/home=/tmp/cache",
+        ])
+        .unwrap();
+        assert_eq!(args.cache_dir_for.len(), 2);
+        assert_eq!(args.cache_dir_for[0].prefix, PathBuf::from("/g/data"));
+        assert_eq!(args.cache_dir_for[0].dir, PathBuf::from("/scratch/cache"));
+
+        let result = Args::try_parse_from(["rudu", "--cache-dir-for", "/g/data"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_group_filters() {
+        let args = Args::try_parse_from(["rudu", "--gid", "1001"]).unwrap();
+        assert_eq!(args.gid, Some(1001));
+        let args = Args::try_parse_from(["rudu", "--group", "climate"]).unwrap();
+        assert_eq!(args.group.as_deref(), Some("climate"));
+        assert!(Args::try_parse_from(["rudu", "--group", "climate", "--gid", "1001"]).is_err());
+        assert!(Args::try_parse_from(["rudu", "--gid", "x"]).is_err());
+    }
+
     #[test]
     fn test_memory_check_interval_parsing() {
         // Test with custom memory check interval
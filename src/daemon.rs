@@ -0,0 +1,195 @@
+//! Scheduling helpers for `rudu daemon`.
+//!
+//! The daemon scans a list of directories once per interval, writing each
+//! result to a file named after the scanned path and the time the scan
+//! started, e.g. `lustre_proj-20240131T060000Z.csv`. This module holds the
+//! parts that are independent of the scan itself: output naming and
+//! retention, waiting for the next round, stopping on `SIGTERM`/`SIGINT`,
+//! and readiness and status messages for systemd (`Type=notify` units).
+
+use crate::manifest::MANIFEST_FILE_NAME;
+use chrono::{DateTime, Utc};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Format of the timestamp in output file names
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Longest sleep between checks for a stop request
+const STOP_POLL: Duration = Duration::from_millis(500);
+
+/// Set by the signal handler installed by [`stop_on_signals`]
+static STOP: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn request_stop(_signal: libc::c_int) {
+    STOP.store(true, Ordering::SeqCst);
+}
+
+/// Makes `SIGTERM` and `SIGINT` set the returned flag instead of killing
+/// the process, so a round can stop between (or, as a cancelled scan,
+/// inside) scans and the daemon exit cleanly.
+pub fn stop_on_signals() -> &'static AtomicBool {
+    let handler = request_stop as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    // SAFETY: the handler only stores to an atomic, which is async-signal-safe
+    unsafe {
+        libc::signal(libc::SIGTERM, handler);
+        libc::signal(libc::SIGINT, handler);
+    }
+    &STOP
+}
+
+/// Sleeps until `deadline`, returning `false` early if `stop` is set.
+pub fn sleep_until(deadline: Instant, stop: &AtomicBool) -> bool {
+    loop {
+        if stop.load(Ordering::SeqCst) {
+            return false;
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return true;
+        }
+        std::thread::sleep((deadline - now).min(STOP_POLL));
+    }
+}
+
+/// Name stem for the outputs of `root`: its path with separators replaced
+/// by `_`, or `root` for `/`.
+pub fn output_stem(root: &Path) -> String {
+    let stem = root
+        .to_string_lossy()
+        .trim_matches('/')
+        .replace(['/', ' '], "_");
+    if stem.is_empty() {
+        "root".to_string()
+    } else {
+        stem
+    }
+}
+
+/// Path in `dir` of the output of the scan of `root` started at `started`.
+pub fn output_path(dir: &Path, root: &Path, started: DateTime<Utc>, extension: &str) -> PathBuf {
+    dir.join(format!(
+        "{}-{}.{}",
+        output_stem(root),
+        started.format(TIMESTAMP_FORMAT),
+        extension
+    ))
+}
+
+/// Path of the run manifest of the daemon output `output`, e.g.
+/// `lustre_proj-20240131T060000Z.manifest.json`; rounds share the output
+/// directory, so each gets its own.
+pub fn manifest_path(output: &Path) -> PathBuf {
+    output.with_extension(MANIFEST_FILE_NAME)
+}
+
+/// Deletes all but the newest `keep` outputs of `root` in `dir`, with their
+/// manifests, returning how many outputs were deleted.
+///
+/// Only files named as by [`output_path`] with the same stem and extension
+/// are considered, so other files in `dir` are left alone.
+pub fn prune_outputs(dir: &Path, root: &Path, extension: &str, keep: usize) -> io::Result<usize> {
+    let prefix = format!("{}-", output_stem(root));
+    let suffix = format!(".{}", extension);
+    let mut outputs: Vec<(DateTime<Utc>, PathBuf)> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let timestamp = name.strip_prefix(&prefix)?.strip_suffix(&suffix)?;
+            let started = chrono::NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+                .ok()?
+                .and_utc();
+            Some((started, entry.path()))
+        })
+        .collect();
+    outputs.sort();
+
+    let excess = outputs.len().saturating_sub(keep);
+    for (_, path) in &outputs[..excess] {
+        std::fs::remove_file(path)?;
+        match std::fs::remove_file(manifest_path(path)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    Ok(excess)
+}
+
+/// Sends `state` (e.g. `READY=1` or `STATUS=...`) to systemd when running
+/// under a `Type=notify` unit; does nothing elsewhere.
+pub fn notify(state: &str) {
+    let Some(socket) = std::env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    if let Err(e) = send_notification(Path::new(&socket), state) {
+        log::debug!("Failed to notify systemd: {}", e);
+    }
+}
+
+fn send_notification(socket: &Path, state: &str) -> io::Result<()> {
+    use std::os::unix::net::UnixDatagram;
+
+    let sender = UnixDatagram::unbound()?;
+    // A leading '@' names a socket in the abstract namespace
+    #[cfg(target_os = "linux")]
+    if let Some(name) = socket.to_str().and_then(|s| s.strip_prefix('@')) {
+        use std::os::linux::net::SocketAddrExt;
+        let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+        sender.send_to_addr(state.as_bytes(), &addr)?;
+        return Ok(());
+    }
+    sender.send_to(state.as_bytes(), socket)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_output_names() {
+        let started = Utc.with_ymd_and_hms(2024, 1, 31, 6, 0, 0).unwrap();
+        assert_eq!(
+            output_path(Path::new("/out"), Path::new("/lustre/proj"), started, "csv"),
+            PathBuf::from("/out/lustre_proj-20240131T060000Z.csv")
+        );
+        assert_eq!(output_stem(Path::new("/")), "root");
+        assert_eq!(output_stem(Path::new("data/my dir/")), "data_my_dir");
+    }
+
+    #[test]
+    fn test_prune_keeps_newest_outputs() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = Path::new("/data");
+        for day in 1..=4 {
+            let started = Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap();
+            let output = output_path(dir.path(), root, started, "csv");
+            std::fs::write(&output, "").unwrap();
+            std::fs::write(manifest_path(&output), "{}").unwrap();
+        }
+        let other = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        std::fs::write(output_path(dir.path(), Path::new("/x"), other, "csv"), "").unwrap();
+        std::fs::write(dir.path().join("data-notes.csv"), "").unwrap();
+
+        assert_eq!(prune_outputs(dir.path(), root, "csv", 2).unwrap(), 2);
+        let mut left: Vec<String> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        left.sort();
+        assert_eq!(
+            left,
+            [
+                "data-20240103T000000Z.csv",
+                "data-20240103T000000Z.manifest.json",
+                "data-20240104T000000Z.csv",
+                "data-20240104T000000Z.manifest.json",
+                "data-notes.csv",
+                "x-20240101T000000Z.csv"
+            ]
+        );
+    }
+}
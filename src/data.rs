@@ -1,9 +1,13 @@
 //! Data structures for representing file system entries.
 //!
 //! This module defines the core data structures used throughout the `rudu` application
-//! for representing files and directories discovered during file system traversal.
+//! for representing files and directories discovered during file system traversal,
+//! and the [`ScanMeta`] describing the run that discovered them.
 
-use std::path::PathBuf;
+use crate::options::ScanOptions;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 
 /// Represents a file or directory entry discovered during scanning.
 ///
@@ -12,25 +16,164 @@ use std::path::PathBuf;
 /// * `size` - Size in bytes
 /// * `owner` - Optional owner (username) of the file/directory
 /// * `inodes` - Optional number of inodes (files/subdirectories) for directories
-/// * `entry_type` - Type of entry (file or directory)
-#[derive(Debug, Clone, serde::Serialize)]
+/// * `entry_type` - Type of entry (file, directory, symlink, ...)
+/// * `previous_size` - Size recorded in the cache before this scan, if any
+/// * `mtime` - Modification time in seconds since the epoch, if known
+/// * `stat` - Further `stat` fields, collected only when the scan asks for
+///   them with `extended_metadata`
+///
+/// # Serialized form
+///
+/// NDJSON, YAML and MessagePack output write entries with their `serde`
+/// representation, which is kept stable so other Rust tools can read it
+/// back into a `FileEntry`:
+///
+/// | Field | Type | Contents |
+/// |-------|------|----------|
+/// | `path` | string | Path as scanned (or as rewritten by `--absolute-paths`/`--relative-to`) |
+/// | `size` | integer | Disk usage in bytes (allocated blocks, not apparent size) |
+/// | `owner` | string or null | User name, or the numeric UID if it has no name |
+/// | `inodes` | integer or null | Direct children of a directory, with `--show-inodes` |
+/// | `entry_type` | string | `File`, `Dir`, `Symlink`, `Socket`, `Fifo`, `Device`, `Unknown` or `Archive` (with `--scan-archives`) |
+/// | `previous_size` | integer, optional | Bytes recorded by the previous cached scan |
+/// | `mtime` | integer, optional | Modification time in seconds since the Unix epoch |
+/// | `stat` | object, optional | [`EntryStat`] fields, with `--extended-metadata` |
+///
+/// Optional fields are left out when unknown, and missing fields default
+/// to null/absent when deserializing, so records written by older
+/// releases still load. New fields may be added, always optional.
+///
+/// ```
+/// use rudu::FileEntry;
+///
+/// let entry: FileEntry =
+///     serde_json::from_str(r#"{"path":"/data/a.nc","size":4096,"entry_type":"File"}"#)?;
+/// assert_eq!(entry.size, 4096);
+/// assert_eq!(entry.owner, None);
+/// # Ok::<(), serde_json::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FileEntry {
     pub path: PathBuf,
     pub size: u64,
+    #[serde(default)]
     pub owner: Option<String>,
+    #[serde(default)]
     pub inodes: Option<u64>,
     pub entry_type: EntryType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtime: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stat: Option<Box<EntryStat>>,
+}
+
+#[cfg(test)]
+impl FileEntry {
+    /// Entry with only a path, size and type set, for unit tests. Chain the
+    /// `with_*` setters, or use struct update syntax, for the other fields.
+    pub(crate) fn for_test(path: impl Into<PathBuf>, size: u64, entry_type: EntryType) -> Self {
+        FileEntry {
+            path: path.into(),
+            size,
+            owner: None,
+            inodes: None,
+            entry_type,
+            previous_size: None,
+            mtime: None,
+            stat: None,
+        }
+    }
+
+    #[cfg_attr(not(feature = "serve"), allow(dead_code))]
+    pub(crate) fn with_owner(mut self, owner: &str) -> Self {
+        self.owner = Some(owner.to_string());
+        self
+    }
+
+    #[cfg_attr(not(feature = "xlsx"), allow(dead_code))]
+    pub(crate) fn with_mtime(mut self, mtime: u64) -> Self {
+        self.mtime = Some(mtime);
+        self
+    }
+}
+
+/// `stat` fields of an entry beyond its size, owner and mtime.
+///
+/// Boxed in [`FileEntry::stat`] so scans that do not collect them pay one
+/// pointer per entry.
+///
+/// # Fields
+/// Serialized as an object with these field names.
+///
+/// * `atime` - Last access time in seconds since the epoch
+/// * `nlink` - Number of hard links
+/// * `uid` / `gid` - Numeric owner and group
+/// * `mode` - File type and permission bits (`st_mode`)
+/// * `dev` - Device ID of the filesystem holding the entry
+/// * `ino` - Inode number
+/// * `apparent_size` - Length in bytes (`st_size`), larger than the disk
+///   usage of sparse files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryStat {
+    pub atime: u64,
+    pub nlink: u64,
+    pub uid: u32,
+    pub gid: u32,
+    pub mode: u32,
+    pub dev: u64,
+    pub ino: u64,
+    #[serde(default)]
+    pub apparent_size: u64,
+}
+
+impl EntryStat {
+    /// Takes the fields from metadata already fetched for the entry.
+    pub fn from_metadata(metadata: &std::fs::Metadata) -> Self {
+        use std::os::unix::fs::MetadataExt;
+
+        Self {
+            atime: metadata.atime().max(0) as u64,
+            nlink: metadata.nlink(),
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+            mode: metadata.mode(),
+            dev: metadata.dev(),
+            ino: metadata.ino(),
+            apparent_size: metadata.size(),
+        }
+    }
 }
 
 /// Represents the type of file system entry.
 ///
+/// Everything but `Dir` is a leaf of the tree: its own size counts towards
+/// its directories, and symlinks are never followed.
+///
 /// # Variants
 /// * `File` - A regular file
 /// * `Dir` - A directory
-#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+/// * `Symlink` - A symbolic link, measured as the link itself
+/// * `Socket` - A Unix domain socket
+/// * `Fifo` - A named pipe
+/// * `Device` - A block or character device
+/// * `Unknown` - A type the platform does not report or rudu does not know
+/// * `Archive` - A tar or zip file whose members were listed below it
+///   (`--scan-archives`)
+///
+/// Serialized as the variant name (`"File"`, `"Dir"`, `"Symlink"`, ...),
+/// unlike the upper-case labels of [`EntryType::as_str`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum EntryType {
     File,
     Dir,
+    Symlink,
+    Socket,
+    Fifo,
+    Device,
+    Unknown,
+    Archive,
 }
 
 impl EntryType {
@@ -39,12 +182,256 @@ impl EntryType {
     /// # Returns
     /// * `"FILE"` for `EntryType::File`
     /// * `"DIR"` for `EntryType::Dir`
+    /// * `"SYMLINK"`, `"SOCKET"`, `"FIFO"`, `"DEVICE"`, `"UNKNOWN"` or `"ARCHIVE"` for the others
     pub fn as_str(&self) -> &'static str {
         match self {
             EntryType::File => "FILE",
             EntryType::Dir => "DIR",
+            EntryType::Symlink => "SYMLINK",
+            EntryType::Socket => "SOCKET",
+            EntryType::Fifo => "FIFO",
+            EntryType::Device => "DEVICE",
+            EntryType::Unknown => "UNKNOWN",
+            EntryType::Archive => "ARCHIVE",
+        }
+    }
+
+    /// Returns true for directories.
+    pub fn is_dir(&self) -> bool {
+        *self == EntryType::Dir
+    }
+
+    /// Returns true for directories and listed archives, the only entries
+    /// with children. Their sizes already add up what is below them, so
+    /// totals over files skip them.
+    pub fn is_container(&self) -> bool {
+        matches!(self, EntryType::Dir | EntryType::Archive)
+    }
+
+    /// Classifies an entry by the file type of its `lstat`, as reported by
+    /// directory walks.
+    pub fn from_file_type(file_type: std::fs::FileType) -> Self {
+        use std::os::unix::fs::FileTypeExt;
+
+        if file_type.is_dir() {
+            EntryType::Dir
+        } else if file_type.is_file() {
+            EntryType::File
+        } else if file_type.is_symlink() {
+            EntryType::Symlink
+        } else if file_type.is_socket() {
+            EntryType::Socket
+        } else if file_type.is_fifo() {
+            EntryType::Fifo
+        } else if file_type.is_block_device() || file_type.is_char_device() {
+            EntryType::Device
+        } else {
+            EntryType::Unknown
+        }
+    }
+}
+
+/// Enum for specifying how to sort scan results.
+///
+/// # Variants
+/// * `Name` - Sort entries alphabetically by path name
+/// * `Size` - Sort entries by size in descending order (largest first)
+/// * `Mtime` - Sort entries by modification time, newest first
+/// * `Owner` - Sort entries alphabetically by owner name
+/// * `Inodes` - Sort directories by inode count, most first
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum SortKey {
+    Name,
+    Size,
+    Mtime,
+    Owner,
+    Inodes,
+}
+
+/// Scan options that affect which entries a run reports.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScanFlags {
+    pub depth: Option<usize>,
+    pub show_files: bool,
+    pub show_owner: bool,
+    pub show_inodes: bool,
+    pub no_cache: bool,
+    pub exclude: Vec<String>,
+}
+
+impl ScanFlags {
+    /// Captures the reporting-relevant options of a scan.
+    pub fn from_options(options: &ScanOptions) -> Self {
+        Self {
+            depth: options.depth,
+            show_files: options.show_files,
+            show_owner: options.show_owner,
+            show_inodes: options.show_inodes,
+            no_cache: options.no_cache,
+            exclude: options.exclude.clone(),
+        }
+    }
+}
+
+/// Recursive entry counts of one directory, gathered by `--count-only`.
+///
+/// # Fields
+/// * `path` - The directory
+/// * `files` - Files (and other non-directories) anywhere below it
+/// * `dirs` - Subdirectories anywhere below it, not counting itself
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DirCounts {
+    pub path: PathBuf,
+    pub files: u64,
+    pub dirs: u64,
+}
+
+impl DirCounts {
+    /// Inodes used by the directory and everything below it, as `du --inodes` counts them
+    pub fn inodes(&self) -> u64 {
+        self.files + self.dirs + 1
+    }
+}
+
+/// A filesystem mounted below the scanned root, found during traversal.
+///
+/// # Fields
+/// * `path` - Directory the filesystem is mounted on
+/// * `device` - Device ID (`st_dev`) of the mounted filesystem
+/// * `fs_type` - Filesystem type from the mount table, e.g. `nfs` (Linux only)
+/// * `excluded` - True if `--exclude-mounts` kept it out of the totals
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MountPoint {
+    pub path: PathBuf,
+    pub device: u64,
+    pub fs_type: Option<String>,
+    pub excluded: bool,
+}
+
+impl MountPoint {
+    /// Describes the mount for summaries, e.g. `/data/.snapshot (nfs, excluded)`.
+    pub fn describe(&self) -> String {
+        let fs_type = self.fs_type.as_deref().unwrap_or("unknown type");
+        if self.excluded {
+            format!("{} ({}, excluded)", self.path.display(), fs_type)
+        } else {
+            format!("{} ({})", self.path.display(), fs_type)
+        }
+    }
+}
+
+/// Facts about a single scan run, produced by the scan layer.
+///
+/// Formatters and metrics sinks take this instead of piecing the same
+/// information together from `Args` and process-wide state.
+///
+/// # Fields
+/// * `run_id` - Identifier shared by every artifact of the run
+/// * `root` - The scanned root path
+/// * `started_at` / `finished_at` - Wall-clock bounds of the scan
+/// * `flags` - Options that shaped the results
+/// * `total_size` - Size of the root directory in bytes
+/// * `file_count` / `dir_count` - Entries discovered, before output filtering
+/// * `cache_hits` / `cache_total` - Directories served from / checked against the cache
+/// * `partial` - True if the scan stopped early (e.g. on the memory limit)
+/// * `mounts` - Mountpoints crossed below the root; those not excluded
+///   contributed to the totals alongside the root's own filesystem
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanMeta {
+    pub run_id: String,
+    pub root: PathBuf,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub flags: ScanFlags,
+    pub total_size: u64,
+    pub file_count: u64,
+    pub dir_count: u64,
+    pub cache_hits: u64,
+    pub cache_total: u64,
+    pub partial: bool,
+    #[serde(default)]
+    pub mounts: Vec<MountPoint>,
+}
+
+impl Default for ScanMeta {
+    fn default() -> Self {
+        let now = Utc::now();
+        Self {
+            run_id: String::new(),
+            root: PathBuf::new(),
+            started_at: now,
+            finished_at: now,
+            flags: ScanFlags::default(),
+            total_size: 0,
+            file_count: 0,
+            dir_count: 0,
+            cache_hits: 0,
+            cache_total: 0,
+            partial: false,
+            mounts: Vec::new(),
+        }
+    }
+}
+
+impl ScanMeta {
+    /// Starts the metadata for a scan of `root` beginning now.
+    pub fn start(root: &Path, options: &ScanOptions) -> Self {
+        Self {
+            run_id: crate::manifest::generate_run_id(),
+            root: root.to_path_buf(),
+            started_at: Utc::now(),
+            flags: ScanFlags::from_options(options),
+            ..Self::default()
         }
     }
+
+    /// Completes the metadata once the scan has produced its entries.
+    ///
+    /// # Arguments
+    /// * `entries` - Every entry the scan discovered
+    /// * `cache_hits` - Directories served from the cache
+    /// * `cache_total` - Directories checked against the cache
+    /// * `partial` - Whether the scan stopped before covering the whole tree
+    pub fn finish(
+        mut self,
+        entries: &[FileEntry],
+        cache_hits: u64,
+        cache_total: u64,
+        partial: bool,
+    ) -> Self {
+        self.finished_at = Utc::now();
+        self.count_entries(entries);
+        self.cache_hits = cache_hits;
+        self.cache_total = cache_total;
+        self.partial = partial;
+        self
+    }
+
+    /// Recomputes the file and directory counts and the total size from
+    /// `entries`, e.g. after `--include` narrowed them down.
+    pub fn count_entries(&mut self, entries: &[FileEntry]) {
+        self.file_count = 0;
+        self.dir_count = 0;
+        self.total_size = 0;
+        for entry in entries {
+            if entry.entry_type.is_container() {
+                self.dir_count += 1;
+            } else {
+                self.file_count += 1;
+            }
+            if entry.path == self.root {
+                self.total_size = entry.size;
+            }
+        }
+    }
+
+    /// Wall-clock duration of the scan in milliseconds.
+    pub fn elapsed_ms(&self) -> u64 {
+        (self.finished_at - self.started_at)
+            .num_milliseconds()
+            .max(0) as u64
+    }
 }
 
 #[cfg(test)]
@@ -60,6 +447,9 @@ mod tests {
             owner: Some("user".to_string()),
             inodes: None,
             entry_type: EntryType::File,
+            previous_size: None,
+            mtime: None,
+            stat: None,
         };
 
         assert_eq!(entry.size, 1024);
@@ -68,9 +458,151 @@ mod tests {
         assert_eq!(entry.entry_type.as_str(), "FILE");
     }
 
+    #[test]
+    fn test_file_entry_serde_round_trip() {
+        let entry = FileEntry {
+            path: PathBuf::from("/data/link"),
+            size: 0,
+            owner: Some("alice".to_string()),
+            inodes: None,
+            entry_type: EntryType::Symlink,
+            previous_size: None,
+            mtime: Some(1_700_000_000),
+            stat: Some(Box::new(EntryStat {
+                atime: 1_700_000_100,
+                nlink: 1,
+                uid: 1000,
+                gid: 100,
+                mode: 0o120777,
+                dev: 2049,
+                ino: 42,
+                apparent_size: 7,
+            })),
+        };
+
+        let json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "path": "/data/link",
+                "size": 0,
+                "owner": "alice",
+                "inodes": null,
+                "entry_type": "Symlink",
+                "mtime": 1_700_000_000u64,
+                "stat": {
+                    "atime": 1_700_000_100u64,
+                    "nlink": 1,
+                    "uid": 1000,
+                    "gid": 100,
+                    "mode": 0o120777,
+                    "dev": 2049,
+                    "ino": 42,
+                    "apparent_size": 7
+                }
+            })
+        );
+        assert_eq!(serde_json::from_value::<FileEntry>(json).unwrap(), entry);
+
+        let minimal: FileEntry =
+            serde_json::from_str(r#"{"path":"/data","size":8192,"entry_type":"Dir"}"#).unwrap();
+        assert_eq!(minimal.entry_type, EntryType::Dir);
+        assert_eq!(
+            (minimal.owner, minimal.mtime, minimal.stat),
+            (None, None, None)
+        );
+    }
+
+    #[test]
+    fn test_scan_meta_totals() {
+        let root = PathBuf::from("/data");
+        let entries = vec![
+            FileEntry {
+                path: root.clone(),
+                size: 4096,
+                owner: None,
+                inodes: Some(2),
+                entry_type: EntryType::Dir,
+                previous_size: None,
+                mtime: None,
+                stat: None,
+            },
+            FileEntry {
+                path: root.join("a.txt"),
+                size: 4096,
+                owner: None,
+                inodes: None,
+                entry_type: EntryType::File,
+                previous_size: None,
+                mtime: None,
+                stat: None,
+            },
+        ];
+
+        let meta = ScanMeta {
+            run_id: "run".to_string(),
+            root: root.clone(),
+            ..ScanMeta::default()
+        }
+        .finish(&entries, 1, 3, true);
+
+        assert_eq!(meta.total_size, 4096);
+        assert_eq!(meta.file_count, 1);
+        assert_eq!(meta.dir_count, 1);
+        assert_eq!((meta.cache_hits, meta.cache_total), (1, 3));
+        assert!(meta.partial);
+        assert!(meta.finished_at >= meta.started_at);
+    }
+
+    #[test]
+    fn test_mount_point_describe() {
+        let mut mount = MountPoint {
+            path: PathBuf::from("/data/.snapshot"),
+            device: 42,
+            fs_type: Some("nfs".to_string()),
+            excluded: true,
+        };
+        assert_eq!(mount.describe(), "/data/.snapshot (nfs, excluded)");
+
+        mount.fs_type = None;
+        mount.excluded = false;
+        assert_eq!(mount.describe(), "/data/.snapshot (unknown type)");
+    }
+
     #[test]
     fn test_entry_type_as_str() {
         assert_eq!(EntryType::File.as_str(), "FILE");
         assert_eq!(EntryType::Dir.as_str(), "DIR");
+        assert_eq!(EntryType::Symlink.as_str(), "SYMLINK");
+        assert_eq!(EntryType::Device.as_str(), "DEVICE");
+    }
+
+    #[test]
+    fn test_entry_type_from_file_type() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let file = dir.path().join("file");
+        let link = dir.path().join("link");
+        std::fs::write(&file, "data").unwrap();
+        std::os::unix::fs::symlink(&file, &link).unwrap();
+        let socket = std::os::unix::net::UnixListener::bind(dir.path().join("socket")).unwrap();
+
+        let type_of = |path: &Path| {
+            EntryType::from_file_type(std::fs::symlink_metadata(path).unwrap().file_type())
+        };
+        assert_eq!(type_of(dir.path()), EntryType::Dir);
+        assert_eq!(type_of(&file), EntryType::File);
+        assert_eq!(type_of(&link), EntryType::Symlink);
+        assert_eq!(type_of(&dir.path().join("socket")), EntryType::Socket);
+        assert_eq!(type_of(Path::new("/dev/null")), EntryType::Device);
+        assert!(!EntryType::Symlink.is_dir());
+        drop(socket);
+    }
+
+    #[test]
+    fn test_archives_are_containers_but_not_directories() {
+        assert!(EntryType::Dir.is_dir() && EntryType::Dir.is_container());
+        assert!(!EntryType::Archive.is_dir());
+        assert!(EntryType::Archive.is_container());
+        assert!(!EntryType::File.is_container());
     }
 }
@@ -0,0 +1,132 @@
+//! Error type of the library API.
+//!
+//! Scanning, caching and writer-level output functions return
+//! [`Result<T>`], whose [`Error`] tells callers what kind of failure
+//! happened so they can match on it, e.g. to retry without the cache or
+//! report a bad pattern back to a user. The CLI-facing functions that take
+//! [`crate::Args`] keep returning `anyhow::Result`.
+//!
+//! ```no_run
+//! use rudu::{Error, ScanOptions};
+//! use rudu::scan::Scanner;
+//! use std::path::Path;
+//!
+//! match Scanner::new(ScanOptions::new()).scan(Path::new("/data")) {
+//!     Ok(result) => println!("{} entries", result.entries.len()),
+//!     Err(Error::InvalidPattern { pattern, .. }) => eprintln!("bad exclude: {pattern}"),
+//!     Err(err) => eprintln!("scan failed: {err}"),
+//! }
+//! ```
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Underlying error of the [`Error`] variants that wrap other failures
+pub type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// Result of the library API
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Failure of a library call
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// A path could not be read, such as a missing scan root or pattern file
+    Io { path: PathBuf, source: io::Error },
+    /// An exclude glob or regex does not compile
+    InvalidPattern { pattern: String, source: BoxError },
+    /// A cache file could not be located, read, encoded or written
+    Cache(BoxError),
+    /// The walk or aggregation of a scan failed
+    Scan(BoxError),
+    /// Entries could not be serialized or written
+    Output(BoxError),
+    /// The memory limit was reached and the scan stopped with partial results
+    MemoryLimitExceeded,
+    /// The scan was cancelled through its cancellation flag
+    Cancelled,
+}
+
+impl Error {
+    /// Wraps an internal error as [`Error::Cache`], unless it already is an [`Error`]
+    pub(crate) fn cache(err: anyhow::Error) -> Self {
+        Self::from_anyhow(err, Self::Cache)
+    }
+
+    /// Wraps an internal error as [`Error::Scan`], unless it already is an [`Error`]
+    pub(crate) fn scan(err: anyhow::Error) -> Self {
+        Self::from_anyhow(err, Self::Scan)
+    }
+
+    /// Wraps an internal error as [`Error::Output`], unless it already is an [`Error`]
+    pub(crate) fn output(err: anyhow::Error) -> Self {
+        Self::from_anyhow(err, Self::Output)
+    }
+
+    /// Converts an unreadable walk entry, keeping its path when it has one
+    pub(crate) fn walk(err: walkdir::Error) -> Self {
+        match err.path().map(PathBuf::from) {
+            Some(path) if err.io_error().is_some() => Self::Io {
+                path,
+                source: err.into_io_error().expect("checked above"),
+            },
+            _ => Self::Scan(err.into()),
+        }
+    }
+
+    fn from_anyhow(err: anyhow::Error, kind: fn(BoxError) -> Self) -> Self {
+        // Typed errors raised deep inside the internals keep their variant
+        match err.downcast::<Self>() {
+            Ok(err) => err,
+            Err(err) => kind(err.into()),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, .. } => write!(f, "Cannot access {}", path.display()),
+            Self::InvalidPattern { pattern, .. } => write!(f, "Invalid pattern: '{}'", pattern),
+            Self::Cache(err) | Self::Scan(err) | Self::Output(err) => err.fmt(f),
+            Self::MemoryLimitExceeded => f.write_str("Memory limit reached"),
+            Self::Cancelled => f.write_str("Scan cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::InvalidPattern { source, .. } => Some(source.as_ref()),
+            // Displayed as the wrapped error itself, so its cause comes next
+            Self::Cache(err) | Self::Scan(err) | Self::Output(err) => err.source(),
+            Self::MemoryLimitExceeded | Self::Cancelled => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_typed_errors_survive_anyhow_round_trip() {
+        let err = anyhow::Error::from(Error::Cancelled).context("while scanning");
+        assert!(matches!(Error::scan(err), Error::Cancelled));
+
+        let err = Err::<(), _>(io::Error::other("disk on fire"))
+            .context("Failed to encode cache data")
+            .unwrap_err();
+        let err = Error::cache(err);
+        assert!(matches!(err, Error::Cache(_)));
+        assert_eq!(err.to_string(), "Failed to encode cache data");
+        assert_eq!(
+            std::error::Error::source(&err).unwrap().to_string(),
+            "disk on fire"
+        );
+    }
+}
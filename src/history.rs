@@ -0,0 +1,312 @@
+//! History of directory totals across runs (`history` feature).
+//!
+//! Every saved [`Snapshot`] also adds the totals of its root and the
+//! directories below it, up to a depth, to an SQLite database, so sizes can
+//! be followed over weeks and months without keeping every snapshot around.
+//! `rudu report trend PATH` reads it back as one [`Trend`] per directory:
+//! growth per day from a least-squares fit over the recorded runs, and when
+//! a quota will be reached at that rate.
+//!
+//! The database is `$RUDU_HISTORY_DB`, by default `history.sqlite` in the
+//! [`data_dir`](crate::snapshot::data_dir), and holds the history of every
+//! scanned root.
+
+use crate::snapshot::{Snapshot, absolute_root, data_dir};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, params};
+use std::path::{Path, PathBuf};
+
+/// Seconds per day, for growth rates
+const DAY: f64 = 24.0 * 60.0 * 60.0;
+
+/// Tables of the history database; runs are recorded once per run ID
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS runs (
+        id INTEGER PRIMARY KEY,
+        run_id TEXT NOT NULL UNIQUE,
+        root TEXT NOT NULL,
+        taken_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS runs_by_root ON runs (root, taken_at);
+    CREATE TABLE IF NOT EXISTS totals (
+        run INTEGER NOT NULL REFERENCES runs (id),
+        path TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        PRIMARY KEY (run, path)
+    ) WITHOUT ROWID;
+";
+
+/// Path of the history database: `$RUDU_HISTORY_DB`, or `history.sqlite`
+/// in the data directory.
+pub fn history_path() -> PathBuf {
+    match std::env::var_os("RUDU_HISTORY_DB") {
+        Some(path) => PathBuf::from(path),
+        None => data_dir().join("history.sqlite"),
+    }
+}
+
+/// An open history database
+pub struct History {
+    conn: Connection,
+}
+
+/// Sizes of one directory over the recorded runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trend {
+    /// Absolute path of the directory
+    pub path: PathBuf,
+    /// Start time and size in bytes of each run, oldest first
+    pub samples: Vec<(DateTime<Utc>, u64)>,
+}
+
+impl Trend {
+    /// Size in the newest run
+    pub fn latest(&self) -> u64 {
+        self.samples.last().map_or(0, |&(_, size)| size)
+    }
+
+    /// Change in bytes from the oldest to the newest run
+    pub fn change(&self) -> i64 {
+        match (self.samples.first(), self.samples.last()) {
+            (Some(&(_, first)), Some(&(_, last))) => last as i64 - first as i64,
+            _ => 0,
+        }
+    }
+
+    /// Growth in bytes per day, the slope of a least-squares line through
+    /// the samples; `None` with fewer than two runs at different times.
+    pub fn growth_per_day(&self) -> Option<f64> {
+        let n = self.samples.len() as f64;
+        let origin = self.samples.first()?.0;
+        let points: Vec<(f64, f64)> = self
+            .samples
+            .iter()
+            .map(|&(at, size)| ((at - origin).num_seconds() as f64 / DAY, size as f64))
+            .collect();
+        let mean_t = points.iter().map(|(t, _)| t).sum::<f64>() / n;
+        let mean_size = points.iter().map(|(_, size)| size).sum::<f64>() / n;
+        let variance: f64 = points.iter().map(|(t, _)| (t - mean_t).powi(2)).sum();
+        if variance == 0.0 {
+            return None;
+        }
+        let covariance: f64 = points
+            .iter()
+            .map(|(t, size)| (t - mean_t) * (size - mean_size))
+            .sum();
+        Some(covariance / variance)
+    }
+
+    /// When the directory reaches `quota` bytes, growing at
+    /// [`growth_per_day`](Trend::growth_per_day) from its newest size.
+    ///
+    /// The newest run's time if it is already there, and `None` if it is
+    /// not growing.
+    pub fn full_at(&self, quota: u64) -> Option<DateTime<Utc>> {
+        let &(last_at, last_size) = self.samples.last()?;
+        if last_size >= quota {
+            return Some(last_at);
+        }
+        let per_day = self.growth_per_day().filter(|&rate| rate > 0.0)?;
+        let days = (quota - last_size) as f64 / per_day;
+        // Rates of a few bytes per day would overflow the date range
+        let seconds = i64::try_from((days * DAY) as u64).ok()?;
+        last_at.checked_add_signed(chrono::TimeDelta::try_seconds(seconds)?)
+    }
+}
+
+impl History {
+    /// Opens the database at `path`, creating it if needed.
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open history {}", path.display()))?;
+        conn.execute_batch(SCHEMA)
+            .with_context(|| format!("Failed to set up history {}", path.display()))?;
+        Ok(Self { conn })
+    }
+
+    /// Opens the database at [`history_path`].
+    pub fn open_default() -> Result<Self> {
+        Self::open(&history_path())
+    }
+
+    /// Adds the totals of the snapshot's root and the directories at most
+    /// `depth` levels below it, returning how many were added.
+    ///
+    /// A run already recorded (by run ID) is skipped.
+    pub fn record(&mut self, snapshot: &Snapshot, depth: usize) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        let inserted = tx.execute(
+            "INSERT OR IGNORE INTO runs (run_id, root, taken_at) VALUES (?1, ?2, ?3)",
+            params![
+                snapshot.run_id,
+                snapshot.root.to_string_lossy(),
+                snapshot.taken_at.timestamp()
+            ],
+        )?;
+        if inserted == 0 {
+            return Ok(0);
+        }
+        let run = tx.last_insert_rowid();
+        let mut added = 0;
+        {
+            let mut insert =
+                tx.prepare("INSERT INTO totals (run, path, size) VALUES (?1, ?2, ?3)")?;
+            for (path, &size) in &snapshot.dirs {
+                if path.components().count() <= depth {
+                    insert.execute(params![run, path.to_string_lossy(), size as i64])?;
+                    added += 1;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(added)
+    }
+
+    /// The trends of `path` and of its recorded subdirectories, from runs
+    /// since `since` (seconds since the epoch), or `None` if no recorded
+    /// root contains `path`.
+    ///
+    /// `path` comes first, followed by its subdirectories by path.
+    pub fn trends(&self, path: &Path, since: Option<u64>) -> Result<Option<Vec<Trend>>> {
+        let path = absolute_root(path);
+        let roots: Vec<String> = self
+            .conn
+            .prepare("SELECT DISTINCT root FROM runs")?
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        // The innermost root holding `path` has the deepest totals for it
+        let Some(root) = roots
+            .iter()
+            .map(PathBuf::from)
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.components().count())
+        else {
+            return Ok(None);
+        };
+        let relative = path.strip_prefix(&root)?.to_path_buf();
+
+        let mut query = self.conn.prepare(
+            "SELECT runs.taken_at, totals.path, totals.size
+             FROM totals JOIN runs ON totals.run = runs.id
+             WHERE runs.root = ?1 AND runs.taken_at >= ?2
+             ORDER BY runs.taken_at",
+        )?;
+        let rows = query.query_map(
+            params![root.to_string_lossy(), since.unwrap_or(0) as i64],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    PathBuf::from(row.get::<_, String>(1)?),
+                    row.get::<_, i64>(2)?,
+                ))
+            },
+        )?;
+
+        let mut trends: Vec<Trend> = Vec::new();
+        for row in rows {
+            let (taken_at, dir, size) = row?;
+            if dir != relative && dir.parent() != Some(relative.as_path()) {
+                continue;
+            }
+            let Some(taken_at) = DateTime::from_timestamp(taken_at, 0) else {
+                continue;
+            };
+            let dir = if dir.as_os_str().is_empty() {
+                root.clone()
+            } else {
+                root.join(dir)
+            };
+            let sample = (taken_at, size.max(0) as u64);
+            match trends.iter_mut().find(|trend| trend.path == dir) {
+                Some(trend) => trend.samples.push(sample),
+                None => trends.push(Trend {
+                    path: dir,
+                    samples: vec![sample],
+                }),
+            }
+        }
+        trends.sort_by(|a, b| {
+            (a.path != path)
+                .cmp(&(b.path != path))
+                .then(a.path.cmp(&b.path))
+        });
+        Ok(Some(trends))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::collections::HashMap;
+
+    fn snapshot(run: &str, day: u32, dirs: &[(&str, u64)]) -> Snapshot {
+        Snapshot {
+            root: PathBuf::from("/data"),
+            taken_at: Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap(),
+            run_id: run.to_string(),
+            dirs: dirs
+                .iter()
+                .map(|(path, size)| (PathBuf::from(path), *size))
+                .collect::<HashMap<_, _>>(),
+        }
+    }
+
+    #[test]
+    fn test_record_and_trend() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut history = History::open(&temp_dir.path().join("history.sqlite")).unwrap();
+        for (run, day, size) in [("a", 1, 1000), ("b", 2, 2000), ("c", 3, 3000)] {
+            let dirs = [
+                ("", size + 10),
+                ("proj", size),
+                ("tmp", 10),
+                ("proj/deep", 5),
+            ];
+            assert_eq!(history.record(&snapshot(run, day, &dirs), 1).unwrap(), 3);
+        }
+        // Recording the same run again adds nothing
+        assert_eq!(history.record(&snapshot("c", 3, &[("", 1)]), 1).unwrap(), 0);
+
+        let trends = history.trends(Path::new("/data"), None).unwrap().unwrap();
+        let paths: Vec<&Path> = trends.iter().map(|t| t.path.as_path()).collect();
+        assert_eq!(
+            paths,
+            [
+                Path::new("/data"),
+                Path::new("/data/proj"),
+                Path::new("/data/tmp")
+            ]
+        );
+        let proj = &trends[1];
+        assert_eq!(proj.latest(), 3000);
+        assert_eq!(proj.change(), 2000);
+        assert_eq!(proj.growth_per_day(), Some(1000.0));
+        assert_eq!(
+            proj.full_at(10_000),
+            Some(Utc.with_ymd_and_hms(2024, 1, 10, 0, 0, 0).unwrap())
+        );
+        assert_eq!(trends[2].full_at(10_000), None);
+
+        let since = Utc
+            .with_ymd_and_hms(2024, 1, 2, 0, 0, 0)
+            .unwrap()
+            .timestamp() as u64;
+        let recent = history
+            .trends(Path::new("/data/proj"), Some(since))
+            .unwrap()
+            .unwrap();
+        assert_eq!(recent[0].samples.len(), 2);
+        assert!(
+            history
+                .trends(Path::new("/elsewhere"), None)
+                .unwrap()
+                .is_none()
+        );
+    }
+}
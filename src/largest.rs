@@ -0,0 +1,128 @@
+//! The largest files of a scan (`--largest-files N`).
+//!
+//! [`LargestFiles`] keeps the `N` biggest files offered to it in a bounded
+//! min-heap, so finding them takes memory for `N` entries however large the
+//! tree is. Scans fill one from every file they measure, before depth
+//! filtering and even with `summarize`, where no file entries are kept
+//! otherwise (see [`ScanOptions::largest_files`](crate::ScanOptions)).
+
+use crate::data::FileEntry;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::path::Path;
+
+/// A file ranked by size; among equal sizes the smaller path ranks higher,
+/// so which files are kept does not depend on the order they were seen in.
+#[derive(Debug)]
+struct Ranked(FileEntry);
+
+impl Ranked {
+    fn outranks(&self, size: u64, path: &Path) -> bool {
+        self.0.size > size || (self.0.size == size && self.0.path.as_path() < path)
+    }
+}
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .size
+            .cmp(&other.0.size)
+            .then_with(|| other.0.path.cmp(&self.0.path))
+    }
+}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Ranked {}
+
+/// The `limit` largest files offered so far
+#[derive(Debug)]
+pub struct LargestFiles {
+    limit: usize,
+    heap: BinaryHeap<Reverse<Ranked>>,
+}
+
+impl LargestFiles {
+    /// Keeps the `limit` largest files.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            heap: BinaryHeap::with_capacity(limit.saturating_add(1).min(1 << 16)),
+        }
+    }
+
+    /// Whether a file of `size` bytes at `path` would be kept, so callers
+    /// can skip building entries (and looking up owners) for the rest.
+    pub fn would_keep(&self, size: u64, path: &Path) -> bool {
+        if self.heap.len() < self.limit {
+            return true;
+        }
+        self.heap
+            .peek()
+            .is_some_and(|Reverse(smallest)| !smallest.outranks(size, path))
+    }
+
+    /// Offers `entry`, keeping it if it is among the largest so far.
+    pub fn offer(&mut self, entry: FileEntry) {
+        if !self.would_keep(entry.size, &entry.path) {
+            return;
+        }
+        self.heap.push(Reverse(Ranked(entry)));
+        if self.heap.len() > self.limit {
+            self.heap.pop();
+        }
+    }
+
+    /// The kept files, largest first.
+    pub fn into_sorted(self) -> Vec<FileEntry> {
+        // Ascending order of `Reverse` is descending order of size
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(Ranked(entry))| entry)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::EntryType;
+
+    #[test]
+    fn test_keeps_largest_in_order() {
+        let mut largest = LargestFiles::new(3);
+        for (path, size) in [
+            ("/a", 5),
+            ("/b", 50),
+            ("/c", 1),
+            ("/d", 20),
+            ("/e", 20),
+            ("/f", 7),
+        ] {
+            largest.offer(FileEntry::for_test(path, size, EntryType::File));
+        }
+        assert!(!largest.would_keep(7, Path::new("/z")));
+        assert!(largest.would_keep(20, Path::new("/0")));
+        let sorted = largest.into_sorted();
+        let kept: Vec<(&str, u64)> = sorted
+            .iter()
+            .map(|e| (e.path.to_str().unwrap(), e.size))
+            .collect();
+        assert_eq!(kept, [("/b", 50), ("/d", 20), ("/e", 20)]);
+
+        let mut none = LargestFiles::new(0);
+        none.offer(FileEntry::for_test("/a", 1, EntryType::File));
+        assert!(none.into_sorted().is_empty());
+    }
+}
@@ -9,25 +9,110 @@
 //! - **Data Structures**: Core types like `FileEntry` for representing filesystem entries
 //! - **Utilities**: Helper functions for disk usage calculation and file processing
 //!
+//! # Cargo features
+//!
+//! - `cli` (default): the [`archive`], [`budget`], [`cli`], [`output`], [`report`] and
+//!   [`rpc`] modules used by the `rudu` binary; enables `progress` and `sysinfo`
+//! - `progress`: terminal spinners while scanning (indicatif)
+//! - `sysinfo`: RSS readings for memory limits and profiles
+//! - `parquet`, `xlsx`: extra output formats
+//! - `serve`: the `rudu serve` HTTP API (tiny_http)
+//! - `history`: the trend history of directory totals (SQLite, bundled)
+//! - `lustre`: the `lustre` module, stripe layouts read by ioctl
+//! - `s3`: the `s3` module, S3 and MinIO buckets as scan roots (ureq)
+//! - `alerts`: the `alert` module, size and growth alerts by webhook (ureq)
+//!   or email
+//! - `async`: the `stream` module
+//!
+//! With `default-features = false`, the scan, cache and data modules build
+//! without clap, indicatif, humansize, csv and sysinfo.
+//!
 //! # Modules
 //!
+//! - `alert`: Size and growth alerts sent by webhook or SMTP for `--alert-threshold` (`alerts` feature)
+//! - [`archive`]: Tar and zip members listed as a virtual subtree for `--scan-archives` (`cli` feature)
+//! - [`budget`]: Per-directory size and inode budgets checked by `--budgets` (`cli` feature)
 //! - [`cache`]: Disk-based caching system for improved performance
+//! - [`cleanup`]: Deleting selected entries to the trash or permanently (`--delete-selected`)
 //! - [`data`]: Core data structures (`FileEntry`, `EntryType`)
-//! - [`cli`]: Command-line interface definitions
-//! - [`output`]: Modular output formatters (terminal, CSV)
+//! - [`error`]: [`Error`] returned by the library API, matchable by failure kind
+//! - [`daemon`]: Output naming, retention and systemd notification for `rudu daemon`
+//! - [`deleted`]: Space held by deleted-but-open files (Linux `/proc` scan)
+//! - [`cli`]: Command-line interface definitions (`cli` feature)
+//! - `history`: SQLite history of directory totals for `rudu report trend` (`history` feature)
+//! - [`largest`]: Bounded min-heap of the largest files for `--largest-files`
+//! - `lustre`: Lustre stripe layouts and OST pools for `--report lustre-pools` (`lustre` feature)
+//! - [`logging`]: Stderr logger for status messages, with `-q`/`-v` levels
+//! - [`manifest`]: Run manifest listing every artifact written with checksums
+//! - [`options`]: [`ScanOptions`] builder for scans, independent of the CLI
+//! - [`output`]: Modular output formatters (terminal, CSV) (`cli` feature)
+//! - [`progress`]: Progress observers for scans, such as the terminal spinner
+//! - [`quota`]: XFS project quotas and user quota files for `--report project-quota` and `user-quota`
+//! - [`report`]: Aggregate usage reports such as per-owner totals (`cli` feature)
+//! - [`rpc`]: JSON-RPC interface over stdin/stdout (`cli` feature)
+//! - `s3`: Signed `ListObjectsV2` listings of `s3://bucket/prefix` roots as scan entries (`s3` feature)
 //! - [`scan`]: File system scanning functionality
+//! - `serve`: HTTP API over the latest scan for `rudu serve` (`serve` feature)
+//! - [`snapshot`]: Saved per-directory sizes of scans and the changes between them (`rudu diff`)
+//! - [`spill`]: Temporary on-disk entry store used when memory nears the limit
+//! - `stream`: Async [`Stream`](futures_core::Stream) of scan entries for tokio services (`async` feature)
+//! - [`testing`]: Synthetic directory trees ([`TreeSpec`](testing::TreeSpec)) for benchmarks and regression tests
 //! - [`thread_pool`]: Thread pool configuration strategies for performance optimization
+//! - [`tuning`]: Thread strategy and stat batching chosen from the root's filesystem type
+//! - [`tree`]: Scan results as a [`DirNode`](tree::DirNode) tree with own and cumulative sizes
 //! - [`utils`]: Utility functions for disk usage and file operations
+//! - [`visit`]: [`EntryVisitor`](visit::EntryVisitor) for custom statistics gathered during a scan
 
+#[cfg(feature = "alerts")]
+pub mod alert;
+#[cfg(feature = "cli")]
+pub mod archive;
+#[cfg(feature = "cli")]
+pub mod budget;
 pub mod cache;
+pub mod cleanup;
+#[cfg(feature = "cli")]
 pub mod cli;
+pub mod daemon;
 pub mod data;
+pub mod deleted;
+pub mod error;
+#[cfg(feature = "history")]
+pub mod history;
+pub mod largest;
+pub mod logging;
+#[cfg(feature = "lustre")]
+pub mod lustre;
+pub mod manifest;
 pub mod memory;
 pub mod metrics;
+pub mod options;
+#[cfg(feature = "cli")]
 pub mod output;
+pub mod progress;
+pub mod quota;
+#[cfg(feature = "cli")]
+pub mod report;
+#[cfg(feature = "cli")]
+pub mod rpc;
+#[cfg(feature = "s3")]
+pub mod s3;
 pub mod scan;
+#[cfg(feature = "serve")]
+pub mod serve;
+pub mod snapshot;
+pub mod spill;
+#[cfg(feature = "async")]
+pub mod stream;
+pub mod testing;
 pub mod thread_pool;
+pub mod tree;
+pub mod tuning;
 pub mod utils;
+pub mod visit;
 
+#[cfg(feature = "cli")]
 pub use cli::Args;
-pub use data::{EntryType, FileEntry};
+pub use data::{EntryType, FileEntry, SortKey};
+pub use error::{Error, Result};
+pub use options::ScanOptions;
@@ -0,0 +1,85 @@
+//! Status and diagnostic messages of the `rudu` binary.
+//!
+//! Scan, cache, output and CLI code report what they are doing through the [`log`]
+//! facade rather than printing directly. The binary installs [`StderrLogger`],
+//! which writes each record to stderr as a plain line, at a level chosen by
+//! `-q`/`-v`:
+//!
+//! | Flags | Shown |
+//! |-------|-------|
+//! | `-q` | Errors only |
+//! | (none) | Errors, warnings and status messages |
+//! | `-v` | Also debug details |
+//! | `-vv` | Everything, including trace output |
+//!
+//! Library users see nothing unless they install a logger of their own.
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::io::Write;
+
+/// Logger writing records to stderr, one line each
+///
+/// Errors, warnings and status messages are written as-is; debug and trace
+/// records are prefixed with their level. Below the info level, only records
+/// from rudu itself are shown, so dependencies do not flood `-vv` output.
+pub struct StderrLogger;
+
+static LOGGER: StderrLogger = StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+            && (metadata.level() <= Level::Info || metadata.target().starts_with("rudu"))
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let mut stderr = std::io::stderr().lock();
+        let _ = match record.level() {
+            Level::Error | Level::Warn | Level::Info => writeln!(stderr, "{}", record.args()),
+            level => writeln!(
+                stderr,
+                "[{}] {}",
+                level.as_str().to_lowercase(),
+                record.args()
+            ),
+        };
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stderr().flush();
+    }
+}
+
+/// The most detailed level shown for `--quiet` and the number of `-v` flags.
+pub fn level_filter(quiet: bool, verbose: u8) -> LevelFilter {
+    match (quiet, verbose) {
+        (true, _) => LevelFilter::Error,
+        (false, 0) => LevelFilter::Info,
+        (false, 1) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace,
+    }
+}
+
+/// Installs [`StderrLogger`] showing records up to `level`.
+///
+/// Only the first call installs the logger; later calls just change the level.
+pub fn init(level: LevelFilter) {
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(level);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_filter() {
+        assert_eq!(level_filter(true, 2), LevelFilter::Error);
+        assert_eq!(level_filter(false, 0), LevelFilter::Info);
+        assert_eq!(level_filter(false, 1), LevelFilter::Debug);
+        assert_eq!(level_filter(false, 3), LevelFilter::Trace);
+    }
+}
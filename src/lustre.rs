@@ -0,0 +1,179 @@
+//! Lustre stripe layouts (`lustre` feature).
+//!
+//! A Lustre file is split into stripes of `stripe_size` bytes spread round
+//! robin over `stripe_count` objects, each on an OST (object storage
+//! target), optionally restricted to a named OST pool. [`file_layout`] reads
+//! a file's layout with the `LL_IOC_LOV_GETSTRIPE` ioctl, without linking
+//! `liblustreapi`; for composite (PFL) layouts the kernel returns the last
+//! instantiated component, which is the one holding the end of the file.
+//!
+//! `--report lustre-pools` and `--report lustre-files` use it to total
+//! usage per pool and to list large files on too few stripes, which put
+//! the whole load of reading them on a handful of OSTs.
+
+use crate::data::{EntryType, FileEntry};
+use rayon::prelude::*;
+use std::path::Path;
+
+/// Bytes one stripe object may hold before a file counts as poorly
+/// striped; larger files should be spread over more OSTs
+pub const MAX_BYTES_PER_STRIPE: u64 = 16 * 1024 * 1024 * 1024;
+
+/// `LOV_USER_MAGIC_V1` from `<lustre/lustre_user.h>`
+const LOV_USER_MAGIC_V1: u32 = 0x0BD1_0BD0;
+
+/// `LOV_USER_MAGIC_V3`, which adds the pool name
+const LOV_USER_MAGIC_V3: u32 = 0x0BD3_0BD0;
+
+/// Size of the `lov_user_md_v1` header
+const HEADER_V1: usize = 32;
+
+/// Size of the `lov_user_md_v3` header, with its 16-byte pool name
+const HEADER_V3: usize = 48;
+
+/// Size of one `lov_user_ost_data_v1` object entry
+const OST_DATA: usize = 24;
+
+/// `LOV_MAX_STRIPE_COUNT`: the most objects a layout can have
+const MAX_STRIPE_COUNT: u16 = 2000;
+
+/// Striping of one file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StripeLayout {
+    /// Number of stripe objects
+    pub stripe_count: u32,
+    /// Bytes written to one object before moving to the next
+    pub stripe_size: u64,
+    /// OST pool the objects were allocated from, if any
+    pub pool: Option<String>,
+    /// Index of the OST holding each object
+    pub osts: Vec<u32>,
+}
+
+impl StripeLayout {
+    /// Whether a file of `size` bytes puts more than
+    /// [`MAX_BYTES_PER_STRIPE`] on each of its objects
+    pub fn is_poorly_striped(&self, size: u64) -> bool {
+        size / u64::from(self.stripe_count.max(1)) > MAX_BYTES_PER_STRIPE
+    }
+}
+
+/// Reads the stripe layout of the Lustre file at `path`.
+#[cfg(target_os = "linux")]
+pub fn file_layout(path: &Path) -> std::io::Result<StripeLayout> {
+    use std::os::fd::AsRawFd;
+
+    /// `LL_IOC_LOV_GETSTRIPE`: `_IOW('f', 155, long)`
+    const LL_IOC_LOV_GETSTRIPE: libc::c_ulong = 0x4008_669b;
+
+    let file = std::fs::File::open(path)?;
+    // Room for the largest layout; the request names the format and size
+    let mut buf = vec![0u8; HEADER_V3 + usize::from(MAX_STRIPE_COUNT) * OST_DATA];
+    buf[..4].copy_from_slice(&LOV_USER_MAGIC_V3.to_ne_bytes());
+    buf[28..30].copy_from_slice(&MAX_STRIPE_COUNT.to_ne_bytes());
+    let result = unsafe {
+        libc::ioctl(
+            file.as_raw_fd(),
+            LL_IOC_LOV_GETSTRIPE as _,
+            buf.as_mut_ptr(),
+        )
+    };
+    if result != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    parse_layout(&buf).ok_or_else(|| std::io::ErrorKind::InvalidData.into())
+}
+
+/// Fallback for platforms without Lustre.
+#[cfg(not(target_os = "linux"))]
+pub fn file_layout(_path: &Path) -> std::io::Result<StripeLayout> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+/// Decodes a `lov_user_md_v1` or `lov_user_md_v3` in native byte order.
+pub fn parse_layout(buf: &[u8]) -> Option<StripeLayout> {
+    let u32_at = |at: usize| Some(u32::from_ne_bytes(buf.get(at..at + 4)?.try_into().ok()?));
+    let u16_at = |at: usize| Some(u16::from_ne_bytes(buf.get(at..at + 2)?.try_into().ok()?));
+
+    let (header, pool) = match u32_at(0)? {
+        LOV_USER_MAGIC_V1 => (HEADER_V1, None),
+        LOV_USER_MAGIC_V3 => {
+            let name = buf.get(HEADER_V1..HEADER_V3)?;
+            let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+            let pool = (!name.is_empty()).then(|| String::from_utf8_lossy(name).into_owned());
+            (HEADER_V3, pool)
+        }
+        _ => return None,
+    };
+    let stripe_size = u64::from(u32_at(24)?);
+    let stripe_count = u16_at(28)?;
+    // Released (HSM) files keep their stripe count without any objects
+    let objects = buf.get(header..).map_or(0, |rest| rest.len() / OST_DATA);
+    let osts = (0..usize::from(stripe_count).min(objects))
+        .filter_map(|i| u32_at(header + i * OST_DATA + 20))
+        .collect();
+    Some(StripeLayout {
+        stripe_count: u32::from(stripe_count),
+        stripe_size,
+        pool,
+        osts,
+    })
+}
+
+/// The layouts of the regular files among `entries`, read in parallel;
+/// files whose layout cannot be read are left out.
+pub fn layouts(entries: &[FileEntry]) -> Vec<(&FileEntry, StripeLayout)> {
+    entries
+        .par_iter()
+        .filter(|e| e.entry_type == EntryType::File)
+        .filter_map(|e| match file_layout(&e.path) {
+            Ok(layout) => Some((e, layout)),
+            Err(err) => {
+                log::debug!("No stripe layout for {}: {}", e.path.display(), err);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout_v3(stripe_count: u16, pool: &str, osts: &[u32]) -> Vec<u8> {
+        let mut buf = vec![0u8; HEADER_V3 + osts.len() * OST_DATA];
+        buf[..4].copy_from_slice(&LOV_USER_MAGIC_V3.to_ne_bytes());
+        buf[24..28].copy_from_slice(&(1u32 << 20).to_ne_bytes());
+        buf[28..30].copy_from_slice(&stripe_count.to_ne_bytes());
+        buf[HEADER_V1..HEADER_V1 + pool.len()].copy_from_slice(pool.as_bytes());
+        for (i, ost) in osts.iter().enumerate() {
+            let at = HEADER_V3 + i * OST_DATA + 20;
+            buf[at..at + 4].copy_from_slice(&ost.to_ne_bytes());
+        }
+        buf
+    }
+
+    #[test]
+    fn test_parse_layout() {
+        let layout = parse_layout(&layout_v3(2, "flash", &[3, 7])).unwrap();
+        assert_eq!(
+            layout,
+            StripeLayout {
+                stripe_count: 2,
+                stripe_size: 1 << 20,
+                pool: Some("flash".to_string()),
+                osts: vec![3, 7],
+            }
+        );
+        assert!(!layout.is_poorly_striped(MAX_BYTES_PER_STRIPE * 2));
+        assert!(layout.is_poorly_striped(MAX_BYTES_PER_STRIPE * 2 + 2));
+
+        let mut v1 = layout_v3(1, "", &[]);
+        v1[..4].copy_from_slice(&LOV_USER_MAGIC_V1.to_ne_bytes());
+        let released = parse_layout(&v1[..HEADER_V1]).unwrap();
+        assert_eq!((released.stripe_count, released.pool), (1, None));
+        assert!(released.osts.is_empty());
+
+        assert_eq!(parse_layout(&[0u8; HEADER_V3]), None);
+    }
+}
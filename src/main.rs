@@ -24,35 +24,77 @@
 //! - [`scan`] - file system traversal and size aggregation
 //! - [`utils`] - helpers for file metadata, ownership, and pattern matching
 
-use anyhow::Result;
-use clap::Parser;
-use std::path::Path;
+use anyhow::{Context, Result};
+use std::borrow::Cow;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 
 mod utils;
-use utils::{build_exclude_matcher, expand_exclude_patterns, path_depth};
+use utils::{
+    SLOW_FILESYSTEMS, build_exclude_matcher, containing_fs_type, containing_mount,
+    expand_exclude_patterns, filter_by_gid, filter_by_mtime, filter_included, format_count,
+    paginate, path_depth, read_pattern_file, top_entries,
+};
 mod scan;
 use scan::scan_files_and_dirs;
 pub mod cli;
-use cli::Args;
+use cli::{Args, CacheCommand, Command, OutputFormat, ReportKind, SortKey};
+use options::ScanOptions;
+use output::columns::{Column, ColumnSpec};
+use output::paths::PathStyle;
 mod data;
-pub use data::{EntryType, FileEntry};
+pub use data::{EntryType, FileEntry, ScanMeta};
+#[cfg(feature = "alerts")]
+mod alert;
+mod archive;
+mod budget;
 pub mod cache;
+pub mod cleanup;
+pub mod daemon;
+pub mod deleted;
+pub mod error;
+#[cfg(feature = "history")]
+pub mod history;
+mod largest;
+pub mod logging;
+#[cfg(feature = "lustre")]
+mod lustre;
+pub mod manifest;
 mod memory;
 pub mod metrics;
+pub mod options;
 pub mod output;
+pub mod progress;
+mod quota;
+pub mod report;
+pub mod rpc;
+#[cfg(feature = "s3")]
+mod s3;
+#[cfg(feature = "serve")]
+pub mod serve;
+pub mod snapshot;
+mod spill;
 pub mod thread_pool;
+#[cfg(feature = "serve")]
+pub mod tree;
+mod tuning;
+pub mod visit;
+use manifest::{MANIFEST_FILE_NAME, Manifest};
 use metrics::{PhaseTimer, ProfileData, print_profile_summary, rss_after_phase, save_stats_json};
+use snapshot::Snapshot;
+use spill::SpilledEntries;
 use thread_pool::{ThreadPoolStrategy, configure_pool};
 
+/// Entries in the last scan above which a new scan asks for confirmation
+const LARGE_SCAN_ENTRIES: u64 = 10_000_000;
+
 /// Sets up the thread pool configuration based on CLI arguments.
 fn setup_thread_pool(args: &Args) -> Result<()> {
     // Skip global thread pool setup when --threads is specified
     // as we'll use local thread pools in the scan module instead
-    if args.threads.is_some() {
-        eprintln!(
-            "Using local thread pool with {} threads",
-            args.threads.unwrap()
-        );
+    if let Some(n) = args.threads {
+        log::debug!("Using local thread pool with {} threads", n);
+        thread_pool::record_local_pool(args.threads_strategy, n);
         return Ok(());
     }
 
@@ -61,7 +103,7 @@ fn setup_thread_pool(args: &Args) -> Result<()> {
         ThreadPoolStrategy::Default => num_cpus::get(),
         ThreadPoolStrategy::Fixed => {
             if args.threads.is_none() {
-                eprintln!(
+                log::warn!(
                     "Warning: --threads-strategy fixed requires --threads N; \
                      falling back to all CPUs."
                 );
@@ -77,37 +119,1333 @@ fn setup_thread_pool(args: &Args) -> Result<()> {
     Ok(())
 }
 
-/// Processes raw file entries by applying depth filtering, sorting, and show_files flags.
+/// Tunes the thread strategy, stat batching and attribute caching for the
+/// filesystem holding `root`, keeping whatever was set explicitly, and logs
+/// the decision.
+fn apply_fs_tuning(root: &Path, args: &mut Args) {
+    let Some(fs_type) = tuning::detect_fs_type(root) else {
+        return;
+    };
+    let tuning = tuning::FsTuning::for_fs_type(&fs_type);
+    let mut applied = Vec::new();
+    if args.threads.is_none() && args.threads_strategy == ThreadPoolStrategy::Default {
+        args.threads_strategy = tuning.threads_strategy;
+        applied.push(format!("{} threads", tuning.threads_strategy.as_str()));
+    }
+    if args.stat_batch.is_none() {
+        args.stat_batch = Some(tuning.stat_batch);
+        applied.push(format!("stat batches of {}", tuning.stat_batch));
+    }
+    if args.cached_attrs.is_none() {
+        args.cached_attrs = Some(tuning.cached_attrs);
+        if tuning.cached_attrs {
+            applied.push("cached attributes".to_string());
+        }
+    }
+    if !applied.is_empty() {
+        log::info!(
+            "{} is on {} ({} filesystem): using {} (--no-fs-tuning to keep the defaults)",
+            root.display(),
+            tuning.fs_type,
+            tuning.class.as_str(),
+            applied.join(", ")
+        );
+    }
+    if args.cached_attrs == Some(true)
+        && containing_mount(root).is_some_and(|mount| mount.has_option("nordirplus"))
+    {
+        log::warn!(
+            "{} is mounted nordirplus: listings return no attributes to reuse, so every \
+             file still costs a round trip",
+            root.display()
+        );
+    }
+    if args.no_cache
+        && matches!(
+            tuning.class,
+            tuning::FsClass::Network | tuning::FsClass::Parallel
+        )
+    {
+        log::warn!(
+            "--no-cache on {} stats every file over the network; repeat scans are much \
+             faster with the cache",
+            tuning.fs_type
+        );
+    }
+}
+
+/// Whether `path` names an S3 bucket or prefix (`s3://bucket/prefix`)
+fn is_s3_path(path: &Path) -> bool {
+    path.to_str().is_some_and(|path| path.starts_with("s3://"))
+}
+
+/// Lists the objects below the `s3://` location `root` as scan entries.
+#[cfg(feature = "s3")]
+fn scan_s3(
+    root: &Path,
+    options: &ScanOptions,
+    exclude: &utils::PathMatcher,
+    sort_keys: &[SortKey],
+) -> Result<scan::ScanResult> {
+    let Some(location) = s3::S3Location::parse(root) else {
+        anyhow::bail!(
+            "Invalid S3 location {}: expected s3://bucket/prefix",
+            root.display()
+        );
+    };
+    let config = s3::S3Config::from_env();
+    if config.credentials.is_none() {
+        log::warn!(
+            "AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY are not set; listing {} anonymously",
+            root.display()
+        );
+    }
+    log::info!("Listing {} from {}", root.display(), config.endpoint);
+    s3::scan(&location, &config, options, exclude, sort_keys)
+}
+
+/// Fallback for builds without the `s3` feature.
+#[cfg(not(feature = "s3"))]
+fn scan_s3(
+    root: &Path,
+    _options: &ScanOptions,
+    _exclude: &utils::PathMatcher,
+    _sort_keys: &[SortKey],
+) -> Result<scan::ScanResult> {
+    anyhow::bail!(
+        "{} is an S3 location; rebuild rudu with --features s3 to scan buckets",
+        root.display()
+    )
+}
+
+/// Processes raw file entries by applying depth filtering, size thresholds, and the
+/// summarize, show_files, files_only and dirs_only flags.
 fn process_entries(root: &Path, args: &Args, raw: Vec<FileEntry>) -> Vec<FileEntry> {
     raw.into_iter()
-        .filter(|entry| {
-            // Apply depth filtering
-            let depth = path_depth(root, &entry.path);
-            match entry.entry_type {
-                EntryType::Dir => args.depth.map(|d| depth <= d).unwrap_or(true),
-                EntryType::File => {
-                    args.show_files && args.depth.map(|d| depth <= d).unwrap_or(true)
-                }
-            }
-        })
+        .filter(|entry| is_listed(root, args, entry))
         .collect()
 }
 
-/// Outputs the results either to CSV file or terminal based on CLI arguments.
+/// Whether `entry` passes the per-entry filters of [`process_entries`].
+fn is_listed(root: &Path, args: &Args, entry: &FileEntry) -> bool {
+    if args.summarize && entry.path != root {
+        return false;
+    }
+    // Apply depth filtering
+    let depth = path_depth(root, &entry.path);
+    let within_depth = args.depth.map(|d| depth <= d).unwrap_or(true);
+    let shown = if entry.entry_type.is_container() {
+        within_depth && !args.files_only
+    } else {
+        (args.show_files || args.all || args.files_only) && !args.dirs_only && within_depth
+    };
+    // Apply size thresholds; directory sizes still include every file
+    shown
+        && args.min_size.is_none_or(|min| entry.size >= min)
+        && args.max_size.is_none_or(|max| entry.size <= max)
+        && args
+            .threshold
+            .is_none_or(|threshold| threshold.admits(entry.size))
+}
+
+/// Writes NDJSON records for `--stream` while the scan runs.
+///
+/// Entries come from [`scan::scan_iter_matching`] in walk order and pass the
+/// same filters as [`process_entries`]; every line is flushed as soon as it
+/// is written, so consumers see records long before a large scan ends. The
+/// cache is neither read nor written, and no stats or manifest are saved.
+fn run_stream(root: &Path, args: &Args, exclude_matcher: &utils::PathMatcher) -> Result<()> {
+    let style = PathStyle::from_args(args, root);
+    let mut out: Box<dyn std::io::Write> = match &args.output {
+        Some(output_file) => Box::new(std::io::BufWriter::new(std::fs::File::create(output_file)?)),
+        None => Box::new(std::io::stdout().lock()),
+    };
+    for entry in scan::scan_iter_matching(root, &ScanOptions::from(args), exclude_matcher) {
+        let mut entry = match entry {
+            Ok(entry) => entry,
+            // Unreadable entries are skipped, as in a full scan
+            Err(err @ error::Error::Io { .. }) => {
+                log::debug!("{}", err);
+                continue;
+            }
+            Err(err) => return Err(err.into()),
+        };
+        if !is_listed(root, args, &entry) {
+            continue;
+        }
+        if let Some(style) = &style {
+            entry.path = style.apply(&entry.path);
+        }
+        output::ndjson::write_entry(&mut out, &entry)?;
+        out.flush()?;
+    }
+    if let Some(output_file) = &args.output {
+        log::info!("NDJSON output written to: {}", output_file);
+    }
+    Ok(())
+}
+
+/// Outputs the results in the format chosen by `--format` and `--output`.
 ///
 /// Delegates to the modular output formatters in [`output`] so that both
-/// code paths share the same serialisation logic and schema.
-fn output_results(entries: &[FileEntry], args: &Args, root: &Path) -> Result<()> {
-    if args.output.is_some() {
-        output::render_csv(entries, args)
+/// code paths share the same serialisation logic and schema. `profile` is
+/// only used by `--template`.
+fn output_results(
+    entries: &[FileEntry],
+    args: &Args,
+    meta: &ScanMeta,
+    profile: Option<&ProfileData>,
+) -> Result<()> {
+    // Formats that write whole paths get them restyled up front; listings
+    // apply --absolute-paths and --relative-to themselves
+    let restyled = match args.output_format() {
+        OutputFormat::Csv
+        | OutputFormat::Tsv
+        | OutputFormat::Ndjson
+        | OutputFormat::Yaml
+        | OutputFormat::Msgpack => {
+            PathStyle::restyle(PathStyle::from_args(args, &meta.root).as_ref(), entries)
+        }
+        #[cfg(feature = "parquet")]
+        OutputFormat::Parquet => {
+            PathStyle::restyle(PathStyle::from_args(args, &meta.root).as_ref(), entries)
+        }
+        _ => Cow::Borrowed(entries),
+    };
+    let entries = restyled.as_ref();
+
+    match args.output_format() {
+        OutputFormat::Csv | OutputFormat::Tsv => output::render_csv(entries, args),
+        OutputFormat::Ndjson => output::render_ndjson(entries, args),
+        OutputFormat::Yaml => output::render_yaml(entries, args),
+        OutputFormat::Msgpack => output::render_msgpack(entries, args),
+        OutputFormat::Ncdu => output::render_ncdu(entries, args, meta),
+        OutputFormat::Folded => output::render_folded(entries, args, meta),
+        OutputFormat::Html => output::render_html(entries, args, meta),
+        #[cfg(feature = "parquet")]
+        OutputFormat::Parquet => output::render_parquet(entries, args),
+        #[cfg(feature = "xlsx")]
+        OutputFormat::Xlsx => output::render_xlsx(entries, args, meta),
+        OutputFormat::Printf => match &args.printf {
+            Some(format) => output::render_printf(entries, args, format),
+            None => Ok(()),
+        },
+        OutputFormat::Template => match &args.template {
+            Some(template) => output::render_template(entries, args, meta, template, profile),
+            None => Ok(()),
+        },
+        OutputFormat::Terminal => match &args.output {
+            Some(output_file) => {
+                let mut file = std::io::BufWriter::new(std::fs::File::create(output_file)?);
+                output::terminal::render_to(&mut file, entries, args, meta)?;
+                log::info!("Output written to: {}", output_file);
+                Ok(())
+            }
+            None => output::render_terminal(entries, args, meta),
+        },
+    }
+}
+
+/// Whether entries spilled to disk during the scan can be merged into the
+/// output as it is written: only CSV, TSV and NDJSON listings write one entry
+/// after another, and only without options that look at the whole listing.
+fn streams_spilled_entries(args: &Args) -> bool {
+    matches!(
+        args.output_format(),
+        OutputFormat::Csv | OutputFormat::Tsv | OutputFormat::Ndjson
+    ) && args.report.is_none()
+        && args.sparse_report.is_none()
+        && args.top.is_none()
+        && args.limit.is_none()
+        && args.offset == 0
+        && args.include.is_empty()
+        && args.include_regex.is_empty()
+        && args.older_than.is_none()
+        && args.newer_than.is_none()
+        && args.gid.is_none()
+        && args.delete_selected.is_none()
+        && !args.snapshot
+        && !args.scan_archives
+        && args.budgets.is_none()
+        && args.alert_threshold.is_none()
+        && args.alert_growth.is_none()
+}
+
+/// Writes a CSV, TSV or NDJSON listing of `entries` and the files spilled to
+/// disk during the scan, merged in sort order and filtered like
+/// [`process_entries`], a chunk at a time so the whole listing is never held
+/// in memory. Returns the number of entries written.
+fn output_spilled(
+    root: &Path,
+    entries: Vec<FileEntry>,
+    spilled: SpilledEntries,
+    args: &Args,
+) -> Result<usize> {
+    const CHUNK_LEN: usize = 16_384;
+    let style = PathStyle::from_args(args, root);
+    let mut out: Box<dyn std::io::Write> = match &args.output {
+        Some(output_file) => Box::new(std::io::BufWriter::new(std::fs::File::create(output_file)?)),
+        None => Box::new(std::io::BufWriter::new(std::io::stdout().lock())),
+    };
+    let columns = ColumnSpec::for_csv(args);
+    let mut dialect = output::csv::Dialect::from_args(args);
+    let time_style = output::csv::csv_time_style(args);
+
+    let mut merged = spilled.merge(entries)?;
+    let mut written = 0;
+    loop {
+        let mut chunk = Vec::with_capacity(CHUNK_LEN);
+        for entry in merged.by_ref() {
+            let mut entry = entry.context("Failed to read spilled entries")?;
+            if !is_listed(root, args, &entry) {
+                continue;
+            }
+            if let Some(style) = &style {
+                entry.path = style.apply(&entry.path);
+            }
+            chunk.push(entry);
+            if chunk.len() == CHUNK_LEN {
+                break;
+            }
+        }
+        match args.output_format() {
+            OutputFormat::Ndjson => output::ndjson::render_to(&mut out, &chunk)?,
+            _ => output::csv::render_to(
+                &mut out,
+                &chunk,
+                &columns,
+                dialect,
+                args.size_format(),
+                &time_style,
+            )?,
+        }
+        // The header goes above the first chunk only
+        dialect.header = false;
+        written += chunk.len();
+        if chunk.len() < CHUNK_LEN {
+            break;
+        }
+    }
+    out.flush()?;
+
+    if let Some(output_file) = &args.output {
+        let label = match args.output_format() {
+            OutputFormat::Ndjson => "NDJSON",
+            OutputFormat::Tsv => "TSV",
+            _ => "CSV",
+        };
+        log::info!("{} output written to: {}", label, output_file);
+    }
+    Ok(written)
+}
+
+/// Writes `manifest.json` next to the primary output when a run produced
+/// more than one artifact, so the set can be archived and verified as a unit.
+fn write_manifest(meta: &ScanMeta, artifacts: &[(&str, PathBuf)]) -> Result<()> {
+    match artifacts.first() {
+        Some((_, primary)) => {
+            write_manifest_to(meta, artifacts, &primary.with_file_name(MANIFEST_FILE_NAME))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Like [`write_manifest`], but writes the manifest to `manifest_path`.
+fn write_manifest_to(
+    meta: &ScanMeta,
+    artifacts: &[(&str, PathBuf)],
+    manifest_path: &Path,
+) -> Result<()> {
+    if artifacts.len() < 2 {
+        return Ok(());
+    }
+
+    let mut manifest = Manifest::for_scan(meta);
+    for (kind, path) in artifacts {
+        manifest.add_artifact(kind, path)?;
+    }
+
+    manifest.save(manifest_path)?;
+    log::info!("Run manifest written to: {}", manifest_path.display());
+    Ok(())
+}
+
+/// Warns when the mount options of the filesystem holding `root` make
+/// access times too coarse for `--report cold` with a window back to
+/// `cutoff`.
+fn warn_about_atime(root: &Path, cutoff: Option<u64>) {
+    let Some(mount) = containing_mount(root) else {
+        return;
+    };
+    if mount.has_option("noatime") {
+        log::warn!(
+            "{} is mounted noatime: reads are not recorded, so --report cold only sees \
+             modifications",
+            root.display()
+        );
+    } else if mount.has_option("relatime") {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        // relatime refreshes an atime at most daily unless the file changed
+        if cutoff.is_some_and(|cutoff| now.saturating_sub(cutoff) < 24 * 60 * 60) {
+            log::warn!(
+                "{} is mounted relatime: access times can lag by up to a day, longer \
+                 than the --cold-after window",
+                root.display()
+            );
+        }
+    }
+}
+
+/// Prints the space held by deleted-but-open files on the scanned filesystem.
+///
+/// Goes to stdout alongside the terminal table, or to stderr when stdout is
+/// reserved for (or bypassed by) a machine-readable output file.
+fn report_deleted_open_files(root: &Path, args: &Args) {
+    let report = deleted::find_deleted_open_files(root);
+    let units = args.size_format();
+
+    let mut lines = vec![format!(
+        "Deleted-but-open files: {} holding {}",
+        report.files.len(),
+        units.format(report.total_size)
+    )];
+    for file in &report.files {
+        lines.push(format!(
+            "  {:<12} pid {:<8} {:<16} {}",
+            units.format(file.size),
+            file.pid,
+            file.process,
+            file.path.display()
+        ));
+    }
+    if report.inaccessible_processes > 0 {
+        lines.push(format!(
+            "  ({} processes could not be inspected; run as root for complete results)",
+            report.inaccessible_processes
+        ));
+    }
+
+    for line in lines {
+        if args.stdout_is_terminal_listing() {
+            println!("{}", line);
+        } else {
+            eprintln!("{}", line);
+        }
+    }
+}
+
+/// Prints entry counts and the cached size for `--estimate` instead of scanning.
+fn run_estimate(root: &Path, args: &Args) -> Result<()> {
+    let expanded_patterns = expand_exclude_patterns(&args.exclude);
+    let exclude_matcher =
+        build_exclude_matcher(&expanded_patterns)?.with_regexes(&args.exclude_regex)?;
+    let estimate = scan::estimate(root, &ScanOptions::from(args), &exclude_matcher)?;
+    let units = args.size_format();
+
+    println!(
+        "{}: {} files, {} directories (counted in {:.1?})",
+        root.display(),
+        format_count(estimate.files as usize),
+        format_count(estimate.dirs as usize),
+        estimate.elapsed
+    );
+    if estimate.cached_dirs == 0 {
+        println!("Size: unknown (no cache for this path; run a full scan to create one)");
+    } else if estimate.uncached_files == 0 {
+        println!("Size: ~{} (from cache)", units.format(estimate.cached_size));
     } else {
-        output::render_terminal(entries, args, root)
+        println!(
+            "Size: at least ~{} (from cache; {} uncached {} not included)",
+            units.format(estimate.cached_size),
+            format_count(estimate.uncached_files as usize),
+            if estimate.uncached_files == 1 {
+                "file"
+            } else {
+                "files"
+            }
+        );
+    }
+    Ok(())
+}
+
+/// Prints recursive entry counts per directory for `--count-only` instead of sizes.
+fn run_count_only(root: &Path, args: &Args) -> Result<()> {
+    if !matches!(
+        args.output_format(),
+        OutputFormat::Terminal | OutputFormat::Csv | OutputFormat::Tsv
+    ) {
+        anyhow::bail!("--count-only can only be written as a table or as CSV/TSV");
+    }
+    let expanded_patterns = expand_exclude_patterns(&args.exclude);
+    let exclude_matcher =
+        build_exclude_matcher(&expanded_patterns)?.with_regexes(&args.exclude_regex)?;
+
+    let (counts, meta) = scan::count_entries(root, &ScanOptions::from(args), &exclude_matcher)?;
+    for mount in &meta.mounts {
+        log::info!("Crossed mountpoint {}", mount.describe());
+    }
+    let report = report::counts(&counts, root, args.depth, &args.sort, args.reverse);
+    report::render(&report, args)
+}
+
+/// Asks before a scan that is likely to run for hours, unless `--yes` was given.
+///
+/// A scan is considered long when the cache from the last scan of `root`
+/// records more than [`LARGE_SCAN_ENTRIES`] entries, or when `root` is on a
+/// slow network filesystem and there is no cache to go by. Without a terminal
+/// to ask on, such scans fail until `--yes` is given.
+///
+/// # Returns
+/// * `Result<bool>` - Whether to go ahead with the scan
+fn confirm_long_scan(root: &Path, args: &Args) -> Result<bool> {
+    if args.yes {
+        return Ok(true);
+    }
+    let previous = cache::open_previous_cache(root, args.cache_location);
+    let reason = match &previous {
+        Some(previous) => {
+            let entries = previous.estimated_entries();
+            if entries <= LARGE_SCAN_ENTRIES {
+                return Ok(true);
+            }
+            let size = previous
+                .lookup(root)
+                .map(|record| record.size())
+                .unwrap_or(0);
+            format!(
+                "the last scan of {} found at least {} entries ({})",
+                root.display(),
+                format_count(entries as usize),
+                args.size_format().format(size)
+            )
+        }
+        None => match containing_fs_type(root) {
+            Some(fs_type) if SLOW_FILESYSTEMS.contains(&fs_type.as_str()) => format!(
+                "{} is on a {} filesystem and has not been scanned before",
+                root.display(),
+                fs_type
+            ),
+            _ => return Ok(true),
+        },
+    };
+
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!(
+            "This scan may take a very long time: {}. Run `rudu --estimate {}` to count its \
+             entries, or pass --yes to scan anyway.",
+            reason,
+            root.display()
+        );
+    }
+    eprintln!("⚠️  This scan may take a very long time: {}.", reason);
+    eprintln!(
+        "   `rudu --estimate {}` counts its entries without a full scan; --yes skips this question.",
+        root.display()
+    );
+    eprint!("Continue? [y/N] ");
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes" | "Yes"))
+}
+
+/// Deletes the innermost entries of the listing with `mode` after showing
+/// them and asking for confirmation, then updates `entries` so the listing
+/// shows the new totals. With `--dry-run` only the list is shown.
+///
+/// Removing a directory also removes whatever in it was never listed, so
+/// with [`cleanup::DeleteMode::Remove`] directories need their own answer
+/// on a terminal (see [`confirm_directory_removal`]).
+fn delete_selected(
+    root: &Path,
+    args: &Args,
+    mode: cleanup::DeleteMode,
+    entries: &mut Vec<FileEntry>,
+) -> Result<()> {
+    // Name, age and group filters select files; directories only carry their totals
+    let files_only = !args.include.is_empty()
+        || !args.include_regex.is_empty()
+        || args.older_than.is_some()
+        || args.newer_than.is_some()
+        || args.gid.is_some()
+        || args.files_only;
+    let mut planned: Vec<(PathBuf, u64, bool)> = cleanup::plan(entries, root, files_only)
+        .into_iter()
+        .map(|entry| (entry.path.clone(), entry.size, entry.entry_type.is_dir()))
+        .collect();
+    if planned.is_empty() {
+        log::info!("Nothing selected to delete");
+        return Ok(());
+    }
+    let total: u64 = planned.iter().map(|(_, size, _)| size).sum();
+    let action = match mode {
+        cleanup::DeleteMode::Trash => "Move to the trash",
+        cleanup::DeleteMode::Remove => "Permanently remove",
+    };
+    eprintln!(
+        "{} {} {} ({}):",
+        action,
+        format_count(planned.len()),
+        if planned.len() == 1 {
+            "entry"
+        } else {
+            "entries"
+        },
+        args.size_format().format(total)
+    );
+    for (path, size, is_dir) in &planned {
+        eprintln!(
+            "  {:>10}  {}{}",
+            args.size_format().format(*size),
+            path.display(),
+            if *is_dir { "/" } else { "" }
+        );
+    }
+    if args.dry_run {
+        eprintln!("Dry run: nothing was deleted");
+        return Ok(());
+    }
+
+    if !args.yes {
+        if !std::io::stdin().is_terminal() {
+            anyhow::bail!("Not deleting without confirmation; pass --yes to delete anyway");
+        }
+        eprint!("{}? [y/N] ", action);
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        if !matches!(answer.trim(), "y" | "Y" | "yes" | "Yes") {
+            log::info!("Nothing deleted");
+            return Ok(());
+        }
+    }
+    if mode == cleanup::DeleteMode::Remove {
+        let dirs = planned.iter().filter(|(_, _, is_dir)| *is_dir).count();
+        if dirs > 0 && !confirm_directory_removal(dirs)? {
+            planned.retain(|(_, _, is_dir)| !is_dir);
+            if planned.is_empty() {
+                log::info!("Nothing deleted");
+                return Ok(());
+            }
+        }
+    }
+
+    let mut deleted = Vec::with_capacity(planned.len());
+    for (path, size, _) in planned {
+        match cleanup::delete(&path, mode) {
+            Ok(Some(trashed)) => {
+                log::info!("Moved {} to {}", path.display(), trashed.display());
+                deleted.push((path, size));
+            }
+            Ok(None) => {
+                log::info!("Removed {}", path.display());
+                deleted.push((path, size));
+            }
+            Err(e) => log::error!("Failed to delete {}: {}", path.display(), e),
+        }
+    }
+    let freed: u64 = deleted.iter().map(|(_, size)| size).sum();
+    log::info!(
+        "Deleted {} of the selected entries, freeing {}",
+        format_count(deleted.len()),
+        args.size_format().format(freed)
+    );
+    cleanup::apply_deletions(entries, &deleted);
+    Ok(())
+}
+
+/// Asks whether `--delete-selected=remove` may remove `dirs` directories
+/// with everything in them.
+///
+/// A directory picked by `--top`, `--min-size` or `--threshold` is listed
+/// for its total, not for what is in it, so `--yes` does not answer this.
+/// Without a terminal to ask on, the directories are kept.
+fn confirm_directory_removal(dirs: usize) -> Result<bool> {
+    let what = format!(
+        "{} {}",
+        format_count(dirs),
+        if dirs == 1 {
+            "directory"
+        } else {
+            "directories"
+        }
+    );
+    if !std::io::stdin().is_terminal() {
+        log::warn!(
+            "Keeping {}: removing whole directories needs confirmation on a terminal; \
+             use --files-only or --delete-selected=trash instead",
+            what
+        );
+        return Ok(false);
     }
+    eprint!(
+        "Also remove the {} marked with /, including everything in them that was not listed? [y/N] ",
+        what
+    );
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes" | "Yes"))
+}
+
+/// Runs a subcommand instead of a scan.
+fn run_command(args: &Args, command: &Command) -> Result<()> {
+    match command {
+        Command::Cache {
+            action: CacheCommand::Merge { inputs, output },
+        } => {
+            let summary = cache::merge_cache_files(inputs, output.as_deref())?;
+            log::info!(
+                "Merged {} cache files into {} ({} entries, {} overlapping paths, root {})",
+                inputs.len(),
+                summary.output.display(),
+                summary.entries,
+                summary.conflicts,
+                summary.root_path.display()
+            );
+            Ok(())
+        }
+        Command::Cache {
+            action: CacheCommand::Warm { path },
+        } => {
+            setup_thread_pool(args)?;
+            let expanded_patterns = expand_exclude_patterns(&args.exclude);
+            let exclude_matcher =
+                build_exclude_matcher(&expanded_patterns)?.with_regexes(&args.exclude_regex)?;
+
+            let result = scan::warm_cache(path, &ScanOptions::from(args), &exclude_matcher)?;
+            log::info!(
+                "Cache warmed for {}: {} entries scanned, {} of {} directories still valid",
+                path.display(),
+                result.entries.len(),
+                result.cache_hits,
+                result.cache_total
+            );
+            Ok(())
+        }
+        Command::Daemon {
+            paths,
+            paths_from,
+            interval,
+            output_dir,
+            keep,
+            once,
+        } => {
+            let mut roots = paths.clone();
+            if let Some(file) = paths_from {
+                roots.extend(read_pattern_file(file)?.into_iter().map(PathBuf::from));
+            }
+            if roots.is_empty() {
+                anyhow::bail!(
+                    "rudu daemon needs directories to scan, as arguments or --paths-from"
+                );
+            }
+            std::fs::create_dir_all(output_dir)?;
+            run_daemon(args, &roots, *interval, output_dir, *keep, *once)
+        }
+        Command::Diff {
+            inputs,
+            since,
+            min_change,
+            top,
+        } => run_diff(args, inputs, since.as_deref(), *min_change, *top),
+        #[cfg(feature = "history")]
+        Command::Report {
+            action: cli::ReportCommand::Trend { path, quota, since },
+        } => run_trend(args, path, *quota, *since),
+        #[cfg(feature = "serve")]
+        Command::Serve {
+            path,
+            listen,
+            interval,
+        } => run_serve(args, path, listen, *interval),
+        Command::Completions { shell } => {
+            cli::write_completions(*shell, &mut std::io::stdout().lock());
+            Ok(())
+        }
+    }
+}
+
+/// The newest snapshot of `root` saved before this run, to measure
+/// `--alert-growth` against.
+fn previous_snapshot(root: &Path) -> Option<Snapshot> {
+    let Some(path) = snapshot::latest(root) else {
+        log::info!(
+            "No snapshot of {} yet; growth alerts start from the next run with --snapshot",
+            root.display()
+        );
+        return None;
+    };
+    Snapshot::load(&path)
+        .map_err(|e| log::warn!("Not checking growth: {:#}", e))
+        .ok()
+}
+
+/// Checks the listed directories of a scan against `--alert-threshold` and
+/// `--alert-growth`, growth being measured from `previous`, and sends the
+/// directories over either limit to `--alert-url` and `--alert-email`.
+///
+/// Failures to deliver are logged rather than failing the scan.
+#[cfg(feature = "alerts")]
+fn send_alerts(args: &Args, entries: &[FileEntry], meta: &ScanMeta, previous: Option<&Snapshot>) {
+    let rules = alert::AlertRules {
+        threshold: args.alert_threshold,
+        growth_percent: args.alert_growth,
+    };
+    if rules.is_empty() {
+        return;
+    }
+    let alerts = alert::check(entries, &meta.root, previous, &rules);
+    if alerts.is_empty() {
+        log::info!(
+            "No directory of {} is over the alert limits",
+            meta.root.display()
+        );
+        return;
+    }
+    let payload = alert::AlertPayload::new(meta, &rules, previous, &alerts);
+    log::warn!("{}", payload.subject());
+    if let Some(url) = &args.alert_url {
+        match alert::post_webhook(url, &payload) {
+            Ok(()) => log::info!("Alert posted to {}", url),
+            Err(e) => log::error!("Failed to post alert: {:#}", e),
+        }
+    }
+    if !args.alert_email.is_empty() {
+        let config = alert::SmtpConfig {
+            server: args.smtp_server.clone(),
+            from: args
+                .alert_from
+                .clone()
+                .unwrap_or_else(|| format!("rudu@{}", alert::hostname())),
+            to: args.alert_email.clone(),
+        };
+        match alert::send_email(&config, &payload) {
+            Ok(()) => log::info!("Alert emailed to {}", config.to.join(", ")),
+            Err(e) => log::error!("Failed to email alert: {:#}", e),
+        }
+    }
+}
+
+/// Fallback for builds without the `alerts` feature, which refuse alert
+/// options up front.
+#[cfg(not(feature = "alerts"))]
+fn send_alerts(
+    _args: &Args,
+    _entries: &[FileEntry],
+    _meta: &ScanMeta,
+    _previous: Option<&Snapshot>,
+) {
+}
+
+/// Prints the directories over their `--budgets` budget after the output:
+/// inline with a terminal listing, on stderr otherwise.
+fn print_budget_violations(violations: &[budget::Violation], args: &Args) -> Result<()> {
+    if violations.is_empty() {
+        log::info!("Every budgeted directory is within its budget");
+        return Ok(());
+    }
+    let report = report::budgets(violations);
+    if args.stdout_is_terminal_listing() {
+        println!();
+        report::write_table(&mut std::io::stdout().lock(), &report, args.size_format())?;
+    } else {
+        report::write_table(&mut std::io::stderr().lock(), &report, args.size_format())?;
+    }
+    Ok(())
+}
+
+/// Saves `snapshot` for `rudu diff` and, with the `history` feature, adds its
+/// directory totals up to `--depth` (default 1) to the trend history.
+#[cfg_attr(not(feature = "history"), allow(unused_variables))]
+fn save_snapshot(snapshot: &Snapshot, args: &Args) -> Result<PathBuf> {
+    let path = snapshot.save_default()?;
+    #[cfg(feature = "history")]
+    history::History::open_default()?.record(snapshot, args.depth.unwrap_or(1))?;
+    Ok(path)
+}
+
+/// Prints the growth of `path` and its subdirectories over the recorded
+/// runs for `rudu report trend`.
+#[cfg(feature = "history")]
+fn run_trend(args: &Args, path: &Path, quota: Option<u64>, since: Option<u64>) -> Result<()> {
+    if !matches!(
+        args.output_format(),
+        OutputFormat::Terminal | OutputFormat::Csv | OutputFormat::Tsv
+    ) {
+        anyhow::bail!("rudu report trend can only be written as a table or as CSV/TSV");
+    }
+    let trends = history::History::open_default()?
+        .trends(path, since)?
+        .filter(|trends| !trends.is_empty())
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "No history for {}; scan it with --snapshot to start recording",
+                path.display()
+            )
+        })?;
+    if let Some(trend) = trends.first().filter(|trend| trend.samples.len() < 2) {
+        log::warn!(
+            "Only one run of {} is recorded; growth needs at least two",
+            trend.path.display()
+        );
+    }
+    report::render(&report::trend(&trends, quota), args)
+}
+
+/// Prints the per-directory changes between two snapshots, or with `since`
+/// between a saved snapshot and a fresh scan.
+fn run_diff(
+    args: &Args,
+    inputs: &[PathBuf],
+    since: Option<&str>,
+    min_change: Option<u64>,
+    top: Option<usize>,
+) -> Result<()> {
+    if !matches!(
+        args.output_format(),
+        OutputFormat::Terminal | OutputFormat::Csv | OutputFormat::Tsv
+    ) {
+        anyhow::bail!("rudu diff can only be written as a table or as CSV/TSV");
+    }
+    let (old, new) = match (since, inputs) {
+        (Some(since), [] | [_]) => {
+            let root = inputs.first().map_or(Path::new("."), PathBuf::as_path);
+            let old_path = if since == "last" {
+                snapshot::latest(root).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "No snapshot of {} saved yet; scan it with --snapshot first",
+                        root.display()
+                    )
+                })?
+            } else {
+                PathBuf::from(since)
+            };
+            let old = Snapshot::load(&old_path)?;
+            log::info!("Comparing with snapshot {}", old_path.display());
+
+            setup_thread_pool(args)?;
+            let exclude_matcher = build_exclude_matcher(&expand_exclude_patterns(&args.exclude))?
+                .with_regexes(&args.exclude_regex)?;
+            let result =
+                scan_files_and_dirs(root, &ScanOptions::from(args), &exclude_matcher, &args.sort)?;
+            let new = Snapshot::from_entries(&result.entries, &result.meta);
+            if args.snapshot {
+                log::info!("Snapshot saved to {}", save_snapshot(&new, args)?.display());
+            }
+            (old, new)
+        }
+        (None, [old, new]) => (Snapshot::load(old)?, Snapshot::load(new)?),
+        (Some(_), _) => anyhow::bail!("rudu diff --since takes a single directory to scan"),
+        (None, _) => {
+            anyhow::bail!(
+                "rudu diff needs two snapshot files, or --since last|FILE and a directory"
+            )
+        }
+    };
+    if old.root != new.root {
+        log::warn!(
+            "Comparing snapshots of different directories: {} and {}",
+            old.root.display(),
+            new.root.display()
+        );
+    }
+
+    let mut changes = snapshot::diff(&old, &new, min_change.unwrap_or(0));
+    log::info!(
+        "{} directories changed between {} and {}",
+        format_count(changes.len()),
+        old.taken_at.format("%Y-%m-%d %H:%M:%S UTC"),
+        new.taken_at.format("%Y-%m-%d %H:%M:%S UTC")
+    );
+    if let Some(top) = top {
+        changes.truncate(top);
+    }
+    report::render(&report::diff(&changes, &new.root), args)
+}
+
+/// Serves the scan of `root` over HTTP, rescanning every `interval` on a
+/// background thread.
+///
+/// The by-owner totals need every file and its owner, so scans bypass the
+/// cache and look owners up, as `--report by-owner` does.
+#[cfg(feature = "serve")]
+fn run_serve(
+    args: &Args,
+    root: &Path,
+    listen: &str,
+    interval: Option<std::time::Duration>,
+) -> Result<()> {
+    let mut args = args.clone();
+    args.path = root.to_path_buf();
+    args.no_cache = true;
+    args.show_owner = true;
+    setup_thread_pool(&args)?;
+    let expanded_patterns = expand_exclude_patterns(&args.exclude);
+    let exclude_matcher =
+        build_exclude_matcher(&expanded_patterns)?.with_regexes(&args.exclude_regex)?;
+
+    let state = std::sync::Arc::new(std::sync::RwLock::new(None));
+    let scanned = std::sync::Arc::clone(&state);
+    let root = root.to_path_buf();
+    std::thread::spawn(move || {
+        loop {
+            let started = std::time::Instant::now();
+            match scan::Scanner::new(ScanOptions::from(&args))
+                .exclude_matcher(&exclude_matcher)
+                .scan(&root)
+            {
+                Ok(result) => {
+                    log::info!(
+                        "Scan of {} finished: {} entries",
+                        root.display(),
+                        result.entries.len()
+                    );
+                    let snapshot = serve::Snapshot::new(&root, result.entries, result.meta);
+                    *scanned.write().unwrap_or_else(|e| e.into_inner()) = Some(snapshot);
+                }
+                Err(e) => log::error!("Scan of {} failed: {:#}", root.display(), e),
+            }
+            let Some(interval) = interval else { break };
+            std::thread::sleep(interval.saturating_sub(started.elapsed()));
+        }
+    });
+
+    serve::serve(listen, &state)
+}
+
+/// Scans `roots` every `interval` until stopped by a signal, or once.
+///
+/// A failed scan is logged and the round goes on with the next path, so one
+/// unreadable directory does not stop the service.
+fn run_daemon(
+    args: &Args,
+    roots: &[PathBuf],
+    interval: std::time::Duration,
+    output_dir: &Path,
+    keep: Option<usize>,
+    once: bool,
+) -> Result<()> {
+    setup_thread_pool(args)?;
+    let expanded_patterns = expand_exclude_patterns(&args.exclude);
+    let exclude_matcher =
+        build_exclude_matcher(&expanded_patterns)?.with_regexes(&args.exclude_regex)?;
+    // Outputs are files, so CSV unless --format says otherwise, as with --output
+    let extension = match args.output_format() {
+        OutputFormat::Terminal if args.format.is_none() => OutputFormat::Csv,
+        format => format,
+    }
+    .extension();
+    let stop = daemon::stop_on_signals();
+
+    daemon::notify("READY=1");
+    log::info!(
+        "Daemon started: {} paths every {}s, outputs in {}",
+        roots.len(),
+        interval.as_secs(),
+        output_dir.display()
+    );
+    loop {
+        let round_started = std::time::Instant::now();
+        for root in roots {
+            if stop.load(std::sync::atomic::Ordering::SeqCst) {
+                break;
+            }
+            daemon::notify(&format!("STATUS=Scanning {}", root.display()));
+            let output = daemon::output_path(output_dir, root, chrono::Utc::now(), extension);
+            match run_daemon_scan(args, root, &output, &exclude_matcher, stop) {
+                Ok(()) => {}
+                Err(e) if matches!(e.downcast_ref(), Some(error::Error::Cancelled)) => break,
+                Err(e) => log::error!("Scan of {} failed: {:#}", root.display(), e),
+            }
+            if let Some(keep) = keep {
+                match daemon::prune_outputs(output_dir, root, extension, keep) {
+                    Ok(0) => {}
+                    Ok(n) => log::info!("Deleted {} old outputs of {}", n, root.display()),
+                    Err(e) => log::error!("Failed to delete old outputs: {}", e),
+                }
+            }
+        }
+
+        if once || stop.load(std::sync::atomic::Ordering::SeqCst) {
+            break;
+        }
+        let next_round = round_started + interval;
+        daemon::notify(&format!(
+            "STATUS=Waiting {}s for the next round",
+            next_round
+                .saturating_duration_since(std::time::Instant::now())
+                .as_secs()
+        ));
+        if !daemon::sleep_until(next_round, stop) {
+            break;
+        }
+    }
+    daemon::notify("STOPPING=1");
+    log::info!("Daemon stopped");
+    Ok(())
+}
+
+/// Scans `root` and writes its output to `output` for one daemon round.
+fn run_daemon_scan(
+    args: &Args,
+    root: &Path,
+    output: &Path,
+    exclude_matcher: &utils::PathMatcher,
+    stop: &std::sync::atomic::AtomicBool,
+) -> Result<()> {
+    let mut args = args.clone();
+    args.path = root.to_path_buf();
+    args.output = Some(output.to_string_lossy().into_owned());
+
+    let result = scan::Scanner::new(ScanOptions::from(&args))
+        .exclude_matcher(exclude_matcher)
+        .sort(&args.sort)
+        .cancel_flag(stop)
+        .scan(root)?;
+    let previous = args
+        .alert_growth
+        .is_some()
+        .then(|| previous_snapshot(root))
+        .flatten();
+    let mut snapshot_path = None;
+    if args.snapshot && result.meta.partial {
+        log::warn!(
+            "Not saving a snapshot of a partial scan of {}",
+            root.display()
+        );
+    } else if args.snapshot {
+        snapshot_path = Some(save_snapshot(
+            &Snapshot::from_entries(&result.entries, &result.meta),
+            &args,
+        )?);
+    }
+    if let Some(path) = &args.budgets {
+        let units = args.size_format();
+        for v in budget::Budgets::from_file(path)?.check(&result.entries, root) {
+            log::warn!(
+                "{} is {} for budget {}: {}, {} inodes",
+                v.path.display(),
+                v.status(),
+                v.pattern,
+                units.format(v.size),
+                format_count(v.inodes as usize)
+            );
+        }
+    }
+    let entries = process_entries(root, &args, result.entries);
+    send_alerts(&args, &entries, &result.meta, previous.as_ref());
+    output_results(&entries, &args, &result.meta, None)?;
+
+    let mut artifacts = vec![("results", output.to_path_buf())];
+    artifacts.extend(snapshot_path.map(|path| ("snapshot", path)));
+    if let Err(e) = write_manifest_to(&result.meta, &artifacts, &daemon::manifest_path(output)) {
+        log::error!("Failed to write run manifest: {}", e);
+    }
+    Ok(())
 }
 
 fn main() -> Result<()> {
-    let args = Args::parse();
+    let mut args = Args::parse_with_env();
+    logging::init(logging::level_filter(args.quiet, args.verbose));
+    args.resolve_stdout_output();
+    // Patterns from --exclude-from files apply like --exclude ones
+    for path in args.exclude_from.clone() {
+        args.exclude.extend(read_pattern_file(&path)?);
+    }
+    // Selected owner or inode fields need that data collected by the scan
+    if let Some(columns) = &args.columns {
+        args.show_owner |= columns.contains(Column::Owner);
+        args.show_inodes |= columns.contains(Column::Inodes);
+    }
+    args.show_owner |= args.sort.contains(&SortKey::Owner);
+    args.show_inodes |= args.sort.contains(&SortKey::Inodes);
+    if let Some(format) = &args.printf {
+        args.show_owner |= format.uses_owner();
+        args.show_inodes |= format.uses_inodes();
+    }
+    if let Some(kind) = args.report {
+        if !matches!(
+            args.output_format(),
+            OutputFormat::Terminal | OutputFormat::Csv | OutputFormat::Tsv
+        ) {
+            anyhow::bail!("--report can only be written as a table or as CSV/TSV");
+        }
+        // Reports need every file, which cache hits would skip walking
+        args.no_cache = true;
+        args.show_owner |= matches!(kind, ReportKind::ByOwner | ReportKind::UserQuota);
+        #[cfg(feature = "lustre")]
+        if matches!(kind, ReportKind::LustrePools | ReportKind::LustreFiles)
+            && containing_fs_type(&args.path).as_deref() != Some("lustre")
+        {
+            anyhow::bail!("{} is not on a Lustre filesystem", args.path.display());
+        }
+        if kind == ReportKind::ProjectQuota {
+            // Fail before scanning rather than print a report without quotas
+            quota::ProjectQuotas::for_path(&args.path)?;
+        }
+        if kind == ReportKind::UserQuota {
+            let Some(path) = &args.user_quotas else {
+                anyhow::bail!(
+                    "--report user-quota needs the quotas to compare with: --user-quotas FILE"
+                );
+            };
+            // Fail before scanning rather than print a report without quotas
+            let quotas = quota::UserQuotas::from_file(path)?;
+            log::info!(
+                "Read quotas of {} {} from {}",
+                format_count(quotas.len()),
+                if quotas.len() == 1 { "user" } else { "users" },
+                path.display()
+            );
+        }
+        if kind == ReportKind::Cold {
+            // Access times are only recorded with extended metadata
+            args.extended_metadata = true;
+            warn_about_atime(&args.path, args.cold_after);
+        }
+        if kind == ReportKind::Hsm {
+            // Offline files are told apart by their apparent size
+            args.extended_metadata = true;
+        }
+        if matches!(kind, ReportKind::Security | ReportKind::Orphans) {
+            // Modes and owner IDs are only recorded with extended metadata
+            args.extended_metadata = true;
+        }
+    }
+    if args.sparse_report.is_some() {
+        if !matches!(
+            args.output_format(),
+            OutputFormat::Terminal | OutputFormat::Csv | OutputFormat::Tsv
+        ) {
+            anyhow::bail!("--sparse-report can only be written as a table or as CSV/TSV");
+        }
+        // Every file is needed, with its apparent size from extended metadata
+        args.no_cache = true;
+        args.extended_metadata = true;
+    }
+    if args.stream && args.output_format() != OutputFormat::Ndjson {
+        anyhow::bail!("--stream can only be written as NDJSON; add --format ndjson");
+    }
+    if let Some(name) = &args.group {
+        // Like chgrp, a name that is no group is tried as a group ID
+        let gid = utils::group_id(name).or_else(|| name.parse().ok());
+        args.gid = Some(gid.ok_or_else(|| anyhow::anyhow!("Unknown group '{}'", name))?);
+    }
+    // Group IDs are recorded with the extended metadata, which is dropped
+    // again after filtering unless it was asked for
+    let strip_stat = args.gid.is_some() && !args.extended_metadata;
+    if args.gid.is_some() {
+        args.extended_metadata = true;
+    }
+    if !args.include.is_empty()
+        || !args.include_regex.is_empty()
+        || args.older_than.is_some()
+        || args.newer_than.is_some()
+        || args.gid.is_some()
+    {
+        // Totals are recomputed from selected files, so every file must be walked
+        args.no_cache = true;
+    }
+    if !args.exclude_regex.is_empty() || !args.exclude_from.is_empty() {
+        // Cached directory totals would still count what these exclude
+        args.no_cache = true;
+    }
+    if args.files_only || args.largest_files.is_some() {
+        // Cache hits skip walking a subtree, which would leave its files out
+        args.no_cache = true;
+    }
+    if args.deterministic {
+        // Cache hits would leave a subtree's files out of some runs but not others
+        args.no_cache = true;
+    }
+    if args.scan_archives {
+        // Cache hits skip walking a subtree, which would leave its archives out
+        args.no_cache = true;
+    }
+    if args.delete_selected.is_some()
+        && args.top.is_none()
+        && args.include.is_empty()
+        && args.include_regex.is_empty()
+        && args.older_than.is_none()
+        && args.newer_than.is_none()
+        && args.gid.is_none()
+        && args.min_size.is_none()
+        && args.max_size.is_none()
+        && args.threshold.is_none()
+    {
+        // Without a filter the listing is the whole tree
+        anyhow::bail!(
+            "--delete-selected needs a selection: --top, --include, --include-regex, \
+             --older-than, --newer-than, --group, --gid, --min-size, --max-size or --threshold"
+        );
+    }
+    if let Some(path) = &args.budgets {
+        // Fail before scanning rather than after it
+        let budgets = budget::Budgets::from_file(path)?;
+        log::info!(
+            "Read {} {} from {}",
+            format_count(budgets.len()),
+            if budgets.len() == 1 {
+                "budget"
+            } else {
+                "budgets"
+            },
+            path.display()
+        );
+        // Inodes are counted from every file, which cache hits would skip walking
+        args.no_cache = true;
+    }
+    let alerting = args.alert_threshold.is_some() || args.alert_growth.is_some();
+    if alerting && args.alert_url.is_none() && args.alert_email.is_empty() {
+        anyhow::bail!(
+            "--alert-threshold and --alert-growth need somewhere to send alerts: \
+             --alert-url or --alert-email"
+        );
+    }
+    if !alerting && (args.alert_url.is_some() || !args.alert_email.is_empty()) {
+        anyhow::bail!(
+            "--alert-url and --alert-email need a limit: --alert-threshold or --alert-growth"
+        );
+    }
+    if alerting && !cfg!(feature = "alerts") {
+        anyhow::bail!("Alerts need the alerts feature; rebuild rudu with --features alerts");
+    }
+    if is_s3_path(&args.path) {
+        if args.estimate
+            || args.count_only
+            || args.delete_selected.is_some()
+            || args.scan_archives
+            || args.gid.is_some()
+            || args.stream
+        {
+            anyhow::bail!(
+                "--estimate, --count-only, --delete-selected, --scan-archives, --group, --gid \
+                 and --stream only work on local directories"
+            );
+        }
+        // Listings have no directory mtimes to validate cached subtrees with
+        args.no_cache = true;
+    }
+    cache::location::configure(&args.cache_dir_for)?;
+    cache::crypt::configure(args.cache_key_file.as_deref())?;
+    if let Some(ref command) = args.command {
+        return run_command(&args, command);
+    }
+    if args.rpc {
+        return rpc::serve(std::io::stdin().lock(), std::io::stdout(), &args);
+    }
+    if args.estimate {
+        return run_estimate(&args.path, &args);
+    }
+    if args.count_only {
+        return run_count_only(&args.path, &args);
+    }
     let root = &args.path;
+    let s3_root = is_s3_path(root);
+    if !s3_root && !confirm_long_scan(root, &args)? {
+        log::info!("Scan cancelled");
+        return Ok(());
+    }
 
     // Initialize profiling if enabled
     let mut profile = if args.profile {
@@ -116,9 +1454,10 @@ fn main() -> Result<()> {
         None
     };
 
-    // Print banner
-    eprintln!(
-        r#"
+    // The banner is only for interactive use; cron jobs and pipelines skip it
+    if log::log_enabled!(log::Level::Info) && std::io::stdout().is_terminal() {
+        eprintln!(
+            r#"
 ------------------------------------------------------------------
         .______       __    __   _______   __    __
         |   _  \     |  |  |  | |       \ |  |  |  |
@@ -129,7 +1468,8 @@ fn main() -> Result<()> {
                     Rust-based du tool
 ------------------------------------------------------------------
                     "#
-    );
+        );
+    }
 
     // Parse args → setup_thread_pool → scan_files_and_dirs → process_entries → output_results
     let setup_timer = if args.profile {
@@ -143,25 +1483,34 @@ fn main() -> Result<()> {
     if args.memory_limit.is_some() && args.threads.is_none() {
         // Use at most 2 threads in HPC mode to reduce memory pressure
         modified_args.threads = Some(std::cmp::min(2, num_cpus::get()));
-        eprintln!(
+        log::info!(
             "HPC mode: Using {} threads to minimize memory usage",
             modified_args.threads.unwrap()
         );
     }
 
+    if !args.no_fs_tuning && !s3_root {
+        apply_fs_tuning(root, &mut modified_args);
+    }
+
     setup_thread_pool(&modified_args)?;
 
     let expanded_patterns = expand_exclude_patterns(&modified_args.exclude);
-    let exclude_matcher = build_exclude_matcher(&expanded_patterns)?;
+    let exclude_matcher =
+        build_exclude_matcher(&expanded_patterns)?.with_regexes(&modified_args.exclude_regex)?;
 
     if let (Some(ref mut prof), Some(timer)) = (profile.as_mut(), setup_timer) {
         prof.add_phase(timer.finish());
     }
 
+    if args.stream {
+        return run_stream(root, &modified_args, &exclude_matcher);
+    }
+
     // Create memory monitor if memory limit is specified
     let memory_monitor = if let Some(memory_limit_mb) = modified_args.memory_limit {
-        eprintln!("Memory limit set to {} MB", memory_limit_mb);
-        eprintln!(
+        log::info!("Memory limit set to {} MB", memory_limit_mb);
+        log::warn!(
             "WARNING: HPC mode: Using conservative settings for resource-constrained environments"
         );
         let monitor = memory::MemoryMonitor::new_with_interval(
@@ -180,21 +1529,97 @@ fn main() -> Result<()> {
         None
     };
 
-    let scan_result = if memory_monitor.is_some() {
+    let options = ScanOptions::from(&modified_args);
+    let scan_result = if s3_root {
+        scan_s3(root, &options, &exclude_matcher, &modified_args.sort)?
+    } else if memory_monitor.is_some() {
         scan::scan_files_and_dirs_with_memory_monitor(
             root,
-            &modified_args,
+            &options,
             &exclude_matcher,
-            modified_args.sort,
+            &modified_args.sort,
             memory_monitor,
         )?
     } else {
-        scan_files_and_dirs(root, &modified_args, &exclude_matcher, modified_args.sort)?
+        scan_files_and_dirs(root, &options, &exclude_matcher, &modified_args.sort)?
+    };
+
+    let mut scan_meta = scan_result.meta;
+    let mut entries = scan_result.entries;
+    // Files spilled to disk under memory pressure; a plain listing merges
+    // them in as it is written, anything else needs them back in memory
+    let mut spilled = scan_result.spilled;
+    if args.largest_files.is_some() {
+        // Ranked during the scan already
+        spilled = None;
+    } else if !streams_spilled_entries(&args)
+        && let Some(spill) = spilled.take()
+    {
+        log::warn!(
+            "Reading {} spilled entries back into memory; only plain CSV, TSV and NDJSON listings are written from disk",
+            format_count(spill.len())
+        );
+        entries = spill
+            .merge(entries)?
+            .collect::<Result<_>>()
+            .context("Failed to read spilled entries")?;
+    }
+    // Read before this run's snapshot replaces it as the newest
+    let previous = args
+        .alert_growth
+        .is_some()
+        .then(|| previous_snapshot(root))
+        .flatten();
+    let mut snapshot_path = None;
+    if args.snapshot && scan_meta.partial {
+        // Directories the scan did not reach would show up as deleted trees
+        log::warn!("Not saving a snapshot of a partial scan");
+    } else if args.snapshot {
+        // Before --include and age filters, so the snapshot covers every directory
+        let path = save_snapshot(&Snapshot::from_entries(&entries, &scan_meta), &args)?;
+        log::info!("Snapshot saved to {}", path.display());
+        snapshot_path = Some(path);
+    }
+    // Like the snapshot, before archive members and filters change what the
+    // directories hold
+    let violations = match &args.budgets {
+        Some(path) => budget::Budgets::from_file(path)?.check(&entries, root),
+        None => Vec::new(),
     };
+    if args.scan_archives {
+        // After the snapshot, which records only what is on disk
+        archive::expand_archives(&mut entries, options.show_inodes);
+        scan::sort_scan_entries(&mut entries, Some(&modified_args.sort), &options);
+        scan_meta.count_entries(&entries);
+    }
+    if !args.include.is_empty() || !args.include_regex.is_empty() {
+        let include_matcher = build_exclude_matcher(&expand_exclude_patterns(&args.include))?
+            .with_regexes(&args.include_regex)?;
+        entries = filter_included(entries, root, &include_matcher);
+        scan_meta.count_entries(&entries);
+    }
+    if args.older_than.is_some() || args.newer_than.is_some() {
+        entries = filter_by_mtime(entries, root, args.older_than, args.newer_than);
+        scan_meta.count_entries(&entries);
+    }
+    if let Some(gid) = args.gid {
+        entries = filter_by_gid(entries, root, gid);
+        if strip_stat {
+            for entry in &mut entries {
+                entry.stat = None;
+            }
+        }
+        scan_meta.count_entries(&entries);
+    }
+
+    // Name the other filesystems that contributed to (or were kept out of) the totals
+    for mount in &scan_meta.mounts {
+        log::info!("Crossed mountpoint {}", mount.describe());
+    }
 
     // Check if memory limit was hit during scanning
     if scan_result.memory_limit_hit {
-        eprintln!(
+        log::warn!(
             "WARNING: Memory limit reached ({} MB). Showing partial results.",
             modified_args.memory_limit.unwrap()
         );
@@ -213,7 +1638,11 @@ fn main() -> Result<()> {
         }
 
         // Add cache statistics to profile
-        prof.set_cache_stats(scan_result.cache_hits, scan_result.cache_total);
+        prof.set_scan_meta(&scan_meta);
+
+        for (operation, stats) in &scan_result.fs_latency {
+            prof.set_fs_latency(operation, *stats);
+        }
     }
 
     // Time the processing phase
@@ -223,7 +1652,34 @@ fn main() -> Result<()> {
         None
     };
 
-    let processed_entries = process_entries(root, &args, scan_result.entries);
+    // Reports aggregate over everything walked, before depth filtering
+    let report = args
+        .report
+        .map(|kind| report::build(kind, &entries, root, &args))
+        .or_else(|| args.sparse_report.map(|n| report::sparse(&entries, n)));
+    let totals_summary = if args.sparse_report.is_some() {
+        Some(report::SparseTotals::from_entries(&entries).summary(args.size_format()))
+    } else if args.report == Some(ReportKind::Hsm) {
+        Some(report::HsmTotals::from_entries(&entries).summary(args.size_format()))
+    } else {
+        None
+    };
+    let mut processed_entries = if args.largest_files.is_some() {
+        // Ranked during the scan, whatever the depth and --summarize
+        scan_result.largest_files
+    } else {
+        process_entries(root, &args, entries)
+    };
+    send_alerts(&args, &processed_entries, &scan_meta, previous.as_ref());
+    if let Some(n) = args.top {
+        processed_entries = top_entries(processed_entries, n, |entry| {
+            entry.path != *root && (args.top_files || entry.entry_type.is_container())
+        });
+    }
+    let remaining = paginate(&mut processed_entries, args.offset, args.limit);
+    if let Some(mode) = args.delete_selected {
+        delete_selected(root, &args, mode, &mut processed_entries)?;
+    }
 
     if let (Some(ref mut prof), Some(timer)) = (profile.as_mut(), process_timer) {
         prof.add_phase(timer.finish());
@@ -236,7 +1692,50 @@ fn main() -> Result<()> {
         None
     };
 
-    output_results(&processed_entries, &args, root)?;
+    let mut entries_listed = processed_entries.len();
+    match (&report, spilled) {
+        (Some(report), _) => report::render(report, &args)?,
+        (None, Some(spilled)) => {
+            entries_listed = output_spilled(root, processed_entries, spilled, &args)?
+        }
+        (None, None) => output_results(&processed_entries, &args, &scan_meta, profile.as_ref())?,
+    }
+    if let Some(summary) = totals_summary {
+        // Kept out of CSV written to stdout, like the pagination note below
+        if args.stdout_is_terminal_listing() {
+            println!("{}", summary);
+        } else {
+            eprintln!("{}", summary);
+        }
+    }
+    if remaining > 0 {
+        let note = format!(
+            "… and {} more {}",
+            format_count(remaining),
+            if remaining == 1 { "entry" } else { "entries" }
+        );
+        // Only a terminal listing on stdout gets the note inline; elsewhere
+        // it would corrupt machine-readable output or NUL-separated records
+        if args.stdout_is_terminal_listing() {
+            println!("{}", note);
+        } else {
+            eprintln!("{}", note);
+        }
+    }
+
+    if args.budgets.is_some() {
+        print_budget_violations(&violations, &args)?;
+    }
+    if args.deleted_open {
+        report_deleted_open_files(root, &args);
+    }
+
+    // Every file written by this run, primary output first
+    let mut artifacts: Vec<(&str, PathBuf)> = Vec::new();
+    if let Some(ref output_path) = args.output {
+        artifacts.push(("results", PathBuf::from(output_path)));
+    }
+    artifacts.extend(snapshot_path.map(|path| ("snapshot", path)));
 
     if let (Some(ref mut prof), Some(timer)) = (profile.as_mut(), output_timer) {
         prof.add_phase(timer.finish());
@@ -247,22 +1746,43 @@ fn main() -> Result<()> {
         prof.memory_peak = rss_after_phase();
 
         // Add metadata about the scan
-        prof.add_metadata("entries_processed", &processed_entries.len().to_string());
-        prof.add_metadata("root_path", &root.display().to_string());
-        if let Some(depth) = args.depth {
-            prof.add_metadata("max_depth", &depth.to_string());
+        prof.add_metadata("entries_processed", &entries_listed.to_string());
+        for (key, value) in thread_pool::current_config().metadata() {
+            prof.add_metadata(key, &value);
         }
 
         // Display profile summary
-        print_profile_summary(&prof);
+        // Keep stdout clean when it carries machine-readable output
+        if args.output.is_none() && !args.stdout_is_terminal_listing() {
+            print_profile_summary(&mut std::io::stderr().lock(), &prof)?;
+        } else {
+            print_profile_summary(&mut std::io::stdout().lock(), &prof)?;
+        }
 
         // Save stats.json if output is being written to a file
         if let Some(ref output_path) = args.output {
-            if let Err(e) = save_stats_json(std::path::Path::new(output_path), &prof) {
-                eprintln!("Failed to save stats.json: {}", e);
+            match save_stats_json(Path::new(output_path), &prof) {
+                Ok(stats_path) => artifacts.push(("stats", stats_path)),
+                Err(e) => log::error!("Failed to save stats.json: {}", e),
             }
         }
     }
 
+    if let Err(e) = write_manifest(&scan_meta, &artifacts) {
+        log::error!("Failed to write run manifest: {}", e);
+    }
+
+    if !violations.is_empty() {
+        // A nonzero exit lets CI jobs and cron scripts act on it
+        anyhow::bail!(
+            "{} {} over budget",
+            format_count(violations.len()),
+            if violations.len() == 1 {
+                "directory is"
+            } else {
+                "directories are"
+            }
+        );
+    }
     Ok(())
 }
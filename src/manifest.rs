@@ -0,0 +1,196 @@
+//! Run manifest describing every artifact produced by a single `rudu` invocation.
+//!
+//! When a run writes more than one file (for example the CSV results plus
+//! `stats.json`), a `manifest.json` is written alongside them listing each
+//! artifact with its size and SHA-256 checksum together with the run ID.
+//! This makes it straightforward to archive a run or hand its outputs to a
+//! downstream pipeline and verify nothing was truncated or swapped in transit.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use rudu::manifest::{Manifest, generate_run_id};
+//! use std::path::Path;
+//!
+//! let mut manifest = Manifest::new(generate_run_id(), Path::new("/data"));
+//! manifest.add_artifact("results", Path::new("out.csv")).unwrap();
+//! manifest.add_artifact("stats", Path::new("stats.json")).unwrap();
+//! manifest.save(Path::new("manifest.json")).unwrap();
+//! ```
+
+use crate::data::ScanMeta;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+/// File name used for the manifest, written next to the primary output.
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// A single file produced during a run.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Artifact {
+    /// What the file contains (e.g. `"results"`, `"stats"`)
+    pub kind: String,
+    /// Path to the file as it was written
+    pub path: PathBuf,
+    /// Size of the file in bytes
+    pub size_bytes: u64,
+    /// Lowercase hex SHA-256 digest of the file contents
+    pub sha256: String,
+}
+
+/// Summary of all artifacts written by one run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Unique identifier for the run that produced these artifacts
+    pub run_id: String,
+    /// Version of rudu that produced the run
+    pub rudu_version: String,
+    /// RFC 3339 timestamp of when the manifest was created
+    pub created_at: String,
+    /// The root path that was scanned
+    pub root_path: PathBuf,
+    /// Every artifact written during the run, in the order they were produced
+    pub artifacts: Vec<Artifact>,
+}
+
+impl Manifest {
+    /// Creates an empty manifest for the given run.
+    ///
+    /// # Arguments
+    /// * `run_id` - Identifier for the run (see [`generate_run_id`])
+    /// * `root_path` - The root path that was scanned
+    pub fn new(run_id: String, root_path: &Path) -> Self {
+        Self {
+            run_id,
+            rudu_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            root_path: root_path.to_path_buf(),
+            artifacts: Vec::new(),
+        }
+    }
+
+    /// Creates an empty manifest for the run described by `meta`.
+    pub fn for_scan(meta: &ScanMeta) -> Self {
+        Self::new(meta.run_id.clone(), &meta.root)
+    }
+
+    /// Records an artifact, computing its size and checksum from disk.
+    ///
+    /// # Arguments
+    /// * `kind` - Short description of what the file contains
+    /// * `path` - Path to the already-written file
+    ///
+    /// # Returns
+    /// * `Result<()>` - Error if the file cannot be read
+    pub fn add_artifact(&mut self, kind: &str, path: &Path) -> Result<()> {
+        let size_bytes = std::fs::metadata(path)
+            .with_context(|| format!("Failed to stat artifact: {}", path.display()))?
+            .len();
+        let sha256 = sha256_file(path)?;
+
+        self.artifacts.push(Artifact {
+            kind: kind.to_string(),
+            path: path.to_path_buf(),
+            size_bytes,
+            sha256,
+        });
+        Ok(())
+    }
+
+    /// Writes the manifest as pretty-printed JSON to `path`.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize manifest")?;
+        std::fs::write(path, json)
+            .with_context(|| format!("Failed to write manifest: {}", path.display()))
+    }
+}
+
+/// Generates a run identifier from the current UTC time and process ID.
+///
+/// The format (`YYYYMMDDTHHMMSSZ-<pid>`) sorts chronologically and is unique
+/// for all practical purposes on a single host.
+pub fn generate_run_id() -> String {
+    format!(
+        "{}-{}",
+        chrono::Utc::now().format("%Y%m%dT%H%M%SZ"),
+        std::process::id()
+    )
+}
+
+/// Computes the lowercase hex SHA-256 digest of a file's contents.
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let file =
+        File::open(path).with_context(|| format!("Failed to open artifact: {}", path.display()))?;
+    let mut reader = BufReader::new(file);
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+
+    loop {
+        let n = reader
+            .read(&mut buf)
+            .with_context(|| format!("Failed to read artifact: {}", path.display()))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_sha256_file_known_digest() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("abc.txt");
+        std::fs::write(&path, "abc").unwrap();
+
+        assert_eq!(
+            sha256_file(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_manifest_records_artifacts_and_round_trips() {
+        let dir = tempdir().unwrap();
+        let results = dir.path().join("out.csv");
+        let stats = dir.path().join("stats.json");
+        std::fs::write(&results, "entry_type,size_bytes\n").unwrap();
+        std::fs::write(&stats, "{}").unwrap();
+
+        let mut manifest = Manifest::new("run-1".to_string(), dir.path());
+        manifest.add_artifact("results", &results).unwrap();
+        manifest.add_artifact("stats", &stats).unwrap();
+
+        let manifest_path = dir.path().join(MANIFEST_FILE_NAME);
+        manifest.save(&manifest_path).unwrap();
+
+        let loaded: Manifest =
+            serde_json::from_str(&std::fs::read_to_string(&manifest_path).unwrap()).unwrap();
+        assert_eq!(loaded.run_id, "run-1");
+        assert_eq!(loaded.artifacts.len(), 2);
+        assert_eq!(loaded.artifacts[0].kind, "results");
+        assert_eq!(loaded.artifacts[0].size_bytes, 22);
+        assert_eq!(loaded.artifacts[1].sha256, sha256_file(&stats).unwrap());
+    }
+
+    #[test]
+    fn test_add_artifact_missing_file_errors() {
+        let mut manifest = Manifest::new(generate_run_id(), Path::new("/"));
+        assert!(
+            manifest
+                .add_artifact("results", Path::new("/nonexistent/rudu-artifact"))
+                .is_err()
+        );
+        assert!(manifest.artifacts.is_empty());
+    }
+}
@@ -1,11 +1,19 @@
 use std::time::{Duration, Instant};
+#[cfg(feature = "sysinfo")]
 use sysinfo::{Pid, System};
 
+/// Tracks the process RSS against a limit.
+///
+/// Without the `sysinfo` feature the RSS is never available, so the limit
+/// is never reported as reached.
+#[cfg_attr(not(feature = "sysinfo"), allow(dead_code))]
 pub struct MemoryMonitor {
     limit_bytes: u64,
     warn_threshold: f64,
     last_check: Instant,
+    #[cfg(feature = "sysinfo")]
     system: System,
+    #[cfg(feature = "sysinfo")]
     pid: Pid,
     check_interval: Duration,
 }
@@ -19,9 +27,12 @@ impl MemoryMonitor {
 
     /// Create a new MemoryMonitor with the specified limit in MB and check interval in ms
     pub fn new_with_interval(limit_mb: u64, check_interval_ms: u64) -> Self {
+        #[cfg(feature = "sysinfo")]
         let mut system = System::new_all();
+        #[cfg(feature = "sysinfo")]
         system.refresh_processes();
 
+        #[cfg(feature = "sysinfo")]
         let pid = Pid::from(std::process::id() as usize);
         let check_interval = Duration::from_millis(check_interval_ms);
 
@@ -29,12 +40,23 @@ impl MemoryMonitor {
             limit_bytes: limit_mb * 1024 * 1024, // Convert MB to bytes
             warn_threshold: 0.95,                // 95% threshold for nearing_limit
             last_check: Instant::now() - check_interval, // Allow immediate first check
+            #[cfg(feature = "sysinfo")]
             system,
+            #[cfg(feature = "sysinfo")]
             pid,
             check_interval,
         }
     }
 
+    /// Sets the fraction of the limit at which [`Self::nearing_limit`]
+    /// reports true (0.95 by default), where scans start spilling entries to
+    /// disk.
+    #[allow(dead_code)] // the CLI keeps the default; kept for library users
+    pub fn with_warn_threshold(mut self, fraction: f64) -> Self {
+        self.warn_threshold = fraction.clamp(0.0, 1.0);
+        self
+    }
+
     /// Returns true if memory usage is at or above 100% of the limit
     ///
     /// Returns false if RSS is not available (None), effectively bypassing
@@ -76,6 +98,7 @@ impl MemoryMonitor {
     ///
     /// Returns None if RSS is not available on this platform, signaling that
     /// memory monitoring should be bypassed entirely.
+    #[cfg(feature = "sysinfo")]
     fn get_current_memory_usage(&mut self) -> Option<u64> {
         let now = Instant::now();
 
@@ -91,6 +114,11 @@ impl MemoryMonitor {
         self.system.process(self.pid).map(|p| p.memory())
     }
 
+    #[cfg(not(feature = "sysinfo"))]
+    fn get_current_memory_usage(&mut self) -> Option<u64> {
+        None
+    }
+
     #[cfg(test)]
     /// Mock version of exceeds_limit for testing with controlled memory values
     pub fn exceeds_limit_with_mock<F>(&self, get_usage: F) -> bool
@@ -120,6 +148,7 @@ impl MemoryMonitor {
 }
 
 #[cfg(test)]
+#[allow(clippy::overly_complex_bool_expr, clippy::nonminimal_bool)]
 mod tests {
     use super::*;
     use std::thread;
@@ -4,6 +4,7 @@
 //! - [`PhaseTimer`] - A wrapper around `Instant` for timing different phases
 //! - [`rss_after_phase`] - Optional memory usage tracking using `sysinfo`
 //! - [`ProfileData`] - Structured data for performance metrics
+//! - [`LatencyHistogram`] - Lock-free latency recording with percentile summaries
 //! - [`print_profile_summary`] - Terminal output for profiling results
 //! - [`save_stats_json`] - JSON output for scripting integration
 //!
@@ -21,10 +22,14 @@
 //! profile.memory_peak = rss_after_phase();
 //! ```
 
+use crate::data::ScanMeta;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, Instant};
+#[cfg(feature = "sysinfo")]
 use sysinfo::System;
 
 /// A timer for measuring the duration of a specific phase or operation.
@@ -121,6 +126,12 @@ pub struct ProfileData {
     pub cache_total: u64,
     /// Additional metadata about the scan
     pub metadata: HashMap<String, String>,
+    /// Filesystem call latency percentiles keyed by operation (e.g. `"stat"`)
+    #[serde(default)]
+    pub fs_latency: BTreeMap<String, LatencyStats>,
+    /// Metadata of the profiled scan, once it has finished
+    #[serde(default)]
+    pub scan: Option<ScanMeta>,
 }
 
 impl ProfileData {
@@ -132,6 +143,8 @@ impl ProfileData {
             cache_hits: 0,
             cache_total: 0,
             metadata: HashMap::new(),
+            fs_latency: BTreeMap::new(),
+            scan: None,
         }
     }
 
@@ -153,6 +166,23 @@ impl ProfileData {
         self.cache_total = total;
     }
 
+    /// Records the metadata of the profiled scan.
+    ///
+    /// Also fills the cache statistics and the `root_path`, `run_id` and
+    /// `max_depth` metadata keys from it.
+    ///
+    /// # Arguments
+    /// * `meta` - Metadata produced by the scan
+    pub fn set_scan_meta(&mut self, meta: &ScanMeta) {
+        self.set_cache_stats(meta.cache_hits, meta.cache_total);
+        self.add_metadata("root_path", &meta.root.display().to_string());
+        self.add_metadata("run_id", &meta.run_id);
+        if let Some(depth) = meta.flags.depth {
+            self.add_metadata("max_depth", &depth.to_string());
+        }
+        self.scan = Some(meta.clone());
+    }
+
     /// Adds a metadata entry to the profile.
     ///
     /// # Arguments
@@ -162,6 +192,15 @@ impl ProfileData {
         self.metadata.insert(key.to_string(), value.to_string());
     }
 
+    /// Records latency percentiles for a filesystem operation.
+    ///
+    /// # Arguments
+    /// * `operation` - The operation name (e.g. `"stat"`, `"readdir"`)
+    /// * `stats` - The summarized latencies
+    pub fn set_fs_latency(&mut self, operation: &str, stats: LatencyStats) {
+        self.fs_latency.insert(operation.to_string(), stats);
+    }
+
     /// Calculates the cache hit rate as a percentage.
     ///
     /// # Returns
@@ -189,6 +228,130 @@ impl Default for ProfileData {
     }
 }
 
+/// Sub-buckets per power of two; 8 gives roughly 12% resolution.
+const LATENCY_SUB_BUCKETS: u64 = 8;
+const LATENCY_LINEAR_LIMIT: u64 = 2 * LATENCY_SUB_BUCKETS;
+const LATENCY_BUCKETS: usize = (LATENCY_LINEAR_LIMIT + 61 * LATENCY_SUB_BUCKETS) as usize;
+
+/// A thread-safe, fixed-memory histogram of operation latencies.
+///
+/// Durations are recorded in nanoseconds into log-linear buckets, so recording
+/// is a single atomic increment and memory use does not grow with the number
+/// of samples. Percentiles are therefore approximate, within one bucket width.
+#[derive(Debug)]
+pub struct LatencyHistogram {
+    buckets: Box<[AtomicU64]>,
+    max_ns: AtomicU64,
+}
+
+/// Percentile summary of a [`LatencyHistogram`], in microseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatencyStats {
+    /// Number of recorded samples
+    pub samples: u64,
+    /// Median latency
+    pub p50_us: f64,
+    /// 95th percentile latency
+    pub p95_us: f64,
+    /// 99th percentile latency
+    pub p99_us: f64,
+    /// Slowest recorded latency
+    pub max_us: f64,
+}
+
+impl LatencyHistogram {
+    /// Creates an empty histogram.
+    pub fn new() -> Self {
+        Self {
+            buckets: (0..LATENCY_BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+            max_ns: AtomicU64::new(0),
+        }
+    }
+
+    /// Records one sample.
+    pub fn record(&self, duration: Duration) {
+        let ns = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        self.buckets[latency_bucket(ns)].fetch_add(1, Ordering::Relaxed);
+        self.max_ns.fetch_max(ns, Ordering::Relaxed);
+    }
+
+    /// Runs `f`, recording how long it took, and returns its result.
+    pub fn time<T>(&self, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.record(start.elapsed());
+        result
+    }
+
+    /// Returns the number of recorded samples.
+    pub fn samples(&self) -> u64 {
+        self.buckets.iter().map(|b| b.load(Ordering::Relaxed)).sum()
+    }
+
+    /// Summarizes the recorded samples as percentiles.
+    ///
+    /// # Returns
+    /// `None` if no samples were recorded.
+    pub fn stats(&self) -> Option<LatencyStats> {
+        let counts: Vec<u64> = self
+            .buckets
+            .iter()
+            .map(|b| b.load(Ordering::Relaxed))
+            .collect();
+        let samples: u64 = counts.iter().sum();
+        if samples == 0 {
+            return None;
+        }
+
+        let max_ns = self.max_ns.load(Ordering::Relaxed);
+        let percentile = |p: f64| {
+            let rank = ((samples as f64 * p).ceil() as u64).max(1);
+            let mut seen = 0;
+            for (bucket, count) in counts.iter().enumerate() {
+                seen += count;
+                if seen >= rank {
+                    return latency_bucket_midpoint(bucket).min(max_ns) as f64 / 1000.0;
+                }
+            }
+            max_ns as f64 / 1000.0
+        };
+
+        Some(LatencyStats {
+            samples,
+            p50_us: percentile(0.50),
+            p95_us: percentile(0.95),
+            p99_us: percentile(0.99),
+            max_us: max_ns as f64 / 1000.0,
+        })
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn latency_bucket(ns: u64) -> usize {
+    if ns < LATENCY_LINEAR_LIMIT {
+        return ns as usize;
+    }
+    let exp = 63 - ns.leading_zeros() as u64;
+    let sub = (ns >> (exp - 3)) & (LATENCY_SUB_BUCKETS - 1);
+    (LATENCY_LINEAR_LIMIT + (exp - 4) * LATENCY_SUB_BUCKETS + sub) as usize
+}
+
+fn latency_bucket_midpoint(bucket: usize) -> u64 {
+    let bucket = bucket as u64;
+    if bucket < LATENCY_LINEAR_LIMIT {
+        return bucket;
+    }
+    let exp = (bucket - LATENCY_LINEAR_LIMIT) / LATENCY_SUB_BUCKETS + 4;
+    let sub = (bucket - LATENCY_LINEAR_LIMIT) % LATENCY_SUB_BUCKETS;
+    let width = 1u64 << (exp - 3);
+    ((LATENCY_SUB_BUCKETS + sub) << (exp - 3)) + width / 2
+}
+
 /// Measures the current process's RSS (Resident Set Size) memory usage.
 ///
 /// This function uses the `sysinfo` crate to get the current process's
@@ -206,12 +369,15 @@ impl Default for ProfileData {
 /// # Returns
 /// The current RSS memory usage in bytes, or `None` if the information
 /// is not available on this platform or if an error occurs.
-#[cfg(any(
-    target_os = "linux",
-    target_os = "macos",
-    target_os = "freebsd",
-    target_os = "netbsd",
-    target_os = "openbsd"
+#[cfg(all(
+    feature = "sysinfo",
+    any(
+        target_os = "linux",
+        target_os = "macos",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    )
 ))]
 pub fn rss_after_phase() -> Option<u64> {
     let mut system = System::new_all();
@@ -235,7 +401,7 @@ pub fn rss_after_phase() -> Option<u64> {
 ///
 /// On Windows, RSS reporting may be less reliable due to differences in
 /// memory management and system API behavior across Windows versions.
-#[cfg(target_os = "windows")]
+#[cfg(all(feature = "sysinfo", target_os = "windows"))]
 pub fn rss_after_phase() -> Option<u64> {
     let mut system = System::new_all();
     system.refresh_processes();
@@ -254,14 +420,18 @@ pub fn rss_after_phase() -> Option<u64> {
     None
 }
 
-/// Fallback implementation for unsupported platforms
-#[cfg(not(any(
-    target_os = "linux",
-    target_os = "macos",
-    target_os = "freebsd",
-    target_os = "netbsd",
-    target_os = "openbsd",
-    target_os = "windows"
+/// Fallback implementation for unsupported platforms and builds without
+/// the `sysinfo` feature
+#[cfg(not(all(
+    feature = "sysinfo",
+    any(
+        target_os = "linux",
+        target_os = "macos",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "windows"
+    )
 )))]
 pub fn rss_after_phase() -> Option<u64> {
     // On unsupported platforms, return None to signal that memory monitoring
@@ -272,9 +442,11 @@ pub fn rss_after_phase() -> Option<u64> {
 /// Prints a formatted profile summary to the terminal.
 ///
 /// This function outputs a human-readable summary of the profiling data,
-/// including phase timings, memory usage, and cache statistics.
+/// including phase timings, memory usage, and cache statistics. It goes to
+/// stdout, or to stderr when stdout carries machine-readable output.
 ///
 /// # Arguments
+/// * `out` - Destination for the summary
 /// * `profile` - The profile data to display
 ///
 /// # Example Output
@@ -285,37 +457,68 @@ pub fn rss_after_phase() -> Option<u64> {
 ///   Aggregation      30 ms
 /// Memory peak:      42 MB
 /// Cache hits:       8123 / 9000 (90.3 %)
+///
+/// Filesystem latency (µs)
+///                        p50      p95      p99      max   samples
+///   readdir             42.0    310.5   1822.0   9120.3      1204
+///   stat                 3.1     18.7    240.2   4410.8     58211
 /// ```
-pub fn print_profile_summary(profile: &ProfileData) {
-    println!("\nScan phase timings");
+pub fn print_profile_summary<W: Write + ?Sized>(
+    out: &mut W,
+    profile: &ProfileData,
+) -> io::Result<()> {
+    writeln!(out, "\nScan phase timings")?;
 
     for phase in &profile.phases {
-        println!("  {:<15} {:>7} ms", phase.name, phase.duration.as_millis());
+        writeln!(
+            out,
+            "  {:<15} {:>7} ms",
+            phase.name,
+            phase.duration.as_millis()
+        )?;
     }
 
     if let Some(memory_peak) = profile.memory_peak {
         let memory_mb = memory_peak as f64 / (1024.0 * 1024.0);
-        println!("Memory peak:      {:.1} MB", memory_mb);
+        writeln!(out, "Memory peak:      {:.1} MB", memory_mb)?;
     }
 
     if profile.cache_total > 0 {
-        println!(
+        writeln!(
+            out,
             "Cache hits:       {} / {} ({:.1}%)",
             profile.cache_hits,
             profile.cache_total,
             profile.cache_hit_rate()
-        );
+        )?;
+    }
+
+    if !profile.fs_latency.is_empty() {
+        writeln!(out, "\nFilesystem latency (µs)")?;
+        writeln!(
+            out,
+            "  {:<15} {:>8} {:>8} {:>8} {:>8} {:>9}",
+            "", "p50", "p95", "p99", "max", "samples"
+        )?;
+        for (operation, stats) in &profile.fs_latency {
+            writeln!(
+                out,
+                "  {:<15} {:>8.1} {:>8.1} {:>8.1} {:>8.1} {:>9}",
+                operation, stats.p50_us, stats.p95_us, stats.p99_us, stats.max_us, stats.samples
+            )?;
+        }
     }
 
     // Print any additional metadata
     if !profile.metadata.is_empty() {
-        println!("\nAdditional metrics:");
+        writeln!(out, "\nAdditional metrics:")?;
         for (key, value) in &profile.metadata {
-            println!("  {:<15} {}", key, value);
+            writeln!(out, "  {:<15} {}", key, value)?;
         }
     }
 
-    println!(); // Extra newline for readability
+    writeln!(out)?; // Extra newline for readability
+    Ok(())
 }
 
 /// Saves profiling statistics to a JSON file for scripting integration.
@@ -329,7 +532,7 @@ pub fn print_profile_summary(profile: &ProfileData) {
 /// * `profile` - The profile data to save
 ///
 /// # Returns
-/// `Ok(())` if the file was written successfully, or an error if writing failed.
+/// The path of the written `stats.json` file, or an error if writing failed.
 ///
 /// # Example
 /// If the main output is being written to `results.csv`, this function
@@ -337,7 +540,7 @@ pub fn print_profile_summary(profile: &ProfileData) {
 pub fn save_stats_json(
     output_path: &Path,
     profile: &ProfileData,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
     let stats_path = output_path.with_file_name("stats.json");
 
     // Create a structured stats object for JSON output
@@ -349,15 +552,17 @@ pub fn save_stats_json(
         "cache_hits": profile.cache_hits,
         "cache_total": profile.cache_total,
         "cache_hit_rate": profile.cache_hit_rate(),
+        "fs_latency_us": profile.fs_latency,
         "metadata": profile.metadata,
+        "scan": profile.scan,
         "timestamp": chrono::Utc::now().to_rfc3339()
     });
 
     std::fs::write(&stats_path, serde_json::to_string_pretty(&stats)?)?;
 
-    eprintln!("Performance stats saved to: {}", stats_path.display());
+    log::info!("Performance stats saved to: {}", stats_path.display());
 
-    Ok(())
+    Ok(stats_path)
 }
 
 /// A convenience macro for timing a block of code.
@@ -398,6 +603,58 @@ mod tests {
     use std::thread;
     use std::time::Duration;
 
+    #[test]
+    fn test_set_scan_meta() {
+        let mut meta = ScanMeta {
+            run_id: "20260101T000000Z-1".to_string(),
+            root: PathBuf::from("/data"),
+            cache_hits: 3,
+            cache_total: 4,
+            ..ScanMeta::default()
+        };
+        meta.flags.depth = Some(2);
+
+        let mut profile = ProfileData::new();
+        profile.set_scan_meta(&meta);
+
+        assert_eq!(profile.cache_hit_rate(), 75.0);
+        assert_eq!(profile.metadata["run_id"], meta.run_id);
+        assert_eq!(profile.metadata["root_path"], "/data");
+        assert_eq!(profile.metadata["max_depth"], "2");
+        assert_eq!(profile.scan, Some(meta));
+    }
+
+    #[test]
+    fn test_latency_histogram_percentiles() {
+        let histogram = LatencyHistogram::new();
+        assert!(histogram.stats().is_none());
+
+        for us in 1..=100 {
+            histogram.record(Duration::from_micros(us));
+        }
+
+        let stats = histogram.stats().unwrap();
+        assert_eq!(stats.samples, 100);
+        assert_eq!(stats.max_us, 100.0);
+        // Buckets are ~12% wide, so allow that much error
+        assert!((stats.p50_us - 50.0).abs() <= 50.0 * 0.13, "{:?}", stats);
+        assert!((stats.p95_us - 95.0).abs() <= 95.0 * 0.13, "{:?}", stats);
+        assert!((stats.p99_us - 99.0).abs() <= 99.0 * 0.13, "{:?}", stats);
+        assert!(stats.p50_us <= stats.p95_us && stats.p95_us <= stats.p99_us);
+        assert!(stats.p99_us <= stats.max_us);
+    }
+
+    #[test]
+    fn test_latency_bucket_boundaries() {
+        for ns in [0u64, 1, 15, 16, 17, 1000, 123_456, u64::MAX / 2, u64::MAX] {
+            let bucket = latency_bucket(ns);
+            assert!(bucket < LATENCY_BUCKETS, "ns={} bucket={}", ns, bucket);
+            assert!(bucket >= latency_bucket(ns / 2));
+        }
+        assert_eq!(latency_bucket(5), 5);
+        assert_eq!(latency_bucket_midpoint(5), 5);
+    }
+
     #[test]
     fn test_phase_timer() {
         let timer = PhaseTimer::new("test_phase");
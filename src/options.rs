@@ -0,0 +1,335 @@
+//! Scan options for library users, independent of the command line.
+//!
+//! The scanning functions in [`crate::scan`] take a [`ScanOptions`] rather
+//! than the clap [`Args`] struct, so embedding rudu does not mean filling in
+//! every CLI-only flag, and new flags do not break library callers. Options
+//! are built from defaults matching a plain `rudu PATH` run:
+//!
+//! ```
+//! use rudu::ScanOptions;
+//!
+//! let options = ScanOptions::new()
+//!     .depth(2)
+//!     .show_owner(true)
+//!     .exclude(["node_modules", ".git"])
+//!     .no_cache(true);
+//! assert_eq!(options.depth, Some(2));
+//! ```
+//!
+//! The CLI converts its arguments with `ScanOptions::from(&args)` (`cli`
+//! feature).
+
+use crate::cache::{CacheLocation, CacheValidation};
+#[cfg(feature = "cli")]
+use crate::cli::Args;
+use crate::thread_pool::ThreadPoolStrategy;
+
+/// Entries walked between memory limit checks by default
+pub const DEFAULT_MEMORY_CHECK_ENTRIES: usize = 1000;
+
+/// Options controlling what a scan walks, caches and reports.
+///
+/// Fields can be read directly; new ones may be added in any release, so
+/// options are only constructed through [`ScanOptions::new`] (or `Default`)
+/// and the builder methods.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ScanOptions {
+    /// Deepest directory level restored from the cache (`None` = unlimited)
+    pub depth: Option<usize>,
+    /// Whether files are reported as well as directories (recorded in the scan metadata)
+    pub show_files: bool,
+    /// Resolve the owner of every entry
+    pub show_owner: bool,
+    /// Count the entries below each directory
+    pub show_inodes: bool,
+    /// Record atime, link count, group, mode, device, inode and apparent size
+    /// of every entry
+    pub extended_metadata: bool,
+    /// Only report the root, like `du -s`
+    pub summarize: bool,
+    /// Keep the N largest files in [`ScanResult::largest_files`](crate::scan::ScanResult)
+    pub largest_files: Option<usize>,
+    /// Reverse the sort order
+    pub reverse: bool,
+    /// Break sort ties by full path, and sort by path without sort keys
+    pub deterministic: bool,
+    /// Glob patterns and directory names to skip
+    pub exclude: Vec<String>,
+    /// Names or globs of mountpoints whose filesystems are skipped
+    pub exclude_mounts: Vec<String>,
+    /// Scan directories marked with `.ruduignore` or `CACHEDIR.TAG`
+    pub no_exclude_markers: bool,
+    /// Neither read nor write the cache
+    pub no_cache: bool,
+    /// Age in seconds after which the cache is ignored
+    pub cache_ttl: u64,
+    /// Where the cache is kept
+    pub cache_location: CacheLocation,
+    /// How cached directories are checked for changes
+    pub cache_validation: CacheValidation,
+    /// Cache file size limit in megabytes
+    pub cache_max_size: Option<u64>,
+    /// Scanning strategy; `WorkStealingUneven` selects the work-stealing scanner
+    pub threads_strategy: ThreadPoolStrategy,
+    /// Least number of files stat'd by one parallel task
+    pub stat_batch: usize,
+    /// Take file attributes from the kernel's attribute cache, as filled by
+    /// NFS READDIRPLUS, instead of revalidating each file with the server
+    pub cached_attrs: bool,
+    /// Sample filesystem latencies for the profile
+    pub profile: bool,
+    /// Entries walked between checks of a memory monitor
+    pub memory_check_entries: usize,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            depth: None,
+            show_files: true,
+            show_owner: false,
+            show_inodes: false,
+            extended_metadata: false,
+            summarize: false,
+            largest_files: None,
+            reverse: false,
+            deterministic: false,
+            exclude: Vec::new(),
+            exclude_mounts: Vec::new(),
+            no_exclude_markers: false,
+            no_cache: false,
+            cache_ttl: 604800,
+            cache_location: CacheLocation::Xdg,
+            cache_validation: CacheValidation::Fast,
+            cache_max_size: None,
+            threads_strategy: ThreadPoolStrategy::Default,
+            stat_batch: 1,
+            cached_attrs: false,
+            profile: false,
+            memory_check_entries: DEFAULT_MEMORY_CHECK_ENTRIES,
+        }
+    }
+}
+
+impl ScanOptions {
+    /// Options of a plain `rudu PATH` run
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits restored cache entries to `depth` levels below the root
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    /// Records whether files are reported as well as directories
+    pub fn show_files(mut self, show_files: bool) -> Self {
+        self.show_files = show_files;
+        self
+    }
+
+    /// Resolves the owner of every entry
+    pub fn show_owner(mut self, show_owner: bool) -> Self {
+        self.show_owner = show_owner;
+        self
+    }
+
+    /// Counts the entries below each directory
+    pub fn show_inodes(mut self, show_inodes: bool) -> Self {
+        self.show_inodes = show_inodes;
+        self
+    }
+
+    /// Records atime, link count, group, mode, device, inode and apparent
+    /// size of every entry in [`FileEntry::stat`](crate::FileEntry::stat)
+    pub fn extended_metadata(mut self, extended_metadata: bool) -> Self {
+        self.extended_metadata = extended_metadata;
+        self
+    }
+
+    /// Only reports the root
+    pub fn summarize(mut self, summarize: bool) -> Self {
+        self.summarize = summarize;
+        self
+    }
+
+    /// Keeps the `n` largest files of the scan, whatever their depth and even
+    /// with `summarize`, in [`ScanResult::largest_files`](crate::scan::ScanResult).
+    ///
+    /// Files in subtrees served from the cache are not walked and so not
+    /// ranked; combine with `no_cache(true)` to rank every file.
+    pub fn largest_files(mut self, n: usize) -> Self {
+        self.largest_files = Some(n);
+        self
+    }
+
+    /// Reverses the sort order
+    pub fn reverse(mut self, reverse: bool) -> Self {
+        self.reverse = reverse;
+        self
+    }
+
+    /// Orders entries stably across runs, breaking ties by full path
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Skips paths matching these glob patterns or directory names
+    pub fn exclude<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.exclude = patterns.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Skips filesystems mounted on directories with these names or paths
+    pub fn exclude_mounts<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.exclude_mounts = patterns.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Scans directories marked with `.ruduignore` or `CACHEDIR.TAG`
+    pub fn no_exclude_markers(mut self, no_exclude_markers: bool) -> Self {
+        self.no_exclude_markers = no_exclude_markers;
+        self
+    }
+
+    /// Disables the cache
+    pub fn no_cache(mut self, no_cache: bool) -> Self {
+        self.no_cache = no_cache;
+        self
+    }
+
+    /// Ignores caches older than `seconds`
+    pub fn cache_ttl(mut self, seconds: u64) -> Self {
+        self.cache_ttl = seconds;
+        self
+    }
+
+    /// Keeps the cache in `location`
+    pub fn cache_location(mut self, location: CacheLocation) -> Self {
+        self.cache_location = location;
+        self
+    }
+
+    /// Checks cached directories with `validation`
+    pub fn cache_validation(mut self, validation: CacheValidation) -> Self {
+        self.cache_validation = validation;
+        self
+    }
+
+    /// Caps the cache file at `megabytes`
+    pub fn cache_max_size(mut self, megabytes: u64) -> Self {
+        self.cache_max_size = Some(megabytes);
+        self
+    }
+
+    /// Picks the scanning strategy
+    pub fn threads_strategy(mut self, strategy: ThreadPoolStrategy) -> Self {
+        self.threads_strategy = strategy;
+        self
+    }
+
+    /// Stats files in parallel tasks of at least `files` files.
+    ///
+    /// Larger batches cut scheduling overhead where a `stat` is cheap;
+    /// batches of one keep the most requests in flight on network
+    /// filesystems (see [`FsTuning`](crate::tuning::FsTuning)).
+    pub fn stat_batch(mut self, files: usize) -> Self {
+        self.stat_batch = files.max(1);
+        self
+    }
+
+    /// Stats files from the kernel's attribute cache without revalidating
+    /// them.
+    ///
+    /// On NFS this uses the attributes that READDIRPLUS returned while
+    /// listing the directory instead of sending a GETATTR per file, at the
+    /// cost of sizes up to the mount's `acregmax` old (see
+    /// [`disk_usage_and_cached_stat`](crate::utils::disk_usage_and_cached_stat)).
+    pub fn cached_attrs(mut self, cached_attrs: bool) -> Self {
+        self.cached_attrs = cached_attrs;
+        self
+    }
+
+    /// Samples filesystem latencies
+    pub fn profile(mut self, profile: bool) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Checks a memory monitor every `entries` walked entries
+    pub fn memory_check_entries(mut self, entries: usize) -> Self {
+        self.memory_check_entries = entries;
+        self
+    }
+}
+
+#[cfg(feature = "cli")]
+impl From<&Args> for ScanOptions {
+    fn from(args: &Args) -> Self {
+        Self {
+            depth: args.depth,
+            show_files: args.show_files,
+            show_owner: args.show_owner,
+            show_inodes: args.show_inodes,
+            extended_metadata: args.extended_metadata,
+            summarize: args.summarize,
+            largest_files: args.largest_files,
+            reverse: args.reverse,
+            deterministic: args.deterministic,
+            exclude: args.exclude.clone(),
+            exclude_mounts: args.exclude_mounts.clone(),
+            no_exclude_markers: args.no_exclude_markers,
+            no_cache: args.no_cache,
+            cache_ttl: args.cache_ttl,
+            cache_location: args.cache_location,
+            cache_validation: args.cache_validation,
+            cache_max_size: args.cache_max_size,
+            threads_strategy: args.threads_strategy,
+            stat_batch: args.stat_batch.unwrap_or(1).max(1),
+            cached_attrs: args.cached_attrs.unwrap_or(false),
+            profile: args.profile,
+            // Check more often for short intervals, less often for long ones
+            memory_check_entries: match args.memory_check_interval_ms {
+                0..=100 => 500,
+                101..=200 => DEFAULT_MEMORY_CHECK_ENTRIES,
+                _ => 2000,
+            },
+        }
+    }
+}
+
+#[cfg(all(test, feature = "cli"))]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_defaults_match_cli_defaults() {
+        let args = Args::try_parse_from(["rudu"]).unwrap();
+        assert_eq!(ScanOptions::from(&args), ScanOptions::new());
+    }
+
+    #[test]
+    fn test_builder_and_args_conversion() {
+        let args =
+            Args::try_parse_from(["rudu", "--depth", "2", "--exclude", "target", "--no-cache"])
+                .unwrap();
+        let options = ScanOptions::new()
+            .depth(2)
+            .exclude(["target"])
+            .no_cache(true);
+        assert_eq!(ScanOptions::from(&args), options);
+        assert_eq!(options.memory_check_entries, DEFAULT_MEMORY_CHECK_ENTRIES);
+    }
+}
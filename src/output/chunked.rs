@@ -0,0 +1,118 @@
+//! Parallel, order-preserving fragment rendering shared by the formatters.
+//!
+//! Formatting millions of entries one at a time on a single thread dominates
+//! the time between the end of a big scan and the first line of output.
+//! [`write_chunked`] instead splits the (already sorted) items into
+//! contiguous chunks, renders each chunk to its own buffer on the rayon pool
+//! and writes the buffers out in their original order.
+//!
+//! Work proceeds in batches of a few chunks per thread, so output starts after
+//! the first batch instead of after the last entry, and at most one batch of
+//! rendered text is held in memory at a time.
+
+use anyhow::Result;
+use rayon::prelude::*;
+use std::io::Write;
+
+/// Default number of entries rendered into one fragment.
+pub const CHUNK_SIZE: usize = 4096;
+
+/// Chunks queued per rayon thread in each batch, to even out uneven chunks.
+const CHUNKS_PER_THREAD: usize = 4;
+
+/// Renders `entries` in parallel fragments and writes them to `out` in order.
+///
+/// Items are usually [`FileEntry`](crate::data::FileEntry) values, but may
+/// carry per-entry context computed up front, such as tree connectors.
+///
+/// # Arguments
+/// * `out` - Destination for the rendered bytes
+/// * `entries` - Items in output order
+/// * `chunk_size` - Items per fragment (clamped to at least 1)
+/// * `render` - Renders one contiguous chunk of items into a buffer
+///
+/// # Returns
+/// * `Result<()>` - The first error from `render` or from writing to `out`
+pub fn write_chunked<T, W, F>(
+    out: &mut W,
+    entries: &[T],
+    chunk_size: usize,
+    render: F,
+) -> Result<()>
+where
+    T: Sync,
+    W: Write + ?Sized,
+    F: Fn(&[T], &mut Vec<u8>) -> Result<()> + Sync,
+{
+    let chunk_size = chunk_size.max(1);
+    let batch_size = chunk_size * rayon::current_num_threads() * CHUNKS_PER_THREAD;
+
+    for batch in entries.chunks(batch_size) {
+        let fragments = batch
+            .par_chunks(chunk_size)
+            .map(|chunk| {
+                let mut buf = Vec::with_capacity(chunk.len() * 64);
+                render(chunk, &mut buf)?;
+                Ok(buf)
+            })
+            .collect::<Result<Vec<Vec<u8>>>>()?;
+
+        for fragment in &fragments {
+            out.write_all(fragment)?;
+        }
+    }
+
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{EntryType, FileEntry};
+    use std::path::PathBuf;
+
+    fn entries(n: usize) -> Vec<FileEntry> {
+        (0..n)
+            .map(|i| FileEntry {
+                path: PathBuf::from(format!("/root/{i}")),
+                size: i as u64,
+                owner: None,
+                inodes: None,
+                entry_type: EntryType::File,
+                previous_size: None,
+                mtime: None,
+                stat: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fragments_are_written_in_order() {
+        let entries = entries(10_000);
+        let mut out = Vec::new();
+        write_chunked(&mut out, &entries, 7, |chunk, buf| {
+            for entry in chunk {
+                writeln!(buf, "{}", entry.size)?;
+            }
+            Ok(())
+        })
+        .unwrap();
+
+        let expected: String = (0..10_000).map(|i| format!("{i}\n")).collect();
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_render_error_is_returned() {
+        let entries = entries(100);
+        let mut out = Vec::new();
+        let result = write_chunked(&mut out, &entries, 0, |chunk, _| {
+            if chunk[0].size == 42 {
+                anyhow::bail!("bad entry");
+            }
+            Ok(())
+        });
+        assert!(result.is_err());
+    }
+}
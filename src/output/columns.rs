@@ -0,0 +1,184 @@
+//! Column selection shared by the terminal and CSV formatters.
+//!
+//! `--columns size,owner,path` chooses which fields are shown and in which
+//! order. Without it, each formatter keeps its own default layout: the
+//! terminal shows the columns enabled by `--show-owner` and `--show-inodes`,
+//! and CSV keeps its fixed schema.
+
+use crate::cli::Args;
+use std::fmt;
+use std::str::FromStr;
+
+/// A field of a [`FileEntry`](crate::data::FileEntry) that can be shown
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Column {
+    /// Directory or file
+    Type,
+    /// Disk usage (CSV: `size_bytes` and `size_human`)
+    Size,
+    /// Owner username
+    Owner,
+    /// Inode count of directories
+    Inodes,
+    /// Path
+    Path,
+    /// Modification time
+    Mtime,
+}
+
+impl Column {
+    /// Every column, in the order listed in help text
+    pub const ALL: [Column; 6] = [
+        Column::Type,
+        Column::Size,
+        Column::Owner,
+        Column::Inodes,
+        Column::Path,
+        Column::Mtime,
+    ];
+
+    /// The name used in `--columns`
+    pub fn name(self) -> &'static str {
+        match self {
+            Column::Type => "type",
+            Column::Size => "size",
+            Column::Owner => "owner",
+            Column::Inodes => "inodes",
+            Column::Path => "path",
+            Column::Mtime => "mtime",
+        }
+    }
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Column::ALL
+            .into_iter()
+            .find(|c| c.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names: Vec<&str> = Column::ALL.iter().map(|c| c.name()).collect();
+                format!(
+                    "unknown column '{}' (expected one of: {})",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// An ordered selection of columns, parsed from a comma-separated list.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColumnSpec(Vec<Column>);
+
+impl FromStr for ColumnSpec {
+    type Err = String;
+
+    /// Parses e.g. `size,owner,path`; columns may not repeat.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut columns = Vec::new();
+        for name in s.split(',').map(str::trim) {
+            if name.is_empty() {
+                return Err(format!("empty column name in '{}'", s));
+            }
+            let column: Column = name.parse()?;
+            if columns.contains(&column) {
+                return Err(format!("column '{}' given more than once", column));
+            }
+            columns.push(column);
+        }
+        Ok(ColumnSpec(columns))
+    }
+}
+
+impl ColumnSpec {
+    /// The selected columns, in display order
+    pub fn columns(&self) -> &[Column] {
+        &self.0
+    }
+
+    /// Check whether `column` is selected
+    pub fn contains(&self, column: Column) -> bool {
+        self.0.contains(&column)
+    }
+
+    /// Terminal columns: `--columns`, or type, size, the owner, inode and
+    /// `--time` columns if enabled, and path.
+    pub fn for_terminal(args: &Args) -> Self {
+        if let Some(spec) = &args.columns {
+            return spec.clone();
+        }
+        let mut columns = vec![Column::Type, Column::Size];
+        if args.show_owner {
+            columns.push(Column::Owner);
+        }
+        if args.show_inodes {
+            columns.push(Column::Inodes);
+        }
+        if args.time {
+            columns.push(Column::Mtime);
+        }
+        columns.push(Column::Path);
+        ColumnSpec(columns)
+    }
+
+    /// CSV columns: `--columns`, or the fixed schema
+    /// `entry_type,size_bytes,size_human,owner,path,inodes`, followed by
+    /// `mtime` with `--time`.
+    pub fn for_csv(args: &Args) -> Self {
+        args.columns.clone().unwrap_or_else(|| {
+            let mut columns = vec![
+                Column::Type,
+                Column::Size,
+                Column::Owner,
+                Column::Path,
+                Column::Inodes,
+            ];
+            if args.time {
+                columns.push(Column::Mtime);
+            }
+            ColumnSpec(columns)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_column_spec() {
+        let spec: ColumnSpec = "size, owner,PATH,mtime".parse().unwrap();
+        assert_eq!(
+            spec.columns(),
+            &[Column::Size, Column::Owner, Column::Path, Column::Mtime]
+        );
+        assert!(spec.contains(Column::Owner));
+        assert!(!spec.contains(Column::Inodes));
+    }
+
+    #[test]
+    fn test_parse_column_spec_errors() {
+        assert!(
+            "size,bogus"
+                .parse::<ColumnSpec>()
+                .unwrap_err()
+                .contains("bogus")
+        );
+        assert!("size,,path".parse::<ColumnSpec>().is_err());
+        assert!("".parse::<ColumnSpec>().is_err());
+        assert!(
+            "size,path,size"
+                .parse::<ColumnSpec>()
+                .unwrap_err()
+                .contains("more than once")
+        );
+    }
+}
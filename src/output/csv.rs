@@ -2,20 +2,81 @@
 //!
 //! This module provides functionality to export file system scan results
 //! to CSV format for further processing or analysis.
+//!
+//! The dialect is configurable for import tools that expect something other
+//! than comma-separated, minimally quoted fields with a header row:
+//! `--csv-delimiter`, `--csv-quote` and `--csv-header`, with `--format tsv`
+//! as a shortcut for tab-separated output.
 
-use crate::cli::{Args, CsvEntry};
+use super::chunked::{CHUNK_SIZE, write_chunked};
+use super::columns::{Column, ColumnSpec};
+use super::time::TimeStyle;
+use super::units::SizeFormat;
+use crate::cli::{Args, CsvQuoting, OutputFormat};
 use crate::data::FileEntry;
+use crate::error::Error;
 use anyhow::Result;
-use csv::Writer;
-use humansize::{DECIMAL, format_size};
+use csv::{QuoteStyle, WriterBuilder};
 use std::fs::File;
-use std::io;
+use std::io::{self, Write};
+
+/// CSV dialect chosen on the command line.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Dialect {
+    /// Field delimiter byte
+    pub delimiter: u8,
+    /// When fields are quoted
+    pub quoting: CsvQuoting,
+    /// Whether a header row is written
+    pub header: bool,
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Dialect {
+            delimiter: b',',
+            quoting: CsvQuoting::Necessary,
+            header: true,
+        }
+    }
+}
+
+impl Dialect {
+    /// The dialect selected by `args`; `--format tsv` defaults to tabs.
+    pub fn from_args(args: &Args) -> Self {
+        let default_delimiter = match args.output_format() {
+            OutputFormat::Tsv => b'\t',
+            _ => b',',
+        };
+        Dialect {
+            delimiter: args.csv_delimiter.unwrap_or(default_delimiter),
+            quoting: args.csv_quote,
+            header: args.csv_header,
+        }
+    }
+
+    /// A headerless writer builder for this dialect
+    pub(crate) fn builder(&self) -> WriterBuilder {
+        let mut builder = WriterBuilder::new();
+        builder
+            .has_headers(false)
+            .delimiter(self.delimiter)
+            .quote_style(match self.quoting {
+                CsvQuoting::Necessary => QuoteStyle::Necessary,
+                CsvQuoting::Always => QuoteStyle::Always,
+                CsvQuoting::NonNumeric => QuoteStyle::NonNumeric,
+                CsvQuoting::Never => QuoteStyle::Never,
+            });
+        builder
+    }
+}
 
 /// Renders file entries to CSV format.
 ///
-/// Converts each `FileEntry` to the canonical `CsvEntry` schema (which includes
-/// a human-readable size column) so that CSV output is consistent regardless of
-/// whether it is written to a file or stdout.
+/// Writes the columns chosen by `--columns`, or by default the fixed schema
+/// `entry_type,size_bytes,size_human,owner,path,inodes`, in the [`Dialect`]
+/// selected by `args`, so that CSV output is consistent regardless of whether
+/// it is written to a file or stdout.
 ///
 /// # Arguments
 /// * `entries` - A slice of already-filtered and sorted file entries to render
@@ -24,31 +85,120 @@ use std::io;
 /// # Returns
 /// * `Result<()>` - Ok if rendering succeeded, Err if there was an issue
 pub fn render(entries: &[FileEntry], args: &Args) -> Result<()> {
-    let writer: Box<dyn io::Write> = if let Some(output_file) = &args.output {
-        Box::new(File::create(output_file)?)
-    } else {
-        Box::new(io::stdout())
-    };
-
-    let mut csv_writer = Writer::from_writer(writer);
-
-    for entry in entries {
-        let csv_entry = CsvEntry {
-            entry_type: entry.entry_type.as_str().to_string(),
-            size_bytes: entry.size,
-            size_human: format_size(entry.size, DECIMAL),
-            owner: entry.owner.clone(),
-            path: entry.path.display().to_string(),
-            inodes: entry.inodes,
-        };
-        csv_writer.serialize(csv_entry)?;
+    match &args.output {
+        Some(output_file) => {
+            render_to(
+                &mut io::BufWriter::new(File::create(output_file)?),
+                entries,
+                &ColumnSpec::for_csv(args),
+                Dialect::from_args(args),
+                args.size_format(),
+                &csv_time_style(args),
+            )?;
+            let label = match args.output_format() {
+                OutputFormat::Tsv => "TSV",
+                _ => "CSV",
+            };
+            log::info!("{} output written to: {}", label, output_file);
+        }
+        None => render_to(
+            &mut io::BufWriter::new(io::stdout().lock()),
+            entries,
+            &ColumnSpec::for_csv(args),
+            Dialect::from_args(args),
+            args.size_format(),
+            &csv_time_style(args),
+        )?,
+    }
+    Ok(())
+}
+
+/// The `mtime` format: `--time-style`, or RFC 3339 in UTC.
+pub(crate) fn csv_time_style(args: &Args) -> TimeStyle {
+    args.time_style.clone().unwrap_or(TimeStyle::Rfc3339)
+}
+
+/// Renders file entries as CSV to an arbitrary writer.
+///
+/// # Arguments
+/// * `out` - Destination for the header and rows
+/// * `entries` - Entries in output order
+/// * `columns` - The columns to write
+/// * `dialect` - Delimiter, quoting and header settings
+/// * `units` - Unit of the `size_human` field
+/// * `time_style` - Format of the `mtime` field
+pub fn render_to<W: Write + ?Sized>(
+    out: &mut W,
+    entries: &[FileEntry],
+    columns: &ColumnSpec,
+    dialect: Dialect,
+    units: SizeFormat,
+    time_style: &TimeStyle,
+) -> crate::error::Result<()> {
+    write_csv(out, entries, columns, dialect, units, time_style).map_err(Error::output)
+}
+
+fn write_csv<W: Write + ?Sized>(
+    out: &mut W,
+    entries: &[FileEntry],
+    columns: &ColumnSpec,
+    dialect: Dialect,
+    units: SizeFormat,
+    time_style: &TimeStyle,
+) -> Result<()> {
+    // The header is written once up front; each chunk is serialized by its
+    // own headerless writer so rows can be built in parallel.
+    if dialect.header {
+        let mut header = dialect.builder().from_writer(Vec::new());
+        header.write_record(columns.columns().iter().flat_map(|c| header_names(*c)))?;
+        out.write_all(&header.into_inner()?)?;
     }
 
-    csv_writer.flush()?;
+    write_chunked(out, entries, CHUNK_SIZE, |chunk, buf| {
+        let mut csv_writer = dialect.builder().from_writer(buf);
+        for entry in chunk {
+            let record: Vec<String> = columns
+                .columns()
+                .iter()
+                .flat_map(|c| fields(entry, *c, units, time_style))
+                .collect();
+            csv_writer.write_record(&record)?;
+        }
+        csv_writer.flush()?;
+        Ok(())
+    })
+}
 
-    if let Some(output_file) = &args.output {
-        eprintln!("CSV output written to: {}", output_file);
+/// CSV header names of a column; size expands to bytes and a human-readable form
+fn header_names(column: Column) -> &'static [&'static str] {
+    match column {
+        Column::Type => &["entry_type"],
+        Column::Size => &["size_bytes", "size_human"],
+        Column::Owner => &["owner"],
+        Column::Inodes => &["inodes"],
+        Column::Path => &["path"],
+        Column::Mtime => &["mtime"],
     }
+}
 
-    Ok(())
+/// Field values of a column for one entry, matching [`header_names`].
+///
+/// Missing values are written as empty fields.
+fn fields(
+    entry: &FileEntry,
+    column: Column,
+    units: SizeFormat,
+    time_style: &TimeStyle,
+) -> Vec<String> {
+    match column {
+        Column::Type => vec![entry.entry_type.as_str().to_string()],
+        Column::Size => vec![entry.size.to_string(), units.format(entry.size)],
+        Column::Owner => vec![entry.owner.clone().unwrap_or_default()],
+        Column::Inodes => vec![entry.inodes.map(|n| n.to_string()).unwrap_or_default()],
+        Column::Path => vec![entry.path.display().to_string()],
+        Column::Mtime => vec![match entry.mtime {
+            Some(_) => time_style.format(entry.mtime),
+            None => String::new(),
+        }],
+    }
 }
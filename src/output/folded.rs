@@ -0,0 +1,111 @@
+//! Folded-stacks output, for flamegraph tooling.
+//!
+//! Writes one line per entry in the "folded" format read by `flamegraph.pl`
+//! and `inferno-flamegraph`: the entry's path as `;`-separated frames,
+//! starting with the scanned root, followed by a space and a weight in bytes.
+//!
+//! ```text
+//! /data;projects;alpha 1048576
+//! /data;projects;alpha;results.h5 52428800
+//! ```
+//!
+//! Flamegraph tools add up the weights of all stacks below a frame, so each
+//! directory is weighted with only the part of its size not accounted for by
+//! its listed descendants; the width of every frame in the graph then matches
+//! rudu's total for that directory. Entries with no bytes of their own are
+//! omitted. `;` and line breaks in file names are replaced with `_`, as they
+//! would otherwise split frames or lines.
+
+use super::chunked::{CHUNK_SIZE, write_chunked};
+use crate::cli::Args;
+use crate::data::{FileEntry, ScanMeta};
+use crate::error::Error;
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Renders file entries as folded stacks.
+///
+/// Writes to the `--output` file if one was given, otherwise to stdout.
+///
+/// # Arguments
+/// * `entries` - A slice of already-filtered and sorted file entries to render
+/// * `args` - Command line arguments that control output formatting
+/// * `meta` - Metadata of the scan; its root is the bottom frame of every stack
+///
+/// # Returns
+/// * `Result<()>` - Ok if rendering succeeded, Err if there was an issue
+pub fn render(entries: &[FileEntry], args: &Args, meta: &ScanMeta) -> Result<()> {
+    match &args.output {
+        Some(output_file) => {
+            render_to(
+                &mut io::BufWriter::new(File::create(output_file)?),
+                entries,
+                &meta.root,
+            )?;
+            log::info!("Folded stacks written to: {}", output_file);
+        }
+        None => render_to(&mut io::stdout().lock(), entries, &meta.root)?,
+    }
+    Ok(())
+}
+
+/// Renders file entries as folded stacks to an arbitrary writer.
+///
+/// # Arguments
+/// * `out` - Destination for the stacks
+/// * `entries` - File entries below `root`, in any order
+/// * `root` - The scanned root
+pub fn render_to<W: Write + ?Sized>(
+    out: &mut W,
+    entries: &[FileEntry],
+    root: &Path,
+) -> crate::error::Result<()> {
+    write_folded(out, entries, root).map_err(Error::output)
+}
+
+fn write_folded<W: Write + ?Sized>(out: &mut W, entries: &[FileEntry], root: &Path) -> Result<()> {
+    let listed: HashSet<&Path> = entries.iter().map(|e| e.path.as_path()).collect();
+
+    // Bytes of each entry already counted by its listed descendants
+    let mut counted: HashMap<&Path, u64> = HashMap::new();
+    for entry in entries {
+        let parent = entry
+            .path
+            .ancestors()
+            .skip(1)
+            .take_while(|a| a.starts_with(root))
+            .find(|a| listed.contains(a));
+        if let Some(parent) = parent {
+            *counted.entry(parent).or_default() += entry.size;
+        }
+    }
+
+    let root_frame = frame(&root.display().to_string());
+    write_chunked(out, entries, CHUNK_SIZE, |chunk, buf| {
+        for entry in chunk {
+            let own = entry
+                .size
+                .saturating_sub(counted.get(entry.path.as_path()).copied().unwrap_or(0));
+            if own == 0 {
+                continue;
+            }
+            buf.extend_from_slice(root_frame.as_bytes());
+            if let Ok(relative) = entry.path.strip_prefix(root) {
+                for component in relative.iter() {
+                    buf.push(b';');
+                    buf.extend_from_slice(frame(&component.to_string_lossy()).as_bytes());
+                }
+            }
+            writeln!(buf, " {}", own)?;
+        }
+        Ok(())
+    })
+}
+
+/// A path component as a frame name, without separators
+fn frame(name: &str) -> String {
+    name.replace([';', '\n', '\r'], "_")
+}
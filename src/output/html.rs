@@ -0,0 +1,380 @@
+//! Self-contained HTML report.
+//!
+//! Produces a single HTML file, with no external scripts, styles or images,
+//! that can be mailed to people who will not run a CLI. It contains:
+//!
+//! - a summary of the scan (root, time, totals);
+//! - a pie chart of the scanned root's direct children, drawn as inline SVG
+//!   with the [`PIE_SLICES`] largest shown individually and the rest grouped;
+//! - a table of all entries, which can be sorted by clicking a column
+//!   header and filtered by path with a small inline script.
+//!
+//! The table is rendered on the server side, so the report stays readable in
+//! viewers that block scripts; only sorting and filtering need them.
+
+use super::chunked::{CHUNK_SIZE, write_chunked};
+use super::paths::PathStyle;
+use super::units::SizeFormat;
+use crate::cli::Args;
+use crate::data::{EntryType, FileEntry, ScanMeta};
+use anyhow::Result;
+use std::borrow::Cow;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Number of top-level entries given their own pie slice
+pub const PIE_SLICES: usize = 10;
+
+/// Slice colors, largest slice first; the last is used for "other"
+const PIE_COLORS: [&str; PIE_SLICES + 1] = [
+    "#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948", "#b07aa1", "#ff9da7",
+    "#9c755f", "#bab0ac", "#d3d3d3",
+];
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; color: #222; }
+h1 { font-size: 1.4em; }
+.summary td { padding: 0 1em 0 0; }
+.chart { display: flex; align-items: center; gap: 2em; margin: 1.5em 0; }
+.legend td { padding: 0.1em 0.6em 0.1em 0; }
+.swatch { display: inline-block; width: 0.9em; height: 0.9em; }
+#filter { margin: 0.5em 0; padding: 0.3em; width: 30em; }
+table.entries { border-collapse: collapse; }
+table.entries th { cursor: pointer; background: #eee; text-align: left; }
+table.entries th, table.entries td { padding: 0.2em 0.8em; border-bottom: 1px solid #ddd; }
+table.entries td.num { text-align: right; }
+";
+
+const SCRIPT: &str = "
+const table = document.querySelector('table.entries');
+const body = table.tBodies[0];
+document.getElementById('filter').addEventListener('input', e => {
+  const needle = e.target.value.toLowerCase();
+  for (const row of body.rows) {
+    row.hidden = !row.dataset.path.toLowerCase().includes(needle);
+  }
+});
+table.querySelectorAll('th').forEach((th, col) => {
+  let ascending = false;
+  th.addEventListener('click', () => {
+    ascending = !ascending;
+    const numeric = th.dataset.type === 'num';
+    const key = row => {
+      const cell = row.cells[col];
+      return numeric ? Number(cell.dataset.value ?? -1) : cell.textContent;
+    };
+    const rows = Array.from(body.rows);
+    rows.sort((a, b) => {
+      const x = key(a), y = key(b);
+      const order = numeric ? x - y : x.localeCompare(y);
+      return ascending ? order : -order;
+    });
+    body.append(...rows);
+  });
+});
+";
+
+/// Renders file entries as a self-contained HTML report.
+///
+/// Writes to the `--output` file if one was given, otherwise to stdout.
+///
+/// # Arguments
+/// * `entries` - A slice of already-filtered and sorted file entries to render
+/// * `args` - Command line arguments that control output formatting
+/// * `meta` - Metadata of the scan, shown in the summary
+///
+/// # Returns
+/// * `Result<()>` - Ok if rendering succeeded, Err if there was an issue
+pub fn render(entries: &[FileEntry], args: &Args, meta: &ScanMeta) -> Result<()> {
+    match &args.output {
+        Some(output_file) => {
+            render_to(
+                &mut io::BufWriter::new(File::create(output_file)?),
+                entries,
+                args,
+                meta,
+            )?;
+            log::info!("HTML report written to: {}", output_file);
+        }
+        None => render_to(&mut io::stdout().lock(), entries, args, meta)?,
+    }
+    Ok(())
+}
+
+/// Renders file entries as a self-contained HTML report to an arbitrary writer.
+///
+/// Owner and inode columns are included when `--show-owner` and
+/// `--show-inodes` are set.
+///
+/// # Arguments
+/// * `out` - Destination for the report
+/// * `entries` - Entries in table order
+/// * `args` - Command line arguments that control output formatting
+/// * `meta` - Metadata of the scan; its root anchors the chart and paths
+pub fn render_to<W: Write + ?Sized>(
+    out: &mut W,
+    entries: &[FileEntry],
+    args: &Args,
+    meta: &ScanMeta,
+) -> Result<()> {
+    let root = meta.root.display().to_string();
+    write!(
+        out,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>rudu: {}</title>\n<style>{}</style>\n</head>\n<body>\n\
+         <h1>Disk usage of {}</h1>\n",
+        escape(&root),
+        STYLE,
+        escape(&root)
+    )?;
+    let units = args.size_format();
+    out.write_all(summary(meta, units).as_bytes())?;
+    out.write_all(pie_chart(entries, &meta.root, units).as_bytes())?;
+
+    write!(
+        out,
+        "<input id=\"filter\" type=\"search\" placeholder=\"Filter by path\">\n\
+         <table class=\"entries\">\n<thead><tr><th>Type</th><th data-type=\"num\">Size</th>"
+    )?;
+    if args.show_owner {
+        write!(out, "<th>Owner</th>")?;
+    }
+    if args.show_inodes {
+        write!(out, "<th data-type=\"num\">Inodes</th>")?;
+    }
+    writeln!(out, "<th>Path</th></tr></thead>\n<tbody>")?;
+
+    let paths = PathStyle::from_args(args, &meta.root);
+    write_chunked(out, entries, CHUNK_SIZE, |chunk, buf| {
+        for entry in chunk {
+            let path = match &paths {
+                Some(paths) => Cow::Owned(paths.apply(&entry.path)),
+                None => Cow::Borrowed(entry.path.strip_prefix(&meta.root).unwrap_or(&entry.path)),
+            };
+            write_row(buf, entry, args, &path, units)?;
+        }
+        Ok(())
+    })?;
+
+    write!(
+        out,
+        "</tbody>\n</table>\n<script>{}</script>\n</body>\n</html>\n",
+        SCRIPT
+    )?;
+    out.flush()?;
+    Ok(())
+}
+
+fn summary(meta: &ScanMeta, units: SizeFormat) -> String {
+    let mut html = String::from("<table class=\"summary\">\n");
+    let rows = [
+        (
+            "Scanned",
+            meta.started_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+        ),
+        ("Total size", units.format(meta.total_size)),
+        ("Directories", meta.dir_count.to_string()),
+        ("Files", meta.file_count.to_string()),
+    ];
+    for (label, value) in rows {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td></tr>",
+            label,
+            escape(&value)
+        );
+    }
+    for mount in &meta.mounts {
+        let _ = writeln!(
+            html,
+            "<tr><td>Mount</td><td>{}</td></tr>",
+            escape(&mount.describe())
+        );
+    }
+    if meta.partial {
+        html.push_str("<tr><td colspan=\"2\"><strong>Partial scan: totals may be incomplete</strong></td></tr>\n");
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+/// Inline SVG pie chart of the direct children of `root`, with a legend.
+///
+/// Returns an empty string when no listed entry is a direct child of `root`
+/// or all of them are empty.
+fn pie_chart(entries: &[FileEntry], root: &Path, units: SizeFormat) -> String {
+    let mut children: Vec<(String, u64)> = entries
+        .iter()
+        .filter(|e| e.path.parent() == Some(root))
+        .map(|e| {
+            let name = e
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            match e.entry_type {
+                EntryType::Dir | EntryType::Archive => (format!("{}/", name), e.size),
+                _ => (name, e.size),
+            }
+        })
+        .collect();
+    children.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let total: u64 = children.iter().map(|(_, size)| size).sum();
+    if total == 0 {
+        return String::new();
+    }
+    if children.len() > PIE_SLICES {
+        let rest: u64 = children.drain(PIE_SLICES..).map(|(_, size)| size).sum();
+        children.push(("(other)".to_string(), rest));
+    }
+
+    let (cx, cy, r) = (110.0, 110.0, 100.0);
+    let mut svg = String::from(
+        "<div class=\"chart\">\n<svg width=\"220\" height=\"220\" viewBox=\"0 0 220 220\">\n",
+    );
+    let mut legend = String::from("<table class=\"legend\">\n");
+    let mut start = 0.0_f64;
+
+    for (i, (name, size)) in children.iter().enumerate() {
+        let color = if name == "(other)" {
+            PIE_COLORS[PIE_SLICES]
+        } else {
+            PIE_COLORS[i]
+        };
+        let fraction = *size as f64 / total as f64;
+        let label = format!("{} ({})", name, units.format(*size));
+
+        if fraction >= 1.0 {
+            let _ = writeln!(
+                svg,
+                "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"><title>{}</title></circle>",
+                cx,
+                cy,
+                r,
+                color,
+                escape(&label)
+            );
+        } else if fraction > 0.0 {
+            // Angles start at 12 o'clock and run clockwise
+            let end = start + fraction;
+            let point = |turns: f64| {
+                let angle = turns * std::f64::consts::TAU - std::f64::consts::FRAC_PI_2;
+                (cx + r * angle.cos(), cy + r * angle.sin())
+            };
+            let (x0, y0) = point(start);
+            let (x1, y1) = point(end);
+            let _ = writeln!(
+                svg,
+                "<path d=\"M{cx},{cy} L{x0:.2},{y0:.2} A{r},{r} 0 {} 1 {x1:.2},{y1:.2} Z\" \
+                 fill=\"{color}\"><title>{}</title></path>",
+                u8::from(fraction > 0.5),
+                escape(&label)
+            );
+            start = end;
+        }
+
+        let _ = writeln!(
+            legend,
+            "<tr><td><span class=\"swatch\" style=\"background:{}\"></span></td>\
+             <td>{}</td><td>{}</td><td>{:.1}%</td></tr>",
+            color,
+            escape(name),
+            units.format(*size),
+            fraction * 100.0
+        );
+    }
+
+    svg.push_str("</svg>\n");
+    legend.push_str("</table>\n");
+    svg + &legend + "</div>\n"
+}
+
+fn write_row(
+    buf: &mut Vec<u8>,
+    entry: &FileEntry,
+    args: &Args,
+    path: &Path,
+    units: SizeFormat,
+) -> io::Result<()> {
+    let path = escape(&path.display().to_string());
+    write!(
+        buf,
+        "<tr data-path=\"{}\"><td>{}</td><td class=\"num\" data-value=\"{}\">{}</td>",
+        path,
+        entry.entry_type.as_str(),
+        entry.size,
+        units.format(entry.size)
+    )?;
+    if args.show_owner {
+        write!(
+            buf,
+            "<td>{}</td>",
+            escape(entry.owner.as_deref().unwrap_or(""))
+        )?;
+    }
+    if args.show_inodes {
+        match entry.inodes {
+            Some(inodes) => write!(buf, "<td class=\"num\" data-value=\"{0}\">{0}</td>", inodes)?,
+            None => write!(buf, "<td class=\"num\"></td>")?,
+        }
+    }
+    writeln!(buf, "<td>{}</td></tr>", path)
+}
+
+/// Escapes text for use in HTML content and attribute values
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            other => escaped.push(other),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("<a href=\"x\">Tom & Jerry's</a>"),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn test_pie_chart_groups_small_children() {
+        let root = Path::new("/data");
+        let mut entries: Vec<FileEntry> = (0..PIE_SLICES + 2)
+            .map(|i| {
+                FileEntry::for_test(format!("/data/d{:02}", i), 100 - i as u64, EntryType::Dir)
+            })
+            .collect();
+        entries.push(FileEntry::for_test("/data/d00/nested", 50, EntryType::Dir));
+        let chart = pie_chart(&entries, root, SizeFormat::Decimal);
+
+        assert_eq!(chart.matches("<path").count(), PIE_SLICES + 1);
+        assert!(chart.contains("d00/"));
+        assert!(!chart.contains("nested"));
+        assert!(!chart.contains(">d10/<"));
+        assert!(chart.contains(&format!(
+            "(other)</td><td>{}",
+            SizeFormat::Decimal.format(90 + 89)
+        )));
+
+        // A single child fills the whole chart
+        let chart = pie_chart(&entries[..1], root, SizeFormat::Decimal);
+        assert!(chart.contains("<circle"));
+        assert!(pie_chart(&[], root, SizeFormat::Decimal).is_empty());
+    }
+}
@@ -8,15 +8,45 @@
 //!
 //! - **Terminal**: Human-readable output with colored prefixes and formatting
 //! - **CSV**: Machine-readable CSV format for data analysis and processing
+//! - **NDJSON**: One JSON object per line for streaming into `jq` or log pipelines
+//! - **MessagePack**: A compact binary array of the JSON records
+//! - **YAML**: The JSON records as a YAML sequence, for tools that read YAML
+//! - **Folded stacks**: Directory sizes for flamegraph tools
+//! - **HTML**: A self-contained report with a sortable table and pie chart
+//! - **ncdu**: ncdu's JSON export format, for browsing results with `ncdu -f`
+//! - **Parquet**: Columnar files for pandas, DuckDB or Spark (`parquet` feature)
+//! - **XLSX**: An Excel workbook with summary and entries sheets (`xlsx` feature)
+//! - **printf**: User-defined records in the style of `find -printf`
+//! - **Template**: Whole reports laid out by a user's Handlebars template
 //!
 //! # Usage
 //!
 //! Each formatter accepts a slice of `FileEntry` objects and command-line
 //! arguments to control the output format. The formatters are designed to
 //! be independent and stateless, making them easy to test and extend.
+//!
+//! Line-oriented formatters render through [`chunked::write_chunked`], which
+//! builds output fragments in parallel and writes them in entry order.
 
+pub mod chunked;
+pub mod columns;
 pub mod csv;
+pub mod folded;
+pub mod html;
+pub mod msgpack;
+pub mod ncdu;
+pub mod ndjson;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+pub mod paths;
+pub mod printf;
+pub mod template;
 pub mod terminal;
+pub mod time;
+pub mod units;
+#[cfg(feature = "xlsx")]
+pub mod xlsx;
+pub mod yaml;
 
 // Re-export the main render functions for convenience
 
@@ -25,6 +55,58 @@ pub mod terminal;
 /// See [`csv::render`] for full documentation.
 pub use csv::render as render_csv;
 
+/// YAML output renderer function.
+///
+/// See [`yaml::render`] for full documentation.
+pub use yaml::render as render_yaml;
+
+/// Folded-stacks renderer function.
+///
+/// See [`folded::render`] for full documentation.
+pub use folded::render as render_folded;
+
+/// HTML report renderer function.
+///
+/// See [`html::render`] for full documentation.
+pub use html::render as render_html;
+
+/// NDJSON output renderer function.
+///
+/// See [`ndjson::render`] for full documentation.
+pub use ndjson::render as render_ndjson;
+
+/// MessagePack output renderer function.
+///
+/// See [`msgpack::render`] for full documentation.
+pub use msgpack::render as render_msgpack;
+
+/// ncdu export renderer function.
+///
+/// See [`ncdu::render`] for full documentation.
+pub use ncdu::render as render_ncdu;
+
+/// Parquet renderer function.
+///
+/// See [`parquet::render`] for full documentation.
+#[cfg(feature = "parquet")]
+pub use self::parquet::render as render_parquet;
+
+/// Excel workbook renderer function.
+///
+/// See [`xlsx::render`] for full documentation.
+#[cfg(feature = "xlsx")]
+pub use xlsx::render as render_xlsx;
+
+/// `--printf` renderer function.
+///
+/// See [`printf::render`] for full documentation.
+pub use printf::render as render_printf;
+
+/// `--template` renderer function.
+///
+/// See [`template::render`] for full documentation.
+pub use template::render as render_template;
+
 /// Terminal output renderer function.
 ///
 /// See [`terminal::render`] for full documentation.
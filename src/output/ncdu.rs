@@ -0,0 +1,178 @@
+//! ncdu-compatible JSON export.
+//!
+//! Writes the ncdu export format (major version 1) so results can be browsed
+//! interactively with `ncdu -f out.json`. The format is a tree: a directory
+//! is a JSON array whose first element describes the directory itself and
+//! whose remaining elements are its children, with files as plain objects.
+//!
+//! rudu's entries are a flat list of subtree totals, while ncdu sums each
+//! item's own size itself. The tree is rebuilt from the entry paths and every
+//! directory is given the part of its total not accounted for by its listed
+//! children, so the totals ncdu shows match rudu's even when `--depth` or
+//! `--show-files false` left out deeper entries.
+
+use crate::cli::Args;
+use crate::data::{EntryType, FileEntry, ScanMeta};
+use crate::error::Error;
+use anyhow::Result;
+use serde_json::json;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Major and minor version of the ncdu export format written
+const FORMAT_VERSION: (u32, u32) = (1, 0);
+
+/// A file or directory in the rebuilt tree
+struct Node {
+    name: String,
+    entry_type: EntryType,
+    /// Subtree total from the scan, or `None` for a directory that is only
+    /// implied by the paths of its descendants
+    size: Option<u64>,
+    children: Vec<usize>,
+}
+
+/// Renders file entries as an ncdu JSON export.
+///
+/// Writes to the `--output` file if one was given, otherwise to stdout.
+///
+/// # Arguments
+/// * `entries` - A slice of already-filtered file entries to render
+/// * `args` - Command line arguments that control output formatting
+/// * `meta` - Metadata of the scan; its root becomes the root of the tree
+///
+/// # Returns
+/// * `Result<()>` - Ok if rendering succeeded, Err if there was an issue
+pub fn render(entries: &[FileEntry], args: &Args, meta: &ScanMeta) -> Result<()> {
+    match &args.output {
+        Some(output_file) => {
+            render_to(
+                &mut io::BufWriter::new(File::create(output_file)?),
+                entries,
+                meta,
+            )?;
+            log::info!("ncdu export written to: {}", output_file);
+        }
+        None => render_to(&mut io::stdout().lock(), entries, meta)?,
+    }
+    Ok(())
+}
+
+/// Renders file entries as an ncdu JSON export to an arbitrary writer.
+///
+/// # Arguments
+/// * `out` - Destination for the export
+/// * `entries` - File entries below `meta.root`, in any order
+/// * `meta` - Metadata of the scan
+pub fn render_to<W: Write + ?Sized>(
+    out: &mut W,
+    entries: &[FileEntry],
+    meta: &ScanMeta,
+) -> crate::error::Result<()> {
+    write_ncdu(out, entries, meta).map_err(Error::output)
+}
+
+fn write_ncdu<W: Write + ?Sized>(
+    out: &mut W,
+    entries: &[FileEntry],
+    meta: &ScanMeta,
+) -> Result<()> {
+    let nodes = build_tree(&meta.root, entries);
+    let header = json!({
+        "progname": env!("CARGO_PKG_NAME"),
+        "progver": env!("CARGO_PKG_VERSION"),
+        "timestamp": meta.started_at.timestamp(),
+    });
+    write!(
+        out,
+        "[{},{},{},",
+        FORMAT_VERSION.0, FORMAT_VERSION.1, header
+    )?;
+    write_node(out, &nodes, 0)?;
+    writeln!(out, "]")?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Rebuilds the directory tree below `root`; the root is node 0.
+///
+/// Entries outside `root` are ignored. Directories missing from `entries`
+/// between a listed entry and the root are created without a size.
+fn build_tree(root: &Path, entries: &[FileEntry]) -> Vec<Node> {
+    let mut nodes = vec![Node {
+        name: root.display().to_string(),
+        entry_type: EntryType::Dir,
+        size: None,
+        children: Vec::new(),
+    }];
+    let mut index: HashMap<PathBuf, usize> = HashMap::from([(root.to_path_buf(), 0)]);
+
+    let mut sorted: Vec<&FileEntry> = entries
+        .iter()
+        .filter(|e| e.path.starts_with(root))
+        .collect();
+    sorted.sort_by(|a, b| a.path.cmp(&b.path));
+
+    for entry in sorted {
+        let id = node_for(&mut nodes, &mut index, &entry.path);
+        nodes[id].entry_type = entry.entry_type;
+        nodes[id].size = Some(entry.size);
+    }
+    nodes
+}
+
+/// Returns the node for `path`, creating it and any missing ancestors.
+fn node_for(nodes: &mut Vec<Node>, index: &mut HashMap<PathBuf, usize>, path: &Path) -> usize {
+    if let Some(&id) = index.get(path) {
+        return id;
+    }
+    // Only the root has no parent below it, and the root is always indexed
+    let parent = node_for(nodes, index, path.parent().unwrap_or(path));
+    let id = nodes.len();
+    nodes.push(Node {
+        name: path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        entry_type: EntryType::Dir,
+        size: None,
+        children: Vec::new(),
+    });
+    nodes[parent].children.push(id);
+    index.insert(path.to_path_buf(), id);
+    id
+}
+
+/// Subtree total of a node: its scanned size, or the sum over its children.
+fn total(nodes: &[Node], id: usize) -> u64 {
+    let node = &nodes[id];
+    node.size
+        .unwrap_or_else(|| node.children.iter().map(|&c| total(nodes, c)).sum())
+}
+
+fn write_node<W: Write + ?Sized>(out: &mut W, nodes: &[Node], id: usize) -> Result<()> {
+    let node = &nodes[id];
+    let children_total: u64 = node.children.iter().map(|&c| total(nodes, c)).sum();
+    let own_size = total(nodes, id).saturating_sub(children_total);
+    let mut info = json!({ "name": node.name, "dsize": own_size });
+
+    match node.entry_type {
+        EntryType::File => write!(out, "{}", info)?,
+        EntryType::Dir | EntryType::Archive => {
+            write!(out, "[{}", info)?;
+            for &child in &node.children {
+                write!(out, ",")?;
+                write_node(out, nodes, child)?;
+            }
+            write!(out, "]")?;
+        }
+        // ncdu marks symlinks, sockets, FIFOs and devices alike
+        _ => {
+            info["notreg"] = json!(true);
+            write!(out, "{}", info)?;
+        }
+    }
+    Ok(())
+}
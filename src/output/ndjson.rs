@@ -0,0 +1,75 @@
+//! NDJSON output formatter for file system scan results.
+//!
+//! Writes one JSON object per line, so output can be piped into `jq` or bulk
+//! ingested (e.g. into Elasticsearch) record by record. Nothing beyond the
+//! current batch of lines is buffered, unlike a single JSON array. With
+//! `--stream` the CLI writes records with [`write_entry`] while the scan runs
+//! instead of rendering the finished listing.
+
+use super::chunked::{CHUNK_SIZE, write_chunked};
+use crate::cli::Args;
+use crate::data::FileEntry;
+use crate::error::Error;
+use anyhow::Result;
+use std::fs::File;
+use std::io::{self, Write};
+
+/// Renders file entries as newline-delimited JSON.
+///
+/// Writes to the `--output` file if one was given, otherwise to stdout.
+///
+/// # Arguments
+/// * `entries` - A slice of already-filtered and sorted file entries to render
+/// * `args` - Command line arguments that control output formatting
+///
+/// # Returns
+/// * `Result<()>` - Ok if rendering succeeded, Err if there was an issue
+pub fn render(entries: &[FileEntry], args: &Args) -> Result<()> {
+    match &args.output {
+        Some(output_file) => {
+            render_to(&mut io::BufWriter::new(File::create(output_file)?), entries)?;
+            log::info!("NDJSON output written to: {}", output_file);
+        }
+        None => render_to(&mut io::stdout().lock(), entries)?,
+    }
+    Ok(())
+}
+
+/// Renders file entries as newline-delimited JSON to an arbitrary writer.
+///
+/// # Arguments
+/// * `out` - Destination for the rendered lines
+/// * `entries` - Entries in output order
+pub fn render_to<W: Write + ?Sized>(
+    out: &mut W,
+    entries: &[FileEntry],
+) -> crate::error::Result<()> {
+    write_ndjson(out, entries).map_err(Error::output)
+}
+
+fn write_ndjson<W: Write + ?Sized>(out: &mut W, entries: &[FileEntry]) -> Result<()> {
+    write_chunked(out, entries, CHUNK_SIZE, |chunk, buf| {
+        for entry in chunk {
+            write_entry(buf, entry)?;
+        }
+        Ok(())
+    })
+}
+
+/// Writes a single entry as one line of JSON.
+///
+/// Callers producing entries incrementally can use this to stream records as
+/// they become available instead of collecting them first.
+///
+/// # Arguments
+/// * `out` - Destination for the line
+/// * `entry` - The entry to write, serialized with its `serde` representation
+pub fn write_entry<W: Write + ?Sized>(out: &mut W, entry: &FileEntry) -> crate::error::Result<()> {
+    write_line(out, entry).map_err(Error::output)
+}
+
+fn write_line<W: Write + ?Sized>(out: &mut W, entry: &FileEntry) -> Result<()> {
+    serde_json::to_writer(&mut *out, entry)?;
+    out.write_all(b"\n")?;
+    Ok(())
+}
@@ -0,0 +1,218 @@
+//! Apache Parquet output formatter (requires the `parquet` feature).
+//!
+//! Writes one row per entry so large scans can be loaded straight into
+//! pandas, DuckDB or Spark. Columnar storage with Snappy compression is far
+//! smaller than CSV for scans with millions of entries, as paths under the
+//! same directory compress well against each other.
+//!
+//! Entries are written in row groups of [`ROW_GROUP_SIZE`] rows, so only one
+//! group's column buffers are held in memory at a time. The schema is:
+//!
+//! ```text
+//! message rudu_entry {
+//!   required binary path (STRING);
+//!   required binary entry_type (STRING);
+//!   required int64 size_bytes (INTEGER(64,false));
+//!   optional binary owner (STRING);
+//!   optional int64 inodes (INTEGER(64,false));
+//!   optional int64 mtime (TIMESTAMP(MILLIS,true));
+//!   optional int64 previous_size (INTEGER(64,false));
+//! }
+//! ```
+
+use crate::cli::Args;
+use crate::data::FileEntry;
+use crate::error::Error;
+use ::parquet::basic::Compression;
+use ::parquet::data_type::{ByteArray, ByteArrayType, DataType, Int64Type};
+use ::parquet::file::properties::WriterProperties;
+use ::parquet::file::writer::{SerializedFileWriter, SerializedRowGroupWriter};
+use ::parquet::schema::parser::parse_message_type;
+use anyhow::{Result, anyhow};
+use std::fs::File;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+/// Number of entries per row group
+pub const ROW_GROUP_SIZE: usize = 1_000_000;
+
+/// Schema of the written rows; see the module documentation
+const SCHEMA: &str = "
+    message rudu_entry {
+        required binary path (STRING);
+        required binary entry_type (STRING);
+        required int64 size_bytes (INTEGER(64,false));
+        optional binary owner (STRING);
+        optional int64 inodes (INTEGER(64,false));
+        optional int64 mtime (TIMESTAMP(MILLIS,true));
+        optional int64 previous_size (INTEGER(64,false));
+    }
+";
+
+/// Renders file entries as a Parquet file.
+///
+/// Writes to the `--output` file if one was given, otherwise to stdout.
+///
+/// # Arguments
+/// * `entries` - A slice of already-filtered and sorted file entries to render
+/// * `args` - Command line arguments that control output formatting
+///
+/// # Returns
+/// * `Result<()>` - Ok if rendering succeeded, Err if there was an issue
+pub fn render(entries: &[FileEntry], args: &Args) -> Result<()> {
+    match &args.output {
+        Some(output_file) => {
+            render_to(File::create(output_file)?, entries)?;
+            log::info!("Parquet output written to: {}", output_file);
+        }
+        None => render_to(io::stdout(), entries)?,
+    }
+    Ok(())
+}
+
+/// Renders file entries as a Parquet file to an arbitrary writer.
+///
+/// # Arguments
+/// * `out` - Destination for the file; Parquet writers take ownership of it
+/// * `entries` - Entries in output order
+pub fn render_to<W: Write + Send>(out: W, entries: &[FileEntry]) -> crate::error::Result<()> {
+    write_parquet(out, entries).map_err(Error::output)
+}
+
+fn write_parquet<W: Write + Send>(out: W, entries: &[FileEntry]) -> Result<()> {
+    let schema = Arc::new(parse_message_type(SCHEMA)?);
+    let props = Arc::new(
+        WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build(),
+    );
+    let mut writer = SerializedFileWriter::new(io::BufWriter::new(out), schema, props)?;
+
+    for group in entries.chunks(ROW_GROUP_SIZE) {
+        let mut row_group = writer.next_row_group()?;
+        write_column::<ByteArrayType>(
+            &mut row_group,
+            &group
+                .iter()
+                .map(|e| ByteArray::from(e.path.to_string_lossy().as_ref()))
+                .collect::<Vec<_>>(),
+            None,
+        )?;
+        write_column::<ByteArrayType>(
+            &mut row_group,
+            &group
+                .iter()
+                .map(|e| ByteArray::from(e.entry_type.as_str()))
+                .collect::<Vec<_>>(),
+            None,
+        )?;
+        write_column::<Int64Type>(
+            &mut row_group,
+            &group.iter().map(|e| e.size as i64).collect::<Vec<_>>(),
+            None,
+        )?;
+        write_optional::<ByteArrayType>(&mut row_group, group, |e| {
+            e.owner.as_deref().map(ByteArray::from)
+        })?;
+        write_optional::<Int64Type>(&mut row_group, group, |e| e.inodes.map(|n| n as i64))?;
+        write_optional::<Int64Type>(&mut row_group, group, |e| {
+            e.mtime.map(|secs| secs as i64 * 1000)
+        })?;
+        write_optional::<Int64Type>(&mut row_group, group, |e| e.previous_size.map(|n| n as i64))?;
+        row_group.close()?;
+    }
+
+    writer.into_inner()?.flush()?;
+    Ok(())
+}
+
+/// Writes the next column of a row group
+fn write_column<T: DataType>(
+    row_group: &mut SerializedRowGroupWriter<'_, impl Write + Send>,
+    values: &[T::T],
+    def_levels: Option<&[i16]>,
+) -> Result<()> {
+    let mut column = row_group
+        .next_column()?
+        .ok_or_else(|| anyhow!("Parquet schema has fewer columns than written"))?;
+    column.typed::<T>().write_batch(values, def_levels, None)?;
+    column.close()?;
+    Ok(())
+}
+
+/// Writes the next, optional, column of a row group; `None` becomes null
+fn write_optional<T: DataType>(
+    row_group: &mut SerializedRowGroupWriter<'_, impl Write + Send>,
+    entries: &[FileEntry],
+    value: impl Fn(&FileEntry) -> Option<T::T>,
+) -> Result<()> {
+    let mut values = Vec::new();
+    let mut def_levels = Vec::with_capacity(entries.len());
+    for entry in entries {
+        match value(entry) {
+            Some(v) => {
+                values.push(v);
+                def_levels.push(1);
+            }
+            None => def_levels.push(0),
+        }
+    }
+    write_column::<T>(row_group, &values, Some(&def_levels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::EntryType;
+    use ::parquet::file::reader::{FileReader, SerializedFileReader};
+    use ::parquet::record::RowAccessor;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_parquet_round_trip() {
+        let entries = vec![
+            FileEntry {
+                path: PathBuf::from("/data/sub"),
+                size: 4096,
+                owner: Some("alice".to_string()),
+                inodes: Some(3),
+                entry_type: EntryType::Dir,
+                previous_size: None,
+                mtime: Some(1_700_000_000),
+                stat: None,
+            },
+            FileEntry {
+                path: PathBuf::from("/data/sub/a.txt"),
+                size: 512,
+                owner: None,
+                inodes: None,
+                entry_type: EntryType::File,
+                previous_size: Some(256),
+                mtime: None,
+                stat: None,
+            },
+        ];
+        let file = tempfile::NamedTempFile::new().unwrap();
+        render_to(file.reopen().unwrap(), &entries).unwrap();
+
+        let reader = SerializedFileReader::new(file.reopen().unwrap()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 2);
+        let rows: Vec<_> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+
+        assert_eq!(rows[0].get_string(0).unwrap(), "/data/sub");
+        assert_eq!(rows[0].get_string(1).unwrap(), "DIR");
+        assert_eq!(rows[0].get_ulong(2).unwrap(), 4096);
+        assert_eq!(rows[0].get_string(3).unwrap(), "alice");
+        assert_eq!(rows[0].get_ulong(4).unwrap(), 3);
+        assert_eq!(rows[0].get_timestamp_millis(5).unwrap(), 1_700_000_000_000);
+
+        assert_eq!(rows[1].get_string(1).unwrap(), "FILE");
+        assert!(rows[1].get_string(3).is_err());
+        assert!(rows[1].get_timestamp_millis(5).is_err());
+        assert_eq!(rows[1].get_ulong(6).unwrap(), 256);
+    }
+}
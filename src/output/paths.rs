@@ -0,0 +1,119 @@
+//! Output path styles chosen by `--absolute-paths` and `--relative-to`.
+//!
+//! By default listings (terminal, HTML) show paths relative to the scan
+//! root and machine-readable formats write them as scanned, e.g. `./src`
+//! for `rudu .`. A [`PathStyle`] instead writes every path absolute, or
+//! relative to an arbitrary prefix, so output can be joined against other
+//! inventories of the same filesystem.
+
+use crate::cli::Args;
+use crate::data::FileEntry;
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+/// A forced style for output paths
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathStyle {
+    /// The scan root, as given
+    root: PathBuf,
+    /// The scan root made absolute, with symlinks resolved if it exists
+    absolute_root: PathBuf,
+    /// The `--relative-to` prefix, made absolute the same way
+    base: Option<PathBuf>,
+}
+
+impl PathStyle {
+    /// The style chosen by `--absolute-paths` or `--relative-to`, or `None`
+    /// to keep each formatter's default.
+    pub fn from_args(args: &Args, root: &Path) -> Option<Self> {
+        match &args.relative_to {
+            Some(base) => Some(PathStyle::relative_to(root, base)),
+            None if args.absolute_paths => Some(PathStyle::absolute(root)),
+            None => None,
+        }
+    }
+
+    /// Absolute paths for entries under `root`
+    pub fn absolute(root: &Path) -> Self {
+        PathStyle {
+            root: root.to_path_buf(),
+            absolute_root: make_absolute(root),
+            base: None,
+        }
+    }
+
+    /// Paths relative to `base` for entries under `root`; entries outside
+    /// `base` keep their absolute path.
+    pub fn relative_to(root: &Path, base: &Path) -> Self {
+        PathStyle {
+            base: Some(make_absolute(base)),
+            ..PathStyle::absolute(root)
+        }
+    }
+
+    /// Rewrites a scanned path in this style.
+    ///
+    /// A path relative to `base` that would be empty (`base` itself) is
+    /// written as `.`.
+    pub fn apply(&self, path: &Path) -> PathBuf {
+        let absolute = match path.strip_prefix(&self.root) {
+            Ok(relative) if relative.as_os_str().is_empty() => self.absolute_root.clone(),
+            Ok(relative) => self.absolute_root.join(relative),
+            Err(_) => make_absolute(path),
+        };
+        match self.base.as_deref().map(|base| absolute.strip_prefix(base)) {
+            Some(Ok(relative)) if relative.as_os_str().is_empty() => PathBuf::from("."),
+            Some(Ok(relative)) => relative.to_path_buf(),
+            _ => absolute,
+        }
+    }
+
+    /// Entries with their paths rewritten by `style`, or unchanged without one.
+    pub fn restyle<'a>(style: Option<&Self>, entries: &'a [FileEntry]) -> Cow<'a, [FileEntry]> {
+        match style {
+            Some(style) => Cow::Owned(
+                entries
+                    .iter()
+                    .map(|entry| FileEntry {
+                        path: style.apply(&entry.path),
+                        ..entry.clone()
+                    })
+                    .collect(),
+            ),
+            None => Cow::Borrowed(entries),
+        }
+    }
+}
+
+/// `path` canonicalized, or made absolute without resolving anything if it
+/// does not exist (such as a prefix from another host's inventory).
+fn make_absolute(path: &Path) -> PathBuf {
+    path.canonicalize()
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_path_style() {
+        let root = Path::new("/rudu-test/projects");
+        let absolute = PathStyle::absolute(root);
+        assert_eq!(absolute.apply(root), root);
+        assert_eq!(absolute.apply(&root.join("a/b")), root.join("a/b"));
+
+        let relative = PathStyle::relative_to(root, Path::new("/rudu-test"));
+        assert_eq!(relative.apply(root), Path::new("projects"));
+        assert_eq!(relative.apply(&root.join("a")), Path::new("projects/a"));
+        assert_eq!(
+            PathStyle::relative_to(root, root).apply(root),
+            Path::new(".")
+        );
+        assert_eq!(
+            PathStyle::relative_to(root, Path::new("/rudu-scratch")).apply(&root.join("a")),
+            root.join("a")
+        );
+    }
+}
@@ -0,0 +1,365 @@
+//! `--printf` output formatter, modelled on `find -printf`.
+//!
+//! The format string is parsed once into literal text and field directives,
+//! then rendered for every entry, so shell pipelines get exactly the fields
+//! they want without post-processing CSV. Nothing is appended automatically:
+//! include `\n` to end each record.
+//!
+//! | Directive | Field |
+//! |-----------|-------|
+//! | `%p` | Path |
+//! | `%P` | Path relative to the scanned root |
+//! | `%f` | File name (last path component) |
+//! | `%s` | Size in bytes |
+//! | `%H` | Human-readable size (e.g. `1.2 MB`), in the `--block-size` or `--binary` unit if given |
+//! | `%u` | Owner username, empty if unknown |
+//! | `%i` | Inode count of directories, empty for files |
+//! | `%y` | Type: `d` for directories, `f` for files, `l` symlinks, `s` sockets, `p` FIFOs, `D` devices, `U` unknown, `a` listed archives |
+//! | `%t` | Modification time in seconds since the epoch, empty if unknown |
+//! | `%%` | A literal `%` |
+//!
+//! The escapes `\n`, `\t`, `\0` and `\\` are also recognised.
+
+use super::chunked::{CHUNK_SIZE, write_chunked};
+use super::units::SizeFormat;
+use crate::cli::Args;
+use crate::data::{EntryType, FileEntry};
+use crate::error::Error;
+use anyhow::Result;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// A field of an entry that a directive expands to
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Field {
+    Path,
+    RelativePath,
+    FileName,
+    SizeBytes,
+    SizeHuman,
+    Owner,
+    Inodes,
+    Type,
+    Mtime,
+}
+
+/// A piece of a parsed format string
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Field(Field),
+}
+
+/// A parsed `--printf` format string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrintfFormat {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+impl FromStr for PrintfFormat {
+    type Err = String;
+
+    /// Parses a format such as `%s\t%u\t%p\n`.
+    ///
+    /// Unknown directives and escapes, and a trailing lone `%` or `\`, are
+    /// rejected rather than printed verbatim.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '%' => {
+                    let field = match chars.next() {
+                        Some('%') => {
+                            literal.push('%');
+                            continue;
+                        }
+                        Some('p') => Field::Path,
+                        Some('P') => Field::RelativePath,
+                        Some('f') => Field::FileName,
+                        Some('s') => Field::SizeBytes,
+                        Some('H') => Field::SizeHuman,
+                        Some('u') => Field::Owner,
+                        Some('i') => Field::Inodes,
+                        Some('y') => Field::Type,
+                        Some('t') => Field::Mtime,
+                        Some(other) => return Err(format!("unknown directive '%{}'", other)),
+                        None => return Err("format ends with an incomplete '%'".to_string()),
+                    };
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Field(field));
+                }
+                '\\' => literal.push(match chars.next() {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('0') => '\0',
+                    Some('\\') => '\\',
+                    Some(other) => return Err(format!("unknown escape '\\{}'", other)),
+                    None => return Err("format ends with an incomplete '\\'".to_string()),
+                }),
+                other => literal.push(other),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Ok(PrintfFormat {
+            source: s.to_string(),
+            segments,
+        })
+    }
+}
+
+impl fmt::Display for PrintfFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl PrintfFormat {
+    /// The format followed by literal `suffix`, e.g. a NUL for `--null`
+    pub fn with_suffix(&self, suffix: &str) -> Self {
+        let mut format = self.clone();
+        format.source.push_str(suffix);
+        match format.segments.last_mut() {
+            Some(Segment::Literal(text)) => text.push_str(suffix),
+            _ => format.segments.push(Segment::Literal(suffix.to_string())),
+        }
+        format
+    }
+
+    /// Check whether the format prints owners (`%u`)
+    pub fn uses_owner(&self) -> bool {
+        self.segments.contains(&Segment::Field(Field::Owner))
+    }
+
+    /// Check whether the format prints inode counts (`%i`)
+    pub fn uses_inodes(&self) -> bool {
+        self.segments.contains(&Segment::Field(Field::Inodes))
+    }
+
+    /// Writes one entry according to the format.
+    ///
+    /// # Arguments
+    /// * `out` - Destination for the rendered record
+    /// * `entry` - The entry to render
+    /// * `root` - The scanned root, which `%P` paths are relative to
+    /// * `units` - Unit of `%H` sizes
+    pub fn write_entry<W: Write + ?Sized>(
+        &self,
+        out: &mut W,
+        entry: &FileEntry,
+        root: &Path,
+        units: SizeFormat,
+    ) -> io::Result<()> {
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => out.write_all(text.as_bytes())?,
+                Segment::Field(field) => write_field(out, *field, entry, root, units)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_field<W: Write + ?Sized>(
+    out: &mut W,
+    field: Field,
+    entry: &FileEntry,
+    root: &Path,
+    units: SizeFormat,
+) -> io::Result<()> {
+    match field {
+        Field::Path => write!(out, "{}", entry.path.display()),
+        Field::RelativePath => write!(
+            out,
+            "{}",
+            entry
+                .path
+                .strip_prefix(root)
+                .unwrap_or(&entry.path)
+                .display()
+        ),
+        Field::FileName => match entry.path.file_name() {
+            Some(name) => write!(out, "{}", name.to_string_lossy()),
+            None => write!(out, "{}", entry.path.display()),
+        },
+        Field::SizeBytes => write!(out, "{}", entry.size),
+        Field::SizeHuman => write!(out, "{}", units.format(entry.size)),
+        Field::Owner => write!(out, "{}", entry.owner.as_deref().unwrap_or("")),
+        Field::Inodes => match entry.inodes {
+            Some(inodes) => write!(out, "{}", inodes),
+            None => Ok(()),
+        },
+        Field::Type => out.write_all(match entry.entry_type {
+            EntryType::Dir => b"d",
+            EntryType::File => b"f",
+            EntryType::Symlink => b"l",
+            EntryType::Socket => b"s",
+            EntryType::Fifo => b"p",
+            EntryType::Device => b"D",
+            EntryType::Unknown => b"U",
+            EntryType::Archive => b"a",
+        }),
+        Field::Mtime => match entry.mtime {
+            Some(mtime) => write!(out, "{}", mtime),
+            None => Ok(()),
+        },
+    }
+}
+
+/// Renders file entries with the `--printf` format.
+///
+/// Writes to the `--output` file if one was given, otherwise to stdout. With
+/// `--null`, each record is followed by a NUL byte.
+///
+/// # Arguments
+/// * `entries` - A slice of already-filtered and sorted file entries to render
+/// * `args` - Command line arguments that control output formatting
+/// * `format` - The parsed `--printf` format string
+///
+/// # Returns
+/// * `Result<()>` - Ok if rendering succeeded, Err if there was an issue
+pub fn render(entries: &[FileEntry], args: &Args, format: &PrintfFormat) -> Result<()> {
+    let terminated;
+    let format = if args.null {
+        terminated = format.with_suffix("\0");
+        &terminated
+    } else {
+        format
+    };
+    match &args.output {
+        Some(output_file) => {
+            render_to(
+                &mut io::BufWriter::new(File::create(output_file)?),
+                entries,
+                format,
+                &args.path,
+                args.size_format(),
+            )?;
+            log::info!("Output written to: {}", output_file);
+        }
+        None => render_to(
+            &mut io::stdout().lock(),
+            entries,
+            format,
+            &args.path,
+            args.size_format(),
+        )?,
+    }
+    Ok(())
+}
+
+/// Renders file entries with a format to an arbitrary writer.
+///
+/// # Arguments
+/// * `out` - Destination for the rendered records
+/// * `entries` - Entries in output order
+/// * `format` - The parsed format string
+/// * `root` - The scanned root, which `%P` paths are relative to
+/// * `units` - Unit of `%H` sizes
+pub fn render_to<W: Write + ?Sized>(
+    out: &mut W,
+    entries: &[FileEntry],
+    format: &PrintfFormat,
+    root: &Path,
+    units: SizeFormat,
+) -> crate::error::Result<()> {
+    write_lines(out, entries, format, root, units).map_err(Error::output)
+}
+
+fn write_lines<W: Write + ?Sized>(
+    out: &mut W,
+    entries: &[FileEntry],
+    format: &PrintfFormat,
+    root: &Path,
+    units: SizeFormat,
+) -> Result<()> {
+    write_chunked(out, entries, CHUNK_SIZE, |chunk, buf| {
+        for entry in chunk {
+            format.write_entry(buf, entry, root, units)?;
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn entry() -> FileEntry {
+        FileEntry {
+            path: PathBuf::from("/data/sub/report.txt"),
+            size: 1500,
+            owner: Some("alice".to_string()),
+            inodes: None,
+            entry_type: EntryType::File,
+            previous_size: None,
+            mtime: Some(1_700_000_000),
+            stat: None,
+        }
+    }
+
+    fn render_one(format: &str) -> String {
+        let format: PrintfFormat = format.parse().unwrap();
+        let mut out = Vec::new();
+        format
+            .write_entry(&mut out, &entry(), Path::new("/data"), SizeFormat::Decimal)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_printf_directives() {
+        assert_eq!(
+            render_one("%s\\t%u\\t%p\\n"),
+            "1500\talice\t/data/sub/report.txt\n"
+        );
+        assert_eq!(
+            render_one("%y %P %f %H"),
+            "f sub/report.txt report.txt 1.50 kB"
+        );
+        assert_eq!(render_one("[%i] %t 100%%"), "[] 1700000000 100%");
+
+        let link = FileEntry {
+            entry_type: EntryType::Symlink,
+            ..entry()
+        };
+        let format: PrintfFormat = "%y".parse().unwrap();
+        let mut out = Vec::new();
+        format
+            .write_entry(&mut out, &link, Path::new("/data"), SizeFormat::Decimal)
+            .unwrap();
+        assert_eq!(out, b"l");
+
+        let format: PrintfFormat = "%s".parse().unwrap();
+        let mut out = Vec::new();
+        format
+            .with_suffix("\0")
+            .write_entry(&mut out, &entry(), Path::new("/data"), SizeFormat::Decimal)
+            .unwrap();
+        assert_eq!(out, b"1500\0");
+
+        let format: PrintfFormat = "%u %p".parse().unwrap();
+        assert!(format.uses_owner());
+        assert!(!format.uses_inodes());
+    }
+
+    #[test]
+    fn test_printf_parse_errors() {
+        assert!("%q".parse::<PrintfFormat>().unwrap_err().contains("%q"));
+        assert!("%s%".parse::<PrintfFormat>().is_err());
+        assert!("%s\\x".parse::<PrintfFormat>().is_err());
+        assert!("%s\\".parse::<PrintfFormat>().is_err());
+    }
+}
@@ -0,0 +1,370 @@
+//! `--template` output: user-supplied Handlebars templates.
+//!
+//! Sites that need a bespoke layout (a plain-text email, wiki markup, a
+//! ticket comment) can describe it in a [Handlebars] template instead of
+//! post-processing CSV or forking a formatter. The template is compiled when
+//! the command line is parsed, so syntax errors are reported before the scan.
+//!
+//! Templates are rendered with this data:
+//!
+//! | Field | Contents |
+//! |-------|----------|
+//! | `root` | The scanned root |
+//! | `run_id` | Run ID, as in `manifest.json` |
+//! | `started_at`, `finished_at` | Scan start and end, RFC 3339 in UTC |
+//! | `elapsed_ms` | Scan duration in milliseconds |
+//! | `totals` | `size`, `size_human`, `files`, `dirs`, `entries` (listed) and `partial` |
+//! | `mounts` | Mountpoints crossed below the root: `path`, `device`, `fs_type` and `excluded` |
+//! | `entries` | Listed entries: `path`, `relative_path`, `name`, `entry_type`, `size`, `size_human`, `owner`, `inodes`, `mtime`, `previous_size`, and `stat` (`atime`, `nlink`, `uid`, `gid`, `mode`, `dev`, `ino`, `apparent_size`) with `--extended-metadata` |
+//! | `profile` | `--profile` data collected up to the output phase, or null |
+//!
+//! Besides the built-in helpers (`each`, `if`, `eq`, `gt`, ...), `{{human n}}`
+//! formats a byte count in the selected size unit. Values are inserted as-is,
+//! except in templates whose file name ends in `.html` or `.htm`, where they
+//! are HTML-escaped.
+//!
+//! ```text
+//! Usage of {{root}}: {{totals.size_human}} in {{totals.files}} files
+//! {{#each entries}}
+//! * {{relative_path}} - {{size_human}}{{#if owner}} ({{owner}}){{/if}}
+//! {{/each}}
+//! ```
+//!
+//! [Handlebars]: https://handlebarsjs.com/guide/
+
+use super::units::SizeFormat;
+use crate::cli::Args;
+use crate::data::{EntryStat, FileEntry, MountPoint, ScanMeta};
+use crate::error::Error;
+use crate::metrics::ProfileData;
+use anyhow::{Context as _, Result};
+use handlebars::{
+    Context, Handlebars, Helper, HelperResult, Output, RenderContext, RenderErrorReason, Template,
+    html_escape, no_escape,
+};
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Name the template is registered under
+const TEMPLATE_NAME: &str = "report";
+
+/// A `--template` file, read and syntax-checked.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReportTemplate {
+    path: PathBuf,
+    source: String,
+}
+
+impl ReportTemplate {
+    /// Reads and compiles the template at `path`.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or is not a valid
+    /// Handlebars template.
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read template {}", path.display()))?;
+        Self::new(path, source)
+    }
+
+    /// A template with the given source; `path` decides whether values are
+    /// HTML-escaped.
+    pub fn new(path: impl Into<PathBuf>, source: String) -> Result<Self> {
+        let path = path.into();
+        Template::compile(&source)
+            .with_context(|| format!("Invalid template {}", path.display()))?;
+        Ok(ReportTemplate { path, source })
+    }
+
+    /// Whether values are HTML-escaped, i.e. the template is an HTML file
+    fn escapes_html(&self) -> bool {
+        self.path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("html") || ext.eq_ignore_ascii_case("htm"))
+    }
+}
+
+/// Data a template is rendered with; see the module documentation
+#[derive(Serialize)]
+struct TemplateData<'a> {
+    root: String,
+    run_id: &'a str,
+    started_at: String,
+    finished_at: String,
+    elapsed_ms: u64,
+    totals: Totals,
+    mounts: &'a [MountPoint],
+    entries: Vec<EntryData<'a>>,
+    profile: Option<&'a ProfileData>,
+}
+
+/// Scan totals exposed to templates as `totals`
+#[derive(Serialize)]
+struct Totals {
+    size: u64,
+    size_human: String,
+    files: u64,
+    dirs: u64,
+    entries: usize,
+    partial: bool,
+}
+
+/// One entry as exposed to templates
+#[derive(Serialize)]
+struct EntryData<'a> {
+    path: String,
+    relative_path: String,
+    name: String,
+    entry_type: &'static str,
+    size: u64,
+    size_human: String,
+    owner: Option<&'a str>,
+    inodes: Option<u64>,
+    mtime: Option<u64>,
+    previous_size: Option<u64>,
+    stat: Option<&'a EntryStat>,
+}
+
+/// Renders file entries through a `--template`.
+///
+/// Writes to the `--output` file if one was given, otherwise to stdout.
+///
+/// # Arguments
+/// * `entries` - A slice of already-filtered and sorted file entries to render
+/// * `args` - Command line arguments that control output formatting
+/// * `meta` - Metadata of the scan
+/// * `template` - The template to render
+/// * `profile` - Profiling data so far, when `--profile` is enabled
+///
+/// # Returns
+/// * `Result<()>` - Ok if rendering succeeded, Err if there was an issue
+pub fn render(
+    entries: &[FileEntry],
+    args: &Args,
+    meta: &ScanMeta,
+    template: &ReportTemplate,
+    profile: Option<&ProfileData>,
+) -> Result<()> {
+    let units = args.size_format();
+    match &args.output {
+        Some(output_file) => {
+            let mut file = io::BufWriter::new(File::create(output_file)?);
+            render_to(&mut file, entries, meta, template, profile, units)?;
+            file.flush()?;
+            log::info!("Template output written to: {}", output_file);
+        }
+        None => render_to(
+            &mut io::stdout().lock(),
+            entries,
+            meta,
+            template,
+            profile,
+            units,
+        )?,
+    }
+    Ok(())
+}
+
+/// Renders file entries through a template to an arbitrary writer.
+///
+/// # Arguments
+/// * `out` - Destination for the rendered text
+/// * `entries` - Entries in output order
+/// * `meta` - Metadata of the scan
+/// * `template` - The template to render
+/// * `profile` - Profiling data so far, if any
+/// * `units` - Unit of `size_human` fields and the `human` helper
+pub fn render_to<W: Write + ?Sized>(
+    out: &mut W,
+    entries: &[FileEntry],
+    meta: &ScanMeta,
+    template: &ReportTemplate,
+    profile: Option<&ProfileData>,
+    units: SizeFormat,
+) -> crate::error::Result<()> {
+    write_report(out, entries, meta, template, profile, units).map_err(Error::output)
+}
+
+fn write_report<W: Write + ?Sized>(
+    out: &mut W,
+    entries: &[FileEntry],
+    meta: &ScanMeta,
+    template: &ReportTemplate,
+    profile: Option<&ProfileData>,
+    units: SizeFormat,
+) -> Result<()> {
+    let mut registry = Handlebars::new();
+    if template.escapes_html() {
+        registry.register_escape_fn(html_escape);
+    } else {
+        registry.register_escape_fn(no_escape);
+    }
+    registry.register_helper(
+        "human",
+        Box::new(
+            move |h: &Helper<'_>,
+                  _: &Handlebars<'_>,
+                  _: &Context,
+                  _: &mut RenderContext<'_, '_>,
+                  out: &mut dyn Output|
+                  -> HelperResult {
+                let bytes = h.param(0).and_then(|p| p.value().as_u64()).ok_or_else(|| {
+                    RenderErrorReason::ParamTypeMismatchForName(
+                        "human",
+                        "0".to_string(),
+                        "a byte count".to_string(),
+                    )
+                })?;
+                out.write(&units.format(bytes))?;
+                Ok(())
+            },
+        ),
+    );
+    registry
+        .register_template_string(TEMPLATE_NAME, &template.source)
+        .with_context(|| format!("Invalid template {}", template.path.display()))?;
+
+    let data = TemplateData {
+        root: meta.root.display().to_string(),
+        run_id: &meta.run_id,
+        started_at: meta.started_at.to_rfc3339(),
+        finished_at: meta.finished_at.to_rfc3339(),
+        elapsed_ms: meta.elapsed_ms(),
+        totals: Totals {
+            size: meta.total_size,
+            size_human: units.format(meta.total_size),
+            files: meta.file_count,
+            dirs: meta.dir_count,
+            entries: entries.len(),
+            partial: meta.partial,
+        },
+        mounts: &meta.mounts,
+        entries: entries
+            .iter()
+            .map(|entry| EntryData {
+                path: entry.path.display().to_string(),
+                relative_path: entry
+                    .path
+                    .strip_prefix(&meta.root)
+                    .unwrap_or(&entry.path)
+                    .display()
+                    .to_string(),
+                name: entry
+                    .path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                entry_type: entry.entry_type.as_str(),
+                size: entry.size,
+                size_human: units.format(entry.size),
+                owner: entry.owner.as_deref(),
+                inodes: entry.inodes,
+                mtime: entry.mtime,
+                previous_size: entry.previous_size,
+                stat: entry.stat.as_deref(),
+            })
+            .collect(),
+        profile,
+    };
+
+    let mut buf = io::BufWriter::new(out);
+    registry
+        .render_to_write(TEMPLATE_NAME, &data, &mut buf)
+        .with_context(|| format!("Failed to render template {}", template.path.display()))?;
+    buf.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::EntryType;
+
+    fn sample() -> (Vec<FileEntry>, ScanMeta) {
+        let entries = vec![
+            FileEntry {
+                path: PathBuf::from("/data/sub"),
+                size: 2_500_000,
+                owner: Some("alice".to_string()),
+                inodes: Some(2),
+                entry_type: EntryType::Dir,
+                previous_size: None,
+                mtime: None,
+                stat: None,
+            },
+            FileEntry {
+                path: PathBuf::from("/data/sub/<a>.txt"),
+                size: 1000,
+                owner: None,
+                inodes: None,
+                entry_type: EntryType::File,
+                previous_size: None,
+                mtime: Some(1_700_000_000),
+                stat: None,
+            },
+        ];
+        let meta = ScanMeta {
+            root: PathBuf::from("/data"),
+            total_size: 2_500_000,
+            file_count: 1,
+            dir_count: 2,
+            ..ScanMeta::default()
+        };
+        (entries, meta)
+    }
+
+    fn render_str(path: &str, source: &str) -> String {
+        let (entries, meta) = sample();
+        let template = ReportTemplate::new(path, source.to_string()).unwrap();
+        let mut out = Vec::new();
+        render_to(
+            &mut out,
+            &entries,
+            &meta,
+            &template,
+            None,
+            SizeFormat::Decimal,
+        )
+        .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_template_data() {
+        let text = render_str(
+            "report.txt",
+            "{{root}}: {{totals.size_human}}, {{totals.files}} files, {{totals.entries}} listed\n\
+             {{#each entries}}{{entry_type}} {{relative_path}} {{size}} [{{owner}}]\n{{/each}}",
+        );
+        assert_eq!(
+            text,
+            "/data: 2.50 MB, 1 files, 2 listed\n\
+             DIR sub 2500000 [alice]\n\
+             FILE sub/<a>.txt 1000 []\n"
+        );
+    }
+
+    #[test]
+    fn test_template_human_helper_and_escaping() {
+        assert_eq!(
+            render_str("t.txt", "{{human totals.size}}"),
+            SizeFormat::Decimal.format(2_500_000)
+        );
+        assert_eq!(
+            render_str("t.wiki", "{{#each entries}}{{name}};{{/each}}"),
+            "sub;<a>.txt;"
+        );
+        assert_eq!(
+            render_str("t.HTML", "{{#each entries}}{{name}};{{/each}}"),
+            "sub;&lt;a&gt;.txt;"
+        );
+    }
+
+    #[test]
+    fn test_invalid_template() {
+        assert!(ReportTemplate::new("t.txt", "{{#each entries}}".to_string()).is_err());
+    }
+}
@@ -2,61 +2,403 @@
 //!
 //! This module provides functionality to display file system scan results
 //! in a human-readable format directly to the terminal.
+//!
+//! When a cache from a previous run exists, directories also get a delta
+//! column showing how much they grew or shrank since that cache was built.
+//!
+//! With `--tree`, entries are grouped under their parent directory and drawn
+//! with box-drawing connectors instead of as a flat list of sub-paths;
+//! siblings keep the order given by `--sort`.
+//!
+//! Output is colored according to a [`Palette`] chosen by `--color`: `auto`
+//! colors only when writing to a terminal and `NO_COLOR` is unset.
+//!
+//! Columns follow `--columns` when given (see [`columns`](super::columns)).
+//! With `--null`, lines end with NUL instead of a newline.
+//!
+//! With `--bar`, each directory also gets a [`usage_bar`] scaled to its
+//! largest sibling, so the biggest subdirectories stand out.
 
-use crate::cli::Args;
-use crate::data::{EntryType, FileEntry};
+use super::chunked::{CHUNK_SIZE, write_chunked};
+use super::columns::{Column, ColumnSpec};
+use super::paths::PathStyle;
+use super::time::TimeStyle;
+use super::units::SizeFormat;
+use crate::cli::{Args, ColorChoice};
+use crate::data::{EntryType, FileEntry, ScanMeta};
 use anyhow::Result;
-use humansize::{DECIMAL, format_size};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{self, IsTerminal, Write};
 use std::path::Path;
 
+/// Width of the `--bar` column in characters
+pub const BAR_WIDTH: usize = 10;
+
+/// ANSI SGR sequences for each part of a terminal line.
+///
+/// An empty sequence leaves that part unstyled; [`Palette::PLAIN`] styles
+/// nothing and produces the same bytes as uncolored output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    /// Directory tag and name
+    pub dir: &'static str,
+    /// File tag and name
+    pub file: &'static str,
+    /// Owner column
+    pub owner: &'static str,
+    /// Size styles as `(minimum bytes, style)`, largest threshold first
+    pub sizes: [(u64, &'static str); 3],
+}
+
+impl Palette {
+    /// No styling at all
+    pub const PLAIN: Palette = Palette {
+        dir: "",
+        file: "",
+        owner: "",
+        sizes: [(0, ""); 3],
+    };
+
+    /// The default colors: bold blue directories, cyan owners, and sizes in
+    /// red from 1 TB, yellow from 1 GB and green from 1 MB
+    pub const DEFAULT: Palette = Palette {
+        dir: "\x1b[1;34m",
+        file: "",
+        owner: "\x1b[36m",
+        sizes: [
+            (1_000_000_000_000, "\x1b[31m"),
+            (1_000_000_000, "\x1b[33m"),
+            (1_000_000, "\x1b[32m"),
+        ],
+    };
+
+    /// Chooses the palette for `--color`.
+    ///
+    /// # Arguments
+    /// * `choice` - The `--color` setting
+    /// * `is_terminal` - Whether the output goes to a terminal
+    pub fn resolve(choice: ColorChoice, is_terminal: bool) -> Palette {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        match choice {
+            ColorChoice::Always => Palette::DEFAULT,
+            ColorChoice::Auto if is_terminal && !no_color => Palette::DEFAULT,
+            _ => Palette::PLAIN,
+        }
+    }
+
+    /// The style for an entry of `size` bytes.
+    pub fn size(&self, size: u64) -> &'static str {
+        self.sizes
+            .iter()
+            .find(|(min, _)| size >= *min)
+            .map_or("", |(_, style)| style)
+    }
+
+    /// Wraps `text` in `style`, resetting afterwards.
+    fn paint(style: &str, text: impl std::fmt::Display) -> String {
+        if style.is_empty() {
+            text.to_string()
+        } else {
+            format!("{}{}\x1b[0m", style, text)
+        }
+    }
+}
+
 /// Renders file entries to terminal output.
 ///
 /// # Arguments
 /// * `entries` - A slice of already-filtered and sorted file entries to render
 /// * `args` - Command line arguments that control output formatting
-/// * `root` - The root path used to strip path prefixes from output
+/// * `meta` - Metadata of the scan; its root is stripped from displayed paths
 ///
 /// # Returns
 /// * `Result<()>` - Ok if rendering succeeded, Err if there was an issue
-pub fn render(entries: &[FileEntry], args: &Args, root: &Path) -> Result<()> {
+pub fn render(entries: &[FileEntry], args: &Args, meta: &ScanMeta) -> Result<()> {
+    let palette = Palette::resolve(args.color, io::stdout().is_terminal());
+    render_styled(&mut io::stdout().lock(), entries, args, meta, &palette)
+}
+
+/// Renders file entries in terminal format to an arbitrary writer.
+///
+/// Lines are formatted in parallel chunks (see [`chunked`](super::chunked))
+/// and written in the order of `entries`. The writer is not a terminal, so
+/// lines are only colored with `--color always`.
+///
+/// # Arguments
+/// * `out` - Destination for the rendered lines
+/// * `entries` - A slice of already-filtered and sorted file entries to render
+/// * `args` - Command line arguments that control output formatting
+/// * `meta` - Metadata of the scan; its root is stripped from displayed paths
+pub fn render_to<W: Write + ?Sized>(
+    out: &mut W,
+    entries: &[FileEntry],
+    args: &Args,
+    meta: &ScanMeta,
+) -> Result<()> {
+    render_styled(
+        out,
+        entries,
+        args,
+        meta,
+        &Palette::resolve(args.color, false),
+    )
+}
+
+/// Renders file entries in terminal format with an explicit palette.
+pub fn render_styled<W: Write + ?Sized>(
+    out: &mut W,
+    entries: &[FileEntry],
+    args: &Args,
+    meta: &ScanMeta,
+    palette: &Palette,
+) -> Result<()> {
+    let root = meta.root.as_path();
+    let ctx = LineContext {
+        columns: ColumnSpec::for_terminal(args),
+        units: args.size_format(),
+        terminator: if args.null { '\0' } else { '\n' },
+        show_delta: entries.iter().any(|e| e.previous_size.is_some()),
+        time_style: args.time_style.clone().unwrap_or(TimeStyle::LongIso),
+        palette,
+        sibling_max: args.bar.then(|| largest_siblings(entries)),
+    };
+
+    if args.tree {
+        let lines = tree_lines(entries, root);
+        return write_chunked(out, &lines, CHUNK_SIZE, |chunk, buf| {
+            for (entry, connectors, name) in chunk {
+                write_line(buf, entry, &ctx, connectors, name)?;
+            }
+            Ok(())
+        });
+    }
+
+    let paths = PathStyle::from_args(args, root);
+    write_chunked(out, entries, CHUNK_SIZE, |chunk, buf| {
+        for entry in chunk {
+            // A --summarize total is labelled with the root itself
+            let display_path = match &paths {
+                Some(paths) => Cow::Owned(paths.apply(&entry.path)),
+                None if args.summarize => Cow::Borrowed(entry.path.as_path()),
+                None => Cow::Borrowed(entry.path.strip_prefix(root).unwrap_or(&entry.path)),
+            };
+            write_line(buf, entry, &ctx, "", display_path.display())?;
+        }
+        Ok(())
+    })
+}
+
+/// Settings shared by every line of one rendering
+struct LineContext<'a> {
+    columns: ColumnSpec,
+    units: SizeFormat,
+    /// Ends each line: a newline, or NUL with `--null`
+    terminator: char,
+    show_delta: bool,
+    time_style: TimeStyle,
+    palette: &'a Palette,
+    /// Size of the largest listed entry under each parent, with `--bar`
+    sibling_max: Option<HashMap<&'a Path, u64>>,
+}
+
+/// Size of the largest entry under each parent directory.
+fn largest_siblings(entries: &[FileEntry]) -> HashMap<&Path, u64> {
+    let mut max: HashMap<&Path, u64> = HashMap::new();
     for entry in entries {
-        let owner = if args.show_owner {
-            entry.owner.clone().unwrap_or_else(|| "unknown".to_string())
+        if let Some(parent) = entry.path.parent() {
+            let slot = max.entry(parent).or_default();
+            *slot = (*slot).max(entry.size);
+        }
+    }
+    max
+}
+
+/// Draws a bar of [`BAR_WIDTH`] characters filled in proportion to
+/// `size / max`, e.g. `"▇▇▇▁▁▁▁▁▁▁"`.
+///
+/// Any non-zero size gets at least one filled block; a zero `max` gives an
+/// empty bar.
+pub fn usage_bar(size: u64, max: u64) -> String {
+    let filled = if max == 0 || size == 0 {
+        0
+    } else {
+        ((size as u128 * BAR_WIDTH as u128 + max as u128 / 2) / max as u128)
+            .clamp(1, BAR_WIDTH as u128) as usize
+    };
+    format!("{}{}", "▇".repeat(filled), "▁".repeat(BAR_WIDTH - filled))
+}
+
+/// Orders entries as a tree and gives each its connectors and name.
+///
+/// Each entry is placed under its nearest listed ancestor; siblings keep
+/// their order in `entries`. Entries without a listed ancestor (normally
+/// just the root) start the tree and are labelled with their path relative
+/// to `root`, or with `root` itself.
+///
+/// # Returns
+/// * `Vec<(&FileEntry, String, String)>` - Entries in depth-first order with
+///   their connectors, such as `"│   ├── "`, and names
+pub fn tree_lines<'a>(
+    entries: &'a [FileEntry],
+    root: &Path,
+) -> Vec<(&'a FileEntry, String, String)> {
+    let index: HashMap<&Path, usize> = entries
+        .iter()
+        .enumerate()
+        .map(|(i, e)| (e.path.as_path(), i))
+        .collect();
+
+    let mut tops = Vec::new();
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); entries.len()];
+    let mut parents: Vec<Option<usize>> = vec![None; entries.len()];
+    for (i, entry) in entries.iter().enumerate() {
+        parents[i] = entry
+            .path
+            .ancestors()
+            .skip(1)
+            .take_while(|a| a.starts_with(root))
+            .find_map(|a| index.get(a).copied());
+        match parents[i] {
+            Some(p) => children[p].push(i),
+            None => tops.push(i),
+        }
+    }
+
+    let mut lines = Vec::with_capacity(entries.len());
+    // (entry, indent inherited from ancestors, connector for this entry)
+    let mut stack: Vec<(usize, String, &str)> =
+        tops.iter().rev().map(|&i| (i, String::new(), "")).collect();
+    while let Some((i, indent, connector)) = stack.pop() {
+        let entry = &entries[i];
+        let base = parents[i].map_or(root, |p| entries[p].path.as_path());
+        let name = entry.path.strip_prefix(base).unwrap_or(&entry.path);
+        let name = if name.as_os_str().is_empty() {
+            root.display().to_string()
         } else {
-            "".to_string()
+            name.display().to_string()
         };
+        lines.push((entry, format!("{}{}", indent, connector), name));
 
-        let display_path = entry.path.strip_prefix(root).unwrap_or(&entry.path);
-
-        match entry.entry_type {
-            EntryType::Dir => {
-                if args.show_inodes {
-                    println!(
-                        "[DIR]  {:<12} {:<10} {:<6} {}",
-                        format_size(entry.size, DECIMAL),
-                        owner,
-                        entry.inodes.unwrap_or(0),
-                        display_path.display()
-                    );
-                } else {
-                    println!(
-                        "[DIR]  {:<12} {:<10} {}",
-                        format_size(entry.size, DECIMAL),
-                        owner,
-                        display_path.display()
-                    );
+        let child_indent = match connector {
+            "├── " => format!("{}│   ", indent),
+            "└── " => format!("{}    ", indent),
+            _ => indent,
+        };
+        let kids = &children[i];
+        for (n, &child) in kids.iter().enumerate().rev() {
+            let connector = if n + 1 == kids.len() {
+                "└── "
+            } else {
+                "├── "
+            };
+            stack.push((child, child_indent.clone(), connector));
+        }
+    }
+    lines
+}
+
+fn write_line(
+    buf: &mut Vec<u8>,
+    entry: &FileEntry,
+    ctx: &LineContext,
+    connectors: &str,
+    display_path: impl std::fmt::Display,
+) -> io::Result<()> {
+    let palette = ctx.palette;
+    let (tag, style) = match entry.entry_type {
+        EntryType::Dir => ("[DIR]", palette.dir),
+        EntryType::File => ("[FILE]", palette.file),
+        EntryType::Symlink => ("[LINK]", palette.file),
+        EntryType::Socket => ("[SOCK]", palette.file),
+        EntryType::Fifo => ("[FIFO]", palette.file),
+        EntryType::Device => ("[DEV]", palette.file),
+        EntryType::Unknown => ("[?]", palette.file),
+        EntryType::Archive => ("[ARC]", palette.dir),
+    };
+
+    // Pad before painting so escape sequences do not count towards widths
+    let fields: Vec<String> = ctx
+        .columns
+        .columns()
+        .iter()
+        .map(|column| match column {
+            Column::Type => format!(
+                "{}{}",
+                Palette::paint(style, tag),
+                " ".repeat(6 - tag.len())
+            ),
+            Column::Size => size_field(entry, ctx),
+            Column::Owner => Palette::paint(
+                palette.owner,
+                format!("{:<10}", entry.owner.as_deref().unwrap_or("unknown")),
+            ),
+            Column::Inodes => match entry.entry_type {
+                EntryType::Dir | EntryType::Archive => format!("{:<6}", entry.inodes.unwrap_or(0)),
+                _ => format!("{:<6}", ""),
+            },
+            Column::Mtime => format!(
+                "{:<width$}",
+                ctx.time_style.format(entry.mtime),
+                width = ctx.time_style.width()
+            ),
+            Column::Path => format!("{}{}", connectors, Palette::paint(style, &display_path)),
+        })
+        .collect();
+    write!(buf, "{}{}", fields.join(" "), ctx.terminator)
+}
+
+/// The size column, followed by the delta and `--bar` columns when shown.
+fn size_field(entry: &FileEntry, ctx: &LineContext) -> String {
+    let palette = ctx.palette;
+    let size = Palette::paint(
+        palette.size(entry.size),
+        format!("{:<12}", ctx.units.format(entry.size)),
+    );
+    let size = if ctx.show_delta {
+        format!(
+            "{} {:<12}",
+            size,
+            format_delta(entry.previous_size, entry.size, ctx.units)
+        )
+    } else {
+        size
+    };
+    // Files keep the column blank so directory bars line up
+    match &ctx.sibling_max {
+        Some(sibling_max) => {
+            let bar = match (entry.entry_type, entry.path.parent()) {
+                (EntryType::Dir | EntryType::Archive, Some(parent)) => {
+                    usage_bar(entry.size, sibling_max.get(parent).copied().unwrap_or(0))
                 }
-            }
-            EntryType::File => {
-                println!(
-                    "[FILE] {:<12} {:<10} {}",
-                    format_size(entry.size, DECIMAL),
-                    owner,
-                    display_path.display()
-                );
-            }
+                (EntryType::Dir, None) => usage_bar(1, 1),
+                _ => " ".repeat(BAR_WIDTH),
+            };
+            format!("{} {}", size, Palette::paint(palette.size(entry.size), bar))
         }
+        None => size,
     }
+}
+
+/// Formats an mtime as local `YYYY-MM-DD HH:MM`, or `-` if unknown.
+pub fn format_mtime(mtime: Option<u64>) -> String {
+    TimeStyle::LongIso.format(mtime)
+}
 
-    Ok(())
+/// Formats the change from `previous` to `current` as a signed size.
+///
+/// # Arguments
+/// * `previous` - Size recorded by the previous scan, if any
+/// * `current` - Size found by this scan
+/// * `units` - Unit to show the difference in
+///
+/// # Returns
+/// * `String` - e.g. `"+12.3 GB"` or `"-4.1 kB"`; empty if there is no
+///   previous size or the size is unchanged
+pub fn format_delta(previous: Option<u64>, current: u64, units: SizeFormat) -> String {
+    match previous {
+        Some(previous) if current > previous => format!("+{}", units.format(current - previous)),
+        Some(previous) if current < previous => format!("-{}", units.format(previous - current)),
+        _ => String::new(),
+    }
 }
@@ -0,0 +1,113 @@
+//! Modification time formatting shared by the terminal and CSV formatters.
+//!
+//! `--time-style` picks the format of `mtime` columns, with the styles of
+//! `du --time-style` plus `rfc3339`. Without it, the terminal shows
+//! `long-iso` in local time and CSV keeps RFC 3339 in UTC.
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, SecondsFormat};
+use std::fmt;
+use std::str::FromStr;
+
+/// How modification times are written
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TimeStyle {
+    /// Local `2023-11-14 22:13:20.000000000 +0000`
+    FullIso,
+    /// Local `2023-11-14 22:13`
+    LongIso,
+    /// Local `2023-11-14`
+    Iso,
+    /// UTC `2023-11-14T22:13:20Z`
+    Rfc3339,
+    /// A `strftime` format in local time, given as `+FORMAT`
+    Custom(String),
+}
+
+impl TimeStyle {
+    /// Formats an mtime in seconds since the epoch, or `-` if unknown
+    pub fn format(&self, mtime: Option<u64>) -> String {
+        let Some(time) = mtime.and_then(|secs| DateTime::from_timestamp(secs as i64, 0)) else {
+            return "-".to_string();
+        };
+        let local = time.with_timezone(&Local);
+        match self {
+            TimeStyle::FullIso => local.format("%Y-%m-%d %H:%M:%S.%f %z").to_string(),
+            TimeStyle::LongIso => local.format("%Y-%m-%d %H:%M").to_string(),
+            TimeStyle::Iso => local.format("%Y-%m-%d").to_string(),
+            TimeStyle::Rfc3339 => time.to_rfc3339_opts(SecondsFormat::Secs, true),
+            TimeStyle::Custom(format) => local.format(format).to_string(),
+        }
+    }
+
+    /// Width of a formatted time, for aligning terminal columns
+    pub fn width(&self) -> usize {
+        self.format(Some(0)).chars().count()
+    }
+}
+
+impl fmt::Display for TimeStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeStyle::FullIso => f.write_str("full-iso"),
+            TimeStyle::LongIso => f.write_str("long-iso"),
+            TimeStyle::Iso => f.write_str("iso"),
+            TimeStyle::Rfc3339 => f.write_str("rfc3339"),
+            TimeStyle::Custom(format) => write!(f, "+{}", format),
+        }
+    }
+}
+
+impl FromStr for TimeStyle {
+    type Err = String;
+
+    /// Parses `full-iso`, `long-iso`, `iso`, `rfc3339` or `+FORMAT`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(format) = s.strip_prefix('+') {
+            if StrftimeItems::new(format).any(|item| item == Item::Error) {
+                return Err(format!("invalid time format '{}'", format));
+            }
+            return Ok(TimeStyle::Custom(format.to_string()));
+        }
+        match s {
+            "full-iso" => Ok(TimeStyle::FullIso),
+            "long-iso" => Ok(TimeStyle::LongIso),
+            "iso" => Ok(TimeStyle::Iso),
+            "rfc3339" => Ok(TimeStyle::Rfc3339),
+            _ => Err(format!(
+                "unknown time style '{}' (expected full-iso, long-iso, iso, rfc3339 or +FORMAT)",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_time_style() {
+        assert_eq!("iso".parse::<TimeStyle>(), Ok(TimeStyle::Iso));
+        assert_eq!("long-iso".parse::<TimeStyle>(), Ok(TimeStyle::LongIso));
+        assert_eq!(
+            "+%Y/%m".parse::<TimeStyle>(),
+            Ok(TimeStyle::Custom("%Y/%m".to_string()))
+        );
+        assert!("locale".parse::<TimeStyle>().is_err());
+        assert!("+%Q".parse::<TimeStyle>().is_err());
+    }
+
+    #[test]
+    fn test_format_time_style() {
+        let mtime = Some(1_700_000_000);
+        assert_eq!(TimeStyle::Rfc3339.format(mtime), "2023-11-14T22:13:20Z");
+        assert_eq!(TimeStyle::Iso.format(None), "-");
+        assert_eq!(TimeStyle::LongIso.width(), 16);
+        assert_eq!(TimeStyle::Iso.width(), 10);
+        assert_eq!(
+            TimeStyle::Custom("%Y".to_string()).format(mtime),
+            TimeStyle::Iso.format(mtime)[..4]
+        );
+    }
+}
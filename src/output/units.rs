@@ -0,0 +1,154 @@
+//! How sizes are rendered for people.
+//!
+//! Sizes default to human-readable SI units (`1.20 MB`), matching earlier
+//! releases. `--binary` switches to powers of 1024 (`1.14 MiB`), and
+//! `-B/--block-size`, `-k` and `-m` print whole blocks the way `du` does,
+//! rounding partial blocks up. Machine-readable fields such as CSV
+//! `size_bytes` always stay in bytes.
+
+use humansize::{BINARY, DECIMAL, format_size};
+
+/// Unit used for human-facing sizes
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum SizeFormat {
+    /// Human-readable, powers of 1000 (`kB`, `MB`, ...)
+    #[default]
+    Decimal,
+    /// Human-readable, powers of 1024 (`KiB`, `MiB`, ...)
+    Binary,
+    /// Number of blocks of the given size, rounded up
+    Blocks(u64),
+}
+
+impl SizeFormat {
+    /// Formats `bytes` in this unit
+    pub fn format(self, bytes: u64) -> String {
+        match self {
+            SizeFormat::Decimal => format_size(bytes, DECIMAL),
+            SizeFormat::Binary => format_size(bytes, BINARY),
+            SizeFormat::Blocks(block) => bytes.div_ceil(block.max(1)).to_string(),
+        }
+    }
+}
+
+/// Parses a block size such as `4096`, `1K`, `4KB` or `1M`.
+///
+/// As with `du`, `K`, `M`, `G` and `T` (optionally followed by `iB`) are
+/// powers of 1024, while `KB`, `MB`, `GB` and `TB` are powers of 1000. A
+/// suffix without a number means one unit.
+///
+/// # Errors
+/// Returns a message for an unknown suffix, a zero size or an overflow.
+pub fn parse_block_size(s: &str) -> Result<u64, String> {
+    match parse_bytes(s, "block size")? {
+        0 => Err("block size must be greater than zero".to_string()),
+        size => Ok(size),
+    }
+}
+
+/// Parses a size threshold such as `0`, `500M` or `1G`, with the same
+/// suffixes as [`parse_block_size`].
+///
+/// # Errors
+/// Returns a message for an unknown suffix or an overflow.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    parse_bytes(s, "size")
+}
+
+/// A du-style `--threshold`: entries below it are hidden, or with a negative
+/// value, entries above it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Threshold {
+    /// Only entries of at least this many bytes are shown (`-t 1G`)
+    AtLeast(u64),
+    /// Only entries of at most this many bytes are shown (`-t -1G`)
+    AtMost(u64),
+}
+
+impl Threshold {
+    /// Whether an entry of `size` bytes is shown
+    pub fn admits(self, size: u64) -> bool {
+        match self {
+            Threshold::AtLeast(min) => size >= min,
+            Threshold::AtMost(max) => size <= max,
+        }
+    }
+}
+
+/// Parses a `--threshold` such as `1G` or `-500M`, with the same suffixes as
+/// [`parse_block_size`].
+///
+/// # Errors
+/// Returns a message for an unknown suffix or an overflow.
+pub fn parse_threshold(s: &str) -> Result<Threshold, String> {
+    match s.strip_prefix('-') {
+        Some(size) => parse_bytes(size, "threshold").map(Threshold::AtMost),
+        None => parse_bytes(s, "threshold").map(Threshold::AtLeast),
+    }
+}
+
+/// Parses a number of bytes with an optional unit suffix; `what` names the
+/// value in error messages
+fn parse_bytes(s: &str, what: &str) -> Result<u64, String> {
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, suffix) = s.split_at(split);
+    let number: u64 = if number.is_empty() {
+        1
+    } else {
+        number
+            .parse()
+            .map_err(|_| format!("invalid {} '{}'", what, s))?
+    };
+
+    let unit: u64 = match suffix.to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KIB" => 1 << 10,
+        "M" | "MIB" => 1 << 20,
+        "G" | "GIB" => 1 << 30,
+        "T" | "TIB" => 1 << 40,
+        "KB" => 1_000,
+        "MB" => 1_000_000,
+        "GB" => 1_000_000_000,
+        "TB" => 1_000_000_000_000,
+        _ => return Err(format!("invalid {} '{}'", what, s)),
+    };
+    number
+        .checked_mul(unit)
+        .ok_or_else(|| format!("{} '{}' is too large", what, s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_size_formats() {
+        assert_eq!(SizeFormat::Decimal.format(1_500_000), "1.50 MB");
+        assert_eq!(SizeFormat::Binary.format(1_572_864), "1.50 MiB");
+        assert_eq!(SizeFormat::Blocks(1024).format(0), "0");
+        assert_eq!(SizeFormat::Blocks(1024).format(1), "1");
+        assert_eq!(SizeFormat::Blocks(1024).format(4096), "4");
+        assert_eq!(SizeFormat::Blocks(1 << 20).format(4097 << 10), "5");
+    }
+
+    #[test]
+    fn test_parse_block_size() {
+        assert_eq!(parse_block_size("4096"), Ok(4096));
+        assert_eq!(parse_block_size("K"), Ok(1024));
+        assert_eq!(parse_block_size("4k"), Ok(4096));
+        assert_eq!(parse_block_size("1MiB"), Ok(1 << 20));
+        assert_eq!(parse_block_size("1MB"), Ok(1_000_000));
+        assert_eq!(parse_block_size("2G"), Ok(2 << 30));
+        assert!(parse_block_size("0").is_err());
+        assert!(parse_block_size("1X").is_err());
+        assert!(parse_block_size("99999999999T").is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("0"), Ok(0));
+        assert_eq!(parse_size("1G"), Ok(1 << 30));
+        assert_eq!(parse_size("500MB"), Ok(500_000_000));
+        assert!(parse_size("1.5G").is_err());
+    }
+}
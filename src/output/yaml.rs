@@ -0,0 +1,69 @@
+//! YAML output formatter for file system scan results.
+//!
+//! Writes the same records as the JSON formatters, as a YAML sequence of
+//! mappings, so playbooks and other tools that read YAML natively can consume
+//! scan results without a converter:
+//!
+//! ```yaml
+//! - path: /data/projects
+//!   size: 4096
+//!   owner: alice
+//!   inodes: 12
+//!   entry_type: Dir
+//! ```
+
+use super::chunked::{CHUNK_SIZE, write_chunked};
+use crate::cli::Args;
+use crate::data::FileEntry;
+use crate::error::Error;
+use anyhow::Result;
+use std::fs::File;
+use std::io::{self, Write};
+
+/// Renders file entries as a YAML sequence.
+///
+/// Writes to the `--output` file if one was given, otherwise to stdout.
+///
+/// # Arguments
+/// * `entries` - A slice of already-filtered and sorted file entries to render
+/// * `args` - Command line arguments that control output formatting
+///
+/// # Returns
+/// * `Result<()>` - Ok if rendering succeeded, Err if there was an issue
+pub fn render(entries: &[FileEntry], args: &Args) -> Result<()> {
+    match &args.output {
+        Some(output_file) => {
+            render_to(&mut io::BufWriter::new(File::create(output_file)?), entries)?;
+            log::info!("YAML output written to: {}", output_file);
+        }
+        None => render_to(&mut io::stdout().lock(), entries)?,
+    }
+    Ok(())
+}
+
+/// Renders file entries as a YAML sequence to an arbitrary writer.
+///
+/// Each chunk is serialized as a sequence of its own; block sequences
+/// written back to back read as a single sequence.
+///
+/// # Arguments
+/// * `out` - Destination for the document
+/// * `entries` - Entries in output order
+pub fn render_to<W: Write + ?Sized>(
+    out: &mut W,
+    entries: &[FileEntry],
+) -> crate::error::Result<()> {
+    write_yaml(out, entries).map_err(Error::output)
+}
+
+fn write_yaml<W: Write + ?Sized>(out: &mut W, entries: &[FileEntry]) -> Result<()> {
+    if entries.is_empty() {
+        writeln!(out, "[]")?;
+        out.flush()?;
+        return Ok(());
+    }
+    write_chunked(out, entries, CHUNK_SIZE, |chunk, buf| {
+        serde_yaml::to_writer(buf, chunk)?;
+        Ok(())
+    })
+}
@@ -0,0 +1,120 @@
+//! Progress reporting for scans.
+//!
+//! Scans report their progress through a [`ProgressObserver`] instead of
+//! drawing a terminal spinner themselves, so GUIs and daemons embedding
+//! [`crate::scan::Scanner`] can surface it their own way. The CLI uses
+//! [`Spinner`]; library scans report nothing unless given an observer.
+//!
+//! Without the `progress` feature, rudu is built without indicatif and
+//! [`Spinner`] draws nothing.
+
+use crate::error::Result;
+#[cfg(feature = "progress")]
+use anyhow::Context;
+#[cfg(not(feature = "progress"))]
+use hidden::ProgressBar;
+#[cfg(feature = "progress")]
+use indicatif::{ProgressBar, ProgressStyle};
+use std::error::Error;
+use std::path::Path;
+#[cfg(feature = "progress")]
+use std::time::Duration;
+
+/// Receives progress events from a running scan.
+///
+/// Every method has an empty default, so observers only implement the
+/// events they care about. Events are delivered on the thread walking the
+/// tree, between directory reads, so implementations should return quickly.
+pub trait ProgressObserver {
+    /// A phase of the scan began: `"Cache-load"`, `"WalkDir"`,
+    /// `"Disk-usage I/O"` or `"Aggregation"`, matching the profile's phases.
+    fn on_phase_start(&self, _phase: &str) {}
+
+    /// The walk reached `path`, a file or directory to be measured.
+    fn on_entry(&self, _path: &Path) {}
+
+    /// The directory `path` was unchanged since the last scan, and its
+    /// subtree was restored from the cache instead of being walked.
+    fn on_cache_hit(&self, _path: &Path) {}
+
+    /// An entry could not be read and is left out of the results; the scan
+    /// carries on. `path` is `None` if the failing path is unknown.
+    fn on_error(&self, _path: Option<&Path>, _error: &dyn Error) {}
+}
+
+/// An observer that ignores every event
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoProgress;
+
+impl ProgressObserver for NoProgress {}
+
+/// A terminal spinner with the elapsed time, advanced by every entry and
+/// cache hit. Dropping it leaves its last frame on screen.
+#[derive(Debug)]
+pub struct Spinner {
+    pb: ProgressBar,
+}
+
+impl Spinner {
+    /// Starts a spinner showing `message`.
+    ///
+    /// The spinner is hidden when status messages are (`--quiet`), and by
+    /// indicatif itself when stderr is not a terminal.
+    pub fn new(message: &str) -> Result<Self> {
+        Ok(Self {
+            pb: progress_spinner(message).map_err(crate::error::Error::output)?,
+        })
+    }
+}
+
+impl ProgressObserver for Spinner {
+    fn on_entry(&self, _path: &Path) {
+        self.pb.tick();
+    }
+
+    fn on_cache_hit(&self, _path: &Path) {
+        self.pb.tick();
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        self.pb.finish();
+    }
+}
+
+/// Starts a progress spinner showing `message` and the elapsed time, for
+/// walks that report progress without a [`ProgressObserver`].
+#[cfg(feature = "progress")]
+pub(crate) fn progress_spinner(message: &str) -> anyhow::Result<ProgressBar> {
+    if !log::log_enabled!(log::Level::Info) {
+        return Ok(ProgressBar::hidden());
+    }
+    let pb = ProgressBar::new_spinner();
+    pb.set_style(
+        ProgressStyle::default_spinner()
+            .tick_strings(&["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"])
+            .template(&format!("{{spinner}} {} [{{elapsed}}]", message))
+            .context("Failed to set progress template")?,
+    );
+    pb.enable_steady_tick(Duration::from_millis(100));
+    Ok(pb)
+}
+
+#[cfg(not(feature = "progress"))]
+pub(crate) fn progress_spinner(_message: &str) -> anyhow::Result<ProgressBar> {
+    Ok(ProgressBar)
+}
+
+/// The subset of indicatif's `ProgressBar` used by scans, drawing nothing
+#[cfg(not(feature = "progress"))]
+mod hidden {
+    #[derive(Debug)]
+    pub struct ProgressBar;
+
+    impl ProgressBar {
+        pub fn tick(&self) {}
+        pub fn finish(&self) {}
+        pub fn finish_and_clear(&self) {}
+    }
+}
@@ -0,0 +1,472 @@
+//! Project and user quotas (`--report project-quota`, `--report user-quota`).
+//!
+//! XFS tags every inode with a project ID, and directories created with the
+//! inherit flag pass theirs on to everything created below them, so a
+//! project usually covers one directory tree. With project quotas enabled
+//! (the `prjquota` mount option), the kernel tracks each project's usage
+//! against its limits, which [`ProjectQuotas`] reads with `quotactl(2)` to
+//! put the size of each scanned directory next to the quota of its project.
+//!
+//! Reading another project's quota needs `CAP_SYS_ADMIN`, so unprivileged
+//! runs still see project IDs but no limits.
+//!
+//! User quotas are read from a file instead ([`UserQuotas`]), as each
+//! filesystem reports them its own way: the output of `repquota`, of
+//! `lfs quota -u` on Lustre, or a CSV file of users and limits.
+
+use crate::utils::{MountInfo, containing_mount};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Mount options that enable project quotas on XFS
+const PROJECT_QUOTA_OPTIONS: &[&str] = &["prjquota", "pquota", "pqnoenforce"];
+
+/// Bytes per quota block in `dqblk` limits
+const QUOTA_BLOCK: u64 = 1024;
+
+/// Usage and limits of one project or user, in bytes; a limit of 0 means none
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Quota {
+    /// Bytes the project uses
+    pub used: u64,
+    /// Soft limit, over which writes are allowed for a grace period
+    pub soft_limit: u64,
+    /// Hard limit, over which writes fail
+    pub hard_limit: u64,
+}
+
+impl Quota {
+    /// The limit that applies: the hard limit, or the soft one without it
+    pub fn limit(&self) -> Option<u64> {
+        [self.hard_limit, self.soft_limit]
+            .into_iter()
+            .find(|&limit| limit > 0)
+    }
+
+    /// Usage as a percentage of [`limit`](Quota::limit)
+    pub fn used_percent(&self) -> Option<f64> {
+        self.limit()
+            .map(|limit| self.used as f64 * 100.0 / limit as f64)
+    }
+}
+
+/// Project quotas of one XFS filesystem, read once per project
+#[derive(Debug)]
+pub struct ProjectQuotas {
+    device: PathBuf,
+    quotas: HashMap<u32, Option<Quota>>,
+    denied: bool,
+}
+
+impl ProjectQuotas {
+    /// The project quotas of the filesystem holding `path`.
+    ///
+    /// Fails unless it is XFS mounted with project quotas.
+    pub fn for_path(path: &Path) -> Result<Self> {
+        let Some(mount) = containing_mount(path) else {
+            anyhow::bail!("Cannot find the filesystem holding {}", path.display());
+        };
+        Self::for_mount(&mount)
+    }
+
+    /// The project quotas of `mount`, which must be XFS with project quotas.
+    pub fn for_mount(mount: &MountInfo) -> Result<Self> {
+        if mount.fs_type != "xfs" {
+            anyhow::bail!(
+                "{} is {}, not XFS; project quotas are only read on XFS",
+                mount.mountpoint.display(),
+                mount.fs_type
+            );
+        }
+        if !PROJECT_QUOTA_OPTIONS
+            .iter()
+            .any(|option| mount.has_option(option))
+        {
+            anyhow::bail!(
+                "{} is not mounted with project quotas (prjquota)",
+                mount.mountpoint.display()
+            );
+        }
+        Ok(Self {
+            device: mount.source.clone(),
+            quotas: HashMap::new(),
+            denied: false,
+        })
+    }
+
+    /// The project of `path` and that project's quota, if it could be read.
+    pub fn lookup(&mut self, path: &Path) -> Option<(u32, Option<Quota>)> {
+        let project = project_id(path).ok()?;
+        if let Some(quota) = self.quotas.get(&project) {
+            return Some((project, *quota));
+        }
+        let quota = match read_project_quota(&self.device, project) {
+            Ok(quota) => Some(quota),
+            Err(e) => {
+                if e.kind() == std::io::ErrorKind::PermissionDenied && !self.denied {
+                    log::warn!("Reading project quotas needs root; showing project IDs only");
+                    self.denied = true;
+                } else if e.kind() != std::io::ErrorKind::PermissionDenied {
+                    log::debug!("No quota for project {}: {}", project, e);
+                }
+                None
+            }
+        };
+        self.quotas.insert(project, quota);
+        Some((project, quota))
+    }
+}
+
+/// Per-user quotas read from a `--user-quotas` file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserQuotas {
+    quotas: HashMap<String, Quota>,
+}
+
+impl UserQuotas {
+    /// Reads the quotas in the file at `path` (see [`UserQuotas::parse`]).
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read user quotas: {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid user quotas: {}", path.display()))
+    }
+
+    /// Parses the output of `repquota`, of one or more `lfs quota -u` runs,
+    /// or CSV lines of `user,limit` or `user,soft,hard`.
+    ///
+    /// `repquota` and `lfs quota` sizes are in KiB unless they carry a unit
+    /// (`-s` and `-h`); CSV sizes are bytes unless suffixed with `K`, `M`,
+    /// `G`, `T` or `P`. A user listed twice, e.g. for several filesystems in
+    /// `repquota -a`, keeps their first quota.
+    pub fn parse(text: &str) -> Result<Self> {
+        let quotas =
+            if text.contains("Disk quotas for usr") || text.contains("Disk quotas for user") {
+                parse_lfs_quota(text)?
+            } else if text
+                .lines()
+                .any(|line| line.starts_with("*** Report for user quotas"))
+            {
+                parse_repquota(text)?
+            } else {
+                parse_quota_csv(text)?
+            };
+        if quotas.is_empty() {
+            anyhow::bail!("no user quotas found");
+        }
+        let mut merged = HashMap::new();
+        for (user, quota) in quotas {
+            merged.entry(user).or_insert(quota);
+        }
+        Ok(Self { quotas: merged })
+    }
+
+    /// The quota of `user`, if listed
+    pub fn get(&self, user: &str) -> Option<&Quota> {
+        self.quotas.get(user)
+    }
+
+    /// Number of users listed
+    pub fn len(&self) -> usize {
+        self.quotas.len()
+    }
+
+    /// Whether no user is listed
+    pub fn is_empty(&self) -> bool {
+        self.quotas.is_empty()
+    }
+}
+
+/// Parses a quota size: a number of `unit` bytes, or with a `K`, `M`, `G`,
+/// `T` or `P` suffix (powers of 1024, optionally followed by `iB` or `B`),
+/// possibly with decimals as printed by `repquota -s` and `lfs quota -h`.
+/// `lfs quota` marks values over their limit with a trailing `*`.
+fn parse_quota_size(field: &str, unit: u64) -> Result<u64> {
+    let field = field.trim_end_matches('*');
+    let split = field
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(field.len());
+    let (number, suffix) = field.split_at(split);
+    let number: f64 = number
+        .parse()
+        .with_context(|| format!("invalid quota size '{}'", field))?;
+    let suffix = suffix.to_ascii_uppercase();
+    let suffix = suffix
+        .strip_suffix("IB")
+        .or_else(|| suffix.strip_suffix('B').filter(|s| !s.is_empty()))
+        .unwrap_or(&suffix);
+    let unit: u64 = match suffix {
+        "" => unit,
+        "B" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        "P" => 1 << 50,
+        _ => anyhow::bail!("invalid quota size '{}'", field),
+    };
+    Ok((number * unit as f64).round() as u64)
+}
+
+/// Quotas from the user tables of `repquota`: after the dashed line under
+/// the headings, each row is `user flags used soft hard ...`.
+fn parse_repquota(text: &str) -> Result<Vec<(String, Quota)>> {
+    let mut quotas = Vec::new();
+    let mut in_table = false;
+    for line in text.lines() {
+        if line.starts_with("---") {
+            in_table = true;
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.is_empty() || line.starts_with("***") {
+            in_table = false;
+            continue;
+        }
+        if !in_table || fields.len() < 5 {
+            continue;
+        }
+        // Users without a name are listed by UID as `#1001`
+        let user = fields[0].trim_start_matches('#');
+        quotas.push((
+            user.to_string(),
+            Quota {
+                used: parse_quota_size(fields[2], QUOTA_BLOCK)?,
+                soft_limit: parse_quota_size(fields[3], QUOTA_BLOCK)?,
+                hard_limit: parse_quota_size(fields[4], QUOTA_BLOCK)?,
+            },
+        ));
+    }
+    Ok(quotas)
+}
+
+/// Quotas from `lfs quota -u USER FS` output, one block per user: a
+/// `Disk quotas for usr USER (uid N):` line, the headings, then the
+/// filesystem with `kbytes quota limit ...`, which moves to a line of its
+/// own when the filesystem name is long.
+fn parse_lfs_quota(text: &str) -> Result<Vec<(String, Quota)>> {
+    let mut quotas = Vec::new();
+    let mut lines = text.lines();
+    while let Some(line) = lines.next() {
+        let Some(rest) = line
+            .trim()
+            .strip_prefix("Disk quotas for usr ")
+            .or_else(|| line.trim().strip_prefix("Disk quotas for user "))
+        else {
+            continue;
+        };
+        let user = rest.split_whitespace().next().unwrap_or_default();
+        let mut fields = Vec::new();
+        for line in lines.by_ref() {
+            if line.trim_start().starts_with("Filesystem") {
+                continue;
+            }
+            fields.extend(line.split_whitespace());
+            if fields.len() >= 4 {
+                break;
+            }
+        }
+        if fields.len() < 4 {
+            anyhow::bail!("no usage line for user {}", user);
+        }
+        quotas.push((
+            user.to_string(),
+            Quota {
+                used: parse_quota_size(fields[1], QUOTA_BLOCK)?,
+                soft_limit: parse_quota_size(fields[2], QUOTA_BLOCK)?,
+                hard_limit: parse_quota_size(fields[3], QUOTA_BLOCK)?,
+            },
+        ));
+    }
+    Ok(quotas)
+}
+
+/// Quotas from CSV lines of `user,limit` (a hard limit) or
+/// `user,soft,hard`; blank lines, `#` comments and a header line are skipped.
+fn parse_quota_csv(text: &str) -> Result<Vec<(String, Quota)>> {
+    let mut quotas = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let size = |field: &str| parse_quota_size(field, 1);
+        let quota = match fields[..] {
+            [_, hard] => size(hard).map(|hard_limit| Quota {
+                hard_limit,
+                ..Quota::default()
+            }),
+            [_, soft, hard] => size(soft).and_then(|soft_limit| {
+                Ok(Quota {
+                    soft_limit,
+                    hard_limit: size(hard)?,
+                    used: 0,
+                })
+            }),
+            _ => Err(anyhow::anyhow!("expected user,limit or user,soft,hard")),
+        };
+        match quota {
+            Ok(quota) => quotas.push((fields[0].to_string(), quota)),
+            // A header such as `user,quota`
+            Err(_) if quotas.is_empty() && number == 0 => {}
+            Err(e) => return Err(e.context(format!("line {}", number + 1))),
+        }
+    }
+    Ok(quotas)
+}
+
+/// Returns the XFS project ID of `path`.
+#[cfg(target_os = "linux")]
+pub fn project_id(path: &Path) -> std::io::Result<u32> {
+    use std::os::fd::AsRawFd;
+
+    /// `struct fsxattr` from `<linux/fs.h>`
+    #[repr(C)]
+    #[derive(Default)]
+    struct FsXattr {
+        xflags: u32,
+        extsize: u32,
+        nextents: u32,
+        projid: u32,
+        cowextsize: u32,
+        pad: [u8; 8],
+    }
+    /// `FS_IOC_FSGETXATTR`: `_IOR('X', 31, struct fsxattr)`
+    const FS_IOC_FSGETXATTR: libc::c_ulong = 0x801c_581f;
+
+    let file = std::fs::File::open(path)?;
+    let mut attr = FsXattr::default();
+    let result = unsafe { libc::ioctl(file.as_raw_fd(), FS_IOC_FSGETXATTR as _, &mut attr) };
+    if result != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(attr.projid)
+}
+
+/// Fallback for platforms without XFS project IDs.
+#[cfg(not(target_os = "linux"))]
+pub fn project_id(_path: &Path) -> std::io::Result<u32> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+/// Reads the quota of `project` on the filesystem on `device`.
+#[cfg(target_os = "linux")]
+fn read_project_quota(device: &Path, project: u32) -> std::io::Result<Quota> {
+    use std::os::unix::ffi::OsStrExt;
+
+    /// `PRJQUOTA` from `<linux/quota.h>`
+    const PRJQUOTA: libc::c_int = 2;
+
+    let device = std::ffi::CString::new(device.as_os_str().as_bytes())?;
+    let mut dqblk = std::mem::MaybeUninit::<libc::dqblk>::zeroed();
+    let result = unsafe {
+        libc::quotactl(
+            libc::QCMD(libc::Q_GETQUOTA, PRJQUOTA),
+            device.as_ptr(),
+            project as libc::c_int,
+            dqblk.as_mut_ptr().cast(),
+        )
+    };
+    if result != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let dqblk = unsafe { dqblk.assume_init() };
+    Ok(Quota {
+        used: dqblk.dqb_curspace,
+        soft_limit: dqblk.dqb_bsoftlimit * QUOTA_BLOCK,
+        hard_limit: dqblk.dqb_bhardlimit * QUOTA_BLOCK,
+    })
+}
+
+/// Fallback for platforms without `quotactl(2)` project quotas.
+#[cfg(not(target_os = "linux"))]
+fn read_project_quota(_device: &Path, _project: u32) -> std::io::Result<Quota> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mount(fs_type: &str, options: &str) -> MountInfo {
+        MountInfo {
+            source: PathBuf::from("/dev/sdb1"),
+            mountpoint: PathBuf::from("/projects"),
+            fs_type: fs_type.to_string(),
+            options: options.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_limits_and_mounts() {
+        let quota = Quota {
+            used: 900,
+            soft_limit: 800,
+            hard_limit: 1000,
+        };
+        assert_eq!(quota.limit(), Some(1000));
+        assert_eq!(quota.used_percent(), Some(90.0));
+        let soft_only = Quota {
+            hard_limit: 0,
+            ..quota
+        };
+        assert_eq!(soft_only.limit(), Some(800));
+        assert_eq!(Quota::default().used_percent(), None);
+
+        assert!(ProjectQuotas::for_mount(&mount("xfs", "rw,prjquota")).is_ok());
+        assert!(ProjectQuotas::for_mount(&mount("xfs", "rw,usrquota")).is_err());
+        assert!(ProjectQuotas::for_mount(&mount("ext4", "rw,prjquota")).is_err());
+    }
+
+    #[test]
+    fn test_user_quota_sources() {
+        let repquota = "\
+*** Report for user quotas on device /dev/sdb1
+Block grace time: 7days; Inode grace time: 7days
+                        Block limits                File limits
+User            used    soft    hard  grace    used  soft  hard  grace
+----------------------------------------------------------------------
+root      --      20       0       0              2     0     0
+alice     +-  600000  400000  500000  6days      12     0     0
+#1001     --    1.5M      2G      3G              1     0     0
+
+*** Report for user quotas on device /dev/sdc1
+----------------------------------------------------------------------
+alice     --       4       0       0              1     0     0
+";
+        let quotas = UserQuotas::parse(repquota).unwrap();
+        assert_eq!(quotas.len(), 3);
+        assert_eq!(
+            quotas.get("alice"),
+            Some(&Quota {
+                used: 600000 * 1024,
+                soft_limit: 400000 * 1024,
+                hard_limit: 500000 * 1024,
+            })
+        );
+        assert_eq!(quotas.get("1001").unwrap().used, 1536 * 1024);
+        assert_eq!(quotas.get("1001").unwrap().hard_limit, 3 << 30);
+
+        let lfs = "\
+Disk quotas for usr bob (uid 1002):
+     Filesystem  kbytes   quota   limit   grace   files   quota   limit   grace
+        /lustre  123456*  100000  120000  6d         12       0       0       -
+Disk quotas for usr carol (uid 1003):
+     Filesystem  kbytes   quota   limit   grace   files   quota   limit   grace
+/lustre/very/long/mount/point
+                   1.5G       0      2G       -      3       0       0       -
+";
+        let quotas = UserQuotas::parse(lfs).unwrap();
+        assert_eq!(quotas.get("bob").unwrap().hard_limit, 120000 * 1024);
+        assert_eq!(quotas.get("bob").unwrap().used, 123456 * 1024);
+        assert_eq!(quotas.get("carol").unwrap().limit(), Some(2 << 30));
+
+        let csv = "user,quota\n# scratch\nalice,1TiB\nbob, 500G, 600GB\n";
+        let quotas = UserQuotas::parse(csv).unwrap();
+        assert_eq!(quotas.get("alice").unwrap().limit(), Some(1 << 40));
+        assert_eq!(quotas.get("bob").unwrap().soft_limit, 500 << 30);
+        assert!(UserQuotas::parse("alice,1T\nbob,lots\n").is_err());
+        assert!(UserQuotas::parse("").is_err());
+    }
+}
@@ -0,0 +1,2429 @@
+//! Usage reports aggregated over a whole scan.
+//!
+//! `--report` replaces the per-entry listing with a summary table, for
+//! questions such as "who is using the space?" that would otherwise need
+//! the CSV output post-processed. Reports are built from every entry the
+//! scan walked, independent of `--show-files`, and rendered as an aligned
+//! table on the terminal or as CSV with `--format csv`/`tsv` or `--output`.
+//!
+//! Available reports:
+//! - [`ReportKind::ByOwner`] - bytes, files and inodes per owner
+//! - [`ReportKind::ByExt`] - bytes and files per extension, with the kind of
+//!   data it holds (netCDF, archives, logs, core dumps, ...)
+//! - [`ReportKind::Age`] - bytes per directory (up to `--depth`, default 1)
+//!   split into buckets by the age of each file's modification time
+//! - [`ReportKind::Cold`] - the outermost directories nothing below which was
+//!   read or modified since `--cold-after`, candidates for archiving to tape
+//! - [`ReportKind::ProjectQuota`] - the size of each directory (up to
+//!   `--depth`, default 1) against the quota of its XFS project, flagging
+//!   projects near their limit
+//! - [`ReportKind::UserQuota`] - bytes per owner against each user's quota
+//!   from `--user-quotas`, most used share first
+//! - [`ReportKind::Hsm`] - resident and migrated bytes per directory (up to
+//!   `--depth`, default 1) on HSM-managed filesystems, followed by totals
+//!   (see [`HsmTotals`])
+//! - [`ReportKind::Security`] - world-writable directories, setuid and setgid
+//!   files and entries whose owner has no passwd entry, most severe first
+//! - [`ReportKind::Orphans`] - bytes, files and directories per user and
+//!   group ID without a passwd or group entry, such as those of departed users
+//! - `ReportKind::LustrePools` - bytes, files, OSTs and poorly striped bytes
+//!   per Lustre OST pool (`lustre` feature)
+//! - `ReportKind::LustreFiles` - large Lustre files spread over too few
+//!   stripes, largest first (`lustre` feature)
+//!
+//! `--sparse-report` lists the largest sparse files with the apparent and
+//! allocated totals of all files through the same tables (see [`sparse`]).
+//!
+//! `--budgets` prints the directories over their budget through the same
+//! tables (see [`budgets`]).
+//!
+//! `--count-only` prints its recursive file and directory counts through the
+//! same tables (see [`counts`]), `rudu diff` its per-directory changes
+//! between snapshots (see [`diff`]), and `rudu report trend` the growth
+//! recorded in the history (`trend`, `history` feature).
+
+use crate::budget::Violation;
+use crate::cli::{Args, OutputFormat, ReportKind, SortKey};
+use crate::data::{DirCounts, EntryType, FileEntry};
+use crate::error::Error;
+#[cfg(feature = "history")]
+use crate::history::Trend;
+#[cfg(feature = "lustre")]
+use crate::lustre::StripeLayout;
+use crate::output::csv::Dialect;
+use crate::output::units::SizeFormat;
+use crate::quota::{ProjectQuotas, Quota, UserQuotas};
+use crate::snapshot::DirChange;
+use crate::utils::{group_name, path_depth, user_name};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Owner shown for entries whose owner could not be resolved
+const UNKNOWN_OWNER: &str = "unknown";
+
+/// Seconds in a day
+const DAY: u64 = 24 * 60 * 60;
+
+/// Default window of `--report cold`: directories untouched for this long
+const COLD_AFTER: u64 = 180 * DAY;
+
+/// Holes smaller than this do not make a file sparse in `--sparse-report`,
+/// so tiny files stored inline in their inode are not listed
+const MIN_SPARSE_HOLE: u64 = 1024 * 1024;
+
+/// Most bytes an offline (HSM-migrated) file keeps on disk: none, or the
+/// single block Lustre reports for released files
+const MAX_OFFLINE_ALLOCATED: u64 = 512;
+
+/// Files at most this long never count as offline in `--report hsm`, so
+/// small files stored inline in their inode are not mistaken for migrated
+const MIN_OFFLINE_SIZE: u64 = 4096;
+
+/// Share of its quota a project or user must use to be flagged by
+/// `--report project-quota` and `--report user-quota`
+const NEAR_QUOTA_PERCENT: f64 = 90.0;
+
+/// `st_mode` bits checked by `--report security`, spelled out as libc's
+/// constants have a different type on each platform
+const MODE_SETUID: u32 = 0o4000;
+const MODE_SETGID: u32 = 0o2000;
+const MODE_STICKY: u32 = 0o1000;
+const MODE_OTHER_WRITE: u32 = 0o0002;
+
+/// Age buckets of `--report age` as `(column title, CSV name, upper bound)`;
+/// a file falls in the first bucket whose bound its age is below.
+const AGE_BUCKETS: &[(&str, &str, u64)] = &[
+    ("<30D", "bytes_under_30d", 30 * DAY),
+    ("30-90D", "bytes_30d_90d", 90 * DAY),
+    ("90D-1Y", "bytes_90d_1y", 365 * DAY),
+    ("1-3Y", "bytes_1y_3y", 3 * 365 * DAY),
+    (">3Y", "bytes_over_3y", u64::MAX),
+];
+
+/// File types recognised by `--report by-ext`, with their (lowercase) extensions.
+///
+/// Compressed tarballs are matched on the full `tar.*` suffix before the
+/// plain compression extensions.
+const FILE_TYPES: &[(&str, &[&str])] = &[
+    ("netCDF", &["nc", "nc4", "cdf", "netcdf"]),
+    ("HDF5", &["h5", "hdf5", "he5", "hdf"]),
+    ("GRIB", &["grb", "grib", "grb2", "grib2"]),
+    ("FITS", &["fits", "fit", "fts"]),
+    ("Zarr", &["zarr"]),
+    (
+        "archive",
+        &[
+            "tar", "tar.gz", "tgz", "tar.bz2", "tbz2", "tar.xz", "txz", "tar.zst", "zip", "7z",
+            "rar",
+        ],
+    ),
+    ("compressed", &["gz", "bz2", "xz", "zst", "lz4", "z"]),
+    ("log", &["log", "out", "err"]),
+    ("tabular", &["csv", "tsv", "parquet", "feather", "arrow"]),
+    (
+        "array",
+        &["npy", "npz", "mat", "pkl", "pickle", "pt", "ckpt"],
+    ),
+    ("text", &["txt", "md", "json", "yaml", "yml", "xml"]),
+    (
+        "image",
+        &["png", "jpg", "jpeg", "tif", "tiff", "gif", "svg"],
+    ),
+    ("video", &["mp4", "mkv", "avi", "mov"]),
+    (
+        "source",
+        &[
+            "c", "h", "cc", "cpp", "hpp", "f", "f90", "f77", "rs", "py", "sh", "r", "jl", "m",
+        ],
+    ),
+    ("object", &["o", "a", "so", "mod", "pyc"]),
+    ("container", &["sif", "simg", "img", "iso"]),
+];
+
+/// A single value in a report row.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    /// Free text, left-aligned
+    Text(String),
+    /// A size, shown in the selected unit on the terminal and in bytes in CSV
+    Bytes(u64),
+    /// A plain count
+    Count(u64),
+    /// A share of the total, in percent
+    Percent(f64),
+    /// A change in size, signed; shown with `+` or `-` in the selected unit
+    /// on the terminal and in bytes in CSV
+    Delta(i64),
+}
+
+/// A column of a report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReportColumn {
+    /// Heading of the terminal table
+    pub title: &'static str,
+    /// CSV header name
+    pub csv_name: &'static str,
+}
+
+/// An aggregated report: ranked rows with one cell per column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub columns: Vec<ReportColumn>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+impl Report {
+    /// The rows as JSON objects keyed by the CSV header names, with sizes in
+    /// bytes and counts and shares as numbers.
+    pub fn json_rows(&self) -> Vec<serde_json::Map<String, serde_json::Value>> {
+        self.rows
+            .iter()
+            .map(|row| {
+                self.columns
+                    .iter()
+                    .zip(row)
+                    .map(|(column, cell)| {
+                        let value = match cell {
+                            Cell::Text(text) => serde_json::Value::from(text.as_str()),
+                            Cell::Bytes(n) | Cell::Count(n) => serde_json::Value::from(*n),
+                            Cell::Percent(share) => serde_json::Value::from(*share),
+                            Cell::Delta(n) => serde_json::Value::from(*n),
+                        };
+                        (column.csv_name.to_string(), value)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Builds the report of the given kind from raw scan entries below `root`.
+pub fn build(kind: ReportKind, entries: &[FileEntry], root: &Path, args: &Args) -> Report {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    match kind {
+        ReportKind::ByOwner => by_owner(entries),
+        ReportKind::ByExt => by_ext(entries),
+        ReportKind::Age => age(
+            entries,
+            root,
+            args.depth.unwrap_or(1),
+            now,
+            &args.sort,
+            args.reverse,
+        ),
+        ReportKind::ProjectQuota => {
+            let mut quotas = ProjectQuotas::for_path(root).ok();
+            project_quota(entries, root, args.depth.unwrap_or(1), |path| {
+                quotas.as_mut()?.lookup(path)
+            })
+        }
+        #[cfg(feature = "lustre")]
+        ReportKind::LustrePools => lustre_pools(&crate::lustre::layouts(entries)),
+        #[cfg(feature = "lustre")]
+        ReportKind::LustreFiles => lustre_files(&crate::lustre::layouts(entries)),
+        ReportKind::UserQuota => {
+            let quotas = args
+                .user_quotas
+                .as_deref()
+                .and_then(|path| UserQuotas::from_file(path).ok())
+                .unwrap_or_default();
+            user_quota(entries, &quotas)
+        }
+        ReportKind::Hsm => hsm(entries, root, args.depth.unwrap_or(1)),
+        ReportKind::Security => security(entries, user_name),
+        ReportKind::Orphans => orphans(entries, user_name, group_name),
+        ReportKind::Cold => cold(
+            entries,
+            root,
+            args.cold_after
+                .unwrap_or_else(|| now.saturating_sub(COLD_AFTER)),
+        ),
+    }
+}
+
+/// Per-owner totals, ranked by bytes.
+///
+/// Bytes and files count regular files only, as directory sizes already
+/// include their contents; inodes count every file and directory owned.
+/// Entries without an owner (e.g. when it could not be looked up) are
+/// grouped under `unknown`.
+pub fn by_owner(entries: &[FileEntry]) -> Report {
+    #[derive(Default)]
+    struct Usage {
+        bytes: u64,
+        files: u64,
+        inodes: u64,
+    }
+
+    let mut owners: HashMap<&str, Usage> = HashMap::new();
+    for entry in entries {
+        let usage = owners
+            .entry(entry.owner.as_deref().unwrap_or(UNKNOWN_OWNER))
+            .or_default();
+        usage.inodes += 1;
+        if !entry.entry_type.is_container() {
+            usage.bytes += entry.size;
+            usage.files += 1;
+        }
+    }
+
+    let mut owners: Vec<(&str, Usage)> = owners.into_iter().collect();
+    owners.sort_by(|(a_name, a), (b_name, b)| {
+        b.bytes
+            .cmp(&a.bytes)
+            .then(b.inodes.cmp(&a.inodes))
+            .then(a_name.cmp(b_name))
+    });
+    let total: u64 = owners.iter().map(|(_, usage)| usage.bytes).sum();
+
+    Report {
+        columns: vec![
+            ReportColumn {
+                title: "OWNER",
+                csv_name: "owner",
+            },
+            ReportColumn {
+                title: "SIZE",
+                csv_name: "size_bytes",
+            },
+            ReportColumn {
+                title: "FILES",
+                csv_name: "files",
+            },
+            ReportColumn {
+                title: "INODES",
+                csv_name: "inodes",
+            },
+            ReportColumn {
+                title: "SHARE",
+                csv_name: "share_percent",
+            },
+        ],
+        rows: owners
+            .into_iter()
+            .map(|(owner, usage)| {
+                vec![
+                    Cell::Text(owner.to_string()),
+                    Cell::Bytes(usage.bytes),
+                    Cell::Count(usage.files),
+                    Cell::Count(usage.inodes),
+                    Cell::Percent(percent(usage.bytes, total)),
+                ]
+            })
+            .collect(),
+    }
+}
+
+/// Per-extension totals of files, ranked by bytes.
+///
+/// Extensions are compared case-insensitively, compressed tarballs keep
+/// their full `tar.gz`-style suffix, and files without an extension are
+/// listed as `-`. Core dumps (`core` or `core.<pid>`) are reported as their
+/// own type, whatever their name.
+pub fn by_ext(entries: &[FileEntry]) -> Report {
+    let mut groups: HashMap<(String, &str), (u64, u64)> = HashMap::new();
+    for entry in entries.iter().filter(|e| e.entry_type == EntryType::File) {
+        let (bytes, files) = groups.entry(classify(&entry.path)).or_default();
+        *bytes += entry.size;
+        *files += 1;
+    }
+
+    let mut groups: Vec<_> = groups.into_iter().collect();
+    groups.sort_by(|(a_key, a), (b_key, b)| b.cmp(a).then(a_key.cmp(b_key)));
+    let total: u64 = groups.iter().map(|(_, (bytes, _))| bytes).sum();
+
+    Report {
+        columns: vec![
+            ReportColumn {
+                title: "EXTENSION",
+                csv_name: "extension",
+            },
+            ReportColumn {
+                title: "TYPE",
+                csv_name: "type",
+            },
+            ReportColumn {
+                title: "SIZE",
+                csv_name: "size_bytes",
+            },
+            ReportColumn {
+                title: "FILES",
+                csv_name: "files",
+            },
+            ReportColumn {
+                title: "SHARE",
+                csv_name: "share_percent",
+            },
+        ],
+        rows: groups
+            .into_iter()
+            .map(|((ext, kind), (bytes, files))| {
+                vec![
+                    Cell::Text(if ext.is_empty() { "-".to_string() } else { ext }),
+                    Cell::Text(kind.to_string()),
+                    Cell::Bytes(bytes),
+                    Cell::Count(files),
+                    Cell::Percent(percent(bytes, total)),
+                ]
+            })
+            .collect(),
+    }
+}
+
+/// The lowercase extension of a file and the type of data it holds
+fn classify(path: &Path) -> (String, &'static str) {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if name == "core"
+        || name
+            .strip_prefix("core.")
+            .is_some_and(|pid| !pid.is_empty() && pid.bytes().all(|b| b.is_ascii_digit()))
+    {
+        return (String::new(), "core dump");
+    }
+
+    // Leading dots mark hidden files, not extensions
+    let stem = name.trim_start_matches('.');
+    let ext = match stem.rsplit_once('.') {
+        Some((rest, last)) if rest.ends_with(".tar") || rest == "tar" => format!("tar.{}", last),
+        Some((_, last)) => last.to_string(),
+        None => String::new(),
+    };
+    let kind = FILE_TYPES
+        .iter()
+        .find(|(_, exts)| exts.contains(&ext.as_str()))
+        .map(|(kind, _)| *kind)
+        .unwrap_or("other");
+    (ext, kind)
+}
+
+/// Bytes of files per directory, bucketed by how long ago they were modified.
+///
+/// There is one row per directory at most `depth` levels below `root`, each
+/// covering all files beneath it, ordered by `sort` keys (path, or largest
+/// first for [`SortKey::Size`]) with path breaking remaining ties, and
+/// reversed with `reverse`. Ages are measured from `now` (seconds since the
+/// epoch); files modified in the future count as new, and files without a
+/// known mtime are left out.
+pub fn age(
+    entries: &[FileEntry],
+    root: &Path,
+    depth: usize,
+    now: u64,
+    sort: &[SortKey],
+    reverse: bool,
+) -> Report {
+    let mut dirs: HashMap<&Path, Vec<u64>> = entries
+        .iter()
+        .filter(|e| e.entry_type == EntryType::Dir && path_depth(root, &e.path) <= depth)
+        .map(|e| (e.path.as_path(), vec![0; AGE_BUCKETS.len()]))
+        .collect();
+
+    for entry in entries.iter().filter(|e| !e.entry_type.is_container()) {
+        let Some(mtime) = entry.mtime else {
+            continue;
+        };
+        let age = now.saturating_sub(mtime);
+        let bucket = AGE_BUCKETS
+            .iter()
+            .position(|(_, _, bound)| age < *bound)
+            .unwrap_or(AGE_BUCKETS.len() - 1);
+        for ancestor in entry
+            .path
+            .ancestors()
+            .skip(1)
+            .take_while(|a| a.starts_with(root))
+        {
+            if let Some(buckets) = dirs.get_mut(ancestor) {
+                buckets[bucket] += entry.size;
+            }
+        }
+    }
+
+    let mut dirs: Vec<(&Path, Vec<u64>, u64)> = dirs
+        .into_iter()
+        .map(|(path, buckets)| {
+            let total = buckets.iter().sum();
+            (path, buckets, total)
+        })
+        .collect();
+    dirs.sort_by(|a, b| {
+        let order = sort
+            .iter()
+            .map(|key| match key {
+                SortKey::Name => a.0.cmp(b.0),
+                SortKey::Size => b.2.cmp(&a.2),
+                // Not shown in the report
+                SortKey::Mtime | SortKey::Owner | SortKey::Inodes => std::cmp::Ordering::Equal,
+            })
+            .find(|order| order.is_ne())
+            .unwrap_or_else(|| a.0.cmp(b.0));
+        if reverse { order.reverse() } else { order }
+    });
+
+    let mut columns = vec![ReportColumn {
+        title: "DIRECTORY",
+        csv_name: "path",
+    }];
+    columns.extend(
+        AGE_BUCKETS
+            .iter()
+            .map(|(title, csv_name, _)| ReportColumn { title, csv_name }),
+    );
+    columns.push(ReportColumn {
+        title: "TOTAL",
+        csv_name: "size_bytes",
+    });
+
+    Report {
+        columns,
+        rows: dirs
+            .into_iter()
+            .map(|(path, buckets, total)| {
+                let mut row = vec![Cell::Text(path.display().to_string())];
+                row.extend(buckets.into_iter().map(Cell::Bytes));
+                row.push(Cell::Bytes(total));
+                row
+            })
+            .collect(),
+    }
+}
+
+/// Directories below `root` whose whole contents were last touched before
+/// `cutoff` (seconds since the epoch), largest first.
+///
+/// A file counts as touched when it was read or modified, so the later of
+/// its atime (recorded with extended metadata) and mtime is used; a
+/// directory also counts its own mtime, which changes when entries are
+/// added or removed. Directory atimes are ignored, as walking the tree
+/// updates them. Only the outermost cold directories are listed, without
+/// the cold directories inside them, and empty ones are left out.
+pub fn cold(entries: &[FileEntry], root: &Path, cutoff: u64) -> Report {
+    // Last touch and file count of every directory
+    let mut dirs: HashMap<&Path, (u64, u64)> = entries
+        .iter()
+        .filter(|e| e.entry_type.is_container())
+        .map(|e| (e.path.as_path(), (0, 0)))
+        .collect();
+
+    for entry in entries {
+        let is_dir = entry.entry_type.is_container();
+        let mut touched = entry.mtime.unwrap_or(0);
+        if !is_dir {
+            touched = touched.max(entry.stat.as_ref().map_or(0, |stat| stat.atime));
+        }
+        // A directory's own mtime counts for it and every directory above
+        for ancestor in entry
+            .path
+            .ancestors()
+            .skip(usize::from(!is_dir))
+            .take_while(|a| a.starts_with(root))
+        {
+            if let Some((last_touched, files)) = dirs.get_mut(ancestor) {
+                *last_touched = (*last_touched).max(touched);
+                *files += u64::from(!is_dir);
+            }
+        }
+    }
+
+    // Directories were touched at least as recently as anything below them,
+    // so everything inside a cold directory is cold too
+    let is_cold = |path: &Path| {
+        dirs.get(path)
+            .is_some_and(|&(last_touched, _)| last_touched < cutoff)
+    };
+    let mut rows: Vec<(&FileEntry, u64, u64)> = entries
+        .iter()
+        .filter(|e| e.entry_type.is_container() && e.size > 0 && is_cold(&e.path))
+        .filter(|e| e.path == root || !e.path.parent().is_some_and(is_cold))
+        .filter_map(|e| {
+            let &(last_touched, files) = dirs.get(e.path.as_path())?;
+            Some((e, files, last_touched))
+        })
+        .collect();
+    rows.sort_by(|a, b| {
+        b.0.size
+            .cmp(&a.0.size)
+            .then_with(|| a.0.path.cmp(&b.0.path))
+    });
+
+    Report {
+        columns: vec![
+            ReportColumn {
+                title: "SIZE",
+                csv_name: "size_bytes",
+            },
+            ReportColumn {
+                title: "FILES",
+                csv_name: "files",
+            },
+            ReportColumn {
+                title: "LAST TOUCHED",
+                csv_name: "last_touched",
+            },
+            ReportColumn {
+                title: "DIRECTORY",
+                csv_name: "path",
+            },
+        ],
+        rows: rows
+            .into_iter()
+            .map(|(entry, files, last_touched)| {
+                let date = chrono::DateTime::from_timestamp(last_touched as i64, 0)
+                    .map_or("-".to_string(), |at| at.format("%Y-%m-%d").to_string());
+                vec![
+                    Cell::Bytes(entry.size),
+                    Cell::Count(files),
+                    Cell::Text(date),
+                    Cell::Text(entry.path.display().to_string()),
+                ]
+            })
+            .collect(),
+    }
+}
+
+/// Directories at most `depth` levels below `root`, in path order, with
+/// their size, XFS project and that project's quota from `lookup`.
+///
+/// `SHARE` is the directory's size as a percentage of the project's limit
+/// and `USED` the project's own usage; projects at [`NEAR_QUOTA_PERCENT`]
+/// or more of their limit are flagged in `STATUS`. Directories whose
+/// project cannot be read are left out.
+pub fn project_quota(
+    entries: &[FileEntry],
+    root: &Path,
+    depth: usize,
+    mut lookup: impl FnMut(&Path) -> Option<(u32, Option<Quota>)>,
+) -> Report {
+    let mut dirs: Vec<&FileEntry> = entries
+        .iter()
+        .filter(|e| e.entry_type == EntryType::Dir && path_depth(root, &e.path) <= depth)
+        .collect();
+    dirs.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let rows = dirs
+        .into_iter()
+        .filter_map(|entry| {
+            let (project, quota) = lookup(&entry.path)?;
+            let limit = quota.and_then(|q| q.limit());
+            let used = quota.and_then(|q| q.used_percent());
+            let status = match used {
+                Some(used) if used >= 100.0 => "over limit",
+                Some(used) if used >= NEAR_QUOTA_PERCENT => "near limit",
+                _ => "",
+            };
+            let none = || Cell::Text("-".to_string());
+            Some(vec![
+                Cell::Bytes(entry.size),
+                Cell::Count(u64::from(project)),
+                limit.map_or_else(none, Cell::Bytes),
+                limit.map_or_else(none, |limit| Cell::Percent(percent(entry.size, limit))),
+                used.map_or_else(none, Cell::Percent),
+                Cell::Text(status.to_string()),
+                Cell::Text(entry.path.display().to_string()),
+            ])
+        })
+        .collect();
+
+    Report {
+        columns: vec![
+            ReportColumn {
+                title: "SIZE",
+                csv_name: "size_bytes",
+            },
+            ReportColumn {
+                title: "PROJECT",
+                csv_name: "project_id",
+            },
+            ReportColumn {
+                title: "QUOTA",
+                csv_name: "quota_bytes",
+            },
+            ReportColumn {
+                title: "SHARE",
+                csv_name: "quota_share",
+            },
+            ReportColumn {
+                title: "USED",
+                csv_name: "project_used",
+            },
+            ReportColumn {
+                title: "STATUS",
+                csv_name: "status",
+            },
+            ReportColumn {
+                title: "DIRECTORY",
+                csv_name: "path",
+            },
+        ],
+        rows,
+    }
+}
+
+/// Bytes per owner against each user's quota, most used share first, then
+/// owners without a limit by bytes.
+///
+/// `USED` counts the regular files the scan found, so it is the usage below
+/// the root rather than on the whole filesystem. `HEADROOM` is what is left
+/// of the limit, negative once it is exceeded; owners at
+/// [`NEAR_QUOTA_PERCENT`] or more of their limit are flagged in `STATUS`,
+/// which also tells owners without a limit from those missing from `quotas`.
+pub fn user_quota(entries: &[FileEntry], quotas: &UserQuotas) -> Report {
+    let mut owners: HashMap<&str, u64> = HashMap::new();
+    for entry in entries.iter().filter(|e| !e.entry_type.is_container()) {
+        *owners
+            .entry(entry.owner.as_deref().unwrap_or(UNKNOWN_OWNER))
+            .or_default() += entry.size;
+    }
+
+    let mut owners: Vec<(&str, u64, Option<Quota>, Option<f64>)> = owners
+        .into_iter()
+        .map(|(owner, used)| {
+            let quota = quotas.get(owner).copied();
+            let limit = quota.and_then(|q| q.limit());
+            (owner, used, quota, limit.map(|limit| percent(used, limit)))
+        })
+        .collect();
+    owners.sort_by(|a, b| {
+        b.3.unwrap_or(-1.0)
+            .total_cmp(&a.3.unwrap_or(-1.0))
+            .then(b.1.cmp(&a.1))
+            .then(a.0.cmp(b.0))
+    });
+
+    Report {
+        columns: vec![
+            ReportColumn {
+                title: "OWNER",
+                csv_name: "owner",
+            },
+            ReportColumn {
+                title: "USED",
+                csv_name: "used_bytes",
+            },
+            ReportColumn {
+                title: "QUOTA",
+                csv_name: "quota_bytes",
+            },
+            ReportColumn {
+                title: "USE",
+                csv_name: "use_percent",
+            },
+            ReportColumn {
+                title: "HEADROOM",
+                csv_name: "headroom_bytes",
+            },
+            ReportColumn {
+                title: "STATUS",
+                csv_name: "status",
+            },
+        ],
+        rows: owners
+            .into_iter()
+            .map(|(owner, used, quota, share)| {
+                let limit = quota.and_then(|q| q.limit());
+                let status = match (quota, share) {
+                    (None, _) => "no quota",
+                    (Some(_), None) => "no limit",
+                    (Some(_), Some(share)) if share > 100.0 => "over limit",
+                    (Some(_), Some(share)) if share >= NEAR_QUOTA_PERCENT => "near limit",
+                    (Some(_), Some(_)) => "under limit",
+                };
+                let none = || Cell::Text("-".to_string());
+                vec![
+                    Cell::Text(owner.to_string()),
+                    Cell::Bytes(used),
+                    limit.map_or_else(none, Cell::Bytes),
+                    share.map_or_else(none, Cell::Percent),
+                    limit.map_or_else(none, |limit| Cell::Delta(limit as i64 - used as i64)),
+                    Cell::Text(status.to_string()),
+                ]
+            })
+            .collect(),
+    }
+}
+
+/// Directories over their `--budgets` budget, in path order, with their
+/// size and inode count next to the limits of the budget line that applies.
+pub fn budgets(violations: &[Violation]) -> Report {
+    let none = || Cell::Text("-".to_string());
+    Report {
+        columns: vec![
+            ReportColumn {
+                title: "SIZE",
+                csv_name: "size_bytes",
+            },
+            ReportColumn {
+                title: "SIZE LIMIT",
+                csv_name: "max_size_bytes",
+            },
+            ReportColumn {
+                title: "INODES",
+                csv_name: "inodes",
+            },
+            ReportColumn {
+                title: "INODE LIMIT",
+                csv_name: "max_inodes",
+            },
+            ReportColumn {
+                title: "STATUS",
+                csv_name: "status",
+            },
+            ReportColumn {
+                title: "BUDGET",
+                csv_name: "pattern",
+            },
+            ReportColumn {
+                title: "DIRECTORY",
+                csv_name: "path",
+            },
+        ],
+        rows: violations
+            .iter()
+            .map(|v| {
+                vec![
+                    Cell::Bytes(v.size),
+                    v.max_size.map_or_else(none, Cell::Bytes),
+                    Cell::Count(v.inodes),
+                    v.max_inodes.map_or_else(none, Cell::Count),
+                    Cell::Text(v.status().to_string()),
+                    Cell::Text(v.pattern.clone()),
+                    Cell::Text(v.path.display().to_string()),
+                ]
+            })
+            .collect(),
+    }
+}
+
+/// Pool shown for files allocated outside any OST pool
+#[cfg(feature = "lustre")]
+const NO_POOL: &str = "-";
+
+/// Totals per OST pool of Lustre files and their layouts, largest first.
+///
+/// `OSTS` counts the distinct OSTs the pool's files are on, `STRIPES` is
+/// their mean stripe count, and `POORLY STRIPED` the bytes in files with
+/// more than [`MAX_BYTES_PER_STRIPE`](crate::lustre::MAX_BYTES_PER_STRIPE)
+/// per stripe.
+#[cfg(feature = "lustre")]
+pub fn lustre_pools(files: &[(&FileEntry, StripeLayout)]) -> Report {
+    #[derive(Default)]
+    struct Pool {
+        files: u64,
+        bytes: u64,
+        stripes: u64,
+        osts: std::collections::HashSet<u32>,
+        poorly_striped: u64,
+    }
+
+    let mut pools: HashMap<&str, Pool> = HashMap::new();
+    for (entry, layout) in files {
+        let pool = pools
+            .entry(layout.pool.as_deref().unwrap_or(NO_POOL))
+            .or_default();
+        pool.files += 1;
+        pool.bytes += entry.size;
+        pool.stripes += u64::from(layout.stripe_count);
+        pool.osts.extend(&layout.osts);
+        if layout.is_poorly_striped(entry.size) {
+            pool.poorly_striped += entry.size;
+        }
+    }
+    let total: u64 = pools.values().map(|p| p.bytes).sum();
+    let mut pools: Vec<(&str, Pool)> = pools.into_iter().collect();
+    pools.sort_by(|a, b| b.1.bytes.cmp(&a.1.bytes).then_with(|| a.0.cmp(b.0)));
+
+    Report {
+        columns: vec![
+            ReportColumn {
+                title: "POOL",
+                csv_name: "pool",
+            },
+            ReportColumn {
+                title: "SIZE",
+                csv_name: "size_bytes",
+            },
+            ReportColumn {
+                title: "FILES",
+                csv_name: "files",
+            },
+            ReportColumn {
+                title: "SHARE",
+                csv_name: "share",
+            },
+            ReportColumn {
+                title: "OSTS",
+                csv_name: "osts",
+            },
+            ReportColumn {
+                title: "STRIPES",
+                csv_name: "mean_stripe_count",
+            },
+            ReportColumn {
+                title: "POORLY STRIPED",
+                csv_name: "poorly_striped_bytes",
+            },
+        ],
+        rows: pools
+            .into_iter()
+            .map(|(name, pool)| {
+                vec![
+                    Cell::Text(name.to_string()),
+                    Cell::Bytes(pool.bytes),
+                    Cell::Count(pool.files),
+                    Cell::Percent(percent(pool.bytes, total)),
+                    Cell::Count(pool.osts.len() as u64),
+                    Cell::Text(format!("{:.1}", pool.stripes as f64 / pool.files as f64)),
+                    Cell::Bytes(pool.poorly_striped),
+                ]
+            })
+            .collect(),
+    }
+}
+
+/// The poorly striped files among Lustre files and their layouts, largest
+/// first, with their striping and the bytes each stripe object holds.
+#[cfg(feature = "lustre")]
+pub fn lustre_files(files: &[(&FileEntry, StripeLayout)]) -> Report {
+    let mut poor: Vec<&(&FileEntry, StripeLayout)> = files
+        .iter()
+        .filter(|(entry, layout)| layout.is_poorly_striped(entry.size))
+        .collect();
+    poor.sort_by(|a, b| {
+        b.0.size
+            .cmp(&a.0.size)
+            .then_with(|| a.0.path.cmp(&b.0.path))
+    });
+
+    Report {
+        columns: vec![
+            ReportColumn {
+                title: "SIZE",
+                csv_name: "size_bytes",
+            },
+            ReportColumn {
+                title: "STRIPES",
+                csv_name: "stripe_count",
+            },
+            ReportColumn {
+                title: "STRIPE SIZE",
+                csv_name: "stripe_size_bytes",
+            },
+            ReportColumn {
+                title: "PER STRIPE",
+                csv_name: "bytes_per_stripe",
+            },
+            ReportColumn {
+                title: "POOL",
+                csv_name: "pool",
+            },
+            ReportColumn {
+                title: "FILE",
+                csv_name: "path",
+            },
+        ],
+        rows: poor
+            .into_iter()
+            .map(|(entry, layout)| {
+                vec![
+                    Cell::Bytes(entry.size),
+                    Cell::Count(u64::from(layout.stripe_count)),
+                    Cell::Bytes(layout.stripe_size),
+                    Cell::Bytes(entry.size / u64::from(layout.stripe_count.max(1))),
+                    Cell::Text(layout.pool.clone().unwrap_or_else(|| NO_POOL.to_string())),
+                    Cell::Text(entry.path.display().to_string()),
+                ]
+            })
+            .collect(),
+    }
+}
+
+/// Apparent size of a regular file with extended metadata, and whether it
+/// is sparse: its disk usage is at most half of it, at least
+/// [`MIN_SPARSE_HOLE`] bytes short.
+fn sparseness(entry: &FileEntry) -> Option<(u64, bool)> {
+    if entry.entry_type != EntryType::File {
+        return None;
+    }
+    let apparent = entry.stat.as_ref()?.apparent_size;
+    let hole = apparent.saturating_sub(entry.size);
+    Some((
+        apparent,
+        hole >= MIN_SPARSE_HOLE && entry.size <= apparent / 2,
+    ))
+}
+
+/// Apparent and allocated totals of the regular files of a scan, for
+/// `--sparse-report`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SparseTotals {
+    /// Regular files with a known apparent size
+    pub files: u64,
+    /// Sum of their apparent sizes
+    pub apparent: u64,
+    /// Sum of their disk usage
+    pub allocated: u64,
+    /// How many of them are sparse
+    pub sparse_files: u64,
+}
+
+impl SparseTotals {
+    /// Totals over `entries`; entries without extended metadata are left out.
+    pub fn from_entries(entries: &[FileEntry]) -> Self {
+        let mut totals = Self::default();
+        for entry in entries {
+            let Some((apparent, sparse)) = sparseness(entry) else {
+                continue;
+            };
+            totals.files += 1;
+            totals.apparent += apparent;
+            totals.allocated += entry.size;
+            totals.sparse_files += u64::from(sparse);
+        }
+        totals
+    }
+
+    /// One-line summary of the totals, e.g. `3 of 120 files are sparse:
+    /// 40.00 GB apparent, 2.10 GB allocated`.
+    pub fn summary(&self, units: SizeFormat) -> String {
+        format!(
+            "{} of {} files are sparse: {} apparent, {} allocated",
+            crate::utils::format_count(self.sparse_files as usize),
+            crate::utils::format_count(self.files as usize),
+            units.format(self.apparent),
+            units.format(self.allocated)
+        )
+    }
+}
+
+/// The `limit` largest sparse files by apparent size, with their disk
+/// usage and the share of them that is unallocated.
+///
+/// Apparent sizes come from extended metadata; files scanned without it are
+/// left out.
+pub fn sparse(entries: &[FileEntry], limit: usize) -> Report {
+    let mut files: Vec<(&FileEntry, u64)> = entries
+        .iter()
+        .filter_map(|e| match sparseness(e)? {
+            (apparent, true) => Some((e, apparent)),
+            _ => None,
+        })
+        .collect();
+    files.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.path.cmp(&b.0.path)));
+    files.truncate(limit);
+
+    Report {
+        columns: vec![
+            ReportColumn {
+                title: "APPARENT",
+                csv_name: "apparent_bytes",
+            },
+            ReportColumn {
+                title: "ALLOCATED",
+                csv_name: "allocated_bytes",
+            },
+            ReportColumn {
+                title: "HOLES",
+                csv_name: "holes_percent",
+            },
+            ReportColumn {
+                title: "FILE",
+                csv_name: "path",
+            },
+        ],
+        rows: files
+            .into_iter()
+            .map(|(entry, apparent)| {
+                vec![
+                    Cell::Bytes(apparent),
+                    Cell::Bytes(entry.size),
+                    Cell::Percent(percent(apparent - entry.size, apparent)),
+                    Cell::Text(entry.path.display().to_string()),
+                ]
+            })
+            .collect(),
+    }
+}
+
+/// Apparent size of a regular file with extended metadata, and whether it
+/// is offline: an HSM has migrated its data to tape or object storage,
+/// leaving at most [`MAX_OFFLINE_ALLOCATED`] bytes on disk for more than
+/// [`MIN_OFFLINE_SIZE`] of contents.
+///
+/// Block counts are all a `stat` shows, so a file that is nothing but a
+/// hole also counts as offline; reading HSM attributes instead (DMF's
+/// `dmattr`, Lustre's `hsm_state`) needs a separate call per file.
+fn residency(entry: &FileEntry) -> Option<(u64, bool)> {
+    if entry.entry_type != EntryType::File {
+        return None;
+    }
+    let apparent = entry.stat.as_ref()?.apparent_size;
+    Some((
+        apparent,
+        apparent > MIN_OFFLINE_SIZE && entry.size <= MAX_OFFLINE_ALLOCATED,
+    ))
+}
+
+/// Resident and migrated totals of the regular files of a scan, for
+/// `--report hsm`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HsmTotals {
+    /// Regular files with a known apparent size
+    pub files: u64,
+    /// Disk usage of the files that are (at least partly) on disk
+    pub resident: u64,
+    /// How many of them are offline
+    pub offline_files: u64,
+    /// Sum of the apparent sizes of the offline files
+    pub migrated: u64,
+}
+
+impl HsmTotals {
+    /// Totals over `entries`; entries without extended metadata are left out.
+    pub fn from_entries(entries: &[FileEntry]) -> Self {
+        let mut totals = Self::default();
+        for entry in entries {
+            let Some((apparent, offline)) = residency(entry) else {
+                continue;
+            };
+            totals.files += 1;
+            if offline {
+                totals.offline_files += 1;
+                totals.migrated += apparent;
+            } else {
+                totals.resident += entry.size;
+            }
+        }
+        totals
+    }
+
+    /// One-line summary of the totals, e.g. `812 of 9,400 files are
+    /// offline: 3.20 TB migrated, 41.00 GB resident`.
+    pub fn summary(&self, units: SizeFormat) -> String {
+        format!(
+            "{} of {} files are offline: {} migrated, {} resident",
+            crate::utils::format_count(self.offline_files as usize),
+            crate::utils::format_count(self.files as usize),
+            units.format(self.migrated),
+            units.format(self.resident)
+        )
+    }
+}
+
+/// Resident and migrated bytes of the files below each directory at most
+/// `depth` levels below `root`, in path order.
+///
+/// `RESIDENT` is the disk usage of files still on disk and `MIGRATED` the
+/// apparent size of offline files, whose data an HSM holds elsewhere and
+/// would recall on first read; `OFFLINE` is the migrated share of the two.
+/// Files scanned without extended metadata are left out.
+pub fn hsm(entries: &[FileEntry], root: &Path, depth: usize) -> Report {
+    let mut dirs: HashMap<&Path, HsmTotals> = entries
+        .iter()
+        .filter(|e| e.entry_type == EntryType::Dir && path_depth(root, &e.path) <= depth)
+        .map(|e| (e.path.as_path(), HsmTotals::default()))
+        .collect();
+
+    for entry in entries {
+        let Some((apparent, offline)) = residency(entry) else {
+            continue;
+        };
+        for ancestor in entry
+            .path
+            .ancestors()
+            .skip(1)
+            .take_while(|a| a.starts_with(root))
+        {
+            if let Some(totals) = dirs.get_mut(ancestor) {
+                totals.files += 1;
+                if offline {
+                    totals.offline_files += 1;
+                    totals.migrated += apparent;
+                } else {
+                    totals.resident += entry.size;
+                }
+            }
+        }
+    }
+
+    let mut dirs: Vec<(&Path, HsmTotals)> = dirs.into_iter().collect();
+    dirs.sort_by(|a, b| a.0.cmp(b.0));
+
+    Report {
+        columns: vec![
+            ReportColumn {
+                title: "RESIDENT",
+                csv_name: "resident_bytes",
+            },
+            ReportColumn {
+                title: "MIGRATED",
+                csv_name: "migrated_bytes",
+            },
+            ReportColumn {
+                title: "OFFLINE FILES",
+                csv_name: "offline_files",
+            },
+            ReportColumn {
+                title: "OFFLINE",
+                csv_name: "offline_percent",
+            },
+            ReportColumn {
+                title: "DIRECTORY",
+                csv_name: "path",
+            },
+        ],
+        rows: dirs
+            .into_iter()
+            .map(|(path, totals)| {
+                vec![
+                    Cell::Bytes(totals.resident),
+                    Cell::Bytes(totals.migrated),
+                    Cell::Count(totals.offline_files),
+                    Cell::Percent(percent(totals.migrated, totals.migrated + totals.resident)),
+                    Cell::Text(path.display().to_string()),
+                ]
+            })
+            .collect(),
+    }
+}
+
+/// Findings of `--report security`, in order of severity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum SecurityIssue {
+    /// A regular file that runs as its owner
+    Setuid,
+    /// A regular file that runs with its group
+    Setgid,
+    /// A directory anyone can create, rename and delete entries in
+    WorldWritable,
+    /// An entry whose owner's user ID has no passwd entry
+    UnknownOwner,
+}
+
+impl SecurityIssue {
+    fn as_str(self) -> &'static str {
+        match self {
+            SecurityIssue::Setuid => "setuid",
+            SecurityIssue::Setgid => "setgid",
+            SecurityIssue::WorldWritable => "world-writable",
+            SecurityIssue::UnknownOwner => "unknown owner",
+        }
+    }
+}
+
+/// World-writable directories, setuid and setgid files and entries whose
+/// owner's user ID `user_name` finds no name for, one row per entry with
+/// all its issues, most severe first and then in path order.
+///
+/// World-writable directories are listed whether or not they have the
+/// sticky bit, which `MODE` shows as `t` (as on `/tmp`, where only owners
+/// may delete their entries). Setgid directories are not flagged, as the
+/// bit only makes new entries inherit the directory's group. Entries
+/// scanned without extended metadata are left out.
+pub fn security(entries: &[FileEntry], mut user_name: impl FnMut(u32) -> Option<String>) -> Report {
+    let mut findings: Vec<(Vec<SecurityIssue>, &FileEntry, u32, String)> = entries
+        .iter()
+        .filter_map(|entry| {
+            let stat = entry.stat.as_ref()?;
+            let mut issues = Vec::new();
+            if entry.entry_type == EntryType::File {
+                if stat.mode & MODE_SETUID != 0 {
+                    issues.push(SecurityIssue::Setuid);
+                }
+                if stat.mode & MODE_SETGID != 0 {
+                    issues.push(SecurityIssue::Setgid);
+                }
+            }
+            if entry.entry_type == EntryType::Dir && stat.mode & MODE_OTHER_WRITE != 0 {
+                issues.push(SecurityIssue::WorldWritable);
+            }
+            let owner = user_name(stat.uid).unwrap_or_else(|| {
+                issues.push(SecurityIssue::UnknownOwner);
+                stat.uid.to_string()
+            });
+            (!issues.is_empty()).then_some((issues, entry, stat.mode, owner))
+        })
+        .collect();
+    findings.sort_by(|a, b| a.0[0].cmp(&b.0[0]).then_with(|| a.1.path.cmp(&b.1.path)));
+
+    Report {
+        columns: vec![
+            ReportColumn {
+                title: "ISSUES",
+                csv_name: "issues",
+            },
+            ReportColumn {
+                title: "MODE",
+                csv_name: "mode",
+            },
+            ReportColumn {
+                title: "OWNER",
+                csv_name: "owner",
+            },
+            ReportColumn {
+                title: "SIZE",
+                csv_name: "size_bytes",
+            },
+            ReportColumn {
+                title: "PATH",
+                csv_name: "path",
+            },
+        ],
+        rows: findings
+            .into_iter()
+            .map(|(issues, entry, mode, owner)| {
+                let issues: Vec<&str> = issues.into_iter().map(SecurityIssue::as_str).collect();
+                vec![
+                    Cell::Text(issues.join(", ")),
+                    Cell::Text(mode_string(mode)),
+                    Cell::Text(owner),
+                    Cell::Bytes(entry.size),
+                    Cell::Text(entry.path.display().to_string()),
+                ]
+            })
+            .collect(),
+    }
+}
+
+/// Totals of the entries of one orphaned user or group ID
+#[derive(Default)]
+struct OrphanTotals<'a> {
+    bytes: u64,
+    files: u64,
+    dirs: u64,
+    largest: Option<&'a FileEntry>,
+}
+
+/// Bytes, files and directories per user ID that `user_name` and group ID
+/// that `group_name` find no name for, largest first.
+///
+/// Like [`by_owner`], bytes count regular files and other non-directories
+/// only, as directory sizes already include their contents. `LARGEST` is
+/// the largest entry owned, a starting point for reassigning (`chown -R`)
+/// or archiving the data. Entries scanned without extended metadata are
+/// left out.
+pub fn orphans(
+    entries: &[FileEntry],
+    mut user_name: impl FnMut(u32) -> Option<String>,
+    mut group_name: impl FnMut(u32) -> Option<String>,
+) -> Report {
+    let mut orphans: HashMap<(&str, u32), OrphanTotals> = HashMap::new();
+    for entry in entries {
+        let Some(stat) = entry.stat.as_ref() else {
+            continue;
+        };
+        let mut keys = Vec::new();
+        if user_name(stat.uid).is_none() {
+            keys.push(("user", stat.uid));
+        }
+        if group_name(stat.gid).is_none() {
+            keys.push(("group", stat.gid));
+        }
+        for key in keys {
+            let totals = orphans.entry(key).or_default();
+            if entry.entry_type.is_container() {
+                totals.dirs += 1;
+            } else {
+                totals.files += 1;
+                totals.bytes += entry.size;
+            }
+            if totals
+                .largest
+                .is_none_or(|largest| entry.size > largest.size)
+            {
+                totals.largest = Some(entry);
+            }
+        }
+    }
+
+    let mut orphans: Vec<((&str, u32), OrphanTotals)> = orphans.into_iter().collect();
+    // Users before groups on ties, then by ID
+    orphans.sort_by(|a, b| {
+        b.1.bytes
+            .cmp(&a.1.bytes)
+            .then(b.0.0.cmp(a.0.0))
+            .then(a.0.1.cmp(&b.0.1))
+    });
+
+    Report {
+        columns: vec![
+            ReportColumn {
+                title: "TYPE",
+                csv_name: "type",
+            },
+            ReportColumn {
+                title: "ID",
+                csv_name: "id",
+            },
+            ReportColumn {
+                title: "SIZE",
+                csv_name: "size_bytes",
+            },
+            ReportColumn {
+                title: "FILES",
+                csv_name: "files",
+            },
+            ReportColumn {
+                title: "DIRS",
+                csv_name: "dirs",
+            },
+            ReportColumn {
+                title: "LARGEST",
+                csv_name: "largest_path",
+            },
+        ],
+        rows: orphans
+            .into_iter()
+            .map(|((kind, id), totals)| {
+                vec![
+                    Cell::Text(kind.to_string()),
+                    Cell::Count(u64::from(id)),
+                    Cell::Bytes(totals.bytes),
+                    Cell::Count(totals.files),
+                    Cell::Count(totals.dirs),
+                    Cell::Text(
+                        totals
+                            .largest
+                            .map_or_else(String::new, |e| e.path.display().to_string()),
+                    ),
+                ]
+            })
+            .collect(),
+    }
+}
+
+/// `mode` (`st_mode`, with the file type bits) as `ls -l` shows it, e.g.
+/// `drwxrwxrwt` or `-rwsr-xr-x`
+fn mode_string(mode: u32) -> String {
+    // File type bits (`S_IFMT`)
+    let kind = match mode & 0o170000 {
+        0o040000 => 'd',
+        0o120000 => 'l',
+        0o140000 => 's',
+        0o010000 => 'p',
+        0o020000 => 'c',
+        0o060000 => 'b',
+        _ => '-',
+    };
+    // Read, write and execute bits per class, with the special bit shown in
+    // place of execute
+    let class = |shift: u32, special: bool, set: char| {
+        let bits = mode >> shift;
+        let execute = match (special, bits & 1 != 0) {
+            (true, true) => set,
+            (true, false) => set.to_ascii_uppercase(),
+            (false, true) => 'x',
+            (false, false) => '-',
+        };
+        [
+            if bits & 4 != 0 { 'r' } else { '-' },
+            if bits & 2 != 0 { 'w' } else { '-' },
+            execute,
+        ]
+    };
+    std::iter::once(kind)
+        .chain(class(6, mode & MODE_SETUID != 0, 's'))
+        .chain(class(3, mode & MODE_SETGID != 0, 's'))
+        .chain(class(0, mode & MODE_STICKY != 0, 't'))
+        .collect()
+}
+
+/// Recursive file, directory and inode counts per directory for `--count-only`.
+///
+/// There is one row per directory at most `depth` levels below `root` (all
+/// of them without a depth), ordered by `sort` keys (path, or most inodes
+/// first for [`SortKey::Inodes`] and [`SortKey::Size`]) with path breaking
+/// remaining ties, and reversed with `reverse`.
+pub fn counts(
+    counts: &[DirCounts],
+    root: &Path,
+    depth: Option<usize>,
+    sort: &[SortKey],
+    reverse: bool,
+) -> Report {
+    let mut dirs: Vec<&DirCounts> = counts
+        .iter()
+        .filter(|c| depth.is_none_or(|depth| path_depth(root, &c.path) <= depth))
+        .collect();
+    dirs.sort_by(|a, b| {
+        let order = sort
+            .iter()
+            .map(|key| match key {
+                SortKey::Name => a.path.cmp(&b.path),
+                SortKey::Size | SortKey::Inodes => b.inodes().cmp(&a.inodes()),
+                // Not collected without stat'ing
+                SortKey::Mtime | SortKey::Owner => std::cmp::Ordering::Equal,
+            })
+            .find(|order| order.is_ne())
+            .unwrap_or_else(|| a.path.cmp(&b.path));
+        if reverse { order.reverse() } else { order }
+    });
+
+    Report {
+        columns: vec![
+            ReportColumn {
+                title: "INODES",
+                csv_name: "inodes",
+            },
+            ReportColumn {
+                title: "FILES",
+                csv_name: "files",
+            },
+            ReportColumn {
+                title: "DIRS",
+                csv_name: "dirs",
+            },
+            ReportColumn {
+                title: "DIRECTORY",
+                csv_name: "path",
+            },
+        ],
+        rows: dirs
+            .into_iter()
+            .map(|dir| {
+                vec![
+                    Cell::Count(dir.inodes()),
+                    Cell::Count(dir.files),
+                    Cell::Count(dir.dirs),
+                    Cell::Text(dir.path.display().to_string()),
+                ]
+            })
+            .collect(),
+    }
+}
+
+/// Per-directory changes between two snapshots for `rudu diff`, in the
+/// order given, with paths below `root`.
+///
+/// Sizes missing from one side (new and deleted trees) show as zero.
+pub fn diff(changes: &[DirChange], root: &Path) -> Report {
+    Report {
+        columns: vec![
+            ReportColumn {
+                title: "CHANGE",
+                csv_name: "change_bytes",
+            },
+            ReportColumn {
+                title: "BEFORE",
+                csv_name: "old_size_bytes",
+            },
+            ReportColumn {
+                title: "AFTER",
+                csv_name: "new_size_bytes",
+            },
+            ReportColumn {
+                title: "STATUS",
+                csv_name: "status",
+            },
+            ReportColumn {
+                title: "DIRECTORY",
+                csv_name: "path",
+            },
+        ],
+        rows: changes
+            .iter()
+            .map(|change| {
+                // The root is the empty path, which `join` would end with a slash
+                let path = if change.path.as_os_str().is_empty() {
+                    root.to_path_buf()
+                } else {
+                    root.join(&change.path)
+                };
+                vec![
+                    Cell::Delta(change.delta()),
+                    Cell::Bytes(change.old_size.unwrap_or(0)),
+                    Cell::Bytes(change.new_size.unwrap_or(0)),
+                    Cell::Text(change.kind().as_str().to_string()),
+                    Cell::Text(path.display().to_string()),
+                ]
+            })
+            .collect(),
+    }
+}
+
+/// Growth of directories over the recorded runs for `rudu report trend`, in
+/// the order given.
+///
+/// With a `quota`, a `FULL ON` column projects when the first directory
+/// (the one asked about) reaches it at its current rate; `-` when it is not
+/// growing.
+#[cfg(feature = "history")]
+pub fn trend(trends: &[Trend], quota: Option<u64>) -> Report {
+    let mut columns = vec![
+        ReportColumn {
+            title: "SIZE",
+            csv_name: "size_bytes",
+        },
+        ReportColumn {
+            title: "CHANGE",
+            csv_name: "change_bytes",
+        },
+        ReportColumn {
+            title: "PER DAY",
+            csv_name: "growth_bytes_per_day",
+        },
+        ReportColumn {
+            title: "RUNS",
+            csv_name: "runs",
+        },
+    ];
+    if quota.is_some() {
+        columns.push(ReportColumn {
+            title: "FULL ON",
+            csv_name: "full_on",
+        });
+    }
+    columns.push(ReportColumn {
+        title: "DIRECTORY",
+        csv_name: "path",
+    });
+
+    let rows = trends
+        .iter()
+        .enumerate()
+        .map(|(i, trend)| {
+            let mut row = vec![
+                Cell::Bytes(trend.latest()),
+                Cell::Delta(trend.change()),
+                Cell::Delta(trend.growth_per_day().unwrap_or(0.0).round() as i64),
+                Cell::Count(trend.samples.len() as u64),
+            ];
+            if let Some(quota) = quota {
+                let full_on = trend
+                    .full_at(quota)
+                    .filter(|_| i == 0)
+                    .map_or("-".to_string(), |at| at.format("%Y-%m-%d").to_string());
+                row.push(Cell::Text(full_on));
+            }
+            row.push(Cell::Text(trend.path.display().to_string()));
+            row
+        })
+        .collect();
+    Report { columns, rows }
+}
+
+/// `part` as a percentage of `total`, or zero for an empty total
+fn percent(part: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / total as f64
+    }
+}
+
+/// Renders a report as CSV for `--format csv`/`tsv` or `--output`, and as a
+/// table otherwise.
+///
+/// Writes to the `--output` file if one was given, otherwise to stdout.
+pub fn render(report: &Report, args: &Args) -> Result<()> {
+    let csv = matches!(args.output_format(), OutputFormat::Csv | OutputFormat::Tsv);
+    let write = |out: &mut dyn Write| -> Result<()> {
+        if csv {
+            Ok(write_csv(out, report, Dialect::from_args(args))?)
+        } else {
+            write_table(out, report, args.size_format())?;
+            Ok(())
+        }
+    };
+    match &args.output {
+        Some(output_file) => {
+            let mut file = io::BufWriter::new(File::create(output_file)?);
+            write(&mut file)?;
+            file.flush()?;
+            log::info!("Report written to: {}", output_file);
+        }
+        None => write(&mut io::stdout().lock())?,
+    }
+    Ok(())
+}
+
+/// Writes a report as an aligned table with a heading row.
+///
+/// Text is left-aligned and numbers right-aligned; sizes use `units`.
+pub fn write_table<W: Write + ?Sized>(
+    out: &mut W,
+    report: &Report,
+    units: SizeFormat,
+) -> io::Result<()> {
+    let rows: Vec<Vec<String>> = report
+        .rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|cell| match cell {
+                    Cell::Text(text) => text.clone(),
+                    Cell::Bytes(bytes) => units.format(*bytes),
+                    Cell::Count(count) => count.to_string(),
+                    Cell::Percent(share) => format!("{:.1}%", share),
+                    Cell::Delta(delta) => format!(
+                        "{}{}",
+                        if *delta < 0 { '-' } else { '+' },
+                        units.format(delta.unsigned_abs())
+                    ),
+                })
+                .collect()
+        })
+        .collect();
+
+    let widths: Vec<usize> = report
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .max()
+                .unwrap_or(0)
+                .max(column.title.len())
+        })
+        .collect();
+    // Columns are aligned like their values; the first row decides
+    let left: Vec<bool> = (0..report.columns.len())
+        .map(|i| {
+            matches!(
+                report.rows.first().map(|row| &row[i]),
+                Some(Cell::Text(_)) | None
+            )
+        })
+        .collect();
+
+    let mut write_line = |fields: Vec<&str>| -> io::Result<()> {
+        let line: Vec<String> = fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                if left[i] {
+                    format!("{:<width$}", field, width = widths[i])
+                } else {
+                    format!("{:>width$}", field, width = widths[i])
+                }
+            })
+            .collect();
+        writeln!(out, "{}", line.join("  ").trim_end())
+    };
+    write_line(report.columns.iter().map(|c| c.title).collect())?;
+    for row in &rows {
+        write_line(row.iter().map(String::as_str).collect())?;
+    }
+    Ok(())
+}
+
+/// Writes a report as CSV in the given dialect.
+///
+/// Sizes are written in bytes and shares with two decimals, so the output
+/// can be loaded without parsing units.
+pub fn write_csv<W: Write + ?Sized>(
+    out: &mut W,
+    report: &Report,
+    dialect: Dialect,
+) -> crate::error::Result<()> {
+    write_csv_rows(out, report, dialect).map_err(Error::output)
+}
+
+fn write_csv_rows<W: Write + ?Sized>(out: &mut W, report: &Report, dialect: Dialect) -> Result<()> {
+    let mut writer = dialect.builder().from_writer(out);
+    if dialect.header {
+        writer.write_record(report.columns.iter().map(|c| c.csv_name))?;
+    }
+    for row in &report.rows {
+        writer.write_record(row.iter().map(|cell| match cell {
+            Cell::Text(text) => text.clone(),
+            Cell::Bytes(n) | Cell::Count(n) => n.to_string(),
+            Cell::Percent(share) => format!("{:.2}", share),
+            Cell::Delta(n) => n.to_string(),
+        }))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn entry(path: &str, entry_type: EntryType, size: u64, owner: Option<&str>) -> FileEntry {
+        FileEntry {
+            owner: owner.map(str::to_string),
+            ..FileEntry::for_test(path, size, entry_type)
+        }
+    }
+
+    fn sample() -> Vec<FileEntry> {
+        vec![
+            entry("/d", EntryType::Dir, 1600, Some("root")),
+            entry("/d/a", EntryType::File, 100, Some("alice")),
+            entry("/d/b", EntryType::File, 300, Some("bob")),
+            entry("/d/sub", EntryType::Dir, 1200, Some("alice")),
+            entry("/d/sub/c", EntryType::File, 1200, Some("alice")),
+            entry("/d/sub/d", EntryType::File, 0, None),
+        ]
+    }
+
+    #[test]
+    fn test_by_owner_ranks_by_bytes() {
+        let report = by_owner(&sample());
+        let owners: Vec<&Cell> = report.rows.iter().map(|row| &row[0]).collect();
+        assert_eq!(
+            owners,
+            [
+                &Cell::Text("alice".into()),
+                &Cell::Text("bob".into()),
+                &Cell::Text("root".into()),
+                &Cell::Text("unknown".into()),
+            ]
+        );
+        assert_eq!(
+            report.rows[0][1..],
+            [
+                Cell::Bytes(1300),
+                Cell::Count(2),
+                Cell::Count(3),
+                Cell::Percent(1300.0 * 100.0 / 1600.0),
+            ]
+        );
+        // Directories count as inodes but not as files or bytes
+        assert_eq!(
+            report.rows[2][1..4],
+            [Cell::Bytes(0), Cell::Count(0), Cell::Count(1)]
+        );
+    }
+
+    #[test]
+    fn test_classify() {
+        let kind = |path: &str| classify(Path::new(path));
+        assert_eq!(kind("/d/run.NC"), ("nc".to_string(), "netCDF"));
+        assert_eq!(kind("/d/backup.tar.gz"), ("tar.gz".to_string(), "archive"));
+        assert_eq!(kind("/d/data.gz"), ("gz".to_string(), "compressed"));
+        assert_eq!(kind("/d/job.123.out"), ("out".to_string(), "log"));
+        assert_eq!(kind("/d/core"), (String::new(), "core dump"));
+        assert_eq!(kind("/d/core.4242"), (String::new(), "core dump"));
+        assert_eq!(kind("/d/core.py"), ("py".to_string(), "source"));
+        assert_eq!(kind("/d/.bashrc"), (String::new(), "other"));
+        assert_eq!(kind("/d/Makefile"), (String::new(), "other"));
+        assert_eq!(kind("/d/x.weird"), ("weird".to_string(), "other"));
+    }
+
+    #[test]
+    fn test_by_ext() {
+        let entries = vec![
+            entry("/d", EntryType::Dir, 4500, None),
+            entry("/d/a.nc", EntryType::File, 1000, None),
+            entry("/d/b.NC", EntryType::File, 2000, None),
+            entry("/d/core.17", EntryType::File, 1500, None),
+            entry("/d/README", EntryType::File, 0, None),
+        ];
+        let report = by_ext(&entries);
+        assert_eq!(report.rows.len(), 3);
+        assert_eq!(
+            report.rows[0],
+            [
+                Cell::Text("nc".into()),
+                Cell::Text("netCDF".into()),
+                Cell::Bytes(3000),
+                Cell::Count(2),
+                Cell::Percent(3000.0 * 100.0 / 4500.0),
+            ]
+        );
+        assert_eq!(
+            report.rows[1][..2],
+            [Cell::Text("-".into()), Cell::Text("core dump".into())]
+        );
+        assert_eq!(
+            report.rows[2][1..4],
+            [Cell::Text("other".into()), Cell::Bytes(0), Cell::Count(1)]
+        );
+    }
+
+    #[test]
+    fn test_age_buckets_per_directory() {
+        let now = 10_000 * DAY;
+        let dated = |path: &str, size: u64, days_old: u64| FileEntry {
+            mtime: Some(now - days_old * DAY),
+            ..entry(path, EntryType::File, size, None)
+        };
+        let entries = vec![
+            entry("/d", EntryType::Dir, 0, None),
+            entry("/d/new", EntryType::Dir, 0, None),
+            entry("/d/old", EntryType::Dir, 0, None),
+            entry("/d/old/deep", EntryType::Dir, 0, None),
+            dated("/d/new/a", 100, 1),
+            dated("/d/new/b", 200, 45),
+            dated("/d/old/c", 300, 200),
+            dated("/d/old/deep/e", 400, 800),
+            dated("/d/old/deep/f", 500, 1100),
+            FileEntry {
+                mtime: Some(now + DAY),
+                ..entry("/d/future", EntryType::File, 7, None)
+            },
+            entry("/d/unknown", EntryType::File, 9, None),
+        ];
+
+        let report = age(&entries, Path::new("/d"), 1, now, &[SortKey::Name], false);
+        assert_eq!(report.columns.len(), AGE_BUCKETS.len() + 2);
+        let bytes = |row: &[Cell]| -> Vec<u64> {
+            row[1..]
+                .iter()
+                .map(|cell| match cell {
+                    Cell::Bytes(n) => *n,
+                    _ => panic!("expected bytes"),
+                })
+                .collect()
+        };
+        let paths: Vec<&Cell> = report.rows.iter().map(|row| &row[0]).collect();
+        assert_eq!(
+            paths,
+            [
+                &Cell::Text("/d".into()),
+                &Cell::Text("/d/new".into()),
+                &Cell::Text("/d/old".into()),
+            ]
+        );
+        assert_eq!(bytes(&report.rows[0]), [107, 200, 300, 400, 500, 1507]);
+        assert_eq!(bytes(&report.rows[1]), [100, 200, 0, 0, 0, 300]);
+        assert_eq!(bytes(&report.rows[2]), [0, 0, 300, 400, 500, 1200]);
+
+        let report = age(&entries, Path::new("/d"), 2, now, &[SortKey::Size], false);
+        assert_eq!(report.rows.len(), 4);
+        assert_eq!(report.rows[1][0], Cell::Text("/d/old".into()));
+        assert_eq!(report.rows[2][0], Cell::Text("/d/old/deep".into()));
+    }
+
+    #[test]
+    fn test_cold_lists_outermost_untouched_directories() {
+        let now = 10_000 * DAY;
+        let touched =
+            |path: &str, entry_type, size, mtime_days, atime_days: Option<u64>| FileEntry {
+                mtime: Some(now - mtime_days * DAY),
+                stat: atime_days.map(|days| {
+                    Box::new(crate::data::EntryStat {
+                        atime: now - days * DAY,
+                        nlink: 1,
+                        uid: 0,
+                        gid: 0,
+                        mode: 0o644,
+                        dev: 0,
+                        ino: 0,
+                        apparent_size: 0,
+                    })
+                }),
+                ..entry(path, entry_type, size, None)
+            };
+        let entries = vec![
+            touched("/d", EntryType::Dir, 1600, 400, None),
+            touched("/d/old", EntryType::Dir, 1000, 400, None),
+            touched("/d/old/a", EntryType::File, 600, 500, Some(400)),
+            touched("/d/old/sub", EntryType::Dir, 400, 400, None),
+            touched("/d/old/sub/b", EntryType::File, 400, 900, None),
+            // Read last week, although written long ago
+            touched("/d/read", EntryType::Dir, 500, 400, None),
+            touched("/d/read/c", EntryType::File, 500, 900, Some(7)),
+            // A file was removed from it recently
+            touched("/d/pruned", EntryType::Dir, 100, 2, None),
+            touched("/d/pruned/e", EntryType::File, 100, 900, None),
+            touched("/d/empty", EntryType::Dir, 0, 900, None),
+        ];
+
+        let report = cold(&entries, Path::new("/d"), now - 180 * DAY);
+        assert_eq!(
+            report.rows,
+            [vec![
+                Cell::Bytes(1000),
+                Cell::Count(2),
+                Cell::Text("1996-04-14".into()),
+                Cell::Text("/d/old".into()),
+            ]]
+        );
+
+        let recent = cold(&entries, Path::new("/d"), now - 3 * DAY);
+        let paths: Vec<&Cell> = recent.rows.iter().map(|row| &row[3]).collect();
+        assert_eq!(
+            paths,
+            [&Cell::Text("/d/old".into()), &Cell::Text("/d/read".into())]
+        );
+        assert_eq!(
+            cold(&entries, Path::new("/d"), now).rows[0][3],
+            Cell::Text("/d".into())
+        );
+    }
+
+    #[test]
+    fn test_project_quota_flags_full_projects() {
+        let entries = vec![
+            entry("/p", EntryType::Dir, 1000, None),
+            entry("/p/full", EntryType::Dir, 950, None),
+            entry("/p/full/deep", EntryType::Dir, 500, None),
+            entry("/p/free", EntryType::Dir, 40, None),
+            entry("/p/unknown", EntryType::Dir, 10, None),
+            entry("/p/file", EntryType::File, 10, None),
+        ];
+        let quota = |used, hard_limit| Quota {
+            used,
+            soft_limit: 0,
+            hard_limit,
+        };
+        let report = project_quota(&entries, Path::new("/p"), 1, |path| {
+            match path.to_str()? {
+                "/p" => Some((0, None)),
+                "/p/full" => Some((7, Some(quota(960, 1000)))),
+                "/p/free" => Some((8, Some(quota(40, 1000)))),
+                _ => None,
+            }
+        });
+        assert_eq!(
+            report.rows,
+            [
+                vec![
+                    Cell::Bytes(1000),
+                    Cell::Count(0),
+                    Cell::Text("-".into()),
+                    Cell::Text("-".into()),
+                    Cell::Text("-".into()),
+                    Cell::Text("".into()),
+                    Cell::Text("/p".into()),
+                ],
+                vec![
+                    Cell::Bytes(40),
+                    Cell::Count(8),
+                    Cell::Bytes(1000),
+                    Cell::Percent(4.0),
+                    Cell::Percent(4.0),
+                    Cell::Text("".into()),
+                    Cell::Text("/p/free".into()),
+                ],
+                vec![
+                    Cell::Bytes(950),
+                    Cell::Count(7),
+                    Cell::Bytes(1000),
+                    Cell::Percent(95.0),
+                    Cell::Percent(96.0),
+                    Cell::Text("near limit".into()),
+                    Cell::Text("/p/full".into()),
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_user_quota_over_and_under() {
+        let quotas = UserQuotas::parse("alice,1000\nbob,2000\nroot,0\n").unwrap();
+        let report = user_quota(&sample(), &quotas);
+        let row = |owner: &str| {
+            report
+                .rows
+                .iter()
+                .find(|row| row[0] == Cell::Text(owner.into()))
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(
+            row("alice")[1..],
+            [
+                Cell::Bytes(1300),
+                Cell::Bytes(1000),
+                Cell::Percent(130.0),
+                Cell::Delta(-300),
+                Cell::Text("over limit".into()),
+            ]
+        );
+        assert_eq!(
+            row("bob")[3..],
+            [
+                Cell::Percent(15.0),
+                Cell::Delta(1700),
+                Cell::Text("under limit".into())
+            ]
+        );
+        // Directories do not count, and unknown owners have no quota
+        assert_eq!(row("unknown")[5], Cell::Text("no quota".into()));
+        let owners: Vec<&Cell> = report.rows.iter().map(|row| &row[0]).collect();
+        assert_eq!(
+            owners,
+            [
+                &Cell::Text("alice".into()),
+                &Cell::Text("bob".into()),
+                &Cell::Text("unknown".into()),
+            ]
+        );
+    }
+
+    #[cfg(feature = "lustre")]
+    #[test]
+    fn test_lustre_pools_and_poorly_striped_files() {
+        use crate::lustre::MAX_BYTES_PER_STRIPE;
+
+        let big = MAX_BYTES_PER_STRIPE * 3;
+        let layout = |stripe_count: u32, pool: Option<&str>, osts: &[u32]| StripeLayout {
+            stripe_count,
+            stripe_size: 1 << 20,
+            pool: pool.map(str::to_string),
+            osts: osts.to_vec(),
+        };
+        let files = [
+            entry("/l/wide", EntryType::File, big, None),
+            entry("/l/narrow", EntryType::File, big, None),
+            entry("/l/small", EntryType::File, 100, None),
+        ];
+        let layouts = vec![
+            (&files[0], layout(4, Some("flash"), &[0, 1, 2, 3])),
+            (&files[1], layout(1, Some("flash"), &[1])),
+            (&files[2], layout(1, None, &[5])),
+        ];
+
+        let pools = lustre_pools(&layouts);
+        assert_eq!(
+            pools.rows[0],
+            [
+                Cell::Text("flash".into()),
+                Cell::Bytes(2 * big),
+                Cell::Count(2),
+                Cell::Percent(percent(2 * big, 2 * big + 100)),
+                Cell::Count(4),
+                Cell::Text("2.5".into()),
+                Cell::Bytes(big),
+            ]
+        );
+        assert_eq!(pools.rows[1][0], Cell::Text("-".into()));
+
+        let poor = lustre_files(&layouts);
+        assert_eq!(poor.rows.len(), 1);
+        assert_eq!(poor.rows[0][3], Cell::Bytes(big));
+        assert_eq!(poor.rows[0][5], Cell::Text("/l/narrow".into()));
+    }
+
+    #[test]
+    fn test_sparse_files_and_totals() {
+        const MB: u64 = 1024 * 1024;
+        let file = |path: &str, allocated: u64, apparent: Option<u64>| FileEntry {
+            stat: apparent.map(|apparent_size| {
+                Box::new(crate::data::EntryStat {
+                    atime: 0,
+                    nlink: 1,
+                    uid: 0,
+                    gid: 0,
+                    mode: 0o100644,
+                    dev: 0,
+                    ino: 0,
+                    apparent_size,
+                })
+            }),
+            ..entry(path, EntryType::File, allocated, None)
+        };
+        let entries = vec![
+            entry("/vm", EntryType::Dir, 0, None),
+            file("/vm/a.img", 10 * MB, Some(100 * MB)),
+            file("/vm/b.img", 3 * MB, Some(200 * MB)),
+            // Mostly allocated
+            file("/vm/c.img", 60 * MB, Some(100 * MB)),
+            // Inline in its inode, not sparse
+            file("/vm/tiny", 0, Some(60)),
+            file("/vm/unknown", 1, None),
+        ];
+
+        let report = sparse(&entries, 10);
+        let rows: Vec<(&Cell, &Cell, &Cell)> = report
+            .rows
+            .iter()
+            .map(|row| (&row[0], &row[1], &row[3]))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                (
+                    &Cell::Bytes(200 * MB),
+                    &Cell::Bytes(3 * MB),
+                    &Cell::Text("/vm/b.img".into())
+                ),
+                (
+                    &Cell::Bytes(100 * MB),
+                    &Cell::Bytes(10 * MB),
+                    &Cell::Text("/vm/a.img".into())
+                ),
+            ]
+        );
+        assert_eq!(report.rows[1][2], Cell::Percent(90.0));
+        assert_eq!(sparse(&entries, 1).rows.len(), 1);
+
+        assert_eq!(
+            SparseTotals::from_entries(&entries),
+            SparseTotals {
+                files: 4,
+                apparent: 400 * MB + 60,
+                allocated: 73 * MB,
+                sparse_files: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_hsm_resident_and_migrated() {
+        const MB: u64 = 1024 * 1024;
+        let file = |path: &str, allocated: u64, apparent: u64| FileEntry {
+            stat: Some(Box::new(crate::data::EntryStat {
+                atime: 0,
+                nlink: 1,
+                uid: 0,
+                gid: 0,
+                mode: 0o100644,
+                dev: 0,
+                ino: 0,
+                apparent_size: apparent,
+            })),
+            ..entry(path, EntryType::File, allocated, None)
+        };
+        let entries = vec![
+            entry("/arc", EntryType::Dir, 0, None),
+            file("/arc/run.nc", 0, 300 * MB),
+            // A released Lustre file keeps one block
+            file("/arc/out.nc", 512, 100 * MB),
+            entry("/arc/new", EntryType::Dir, 0, None),
+            file("/arc/new/run.nc", 100 * MB, 100 * MB),
+            // Inline in its inode, not offline
+            file("/arc/new/tiny", 0, 60),
+            entry("/arc/new/unknown", EntryType::File, 4, None),
+        ];
+
+        let report = hsm(&entries, Path::new("/arc"), 1);
+        assert_eq!(
+            report.rows,
+            [
+                vec![
+                    Cell::Bytes(100 * MB),
+                    Cell::Bytes(400 * MB),
+                    Cell::Count(2),
+                    Cell::Percent(80.0),
+                    Cell::Text("/arc".into()),
+                ],
+                vec![
+                    Cell::Bytes(100 * MB),
+                    Cell::Bytes(0),
+                    Cell::Count(0),
+                    Cell::Percent(0.0),
+                    Cell::Text("/arc/new".into()),
+                ],
+            ]
+        );
+        assert_eq!(hsm(&entries, Path::new("/arc"), 0).rows.len(), 1);
+
+        let totals = HsmTotals::from_entries(&entries);
+        assert_eq!(
+            totals,
+            HsmTotals {
+                files: 4,
+                resident: 100 * MB,
+                offline_files: 2,
+                migrated: 400 * MB,
+            }
+        );
+        assert_eq!(
+            totals.summary(SizeFormat::default()),
+            "2 of 4 files are offline: 419.43 MB migrated, 104.86 MB resident"
+        );
+    }
+
+    #[test]
+    fn test_security_findings() {
+        let with_stat = |path: &str, entry_type, mode, uid| FileEntry {
+            stat: Some(Box::new(crate::data::EntryStat {
+                atime: 0,
+                nlink: 1,
+                uid,
+                gid: 0,
+                mode,
+                dev: 0,
+                ino: 0,
+                apparent_size: 0,
+            })),
+            ..entry(path, entry_type, 100, None)
+        };
+        let entries = vec![
+            with_stat("/d", EntryType::Dir, 0o40755, 0),
+            with_stat("/d/tmp", EntryType::Dir, 0o41777, 0),
+            with_stat("/d/open", EntryType::Dir, 0o40777, 1000),
+            // Setgid directories only pass their group on
+            with_stat("/d/shared", EntryType::Dir, 0o42775, 0),
+            with_stat("/d/open/su", EntryType::File, 0o104755, 4242),
+            with_stat("/d/sg", EntryType::File, 0o102755, 0),
+            with_stat("/d/gone", EntryType::File, 0o100644, 4242),
+            entry("/d/no-stat", EntryType::File, 100, None),
+        ];
+
+        let names = |uid| match uid {
+            0 => Some("root".to_string()),
+            1000 => Some("alice".to_string()),
+            _ => None,
+        };
+        let report = security(&entries, names);
+        let rows: Vec<Vec<String>> = report
+            .rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .filter_map(|cell| match cell {
+                        Cell::Text(text) => Some(text.clone()),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ["setuid, unknown owner", "-rwsr-xr-x", "4242", "/d/open/su"],
+                ["setgid", "-rwxr-sr-x", "root", "/d/sg"],
+                ["world-writable", "drwxrwxrwx", "alice", "/d/open"],
+                ["world-writable", "drwxrwxrwt", "root", "/d/tmp"],
+                ["unknown owner", "-rw-r--r--", "4242", "/d/gone"],
+            ]
+        );
+        assert_eq!(mode_string(0o104644), "-rwSr--r--");
+    }
+
+    #[test]
+    fn test_orphans_per_user_and_group() {
+        let owned = |path: &str, entry_type, size, uid, gid| FileEntry {
+            stat: Some(Box::new(crate::data::EntryStat {
+                atime: 0,
+                nlink: 1,
+                uid,
+                gid,
+                mode: 0o100644,
+                dev: 0,
+                ino: 0,
+                apparent_size: size,
+            })),
+            ..entry(path, entry_type, size, None)
+        };
+        let entries = vec![
+            owned("/d", EntryType::Dir, 7000, 0, 0),
+            owned("/d/gone", EntryType::Dir, 6000, 5001, 100),
+            owned("/d/gone/a", EntryType::File, 4000, 5001, 100),
+            owned("/d/gone/b", EntryType::File, 1000, 5001, 6001),
+            owned("/d/gone/c", EntryType::File, 1000, 0, 0),
+            owned("/d/old", EntryType::File, 1000, 5002, 6001),
+            entry("/d/no-stat", EntryType::File, 100, None),
+        ];
+
+        let known = |id| (id < 5000).then(|| format!("name{id}"));
+        let report = orphans(&entries, known, known);
+        let row = |kind: &str, id, bytes, files, dirs, largest: &str| {
+            vec![
+                Cell::Text(kind.into()),
+                Cell::Count(id),
+                Cell::Bytes(bytes),
+                Cell::Count(files),
+                Cell::Count(dirs),
+                Cell::Text(largest.into()),
+            ]
+        };
+        assert_eq!(
+            report.rows,
+            [
+                row("user", 5001, 5000, 2, 1, "/d/gone"),
+                row("group", 6001, 2000, 2, 0, "/d/gone/b"),
+                row("user", 5002, 1000, 1, 0, "/d/old"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_counts_per_directory() {
+        let dir = |path: &str, files, dirs| DirCounts {
+            path: PathBuf::from(path),
+            files,
+            dirs,
+        };
+        let counts = vec![
+            dir("/d/a/deep", 5, 0),
+            dir("/d/a", 6, 1),
+            dir("/d/b", 9, 0),
+            dir("/d", 16, 3),
+        ];
+
+        let report = super::counts(&counts, Path::new("/d"), Some(1), &[SortKey::Inodes], false);
+        let rows: Vec<(&Cell, &Cell)> = report.rows.iter().map(|row| (&row[0], &row[3])).collect();
+        assert_eq!(
+            rows,
+            [
+                (&Cell::Count(20), &Cell::Text("/d".into())),
+                (&Cell::Count(10), &Cell::Text("/d/b".into())),
+                (&Cell::Count(8), &Cell::Text("/d/a".into())),
+            ]
+        );
+
+        let report = super::counts(&counts, Path::new("/d"), None, &[SortKey::Name], true);
+        assert_eq!(report.rows.len(), 4);
+        assert_eq!(report.rows[0][3], Cell::Text("/d/b".into()));
+        assert_eq!(report.rows[1][3], Cell::Text("/d/a/deep".into()));
+    }
+
+    #[test]
+    fn test_write_table() {
+        let mut out = Vec::new();
+        write_table(&mut out, &by_owner(&sample()), SizeFormat::Blocks(1)).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "OWNER    SIZE  FILES  INODES  SHARE\n\
+             alice    1300      2       3  81.2%\n\
+             bob       300      1       1  18.8%\n\
+             root        0      0       1   0.0%\n\
+             unknown     0      1       1   0.0%\n"
+        );
+    }
+
+    #[test]
+    fn test_write_csv() {
+        let mut out = Vec::new();
+        write_csv(&mut out, &by_owner(&sample()), Dialect::default()).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("owner,size_bytes,files,inodes,share_percent")
+        );
+        assert_eq!(lines.next(), Some("alice,1300,2,3,81.25"));
+        assert_eq!(lines.count(), 3);
+    }
+
+    #[test]
+    fn test_diff_table() {
+        let changes = [
+            DirChange {
+                path: PathBuf::from("a"),
+                old_size: Some(100),
+                new_size: Some(400),
+            },
+            DirChange {
+                path: PathBuf::from("gone"),
+                old_size: Some(200),
+                new_size: None,
+            },
+        ];
+        let mut out = Vec::new();
+        write_table(
+            &mut out,
+            &diff(&changes, Path::new("/d")),
+            SizeFormat::Blocks(1),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            [
+                "CHANGE  BEFORE  AFTER  STATUS   DIRECTORY",
+                "  +300     100    400  grown    /d/a",
+                "  -200     200      0  deleted  /d/gone",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+}
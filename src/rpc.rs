@@ -0,0 +1,503 @@
+//! JSON-RPC 2.0 interface over stdin/stdout for embedding `rudu`.
+//!
+//! Started with `rudu --rpc`, the process reads one JSON-RPC request per line
+//! from stdin and writes one response (or notification) per line to stdout.
+//! Scans run in the background, so `status`, `query` and `cancel` requests are
+//! answered while a scan is still in progress. Diagnostics continue to go to
+//! stderr and never interleave with the protocol stream.
+//!
+//! # Methods
+//!
+//! | Method   | Params                                                          | Result |
+//! |----------|-----------------------------------------------------------------|--------|
+//! | `scan`   | `path`, optional `depth`, `exclude`, `no_cache`                 | `{"scan_id"}` |
+//! | `status` | `scan_id`                                                       | state, entry count, total size, elapsed time |
+//! | `query`  | `scan_id`, optional `path`, `depth`, `sort`, `reverse`, `limit` | matching entries |
+//! | `cancel` | `scan_id`                                                       | `{"cancelled": bool}` |
+//!
+//! When a scan ends, a `scan.finished` notification carrying the same fields
+//! as `status` is sent.
+//!
+//! # Example
+//!
+//! ```text
+//! → {"jsonrpc":"2.0","id":1,"method":"scan","params":{"path":"/data"}}
+//! ← {"jsonrpc":"2.0","id":1,"result":{"scan_id":1}}
+//! ← {"jsonrpc":"2.0","method":"scan.finished","params":{"scan_id":1,"state":"completed",...}}
+//! → {"jsonrpc":"2.0","id":2,"method":"query","params":{"scan_id":1,"depth":1,"sort":"size"}}
+//! ← {"jsonrpc":"2.0","id":2,"result":{"total":3,"entries":[...]}}
+//! ```
+
+use crate::cli::{Args, SortKey};
+use crate::data::{FileEntry, ScanMeta};
+use crate::error::Error;
+use crate::options::ScanOptions;
+use crate::scan::scan_files_and_dirs_cancellable;
+use crate::utils::{build_exclude_matcher, expand_exclude_patterns, path_depth, sort_entries};
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Invalid JSON was received
+pub const PARSE_ERROR: i64 = -32700;
+/// The JSON sent is not a valid request object
+pub const INVALID_REQUEST: i64 = -32600;
+/// The method does not exist
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// Invalid method parameters
+pub const INVALID_PARAMS: i64 = -32602;
+/// No scan with the given ID exists
+pub const UNKNOWN_SCAN: i64 = -32001;
+/// The scan has not completed, so its results cannot be queried
+pub const SCAN_NOT_READY: i64 = -32002;
+
+/// Lifecycle state of a background scan
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanState {
+    Running,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+#[derive(Debug)]
+struct ScanJob {
+    state: ScanState,
+    root: PathBuf,
+    started: Instant,
+    elapsed: Option<Duration>,
+    entries: Vec<FileEntry>,
+    meta: Option<ScanMeta>,
+    error: Option<String>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl ScanJob {
+    fn status(&self, scan_id: u64) -> Value {
+        let meta = self.meta.as_ref();
+        json!({
+            "scan_id": scan_id,
+            "state": self.state,
+            "path": self.root,
+            "entries": self.entries.len(),
+            "total_size": meta.map(|m| m.total_size),
+            "run_id": meta.map(|m| m.run_id.as_str()),
+            "elapsed_ms": self.elapsed.unwrap_or_else(|| self.started.elapsed()).as_millis() as u64,
+            "error": self.error,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ScanParams {
+    path: PathBuf,
+    depth: Option<usize>,
+    #[serde(default)]
+    exclude: Vec<String>,
+    #[serde(default)]
+    no_cache: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScanIdParams {
+    scan_id: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct QueryParams {
+    scan_id: u64,
+    path: Option<PathBuf>,
+    depth: Option<usize>,
+    sort: Option<String>,
+    #[serde(default)]
+    reverse: bool,
+    limit: Option<usize>,
+}
+
+/// A JSON-RPC error, converted into the response's `error` member
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Dispatches JSON-RPC requests and owns the background scans.
+///
+/// `W` is where responses and notifications are written; `serve` uses stdout.
+pub struct RpcServer<W: Write + Send + 'static> {
+    base_args: Args,
+    out: Arc<Mutex<W>>,
+    scans: Arc<Mutex<HashMap<u64, ScanJob>>>,
+    workers: Mutex<Vec<JoinHandle<()>>>,
+    next_id: AtomicU64,
+}
+
+impl<W: Write + Send + 'static> RpcServer<W> {
+    /// Creates a server whose scans inherit options from `base_args`.
+    pub fn new(base_args: Args, out: W) -> Self {
+        Self {
+            base_args,
+            out: Arc::new(Mutex::new(out)),
+            scans: Arc::new(Mutex::new(HashMap::new())),
+            workers: Mutex::new(Vec::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Handles one line of input and returns the response, if any.
+    ///
+    /// Notifications (requests without an `id`) produce no response.
+    pub fn handle_line(&self, line: &str) -> Option<Value> {
+        let request: Value = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => return Some(error_response(Value::Null, PARSE_ERROR, &e.to_string())),
+        };
+
+        let id = request.get("id").cloned();
+        let method = match request.get("method").and_then(Value::as_str) {
+            Some(method) => method,
+            None => {
+                return Some(error_response(
+                    id.unwrap_or(Value::Null),
+                    INVALID_REQUEST,
+                    "Missing method",
+                ));
+            }
+        };
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+        let result = match method {
+            "scan" => parse_params(params).and_then(|p| self.scan(p)),
+            "status" => parse_params(params).and_then(|p: ScanIdParams| self.status(p.scan_id)),
+            "query" => parse_params(params).and_then(|p| self.query(p)),
+            "cancel" => parse_params(params).and_then(|p: ScanIdParams| self.cancel(p.scan_id)),
+            other => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method: {}", other),
+            )),
+        };
+
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => error_response(id, e.code, &e.message),
+        })
+    }
+
+    /// Writes a response or notification as one line.
+    pub fn send(&self, message: &Value) -> Result<()> {
+        send(&self.out, message)
+    }
+
+    /// Blocks until every background scan has finished.
+    pub fn wait(&self) {
+        let workers: Vec<JoinHandle<()>> = std::mem::take(&mut *lock(&self.workers));
+        for worker in workers {
+            let _ = worker.join();
+        }
+    }
+
+    fn scan(&self, params: ScanParams) -> Result<Value, RpcError> {
+        let mut args = self.base_args.clone();
+        args.path = params.path.clone();
+        args.depth = params.depth;
+        args.no_cache = params.no_cache || self.base_args.no_cache;
+        args.exclude.extend(params.exclude);
+
+        let matcher = build_exclude_matcher(&expand_exclude_patterns(&args.exclude))
+            .map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))?;
+
+        let scan_id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let cancel = Arc::new(AtomicBool::new(false));
+        lock(&self.scans).insert(
+            scan_id,
+            ScanJob {
+                state: ScanState::Running,
+                root: params.path,
+                started: Instant::now(),
+                elapsed: None,
+                entries: Vec::new(),
+                meta: None,
+                error: None,
+                cancel: Arc::clone(&cancel),
+            },
+        );
+
+        let scans = Arc::clone(&self.scans);
+        let out = Arc::clone(&self.out);
+        let worker = std::thread::spawn(move || {
+            let options = ScanOptions::from(&args);
+            let result = scan_files_and_dirs_cancellable(
+                &args.path, &options, &matcher, &args.sort, &cancel,
+            );
+
+            let status = {
+                let mut scans = lock(&scans);
+                let Some(job) = scans.get_mut(&scan_id) else {
+                    return;
+                };
+                job.elapsed = Some(job.started.elapsed());
+                match result {
+                    Ok(result) => {
+                        job.state = ScanState::Completed;
+                        job.entries = result.entries;
+                        job.meta = Some(result.meta);
+                    }
+                    Err(Error::Cancelled) => job.state = ScanState::Cancelled,
+                    Err(e) => {
+                        job.state = ScanState::Failed;
+                        job.error = Some(e.to_string());
+                    }
+                }
+                job.status(scan_id)
+            };
+
+            let notification =
+                json!({ "jsonrpc": "2.0", "method": "scan.finished", "params": status });
+            if let Err(e) = send(&out, &notification) {
+                log::error!("Failed to send scan.finished notification: {}", e);
+            }
+        });
+        lock(&self.workers).push(worker);
+
+        Ok(json!({ "scan_id": scan_id }))
+    }
+
+    fn status(&self, scan_id: u64) -> Result<Value, RpcError> {
+        lock(&self.scans)
+            .get(&scan_id)
+            .map(|job| job.status(scan_id))
+            .ok_or_else(|| unknown_scan(scan_id))
+    }
+
+    fn cancel(&self, scan_id: u64) -> Result<Value, RpcError> {
+        let scans = lock(&self.scans);
+        let job = scans.get(&scan_id).ok_or_else(|| unknown_scan(scan_id))?;
+        let running = job.state == ScanState::Running;
+        if running {
+            job.cancel.store(true, Ordering::Relaxed);
+        }
+        Ok(json!({ "cancelled": running }))
+    }
+
+    fn query(&self, params: QueryParams) -> Result<Value, RpcError> {
+        let sort = params
+            .sort
+            .as_deref()
+            .map(|s| {
+                s.split(',')
+                    .map(|key| SortKey::from_str(key.trim(), true))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()
+            .map_err(|e| RpcError::new(INVALID_PARAMS, e))?;
+
+        let scans = lock(&self.scans);
+        let job = scans
+            .get(&params.scan_id)
+            .ok_or_else(|| unknown_scan(params.scan_id))?;
+        if job.state != ScanState::Completed {
+            return Err(RpcError::new(
+                SCAN_NOT_READY,
+                format!("Scan {} has not completed", params.scan_id),
+            ));
+        }
+
+        let base = params.path.unwrap_or_else(|| job.root.clone());
+        let mut matches: Vec<FileEntry> = job
+            .entries
+            .iter()
+            .filter(|e| e.path.starts_with(&base))
+            .filter(|e| {
+                params
+                    .depth
+                    .is_none_or(|depth| path_depth(&base, &e.path) <= depth)
+            })
+            .cloned()
+            .collect();
+        drop(scans);
+
+        if let Some(sort) = sort {
+            sort_entries(&mut matches, &sort, params.reverse);
+        }
+        let total = matches.len();
+        if let Some(limit) = params.limit {
+            matches.truncate(limit);
+        }
+
+        Ok(json!({ "total": total, "entries": matches }))
+    }
+}
+
+/// Runs the JSON-RPC loop until `input` is exhausted.
+///
+/// After end of input, scans that are still running are allowed to finish so
+/// their `scan.finished` notifications are delivered.
+///
+/// # Arguments
+/// * `input` - Line-oriented request stream (stdin)
+/// * `output` - Response stream (stdout)
+/// * `base_args` - Options that scans inherit unless overridden per request
+pub fn serve<R: BufRead, W: Write + Send + 'static>(
+    input: R,
+    output: W,
+    base_args: &Args,
+) -> Result<()> {
+    let server = RpcServer::new(base_args.clone(), output);
+
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = server.handle_line(&line) {
+            server.send(&response)?;
+        }
+    }
+
+    server.wait();
+    Ok(())
+}
+
+fn parse_params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn unknown_scan(scan_id: u64) -> RpcError {
+    RpcError::new(UNKNOWN_SCAN, format!("Unknown scan_id: {}", scan_id))
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+fn send<W: Write>(out: &Mutex<W>, message: &Value) -> Result<()> {
+    let mut out = lock(out);
+    serde_json::to_writer(&mut *out, message)?;
+    out.write_all(b"\n")?;
+    out.flush()?;
+    Ok(())
+}
+
+fn lock<T>(m: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    m.lock().unwrap_or_else(|poison| poison.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn server() -> RpcServer<Vec<u8>> {
+        let args = Args::try_parse_from(["rudu", "--no-cache"]).unwrap();
+        RpcServer::new(args, Vec::new())
+    }
+
+    fn request(server: &RpcServer<Vec<u8>>, line: &str) -> Value {
+        server
+            .handle_line(line)
+            .expect("request should get a response")
+    }
+
+    #[test]
+    fn test_protocol_errors() {
+        let server = server();
+
+        let response = request(&server, "not json");
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+        assert_eq!(response["id"], Value::Null);
+
+        let response = request(&server, r#"{"jsonrpc":"2.0","id":1,"method":"nope"}"#);
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(response["id"], 1);
+
+        let response = request(&server, r#"{"jsonrpc":"2.0","id":2,"method":"scan"}"#);
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+
+        let response = request(
+            &server,
+            r#"{"jsonrpc":"2.0","id":3,"method":"status","params":{"scan_id":42}}"#,
+        );
+        assert_eq!(response["error"]["code"], UNKNOWN_SCAN);
+
+        // Notifications get no response
+        assert!(
+            server
+                .handle_line(r#"{"jsonrpc":"2.0","method":"status","params":{"scan_id":1}}"#)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_scan_status_and_query() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("sub").join("a.bin"), vec![0u8; 8192]).unwrap();
+        std::fs::write(dir.path().join("b.txt"), "hello").unwrap();
+
+        let server = server();
+        let scan = json!({
+            "jsonrpc": "2.0", "id": 1, "method": "scan",
+            "params": { "path": dir.path() },
+        });
+        let response = request(&server, &scan.to_string());
+        let scan_id = response["result"]["scan_id"].as_u64().unwrap();
+
+        server.wait();
+
+        let status = request(
+            &server,
+            &json!({"jsonrpc":"2.0","id":2,"method":"status","params":{"scan_id":scan_id}})
+                .to_string(),
+        );
+        assert_eq!(status["result"]["state"], "completed");
+        assert_eq!(status["result"]["entries"], 4);
+
+        let query = request(
+            &server,
+            &json!({
+                "jsonrpc": "2.0", "id": 3, "method": "query",
+                "params": { "scan_id": scan_id, "depth": 1, "sort": "size", "limit": 2 },
+            })
+            .to_string(),
+        );
+        assert_eq!(query["result"]["total"], 3);
+        let entries = query["result"]["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["path"], dir.path().to_string_lossy().as_ref());
+
+        // The finished notification was written to the output stream
+        let output = String::from_utf8(lock(&server.out).clone()).unwrap();
+        assert!(output.contains("\"method\":\"scan.finished\""));
+
+        // Cancelling a finished scan is a no-op
+        let cancel = request(
+            &server,
+            &json!({"jsonrpc":"2.0","id":4,"method":"cancel","params":{"scan_id":scan_id}})
+                .to_string(),
+        );
+        assert_eq!(cancel["result"]["cancelled"], false);
+    }
+}
@@ -5,7 +5,7 @@
 //! - Disk usage measurement using `libc::stat`
 //! - Parallel size aggregation for directories using `DashMap` and `rayon`
 //! - Filtering via glob-based exclude rules
-//! - Progress spinner via `indicatif`
+//! - Progress reporting through a [`ProgressObserver`], e.g. a terminal spinner
 //!
 //! The main entry point is [`scan_files_and_dirs`], which returns:
 //! - A unified list of [`FileEntry`] objects containing both files and directories
@@ -19,53 +19,64 @@
 //! - Directory sizes are accumulated efficiently using parent path caching
 //! - Single-pass processing reduces memory allocations and improves cache locality
 
-use crate::Args;
-use crate::cache::{CacheEntry, CacheEntryParams, load_cache, save_cache_with_mtime};
-use crate::cli::SortKey;
-use crate::data::{EntryType, FileEntry};
+use crate::cache::model::is_in_tree_cache_name;
+use crate::cache::{
+    CacheEntry, CacheEntryParams, CacheLocation, CachedRecord, MappedCache, open_cache,
+    open_previous_cache, save_cache_with_limit,
+};
+use crate::data::SortKey;
+use crate::data::{DirCounts, EntryStat, EntryType, FileEntry, MountPoint, ScanMeta};
+use crate::error::{Error, Result};
+use crate::largest::LargestFiles;
 use crate::memory::MemoryMonitor;
-use crate::metrics::{PhaseResult, PhaseTimer};
-use crate::utils::{disk_usage, get_dir_metadata, get_owner, path_depth, sort_entries};
-use anyhow::{Context, Result};
+use crate::metrics::{LatencyHistogram, LatencyStats, PhaseResult, PhaseTimer};
+use crate::options::ScanOptions;
+use crate::progress::{NoProgress, ProgressObserver, Spinner, progress_spinner};
+use crate::spill::{EntrySpill, SpilledEntries};
+use crate::utils::{
+    PathMatcher, build_exclude_matcher, disk_usage_and_cached_stat, disk_usage_and_stat,
+    expand_exclude_patterns, get_dir_metadata, get_owner, has_exclude_marker, mount_fs_type,
+    path_depth, sort_entries,
+};
+use crate::visit::EntryVisitor;
 use dashmap::DashMap;
-use indicatif::{ProgressBar, ProgressStyle};
+use rayon::ThreadPool;
 use rayon::prelude::*;
-use std::collections::HashMap;
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::ffi::OsStr;
+use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use walkdir::WalkDir;
 
 /// Recursively restores cached subdirectory entries for a directory cache hit.
 ///
-/// Replaces the previous O(n) per-hit loop over all cache entries with an O(depth)
-/// descent through a pre-built parent→children index, reducing the overall
-/// subtree-restoration cost from O(n×k) to O(n) across all cache hits.
+/// Children are read straight from the mapped cache's child index, so each
+/// restored directory costs one record read and no parent→children index has
+/// to be built up front.
 #[allow(clippy::too_many_arguments)]
 fn restore_subtree(
     root: &Path,
-    path: &Path,
-    children_index: &HashMap<PathBuf, Vec<PathBuf>>,
-    cache: &HashMap<PathBuf, CacheEntry>,
+    cache: &MappedCache,
+    record: &CachedRecord<'_>,
     max_depth: Option<usize>,
-    exclude_matcher: &globset::GlobSet,
+    exclude_matcher: &PathMatcher,
     exclude_patterns: &[String],
     dir_totals: &DashMap<PathBuf, u64>,
     directory_children: &DashMap<PathBuf, u64>,
     cached_dirs: &DashMap<PathBuf, CacheEntry>,
     new_cache_entries: &mut HashMap<PathBuf, CacheEntry>,
 ) {
-    let children = match children_index.get(path) {
-        Some(c) => c,
-        None => return,
-    };
-    for child_path in children {
-        let sub_depth = path_depth(root, child_path);
+    for child in cache.children(record) {
+        let child_path = child.path();
+        let sub_depth = path_depth(root, &child_path);
         if !max_depth.map(|d| sub_depth <= d).unwrap_or(true) {
             continue;
         }
-        if exclude_matcher.is_match(child_path) {
+        if exclude_matcher.is_match(&child_path) {
             continue;
         }
         if child_path.components().any(|c| {
@@ -75,27 +86,25 @@ fn restore_subtree(
         }) {
             continue;
         }
-        if let Some(cached_subentry) = cache.get(child_path) {
-            cached_dirs.insert(child_path.clone(), cached_subentry.clone());
-            new_cache_entries.insert(child_path.clone(), cached_subentry.clone());
-            dir_totals.insert(child_path.clone(), cached_subentry.size);
-            if let Some(inode_count) = cached_subentry.inode_cnt {
-                directory_children.insert(child_path.clone(), inode_count);
-            }
-            restore_subtree(
-                root,
-                child_path,
-                children_index,
-                cache,
-                max_depth,
-                exclude_matcher,
-                exclude_patterns,
-                dir_totals,
-                directory_children,
-                cached_dirs,
-                new_cache_entries,
-            );
+        let cached_subentry = child.to_entry();
+        dir_totals.insert(child_path.clone(), cached_subentry.size);
+        if let Some(inode_count) = cached_subentry.inode_cnt {
+            directory_children.insert(child_path.clone(), inode_count);
         }
+        cached_dirs.insert(child_path.clone(), cached_subentry.clone());
+        new_cache_entries.insert(child_path, cached_subentry);
+        restore_subtree(
+            root,
+            cache,
+            &child,
+            max_depth,
+            exclude_matcher,
+            exclude_patterns,
+            dir_totals,
+            directory_children,
+            cached_dirs,
+            new_cache_entries,
+        );
     }
 }
 
@@ -104,7 +113,8 @@ fn restore_subtree(
 pub enum MemoryLimitStatus {
     /// Scan completed normally without memory pressure
     Normal,
-    /// Scan completed but was nearing memory limit (disabled some features)
+    /// Scan completed but was nearing memory limit (disabled some features and
+    /// spilled the remaining files to disk)
     NearingLimit,
     /// Scan was terminated due to memory limit being exceeded
     MemoryLimitHit,
@@ -120,6 +130,16 @@ pub struct ScanResult {
     pub phase_timings: Vec<PhaseResult>,
     #[allow(dead_code)]
     pub memory_status: MemoryLimitStatus,
+    /// Filesystem call latency percentiles, populated only when profiling
+    pub fs_latency: BTreeMap<String, LatencyStats>,
+    /// Run-level facts handed to formatters and metrics sinks
+    pub meta: ScanMeta,
+    /// The largest files, largest first, with
+    /// [`ScanOptions::largest_files`]; empty otherwise
+    pub largest_files: Vec<FileEntry>,
+    /// Files measured after memory neared the limit, kept on disk rather than
+    /// in `entries`; see [`ScanResult::into_entries`]
+    pub spilled: Option<SpilledEntries>,
 }
 
 impl Default for ScanResult {
@@ -131,7 +151,186 @@ impl Default for ScanResult {
             memory_limit_hit: false,
             phase_timings: Vec::new(),
             memory_status: MemoryLimitStatus::Normal,
+            fs_latency: BTreeMap::new(),
+            meta: ScanMeta::default(),
+            largest_files: Vec::new(),
+            spilled: None,
+        }
+    }
+}
+
+impl ScanResult {
+    /// Returns the result, or [`Error::MemoryLimitExceeded`] if the memory
+    /// limit stopped the scan early, for callers that cannot use partial results
+    #[allow(dead_code)] // the CLI shows partial results; kept for library users
+    pub fn complete(self) -> Result<Self> {
+        if self.memory_limit_hit {
+            return Err(Error::MemoryLimitExceeded);
+        }
+        Ok(self)
+    }
+
+    /// Every entry of the scan in order, reading the [`spilled`](Self::spilled)
+    /// files back into memory; [`SpilledEntries::merge`] streams them instead.
+    #[allow(dead_code)] // the CLI streams spilled entries; kept for library users
+    pub fn into_entries(self) -> Result<Vec<FileEntry>> {
+        match self.spilled {
+            Some(spilled) => spilled
+                .merge(self.entries)
+                .and_then(|merged| merged.collect())
+                .map_err(Error::scan),
+            None => Ok(self.entries),
+        }
+    }
+}
+
+/// Filesystem call latencies sampled during a profiled scan
+///
+/// `stat` covers every metadata call rudu issues itself. `readdir` is measured
+/// around the walker and covers opening a directory plus reading its first
+/// batch of entries, excluding any `stat` time spent in the cache check.
+#[derive(Debug, Default)]
+struct FsLatency {
+    stat: LatencyHistogram,
+    readdir: LatencyHistogram,
+}
+
+impl FsLatency {
+    fn into_stats(self) -> BTreeMap<String, LatencyStats> {
+        [("stat", self.stat), ("readdir", self.readdir)]
+            .into_iter()
+            .filter_map(|(name, histogram)| Some((name.to_string(), histogram.stats()?)))
+            .collect()
+    }
+}
+
+/// Returns true if `entry` is a directory below the root that opted out of
+/// scanning with a `.ruduignore` or `CACHEDIR.TAG` marker
+fn is_marked_excluded(entry: &walkdir::DirEntry, options: &ScanOptions) -> bool {
+    !options.no_exclude_markers
+        && entry.depth() > 0
+        && entry.file_type().is_dir()
+        && has_exclude_marker(entry.path())
+}
+
+/// Returns true if `entry` is the root's in-tree cache file (or its temporary
+/// file), which must not count towards the root or change between runs
+fn is_in_tree_cache_file(entry: &walkdir::DirEntry, in_tree_cache: bool) -> bool {
+    in_tree_cache && entry.depth() == 1 && is_in_tree_cache_name(entry.file_name())
+}
+
+/// Runs a metadata call, recording its latency when profiling
+fn timed_stat<T>(latency: Option<&FsLatency>, f: impl FnOnce() -> T) -> T {
+    match latency {
+        Some(latency) => latency.stat.time(f),
+        None => f(),
+    }
+}
+
+/// Disk usage, mtime and (with extended metadata) further `stat` fields of a file
+type FileStat = (u64, Option<u64>, Option<Box<EntryStat>>);
+
+/// Measures a file from one `stat()` call, from the attribute cache with
+/// `options.cached_attrs`, keeping its [`EntryStat`] only when extended
+/// metadata was asked for
+fn stat_file(path: &Path, options: &ScanOptions) -> FileStat {
+    let (size, mtime, stat) = if options.cached_attrs {
+        disk_usage_and_cached_stat(path)
+    } else {
+        disk_usage_and_stat(path)
+    };
+    (
+        size,
+        mtime,
+        stat.filter(|_| options.extended_metadata).map(Box::new),
+    )
+}
+
+/// Offers a measured file to `largest`, building its entry (and looking up
+/// its owner) only if it ranks among the largest so far.
+fn offer_largest(
+    largest: &mut LargestFiles,
+    options: &ScanOptions,
+    path: &Path,
+    entry_type: EntryType,
+    (size, mtime, stat): (u64, Option<u64>, &Option<Box<EntryStat>>),
+) {
+    if !largest.would_keep(size, path) {
+        return;
+    }
+    largest.offer(FileEntry {
+        path: path.to_path_buf(),
+        size,
+        owner: if options.show_owner {
+            get_owner(path)
+        } else {
+            None
+        },
+        inodes: None,
+        entry_type,
+        previous_size: None,
+        mtime,
+        stat: stat.clone(),
+    });
+}
+
+/// Finds mountpoints during a depth-first walk by comparing each directory's
+/// device with its parent's, and skips those matching `--exclude-mounts`
+struct MountTracker {
+    /// Raw `--exclude-mounts` patterns, compared against directory names
+    names: Vec<String>,
+    /// The same patterns as globs over the full path
+    matcher: PathMatcher,
+    /// Device of each directory on the path from the root to the current entry
+    devices: Vec<u64>,
+    mounts: Vec<MountPoint>,
+}
+
+impl MountTracker {
+    fn new(options: &ScanOptions) -> Result<Self> {
+        Ok(Self {
+            names: options.exclude_mounts.clone(),
+            matcher: build_exclude_matcher(&expand_exclude_patterns(&options.exclude_mounts))?,
+            devices: Vec::new(),
+            mounts: Vec::new(),
+        })
+    }
+
+    /// Records a walked entry; returns false if it is a mountpoint whose
+    /// filesystem is excluded
+    fn admit(&mut self, entry: &walkdir::DirEntry) -> bool {
+        if !entry.file_type().is_dir() {
+            return true;
+        }
+        self.devices.truncate(entry.depth());
+        let parent = self.devices.last().copied();
+        // A directory that cannot be stat'd is assumed to be on its parent's filesystem
+        let Some(device) = entry.metadata().ok().map(|m| m.dev()).or(parent) else {
+            return true;
+        };
+        if parent.is_some_and(|parent| parent != device) {
+            let path = entry.path();
+            let excluded = self.matcher.is_match(path)
+                || self
+                    .names
+                    .iter()
+                    .any(|name| entry.file_name() == OsStr::new(name));
+            self.mounts.push(MountPoint {
+                path: path.to_path_buf(),
+                device,
+                fs_type: mount_fs_type(path),
+                excluded,
+            });
+            if excluded {
+                return false;
+            }
         }
+        self.devices.push(device);
+        true
+    }
+
+    fn into_mounts(self) -> Vec<MountPoint> {
+        self.mounts
     }
 }
 
@@ -139,8 +338,10 @@ impl Default for ScanResult {
 #[derive(Debug)]
 struct ScanJob {
     path: PathBuf,
-    is_file: bool,
+    entry_type: EntryType,
     size: u64,
+    mtime: Option<u64>,
+    stat: Option<Box<EntryStat>>,
     parent_paths: Vec<PathBuf>,
 }
 
@@ -154,20 +355,19 @@ struct ScanJob {
 ///   only stat'd once.
 /// - The rayon scope is used exclusively for accumulation; FileEntry construction runs
 ///   after the scope exits (guaranteeing all accumulation tasks are complete).
+#[allow(clippy::too_many_arguments)]
 fn scan_with_work_stealing(
     root: &Path,
-    args: &Args,
-    exclude_matcher: &globset::GlobSet,
-    sort_key: SortKey,
-) -> Result<ScanResult> {
-    let pb = ProgressBar::new_spinner();
-    pb.set_style(
-        ProgressStyle::default_spinner()
-            .tick_strings(&["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"])
-            .template("{spinner} Scanning files with work-stealing... [{elapsed}]")
-            .context("Failed to set progress template")?,
-    );
-    pb.enable_steady_tick(Duration::from_millis(100));
+    options: &ScanOptions,
+    exclude_matcher: &PathMatcher,
+    sort_keys: Option<&[SortKey]>,
+    observer: &dyn ProgressObserver,
+    visitor: Option<&dyn EntryVisitor>,
+    pool: Option<&ThreadPool>,
+) -> anyhow::Result<ScanResult> {
+    let meta = ScanMeta::start(root, options);
+    let in_tree_cache = options.cache_location.resolve(root) == CacheLocation::InTree;
+    let mut mounts = MountTracker::new(options)?;
 
     // Single WalkDir pass — reused for both accumulation and FileEntry creation.
     let walker_entries: Vec<walkdir::DirEntry> = WalkDir::new(root)
@@ -175,14 +375,25 @@ fn scan_with_work_stealing(
         .into_iter()
         .filter_entry(|e| {
             !exclude_matcher.is_match(e.path())
-                && !e
-                    .path()
-                    .components()
-                    .any(|c| args.exclude.iter().any(|x| c.as_os_str() == OsStr::new(x)))
+                && !e.path().components().any(|c| {
+                    options
+                        .exclude
+                        .iter()
+                        .any(|x| c.as_os_str() == OsStr::new(x))
+                })
+                && !is_marked_excluded(e, options)
+                && !is_in_tree_cache_file(e, in_tree_cache)
+                && mounts.admit(e)
         })
-        .filter_map(|e| {
-            pb.tick();
-            e.ok()
+        .filter_map(|e| match e {
+            Ok(e) => {
+                observer.on_entry(e.path());
+                Some(e)
+            }
+            Err(err) => {
+                observer.on_error(err.path(), &err);
+                None
+            }
         })
         .collect();
 
@@ -201,7 +412,7 @@ fn scan_with_work_stealing(
         .map(|(path, _)| path.clone())
         .collect();
 
-    eprintln!(
+    log::info!(
         "🔍 Found {} large directories (>10k entries) to process with work-stealing",
         large_dirs.len()
     );
@@ -210,34 +421,77 @@ fn scan_with_work_stealing(
     let dir_totals: DashMap<PathBuf, u64> = DashMap::new();
     let directory_children: DashMap<PathBuf, u64> = DashMap::new();
     // Per-file sizes stored here so we never call disk_usage twice for the same file.
-    let file_sizes: DashMap<PathBuf, u64> = DashMap::new();
+    let file_sizes: DashMap<PathBuf, FileStat> = DashMap::new();
 
     // Accumulation phase: the scope guarantees all spawned tasks complete before we
     // proceed to FileEntry construction, so dir_totals / file_sizes are fully populated.
-    rayon::scope(|scope| {
-        // Spawn a task per large directory so its entries are processed in parallel
-        // with the "remaining" par_iter below, using rayon's work-stealing scheduler.
-        for large_dir in &large_dirs {
-            let large_dir_entries: Vec<walkdir::DirEntry> = walker_entries
-                .iter()
-                .filter(|e| e.path().parent() == Some(large_dir.as_path()))
-                .cloned()
-                .collect();
-
-            let dir_totals_ref = &dir_totals;
-            let file_sizes_ref = &file_sizes;
-            let directory_children_ref = &directory_children;
-            let args_ref = args;
+    in_pool(pool, || {
+        rayon::scope(|scope| {
+            // Spawn a task per large directory so its entries are processed in parallel
+            // with the "remaining" par_iter below, using rayon's work-stealing scheduler.
+            for large_dir in &large_dirs {
+                let large_dir_entries: Vec<walkdir::DirEntry> = walker_entries
+                    .iter()
+                    .filter(|e| e.path().parent() == Some(large_dir.as_path()))
+                    .cloned()
+                    .collect();
+
+                let dir_totals_ref = &dir_totals;
+                let file_sizes_ref = &file_sizes;
+                let directory_children_ref = &directory_children;
+                let options_ref = options;
+
+                scope.spawn(move |_| {
+                    large_dir_entries
+                        .par_iter()
+                        .with_min_len(options_ref.stat_batch)
+                        .for_each(|entry| {
+                            let path = entry.path().to_path_buf();
+                            if !entry.file_type().is_dir() {
+                                let (size, mtime, stat) = stat_file(&path, options_ref);
+                                file_sizes_ref.insert(path.clone(), (size, mtime, stat));
+                                let mut cur = path.parent();
+                                while let Some(p) = cur {
+                                    dir_totals_ref
+                                        .entry(p.to_path_buf())
+                                        .and_modify(|v| *v += size)
+                                        .or_insert(size);
+                                    if p == root {
+                                        break;
+                                    }
+                                    cur = p.parent();
+                                }
+                            }
+                            if options_ref.show_inodes
+                                && let Some(parent) = path.parent()
+                            {
+                                *directory_children_ref
+                                    .entry(parent.to_path_buf())
+                                    .or_insert(0) += 1;
+                            }
+                        });
+                });
+            }
 
-            scope.spawn(move |_| {
-                large_dir_entries.par_iter().for_each(|entry| {
+            // Process the remaining entries (those not in large directories) in parallel.
+            // This runs concurrently with the scope.spawn'd tasks above via work-stealing.
+            walker_entries
+                .par_iter()
+                .with_min_len(options.stat_batch)
+                .filter(|e| {
+                    e.path()
+                        .parent()
+                        .map(|p| !large_dirs.contains(p))
+                        .unwrap_or(true)
+                })
+                .for_each(|entry| {
                     let path = entry.path().to_path_buf();
-                    if entry.file_type().is_file() {
-                        let size = disk_usage(&path);
-                        file_sizes_ref.insert(path.clone(), size);
+                    if !entry.file_type().is_dir() {
+                        let (size, mtime, stat) = stat_file(&path, options);
+                        file_sizes.insert(path.clone(), (size, mtime, stat));
                         let mut cur = path.parent();
                         while let Some(p) = cur {
-                            dir_totals_ref
+                            dir_totals
                                 .entry(p.to_path_buf())
                                 .and_modify(|v| *v += size)
                                 .or_insert(size);
@@ -247,99 +501,91 @@ fn scan_with_work_stealing(
                             cur = p.parent();
                         }
                     }
-                    if args_ref.show_inodes {
-                        if let Some(parent) = path.parent() {
-                            *directory_children_ref
-                                .entry(parent.to_path_buf())
-                                .or_insert(0) += 1;
-                        }
+                    if options.show_inodes
+                        && let Some(parent) = path.parent()
+                    {
+                        *directory_children.entry(parent.to_path_buf()).or_insert(0) += 1;
                     }
                 });
-            });
-        }
+        })
+    });
 
-        // Process the remaining entries (those not in large directories) in parallel.
-        // This runs concurrently with the scope.spawn'd tasks above via work-stealing.
+    // Build FileEntry objects from the already-collected walker_entries.
+    // Sizes come from file_sizes (populated above) — no second disk_usage call.
+    let mut final_entries: Vec<FileEntry> = in_pool(pool, || {
         walker_entries
             .par_iter()
-            .filter(|e| {
-                e.path()
-                    .parent()
-                    .map(|p| !large_dirs.contains(p))
-                    .unwrap_or(true)
-            })
-            .for_each(|entry| {
+            .map(|entry| {
                 let path = entry.path().to_path_buf();
-                if entry.file_type().is_file() {
-                    let size = disk_usage(&path);
-                    file_sizes.insert(path.clone(), size);
-                    let mut cur = path.parent();
-                    while let Some(p) = cur {
-                        dir_totals
-                            .entry(p.to_path_buf())
-                            .and_modify(|v| *v += size)
-                            .or_insert(size);
-                        if p == root {
-                            break;
-                        }
-                        cur = p.parent();
+                let file_entry = if !entry.file_type().is_dir() {
+                    let (size, mtime, stat) = file_sizes
+                        .remove(&path)
+                        .map(|(_, v)| v)
+                        .unwrap_or((0, None, None));
+                    FileEntry {
+                        path: path.clone(),
+                        size,
+                        owner: if options.show_owner {
+                            get_owner(&path)
+                        } else {
+                            None
+                        },
+                        inodes: None,
+                        entry_type: EntryType::from_file_type(entry.file_type()),
+                        previous_size: None,
+                        mtime,
+                        stat,
                     }
-                }
-                if args.show_inodes {
-                    if let Some(parent) = path.parent() {
-                        *directory_children.entry(parent.to_path_buf()).or_insert(0) += 1;
+                } else {
+                    let size = dir_totals.get(&path).map(|v| *v).unwrap_or(0);
+                    let metadata = entry.metadata().ok();
+                    let inode_count = if options.show_inodes {
+                        directory_children.get(&path).map(|v| *v).unwrap_or(0)
+                    } else {
+                        0
+                    };
+                    FileEntry {
+                        path: path.clone(),
+                        size,
+                        owner: if options.show_owner {
+                            get_owner(&path)
+                        } else {
+                            None
+                        },
+                        inodes: if options.show_inodes {
+                            Some(inode_count)
+                        } else {
+                            None
+                        },
+                        entry_type: EntryType::Dir,
+                        previous_size: None,
+                        mtime: metadata.as_ref().map(|m| m.mtime() as u64),
+                        stat: metadata
+                            .as_ref()
+                            .filter(|_| options.extended_metadata)
+                            .map(|m| Box::new(EntryStat::from_metadata(m))),
                     }
+                };
+                if let Some(visitor) = visitor {
+                    visitor.visit(&file_entry);
                 }
-            });
+                file_entry
+            })
+            .collect()
     });
 
-    pb.finish_with_message("Work-stealing scan complete");
-
-    // Build FileEntry objects from the already-collected walker_entries.
-    // Sizes come from file_sizes (populated above) — no second disk_usage call.
-    let mut final_entries: Vec<FileEntry> = walker_entries
-        .par_iter()
-        .map(|entry| {
-            let path = entry.path().to_path_buf();
-            if entry.file_type().is_file() {
-                FileEntry {
-                    path: path.clone(),
-                    size: file_sizes.get(&path).map(|v| *v).unwrap_or(0),
-                    owner: if args.show_owner {
-                        get_owner(&path)
-                    } else {
-                        None
-                    },
-                    inodes: None,
-                    entry_type: EntryType::File,
-                }
-            } else {
-                let size = dir_totals.get(&path).map(|v| *v).unwrap_or(0);
-                let inode_count = if args.show_inodes {
-                    directory_children.get(&path).map(|v| *v).unwrap_or(0)
-                } else {
-                    0
-                };
-                FileEntry {
-                    path: path.clone(),
-                    size,
-                    owner: if args.show_owner {
-                        get_owner(&path)
-                    } else {
-                        None
-                    },
-                    inodes: if args.show_inodes {
-                        Some(inode_count)
-                    } else {
-                        None
-                    },
-                    entry_type: EntryType::Dir,
-                }
+    let largest_files = options.largest_files.map_or_else(Vec::new, |n| {
+        let mut largest = LargestFiles::new(n);
+        for entry in final_entries.iter().filter(|e| !e.entry_type.is_dir()) {
+            if largest.would_keep(entry.size, &entry.path) {
+                largest.offer(entry.clone());
             }
-        })
-        .collect();
-
-    sort_entries(&mut final_entries, sort_key);
+        }
+        largest.into_sorted()
+    });
+    sort_scan_entries(&mut final_entries, sort_keys, options);
+    let mut meta = meta.finish(&final_entries, 0, 0, false);
+    meta.mounts = mounts.into_mounts();
 
     Ok(ScanResult {
         entries: final_entries,
@@ -348,9 +594,416 @@ fn scan_with_work_stealing(
         memory_limit_hit: false,
         phase_timings: Vec::new(),
         memory_status: MemoryLimitStatus::Normal,
+        fs_latency: BTreeMap::new(),
+        meta,
+        largest_files,
+        spilled: None,
     })
 }
 
+/// Sorts the entries of a scan by `sort_keys`.
+///
+/// With `options.deterministic`, ties are broken by full path and entries
+/// are sorted by path even without keys, so neither hash map iteration in
+/// the scan and cache nor the order of parallel work shows in the result.
+pub(crate) fn sort_scan_entries(
+    entries: &mut [FileEntry],
+    sort_keys: Option<&[SortKey]>,
+    options: &ScanOptions,
+) {
+    if let Some(keys) = scan_sort_keys(sort_keys, options) {
+        sort_entries(entries, &keys, options.reverse);
+    }
+}
+
+/// The keys [`sort_scan_entries`] sorts by, or `None` to keep walk order
+fn scan_sort_keys(sort_keys: Option<&[SortKey]>, options: &ScanOptions) -> Option<Vec<SortKey>> {
+    match sort_keys {
+        Some(keys) if options.deterministic && !keys.contains(&SortKey::Name) => {
+            Some(keys.iter().copied().chain([SortKey::Name]).collect())
+        }
+        Some(keys) => Some(keys.to_vec()),
+        None if options.deterministic => Some(vec![SortKey::Name]),
+        None => None,
+    }
+}
+
+/// Runs `op` on `pool`, or on the current pool (Rayon's global pool outside
+/// of one) without a pool
+fn in_pool<R: Send>(pool: Option<&ThreadPool>, op: impl FnOnce() -> R + Send) -> R {
+    match pool {
+        Some(pool) => pool.install(op),
+        None => op(),
+    }
+}
+
+/// Whether a scan with `options` and no memory monitor or cancellation flag
+/// uses the work-stealing scanner
+fn uses_work_stealing(options: &ScanOptions) -> bool {
+    options.threads_strategy == crate::thread_pool::ThreadPoolStrategy::WorkStealingUneven
+}
+
+/// A configurable scan for embedding rudu.
+///
+/// Unlike the `scan_files_and_dirs*` functions, which draw a terminal
+/// spinner, a `Scanner` reports progress only to the [`ProgressObserver`]
+/// it is given, so GUIs and daemons can show it their own way:
+///
+/// ```no_run
+/// use rudu::ScanOptions;
+/// use rudu::progress::ProgressObserver;
+/// use rudu::scan::Scanner;
+/// use std::path::Path;
+/// use std::sync::atomic::{AtomicU64, Ordering};
+///
+/// #[derive(Default)]
+/// struct Counter(AtomicU64);
+///
+/// impl ProgressObserver for Counter {
+///     fn on_entry(&self, _path: &Path) {
+///         self.0.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// let counter = Counter::default();
+/// let result = Scanner::new(ScanOptions::new().no_cache(true))
+///     .observer(&counter)
+///     .scan(Path::new("/data"))?;
+/// println!("{} entries walked", counter.0.load(Ordering::Relaxed));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+///
+/// Without [`Scanner::exclude_matcher`] the exclusions are compiled from
+/// `options.exclude`; without [`Scanner::sort`] entries are left unsorted,
+/// or sorted by path with `options.deterministic`.
+/// A scan with a memory monitor or cancellation flag always uses the
+/// incremental scanner, even with the work-stealing strategy. With
+/// [`Scanner::thread_pool`] the scan never touches the global pool, and
+/// `threads_strategy` only selects the scanner.
+pub struct Scanner<'a> {
+    options: ScanOptions,
+    exclude_matcher: Option<&'a PathMatcher>,
+    sort_keys: Option<&'a [SortKey]>,
+    observer: &'a dyn ProgressObserver,
+    visitor: Option<&'a dyn EntryVisitor>,
+    monitor: Option<Arc<Mutex<MemoryMonitor>>>,
+    cancel: Option<&'a AtomicBool>,
+    pool: Option<&'a ThreadPool>,
+}
+
+impl<'a> Scanner<'a> {
+    /// A scanner with `options` that reports no progress
+    pub fn new(options: ScanOptions) -> Self {
+        Self {
+            options,
+            exclude_matcher: None,
+            sort_keys: None,
+            observer: &NoProgress,
+            visitor: None,
+            monitor: None,
+            cancel: None,
+            pool: None,
+        }
+    }
+
+    /// Skips paths matching `matcher`, e.g. one that also holds regexes
+    pub fn exclude_matcher(mut self, matcher: &'a PathMatcher) -> Self {
+        self.exclude_matcher = Some(matcher);
+        self
+    }
+
+    /// Sorts the entries by `keys`, ties broken by later keys
+    pub fn sort(mut self, keys: &'a [SortKey]) -> Self {
+        self.sort_keys = Some(keys);
+        self
+    }
+
+    /// Reports progress to `observer`
+    pub fn observer(mut self, observer: &'a dyn ProgressObserver) -> Self {
+        self.observer = observer;
+        self
+    }
+
+    /// Hands every entry to `visitor` as the scan aggregates it
+    #[allow(dead_code)] // the CLI has no custom statistics; kept for library users
+    pub fn visitor(mut self, visitor: &'a dyn EntryVisitor) -> Self {
+        self.visitor = Some(visitor);
+        self
+    }
+
+    /// Checks memory use with `monitor`: near its limit files are spilled to
+    /// disk, and at the limit the scan stops early
+    pub fn memory_monitor(mut self, monitor: Arc<Mutex<MemoryMonitor>>) -> Self {
+        self.monitor = Some(monitor);
+        self
+    }
+
+    /// Stops the scan with an error once `cancel` is set
+    pub fn cancel_flag(mut self, cancel: &'a AtomicBool) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Runs the parallel parts of the scan on `pool` instead of Rayon's
+    /// global pool, so embedding applications keep control of their thread
+    /// budget. The walk itself, and progress events, stay on the calling
+    /// thread.
+    #[allow(dead_code)] // the CLI configures the global pool; kept for library users
+    pub fn thread_pool(mut self, pool: &'a ThreadPool) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// Scans `root`.
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidPattern`] if the exclude patterns are invalid,
+    /// [`Error::Cancelled`] if the scan was cancelled, or [`Error::Scan`] if
+    /// the root could not be scanned.
+    pub fn scan(&self, root: &Path) -> Result<ScanResult> {
+        let built;
+        let exclude_matcher = match self.exclude_matcher {
+            Some(matcher) => matcher,
+            None => {
+                built = build_exclude_matcher(&expand_exclude_patterns(&self.options.exclude))?;
+                &built
+            }
+        };
+        let result =
+            if self.monitor.is_none() && self.cancel.is_none() && uses_work_stealing(&self.options)
+            {
+                scan_with_work_stealing(
+                    root,
+                    &self.options,
+                    exclude_matcher,
+                    self.sort_keys,
+                    self.observer,
+                    self.visitor,
+                    self.pool,
+                )
+            } else {
+                scan_files_and_dirs_with_monitor(
+                    root,
+                    &self.options,
+                    exclude_matcher,
+                    self.sort_keys,
+                    self.monitor.clone(),
+                    self.cancel,
+                    self.observer,
+                    self.visitor,
+                    self.pool,
+                )
+            };
+        result.map_err(Error::scan)
+    }
+}
+
+/// Scans `root` lazily, yielding entries as their subtrees complete.
+///
+/// Files are yielded as they are reached and each directory right after
+/// the last entry below it, with its total size, so consumers can process
+/// results incrementally while memory stays proportional to the depth of
+/// the tree rather than its size. The root comes last.
+///
+/// Like the other scans this honours the exclusions, mount and marker
+/// options and `show_owner`/`show_inodes` (direct children per directory),
+/// and yields every entry regardless of `depth`; with `summarize` only the
+/// root is yielded. The cache is neither read nor written, and entries are
+/// in walk order rather than sorted.
+///
+/// Unreadable entries are yielded as errors and the walk carries on; an
+/// invalid exclude pattern is yielded as the only item.
+///
+/// # Example
+/// ```no_run
+/// use rudu::ScanOptions;
+/// use rudu::scan::scan_iter;
+/// use std::path::Path;
+///
+/// let options = ScanOptions::new().exclude([".git"]);
+/// for entry in scan_iter(Path::new("/data"), &options) {
+///     let entry = entry?;
+///     println!("{}\t{}", entry.size, entry.path.display());
+/// }
+/// # Ok::<(), rudu::Error>(())
+/// ```
+#[allow(dead_code)] // the CLI streams with its own matcher; kept for library users
+pub fn scan_iter(
+    root: &Path,
+    options: &ScanOptions,
+) -> impl Iterator<Item = Result<FileEntry>> + Send + use<> {
+    walk_iter(
+        root,
+        options,
+        build_exclude_matcher(&expand_exclude_patterns(&options.exclude)),
+    )
+}
+
+/// Like [`scan_iter`], but skips the paths matched by `exclude_matcher`,
+/// which may also hold regular expressions, instead of building a matcher
+/// from `options.exclude` (directory names in it are still skipped).
+pub fn scan_iter_matching(
+    root: &Path,
+    options: &ScanOptions,
+    exclude_matcher: &PathMatcher,
+) -> impl Iterator<Item = Result<FileEntry>> + Send + use<> {
+    walk_iter(root, options, Ok(exclude_matcher.clone()))
+}
+
+fn walk_iter(
+    root: &Path,
+    options: &ScanOptions,
+    matcher: Result<PathMatcher>,
+) -> impl Iterator<Item = Result<FileEntry>> + Send + use<> {
+    let walker = matcher
+        .and_then(|matcher| Ok((matcher, MountTracker::new(options)?)))
+        .map(|(matcher, mut mounts)| {
+            let filter_options = options.clone();
+            let in_tree_cache = options.cache_location.resolve(root) == CacheLocation::InTree;
+            WalkDir::new(root)
+                .follow_links(false)
+                .into_iter()
+                .filter_entry(move |e| {
+                    !matcher.is_match(e.path())
+                        && !e.path().components().any(|c| {
+                            filter_options
+                                .exclude
+                                .iter()
+                                .any(|x| c.as_os_str() == OsStr::new(x))
+                        })
+                        && !is_marked_excluded(e, &filter_options)
+                        && !is_in_tree_cache_file(e, in_tree_cache)
+                        && mounts.admit(e)
+                })
+        });
+    let (walker, error) = match walker {
+        Ok(walker) => (Some(walker), None),
+        Err(err) => (None, Some(err)),
+    };
+    let options = options.clone();
+    error
+        .map(Err)
+        .into_iter()
+        .chain(walker.into_iter().flat_map(move |walker| ScanIter {
+            walker,
+            options: options.clone(),
+            open: Vec::new(),
+            ready: VecDeque::new(),
+        }))
+}
+
+/// A directory of [`scan_iter`] whose subtree is still being walked
+struct OpenDir {
+    path: PathBuf,
+    depth: usize,
+    size: u64,
+    children: u64,
+    mtime: Option<u64>,
+    stat: Option<Box<EntryStat>>,
+}
+
+/// The state of a [`scan_iter`] walk
+struct ScanIter<I> {
+    walker: I,
+    options: ScanOptions,
+    /// Directories on the path from the root to the current entry
+    open: Vec<OpenDir>,
+    /// Entries completed but not yet yielded
+    ready: VecDeque<FileEntry>,
+}
+
+impl<I> ScanIter<I> {
+    /// Completes the open directories at `depth` or deeper
+    fn close_to(&mut self, depth: usize) {
+        while self.open.last().is_some_and(|dir| dir.depth >= depth) {
+            let Some(dir) = self.open.pop() else { break };
+            if let Some(parent) = self.open.last_mut() {
+                parent.size += dir.size;
+            }
+            if self.options.summarize && dir.depth > 0 {
+                continue;
+            }
+            self.ready.push_back(FileEntry {
+                owner: self
+                    .options
+                    .show_owner
+                    .then(|| get_owner(&dir.path))
+                    .flatten(),
+                inodes: self.options.show_inodes.then_some(dir.children),
+                path: dir.path,
+                size: dir.size,
+                entry_type: EntryType::Dir,
+                previous_size: None,
+                mtime: dir.mtime,
+                stat: dir.stat,
+            });
+        }
+    }
+
+    /// Records a walked entry, queueing it if it is a file
+    fn visit(&mut self, entry: walkdir::DirEntry) {
+        let depth = entry.depth();
+        self.close_to(depth);
+        if let Some(parent) = self.open.last_mut() {
+            parent.children += 1;
+        }
+        if entry.file_type().is_dir() {
+            let metadata = entry.metadata().ok();
+            self.open.push(OpenDir {
+                depth,
+                size: 0,
+                children: 0,
+                mtime: metadata.as_ref().map(|m| m.mtime() as u64),
+                stat: metadata
+                    .as_ref()
+                    .filter(|_| self.options.extended_metadata)
+                    .map(|m| Box::new(EntryStat::from_metadata(m))),
+                path: entry.into_path(),
+            });
+            return;
+        }
+        let entry_type = EntryType::from_file_type(entry.file_type());
+        let path = entry.into_path();
+        let (size, mtime, stat) = stat_file(&path, &self.options);
+        if let Some(parent) = self.open.last_mut() {
+            parent.size += size;
+        }
+        if !self.options.summarize {
+            self.ready.push_back(FileEntry {
+                owner: self.options.show_owner.then(|| get_owner(&path)).flatten(),
+                path,
+                size,
+                inodes: None,
+                entry_type,
+                previous_size: None,
+                mtime,
+                stat,
+            });
+        }
+    }
+}
+
+impl<I> Iterator for ScanIter<I>
+where
+    I: Iterator<Item = walkdir::Result<walkdir::DirEntry>>,
+{
+    type Item = Result<FileEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(entry) = self.ready.pop_front() {
+                return Some(Ok(entry));
+            }
+            match self.walker.next() {
+                Some(Ok(entry)) => self.visit(entry),
+                Some(Err(err)) => return Some(Err(Error::walk(err))),
+                None if self.open.is_empty() => return None,
+                None => self.close_to(0),
+            }
+        }
+    }
+}
+
 /// Recursively scans a directory tree and returns a list of file and directory entries.
 ///
 /// This function performs a comprehensive file system scan, including:
@@ -363,9 +1016,9 @@ fn scan_with_work_stealing(
 ///
 /// # Arguments
 /// * `root` - The root path to start scanning from
-/// * `args` - Command line arguments controlling scan behavior
+/// * `options` - Options controlling scan behavior
 /// * `exclude_matcher` - Compiled glob patterns for excluding files/directories
-/// * `sort_key` - How to sort the resulting entries (by name or size)
+/// * `sort_keys` - How to sort the resulting entries (by name or size, ties by later keys)
 ///
 /// # Returns
 /// * `Result<Vec<FileEntry>>` - A vector of file and directory entries on success
@@ -377,17 +1030,21 @@ fn scan_with_work_stealing(
 /// - File system access errors occur during scanning
 pub fn scan_files_and_dirs(
     root: &Path,
-    args: &Args,
-    exclude_matcher: &globset::GlobSet,
-    sort_key: SortKey,
+    options: &ScanOptions,
+    exclude_matcher: &PathMatcher,
+    sort_keys: &[SortKey],
 ) -> Result<ScanResult> {
-    // Use work-stealing strategy for uneven trees if selected
-    if args.threads_strategy == crate::thread_pool::ThreadPoolStrategy::WorkStealingUneven {
-        return scan_with_work_stealing(root, args, exclude_matcher, sort_key);
+    // Use incremental scanning by default (unless work-stealing is selected)
+    if !uses_work_stealing(options) {
+        return scan_files_and_dirs_incremental(root, options, exclude_matcher, sort_keys);
     }
 
-    // Use incremental scanning by default (unless work-stealing is selected)
-    scan_files_and_dirs_incremental(root, args, exclude_matcher, sort_key)
+    let spinner = Spinner::new("Scanning files with work-stealing...")?;
+    Scanner::new(options.clone())
+        .exclude_matcher(exclude_matcher)
+        .sort(sort_keys)
+        .observer(&spinner)
+        .scan(root)
 }
 
 /// Scan files and directories with memory monitoring support
@@ -399,21 +1056,30 @@ pub fn scan_files_and_dirs(
 ///
 /// # Arguments
 /// * `root` - The root path to start scanning from
-/// * `args` - Command line arguments controlling scan behavior
+/// * `options` - Options controlling scan behavior
 /// * `exclude_matcher` - Compiled glob patterns for excluding files/directories
-/// * `sort_key` - How to sort the resulting entries (by name or size)
+/// * `sort_keys` - How to sort the resulting entries (by name or size, ties by later keys)
 /// * `monitor` - Optional memory monitor for limiting memory usage
 ///
 /// # Returns
 /// * `Result<ScanResult>` - Scan results with memory status information
 pub fn scan_files_and_dirs_with_memory_monitor(
     root: &Path,
-    args: &Args,
-    exclude_matcher: &globset::GlobSet,
-    sort_key: SortKey,
+    options: &ScanOptions,
+    exclude_matcher: &PathMatcher,
+    sort_keys: &[SortKey],
     monitor: Option<Arc<Mutex<MemoryMonitor>>>,
 ) -> Result<ScanResult> {
-    scan_files_and_dirs_with_monitor(root, args, exclude_matcher, sort_key, monitor)
+    let Some(monitor) = monitor else {
+        return scan_files_and_dirs_incremental(root, options, exclude_matcher, sort_keys);
+    };
+    let spinner = Spinner::new("Incremental scan in progress...")?;
+    Scanner::new(options.clone())
+        .exclude_matcher(exclude_matcher)
+        .sort(sort_keys)
+        .observer(&spinner)
+        .memory_monitor(monitor)
+        .scan(root)
 }
 
 /// Incremental scanning with caching support
@@ -428,57 +1094,325 @@ pub fn scan_files_and_dirs_with_memory_monitor(
 /// 3. Save updated cache to disk
 pub fn scan_files_and_dirs_incremental(
     root: &Path,
-    args: &Args,
-    exclude_matcher: &globset::GlobSet,
-    sort_key: SortKey,
+    options: &ScanOptions,
+    exclude_matcher: &PathMatcher,
+    sort_keys: &[SortKey],
+) -> Result<ScanResult> {
+    let spinner = Spinner::new("Incremental scan in progress...")?;
+    let options = ScanOptions {
+        threads_strategy: crate::thread_pool::ThreadPoolStrategy::Default,
+        ..options.clone()
+    };
+    Scanner::new(options)
+        .exclude_matcher(exclude_matcher)
+        .sort(sort_keys)
+        .observer(&spinner)
+        .scan(root)
+}
+
+/// Incremental scanning that can be stopped from another thread
+///
+/// The scan checks `cancel` between directory entries and returns an error as
+/// soon as it is set. A cancelled scan does not update the cache, since its
+/// directory totals would be incomplete.
+///
+/// # Arguments
+/// * `root` - The root path to start scanning from
+/// * `options` - Options controlling scan behavior
+/// * `exclude_matcher` - Compiled glob patterns for excluding files/directories
+/// * `sort_keys` - How to sort the resulting entries (by name or size, ties by later keys)
+/// * `cancel` - Flag that requests cancellation when set to `true`
+///
+/// # Returns
+/// * `Result<ScanResult>` - Scan results, or an error if the scan failed or was cancelled
+pub fn scan_files_and_dirs_cancellable(
+    root: &Path,
+    options: &ScanOptions,
+    exclude_matcher: &PathMatcher,
+    sort_keys: &[SortKey],
+    cancel: &AtomicBool,
+) -> Result<ScanResult> {
+    let spinner = Spinner::new("Incremental scan in progress...")?;
+    Scanner::new(options.clone())
+        .exclude_matcher(exclude_matcher)
+        .sort(sort_keys)
+        .observer(&spinner)
+        .cancel_flag(cancel)
+        .scan(root)
+}
+
+/// Scan purely to populate the cache for `root`
+///
+/// Intended for scheduled jobs that keep the cache fresh so interactive runs
+/// are fast. Since nothing is rendered, entries are left unsorted and owner
+/// names are not resolved; caching is always enabled regardless of `--no-cache`.
+///
+/// # Arguments
+/// * `root` - The root path whose cache should be warmed
+/// * `options` - Options controlling scan behavior
+/// * `exclude_matcher` - Compiled glob patterns for excluding files/directories
+///
+/// # Returns
+/// * `Result<ScanResult>` - Scan results; `cache_hits` and `cache_total` show
+///   how much of the previous cache was still valid
+pub fn warm_cache(
+    root: &Path,
+    options: &ScanOptions,
+    exclude_matcher: &PathMatcher,
 ) -> Result<ScanResult> {
-    scan_files_and_dirs_with_monitor(root, args, exclude_matcher, sort_key, None)
+    let options = ScanOptions {
+        show_owner: false,
+        no_cache: false,
+        threads_strategy: crate::thread_pool::ThreadPoolStrategy::Default,
+        ..options.clone()
+    };
+    let spinner = Spinner::new("Incremental scan in progress...")?;
+    Scanner::new(options)
+        .exclude_matcher(exclude_matcher)
+        .observer(&spinner)
+        .scan(root)
+}
+
+/// Entry counts and cached sizes gathered by [`estimate`]
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Estimate {
+    /// Files (and other non-directories) found below the root
+    pub files: u64,
+    /// Directories found, including the root
+    pub dirs: u64,
+    /// Total size of the outermost directories found in the cache, as of the
+    /// run that cached them
+    pub cached_size: u64,
+    /// Directories whose size was taken from the cache
+    pub cached_dirs: u64,
+    /// Files below no cached directory, whose size is unknown
+    pub uncached_files: u64,
+    /// Time spent walking the tree
+    pub elapsed: Duration,
+}
+
+/// Walks `root` without stat'ing files to estimate the cost of a full scan
+///
+/// File types come from the directory entries themselves, so only directories
+/// are looked at beyond `readdir`. Sizes are taken from the existing cache
+/// without validating it, which makes them approximate: directories changed
+/// since they were cached are counted at their old size, and files outside
+/// any cached directory are only counted.
+///
+/// # Arguments
+/// * `root` - The root path to estimate
+/// * `options` - Options controlling exclusions and the cache location
+/// * `exclude_matcher` - Compiled glob patterns for excluding files/directories
+///
+/// # Returns
+/// * `Result<Estimate>` - Entry counts and the cached size
+///
+/// # Errors
+/// Returns an error if the root path is inaccessible or the progress
+/// spinner cannot be set up.
+pub fn estimate(
+    root: &Path,
+    options: &ScanOptions,
+    exclude_matcher: &PathMatcher,
+) -> Result<Estimate> {
+    let started = Instant::now();
+    std::fs::metadata(root).map_err(|source| Error::Io {
+        path: root.to_path_buf(),
+        source,
+    })?;
+    let in_tree_cache = options.cache_location.resolve(root) == CacheLocation::InTree;
+    let cache = if options.no_cache {
+        None
+    } else {
+        open_previous_cache(root, options.cache_location)
+    };
+    let mut mounts = MountTracker::new(options)?;
+    let pb = progress_spinner("Counting entries...").map_err(Error::output)?;
+
+    let walker = WalkDir::new(root)
+        .follow_links(false)
+        .into_iter()
+        .filter_entry(|e| {
+            !exclude_matcher.is_match(e.path())
+                && !e.path().components().any(|c| {
+                    options
+                        .exclude
+                        .iter()
+                        .any(|x| c.as_os_str() == OsStr::new(x))
+                })
+                && !is_marked_excluded(e, options)
+                && !is_in_tree_cache_file(e, in_tree_cache)
+                && mounts.admit(e)
+        });
+
+    let mut estimate = Estimate::default();
+    // Depth of the cached directory the walk is currently inside, if any
+    let mut cached_depth: Option<usize> = None;
+    for entry in walker.filter_map(|e| e.ok()) {
+        pb.tick();
+        if cached_depth.is_some_and(|depth| entry.depth() <= depth) {
+            cached_depth = None;
+        }
+        if !entry.file_type().is_dir() {
+            estimate.files += 1;
+            if cached_depth.is_none() {
+                estimate.uncached_files += 1;
+            }
+            continue;
+        }
+        estimate.dirs += 1;
+        if cached_depth.is_none()
+            && let Some(record) = cache.as_ref().and_then(|c| c.lookup(entry.path()))
+        {
+            estimate.cached_size += record.size();
+            estimate.cached_dirs += 1;
+            cached_depth = Some(entry.depth());
+        }
+    }
+
+    pb.finish_and_clear();
+    estimate.elapsed = started.elapsed();
+    Ok(estimate)
+}
+
+/// Counts the files and directories below every directory of `root`
+///
+/// Nothing is stat'd beyond what exclusion checks need: file types come from
+/// the directory entries, and sizes, owners and the cache are not used, so
+/// this is much faster than a full scan on filesystems where inode quotas
+/// run out before space does.
+///
+/// # Arguments
+/// * `root` - The root path to count
+/// * `options` - Options controlling exclusions
+/// * `exclude_matcher` - Compiled glob patterns for excluding files/directories
+///
+/// # Returns
+/// * `Result<(Vec<DirCounts>, ScanMeta)>` - Recursive counts of every
+///   directory walked, in walk order, and the run's metadata
+///
+/// # Errors
+/// Returns an error if the root path is inaccessible or the progress
+/// spinner cannot be set up.
+pub fn count_entries(
+    root: &Path,
+    options: &ScanOptions,
+    exclude_matcher: &PathMatcher,
+) -> Result<(Vec<DirCounts>, ScanMeta)> {
+    std::fs::metadata(root).map_err(|source| Error::Io {
+        path: root.to_path_buf(),
+        source,
+    })?;
+    let mut meta = ScanMeta::start(root, options);
+    let in_tree_cache = options.cache_location.resolve(root) == CacheLocation::InTree;
+    let mut mounts = MountTracker::new(options)?;
+    let pb = progress_spinner("Counting entries...").map_err(Error::output)?;
+
+    let walker = WalkDir::new(root)
+        .follow_links(false)
+        .into_iter()
+        .filter_entry(|e| {
+            !exclude_matcher.is_match(e.path())
+                && !e.path().components().any(|c| {
+                    options
+                        .exclude
+                        .iter()
+                        .any(|x| c.as_os_str() == OsStr::new(x))
+                })
+                && !is_marked_excluded(e, options)
+                && !is_in_tree_cache_file(e, in_tree_cache)
+                && mounts.admit(e)
+        });
+
+    // Directories on the path to the current entry, one per depth; a
+    // directory's counts are final once the walk moves out of it
+    let mut open: Vec<DirCounts> = Vec::new();
+    let mut counts = Vec::new();
+    let close = |open: &mut Vec<DirCounts>, counts: &mut Vec<DirCounts>| {
+        if let Some(dir) = open.pop() {
+            if let Some(parent) = open.last_mut() {
+                parent.files += dir.files;
+                parent.dirs += dir.dirs + 1;
+            }
+            counts.push(dir);
+        }
+    };
+    for entry in walker.filter_map(|e| e.ok()) {
+        pb.tick();
+        while open.len() > entry.depth() {
+            close(&mut open, &mut counts);
+        }
+        if entry.file_type().is_dir() {
+            open.push(DirCounts {
+                path: entry.into_path(),
+                files: 0,
+                dirs: 0,
+            });
+        } else if let Some(parent) = open.last_mut() {
+            parent.files += 1;
+        }
+    }
+    while !open.is_empty() {
+        close(&mut open, &mut counts);
+    }
+    pb.finish_and_clear();
+
+    meta.finished_at = chrono::Utc::now();
+    if let Some(root_counts) = counts.last() {
+        meta.file_count = root_counts.files;
+        meta.dir_count = root_counts.dirs + 1;
+    }
+    meta.mounts = mounts.into_mounts();
+    Ok((counts, meta))
 }
 
 /// Incremental scanning with optional memory monitoring
 ///
 /// This is the main implementation that supports memory monitoring.
+#[allow(clippy::too_many_arguments)]
 fn scan_files_and_dirs_with_monitor(
     root: &Path,
-    args: &Args,
-    exclude_matcher: &globset::GlobSet,
-    sort_key: SortKey,
+    options: &ScanOptions,
+    exclude_matcher: &PathMatcher,
+    sort_keys: Option<&[SortKey]>,
     monitor: Option<Arc<Mutex<MemoryMonitor>>>,
-) -> Result<ScanResult> {
+    cancel: Option<&AtomicBool>,
+    observer: &dyn ProgressObserver,
+    visitor: Option<&dyn EntryVisitor>,
+    pool: Option<&ThreadPool>,
+) -> anyhow::Result<ScanResult> {
+    let meta = ScanMeta::start(root, options);
+    let in_tree_cache = options.cache_location.resolve(root) == CacheLocation::InTree;
     let mut phase_timings = Vec::new();
 
     // Capture root mtime before any directory modifications
     let root_mtime = crate::cache::model::get_root_mtime(root);
 
     // Cache loading phase
+    observer.on_phase_start("Cache-load");
     let cache_timer = PhaseTimer::new("Cache-load");
-    let cache = if args.no_cache {
-        eprintln!("Cache disabled, performing full scan");
-        std::collections::HashMap::new()
+    // Sizes from the previous run, kept even if the cache is about to be invalidated
+    let previous = if options.no_cache {
+        None
     } else {
-        {
-            let cache = load_cache(root, args.cache_ttl);
-            if cache.is_empty() {
-                eprintln!("📦 No cache found, performing full scan");
-            }
-            cache
+        open_previous_cache(root, options.cache_location)
+    };
+    let cache = if options.no_cache {
+        log::info!("Cache disabled, performing full scan");
+        None
+    } else {
+        let cache =
+            open_cache(root, options.cache_ttl, options.cache_location).filter(|c| !c.is_empty());
+        if cache.is_none() {
+            log::info!("📦 No cache found, performing full scan");
         }
+        cache
     };
     phase_timings.push(cache_timer.finish());
 
     let cache_hits = std::sync::atomic::AtomicUsize::new(0);
     let cache_misses = std::sync::atomic::AtomicUsize::new(0);
 
-    // Setup progress spinner
-    let pb = ProgressBar::new_spinner();
-    pb.set_style(
-        ProgressStyle::default_spinner()
-            .tick_strings(&["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"])
-            .template("{spinner} Incremental scan in progress... [{elapsed}]")
-            .context("Failed to set progress template")?,
-    );
-    pb.enable_steady_tick(Duration::from_millis(100));
-
     // Data structures for aggregating results
     let dir_totals: DashMap<PathBuf, u64> = DashMap::new();
     let directory_children: DashMap<PathBuf, u64> = DashMap::new();
@@ -489,31 +1423,36 @@ fn scan_files_and_dirs_with_monitor(
     // Memory monitoring state
     let mut memory_nearing_limit = false;
     let mut entry_counter = 0;
-    // Calculate check interval based on CLI setting - check more frequently if interval is shorter
-    let memory_check_interval: usize = if args.memory_check_interval_ms <= 100 {
-        500 // Very frequent checks for short intervals
-    } else if args.memory_check_interval_ms <= 200 {
-        1000 // Normal interval for default setting
-    } else {
-        2000 // Less frequent checks for longer intervals to reduce overhead
+    let memory_check_interval = options.memory_check_entries.max(1);
+
+    // Timestamp recorded as `last_hit` on directories validated from cache
+    let scan_started = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    // Filesystem latency sampling (profiling only)
+    let latency = options.profile.then(FsLatency::default);
+    // stat time spent inside the walker's filter, excluded from readdir samples
+    let inline_stat = Cell::new(Duration::ZERO);
+    let stat_dir = |path: &Path| match latency.as_ref() {
+        Some(latency) => {
+            let started = Instant::now();
+            let metadata = get_dir_metadata(path);
+            let elapsed = started.elapsed();
+            latency.stat.record(elapsed);
+            inline_stat.set(inline_stat.get() + elapsed);
+            metadata
+        }
+        None => get_dir_metadata(path),
     };
 
     // WalkDir phase
+    observer.on_phase_start("WalkDir");
     let walkdir_timer = PhaseTimer::new("WalkDir");
+    let mut mounts = MountTracker::new(options)?;
 
-    // Pre-build parent → children index so that subtree restoration on a cache hit is O(n)
-    // overall rather than O(n×k) (iterating all cache entries for each hit).
-    let mut children_index: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
-    for cached_path in cache.keys() {
-        if let Some(parent) = cached_path.parent() {
-            children_index
-                .entry(parent.to_path_buf())
-                .or_default()
-                .push(cached_path.clone());
-        }
-    }
-
-    let walker_iter = WalkDir::new(root)
+    let mut walker_iter = WalkDir::new(root)
         .follow_links(false)
         .into_iter()
         .filter_entry(|e| {
@@ -524,53 +1463,68 @@ fn scan_files_and_dirs_with_monitor(
                 return false;
             }
 
-            if path
-                .components()
-                .any(|c| args.exclude.iter().any(|x| c.as_os_str() == OsStr::new(x)))
-            {
+            if path.components().any(|c| {
+                options
+                    .exclude
+                    .iter()
+                    .any(|x| c.as_os_str() == OsStr::new(x))
+            }) {
                 return false;
             }
 
-            // For directories, check if we can skip based on cache
-            if e.file_type().is_dir() && !args.no_cache {
-                if let Some(cached_entry) = cache.get(&path.to_path_buf()) {
-                    if let Some(current_metadata) = get_dir_metadata(path) {
-                        if cached_entry.is_valid(current_metadata.mtime, current_metadata.nlink) {
-                            // Cache hit - we can skip this subtree
-                            cache_hits.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-
-                            // Reuse cached aggregated values
-                            dir_totals.insert(path.to_path_buf(), cached_entry.size);
-                            if let Some(inode_count) = cached_entry.inode_cnt {
-                                directory_children.insert(path.to_path_buf(), inode_count);
-                            }
+            if is_marked_excluded(e, options) || is_in_tree_cache_file(e, in_tree_cache) {
+                return false;
+            }
 
-                            // Store cached directory info for later FileEntry creation
-                            cached_dirs.insert(path.to_path_buf(), cached_entry.clone());
-
-                            // Add to new cache (preserving valid entries)
-                            new_cache_entries.insert(path.to_path_buf(), cached_entry.clone());
-
-                            // Restore cached subdirectory entries using the pre-built
-                            // children_index for O(n) overall cost instead of O(n×k).
-                            restore_subtree(
-                                root,
-                                path,
-                                &children_index,
-                                &cache,
-                                args.depth,
-                                exclude_matcher,
-                                &args.exclude,
-                                &dir_totals,
-                                &directory_children,
-                                &cached_dirs,
-                                &mut new_cache_entries,
-                            );
-
-                            pb.tick();
-                            return false; // Skip walking into this subtree
-                        }
+            if !mounts.admit(e) {
+                return false;
+            }
+
+            // For directories, check if we can skip based on cache
+            if e.file_type().is_dir() && !options.no_cache {
+                if let Some(cache) = cache.as_ref()
+                    && let Some(record) = cache.lookup(path)
+                    && let Some(current_metadata) = stat_dir(path)
+                    && record.is_valid_with(
+                        options.cache_validation,
+                        current_metadata.mtime,
+                        current_metadata.nlink,
+                        current_metadata.ctime,
+                    )
+                {
+                    // Cache hit - we can skip this subtree
+                    cache_hits.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    let mut cached_entry = record.to_entry();
+                    cached_entry.last_hit = scan_started;
+
+                    // Reuse cached aggregated values
+                    dir_totals.insert(path.to_path_buf(), cached_entry.size);
+                    if let Some(inode_count) = cached_entry.inode_cnt {
+                        directory_children.insert(path.to_path_buf(), inode_count);
                     }
+
+                    // Store cached directory info for later FileEntry creation
+                    cached_dirs.insert(path.to_path_buf(), cached_entry.clone());
+
+                    // Add to new cache (preserving valid entries)
+                    new_cache_entries.insert(path.to_path_buf(), cached_entry);
+
+                    // Restore cached subdirectory entries from the mapped child index
+                    restore_subtree(
+                        root,
+                        cache,
+                        &record,
+                        options.depth,
+                        exclude_matcher,
+                        &options.exclude,
+                        &dir_totals,
+                        &directory_children,
+                        &cached_dirs,
+                        &mut new_cache_entries,
+                    );
+
+                    observer.on_cache_hit(path);
+                    return false; // Skip walking into this subtree
                 }
                 cache_misses.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
             }
@@ -580,30 +1534,114 @@ fn scan_files_and_dirs_with_monitor(
 
     // Collect entries with memory monitoring
     let mut walker_entries: Vec<walkdir::DirEntry> = Vec::new();
+    let mut largest = options.largest_files.map(LargestFiles::new);
+    // Files measured during the walk once memory is nearing the limit
+    let mut spill: Option<EntrySpill> = None;
+    let mut spilled_files: u64 = 0;
     let mut memory_exceeded = false;
-
-    for entry in walker_iter.flatten() {
-        pb.tick();
+    let mut pending_readdir: Option<Duration> = None;
+
+    loop {
+        let next_started = latency.as_ref().map(|_| Instant::now());
+        let next = walker_iter.next();
+        if let (Some(latency), Some(started)) = (latency.as_ref(), next_started) {
+            let elapsed = started
+                .elapsed()
+                .saturating_sub(inline_stat.replace(Duration::ZERO));
+            // A directory is opened when it is yielded and first read on the following call
+            if let Some(opened) = pending_readdir.take() {
+                latency.readdir.record(opened + elapsed);
+            }
+            if let Some(Ok(entry)) = &next
+                && entry.file_type().is_dir()
+            {
+                pending_readdir = Some(elapsed);
+            }
+        }
+        let entry = match next {
+            Some(Ok(entry)) => entry,
+            Some(Err(err)) => {
+                observer.on_error(err.path(), &err);
+                continue;
+            }
+            None => break,
+        };
+        if cancel.is_some_and(|c| c.load(std::sync::atomic::Ordering::Relaxed)) {
+            return Err(Error::Cancelled.into());
+        }
+        observer.on_entry(entry.path());
 
         // Increment counter and check memory every N entries
         entry_counter += 1;
-        if entry_counter % memory_check_interval == 0 {
-            if let Some(ref monitor) = monitor {
-                if let Ok(mut mem_monitor) = monitor.lock() {
-                    if mem_monitor.exceeds_limit() {
-                        eprintln!("⚠️  Memory limit exceeded, terminating scan early");
-                        memory_exceeded = true;
-                        break;
-                    } else if !memory_nearing_limit && mem_monitor.nearing_limit() {
-                        eprintln!(
-                            "⚠️  Memory usage nearing limit, disabling cache and heavy features"
-                        );
-                        memory_nearing_limit = true;
-                        // Disable caching dynamically to reduce memory usage
-                        crate::cache::set_enabled(false);
-                    }
+        if entry_counter % memory_check_interval == 0
+            && let Some(ref monitor) = monitor
+            && let Ok(mut mem_monitor) = monitor.lock()
+        {
+            if mem_monitor.exceeds_limit() {
+                log::warn!("⚠️  Memory limit exceeded, terminating scan early");
+                memory_exceeded = true;
+                break;
+            } else if !memory_nearing_limit && mem_monitor.nearing_limit() {
+                log::warn!(
+                    "⚠️  Memory usage nearing limit, disabling cache and spilling entries to disk"
+                );
+                memory_nearing_limit = true;
+                // Disable caching dynamically to reduce memory usage
+                crate::cache::set_enabled(false);
+                // Runs are sorted like the listing, so they merge into it at the end
+                let keys = scan_sort_keys(sort_keys, options).unwrap_or_default();
+                spill = EntrySpill::new(&keys, options.reverse)
+                    .inspect_err(|e| log::warn!("Cannot spill entries to disk: {e:#}"))
+                    .ok();
+            }
+        }
+
+        // Measure files right away rather than holding them until the walk ends
+        if let Some(spill) = spill.as_mut()
+            && !entry.file_type().is_dir()
+        {
+            let entry_type = EntryType::from_file_type(entry.file_type());
+            let path = entry.into_path();
+            let (size, mtime, stat) = timed_stat(latency.as_ref(), || stat_file(&path, options));
+            if let Some(largest) = largest.as_mut() {
+                offer_largest(largest, options, &path, entry_type, (size, mtime, &stat));
+            }
+            let mut current = path.parent();
+            while let Some(parent_path) = current {
+                *dir_totals.entry(parent_path.to_path_buf()).or_insert(0) += size;
+                if parent_path == root {
+                    break;
+                }
+                current = parent_path.parent();
+            }
+            if options.show_inodes
+                && let Some(parent) = path.parent()
+            {
+                *directory_children.entry(parent.to_path_buf()).or_insert(0) += 1;
+            }
+            spilled_files += 1;
+            // With --summarize only the root is reported, so files get no entries
+            if !options.summarize {
+                let entry = FileEntry {
+                    owner: if options.show_owner {
+                        get_owner(&path)
+                    } else {
+                        None
+                    },
+                    path,
+                    size,
+                    inodes: None,
+                    entry_type,
+                    previous_size: None,
+                    mtime,
+                    stat,
+                };
+                if let Some(visitor) = visitor {
+                    visitor.visit(&entry);
                 }
+                spill.push(entry)?;
             }
+            continue;
         }
 
         walker_entries.push(entry);
@@ -612,45 +1650,66 @@ fn scan_files_and_dirs_with_monitor(
     phase_timings.push(walkdir_timer.finish());
 
     // Disk I/O phase - process entries that weren't cached
+    observer.on_phase_start("Disk-usage I/O");
     let disk_io_timer = PhaseTimer::new("Disk-usage I/O");
-    let scan_jobs: Vec<ScanJob> = walker_entries
-        .par_iter()
-        .map(|entry| {
-            let path = entry.path().to_path_buf();
-            let is_file = entry.file_type().is_file();
-            let size = if is_file { disk_usage(&path) } else { 0 };
-
-            let parent_paths = if is_file {
-                let mut parents = Vec::new();
-                let mut current = path.parent();
-                while let Some(parent_path) = current {
-                    parents.push(parent_path.to_path_buf());
-                    if parent_path == root {
-                        break;
+    let scan_jobs: Vec<ScanJob> = in_pool(pool, || {
+        walker_entries
+            .par_iter()
+            .with_min_len(options.stat_batch)
+            .map(|entry| {
+                let path = entry.path().to_path_buf();
+                let entry_type = EntryType::from_file_type(entry.file_type());
+                let is_file = !entry_type.is_dir();
+                let (size, mtime, stat) = if is_file {
+                    timed_stat(latency.as_ref(), || stat_file(&path, options))
+                } else {
+                    (0, None, None)
+                };
+
+                let parent_paths = if is_file {
+                    let mut parents = Vec::new();
+                    let mut current = path.parent();
+                    while let Some(parent_path) = current {
+                        parents.push(parent_path.to_path_buf());
+                        if parent_path == root {
+                            break;
+                        }
+                        current = parent_path.parent();
                     }
-                    current = parent_path.parent();
-                }
-                parents
-            } else {
-                Vec::new()
-            };
+                    parents
+                } else {
+                    Vec::new()
+                };
 
-            ScanJob {
-                path,
-                is_file,
-                size,
-                parent_paths,
-            }
-        })
-        .collect();
+                ScanJob {
+                    path,
+                    entry_type,
+                    size,
+                    mtime,
+                    stat,
+                    parent_paths,
+                }
+            })
+            .collect()
+    });
     phase_timings.push(disk_io_timer.finish());
 
     // Aggregation phase
+    observer.on_phase_start("Aggregation");
     let aggregation_timer = PhaseTimer::new("Aggregation");
 
     // Accumulate directory sizes from file scan jobs
     for job in &scan_jobs {
-        if job.is_file {
+        if !job.entry_type.is_dir() {
+            if let Some(largest) = largest.as_mut() {
+                offer_largest(
+                    largest,
+                    options,
+                    &job.path,
+                    job.entry_type,
+                    (job.size, job.mtime, &job.stat),
+                );
+            }
             for parent_path in &job.parent_paths {
                 dir_totals
                     .entry(parent_path.clone())
@@ -660,8 +1719,8 @@ fn scan_files_and_dirs_with_monitor(
         }
     }
 
-    // Count children for inode tracking - skip if memory nearing limit to save memory
-    if args.show_inodes && !memory_nearing_limit {
+    // Count children for inode tracking; spilled files were counted as they were walked
+    if options.show_inodes {
         for job in &scan_jobs {
             if let Some(parent) = job.path.parent() {
                 *directory_children.entry(parent.to_path_buf()).or_insert(0) += 1;
@@ -670,69 +1729,86 @@ fn scan_files_and_dirs_with_monitor(
     }
 
     // Create FileEntry objects from scan jobs and collect cache entries
-    let scanned_entries: Vec<(FileEntry, Option<CacheEntry>)> = scan_jobs
-        .par_iter()
-        .map(|job| {
-            let (entry, cache_entry) = if job.is_file {
-                let entry = FileEntry {
-                    path: job.path.clone(),
-                    size: job.size,
-                    owner: if args.show_owner {
-                        get_owner(&job.path)
-                    } else {
-                        None
-                    },
-                    inodes: None,
-                    entry_type: EntryType::File,
-                };
-                (entry, None)
-            } else {
-                let size = dir_totals.get(&job.path).map(|v| *v).unwrap_or(0);
-                let inode_count = if args.show_inodes {
-                    directory_children.get(&job.path).map(|v| *v).unwrap_or(0)
+    // With --summarize only the root is reported, so files get no entries
+    let scanned_entries: Vec<(FileEntry, Option<CacheEntry>)> = in_pool(pool, || {
+        scan_jobs
+            .par_iter()
+            .filter(|job| !options.summarize || job.entry_type.is_dir())
+            .map(|job| {
+                let (entry, cache_entry) = if !job.entry_type.is_dir() {
+                    let entry = FileEntry {
+                        path: job.path.clone(),
+                        size: job.size,
+                        owner: if options.show_owner {
+                            get_owner(&job.path)
+                        } else {
+                            None
+                        },
+                        inodes: None,
+                        entry_type: job.entry_type,
+                        previous_size: None,
+                        mtime: job.mtime,
+                        stat: job.stat.clone(),
+                    };
+                    (entry, None)
                 } else {
-                    0
-                };
-
-                // Create cache entry for this directory
-                let cache_entry = get_dir_metadata(&job.path).map(|metadata| {
-                    CacheEntry::new(CacheEntryParams {
+                    let size = dir_totals.get(&job.path).map(|v| *v).unwrap_or(0);
+                    let inode_count = if options.show_inodes {
+                        directory_children.get(&job.path).map(|v| *v).unwrap_or(0)
+                    } else {
+                        0
+                    };
+
+                    // Create cache entry for this directory
+                    let metadata = timed_stat(latency.as_ref(), || get_dir_metadata(&job.path));
+                    let cache_entry = metadata.as_ref().map(|metadata| {
+                        CacheEntry::new(CacheEntryParams {
+                            path: job.path.clone(),
+                            size,
+                            mtime: metadata.mtime,
+                            ctime: Some(metadata.ctime),
+                            nlink: metadata.nlink,
+                            inode_cnt: if options.show_inodes {
+                                Some(inode_count)
+                            } else {
+                                None
+                            },
+                            owner: metadata.owner,
+                            entry_type: EntryType::Dir,
+                        })
+                    });
+
+                    let entry = FileEntry {
                         path: job.path.clone(),
                         size,
-                        mtime: metadata.mtime,
-                        nlink: metadata.nlink,
-                        inode_cnt: if args.show_inodes {
+                        owner: if options.show_owner {
+                            get_owner(&job.path)
+                        } else {
+                            None
+                        },
+                        inodes: if options.show_inodes {
                             Some(inode_count)
                         } else {
                             None
                         },
-                        owner: metadata.owner,
                         entry_type: EntryType::Dir,
-                    })
-                });
-
-                let entry = FileEntry {
-                    path: job.path.clone(),
-                    size,
-                    owner: if args.show_owner {
-                        get_owner(&job.path)
-                    } else {
-                        None
-                    },
-                    inodes: if args.show_inodes {
-                        Some(inode_count)
-                    } else {
-                        None
-                    },
-                    entry_type: EntryType::Dir,
+                        previous_size: None,
+                        mtime: cache_entry.as_ref().map(|c| c.mtime),
+                        stat: metadata
+                            .filter(|_| options.extended_metadata)
+                            .map(|m| Box::new(m.stat)),
+                    };
+
+                    (entry, cache_entry)
                 };
 
+                if let Some(visitor) = visitor {
+                    visitor.visit(&entry);
+                }
                 (entry, cache_entry)
-            };
-
-            (entry, cache_entry)
-        })
-        .collect();
+            })
+            .collect()
+    });
 
     // Separate entries and cache entries
     let mut file_entries: Vec<FileEntry> = Vec::new();
@@ -750,58 +1826,92 @@ fn scan_files_and_dirs_with_monitor(
         .map(|entry| (entry.key().clone(), entry.value().clone()))
         .collect();
 
-    let mut cached_entries: Vec<FileEntry> = cached_entries_vec
-        .par_iter()
-        .map(|(path, cached_entry)| FileEntry {
-            path: path.clone(),
-            size: cached_entry.size,
-            owner: if args.show_owner {
-                get_owner(path)
-            } else {
-                None
-            },
-            inodes: cached_entry.inode_cnt,
-            entry_type: cached_entry.entry_type,
-        })
-        .collect();
+    let mut cached_entries: Vec<FileEntry> = in_pool(pool, || {
+        cached_entries_vec
+            .par_iter()
+            .map(|(path, cached_entry)| {
+                let entry = FileEntry {
+                    path: path.clone(),
+                    size: cached_entry.size,
+                    owner: if options.show_owner {
+                        get_owner(path)
+                    } else {
+                        None
+                    },
+                    inodes: cached_entry.inode_cnt,
+                    entry_type: cached_entry.entry_type,
+                    previous_size: None,
+                    mtime: Some(cached_entry.mtime),
+                    // Restored directories were not stat'd by this scan
+                    stat: if options.extended_metadata {
+                        get_dir_metadata(path).map(|m| Box::new(m.stat))
+                    } else {
+                        None
+                    },
+                };
+                if let Some(visitor) = visitor {
+                    visitor.visit(&entry);
+                }
+                entry
+            })
+            .collect()
+    });
 
     // Combine scanned and cached entries
     let mut all_entries = file_entries;
     all_entries.append(&mut cached_entries);
+    let spilled = spill.map(EntrySpill::finish).transpose()?;
+    if let Some(spilled) = &spilled {
+        log::info!(
+            "Keeping {} spilled entries on disk until output",
+            spilled.len()
+        );
+    }
 
     phase_timings.push(aggregation_timer.finish());
 
-    pb.finish_with_message("Incremental scan complete");
-
     // Print cache statistics
     let hits = cache_hits.load(std::sync::atomic::Ordering::Relaxed);
     let misses = cache_misses.load(std::sync::atomic::Ordering::Relaxed);
     if hits > 0 || misses > 0 {
-        eprintln!(
+        log::info!(
             "📊 Cache stats: {} hits, {} misses ({}% hit rate)",
             hits,
             misses,
-            if hits + misses > 0 {
-                hits * 100 / (hits + misses)
-            } else {
-                0
-            }
+            (hits * 100).checked_div(hits + misses).unwrap_or(0)
         );
     }
 
     // Save updated cache (unless disabled or memory constrained)
-    if !args.no_cache && !memory_nearing_limit {
-        if let Err(e) = save_cache_with_mtime(root, &new_cache_entries, root_mtime) {
-            eprintln!("Failed to save cache: {}", e);
+    if !options.no_cache && !memory_nearing_limit {
+        let max_bytes = options.cache_max_size.map(|mb| mb * 1024 * 1024);
+        if let Err(e) = save_cache_with_limit(
+            root,
+            &new_cache_entries,
+            root_mtime,
+            max_bytes,
+            options.cache_location,
+        ) {
+            log::error!("Failed to save cache: {}", e);
         } else {
-            eprintln!("Cache updated with {} entries", new_cache_entries.len());
+            log::info!("Cache updated with {} entries", new_cache_entries.len());
         }
     } else if memory_nearing_limit {
-        eprintln!("⚠️  Cache saving disabled due to memory constraints");
+        log::warn!("⚠️  Cache saving disabled due to memory constraints");
     }
 
     // Sort and return results
-    sort_entries(&mut all_entries, sort_key);
+    // Attach the sizes the cache held before this scan so output can show deltas
+    if let Some(previous) = previous.as_ref() {
+        for entry in all_entries
+            .iter_mut()
+            .filter(|e| e.entry_type == EntryType::Dir)
+        {
+            entry.previous_size = previous.lookup(&entry.path).map(|record| record.size());
+        }
+    }
+
+    sort_scan_entries(&mut all_entries, sort_keys, options);
     let cache_hits_val = hits;
     let cache_total_val = hits + misses;
 
@@ -814,6 +1924,23 @@ fn scan_files_and_dirs_with_monitor(
         MemoryLimitStatus::Normal
     };
 
+    let mut meta = meta.finish(
+        &all_entries,
+        cache_hits_val as u64,
+        cache_total_val as u64,
+        memory_exceeded,
+    );
+    meta.mounts = mounts.into_mounts();
+    meta.file_count += spilled.as_ref().map_or(0, |spilled| spilled.len() as u64);
+    if options.summarize {
+        meta.file_count = scan_jobs
+            .iter()
+            .filter(|job| !job.entry_type.is_dir())
+            .count() as u64
+            + spilled_files;
+        all_entries.retain(|e| e.path == root);
+    }
+
     Ok(ScanResult {
         entries: all_entries,
         cache_hits: cache_hits_val as u64,
@@ -821,5 +1948,9 @@ fn scan_files_and_dirs_with_monitor(
         memory_limit_hit: memory_exceeded,
         phase_timings,
         memory_status,
+        fs_latency: latency.map(FsLatency::into_stats).unwrap_or_default(),
+        meta,
+        largest_files: largest.map(LargestFiles::into_sorted).unwrap_or_default(),
+        spilled,
     })
 }
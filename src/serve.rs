@@ -0,0 +1,267 @@
+//! HTTP API over the latest scan (`rudu serve`, `serve` feature).
+//!
+//! The server keeps the most recent [`Snapshot`] of a scan in memory and
+//! answers `GET` requests with JSON, so dashboards can query disk usage
+//! without running scans or parsing output files:
+//!
+//! - `/stats`: the run's [`ScanMeta`] (totals, counts, cache hits) and when
+//!   the scan finished
+//! - `/tree?path=P&depth=N`: the [`DirNode`] for `P` (default: the root)
+//!   with `N` levels of children (default 1)
+//! - `/top?n=N&files=true`: the `N` largest directories (default 10), or
+//!   files with `files=true`
+//! - `/by-owner`: bytes, files, inodes and share per owner, as the
+//!   `--report by-owner` rows
+//!
+//! Until the first scan finishes every endpoint answers `503`.
+
+use crate::data::{FileEntry, ScanMeta};
+use crate::report;
+use crate::tree::DirNode;
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::RwLock;
+
+/// Directories listed by `/top` without `n`
+const DEFAULT_TOP: usize = 10;
+
+/// The scan results the server answers from
+pub struct Snapshot {
+    /// Every entry of the scan
+    pub entries: Vec<FileEntry>,
+    /// Run-level facts of the scan
+    pub meta: ScanMeta,
+    /// The entries as a tree below the root
+    pub tree: DirNode,
+    /// When the scan finished, as an RFC 3339 timestamp
+    pub scanned_at: String,
+}
+
+impl Snapshot {
+    /// Builds the snapshot of a scan of `root`.
+    pub fn new(root: &Path, entries: Vec<FileEntry>, meta: ScanMeta) -> Self {
+        Self {
+            tree: DirNode::from_entries(root, &entries),
+            entries,
+            meta,
+            scanned_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        }
+    }
+}
+
+/// Answers a `GET` of `url` (path and query) from `snapshot`, returning the
+/// HTTP status and JSON body.
+pub fn handle(snapshot: Option<&Snapshot>, url: &str) -> (u16, Value) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let params: HashMap<String, String> = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect();
+
+    if !matches!(path, "/stats" | "/tree" | "/top" | "/by-owner") {
+        return error(404, format!("no endpoint {}", path));
+    }
+    let Some(snapshot) = snapshot else {
+        return error(503, "the first scan has not finished yet".to_string());
+    };
+    match path {
+        "/stats" => (
+            200,
+            json!({
+                "scanned_at": snapshot.scanned_at,
+                "meta": snapshot.meta,
+            }),
+        ),
+        "/tree" => {
+            let depth = match parse_param(&params, "depth", 1) {
+                Ok(depth) => depth,
+                Err(response) => return response,
+            };
+            let node = match params.get("path") {
+                Some(path) => snapshot.tree.find(Path::new(path)),
+                None => Some(&snapshot.tree),
+            };
+            match node {
+                Some(node) => (200, json!(prune(node, depth))),
+                None => error(404, format!("{} is not in the scan", params["path"])),
+            }
+        }
+        "/top" => {
+            let n = match parse_param(&params, "n", DEFAULT_TOP) {
+                Ok(n) => n,
+                Err(response) => return response,
+            };
+            let files = params.get("files").is_some_and(|v| v == "true");
+            let mut top: Vec<&FileEntry> = snapshot
+                .entries
+                .iter()
+                .filter(|e| e.path != snapshot.tree.path && e.entry_type.is_dir() != files)
+                .collect();
+            top.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+            top.truncate(n);
+            (200, json!(top))
+        }
+        _ => (200, json!(report::by_owner(&snapshot.entries).json_rows())),
+    }
+}
+
+/// Serves the snapshot in `state` on `listen` (e.g. `127.0.0.1:8080`) until
+/// the process ends.
+///
+/// # Errors
+/// Fails if the address cannot be bound.
+pub fn serve(listen: &str, state: &RwLock<Option<Snapshot>>) -> Result<()> {
+    let server = tiny_http::Server::http(listen)
+        .map_err(|e| anyhow::anyhow!(e))
+        .with_context(|| format!("Failed to listen on {}", listen))?;
+    log::info!("Serving the scan API on http://{}", listen);
+
+    for request in server.incoming_requests() {
+        let (status, body) = if *request.method() == tiny_http::Method::Get {
+            let snapshot = state.read().unwrap_or_else(|e| e.into_inner());
+            handle(snapshot.as_ref(), request.url())
+        } else {
+            error(405, "only GET requests are supported".to_string())
+        };
+        log::debug!("{} {} -> {}", request.method(), request.url(), status);
+        let response = tiny_http::Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(
+                tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                    .expect("static header is valid"),
+            );
+        if let Err(e) = request.respond(response) {
+            log::warn!("Failed to send response: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// An error response with `message`
+fn error(status: u16, message: String) -> (u16, Value) {
+    (status, json!({ "error": message }))
+}
+
+/// The value of query parameter `name` as a number, or `default`.
+fn parse_param(
+    params: &HashMap<String, String>,
+    name: &str,
+    default: usize,
+) -> std::result::Result<usize, (u16, Value)> {
+    match params.get(name) {
+        None => Ok(default),
+        Some(value) => value
+            .parse()
+            .map_err(|_| error(400, format!("{} must be a number, got '{}'", name, value))),
+    }
+}
+
+/// `node` with its children cut off below `depth` levels.
+fn prune(node: &DirNode, depth: usize) -> DirNode {
+    DirNode {
+        path: node.path.clone(),
+        entry_type: node.entry_type,
+        size: node.size,
+        own_size: node.own_size,
+        files: node.files,
+        dirs: node.dirs,
+        children: if depth == 0 {
+            Vec::new()
+        } else {
+            node.children
+                .iter()
+                .map(|child| prune(child, depth - 1))
+                .collect()
+        },
+    }
+}
+
+/// Decodes `%XX` escapes and `+` (as a space) in a query string component.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = |b: u8| (b as char).to_digit(16);
+                match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                    (Some(high), Some(low)) => {
+                        out.push((high * 16 + low) as u8);
+                        i += 2;
+                    }
+                    _ => out.push(b'%'),
+                }
+            }
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::EntryType;
+    use crate::options::ScanOptions;
+
+    fn snapshot() -> Snapshot {
+        let root = Path::new("/data");
+        let entries = vec![
+            FileEntry::for_test("/data", 7000, EntryType::Dir).with_owner("root"),
+            FileEntry::for_test("/data/a", 5000, EntryType::Dir).with_owner("alice"),
+            FileEntry::for_test("/data/a/x.nc", 5000, EntryType::File).with_owner("alice"),
+            FileEntry::for_test("/data/b b", 2000, EntryType::Dir).with_owner("bob"),
+            FileEntry::for_test("/data/b b/y.txt", 2000, EntryType::File).with_owner("bob"),
+        ];
+        let mut meta = ScanMeta::start(root, &ScanOptions::new());
+        meta.count_entries(&entries);
+        Snapshot::new(root, entries, meta)
+    }
+
+    #[test]
+    fn test_endpoints() {
+        let snapshot = snapshot();
+        let get = |url: &str| handle(Some(&snapshot), url);
+
+        let (status, stats) = get("/stats");
+        assert_eq!(status, 200);
+        assert_eq!(stats["meta"]["total_size"], 7000);
+
+        let (status, tree) = get("/tree");
+        assert_eq!(status, 200);
+        assert_eq!(tree["children"][0]["path"], "/data/a");
+        assert_eq!(tree["children"][0]["children"], json!([]));
+        let (_, tree) = get("/tree?path=%2Fdata%2Fb+b&depth=1");
+        assert_eq!(tree["children"][0]["path"], "/data/b b/y.txt");
+
+        let (_, top) = get("/top?n=1");
+        assert_eq!(top.as_array().unwrap().len(), 1);
+        assert_eq!(top[0]["path"], "/data/a");
+        let (_, top) = get("/top?files=true");
+        assert_eq!(top[1]["path"], "/data/b b/y.txt");
+
+        let (status, owners) = get("/by-owner");
+        assert_eq!(status, 200);
+        assert_eq!(owners[0]["owner"], "alice");
+        assert_eq!(owners[0]["size_bytes"], 5000);
+    }
+
+    #[test]
+    fn test_errors() {
+        let snapshot = snapshot();
+        assert_eq!(handle(Some(&snapshot), "/nope").0, 404);
+        assert_eq!(handle(Some(&snapshot), "/tree?path=/elsewhere").0, 404);
+        assert_eq!(handle(Some(&snapshot), "/top?n=many").0, 400);
+        assert_eq!(handle(None, "/stats").0, 503);
+        assert_eq!(handle(None, "/nope").0, 404);
+    }
+}
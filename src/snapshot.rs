@@ -0,0 +1,322 @@
+//! Saved directory sizes of past scans, and the changes between them.
+//!
+//! A [`Snapshot`] records the size of every directory a scan found, relative
+//! to the scanned root, in a compact bincode file. Scans run with
+//! `--snapshot` save one per run under [`snapshot_root`], and `rudu diff`
+//! compares two of them, or the last one with a fresh scan, with [`diff`]:
+//! growth rather than absolute size is what points at the directory filling
+//! the disk.
+//!
+//! Snapshots hold directories only, so they stay small next to the cache and
+//! file-level changes show up in the totals of their directories.
+
+use crate::data::{FileEntry, ScanMeta};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Leading bytes of a snapshot file
+const MAGIC: &[u8; 8] = b"RUDUSNAP";
+
+/// Format version written after [`MAGIC`]
+const VERSION: u32 = 1;
+
+/// Extension of snapshot files
+pub const SNAPSHOT_EXTENSION: &str = "snap";
+
+/// Directory sizes of one scan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Root the scan started from
+    pub root: PathBuf,
+    /// When the scan started
+    pub taken_at: DateTime<Utc>,
+    /// Run ID of the scan, as in `stats.json` and manifests
+    pub run_id: String,
+    /// Size in bytes of every directory, by path relative to `root` (the
+    /// root itself is the empty path)
+    pub dirs: HashMap<PathBuf, u64>,
+}
+
+impl Snapshot {
+    /// The snapshot of a scan's entries; entries outside the root and
+    /// non-directories are left out.
+    ///
+    /// The root is recorded as an absolute path, so snapshots of `.` and of
+    /// the same directory by name are found and compared together.
+    pub fn from_entries(entries: &[FileEntry], meta: &ScanMeta) -> Self {
+        let dirs = entries
+            .iter()
+            .filter(|e| e.entry_type.is_dir())
+            .filter_map(|e| {
+                let relative = e.path.strip_prefix(&meta.root).ok()?;
+                Some((relative.to_path_buf(), e.size))
+            })
+            .collect();
+        Self {
+            root: absolute_root(&meta.root),
+            taken_at: meta.started_at,
+            run_id: meta.run_id.clone(),
+            dirs,
+        }
+    }
+
+    /// Writes the snapshot to `path`.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("Failed to create snapshot {}", path.display()))?;
+        let mut out = BufWriter::new(file);
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        bincode::serialize_into(&mut out, self).context("Failed to write snapshot")?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Reads the snapshot at `path`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Failed to open snapshot {}", path.display()))?;
+        let mut input = BufReader::new(file);
+        let mut header = [0u8; 12];
+        input
+            .read_exact(&mut header)
+            .ok()
+            .filter(|_| header[..8] == MAGIC[..])
+            .with_context(|| format!("{} is not a rudu snapshot", path.display()))?;
+        let version = u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+        if version != VERSION {
+            anyhow::bail!(
+                "{} is a version {} snapshot; this rudu reads version {}",
+                path.display(),
+                version,
+                VERSION
+            );
+        }
+        bincode::deserialize_from(input)
+            .with_context(|| format!("Failed to read snapshot {}", path.display()))
+    }
+
+    /// Saves the snapshot in the snapshot directory of its root, returning
+    /// the file's path.
+    pub fn save_default(&self) -> Result<PathBuf> {
+        let dir = snapshot_dir(&self.root);
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        let path = dir.join(format!(
+            "{}.{}",
+            self.taken_at.format("%Y%m%dT%H%M%SZ"),
+            SNAPSHOT_EXTENSION
+        ));
+        self.save(&path)?;
+        Ok(path)
+    }
+}
+
+/// Directory holding the snapshots of all roots: `$RUDU_SNAPSHOT_DIR`, or
+/// `snapshots` in [`data_dir`].
+pub fn snapshot_root() -> PathBuf {
+    match std::env::var_os("RUDU_SNAPSHOT_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => data_dir().join("snapshots"),
+    }
+}
+
+/// Directory for data rudu keeps across runs: `rudu` in `$XDG_DATA_HOME`
+/// (`~/.local/share`).
+pub fn data_dir() -> PathBuf {
+    std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .unwrap_or_else(std::env::temp_dir)
+        .join("rudu")
+}
+
+/// Directory holding the snapshots of `root`, named after its absolute path.
+pub fn snapshot_dir(root: &Path) -> PathBuf {
+    snapshot_root().join(crate::daemon::output_stem(&absolute_root(root)))
+}
+
+/// `root` resolved to an absolute path without symlinks, or as given if it
+/// cannot be resolved.
+pub(crate) fn absolute_root(root: &Path) -> PathBuf {
+    root.canonicalize().unwrap_or_else(|_| root.to_path_buf())
+}
+
+/// The newest saved snapshot of `root`, if any.
+pub fn latest(root: &Path) -> Option<PathBuf> {
+    std::fs::read_dir(snapshot_dir(root))
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext == SNAPSHOT_EXTENSION)
+        })
+        // Names are UTC timestamps, so the greatest is the newest
+        .max()
+}
+
+/// How a directory changed between two snapshots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// Larger than before
+    Grown,
+    /// Smaller than before
+    Shrunk,
+    /// A directory tree that did not exist before
+    New,
+    /// A directory tree that no longer exists
+    Deleted,
+}
+
+impl ChangeKind {
+    /// Lowercase name for reports
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChangeKind::Grown => "grown",
+            ChangeKind::Shrunk => "shrunk",
+            ChangeKind::New => "new",
+            ChangeKind::Deleted => "deleted",
+        }
+    }
+}
+
+/// A directory whose size changed between two snapshots
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirChange {
+    /// Path relative to the root
+    pub path: PathBuf,
+    /// Size in the older snapshot, if the directory was in it
+    pub old_size: Option<u64>,
+    /// Size in the newer snapshot, if the directory is in it
+    pub new_size: Option<u64>,
+}
+
+impl DirChange {
+    /// Whether the directory grew, shrank, appeared or disappeared
+    pub fn kind(&self) -> ChangeKind {
+        match (self.old_size, self.new_size) {
+            (None, _) => ChangeKind::New,
+            (_, None) => ChangeKind::Deleted,
+            (Some(old), Some(new)) if new >= old => ChangeKind::Grown,
+            _ => ChangeKind::Shrunk,
+        }
+    }
+
+    /// Change in bytes, negative when space was freed
+    pub fn delta(&self) -> i64 {
+        self.new_size.unwrap_or(0) as i64 - self.old_size.unwrap_or(0) as i64
+    }
+}
+
+/// The directories whose size changed from `old` to `new` by at least
+/// `min_change` bytes, largest change first.
+///
+/// New and deleted trees are reported once, at their topmost directory,
+/// rather than once per directory inside them.
+pub fn diff(old: &Snapshot, new: &Snapshot, min_change: u64) -> Vec<DirChange> {
+    let mut changes: Vec<DirChange> = Vec::new();
+    for (path, &new_size) in &new.dirs {
+        let old_size = old.dirs.get(path).copied();
+        let appeared_with_parent = old_size.is_none()
+            && path.parent().is_some_and(|parent| {
+                new.dirs.contains_key(parent) && !old.dirs.contains_key(parent)
+            });
+        if old_size != Some(new_size) && !appeared_with_parent {
+            changes.push(DirChange {
+                path: path.clone(),
+                old_size,
+                new_size: Some(new_size),
+            });
+        }
+    }
+    for (path, &old_size) in &old.dirs {
+        let gone_with_parent = path
+            .parent()
+            .is_some_and(|parent| old.dirs.contains_key(parent) && !new.dirs.contains_key(parent));
+        if !new.dirs.contains_key(path) && !gone_with_parent {
+            changes.push(DirChange {
+                path: path.clone(),
+                old_size: Some(old_size),
+                new_size: None,
+            });
+        }
+    }
+
+    changes.retain(|c| c.delta().unsigned_abs() >= min_change.max(1));
+    changes.sort_by(|a, b| {
+        b.delta()
+            .unsigned_abs()
+            .cmp(&a.delta().unsigned_abs())
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(dirs: &[(&str, u64)]) -> Snapshot {
+        Snapshot {
+            root: PathBuf::from("/data"),
+            taken_at: Utc::now(),
+            run_id: "test".to_string(),
+            dirs: dirs
+                .iter()
+                .map(|(path, size)| (PathBuf::from(path), *size))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_diff_reports_growth_and_whole_trees() {
+        let old = snapshot(&[
+            ("", 1000),
+            ("a", 600),
+            ("b", 300),
+            ("gone", 100),
+            ("gone/sub", 50),
+            ("same", 0),
+        ]);
+        let new = snapshot(&[
+            ("", 1900),
+            ("a", 1500),
+            ("b", 200),
+            ("fresh", 200),
+            ("fresh/sub", 150),
+            ("same", 0),
+        ]);
+        let changes = diff(&old, &new, 0);
+        let summary: Vec<(&str, ChangeKind, i64)> = changes
+            .iter()
+            .map(|c| (c.path.to_str().unwrap(), c.kind(), c.delta()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("", ChangeKind::Grown, 900),
+                ("a", ChangeKind::Grown, 900),
+                ("fresh", ChangeKind::New, 200),
+                ("b", ChangeKind::Shrunk, -100),
+                ("gone", ChangeKind::Deleted, -100),
+            ]
+        );
+        assert_eq!(diff(&old, &new, 500).len(), 2);
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("one.snap");
+        let saved = snapshot(&[("", 10), ("a/b", 5)]);
+        saved.save(&path).unwrap();
+        assert_eq!(Snapshot::load(&path).unwrap(), saved);
+
+        std::fs::write(&path, b"not a snapshot").unwrap();
+        assert!(Snapshot::load(&path).is_err());
+    }
+}
@@ -0,0 +1,441 @@
+//! Temporary on-disk store for scan entries under memory pressure.
+//!
+//! When a scan's [`MemoryMonitor`](crate::memory::MemoryMonitor) reports that
+//! memory use is nearing its limit, the scanner stops keeping walked files in
+//! memory until the end of the walk. Each file is measured as it is reached
+//! and its finished [`FileEntry`] is appended to an [`EntrySpill`], which
+//! sorts them in runs of [`RUN_LEN`] entries and writes each run out to a
+//! temporary file.
+//!
+//! This drops the per-file bookkeeping of the walk (directory entries, stat
+//! jobs and their parent path lists), which is several times the size of the
+//! entries themselves, so large scans can finish instead of stopping early.
+//! The finished store, [`SpilledEntries`], is not read back as a whole:
+//! [`SpilledEntries::merge`] merges its runs with the entries kept in memory
+//! one entry at a time, so output can be written without holding the
+//! listing.
+//!
+//! The file is created in the system temporary directory (`$TMPDIR`) and
+//! unlinked right away, so it disappears with the store even if the process
+//! is killed.
+
+use crate::data::{EntryStat, EntryType, FileEntry, SortKey};
+use crate::utils::{compare_entries, sort_entries};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Seek};
+use std::os::unix::fs::FileExt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{self, AtomicU64};
+
+/// Entries sorted in memory and written out together as one run
+pub const RUN_LEN: usize = 65_536;
+
+/// Distinguishes the stores of one process in their file names
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// An entry as written to the spill file.
+///
+/// `FileEntry` skips empty optional fields when serialized, which bincode,
+/// having no field names, cannot read back; this mirror writes every field.
+#[derive(Serialize, Deserialize)]
+struct Record {
+    path: PathBuf,
+    size: u64,
+    owner: Option<String>,
+    inodes: Option<u64>,
+    entry_type: EntryType,
+    previous_size: Option<u64>,
+    mtime: Option<u64>,
+    stat: Option<EntryStat>,
+}
+
+impl From<FileEntry> for Record {
+    fn from(entry: FileEntry) -> Self {
+        Self {
+            path: entry.path,
+            size: entry.size,
+            owner: entry.owner,
+            inodes: entry.inodes,
+            entry_type: entry.entry_type,
+            previous_size: entry.previous_size,
+            mtime: entry.mtime,
+            stat: entry.stat.map(|stat| *stat),
+        }
+    }
+}
+
+impl From<Record> for FileEntry {
+    fn from(record: Record) -> Self {
+        Self {
+            path: record.path,
+            size: record.size,
+            owner: record.owner,
+            inodes: record.inodes,
+            entry_type: record.entry_type,
+            previous_size: record.previous_size,
+            mtime: record.mtime,
+            stat: record.stat.map(Box::new),
+        }
+    }
+}
+
+/// The order runs are sorted in, as passed to [`sort_entries`]
+#[derive(Debug)]
+struct Order {
+    keys: Vec<SortKey>,
+    reverse: bool,
+}
+
+/// A sorted run of entries in the spill file
+#[derive(Debug, Clone, Copy)]
+struct Run {
+    /// Offset of its first record
+    start: u64,
+    /// Number of records
+    len: usize,
+}
+
+/// Append-only store of entries in an unlinked temporary file
+pub struct EntrySpill {
+    file: BufWriter<File>,
+    run: Vec<FileEntry>,
+    runs: Vec<Run>,
+    order: Order,
+    len: usize,
+}
+
+impl EntrySpill {
+    /// Creates an empty store backed by a new temporary file, whose runs are
+    /// sorted by `keys` as [`sort_entries`] would sort them. With no keys the
+    /// entries keep the order they were added in.
+    pub fn new(keys: &[SortKey], reverse: bool) -> Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "rudu-spill-{}-{}",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, atomic::Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .with_context(|| format!("Failed to create spill file {}", path.display()))?;
+        // The open handle keeps the data; nothing is left behind on exit
+        let _ = std::fs::remove_file(&path);
+        Ok(Self {
+            file: BufWriter::new(file),
+            run: Vec::new(),
+            runs: Vec::new(),
+            order: Order {
+                keys: keys.to_vec(),
+                reverse,
+            },
+            len: 0,
+        })
+    }
+
+    /// Adds `entry`, writing out the current run once it is full.
+    pub fn push(&mut self, entry: FileEntry) -> Result<()> {
+        self.run.push(entry);
+        self.len += 1;
+        if self.run.len() >= RUN_LEN {
+            self.write_run()?;
+        }
+        Ok(())
+    }
+
+    /// Number of entries stored
+    #[allow(dead_code)] // the scanner counts files itself; kept for library users
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no entries were stored
+    #[allow(dead_code)] // kept for library users
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Sorts the buffered entries and appends them to the file as a run.
+    fn write_run(&mut self) -> Result<()> {
+        if self.run.is_empty() {
+            return Ok(());
+        }
+        sort_entries(&mut self.run, &self.order.keys, self.order.reverse);
+        let start = self
+            .file
+            .stream_position()
+            .context("Failed to write entries to the spill file")?;
+        let len = self.run.len();
+        for entry in self.run.drain(..) {
+            bincode::serialize_into(&mut self.file, &Record::from(entry))
+                .context("Failed to write entries to the spill file")?;
+        }
+        self.runs.push(Run { start, len });
+        Ok(())
+    }
+
+    /// Writes out the last run and closes the store for adding.
+    pub fn finish(mut self) -> Result<SpilledEntries> {
+        self.write_run()?;
+        let file = self
+            .file
+            .into_inner()
+            .map_err(|e| e.into_error())
+            .context("Failed to flush the spill file")?;
+        Ok(SpilledEntries {
+            file: Arc::new(file),
+            runs: self.runs,
+            order: Arc::new(self.order),
+            len: self.len,
+        })
+    }
+}
+
+/// The entries of a finished [`EntrySpill`], kept on disk as sorted runs
+#[derive(Debug)]
+pub struct SpilledEntries {
+    file: Arc<File>,
+    runs: Vec<Run>,
+    order: Arc<Order>,
+    len: usize,
+}
+
+impl SpilledEntries {
+    /// Number of entries stored
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no entries were stored
+    #[allow(dead_code)] // the CLI only needs len(); kept for library users
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Merges the stored entries into `entries`, which must already be in the
+    /// order the store was created with.
+    ///
+    /// Only the next entry of each run is held in memory. Entries that
+    /// compare equal keep their order: those of `entries` first, then the
+    /// stored ones in the order they were added, as if every entry had been
+    /// appended to `entries` and stably sorted.
+    pub fn merge(self, entries: Vec<FileEntry>) -> Result<Merge> {
+        let mut merge = Merge {
+            entries: entries.into_iter(),
+            runs: self
+                .runs
+                .iter()
+                .map(|run| RunReader {
+                    reader: BufReader::new(FileAt {
+                        file: Arc::clone(&self.file),
+                        offset: run.start,
+                    }),
+                    remaining: run.len,
+                })
+                .collect(),
+            heap: BinaryHeap::with_capacity(self.runs.len() + 1),
+            order: self.order,
+            failed: false,
+        };
+        for source in 0..=merge.runs.len() {
+            if let Some(entry) = merge.next_from(source)? {
+                merge.push_head(entry, source);
+            }
+        }
+        Ok(merge)
+    }
+}
+
+/// Reads a file from an offset of its own, so runs can share one handle
+struct FileAt {
+    file: Arc<File>,
+    offset: u64,
+}
+
+impl Read for FileAt {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.file.read_at(buf, self.offset)?;
+        self.offset += read as u64;
+        Ok(read)
+    }
+}
+
+/// The records of one run not yet merged
+struct RunReader {
+    reader: BufReader<FileAt>,
+    remaining: usize,
+}
+
+impl RunReader {
+    fn next_entry(&mut self) -> Result<Option<FileEntry>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        let record: Record =
+            bincode::deserialize_from(&mut self.reader).context("Failed to read the spill file")?;
+        Ok(Some(record.into()))
+    }
+}
+
+/// The next entry of one source of a [`Merge`]
+struct Head {
+    entry: FileEntry,
+    /// 0 for the entries kept in memory, then one per run
+    source: usize,
+    order: Arc<Order>,
+}
+
+impl Ord for Head {
+    // Reversed, so the max-heap pops the first entry, earlier sources first
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_entries(
+            &other.entry,
+            &self.entry,
+            &self.order.keys,
+            self.order.reverse,
+        )
+        .then(other.source.cmp(&self.source))
+    }
+}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Head {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Head {}
+
+/// Iterator over the entries of [`SpilledEntries::merge`], in order.
+///
+/// Yields an error and stops if the spill file cannot be read.
+pub struct Merge {
+    entries: std::vec::IntoIter<FileEntry>,
+    runs: Vec<RunReader>,
+    heap: BinaryHeap<Head>,
+    order: Arc<Order>,
+    failed: bool,
+}
+
+impl Merge {
+    fn next_from(&mut self, source: usize) -> Result<Option<FileEntry>> {
+        match source {
+            0 => Ok(self.entries.next()),
+            run => self.runs[run - 1].next_entry(),
+        }
+    }
+
+    fn push_head(&mut self, entry: FileEntry, source: usize) {
+        self.heap.push(Head {
+            entry,
+            source,
+            order: Arc::clone(&self.order),
+        });
+    }
+}
+
+impl Iterator for Merge {
+    type Item = Result<FileEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let Head { entry, source, .. } = self.heap.pop()?;
+        match self.next_from(source) {
+            Ok(Some(next)) => self.push_head(next, source),
+            Ok(None) => {}
+            Err(e) => {
+                self.failed = true;
+                return Some(Err(e));
+            }
+        }
+        Some(Ok(entry))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(i: usize) -> FileEntry {
+        FileEntry {
+            owner: i.is_multiple_of(2).then(|| "alice".to_string()),
+            mtime: Some(1_700_000_000 + i as u64),
+            stat: i.is_multiple_of(3).then(|| {
+                Box::new(EntryStat {
+                    atime: 1,
+                    nlink: 1,
+                    uid: 1000,
+                    gid: 1000,
+                    mode: 0o100644,
+                    dev: 1,
+                    ino: i as u64,
+                    apparent_size: i as u64,
+                })
+            }),
+            ..FileEntry::for_test(format!("/data/file{i}"), i as u64, EntryType::File)
+        }
+    }
+
+    fn merged(spill: EntrySpill, entries: Vec<FileEntry>) -> Vec<FileEntry> {
+        spill
+            .finish()
+            .unwrap()
+            .merge(entries)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_spilled_entries_merge_in_sort_order() {
+        let count = RUN_LEN * 2 + 17;
+        let mut spill = EntrySpill::new(&[SortKey::Size], false).unwrap();
+        assert!(spill.is_empty());
+        // Scrambled sizes, so every run holds some of each range
+        for i in 0..count {
+            spill.push(entry(i * 7919 % count)).unwrap();
+        }
+        assert_eq!(spill.len(), count);
+        let in_memory: Vec<FileEntry> = (0..50).rev().map(|i| entry(count + i * 3)).collect();
+
+        let mut expected: Vec<FileEntry> = in_memory.clone();
+        expected.extend((0..count).map(entry));
+        sort_entries(&mut expected, &[SortKey::Size], false);
+        let merged = merged(spill, in_memory);
+        assert_eq!(merged.len(), count + 50);
+        assert_eq!(merged, expected);
+    }
+
+    #[test]
+    fn test_unsorted_spill_keeps_the_order_entries_were_added_in() {
+        let count = RUN_LEN + 5;
+        let mut spill = EntrySpill::new(&[], false).unwrap();
+        for i in 3..count {
+            spill.push(entry(i)).unwrap();
+        }
+        let merged = merged(spill, (0..3).map(entry).collect());
+        assert_eq!(merged, (0..count).map(entry).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_empty_spill() {
+        let spill = EntrySpill::new(&[SortKey::Name], false).unwrap();
+        let merged = merged(spill, vec![entry(1)]);
+        assert_eq!(merged, vec![entry(1)]);
+    }
+}
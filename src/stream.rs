@@ -0,0 +1,109 @@
+//! Async scanning for tokio services (`async` feature).
+//!
+//! [`scan_stream`] runs [`crate::scan::scan_iter`] on tokio's blocking pool
+//! and hands its entries to an async task as a [`Stream`], so web backends
+//! can embed rudu without managing blocking threads themselves:
+//!
+//! ```no_run
+//! use rudu::ScanOptions;
+//! use rudu::stream::scan_stream;
+//! use std::future::poll_fn;
+//! use std::pin::Pin;
+//! use futures_core::Stream;
+//!
+//! # async fn run() -> rudu::Result<()> {
+//! let mut entries = scan_stream("/data", &ScanOptions::new());
+//! while let Some(entry) = poll_fn(|cx| Pin::new(&mut entries).poll_next(cx)).await {
+//!     let entry = entry?;
+//!     println!("{}\t{}", entry.size, entry.path.display());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! With `futures` or `tokio-stream` the loop is just `entries.next().await`.
+
+use crate::data::FileEntry;
+use crate::error::Result;
+use crate::options::ScanOptions;
+use crate::scan::scan_iter;
+use futures_core::Stream;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
+
+/// Entries buffered between the walk and the consumer before the walk waits
+const CHANNEL_CAPACITY: usize = 256;
+
+/// Streams the entries of the tree at `root`, in the order and with the
+/// options of [`scan_iter`].
+///
+/// The walk runs on tokio's blocking pool, so this must be called from
+/// within a tokio runtime. It is paced by the consumer: at most a few
+/// hundred entries are buffered, and dropping the stream stops the walk.
+pub fn scan_stream(root: impl Into<PathBuf>, options: &ScanOptions) -> ScanStream {
+    let root = root.into();
+    let options = options.clone();
+    let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+    tokio::task::spawn_blocking(move || {
+        for entry in scan_iter(&root, &options) {
+            if tx.blocking_send(entry).is_err() {
+                // The stream was dropped
+                break;
+            }
+        }
+    });
+    ScanStream { rx }
+}
+
+/// Entries of a running scan, returned by [`scan_stream`]
+#[derive(Debug)]
+pub struct ScanStream {
+    rx: mpsc::Receiver<Result<FileEntry>>,
+}
+
+impl Stream for ScanStream {
+    type Item = Result<FileEntry>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::future::poll_fn;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_scan_stream_yields_scan_iter_entries() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir(temp.path().join("sub")).unwrap();
+        fs::write(temp.path().join("sub/a.txt"), "hello").unwrap();
+        fs::write(temp.path().join("b.txt"), "world").unwrap();
+        let options = ScanOptions::new().no_cache(true);
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let mut streamed = runtime.block_on(async {
+            let mut stream = scan_stream(temp.path(), &options);
+            let mut paths = Vec::new();
+            while let Some(entry) = poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+                paths.push(entry.unwrap().path);
+            }
+            paths
+        });
+
+        let mut expected: Vec<_> = scan_iter(temp.path(), &options)
+            .map(|entry| entry.unwrap().path)
+            .collect();
+        expected.sort();
+        streamed.sort();
+        assert_eq!(streamed, expected);
+        assert_eq!(streamed.len(), 4);
+    }
+}
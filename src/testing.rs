@@ -0,0 +1,240 @@
+//! Synthetic directory trees for benchmarks and regression tests.
+//!
+//! [`TreeSpec`] describes a tree by its depth, fanout, files per directory,
+//! file sizes and their skew, and the hard links and symlinks in each
+//! directory; [`TreeSpec::generate`] writes it below a directory and reports
+//! what it created, so scans can be checked against known totals:
+//!
+//! ```no_run
+//! use rudu::ScanOptions;
+//! use rudu::scan::Scanner;
+//! use rudu::testing::TreeSpec;
+//!
+//! let root = std::path::Path::new("/tmp/rudu-bench");
+//! let stats = TreeSpec::new()
+//!     .depth(4)
+//!     .fanout(5)
+//!     .files_per_dir(20)
+//!     .file_size(16 * 1024)
+//!     .skew(1.0)
+//!     .generate(root)?;
+//! let result = Scanner::new(ScanOptions::new().no_cache(true)).scan(root)?;
+//! assert_eq!(result.meta.file_count, stats.files + stats.hardlinks + stats.symlinks);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! Trees are fully determined by the spec, so two runs with the same spec
+//! produce the same names and sizes. Files are written with real data rather
+//! than left sparse, so their disk usage matches their size rounded up to
+//! the filesystem block size.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Bytes written per call when filling a file
+const WRITE_CHUNK: usize = 64 * 1024;
+
+/// Shape of a synthetic tree
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeSpec {
+    /// Directory levels holding files, the root included
+    pub depth: usize,
+    /// Subdirectories in each directory above the last level
+    pub fanout: usize,
+    /// Regular files in each directory
+    pub files_per_dir: usize,
+    /// Size in bytes of a directory's largest file, and of every file when
+    /// `skew` is 0
+    pub file_size: u64,
+    /// How unevenly file sizes are spread: file `i` of a directory is
+    /// `file_size / (i + 1)^skew` bytes, so 0 gives equal sizes and 1 or
+    /// more a few large files among many small ones
+    pub skew: f64,
+    /// Hard links in each directory, each to one of its files
+    pub hardlinks: usize,
+    /// Symlinks in each directory, each to one of its files
+    pub symlinks: usize,
+}
+
+impl Default for TreeSpec {
+    fn default() -> Self {
+        Self {
+            depth: 3,
+            fanout: 3,
+            files_per_dir: 10,
+            file_size: 4096,
+            skew: 0.0,
+            hardlinks: 0,
+            symlinks: 0,
+        }
+    }
+}
+
+/// What [`TreeSpec::generate`] created
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TreeStats {
+    /// Directories created below the root
+    pub dirs: u64,
+    /// Regular files, hard links excluded
+    pub files: u64,
+    /// Hard links to the regular files
+    pub hardlinks: u64,
+    /// Symlinks to the regular files
+    pub symlinks: u64,
+    /// Total size of the regular files in bytes, each counted once
+    pub bytes: u64,
+}
+
+impl TreeSpec {
+    /// A spec with the defaults: depth 3, fanout 3, 10 files of 4 KiB per
+    /// directory, no skew, links or symlinks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the directory levels holding files, the root included
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Sets the subdirectories per directory
+    pub fn fanout(mut self, fanout: usize) -> Self {
+        self.fanout = fanout;
+        self
+    }
+
+    /// Sets the regular files per directory
+    pub fn files_per_dir(mut self, files: usize) -> Self {
+        self.files_per_dir = files;
+        self
+    }
+
+    /// Sets the size of a directory's largest file in bytes
+    pub fn file_size(mut self, bytes: u64) -> Self {
+        self.file_size = bytes;
+        self
+    }
+
+    /// Sets the skew of file sizes within a directory (see [`TreeSpec::skew`])
+    pub fn skew(mut self, skew: f64) -> Self {
+        self.skew = skew.max(0.0);
+        self
+    }
+
+    /// Sets the hard links per directory
+    pub fn hardlinks(mut self, links: usize) -> Self {
+        self.hardlinks = links;
+        self
+    }
+
+    /// Sets the symlinks per directory
+    pub fn symlinks(mut self, links: usize) -> Self {
+        self.symlinks = links;
+        self
+    }
+
+    /// Size in bytes of file `index` in each directory
+    pub fn size_of(&self, index: usize) -> u64 {
+        (self.file_size as f64 / ((index + 1) as f64).powf(self.skew)) as u64
+    }
+
+    /// Writes the tree below `root`, creating `root` if needed.
+    ///
+    /// Files are named `file_{i}.txt`, subdirectories `subdir_{i}`, hard
+    /// links `hardlink_{i}` and symlinks `symlink_{i}`; links point at the
+    /// directory's files in order, wrapping around when there are more links
+    /// than files. Links are skipped in directories without files.
+    ///
+    /// # Errors
+    /// The first error from creating a directory, file or link.
+    pub fn generate(&self, root: &Path) -> io::Result<TreeStats> {
+        fs::create_dir_all(root)?;
+        let mut stats = TreeStats::default();
+        if self.depth > 0 {
+            self.generate_level(root, self.depth, &mut stats)?;
+        }
+        Ok(stats)
+    }
+
+    fn generate_level(&self, dir: &Path, depth: usize, stats: &mut TreeStats) -> io::Result<()> {
+        for i in 0..self.files_per_dir {
+            let size = self.size_of(i);
+            write_file(&dir.join(format!("file_{i}.txt")), size)?;
+            stats.files += 1;
+            stats.bytes += size;
+        }
+
+        if self.files_per_dir > 0 {
+            for i in 0..self.hardlinks {
+                let target = dir.join(format!("file_{}.txt", i % self.files_per_dir));
+                fs::hard_link(target, dir.join(format!("hardlink_{i}")))?;
+                stats.hardlinks += 1;
+            }
+            for i in 0..self.symlinks {
+                let target = format!("file_{}.txt", i % self.files_per_dir);
+                std::os::unix::fs::symlink(target, dir.join(format!("symlink_{i}")))?;
+                stats.symlinks += 1;
+            }
+        }
+
+        if depth > 1 {
+            for i in 0..self.fanout {
+                let subdir = dir.join(format!("subdir_{i}"));
+                fs::create_dir(&subdir)?;
+                stats.dirs += 1;
+                self.generate_level(&subdir, depth - 1, stats)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes `size` bytes of data to a new file at `path`.
+fn write_file(path: &Path, size: u64) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    let chunk = [b'x'; WRITE_CHUNK];
+    let mut remaining = size;
+    while remaining > 0 {
+        let len = remaining.min(WRITE_CHUNK as u64) as usize;
+        file.write_all(&chunk[..len])?;
+        remaining -= len as u64;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_counts_what_it_creates() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path().join("tree");
+        let spec = TreeSpec::new()
+            .depth(3)
+            .fanout(2)
+            .files_per_dir(4)
+            .file_size(1000)
+            .skew(1.0)
+            .hardlinks(1)
+            .symlinks(2);
+        let stats = spec.generate(&root).unwrap();
+
+        // 1 + 2 + 4 directories hold files
+        assert_eq!(stats.dirs, 6);
+        assert_eq!(stats.files, 28);
+        assert_eq!(stats.hardlinks, 7);
+        assert_eq!(stats.symlinks, 14);
+        assert_eq!(stats.bytes, 7 * (1000 + 500 + 333 + 250));
+
+        let leaf = root.join("subdir_1/subdir_0");
+        assert_eq!(fs::metadata(leaf.join("file_2.txt")).unwrap().len(), 333);
+        assert_eq!(
+            fs::read_link(leaf.join("symlink_1")).unwrap(),
+            Path::new("file_1.txt")
+        );
+        assert!(!leaf.join("subdir_0").exists());
+    }
+}
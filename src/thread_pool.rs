@@ -8,12 +8,19 @@
 //! - `Fixed`: Uses a fixed number of threads
 //! - `NumCpusMinus1`: Uses number of CPUs minus 1 (leaves one CPU free)
 //! - `IOHeavy`: Optimized for I/O-heavy workloads (typically 2x CPU count)
+//!
+//! The configuration that was actually applied can be inspected afterwards
+//! with [`current_config`].
 
 use anyhow::{Context, Result};
-use clap::ValueEnum;
+use std::sync::RwLock;
+
+/// The most recently applied thread pool configuration
+static CURRENT_CONFIG: RwLock<Option<ThreadPoolConfig>> = RwLock::new(None);
 
 /// Thread pool configuration strategies.
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
 pub enum ThreadPoolStrategy {
     /// Use Rayon's default thread pool configuration
     Default,
@@ -40,6 +47,107 @@ impl ThreadPoolStrategy {
     }
 }
 
+/// Whether work runs on Rayon's global pool or a caller-owned local pool.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PoolScope {
+    /// Rayon's process-wide global pool
+    Global,
+    /// A local pool sized by `--threads`
+    Local,
+}
+
+impl PoolScope {
+    /// Returns a string representation of the scope for display purposes.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PoolScope::Global => "global",
+            PoolScope::Local => "local",
+        }
+    }
+}
+
+/// The resolved thread pool configuration for this process.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct ThreadPoolConfig {
+    /// The strategy that was selected
+    pub strategy: ThreadPoolStrategy,
+    /// Number of worker threads the strategy resolved to
+    pub threads: usize,
+    /// Number of logical CPUs available
+    pub available_cpus: usize,
+    /// Whether a global or local pool is in use
+    pub scope: PoolScope,
+}
+
+impl ThreadPoolConfig {
+    /// Returns the configuration as key/value pairs for profile metadata.
+    pub fn metadata(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("thread_pool_strategy", self.strategy.as_str().to_string()),
+            ("thread_pool_threads", self.threads.to_string()),
+            ("thread_pool_scope", self.scope.as_str().to_string()),
+            ("available_cpus", self.available_cpus.to_string()),
+        ]
+    }
+}
+
+/// Returns the thread pool configuration currently in effect.
+///
+/// If neither [`configure_pool`] nor [`record_local_pool`] has been called,
+/// this describes Rayon's default global pool.
+///
+/// # Examples
+/// ```rust
+/// use rudu::thread_pool::current_config;
+///
+/// let config = current_config();
+/// println!("{} threads ({})", config.threads, config.scope.as_str());
+/// ```
+pub fn current_config() -> ThreadPoolConfig {
+    read_config(&CURRENT_CONFIG)
+}
+
+/// Reads the configuration recorded in `slot`, falling back to Rayon's
+/// default global pool.
+fn read_config(slot: &RwLock<Option<ThreadPoolConfig>>) -> ThreadPoolConfig {
+    slot.read()
+        .ok()
+        .and_then(|config| *config)
+        .unwrap_or_else(|| ThreadPoolConfig {
+            strategy: ThreadPoolStrategy::Default,
+            threads: rayon::current_num_threads(),
+            available_cpus: num_cpus::get(),
+            scope: PoolScope::Global,
+        })
+}
+
+/// Records that a local pool with `n_threads` workers is used instead of the global pool.
+///
+/// # Arguments
+/// * `strategy` - The strategy selected on the command line
+/// * `n_threads` - Number of threads requested for the local pool
+pub fn record_local_pool(strategy: ThreadPoolStrategy, n_threads: usize) {
+    write_config(&CURRENT_CONFIG, strategy, n_threads, PoolScope::Local);
+}
+
+/// Records the configuration in `slot`; [`CURRENT_CONFIG`] outside of tests,
+/// which use their own slot so they do not race on the global one.
+fn write_config(
+    slot: &RwLock<Option<ThreadPoolConfig>>,
+    strategy: ThreadPoolStrategy,
+    threads: usize,
+    scope: PoolScope,
+) {
+    if let Ok(mut current) = slot.write() {
+        *current = Some(ThreadPoolConfig {
+            strategy,
+            threads,
+            available_cpus: num_cpus::get(),
+            scope,
+        });
+    }
+}
+
 /// Configures the global thread pool based on the given strategy and number of threads.
 ///
 /// # Arguments
@@ -62,10 +170,16 @@ pub fn configure_pool(strategy: ThreadPoolStrategy, n_threads: usize) -> Result<
         ThreadPoolStrategy::Default => {
             // Use Rayon's default configuration
             let default_threads = num_cpus::get();
-            eprintln!(
+            log::info!(
                 "🔧 Using default thread pool strategy ({} threads)",
                 default_threads
             );
+            write_config(
+                &CURRENT_CONFIG,
+                strategy,
+                default_threads,
+                PoolScope::Global,
+            );
             return Ok(default_threads);
         }
         ThreadPoolStrategy::Fixed => {
@@ -93,8 +207,9 @@ pub fn configure_pool(strategy: ThreadPoolStrategy, n_threads: usize) -> Result<
         .num_threads(actual_threads)
         .build_global()
         .context("Failed to configure thread pool")?;
+    write_config(&CURRENT_CONFIG, strategy, actual_threads, PoolScope::Global);
 
-    eprintln!(
+    log::info!(
         "🔧 Using {} strategy with {} threads",
         strategy.as_str(),
         actual_threads
@@ -118,6 +233,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_current_config_reflects_local_pool() {
+        let slot = RwLock::new(None);
+        assert_eq!(read_config(&slot).scope, PoolScope::Global);
+
+        write_config(&slot, ThreadPoolStrategy::Fixed, 3, PoolScope::Local);
+        let config = read_config(&slot);
+
+        assert_eq!(config.strategy, ThreadPoolStrategy::Fixed);
+        assert_eq!(config.threads, 3);
+        assert_eq!(config.scope, PoolScope::Local);
+        assert_eq!(config.available_cpus, num_cpus::get());
+        assert!(
+            config
+                .metadata()
+                .contains(&("thread_pool_scope", "local".to_string()))
+        );
+    }
+
     #[test]
     fn test_configure_pool_num_cpus_minus_1() {
         let cpus = num_cpus::get();
@@ -0,0 +1,246 @@
+//! Scan results as a directory tree.
+//!
+//! Scans return a flat list of [`FileEntry`] values, each carrying the total
+//! size of its subtree. [`DirNode`] rebuilds the hierarchy from their paths,
+//! with every node's own and cumulative size and the number of files and
+//! directories below it, for UIs and treemaps that would otherwise
+//! reconstruct it from path prefixes themselves:
+//!
+//! ```no_run
+//! use rudu::ScanOptions;
+//! use rudu::tree::scan_tree;
+//!
+//! let tree = scan_tree("/data".as_ref(), &ScanOptions::new())?;
+//! for child in &tree.children {
+//!     println!("{}\t{}\t{} files", child.name(), child.size, child.files);
+//! }
+//! # Ok::<(), rudu::Error>(())
+//! ```
+//!
+//! Subtrees restored from the cache are listed as directories only, so their
+//! files do not appear as children; their sizes still add up, as the
+//! difference ends up in the directory's `own_size`. Scan with
+//! [`ScanOptions::no_cache`] for a tree with every file.
+
+use crate::data::{EntryType, FileEntry};
+use crate::error::Result;
+use crate::options::ScanOptions;
+use crate::scan::Scanner;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// A file or directory with its subtree.
+///
+/// Children are ordered largest first, ties by path, so the order is the
+/// same for every scan of an unchanged tree.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DirNode {
+    /// Full path of the entry
+    pub path: PathBuf,
+    /// Type of the entry; directories only implied by the paths below them
+    /// are `Dir`
+    pub entry_type: EntryType,
+    /// Total size of the subtree in bytes, as reported by the scan
+    pub size: u64,
+    /// Part of `size` not accounted for by `children`: a file's size, and
+    /// for a directory the entries the scan did not list individually
+    pub own_size: u64,
+    /// Files and other non-directories in the subtree, excluding this node
+    pub files: u64,
+    /// Directories in the subtree, excluding this node
+    pub dirs: u64,
+    /// Entries directly below this one
+    pub children: Vec<DirNode>,
+}
+
+impl DirNode {
+    /// Builds the tree below `root` from the flat entries of a scan.
+    ///
+    /// Entries outside `root` are ignored, and entries may come in any
+    /// order. Directories between a listed entry and the root that are
+    /// missing from `entries` (e.g. cut by a depth limit) are created, with
+    /// the total of their children as their size.
+    pub fn from_entries(root: &Path, entries: &[FileEntry]) -> Self {
+        let mut arena = Arena::new(root);
+        for entry in entries.iter().filter(|e| e.path.starts_with(root)) {
+            let id = arena.node_for(&entry.path);
+            arena.nodes[id].entry_type = entry.entry_type;
+            arena.nodes[id].size = Some(entry.size);
+        }
+        arena.build(0)
+    }
+
+    /// The last component of the path, or the whole path for a root
+    /// without one (such as `/`).
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.path.display().to_string())
+    }
+
+    /// Whether the node is a directory
+    pub fn is_dir(&self) -> bool {
+        self.entry_type.is_dir()
+    }
+
+    /// Visits this node and every node below it, parents before children.
+    pub fn walk(&self, visit: &mut impl FnMut(&DirNode)) {
+        visit(self);
+        for child in &self.children {
+            child.walk(visit);
+        }
+    }
+
+    /// The node for `path` in this subtree, if it is in the tree.
+    pub fn find(&self, path: &Path) -> Option<&DirNode> {
+        let rest = path.strip_prefix(&self.path).ok()?;
+        let mut node = self;
+        for component in rest.components() {
+            node = node
+                .children
+                .iter()
+                .find(|child| child.path.file_name() == Some(component.as_os_str()))?;
+        }
+        Some(node)
+    }
+}
+
+/// Scans `root` and returns the result as a tree.
+///
+/// # Errors
+/// The errors of [`Scanner::scan`].
+pub fn scan_tree(root: &Path, options: &ScanOptions) -> Result<DirNode> {
+    let result = Scanner::new(options.clone()).scan(root)?;
+    Ok(DirNode::from_entries(root, &result.entries))
+}
+
+/// A node while the tree is being assembled
+struct Slot {
+    path: PathBuf,
+    entry_type: EntryType,
+    /// Subtree total from the scan, or `None` for an implied directory
+    size: Option<u64>,
+    children: Vec<usize>,
+}
+
+/// Nodes indexed by path, the root being node 0
+struct Arena {
+    nodes: Vec<Slot>,
+    index: HashMap<PathBuf, usize>,
+}
+
+impl Arena {
+    fn new(root: &Path) -> Self {
+        Self {
+            nodes: vec![Slot {
+                path: root.to_path_buf(),
+                entry_type: EntryType::Dir,
+                size: None,
+                children: Vec::new(),
+            }],
+            index: HashMap::from([(root.to_path_buf(), 0)]),
+        }
+    }
+
+    /// Returns the node for `path`, creating it and any missing ancestors.
+    fn node_for(&mut self, path: &Path) -> usize {
+        if let Some(&id) = self.index.get(path) {
+            return id;
+        }
+        // Only the root has no parent below it, and the root is always indexed
+        let parent = self.node_for(path.parent().unwrap_or(path));
+        let id = self.nodes.len();
+        self.nodes.push(Slot {
+            path: path.to_path_buf(),
+            entry_type: EntryType::Dir,
+            size: None,
+            children: Vec::new(),
+        });
+        self.nodes[parent].children.push(id);
+        self.index.insert(path.to_path_buf(), id);
+        id
+    }
+
+    /// Converts node `id` and its subtree into a [`DirNode`].
+    fn build(&self, id: usize) -> DirNode {
+        let slot = &self.nodes[id];
+        let mut children: Vec<DirNode> = slot.children.iter().map(|&c| self.build(c)).collect();
+        children.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+
+        let children_size: u64 = children.iter().map(|c| c.size).sum();
+        let size = slot.size.unwrap_or(children_size);
+        let (files, dirs) = children.iter().fold((0, 0), |(files, dirs), child| {
+            let (own_files, own_dirs) = if child.entry_type.is_container() {
+                (0, 1)
+            } else {
+                (1, 0)
+            };
+            (
+                files + own_files + child.files,
+                dirs + own_dirs + child.dirs,
+            )
+        });
+        DirNode {
+            path: slot.path.clone(),
+            entry_type: slot.entry_type,
+            size,
+            own_size: size.saturating_sub(children_size),
+            files,
+            dirs,
+            children,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tree_from_entries() {
+        let entries = [
+            FileEntry::for_test("/data/b/small.txt", 100, EntryType::File),
+            FileEntry::for_test("/data", 10_000, EntryType::Dir),
+            FileEntry::for_test("/data/a", 5_000, EntryType::Dir),
+            FileEntry::for_test("/data/a/big.nc", 4_000, EntryType::File),
+            FileEntry::for_test("/data/b", 100, EntryType::Dir),
+            FileEntry::for_test("/data/link", 10, EntryType::Symlink),
+            FileEntry::for_test("/elsewhere/x", 1, EntryType::File),
+        ];
+        let tree = DirNode::from_entries(Path::new("/data"), &entries);
+
+        assert_eq!(tree.size, 10_000);
+        assert_eq!(tree.own_size, 10_000 - 5_000 - 100 - 10);
+        assert_eq!((tree.files, tree.dirs), (3, 2));
+        let names: Vec<String> = tree.children.iter().map(DirNode::name).collect();
+        assert_eq!(names, ["a", "b", "link"]);
+
+        let a = tree.find(Path::new("/data/a")).unwrap();
+        assert_eq!((a.size, a.own_size, a.files, a.dirs), (5_000, 1_000, 1, 0));
+        let link = tree.find(Path::new("/data/link")).unwrap();
+        assert_eq!(link.entry_type, EntryType::Symlink);
+        assert_eq!(link.own_size, 10);
+        assert!(tree.find(Path::new("/elsewhere/x")).is_none());
+
+        let mut visited = 0;
+        tree.walk(&mut |_| visited += 1);
+        assert_eq!(visited, 6);
+    }
+
+    #[test]
+    fn test_implied_directories_sum_their_children() {
+        let entries = [
+            FileEntry::for_test("/data/deep/er/one", 300, EntryType::File),
+            FileEntry::for_test("/data/deep/er/two", 200, EntryType::File),
+        ];
+        let tree = DirNode::from_entries(Path::new("/data"), &entries);
+
+        assert_eq!(tree.size, 500);
+        assert_eq!((tree.files, tree.dirs), (2, 2));
+        let er = tree.find(Path::new("/data/deep/er")).unwrap();
+        assert_eq!((er.size, er.own_size), (500, 0));
+        assert_eq!(er.children[0].name(), "one");
+    }
+}
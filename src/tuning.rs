@@ -0,0 +1,198 @@
+//! Scan settings chosen from the filesystem being scanned.
+//!
+//! Walking a local ext4 disk and a Lustre or NFS mount are different
+//! problems: locally every `stat` is a cheap syscall and extra threads only
+//! add contention, while on a network or parallel filesystem each one is a
+//! round trip to a server and the scan goes as fast as the number of
+//! requests in flight. [`detect_fs_type`] identifies the root's filesystem
+//! from its `statfs` magic number (falling back to the mount table for
+//! FUSE and unknown magics), and [`FsTuning::for_fs_type`] picks a thread
+//! strategy and stat batch size for it.
+//!
+//! NFS clients list directories with READDIRPLUS, which returns each
+//! entry's attributes along with its name and leaves them in the client's
+//! attribute cache. A plain `stat` still asks the server whether they are
+//! current, one round trip per file; on NFS the tuning therefore stats
+//! files from the cache ([`ScanOptions::cached_attrs`](crate::ScanOptions::cached_attrs)).
+//!
+//! The CLI applies the tuning unless `--threads`, `--threads-strategy`,
+//! `--stat-batch`, `--cached-attrs` or `--no-fs-tuning` say otherwise, and
+//! logs the decision.
+
+use crate::thread_pool::ThreadPoolStrategy;
+use std::path::Path;
+
+/// Stat batch size on local filesystems, where a `stat` is too cheap to be
+/// worth a task of its own
+pub const LOCAL_STAT_BATCH: usize = 64;
+
+/// Kinds of filesystem that scan differently
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FsClass {
+    /// Block-device filesystems such as ext4, XFS and btrfs
+    Local,
+    /// Memory-backed filesystems such as tmpfs
+    Memory,
+    /// Filesystems served over the network, such as NFS and SMB
+    Network,
+    /// Parallel cluster filesystems, such as Lustre and GPFS
+    Parallel,
+    /// Anything else, scanned with the defaults
+    Unknown,
+}
+
+impl FsClass {
+    /// Lowercase name for log messages
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FsClass::Local => "local",
+            FsClass::Memory => "memory",
+            FsClass::Network => "network",
+            FsClass::Parallel => "parallel",
+            FsClass::Unknown => "unknown",
+        }
+    }
+
+    /// The class of a filesystem type as named in the mount table
+    pub fn of(fs_type: &str) -> Self {
+        match fs_type {
+            "ext2" | "ext3" | "ext4" | "xfs" | "btrfs" | "zfs" | "f2fs" | "jfs" | "reiserfs"
+            | "vfat" | "exfat" | "ntfs" | "ntfs3" | "apfs" | "hfs" => FsClass::Local,
+            "tmpfs" | "ramfs" => FsClass::Memory,
+            "lustre" | "gpfs" | "beegfs" | "ceph" | "glusterfs" | "fuse.glusterfs" | "panfs"
+            | "wekafs" => FsClass::Parallel,
+            "nfs" | "nfs4" | "cifs" | "smb3" | "smbfs" | "9p" | "afs" => FsClass::Network,
+            fs_type if fs_type.starts_with("fuse.") => FsClass::Network,
+            _ => FsClass::Unknown,
+        }
+    }
+}
+
+/// Scan settings for one filesystem type
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FsTuning {
+    /// Filesystem type, e.g. `ext4` or `lustre`
+    pub fs_type: String,
+    /// Its class
+    pub class: FsClass,
+    /// Thread strategy to scan it with
+    pub threads_strategy: ThreadPoolStrategy,
+    /// Files stat'd per parallel task (see
+    /// [`ScanOptions::stat_batch`](crate::ScanOptions::stat_batch))
+    pub stat_batch: usize,
+    /// Whether to stat files from the attribute cache that listing a
+    /// directory filled, as NFS READDIRPLUS does
+    pub cached_attrs: bool,
+}
+
+impl FsTuning {
+    /// The settings for `fs_type`.
+    ///
+    /// Network and parallel filesystems get twice as many threads as CPUs
+    /// ([`ThreadPoolStrategy::IOHeavy`]) and one file per task, to keep as
+    /// many requests in flight as possible. Local disks keep one thread per
+    /// CPU and stat files in batches of [`LOCAL_STAT_BATCH`]. NFS mounts
+    /// also take file attributes from the client's cache.
+    pub fn for_fs_type(fs_type: &str) -> Self {
+        let class = FsClass::of(fs_type);
+        let (threads_strategy, stat_batch) = match class {
+            FsClass::Network | FsClass::Parallel => (ThreadPoolStrategy::IOHeavy, 1),
+            FsClass::Local | FsClass::Memory => (ThreadPoolStrategy::Default, LOCAL_STAT_BATCH),
+            FsClass::Unknown => (ThreadPoolStrategy::Default, 1),
+        };
+        Self {
+            fs_type: fs_type.to_string(),
+            class,
+            threads_strategy,
+            stat_batch,
+            cached_attrs: fs_type.starts_with("nfs"),
+        }
+    }
+}
+
+/// Returns the type of the filesystem holding `path`, by `statfs` magic
+/// number, or from the mount table for FUSE and magics not known here.
+pub fn detect_fs_type(path: &Path) -> Option<String> {
+    match statfs_magic(path).and_then(fs_type_from_magic) {
+        Some(fs_type) if fs_type != "fuse" => Some(fs_type.to_string()),
+        magic_type => {
+            crate::utils::containing_fs_type(path).or_else(|| magic_type.map(str::to_string))
+        }
+    }
+}
+
+/// The filesystem type with `statfs` magic number `magic`, from
+/// `<linux/magic.h>` and the filesystems' own headers.
+pub fn fs_type_from_magic(magic: u64) -> Option<&'static str> {
+    Some(match magic {
+        0xEF53 => "ext4",
+        0x5846_5342 => "xfs",
+        0x9123_683E => "btrfs",
+        0x2FC1_2FC1 => "zfs",
+        0xF2F5_2010 => "f2fs",
+        0x0102_1994 => "tmpfs",
+        0x8584_58F6 => "ramfs",
+        0x6969 => "nfs",
+        0xFF53_4D42 => "cifs",
+        0xFE53_4D42 => "smb3",
+        0x0102_1997 => "9p",
+        0x0BD0_0BD0 => "lustre",
+        0x4750_4653 => "gpfs",
+        0x1983_0326 => "beegfs",
+        0x00C3_6400 => "ceph",
+        0x6573_5546 => "fuse",
+        0x794C_7630 => "overlay",
+        _ => return None,
+    })
+}
+
+/// The `f_type` magic number of the filesystem holding `path`
+#[cfg(target_os = "linux")]
+fn statfs_magic(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut buf = std::mem::MaybeUninit::<libc::statfs>::uninit();
+    if unsafe { libc::statfs(c_path.as_ptr(), buf.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    let buf = unsafe { buf.assume_init() };
+    // f_type is signed on some architectures; magics are 32-bit
+    #[allow(clippy::unnecessary_cast)]
+    Some(buf.f_type as u64 & 0xFFFF_FFFF)
+}
+
+/// Fallback for platforms whose `statfs` has no Linux magic numbers.
+#[cfg(not(target_os = "linux"))]
+fn statfs_magic(_path: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tuning_by_filesystem() {
+        assert_eq!(fs_type_from_magic(0xEF53), Some("ext4"));
+        assert_eq!(fs_type_from_magic(0x0BD0_0BD0), Some("lustre"));
+        assert_eq!(fs_type_from_magic(1), None);
+
+        let lustre = FsTuning::for_fs_type("lustre");
+        assert_eq!(lustre.class, FsClass::Parallel);
+        assert_eq!(lustre.threads_strategy, ThreadPoolStrategy::IOHeavy);
+        assert_eq!(lustre.stat_batch, 1);
+        assert!(!lustre.cached_attrs);
+        assert!(FsTuning::for_fs_type("nfs4").cached_attrs);
+        assert_eq!(FsClass::of("fuse.sshfs"), FsClass::Network);
+        let ext4 = FsTuning::for_fs_type("ext4");
+        assert_eq!(
+            (ext4.threads_strategy, ext4.stat_batch),
+            (ThreadPoolStrategy::Default, LOCAL_STAT_BATCH)
+        );
+        assert_eq!(FsTuning::for_fs_type("overlay").class, FsClass::Unknown);
+
+        // The filesystem holding the test's working directory is detected
+        assert!(detect_fs_type(Path::new(".")).is_some());
+    }
+}
@@ -5,50 +5,162 @@
 //! - Directory depth comparison
 //! - File/directory owner name resolution
 //! - Glob-based exclusion pattern parsing
+//! - Per-directory exclusion markers (`.ruduignore`, `CACHEDIR.TAG`)
 //!
 //! All functions are platform-aware and safe to use with Unix filesystems.
 //! Used throughout the main binary for performance and filtering.
 
-use crate::cli::SortKey;
-use crate::data::FileEntry;
-use anyhow::{Context, Result};
+use crate::data::SortKey;
+use crate::data::{EntryStat, EntryType, FileEntry};
+use crate::error::{Error, Result};
 use globset::{Glob, GlobSet, GlobSetBuilder};
-use libc::{c_char, getpwuid_r, passwd, stat as libc_stat, stat};
-use std::collections::HashMap;
+use libc::{
+    c_char, getgrgid_r, getgrnam_r, getpwuid_r, group, lstat as libc_lstat, passwd,
+    stat as libc_stat, stat,
+};
+use regex::{Regex, RegexSet};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
 use std::hash::{Hash, Hasher};
 use std::mem::MaybeUninit;
 use std::os::unix::ffi::OsStrExt;
 use std::process::Command;
 use std::sync::Mutex;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::{ffi::CStr, ffi::CString, path::Path};
+use std::{ffi::CStr, ffi::CString, path::Path, path::PathBuf};
 
 /// Returns the actual disk usage (in bytes) of a file or directory.
 ///
-/// Uses the `st_blocks` field from `stat()` multiplied by 512 to get
-/// the actual disk space used, similar to the `du` command.
+/// Uses the `st_blocks` field from `lstat()` multiplied by 512 to get
+/// the actual disk space used, similar to the `du` command. Like `du`, a
+/// symlink is measured itself rather than its target.
 ///
 /// # Arguments
 /// * `path` - The file or directory path to check
 ///
 /// # Returns
 /// * `u64` - The disk usage in bytes, or 0 if the path cannot be accessed
+#[allow(dead_code)] // the scanner uses disk_usage_and_mtime; kept for library users
 pub fn disk_usage(path: &Path) -> u64 {
+    disk_usage_and_mtime(path).0
+}
+
+/// Returns the disk usage (in bytes) and mtime of a file from one `stat()` call.
+///
+/// # Arguments
+/// * `path` - The file or directory path to check
+///
+/// # Returns
+/// * `(u64, Option<u64>)` - The disk usage as for [`disk_usage`], and the
+///   modification time in seconds since the epoch; `(0, None)` if the path
+///   cannot be accessed
+pub fn disk_usage_and_mtime(path: &Path) -> (u64, Option<u64>) {
+    let (size, mtime, _) = disk_usage_and_stat(path);
+    (size, mtime)
+}
+
+/// Returns the disk usage, mtime and further `stat` fields of a file from
+/// one `lstat()` call.
+///
+/// # Arguments
+/// * `path` - The file or directory path to check
+///
+/// # Returns
+/// * `(u64, Option<u64>, Option<EntryStat>)` - The disk usage and mtime as
+///   for [`disk_usage_and_mtime`], and the remaining fields; `(0, None, None)`
+///   if the path cannot be accessed
+pub fn disk_usage_and_stat(path: &Path) -> (u64, Option<u64>, Option<EntryStat>) {
     let c_path = match CString::new(path.as_os_str().as_bytes()) {
         Ok(p) => p,
-        Err(_) => return 0,
+        Err(_) => return (0, None, None),
     };
 
     // Use MaybeUninit to avoid undefined behavior with zeroed stat struct
     let mut stat_buf = std::mem::MaybeUninit::<stat>::uninit();
-    let result = unsafe { libc_stat(c_path.as_ptr(), stat_buf.as_mut_ptr()) };
+    let result = unsafe { libc_lstat(c_path.as_ptr(), stat_buf.as_mut_ptr()) };
 
     if result != 0 {
-        return 0;
+        return (0, None, None);
     }
 
     let stat_buf = unsafe { stat_buf.assume_init() };
-    (stat_buf.st_blocks as u64) * 512
+    (
+        (stat_buf.st_blocks as u64) * 512,
+        Some(stat_buf.st_mtime as u64),
+        Some(entry_stat(&stat_buf)),
+    )
+}
+
+/// Like [`disk_usage_and_stat`], but lets the kernel answer from its
+/// attribute cache instead of revalidating the file with the server.
+///
+/// On NFS the cache is filled by the READDIRPLUS replies that listed the
+/// directory, so this saves a GETATTR round trip per file; the attributes
+/// can be as old as the mount's `acregmax` (60 seconds by default). Local
+/// filesystems answer as for `lstat`. Uses `statx` with
+/// `AT_STATX_DONT_SYNC`, and `lstat` where `statx` is missing.
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+pub fn disk_usage_and_cached_stat(path: &Path) -> (u64, Option<u64>, Option<EntryStat>) {
+    let c_path = match CString::new(path.as_os_str().as_bytes()) {
+        Ok(p) => p,
+        Err(_) => return (0, None, None),
+    };
+
+    let mut statx_buf = std::mem::MaybeUninit::<libc::statx>::uninit();
+    let result = unsafe {
+        libc::statx(
+            libc::AT_FDCWD,
+            c_path.as_ptr(),
+            libc::AT_SYMLINK_NOFOLLOW | libc::AT_STATX_DONT_SYNC,
+            libc::STATX_BASIC_STATS,
+            statx_buf.as_mut_ptr(),
+        )
+    };
+
+    if result != 0 {
+        // Kernels before 4.11 and some seccomp sandboxes refuse statx
+        return match std::io::Error::last_os_error().raw_os_error() {
+            Some(libc::ENOSYS | libc::EPERM) => disk_usage_and_stat(path),
+            _ => (0, None, None),
+        };
+    }
+
+    let statx_buf = unsafe { statx_buf.assume_init() };
+    (
+        statx_buf.stx_blocks * 512,
+        Some(statx_buf.stx_mtime.tv_sec as u64),
+        Some(EntryStat {
+            atime: statx_buf.stx_atime.tv_sec.max(0) as u64,
+            nlink: u64::from(statx_buf.stx_nlink),
+            uid: statx_buf.stx_uid,
+            gid: statx_buf.stx_gid,
+            mode: u32::from(statx_buf.stx_mode),
+            dev: libc::makedev(statx_buf.stx_dev_major, statx_buf.stx_dev_minor),
+            ino: statx_buf.stx_ino,
+            apparent_size: statx_buf.stx_size,
+        }),
+    )
+}
+
+/// Fallback for platforms without `statx`: a plain [`disk_usage_and_stat`].
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+pub fn disk_usage_and_cached_stat(path: &Path) -> (u64, Option<u64>, Option<EntryStat>) {
+    disk_usage_and_stat(path)
+}
+
+/// Copies the [`EntryStat`] fields out of a `stat` buffer
+#[allow(clippy::unnecessary_cast)] // field widths differ between platforms
+fn entry_stat(stat_buf: &stat) -> EntryStat {
+    EntryStat {
+        atime: stat_buf.st_atime.max(0) as u64,
+        nlink: stat_buf.st_nlink as u64,
+        uid: stat_buf.st_uid,
+        gid: stat_buf.st_gid,
+        mode: stat_buf.st_mode as u32,
+        dev: stat_buf.st_dev as u64,
+        ino: stat_buf.st_ino as u64,
+        apparent_size: stat_buf.st_size.max(0) as u64,
+    }
 }
 
 /// Calculates how many path components lie between `root` and `path`.
@@ -59,35 +171,252 @@ pub fn path_depth(root: &Path, path: &Path) -> usize {
         .unwrap_or(0)
 }
 
-/// Sorts entries based on the provided sort key.
+/// Sorts entries by one or more sort keys.
+///
+/// Entries are compared by the first key, ties by the second, and so on;
+/// entries equal on every key keep their relative order.
 ///
 /// # Arguments
 /// * `entries` - A mutable reference to the vector of entries to sort
-/// * `sort_key` - The sorting criterion to use
+/// * `keys` - The sorting criteria, most significant first
+/// * `reverse` - Reverse the whole order, e.g. smallest first for `Size`
 ///
 /// # Behavior
 /// * `SortKey::Size` - Sorts by size in descending order (largest first)
 /// * `SortKey::Name` - Sorts by path name in ascending order
-pub fn sort_entries(entries: &mut [FileEntry], sort_key: SortKey) {
-    match sort_key {
-        SortKey::Size => entries.sort_by(|a, b| b.size.cmp(&a.size)),
-        SortKey::Name => entries.sort_by(|a, b| a.path.cmp(&b.path)),
+/// * `SortKey::Mtime` - Sorts by modification time, newest first
+/// * `SortKey::Owner` - Sorts by owner name in ascending order
+/// * `SortKey::Inodes` - Sorts by inode count in descending order
+///
+/// Entries without a modification time, owner or inode count (such as files
+/// for `Inodes`) come after those with one.
+pub fn sort_entries(entries: &mut [FileEntry], keys: &[SortKey], reverse: bool) {
+    entries.sort_by(|a, b| compare_entries(a, b, keys, reverse));
+}
+
+/// Compares two entries the way [`sort_entries`] orders them.
+pub fn compare_entries(
+    a: &FileEntry,
+    b: &FileEntry,
+    keys: &[SortKey],
+    reverse: bool,
+) -> std::cmp::Ordering {
+    let order = keys
+        .iter()
+        .map(|key| match key {
+            SortKey::Size => b.size.cmp(&a.size),
+            SortKey::Name => a.path.cmp(&b.path),
+            SortKey::Mtime => b.mtime.cmp(&a.mtime),
+            SortKey::Owner => (a.owner.is_none(), &a.owner).cmp(&(b.owner.is_none(), &b.owner)),
+            SortKey::Inodes => b.inodes.cmp(&a.inodes),
+        })
+        .find(|order| order.is_ne())
+        .unwrap_or(std::cmp::Ordering::Equal);
+    if reverse { order.reverse() } else { order }
+}
+
+/// Selects the `n` largest entries accepted by `keep`, largest first.
+///
+/// Uses a bounded min-heap, so selecting from millions of entries costs
+/// O(len · log n) rather than a full sort. Entries of equal size keep their
+/// relative order.
+///
+/// # Arguments
+/// * `entries` - Entries to select from
+/// * `n` - Maximum number of entries to return
+/// * `keep` - Which entries take part in the ranking
+pub fn top_entries(
+    entries: Vec<FileEntry>,
+    n: usize,
+    keep: impl Fn(&FileEntry) -> bool,
+) -> Vec<FileEntry> {
+    // Min-heap on (size, earlier first): the top is the next entry to evict
+    let mut heap: BinaryHeap<Reverse<(u64, Reverse<usize>)>> = BinaryHeap::with_capacity(n + 1);
+    for (i, entry) in entries.iter().enumerate() {
+        if n == 0 || !keep(entry) {
+            continue;
+        }
+        heap.push(Reverse((entry.size, Reverse(i))));
+        if heap.len() > n {
+            heap.pop();
+        }
     }
+
+    // Ascending in `Reverse` order, i.e. largest first
+    let selected = heap.into_sorted_vec();
+    let mut slots: Vec<Option<FileEntry>> = entries.into_iter().map(Some).collect();
+    selected
+        .into_iter()
+        .filter_map(|Reverse((_, Reverse(i)))| slots[i].take())
+        .collect()
+}
+
+/// Narrows scan results down to the files selected by `--include`.
+///
+/// A file is included if its path, or any of its directories below `root`,
+/// matches `include`. Directories are kept only if they match or lead to
+/// included files or directories, with sizes and inode counts recomputed as
+/// described for [`retain_selected`]. The root is always kept. Order is
+/// preserved.
+///
+/// # Arguments
+/// * `entries` - Every entry the scan produced
+/// * `root` - The scanned root
+/// * `include` - Compiled `--include` and `--include-regex` patterns
+pub fn filter_included(
+    entries: Vec<FileEntry>,
+    root: &Path,
+    include: &PathMatcher,
+) -> Vec<FileEntry> {
+    retain_selected(entries, root, |e| {
+        e.path
+            .ancestors()
+            .take_while(|a| a.starts_with(root))
+            .any(|a| include.is_match(a))
+    })
+}
+
+/// Narrows scan results down to the files selected by `--older-than` and
+/// `--newer-than`.
+///
+/// A file is kept if it was modified before `older_than` and at or after
+/// `newer_than` (seconds since the epoch); files without a known
+/// modification time are dropped. Directories are kept if they lead to kept
+/// files, with sizes recomputed over those files. The root is always kept.
+pub fn filter_by_mtime(
+    entries: Vec<FileEntry>,
+    root: &Path,
+    older_than: Option<u64>,
+    newer_than: Option<u64>,
+) -> Vec<FileEntry> {
+    retain_selected(entries, root, |e| {
+        !e.entry_type.is_container()
+            && e.mtime.is_some_and(|mtime| {
+                older_than.is_none_or(|cutoff| mtime < cutoff)
+                    && newer_than.is_none_or(|cutoff| mtime >= cutoff)
+            })
+    })
+}
+
+/// Narrows scan results down to the files owned by group `gid` for
+/// `--group` and `--gid`.
+///
+/// Group IDs come from the extended metadata of the scan; files without it
+/// are dropped. Directories are kept if they lead to kept files, with sizes
+/// recomputed over those files. The root is always kept.
+pub fn filter_by_gid(entries: Vec<FileEntry>, root: &Path, gid: u32) -> Vec<FileEntry> {
+    retain_selected(entries, root, |e| {
+        !e.entry_type.is_container() && e.stat.as_ref().is_some_and(|stat| stat.gid == gid)
+    })
+}
+
+/// Keeps the `selected` files and directories and the directories leading
+/// to them.
+///
+/// Kept directories get their size recomputed as the total of the selected
+/// files below them and their inode count, where present, as the number of
+/// kept direct children. The root is always kept. Order is preserved.
+fn retain_selected(
+    entries: Vec<FileEntry>,
+    root: &Path,
+    selected: impl Fn(&FileEntry) -> bool,
+) -> Vec<FileEntry> {
+    let mut entries: Vec<FileEntry> = entries
+        .into_iter()
+        .filter(|e| e.entry_type.is_container() || selected(e))
+        .collect();
+
+    // Directories on the way to a selected file or directory, with the
+    // total size of the selected files below them
+    let mut totals: HashMap<PathBuf, u64> = HashMap::new();
+    for entry in &entries {
+        let (start, size) = match entry.entry_type {
+            EntryType::Dir | EntryType::Archive if selected(entry) => (0, 0),
+            EntryType::Dir | EntryType::Archive => continue,
+            _ => (1, entry.size),
+        };
+        for ancestor in entry
+            .path
+            .ancestors()
+            .skip(start)
+            .take_while(|a| a.starts_with(root))
+        {
+            *totals.entry(ancestor.to_path_buf()).or_default() += size;
+        }
+    }
+    entries.retain_mut(|e| {
+        if !e.entry_type.is_container() {
+            return true;
+        }
+        match totals.get(&e.path) {
+            Some(total) => e.size = *total,
+            None if e.path == root => e.size = 0,
+            None => return false,
+        }
+        true
+    });
+
+    let mut children: HashMap<PathBuf, u64> = HashMap::new();
+    for entry in &entries {
+        if let Some(parent) = entry.path.parent() {
+            *children.entry(parent.to_path_buf()).or_default() += 1;
+        }
+    }
+    for entry in entries.iter_mut().filter(|e| e.inodes.is_some()) {
+        entry.inodes = Some(children.get(&entry.path).copied().unwrap_or(0));
+    }
+    entries
+}
+
+/// Keeps one page of `entries`: skips the first `offset` and keeps at most
+/// `limit` of the rest.
+///
+/// # Returns
+/// * `usize` - The number of entries dropped after the page
+pub fn paginate(entries: &mut Vec<FileEntry>, offset: usize, limit: Option<usize>) -> usize {
+    entries.drain(..offset.min(entries.len()));
+    let limit = limit.unwrap_or(usize::MAX);
+    let remaining = entries.len().saturating_sub(limit);
+    entries.truncate(limit);
+    remaining
+}
+
+/// Formats a count with `,` between groups of three digits, e.g. `1,234,567`.
+pub fn format_count(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(digit);
+    }
+    out
 }
 
 // Global cache for UID to username mapping to avoid repeated segfaults
-static UID_CACHE: std::sync::LazyLock<Mutex<HashMap<u32, String>>> =
+static UID_CACHE: std::sync::LazyLock<Mutex<HashMap<u32, Option<String>>>> =
     std::sync::LazyLock::new(|| Mutex::new(HashMap::new()));
 
 // Flag to track if we've encountered getpwuid issues
 static GETPWUID_BROKEN: AtomicBool = AtomicBool::new(false);
 
+// Cache of GID to group name lookups, like UID_CACHE
+static GID_CACHE: std::sync::LazyLock<Mutex<HashMap<u32, Option<String>>>> =
+    std::sync::LazyLock::new(|| Mutex::new(HashMap::new()));
+
 /// Fallback function to resolve UID to username using getent command
 /// This is used when getpwuid_r fails but getent works
 fn resolve_uid_with_getent(uid: u32) -> Option<String> {
+    resolve_id_with_getent("passwd", uid)
+}
+
+/// Looks up `id` in the `database` (`passwd` or `group`) with getent,
+/// returning the name of its entry
+fn resolve_id_with_getent(database: &str, id: u32) -> Option<String> {
     let output = Command::new("getent")
-        .arg("passwd")
-        .arg(uid.to_string())
+        .arg(database)
+        .arg(id.to_string())
         .output()
         .ok()?;
 
@@ -98,7 +427,8 @@ fn resolve_uid_with_getent(uid: u32) -> Option<String> {
     let output_str = String::from_utf8(output.stdout).ok()?;
     let line = output_str.trim();
 
-    // Parse passwd format: username:password:uid:gid:gecos:home:shell
+    // Parse passwd format: username:password:uid:gid:gecos:home:shell (group
+    // entries also start with the name)
     let parts: Vec<&str> = line.split(':').collect();
     if !parts.is_empty() {
         Some(parts[0].to_string())
@@ -109,13 +439,9 @@ fn resolve_uid_with_getent(uid: u32) -> Option<String> {
 
 /// Returns the username (or UID as a string) for the file or directory owner.
 ///
-/// Uses `libc::getpwuid` to resolve user ID to a username. If the username
-/// cannot be resolved, returns the numeric UID as a string.
-///
-/// This function implements several safety measures:
-/// - Thread-safe caching to avoid repeated calls for the same UID
-/// - Panic handling to prevent segfaults
-/// - Fallback to UID strings when getpwuid is broken
+/// Resolves the owner's user ID with [`user_name`]. If the username cannot
+/// be resolved, returns the numeric UID as a string. Symlinks report their
+/// own owner, not their target's.
 ///
 /// # Arguments
 /// * `path` - The file or directory path to check
@@ -127,7 +453,7 @@ pub fn get_owner(path: &Path) -> Option<String> {
 
     // Use MaybeUninit to avoid undefined behavior with zeroed stat struct
     let mut stat_buf = std::mem::MaybeUninit::<stat>::uninit();
-    let result = unsafe { libc_stat(c_path.as_ptr(), stat_buf.as_mut_ptr()) };
+    let result = unsafe { libc_lstat(c_path.as_ptr(), stat_buf.as_mut_ptr()) };
 
     if result != 0 {
         return None;
@@ -135,78 +461,89 @@ pub fn get_owner(path: &Path) -> Option<String> {
 
     let stat_buf = unsafe { stat_buf.assume_init() };
     let uid = stat_buf.st_uid;
+    Some(user_name(uid).unwrap_or_else(|| uid.to_string()))
+}
 
-    // Check if getpwuid is known to be broken
-    if GETPWUID_BROKEN.load(Ordering::Relaxed) {
-        return Some(uid.to_string());
-    }
-
+/// Returns the name of the user with ID `uid`, or `None` if there is no
+/// passwd entry for it, e.g. for the files of a departed user.
+///
+/// This function implements several safety measures:
+/// - Thread-safe caching to avoid repeated calls for the same UID
+/// - Panic handling to prevent segfaults
+/// - Fallback to `getent passwd` when getpwuid_r fails or is broken
+pub fn user_name(uid: u32) -> Option<String> {
     // Try to get from cache first
-    if let Ok(cache) = UID_CACHE.lock() {
-        if let Some(cached_name) = cache.get(&uid) {
-            return Some(cached_name.clone());
-        }
+    if let Ok(cache) = UID_CACHE.lock()
+        && let Some(cached_name) = cache.get(&uid)
+    {
+        return cached_name.clone();
     }
 
-    // Try to resolve the UID to a username using thread-safe getpwuid_r
-    let resolved_name = match std::panic::catch_unwind(|| {
-        // Use thread-safe getpwuid_r instead of getpwuid
-        let mut pwd = MaybeUninit::<passwd>::uninit();
-        let mut buf = [0u8; 4096]; // Buffer for getpwuid_r
-        let mut result: *mut passwd = std::ptr::null_mut();
-
-        let ret = unsafe {
-            getpwuid_r(
-                uid,
-                pwd.as_mut_ptr(),
-                buf.as_mut_ptr() as *mut c_char,
-                buf.len(),
-                &mut result,
-            )
-        };
-
-        // Check if getpwuid_r succeeded
-        if ret != 0 || result.is_null() {
-            return None;
-        }
-
-        // Safe to dereference result now
-        unsafe {
-            let pw_name = (*result).pw_name;
-            if pw_name.is_null() {
+    // Try to resolve the UID to a username using thread-safe getpwuid_r,
+    // unless it is known to be broken
+    let resolved = if GETPWUID_BROKEN.load(Ordering::Relaxed) {
+        Ok(None)
+    } else {
+        std::panic::catch_unwind(|| {
+            // Use thread-safe getpwuid_r instead of getpwuid
+            let mut pwd = MaybeUninit::<passwd>::uninit();
+            let mut buf = [0u8; 4096]; // Buffer for getpwuid_r
+            let mut result: *mut passwd = std::ptr::null_mut();
+
+            let ret = unsafe {
+                getpwuid_r(
+                    uid,
+                    pwd.as_mut_ptr(),
+                    buf.as_mut_ptr() as *mut c_char,
+                    buf.len(),
+                    &mut result,
+                )
+            };
+
+            // Check if getpwuid_r succeeded
+            if ret != 0 || result.is_null() {
                 return None;
             }
 
-            // Try to create a CStr from the pointer
-            let name = CStr::from_ptr(pw_name);
-            name.to_str().ok().map(String::from)
-        }
-    }) {
-        Ok(Some(username)) => username,
+            // Safe to dereference result now
+            unsafe {
+                let pw_name = (*result).pw_name;
+                if pw_name.is_null() {
+                    return None;
+                }
+
+                // Try to create a CStr from the pointer
+                let name = CStr::from_ptr(pw_name);
+                name.to_str().ok().map(String::from)
+            }
+        })
+    };
+    let resolved_name = match resolved {
+        Ok(Some(username)) => Some(username),
         Ok(None) => {
             // Try fallback to getent command
             if let Some(username) = resolve_uid_with_getent(uid) {
                 static FIRST_SUCCESS: std::sync::Once = std::sync::Once::new();
                 FIRST_SUCCESS.call_once(|| {
-                    eprintln!("Info: getpwuid_r failed but getent works. Using getent as fallback for UID resolution.");
+                    log::info!("Using getent as fallback for UID resolution.");
                 });
-                username
+                Some(username)
             } else {
                 // Both methods failed - warn but continue
                 static FIRST_WARN: std::sync::Once = std::sync::Once::new();
                 FIRST_WARN.call_once(|| {
-                    eprintln!("Warning: Failed to resolve username for UID {} (both getpwuid_r and getent failed). Further warnings will be suppressed.", uid);
+                    log::warn!("Failed to resolve username for UID {} (both getpwuid_r and getent failed). Further warnings will be suppressed.", uid);
                 });
-                uid.to_string()
+                None
             }
         }
         Err(_) => {
-            // Panic occurred - mark getpwuid as broken and fallback to UID strings
+            // Panic occurred - mark getpwuid as broken and fall back to getent
             GETPWUID_BROKEN.store(true, Ordering::Relaxed);
-            eprintln!(
-                "Warning: getpwuid() is causing segfaults. Falling back to UID display for all remaining files."
+            log::warn!(
+                "getpwuid() is causing segfaults. Falling back to getent for all remaining files."
             );
-            uid.to_string()
+            resolve_uid_with_getent(uid)
         }
     };
 
@@ -215,10 +552,91 @@ pub fn get_owner(path: &Path) -> Option<String> {
         cache.insert(uid, resolved_name.clone());
     }
 
-    Some(resolved_name)
+    resolved_name
+}
+
+/// Returns the name of the group with ID `gid`, or `None` if there is no
+/// group entry for it.
+///
+/// Lookups are cached like those of [`user_name`], and fall back to
+/// `getent group` when getgrgid_r finds nothing.
+pub fn group_name(gid: u32) -> Option<String> {
+    if let Ok(cache) = GID_CACHE.lock()
+        && let Some(cached_name) = cache.get(&gid)
+    {
+        return cached_name.clone();
+    }
+
+    let mut grp = MaybeUninit::<group>::uninit();
+    let mut buf = [0u8; 4096]; // Buffer for getgrgid_r
+    let mut result: *mut group = std::ptr::null_mut();
+    let ret = unsafe {
+        getgrgid_r(
+            gid,
+            grp.as_mut_ptr(),
+            buf.as_mut_ptr() as *mut c_char,
+            buf.len(),
+            &mut result,
+        )
+    };
+    // Safe to dereference result once getgrgid_r found an entry
+    let resolved_name = unsafe {
+        if ret == 0 && !result.is_null() && !(*result).gr_name.is_null() {
+            CStr::from_ptr((*result).gr_name)
+                .to_str()
+                .ok()
+                .map(String::from)
+        } else {
+            None
+        }
+    }
+    .or_else(|| resolve_id_with_getent("group", gid));
+
+    if let Ok(mut cache) = GID_CACHE.lock() {
+        cache.insert(gid, resolved_name.clone());
+    }
+
+    resolved_name
+}
+
+/// Returns the ID of the group called `name`, or `None` if there is no
+/// such group, falling back to `getent group` when getgrnam_r finds nothing.
+pub fn group_id(name: &str) -> Option<u32> {
+    let c_name = CString::new(name).ok()?;
+    let mut grp = MaybeUninit::<group>::uninit();
+    let mut buf = [0u8; 4096]; // Buffer for getgrnam_r
+    let mut result: *mut group = std::ptr::null_mut();
+    let ret = unsafe {
+        getgrnam_r(
+            c_name.as_ptr(),
+            grp.as_mut_ptr(),
+            buf.as_mut_ptr() as *mut c_char,
+            buf.len(),
+            &mut result,
+        )
+    };
+    if ret == 0 && !result.is_null() {
+        // Safe to dereference result now
+        return Some(unsafe { (*result).gr_gid });
+    }
+
+    // Parse group format: name:password:gid:members
+    let output = Command::new("getent")
+        .arg("group")
+        .arg(name)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    String::from_utf8(output.stdout)
+        .ok()?
+        .trim()
+        .split(':')
+        .nth(2)?
+        .parse()
+        .ok()
 }
 
-/// Expands exclude patterns into common glob forms:
+/// Expands exclude (and include) patterns into common glob forms:
 /// For example, "node_modules" becomes:
 ///   - `**/node_modules`
 ///   - `**/node_modules/**`
@@ -239,29 +657,140 @@ pub fn expand_exclude_patterns(patterns: &[String]) -> Vec<String> {
     expanded
 }
 
-/// Compiles a list of glob patterns into a `GlobSet` matcher,
+/// Reads patterns from a file such as an `--exclude-from` list.
+///
+/// Each line holds one pattern. Surrounding whitespace is trimmed, and blank
+/// lines and lines starting with `#` are ignored, as in tar and rsync
+/// exclude files.
+///
+/// # Errors
+/// Returns an error if the file cannot be read.
+pub fn read_pattern_file(path: &Path) -> Result<Vec<String>> {
+    let contents = std::fs::read_to_string(path).map_err(|source| Error::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Glob patterns plus optional regular expressions that paths are tested
+/// against, for `--exclude`/`--exclude-regex` and `--include`/`--include-regex`.
+///
+/// A path matches if any glob or any regex matches it. Regexes are searched
+/// for anywhere in the full path, so anchor them (`^`, `$`, `/`) as needed.
+#[derive(Clone, Debug)]
+pub struct PathMatcher {
+    globs: GlobSet,
+    regexes: RegexSet,
+}
+
+impl Default for PathMatcher {
+    fn default() -> Self {
+        PathMatcher {
+            globs: GlobSet::empty(),
+            regexes: RegexSet::empty(),
+        }
+    }
+}
+
+impl PathMatcher {
+    /// Adds regular expressions to match paths against.
+    ///
+    /// # Errors
+    /// Returns an error naming the first invalid expression.
+    pub fn with_regexes(mut self, patterns: &[String]) -> Result<Self> {
+        for pattern in patterns {
+            Regex::new(pattern).map_err(|err| Error::InvalidPattern {
+                pattern: pattern.clone(),
+                source: err.into(),
+            })?;
+        }
+        self.regexes = RegexSet::new(patterns).map_err(|err| Error::InvalidPattern {
+            pattern: patterns.join(" "),
+            source: err.into(),
+        })?;
+        Ok(self)
+    }
+
+    /// Whether any glob or regex matches `path`
+    pub fn is_match<P: AsRef<Path>>(&self, path: P) -> bool {
+        let path = path.as_ref();
+        self.globs.is_match(path)
+            || (!self.regexes.is_empty() && self.regexes.is_match(&path.to_string_lossy()))
+    }
+}
+
+/// Compiles a list of glob patterns into a [`PathMatcher`],
 /// which can be used to test paths efficiently.
-pub fn build_exclude_matcher(patterns: &[String]) -> Result<GlobSet> {
+pub fn build_exclude_matcher(patterns: &[String]) -> Result<PathMatcher> {
     let mut builder = GlobSetBuilder::new();
     for pattern in patterns {
-        let glob =
-            Glob::new(pattern).with_context(|| format!("Invalid glob pattern: '{}'", pattern))?;
+        let glob = Glob::new(pattern).map_err(|err| Error::InvalidPattern {
+            pattern: pattern.clone(),
+            source: err.into(),
+        })?;
         builder.add(glob);
     }
-    builder.build().context("Failed to build glob set")
+    let globs = builder.build().map_err(|err| Error::InvalidPattern {
+        pattern: patterns.join(" "),
+        source: err.into(),
+    })?;
+    Ok(PathMatcher {
+        globs,
+        regexes: RegexSet::empty(),
+    })
+}
+
+/// Name of the marker file that excludes its directory from scans
+pub const RUDUIGNORE_MARKER: &str = ".ruduignore";
+
+/// Name of the standard cache directory tag file (<https://bford.info/cachedir/>)
+pub const CACHEDIR_TAG: &str = "CACHEDIR.TAG";
+
+/// Header a `CACHEDIR.TAG` file must start with to be honored
+pub const CACHEDIR_TAG_SIGNATURE: &[u8] = b"Signature: 8a477f597d28d172789f06886806bc55";
+
+/// Returns true if `dir` opts out of scanning via an exclusion marker.
+///
+/// A directory is excluded if it contains a `.ruduignore` file (of any
+/// content) or a `CACHEDIR.TAG` file starting with the standard signature.
+pub fn has_exclude_marker(dir: &Path) -> bool {
+    if dir.join(RUDUIGNORE_MARKER).exists() {
+        return true;
+    }
+    is_cachedir_tag(&dir.join(CACHEDIR_TAG))
+}
+
+fn is_cachedir_tag(tag: &Path) -> bool {
+    use std::io::Read;
+
+    let mut header = [0u8; CACHEDIR_TAG_SIGNATURE.len()];
+    std::fs::File::open(tag)
+        .and_then(|mut f| f.read_exact(&mut header))
+        .map(|_| header == CACHEDIR_TAG_SIGNATURE)
+        .unwrap_or(false)
 }
 
 /// Directory metadata for caching purposes
 #[derive(Debug, Clone)]
 pub struct DirMetadata {
     pub mtime: u64,
+    pub ctime: u64,
     pub nlink: u64,
     #[allow(dead_code)]
     pub size: u64,
     pub owner: Option<u32>,
+    /// The remaining `stat` fields, for scans collecting extended metadata
+    pub stat: EntryStat,
 }
 
-/// Get directory metadata (mtime, nlink, size, owner) for caching
+/// Get directory metadata (mtime, ctime, nlink, size, owner) for caching
+#[allow(clippy::unnecessary_cast)] // st_nlink is narrower than u64 on some platforms
 pub fn get_dir_metadata(path: &Path) -> Option<DirMetadata> {
     let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
 
@@ -276,12 +805,185 @@ pub fn get_dir_metadata(path: &Path) -> Option<DirMetadata> {
     let stat_buf = unsafe { stat_buf.assume_init() };
     Some(DirMetadata {
         mtime: stat_buf.st_mtime as u64,
+        ctime: stat_buf.st_ctime as u64,
         nlink: stat_buf.st_nlink as u64,
         size: (stat_buf.st_blocks as u64) * 512,
         owner: Some(stat_buf.st_uid),
+        stat: entry_stat(&stat_buf),
+    })
+}
+
+/// Returns the filesystem type mounted on `path`, from `/proc/self/mounts`.
+///
+/// Returns `None` if `path` is not a mountpoint in the table, or on
+/// platforms without `/proc`.
+#[cfg(target_os = "linux")]
+pub fn mount_fs_type(path: &Path) -> Option<String> {
+    let table = std::fs::read_to_string("/proc/self/mounts").ok()?;
+    fs_type_in_mount_table(&table, path)
+}
+
+/// Fallback for platforms without a Linux-style `/proc`.
+#[cfg(not(target_os = "linux"))]
+pub fn mount_fs_type(_path: &Path) -> Option<String> {
+    None
+}
+
+/// Returns the type of the filesystem holding `path`, from `/proc/self/mounts`.
+///
+/// `path` is canonicalized first, so relative paths and symlinks resolve to
+/// the filesystem they point into. Returns `None` if that fails, or on
+/// platforms without `/proc`.
+#[cfg(target_os = "linux")]
+pub fn containing_fs_type(path: &Path) -> Option<String> {
+    let path = path.canonicalize().ok()?;
+    let table = std::fs::read_to_string("/proc/self/mounts").ok()?;
+    containing_fs_type_in_mount_table(&table, &path)
+}
+
+/// Fallback for platforms without a Linux-style `/proc`.
+#[cfg(not(target_os = "linux"))]
+pub fn containing_fs_type(_path: &Path) -> Option<String> {
+    None
+}
+
+/// Filesystem types that are typically slow to walk: network and parallel
+/// filesystems, where every `stat` is a round trip to a server
+pub const SLOW_FILESYSTEMS: &[&str] = &[
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "lustre",
+    "gpfs",
+    "beegfs",
+    "ceph",
+    "glusterfs",
+    "fuse.glusterfs",
+    "fuse.sshfs",
+    "fuse.s3fs",
+    "fuse.rclone",
+    "9p",
+];
+
+/// A filesystem in the mount table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountInfo {
+    /// Device or remote source, e.g. `/dev/sdb1` or `filer:/vol/data`
+    pub source: PathBuf,
+    /// Directory it is mounted on
+    pub mountpoint: PathBuf,
+    /// Filesystem type, e.g. `xfs`
+    pub fs_type: String,
+    /// Comma-separated mount options, e.g. `rw,relatime`
+    pub options: String,
+}
+
+impl MountInfo {
+    /// Whether `option` is one of the mount options
+    pub fn has_option(&self, option: &str) -> bool {
+        self.options.split(',').any(|o| o == option)
+    }
+}
+
+/// Looks up the filesystem type mounted on `path` in a `/proc/mounts`-style
+/// table (`device mountpoint type options ...`, with spaces and other special
+/// characters in paths escaped as octal, e.g. `\040`).
+///
+/// When several filesystems are stacked on the same mountpoint, the last
+/// (visible) one wins.
+pub fn fs_type_in_mount_table(table: &str, path: &Path) -> Option<String> {
+    mount_table_entries(table)
+        .filter(|mount| mount.mountpoint == path)
+        .last()
+        .map(|mount| mount.fs_type)
+}
+
+/// Like [`fs_type_in_mount_table`], for the mount holding `path`: the one on
+/// its longest ancestor (or itself) in the table.
+pub fn containing_fs_type_in_mount_table(table: &str, path: &Path) -> Option<String> {
+    containing_mount_in_table(table, path).map(|mount| mount.fs_type)
+}
+
+/// Returns the mount holding `path`, from `/proc/self/mounts`, with `path`
+/// canonicalized as by [`containing_fs_type`].
+#[cfg(target_os = "linux")]
+pub fn containing_mount(path: &Path) -> Option<MountInfo> {
+    let path = path.canonicalize().ok()?;
+    let table = std::fs::read_to_string("/proc/self/mounts").ok()?;
+    containing_mount_in_table(&table, &path)
+}
+
+/// Fallback for platforms without a Linux-style `/proc`.
+#[cfg(not(target_os = "linux"))]
+pub fn containing_mount(_path: &Path) -> Option<MountInfo> {
+    None
+}
+
+/// The mount on the longest ancestor of `path` (or `path` itself) in a
+/// `/proc/mounts`-style table
+pub fn containing_mount_in_table(table: &str, path: &Path) -> Option<MountInfo> {
+    let mut best: Option<(usize, MountInfo)> = None;
+    for mount in mount_table_entries(table) {
+        if path.starts_with(&mount.mountpoint) {
+            let depth = mount.mountpoint.components().count();
+            // Later entries are mounted over earlier ones on the same path
+            if best
+                .as_ref()
+                .is_none_or(|(best_depth, _)| depth >= *best_depth)
+            {
+                best = Some((depth, mount));
+            }
+        }
+    }
+    best.map(|(_, mount)| mount)
+}
+
+/// The mounts of a `/proc/mounts`-style table, in order
+fn mount_table_entries(table: &str) -> impl Iterator<Item = MountInfo> + '_ {
+    table.lines().filter_map(|line| {
+        let mut fields = line.split_whitespace();
+        let source = unescape_mount_path(fields.next()?);
+        let mountpoint = unescape_mount_path(fields.next()?);
+        let fs_type = fields.next()?;
+        Some(MountInfo {
+            source: PathBuf::from(source),
+            mountpoint: PathBuf::from(mountpoint),
+            fs_type: fs_type.to_string(),
+            options: fields.next().unwrap_or("").to_string(),
+        })
     })
 }
 
+/// Decodes the octal escapes (`\040` for a space) used for mountpoints in
+/// the mount table
+fn unescape_mount_path(field: &str) -> std::ffi::OsString {
+    use std::os::unix::ffi::OsStringExt;
+
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let octal = bytes.get(i + 1..i + 4).and_then(|digits| {
+            std::str::from_utf8(digits)
+                .ok()
+                .and_then(|digits| u8::from_str_radix(digits, 8).ok())
+        });
+        match (bytes[i], octal) {
+            (b'\\', Some(byte)) => {
+                out.push(byte);
+                i += 4;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    std::ffi::OsString::from_vec(out)
+}
+
 /// Calculate a stable, version-independent hash of a path for use in cache lookups.
 ///
 /// Uses FNV-1a rather than `DefaultHasher`, which has no cross-version stability
@@ -0,0 +1,57 @@
+//! Custom statistics gathered during a scan.
+//!
+//! An [`EntryVisitor`] given to [`crate::scan::Scanner::visitor`] sees every
+//! entry as the scan aggregates it, so library users can compute their own
+//! metrics, such as usage per billing project, in the same pass instead of
+//! walking the returned entries again.
+//!
+//! ```no_run
+//! use rudu::{EntryType, FileEntry, ScanOptions};
+//! use rudu::scan::Scanner;
+//! use rudu::visit::EntryVisitor;
+//! use std::collections::HashMap;
+//! use std::path::Path;
+//! use std::sync::Mutex;
+//!
+//! /// Bytes per top-level project directory
+//! #[derive(Default)]
+//! struct ProjectUsage(Mutex<HashMap<String, u64>>);
+//!
+//! impl EntryVisitor for ProjectUsage {
+//!     fn visit(&self, entry: &FileEntry) {
+//!         if entry.entry_type != EntryType::File {
+//!             return;
+//!         }
+//!         let Ok(rel) = entry.path.strip_prefix("/projects") else { return };
+//!         if let Some(project) = rel.iter().next() {
+//!             let project = project.to_string_lossy().into_owned();
+//!             *self.0.lock().unwrap().entry(project).or_default() += entry.size;
+//!         }
+//!     }
+//! }
+//!
+//! let usage = ProjectUsage::default();
+//! Scanner::new(ScanOptions::new())
+//!     .visitor(&usage)
+//!     .scan(Path::new("/projects"))?;
+//! # Ok::<(), rudu::Error>(())
+//! ```
+
+use crate::data::FileEntry;
+
+/// Receives every entry of a scan as it is aggregated.
+///
+/// Entries are visited from the scan's worker threads in no particular
+/// order, so implementations must be thread-safe and should keep their
+/// state behind atomics or locks. Directories are visited with their final
+/// total size; sizes from a previous run (`previous_size`) are not yet set.
+pub trait EntryVisitor: Sync {
+    /// Called once for each file and directory entry the scan builds.
+    fn visit(&self, entry: &FileEntry);
+}
+
+impl<F: Fn(&FileEntry) + Sync> EntryVisitor for F {
+    fn visit(&self, entry: &FileEntry) {
+        self(entry)
+    }
+}
@@ -1,15 +1,22 @@
-use rudu::cli::{Args, SortKey};
+use rudu::ScanOptions;
+use rudu::cli::SortKey;
 use rudu::data::EntryType;
 use rudu::memory::MemoryMonitor;
+use rudu::progress::ProgressObserver;
 use rudu::scan::{
-    scan_files_and_dirs, scan_files_and_dirs_incremental, scan_files_and_dirs_with_memory_monitor,
+    Scanner, count_entries, estimate, scan_files_and_dirs, scan_files_and_dirs_incremental,
+    scan_files_and_dirs_with_memory_monitor, scan_iter, scan_iter_matching, warm_cache,
 };
 use rudu::thread_pool::ThreadPoolStrategy;
 use rudu::utils::{build_exclude_matcher, expand_exclude_patterns, path_depth};
 use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use tempfile::TempDir;
 
+/// Serialises tests in this file that point `RUDU_CACHE_DIR` somewhere else
+static CACHE_ENV_LOCK: Mutex<()> = Mutex::new(());
+
 #[test]
 fn test_inode_counting_with_tempdir() {
     // Create a temporary directory structure for testing
@@ -42,31 +49,15 @@ fn test_inode_counting_with_tempdir() {
     fs::write(dir2.join("file4.txt"), "content4").expect("Failed to write file4");
     fs::write(root_path.join("file5.txt"), "content5").expect("Failed to write file5");
 
-    // Set up args for scanning
-    let args = Args {
-        path: root_path.to_path_buf(),
-        depth: None,
-        sort: SortKey::Name,
-        show_files: true,
-        exclude: vec![],
-        show_owner: false,
-        output: None,
-        threads: None,
-        show_inodes: true,
-        threads_strategy: ThreadPoolStrategy::Default,
-        no_cache: true,
-        cache_ttl: 604800,
-        profile: false,
-        memory_limit: None,
-        memory_check_interval_ms: 200,
-    };
+    // Set up options for scanning
+    let options = ScanOptions::new().show_inodes(true).no_cache(true);
 
-    let exclude_patterns = expand_exclude_patterns(&args.exclude);
+    let exclude_patterns = expand_exclude_patterns(&options.exclude);
     let exclude_matcher =
         build_exclude_matcher(&exclude_patterns).expect("Failed to build exclude matcher");
 
     // Scan the directory
-    let entries = scan_files_and_dirs(root_path, &args, &exclude_matcher, args.sort)
+    let entries = scan_files_and_dirs(root_path, &options, &exclude_matcher, &[SortKey::Name])
         .expect("Failed to scan directory");
 
     // Verify the results
@@ -143,31 +134,18 @@ fn test_exclude_patterns_with_tempdir() {
     fs::write(src.join("main.rs"), "fn main() {}").expect("Failed to write main.rs");
     fs::write(debug.join("app"), "binary").expect("Failed to write app");
 
-    // Set up args with exclusions
-    let args = Args {
-        path: root_path.to_path_buf(),
-        depth: None,
-        sort: SortKey::Name,
-        show_files: true,
-        exclude: vec!["node_modules".to_string(), "target".to_string()],
-        show_owner: false,
-        output: None,
-        threads: None,
-        show_inodes: true,
-        threads_strategy: ThreadPoolStrategy::Default,
-        no_cache: true,
-        cache_ttl: 604800,
-        profile: false,
-        memory_limit: None,
-        memory_check_interval_ms: 200,
-    };
+    // Set up options with exclusions
+    let options = ScanOptions::new()
+        .show_inodes(true)
+        .exclude(["node_modules", "target"])
+        .no_cache(true);
 
-    let exclude_patterns = expand_exclude_patterns(&args.exclude);
+    let exclude_patterns = expand_exclude_patterns(&options.exclude);
     let exclude_matcher =
         build_exclude_matcher(&exclude_patterns).expect("Failed to build exclude matcher");
 
     // Scan the directory
-    let entries = scan_files_and_dirs(root_path, &args, &exclude_matcher, args.sort)
+    let entries = scan_files_and_dirs(root_path, &options, &exclude_matcher, &[SortKey::Name])
         .expect("Failed to scan directory");
 
     // Verify that excluded directories are not present
@@ -223,34 +201,18 @@ fn test_depth_filtering_with_tempdir() {
         .expect("Failed to write file_at_level1.txt");
 
     // Test with depth limit of 2
-    let args = Args {
-        path: root_path.to_path_buf(),
-        depth: Some(2),
-        sort: SortKey::Name,
-        show_files: true,
-        exclude: vec![],
-        show_owner: false,
-        output: None,
-        threads: None,
-        show_inodes: true,
-        threads_strategy: ThreadPoolStrategy::Default,
-        no_cache: true,
-        cache_ttl: 604800,
-        profile: false,
-        memory_limit: None,
-        memory_check_interval_ms: 200,
-    };
+    let options = ScanOptions::new().depth(2).show_inodes(true).no_cache(true);
 
-    let exclude_patterns = expand_exclude_patterns(&args.exclude);
+    let exclude_patterns = expand_exclude_patterns(&options.exclude);
     let exclude_matcher =
         build_exclude_matcher(&exclude_patterns).expect("Failed to build exclude matcher");
 
     // Scan the directory (returns all entries; depth filtering is a display concern)
-    let entries = scan_files_and_dirs(root_path, &args, &exclude_matcher, args.sort)
+    let entries = scan_files_and_dirs(root_path, &options, &exclude_matcher, &[SortKey::Name])
         .expect("Failed to scan directory");
 
     // Apply depth filtering inline using path_depth (filter_by_depth was removed in Fix #15)
-    let depth_limit = args.depth.unwrap();
+    let depth_limit = options.depth.unwrap();
     let filtered_entries: Vec<_> = entries
         .entries
         .iter()
@@ -258,7 +220,7 @@ fn test_depth_filtering_with_tempdir() {
             let d = path_depth(root_path, &e.path);
             match e.entry_type {
                 EntryType::Dir => d <= depth_limit,
-                EntryType::File => args.show_files && d <= depth_limit,
+                _ => options.show_files && d <= depth_limit,
             }
         })
         .collect();
@@ -296,31 +258,15 @@ fn test_size_calculation_with_tempdir() {
     fs::write(root_path.join("file1.txt"), &file1_content).expect("Failed to write file1");
     fs::write(root_path.join("file2.txt"), &file2_content).expect("Failed to write file2");
 
-    // Set up args for scanning
-    let args = Args {
-        path: root_path.to_path_buf(),
-        depth: None,
-        sort: SortKey::Size,
-        show_files: true,
-        exclude: vec![],
-        show_owner: false,
-        output: None,
-        threads: None,
-        show_inodes: false,
-        threads_strategy: ThreadPoolStrategy::Default,
-        no_cache: true,
-        cache_ttl: 604800,
-        profile: false,
-        memory_limit: None,
-        memory_check_interval_ms: 200,
-    };
+    // Set up options for scanning
+    let options = ScanOptions::new().no_cache(true);
 
-    let exclude_patterns = expand_exclude_patterns(&args.exclude);
+    let exclude_patterns = expand_exclude_patterns(&options.exclude);
     let exclude_matcher =
         build_exclude_matcher(&exclude_patterns).expect("Failed to build exclude matcher");
 
     // Scan the directory
-    let entries = scan_files_and_dirs(root_path, &args, &exclude_matcher, args.sort)
+    let entries = scan_files_and_dirs(root_path, &options, &exclude_matcher, &[SortKey::Size])
         .expect("Failed to scan directory");
 
     // Find file entries
@@ -365,26 +311,10 @@ fn test_memory_limit_with_small_temp_dir() {
     fs::write(dir1.join("file1.txt"), "small content 1").expect("Failed to write file1");
     fs::write(root_path.join("file2.txt"), "small content 2").expect("Failed to write file2");
 
-    // Set up args for scanning with a very low memory limit (1 MB)
-    let args = Args {
-        path: root_path.to_path_buf(),
-        depth: None,
-        sort: SortKey::Name,
-        show_files: true,
-        exclude: vec![],
-        show_owner: false,
-        output: None,
-        threads: None,
-        show_inodes: false,
-        threads_strategy: ThreadPoolStrategy::Default,
-        no_cache: true, // Disable cache to simplify the test
-        cache_ttl: 604800,
-        profile: false,
-        memory_limit: Some(1),        // 1 MB limit - very low
-        memory_check_interval_ms: 50, // Check very frequently
-    };
+    // Check the memory limit very frequently
+    let options = ScanOptions::new().no_cache(true).memory_check_entries(500);
 
-    let exclude_patterns = expand_exclude_patterns(&args.exclude);
+    let exclude_patterns = expand_exclude_patterns(&options.exclude);
     let exclude_matcher =
         build_exclude_matcher(&exclude_patterns).expect("Failed to build exclude matcher");
 
@@ -394,9 +324,9 @@ fn test_memory_limit_with_small_temp_dir() {
     // Scan the directory with memory monitoring
     let result = scan_files_and_dirs_with_memory_monitor(
         root_path,
-        &args,
+        &options,
         &exclude_matcher,
-        args.sort,
+        &[SortKey::Name],
         Some(memory_monitor.clone()),
     );
 
@@ -452,28 +382,13 @@ fn test_incremental_scan_returns_correct_entries() {
     fs::write(alpha.join("b.txt"), vec![0u8; 4096]).unwrap();
     fs::write(beta.join("c.txt"), vec![0u8; 4096]).unwrap();
 
-    let args = Args {
-        path: root.to_path_buf(),
-        depth: None,
-        sort: SortKey::Name,
-        show_files: true,
-        exclude: vec![],
-        show_owner: false,
-        output: None,
-        threads: None,
-        show_inodes: false,
-        threads_strategy: ThreadPoolStrategy::Default,
-        no_cache: true,
-        cache_ttl: 604800,
-        profile: false,
-        memory_limit: None,
-        memory_check_interval_ms: 200,
-    };
+    let options = ScanOptions::new().no_cache(true);
 
-    let exclude_patterns = expand_exclude_patterns(&args.exclude);
+    let exclude_patterns = expand_exclude_patterns(&options.exclude);
     let exclude_matcher = build_exclude_matcher(&exclude_patterns).unwrap();
 
-    let result = scan_files_and_dirs_incremental(root, &args, &exclude_matcher, args.sort);
+    let result =
+        scan_files_and_dirs_incremental(root, &options, &exclude_matcher, &[SortKey::Name]);
     assert!(
         result.is_ok(),
         "incremental scan should not error: {:?}",
@@ -520,40 +435,24 @@ fn test_incremental_scan_second_run_uses_cache() {
     fs::write(subdir.join("data.txt"), vec![1u8; 4096]).unwrap();
 
     // Use a dedicated cache dir so the test is isolated
+    let _lock = CACHE_ENV_LOCK.lock().unwrap_or_else(|p| p.into_inner());
     let cache_dir = TempDir::new().expect("Failed to create cache temp dir");
     // SAFETY: this test is single-threaded with respect to RUDU_CACHE_DIR;
     // the variable is restored before the test returns.
     unsafe { std::env::set_var("RUDU_CACHE_DIR", cache_dir.path()) };
 
-    let make_args = || Args {
-        path: root.to_path_buf(),
-        depth: None,
-        sort: SortKey::Name,
-        show_files: true,
-        exclude: vec![],
-        show_owner: false,
-        output: None,
-        threads: None,
-        show_inodes: false,
-        threads_strategy: ThreadPoolStrategy::Default,
-        no_cache: false, // enable caching
-        cache_ttl: 604800,
-        profile: false,
-        memory_limit: None,
-        memory_check_interval_ms: 200,
-    };
+    let options = ScanOptions::new();
 
     let exclude_patterns = expand_exclude_patterns(&[]);
     let exclude_matcher = build_exclude_matcher(&exclude_patterns).unwrap();
 
     // First scan — populates the cache
-    let first =
-        scan_files_and_dirs_incremental(root, &make_args(), &exclude_matcher, SortKey::Name)
-            .expect("first scan should succeed");
+    let first = scan_files_and_dirs_incremental(root, &options, &exclude_matcher, &[SortKey::Name])
+        .expect("first scan should succeed");
 
     // Second scan — should see cache entries
     let second =
-        scan_files_and_dirs_incremental(root, &make_args(), &exclude_matcher, SortKey::Name)
+        scan_files_and_dirs_incremental(root, &options, &exclude_matcher, &[SortKey::Name])
             .expect("second scan should succeed");
 
     // SAFETY: restoring the env var we set above.
@@ -567,3 +466,811 @@ fn test_incremental_scan_second_run_uses_cache() {
         second.cache_total,
     );
 }
+
+#[test]
+fn test_exclude_marker_directories_are_skipped() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let root_path = temp_dir.path();
+
+    let kept = root_path.join("kept");
+    let scratch = root_path.join("scratch");
+    let build_cache = root_path.join("build-cache");
+    for dir in [&kept, &scratch, &build_cache] {
+        fs::create_dir(dir).expect("Failed to create dir");
+        fs::write(dir.join("data.bin"), vec![0u8; 8192]).expect("Failed to write file");
+    }
+    fs::write(scratch.join(".ruduignore"), "").expect("Failed to write marker");
+    fs::write(
+        build_cache.join("CACHEDIR.TAG"),
+        "Signature: 8a477f597d28d172789f06886806bc55\n",
+    )
+    .expect("Failed to write tag");
+
+    let mut options = ScanOptions::new().no_cache(true);
+    let exclude_matcher = build_exclude_matcher(&[]).expect("Failed to build exclude matcher");
+
+    let result = scan_files_and_dirs(root_path, &options, &exclude_matcher, &[SortKey::Name])
+        .expect("Failed to scan directory");
+    assert!(result.entries.iter().any(|e| e.path == kept));
+    assert!(!result.entries.iter().any(|e| e.path.starts_with(&scratch)));
+    assert!(
+        !result
+            .entries
+            .iter()
+            .any(|e| e.path.starts_with(&build_cache))
+    );
+
+    // The override flag scans marked directories as usual
+    options = options.no_exclude_markers(true);
+    let result = scan_files_and_dirs(root_path, &options, &exclude_matcher, &[SortKey::Name])
+        .expect("Failed to scan directory");
+    assert!(result.entries.iter().any(|e| e.path == scratch));
+    assert!(result.entries.iter().any(|e| e.path == build_cache));
+}
+
+#[test]
+fn test_cache_warm_populates_cache_for_next_scan() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let root = temp_dir.path();
+    fs::create_dir(root.join("warm_dir")).unwrap();
+    fs::write(root.join("warm_dir").join("data.txt"), vec![1u8; 4096]).unwrap();
+
+    let _lock = CACHE_ENV_LOCK.lock().unwrap_or_else(|p| p.into_inner());
+    let cache_dir = TempDir::new().expect("Failed to create cache temp dir");
+    // SAFETY: serialised by CACHE_ENV_LOCK and restored before returning.
+    unsafe { std::env::set_var("RUDU_CACHE_DIR", cache_dir.path()) };
+
+    let options = ScanOptions::new().show_owner(true).no_cache(true);
+    let exclude_matcher = build_exclude_matcher(&[]).unwrap();
+
+    let warmed = warm_cache(root, &options, &exclude_matcher).expect("warm should succeed");
+    // Owner lookups are skipped while warming
+    assert!(warmed.entries.iter().all(|e| e.owner.is_none()));
+
+    let scan_options = options.no_cache(false);
+    let second =
+        scan_files_and_dirs_incremental(root, &scan_options, &exclude_matcher, &[SortKey::Size])
+            .expect("scan should succeed");
+
+    // SAFETY: restoring the env var we set above.
+    unsafe { std::env::remove_var("RUDU_CACHE_DIR") };
+
+    assert!(
+        second.cache_hits > 0,
+        "scan after warm should hit the cache"
+    );
+}
+
+#[test]
+fn test_estimate_counts_entries_and_uses_cached_sizes() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let root = temp_dir.path();
+    fs::create_dir_all(root.join("a").join("b")).unwrap();
+    fs::write(root.join("a").join("one.txt"), vec![1u8; 4096]).unwrap();
+    fs::write(root.join("a").join("b").join("two.txt"), vec![2u8; 4096]).unwrap();
+
+    let _lock = CACHE_ENV_LOCK.lock().unwrap_or_else(|p| p.into_inner());
+    let cache_dir = TempDir::new().expect("Failed to create cache temp dir");
+    // SAFETY: serialised by CACHE_ENV_LOCK and restored before returning.
+    unsafe { std::env::set_var("RUDU_CACHE_DIR", cache_dir.path()) };
+
+    let options = ScanOptions::new().show_owner(true);
+    let exclude_matcher = build_exclude_matcher(&[]).unwrap();
+
+    let cold = estimate(root, &options, &exclude_matcher).expect("estimate should succeed");
+    let warmed = warm_cache(root, &options, &exclude_matcher).expect("warm should succeed");
+    let warm = estimate(root, &options, &exclude_matcher).expect("estimate should succeed");
+
+    // SAFETY: restoring the env var we set above.
+    unsafe { std::env::remove_var("RUDU_CACHE_DIR") };
+
+    assert_eq!((cold.files, cold.dirs), (2, 3));
+    assert_eq!(cold.cached_dirs, 0);
+    assert_eq!(cold.uncached_files, 2);
+
+    let root_size = warmed
+        .entries
+        .iter()
+        .find(|e| e.path == root)
+        .map(|e| e.size)
+        .unwrap();
+    assert_eq!((warm.files, warm.dirs), (2, 3));
+    assert_eq!(
+        warm.cached_dirs, 1,
+        "only the outermost cached directory counts"
+    );
+    assert_eq!(warm.cached_size, root_size);
+    assert_eq!(warm.uncached_files, 0);
+}
+
+#[test]
+fn test_count_entries_recursive_per_directory() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let root = temp_dir.path();
+    fs::create_dir_all(root.join("a").join("b")).unwrap();
+    fs::create_dir(root.join("c")).unwrap();
+    fs::write(root.join("top.txt"), b"x").unwrap();
+    fs::write(root.join("a").join("one.txt"), b"x").unwrap();
+    fs::write(root.join("a").join("b").join("two.txt"), b"x").unwrap();
+    fs::write(root.join("a").join("b").join("three.txt"), b"x").unwrap();
+
+    let options = ScanOptions::new().show_owner(true);
+    let exclude_matcher = build_exclude_matcher(&[]).unwrap();
+
+    let (counts, meta) =
+        count_entries(root, &options, &exclude_matcher).expect("count should succeed");
+    let of = |path: PathBuf| {
+        counts
+            .iter()
+            .find(|c| c.path == path)
+            .map(|c| (c.files, c.dirs))
+            .unwrap()
+    };
+
+    assert_eq!(counts.len(), 4);
+    assert_eq!(of(root.to_path_buf()), (4, 3));
+    assert_eq!(of(root.join("a")), (3, 1));
+    assert_eq!(of(root.join("a").join("b")), (2, 0));
+    assert_eq!(of(root.join("c")), (0, 0));
+    assert_eq!((meta.file_count, meta.dir_count), (4, 4));
+}
+
+#[test]
+fn test_exclude_regex_and_exclude_from_are_not_served_from_cache() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let root = temp_dir.path().join("tree");
+    fs::create_dir_all(root.join("a/skipme")).unwrap();
+    fs::create_dir_all(root.join("a/keep")).unwrap();
+    fs::write(root.join("a/skipme/big.bin"), vec![1u8; 64 * 1024]).unwrap();
+    fs::write(root.join("a/keep/small.bin"), vec![2u8; 4096]).unwrap();
+    let pattern_file = temp_dir.path().join("excludes.txt");
+    fs::write(&pattern_file, "skipme\n").unwrap();
+    let cache_dir = TempDir::new().expect("Failed to create cache temp dir");
+
+    // Size of `a` in NDJSON output of a CLI run sharing `cache_dir`
+    let size_of_a = |extra: &[&str]| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_rudu"))
+            .arg("-q")
+            .arg(&root)
+            .args(["--format", "ndjson"])
+            .args(extra)
+            .env("RUDU_CACHE_DIR", cache_dir.path())
+            .output()
+            .expect("rudu should run");
+        assert!(output.status.success(), "rudu failed: {output:?}");
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<rudu::FileEntry>(line).unwrap())
+            .find(|entry| entry.path == root.join("a"))
+            .map(|entry| entry.size)
+            .unwrap()
+    };
+
+    // The first run caches `a` with `skipme` counted in
+    let full = size_of_a(&[]);
+    let cached = size_of_a(&[]);
+    assert_eq!(full, cached);
+    let excluded = size_of_a(&["--exclude-regex", "/skipme$"]);
+    assert!(
+        excluded < full,
+        "{excluded} should leave skipme out of {full}"
+    );
+    let pattern_file = pattern_file.to_str().unwrap();
+    assert_eq!(size_of_a(&["--exclude-from", pattern_file]), excluded);
+}
+
+#[test]
+fn test_in_tree_cache_is_excluded_and_survives_its_own_write() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let root = temp_dir.path();
+    fs::create_dir(root.join("shared")).unwrap();
+    fs::write(root.join("shared").join("data.txt"), vec![1u8; 4096]).unwrap();
+
+    let options = ScanOptions::new().cache_location(rudu::cache::CacheLocation::InTree);
+    let exclude_matcher = build_exclude_matcher(&[]).unwrap();
+
+    let first = scan_files_and_dirs_incremental(root, &options, &exclude_matcher, &[SortKey::Name])
+        .expect("scan should succeed");
+    let cache_file = root.join(rudu::cache::model::IN_TREE_CACHE_FILE);
+    assert!(cache_file.is_file(), "cache should be written in-tree");
+
+    // A later `auto` scan picks up the shared cache; the cache file itself is
+    // neither listed nor counted, and writing it did not invalidate it
+    let auto_options = options
+        .clone()
+        .cache_location(rudu::cache::CacheLocation::Auto);
+    let second =
+        scan_files_and_dirs_incremental(root, &auto_options, &exclude_matcher, &[SortKey::Name])
+            .expect("scan should succeed");
+
+    for result in [&first, &second] {
+        assert!(result.entries.iter().all(|e| e.path != cache_file));
+    }
+    let root_size =
+        |entries: &[rudu::data::FileEntry]| entries.iter().find(|e| e.path == root).map(|e| e.size);
+    assert_eq!(root_size(&first.entries), root_size(&second.entries));
+    assert!(
+        second.cache_hits > 0,
+        "second scan should reuse the in-tree cache"
+    );
+}
+
+#[test]
+fn test_scan_with_options_builder() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let root = temp_dir.path();
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::create_dir_all(root.join("target")).unwrap();
+    fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+    fs::write(root.join("target/app"), vec![0u8; 8192]).unwrap();
+
+    // Library callers need no CLI arguments at all
+    let options = ScanOptions::new()
+        .exclude(["target"])
+        .show_inodes(true)
+        .no_cache(true);
+    let exclude_matcher =
+        build_exclude_matcher(&expand_exclude_patterns(&options.exclude)).unwrap();
+    let result = scan_files_and_dirs(root, &options, &exclude_matcher, &[SortKey::Name])
+        .expect("scan should succeed");
+
+    let paths: Vec<PathBuf> = result.entries.iter().map(|e| e.path.clone()).collect();
+    assert_eq!(
+        paths,
+        [
+            root.to_path_buf(),
+            root.join("src"),
+            root.join("src/main.rs")
+        ]
+    );
+    assert_eq!(result.entries[1].inodes, Some(1));
+    assert_eq!(result.meta.flags.exclude, ["target"]);
+}
+
+/// Records the progress events of a scan
+#[derive(Default)]
+struct RecordingObserver {
+    phases: Mutex<Vec<String>>,
+    entries: Mutex<Vec<PathBuf>>,
+    cache_hits: Mutex<Vec<PathBuf>>,
+}
+
+impl ProgressObserver for RecordingObserver {
+    fn on_phase_start(&self, phase: &str) {
+        self.phases.lock().unwrap().push(phase.to_string());
+    }
+
+    fn on_entry(&self, path: &Path) {
+        self.entries.lock().unwrap().push(path.to_path_buf());
+    }
+
+    fn on_cache_hit(&self, path: &Path) {
+        self.cache_hits.lock().unwrap().push(path.to_path_buf());
+    }
+}
+
+#[test]
+fn test_scanner_reports_progress_to_observer() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let root = temp_dir.path();
+    fs::create_dir(root.join("sub")).unwrap();
+    fs::write(root.join("sub/data.txt"), vec![1u8; 4096]).unwrap();
+
+    let options = ScanOptions::new().cache_location(rudu::cache::CacheLocation::InTree);
+    let first = RecordingObserver::default();
+    let result = Scanner::new(options.clone())
+        .sort(&[SortKey::Name])
+        .observer(&first)
+        .scan(root)
+        .expect("scan should succeed");
+    assert_eq!(result.entries.len(), 3);
+    assert_eq!(
+        *first.phases.lock().unwrap(),
+        ["Cache-load", "WalkDir", "Disk-usage I/O", "Aggregation"]
+    );
+    assert_eq!(
+        *first.entries.lock().unwrap(),
+        [
+            root.to_path_buf(),
+            root.join("sub"),
+            root.join("sub/data.txt")
+        ]
+    );
+    assert!(first.cache_hits.lock().unwrap().is_empty());
+
+    let second = RecordingObserver::default();
+    // The unchanged root's directories are restored without walking it
+    let result = Scanner::new(options)
+        .observer(&second)
+        .scan(root)
+        .expect("rescan should succeed");
+    assert_eq!(result.entries.len(), 2);
+    assert_eq!(*second.cache_hits.lock().unwrap(), [root.to_path_buf()]);
+    assert!(second.entries.lock().unwrap().is_empty());
+}
+
+#[test]
+fn test_scan_iter_streams_entries_after_their_subtrees() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let root = temp_dir.path();
+    fs::create_dir_all(root.join("a/b")).unwrap();
+    fs::create_dir(root.join("c")).unwrap();
+    fs::write(root.join("a/one.txt"), vec![1u8; 4096]).unwrap();
+    fs::write(root.join("a/b/two.txt"), vec![2u8; 8192]).unwrap();
+    fs::write(root.join("c/three.txt"), vec![3u8; 4096]).unwrap();
+
+    let options = ScanOptions::new().no_cache(true).show_inodes(true);
+    let streamed: Vec<_> = scan_iter(root, &options)
+        .collect::<rudu::Result<_>>()
+        .expect("scan should succeed");
+
+    // Every directory follows everything below it, and the root comes last
+    for (i, entry) in streamed.iter().enumerate() {
+        assert!(
+            streamed[i + 1..]
+                .iter()
+                .all(|later| !later.path.starts_with(&entry.path) || later.path == entry.path),
+            "{} came before entries below it",
+            entry.path.display()
+        );
+    }
+    assert_eq!(streamed.last().map(|e| e.path.as_path()), Some(root));
+
+    // Sizes and inode counts match a regular scan
+    let exclude_matcher = build_exclude_matcher(&[]).unwrap();
+    let mut scanned = scan_files_and_dirs(root, &options, &exclude_matcher, &[SortKey::Name])
+        .expect("scan should succeed")
+        .entries;
+    let mut streamed = streamed;
+    streamed.sort_by(|a, b| a.path.cmp(&b.path));
+    scanned.sort_by(|a, b| a.path.cmp(&b.path));
+    let summary = |entries: &[rudu::data::FileEntry]| {
+        entries
+            .iter()
+            .map(|e| (e.path.clone(), e.size, e.inodes, e.entry_type))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(summary(&streamed), summary(&scanned));
+
+    let root_only: Vec<_> = scan_iter(root, &options.summarize(true))
+        .map(|e| e.unwrap().path)
+        .collect();
+    assert_eq!(root_only, [root.to_path_buf()]);
+}
+
+#[test]
+fn test_scan_iter_matching_skips_regex_exclusions() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let root = temp_dir.path();
+    fs::create_dir_all(root.join("keep")).unwrap();
+    fs::create_dir_all(root.join("build-1234")).unwrap();
+    fs::write(root.join("keep/data.txt"), vec![1u8; 4096]).unwrap();
+    fs::write(root.join("build-1234/out.o"), vec![2u8; 8192]).unwrap();
+    fs::write(root.join("keep/scratch.tmp"), vec![3u8; 4096]).unwrap();
+
+    let options = ScanOptions::new().no_cache(true).exclude(["*.tmp"]);
+    let exclude_matcher = build_exclude_matcher(&expand_exclude_patterns(&options.exclude))
+        .unwrap()
+        .with_regexes(&[r"/build-\d+$".to_string()])
+        .unwrap();
+    let streamed: Vec<_> = scan_iter_matching(root, &options, &exclude_matcher)
+        .map(|e| e.expect("scan should succeed").path)
+        .collect();
+
+    assert!(streamed.contains(&root.join("keep/data.txt")));
+    assert!(
+        !streamed
+            .iter()
+            .any(|p| p.starts_with(root.join("build-1234")))
+    );
+    assert!(!streamed.contains(&root.join("keep/scratch.tmp")));
+    assert_eq!(streamed.last().map(|p| p.as_path()), Some(root));
+}
+
+#[test]
+fn test_scan_errors_are_typed() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let root = temp_dir.path();
+    fs::write(root.join("file.txt"), "data").unwrap();
+    let options = ScanOptions::new().no_cache(true);
+
+    let cancel = std::sync::atomic::AtomicBool::new(true);
+    let result = Scanner::new(options.clone())
+        .cancel_flag(&cancel)
+        .scan(root);
+    assert!(matches!(result, Err(rudu::Error::Cancelled)));
+
+    let result = Scanner::new(options.clone().exclude(["[invalid"])).scan(root);
+    assert!(matches!(result, Err(rudu::Error::InvalidPattern { .. })));
+
+    let missing = root.join("missing");
+    let exclude_matcher = build_exclude_matcher(&[]).unwrap();
+    match estimate(&missing, &options, &exclude_matcher) {
+        Err(rudu::Error::Io { path, .. }) => assert_eq!(path, missing),
+        other => panic!("expected an I/O error, got {:?}", other.map(|e| e.files)),
+    }
+
+    let result = Scanner::new(options)
+        .scan(root)
+        .expect("scan should succeed");
+    assert!(result.complete().is_ok());
+}
+
+#[test]
+fn test_scanner_visits_every_entry() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let root = temp_dir.path();
+    fs::create_dir(root.join("sub")).unwrap();
+    fs::write(root.join("sub/data.txt"), vec![1u8; 4096]).unwrap();
+    fs::write(root.join("top.txt"), vec![1u8; 4096]).unwrap();
+
+    for strategy in [
+        ThreadPoolStrategy::Default,
+        ThreadPoolStrategy::WorkStealingUneven,
+    ] {
+        let visited = Mutex::new(Vec::new());
+        let file_bytes = std::sync::atomic::AtomicU64::new(0);
+        let visitor = |entry: &rudu::FileEntry| {
+            if entry.entry_type == EntryType::File {
+                file_bytes.fetch_add(entry.size, std::sync::atomic::Ordering::Relaxed);
+            }
+            visited.lock().unwrap().push(entry.path.clone());
+        };
+        let result = Scanner::new(ScanOptions::new().no_cache(true).threads_strategy(strategy))
+            .visitor(&visitor)
+            .scan(root)
+            .expect("scan should succeed");
+
+        let mut visited = visited.into_inner().unwrap();
+        visited.sort();
+        let mut returned: Vec<PathBuf> = result.entries.iter().map(|e| e.path.clone()).collect();
+        returned.sort();
+        assert_eq!(visited, returned);
+        let root_size = result
+            .entries
+            .iter()
+            .find(|e| e.path == root)
+            .map(|e| e.size)
+            .unwrap();
+        assert_eq!(file_bytes.into_inner(), root_size);
+    }
+}
+
+#[test]
+fn test_scanner_runs_on_injected_thread_pool() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let root = temp_dir.path();
+    fs::create_dir(root.join("sub")).unwrap();
+    for i in 0..20 {
+        fs::write(root.join(format!("sub/file{i}.txt")), vec![1u8; 1024]).unwrap();
+    }
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(2)
+        .thread_name(|i| format!("embedder-{i}"))
+        .build()
+        .unwrap();
+
+    for strategy in [
+        ThreadPoolStrategy::Default,
+        ThreadPoolStrategy::WorkStealingUneven,
+    ] {
+        let threads = Mutex::new(std::collections::HashSet::new());
+        let visitor = |_: &rudu::FileEntry| {
+            let name = std::thread::current().name().map(String::from);
+            threads.lock().unwrap().insert(name);
+        };
+        let result = Scanner::new(ScanOptions::new().no_cache(true).threads_strategy(strategy))
+            .visitor(&visitor)
+            .thread_pool(&pool)
+            .scan(root)
+            .expect("scan should succeed");
+
+        assert_eq!(result.entries.len(), 22);
+        let threads = threads.into_inner().unwrap();
+        assert!(
+            threads
+                .iter()
+                .all(|name| name.as_deref().is_some_and(|n| n.starts_with("embedder-"))),
+            "entries built outside the injected pool: {threads:?}"
+        );
+    }
+}
+
+#[test]
+fn test_deterministic_scans_break_ties_by_path() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let root = temp_dir.path();
+    for dir in ["b", "a", "c"] {
+        fs::create_dir(root.join(dir)).unwrap();
+        for file in ["z.dat", "m.dat", "a.dat"] {
+            fs::write(root.join(dir).join(file), vec![1u8; 4096]).unwrap();
+        }
+    }
+
+    let orders: Vec<Vec<PathBuf>> = [
+        ThreadPoolStrategy::Default,
+        ThreadPoolStrategy::WorkStealingUneven,
+    ]
+    .into_iter()
+    .map(|strategy| {
+        let options = ScanOptions::new()
+            .no_cache(true)
+            .threads_strategy(strategy)
+            .deterministic(true);
+        Scanner::new(options)
+            .sort(&[SortKey::Size])
+            .scan(root)
+            .expect("scan should succeed")
+            .entries
+            .into_iter()
+            .map(|e| e.path)
+            .collect()
+    })
+    .collect();
+
+    assert_eq!(orders[0], orders[1]);
+    // All files have the same size, so they are ordered by path
+    let files: Vec<&PathBuf> = orders[0].iter().filter(|p| p.is_file()).collect();
+    let mut sorted = files.clone();
+    sorted.sort();
+    assert_eq!(files, sorted);
+}
+
+#[test]
+fn test_scan_tree_matches_flat_entries() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let root = temp_dir.path();
+    fs::create_dir_all(root.join("a/b")).unwrap();
+    fs::write(root.join("a/b/data.bin"), vec![1u8; 8192]).unwrap();
+    fs::write(root.join("top.txt"), vec![1u8; 4096]).unwrap();
+
+    let options = ScanOptions::new().no_cache(true);
+    let tree = rudu::tree::scan_tree(root, &options).expect("scan should succeed");
+    let flat = Scanner::new(options).scan(root).unwrap().entries;
+
+    let mut nodes = 0;
+    tree.walk(&mut |node| {
+        nodes += 1;
+        let entry = flat.iter().find(|e| e.path == node.path).unwrap();
+        assert_eq!(node.size, entry.size);
+    });
+    assert_eq!(nodes, flat.len());
+    assert_eq!((tree.files, tree.dirs), (2, 2));
+    assert_eq!(tree.children[0].name(), "a");
+}
+
+#[test]
+fn test_scan_counts_synthetic_tree() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let root = temp_dir.path();
+    let stats = rudu::testing::TreeSpec::new()
+        .depth(3)
+        .fanout(3)
+        .files_per_dir(5)
+        .skew(1.0)
+        .hardlinks(1)
+        .symlinks(1)
+        .generate(root)
+        .expect("tree should be generated");
+
+    let result = Scanner::new(ScanOptions::new().no_cache(true))
+        .scan(root)
+        .expect("scan should succeed");
+    let count = |kind: EntryType| {
+        result
+            .entries
+            .iter()
+            .filter(|e| e.entry_type == kind)
+            .count()
+    };
+    assert_eq!(count(EntryType::File) as u64, stats.files + stats.hardlinks);
+    assert_eq!(count(EntryType::Symlink) as u64, stats.symlinks);
+    // The root is listed as well
+    assert_eq!(count(EntryType::Dir) as u64, stats.dirs + 1);
+}
+
+#[test]
+fn test_largest_files_in_summarize_mode() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let root = temp_dir.path();
+    rudu::testing::TreeSpec::new()
+        .depth(3)
+        .fanout(2)
+        .files_per_dir(4)
+        .file_size(64 * 1024)
+        .skew(1.0)
+        .generate(root)
+        .expect("tree should be generated");
+
+    let full = Scanner::new(ScanOptions::new().no_cache(true))
+        .scan(root)
+        .expect("scan should succeed");
+    let mut expected: Vec<(u64, PathBuf)> = full
+        .entries
+        .iter()
+        .filter(|e| e.entry_type == EntryType::File)
+        .map(|e| (e.size, e.path.clone()))
+        .collect();
+    expected.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    expected.truncate(5);
+
+    let summarized = Scanner::new(
+        ScanOptions::new()
+            .no_cache(true)
+            .summarize(true)
+            .largest_files(5),
+    )
+    .scan(root)
+    .expect("scan should succeed");
+    assert_eq!(summarized.entries.len(), 1);
+    let largest: Vec<(u64, PathBuf)> = summarized
+        .largest_files
+        .iter()
+        .map(|e| (e.size, e.path.clone()))
+        .collect();
+    assert_eq!(largest, expected);
+}
+
+#[test]
+fn test_snapshot_diff_between_scans() {
+    use rudu::snapshot::{ChangeKind, Snapshot, diff};
+
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let root = temp_dir.path();
+    rudu::testing::TreeSpec::new()
+        .depth(2)
+        .fanout(2)
+        .files_per_dir(2)
+        .file_size(128 * 1024)
+        .generate(root)
+        .expect("tree should be generated");
+    let scan = || {
+        let result = Scanner::new(ScanOptions::new().no_cache(true))
+            .scan(root)
+            .expect("scan should succeed");
+        Snapshot::from_entries(&result.entries, &result.meta)
+    };
+    let before = scan();
+
+    fs::write(root.join("subdir_0/big.dat"), vec![b'x'; 1024 * 1024]).unwrap();
+    fs::remove_dir_all(root.join("subdir_1")).unwrap();
+    fs::create_dir_all(root.join("fresh/inner")).unwrap();
+    fs::write(root.join("fresh/inner/data.bin"), vec![b'x'; 512 * 1024]).unwrap();
+    let after = scan();
+
+    let changes: Vec<(PathBuf, ChangeKind)> = diff(&before, &after, 100 * 1024)
+        .into_iter()
+        .map(|change| (change.path.clone(), change.kind()))
+        .collect();
+    assert_eq!(
+        changes,
+        [
+            (PathBuf::from(""), ChangeKind::Grown),
+            (PathBuf::from("subdir_0"), ChangeKind::Grown),
+            (PathBuf::from("fresh"), ChangeKind::New),
+            (PathBuf::from("subdir_1"), ChangeKind::Deleted),
+        ]
+    );
+}
+
+#[test]
+fn test_extended_metadata_is_collected_only_on_request() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let root = temp_dir.path();
+    fs::create_dir(root.join("sub")).unwrap();
+    fs::write(root.join("sub/data.txt"), vec![1u8; 4096]).unwrap();
+    let expected = |path: &Path| {
+        rudu::data::EntryStat::from_metadata(&fs::metadata(path).expect("entry exists"))
+    };
+
+    let plain = Scanner::new(ScanOptions::new().no_cache(true))
+        .scan(root)
+        .expect("scan should succeed");
+    assert!(plain.entries.iter().all(|e| e.stat.is_none()));
+
+    let options = ScanOptions::new()
+        .extended_metadata(true)
+        .cache_location(rudu::cache::CacheLocation::InTree);
+    for strategy in [
+        ThreadPoolStrategy::Default,
+        ThreadPoolStrategy::WorkStealingUneven,
+        // The second default scan restores the directories from the cache
+        ThreadPoolStrategy::Default,
+    ] {
+        let result = Scanner::new(options.clone().threads_strategy(strategy))
+            .scan(root)
+            .expect("scan should succeed");
+        assert!(!result.entries.is_empty());
+        for entry in &result.entries {
+            let stat = entry.stat.as_deref().expect("stat should be collected");
+            let mut expected = expected(&entry.path);
+            // Walking a directory updates its access time
+            expected.atime = stat.atime;
+            assert_eq!(*stat, expected, "{}", entry.path.display());
+        }
+    }
+
+    let streamed: Vec<_> = scan_iter(root, &ScanOptions::new().extended_metadata(true))
+        .collect::<rudu::Result<_>>()
+        .expect("scan should succeed");
+    assert!(streamed.iter().all(|e| e.stat.is_some()));
+}
+
+#[test]
+fn test_symlinks_and_special_files_are_typed() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let root = temp_dir.path();
+    fs::create_dir(root.join("sub")).unwrap();
+    fs::write(root.join("sub/data.txt"), vec![1u8; 64 * 1024]).unwrap();
+    std::os::unix::fs::symlink(root.join("sub/data.txt"), root.join("link")).unwrap();
+    std::os::unix::fs::symlink(root.join("sub"), root.join("dir-link")).unwrap();
+    let _socket = std::os::unix::net::UnixListener::bind(root.join("socket")).unwrap();
+
+    let data_size = rudu::utils::disk_usage(&root.join("sub/data.txt"));
+    for strategy in [
+        ThreadPoolStrategy::Default,
+        ThreadPoolStrategy::WorkStealingUneven,
+    ] {
+        let result = Scanner::new(ScanOptions::new().no_cache(true).threads_strategy(strategy))
+            .scan(root)
+            .expect("scan should succeed");
+        let type_of = |name: &str| {
+            result
+                .entries
+                .iter()
+                .find(|e| e.path == root.join(name))
+                .map(|e| e.entry_type)
+        };
+        assert_eq!(type_of("link"), Some(EntryType::Symlink));
+        assert_eq!(type_of("dir-link"), Some(EntryType::Symlink));
+        assert_eq!(type_of("socket"), Some(EntryType::Socket));
+        assert_eq!(type_of("sub/data.txt"), Some(EntryType::File));
+
+        // Links are measured themselves, so the target is counted once
+        let root_entry = result.entries.iter().find(|e| e.path == root).unwrap();
+        assert!(root_entry.size < 2 * data_size);
+        assert_eq!(result.meta.dir_count, 2);
+        assert_eq!(result.meta.file_count, 4);
+    }
+
+    let streamed: Vec<_> = scan_iter(root, &ScanOptions::new())
+        .collect::<rudu::Result<_>>()
+        .expect("scan should succeed");
+    assert!(
+        streamed
+            .iter()
+            .any(|e| e.path == root.join("link") && e.entry_type == EntryType::Symlink)
+    );
+}
+
+#[test]
+fn test_delete_selected_remove_keeps_directories_without_a_terminal() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let root = temp_dir.path().join("data");
+    // Listed for its total only: none of its files reaches --min-size
+    fs::create_dir_all(root.join("many")).unwrap();
+    for i in 0..12 {
+        fs::write(root.join("many").join(format!("f{i}")), vec![1u8; 8192]).unwrap();
+    }
+    fs::write(root.join("solo.bin"), vec![1u8; 64 * 1024]).unwrap();
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_rudu"))
+        .arg("-q")
+        .arg(&root)
+        .args([
+            "--min-size",
+            "32K",
+            "--delete-selected=remove",
+            "--yes",
+            "--no-cache",
+        ])
+        .stdin(std::process::Stdio::null())
+        .output()
+        .expect("rudu should run");
+    assert!(output.status.success(), "rudu failed: {output:?}");
+
+    // --yes covers the listed file, but not the directory's unlisted files
+    assert!(!root.join("solo.bin").exists());
+    assert_eq!(fs::read_dir(root.join("many")).unwrap().count(), 12);
+}
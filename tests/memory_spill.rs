@@ -0,0 +1,94 @@
+//! Scans that spill entries to disk when memory nears the limit.
+//!
+//! Kept in its own test binary because nearing the limit turns the cache off
+//! for the whole process.
+
+use rudu::data::EntryType;
+use rudu::memory::MemoryMonitor;
+use rudu::scan::{MemoryLimitStatus, Scanner};
+use rudu::{ScanOptions, SortKey};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+
+#[test]
+fn test_spilled_scan_matches_in_memory_scan() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let root = temp_dir.path();
+    for dir in ["a", "a/deep", "b"] {
+        fs::create_dir(root.join(dir)).unwrap();
+        for i in 0..20 {
+            fs::write(root.join(dir).join(format!("f{i}")), vec![0u8; 4096 * i]).unwrap();
+        }
+    }
+
+    let options = ScanOptions::new()
+        .no_cache(true)
+        .show_inodes(true)
+        .memory_check_entries(1);
+    let listing = |entries: &[rudu::FileEntry]| {
+        let mut listing: Vec<(PathBuf, u64, EntryType, Option<u64>)> = entries
+            .iter()
+            .map(|e| (e.path.clone(), e.size, e.entry_type, e.inodes))
+            .collect();
+        listing.sort_by(|a, b| a.0.cmp(&b.0));
+        listing
+    };
+
+    let in_memory = Scanner::new(options.clone()).scan(root).unwrap();
+    assert_eq!(in_memory.memory_status, MemoryLimitStatus::Normal);
+
+    // A limit no process reaches, reported as nearing from the first check
+    let monitor = MemoryMonitor::new(u64::MAX >> 24).with_warn_threshold(0.0);
+    let spilled = Scanner::new(options)
+        .memory_monitor(Arc::new(Mutex::new(monitor)))
+        .scan(root)
+        .unwrap();
+    assert_eq!(spilled.memory_status, MemoryLimitStatus::NearingLimit);
+    assert!(!spilled.memory_limit_hit);
+    assert_eq!(spilled.meta.file_count, 60);
+
+    // The spilled files stay on disk until merged back in
+    assert!(spilled.spilled.as_ref().is_some_and(|s| !s.is_empty()));
+    assert!(spilled.entries.len() < in_memory.entries.len());
+    let entries = spilled.into_entries().unwrap();
+    assert_eq!(listing(&entries), listing(&in_memory.entries));
+}
+
+#[test]
+fn test_spilled_scan_merges_in_sort_order() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let root = temp_dir.path();
+    for dir in ["a", "b"] {
+        fs::create_dir(root.join(dir)).unwrap();
+        for i in 0..30 {
+            fs::write(root.join(dir).join(format!("f{i}")), vec![0u8; 100 * i]).unwrap();
+        }
+    }
+
+    let options = ScanOptions::new()
+        .no_cache(true)
+        .deterministic(true)
+        .memory_check_entries(1);
+    let keys = [SortKey::Size];
+    let in_memory = Scanner::new(options.clone())
+        .sort(&keys)
+        .scan(root)
+        .unwrap();
+    let monitor = MemoryMonitor::new(u64::MAX >> 24).with_warn_threshold(0.0);
+    let spilled = Scanner::new(options)
+        .sort(&keys)
+        .memory_monitor(Arc::new(Mutex::new(monitor)))
+        .scan(root)
+        .unwrap();
+    assert!(spilled.spilled.is_some());
+
+    let paths = |entries: Vec<rudu::FileEntry>| -> Vec<PathBuf> {
+        entries.into_iter().map(|e| e.path).collect()
+    };
+    assert_eq!(
+        paths(spilled.into_entries().unwrap()),
+        paths(in_memory.entries)
+    );
+}
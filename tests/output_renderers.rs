@@ -1,9 +1,13 @@
-use rudu::cli::{Args, SortKey};
-use rudu::data::{EntryType, FileEntry};
-use rudu::output::{csv, terminal};
+use rudu::cli::{Args, ColorChoice, CsvQuoting, OutputFormat, SortKey};
+use rudu::data::{EntryType, FileEntry, ScanMeta};
+use rudu::output::columns::ColumnSpec;
+use rudu::output::paths::PathStyle;
+use rudu::output::time::TimeStyle;
+use rudu::output::units::SizeFormat;
+use rudu::output::{csv, folded, html, msgpack, ncdu, ndjson, printf, terminal, yaml};
 use rudu::thread_pool::ThreadPoolStrategy;
 use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tempfile::NamedTempFile;
 
 fn make_test_entries() -> Vec<FileEntry> {
@@ -14,6 +18,9 @@ fn make_test_entries() -> Vec<FileEntry> {
             owner: Some("testuser".to_string()),
             inodes: Some(5),
             entry_type: EntryType::Dir,
+            previous_size: None,
+            mtime: None,
+            stat: None,
         },
         FileEntry {
             path: PathBuf::from("/test/file1.txt"),
@@ -21,15 +28,25 @@ fn make_test_entries() -> Vec<FileEntry> {
             owner: Some("testuser".to_string()),
             inodes: None,
             entry_type: EntryType::File,
+            previous_size: None,
+            mtime: None,
+            stat: None,
         },
     ]
 }
 
+fn make_meta(root: PathBuf) -> ScanMeta {
+    ScanMeta {
+        root,
+        ..ScanMeta::default()
+    }
+}
+
 fn make_args(root: PathBuf) -> Args {
     Args {
         path: root,
         depth: None,
-        sort: SortKey::Name,
+        sort: vec![SortKey::Name],
         show_files: true,
         exclude: Vec::new(),
         show_owner: true,
@@ -42,6 +59,85 @@ fn make_args(root: PathBuf) -> Args {
         profile: false,
         memory_limit: None,
         memory_check_interval_ms: 200,
+        deleted_open: false,
+        cache_validation: rudu::cache::CacheValidation::Fast,
+        command: None,
+        no_exclude_markers: false,
+        cache_max_size: None,
+        rpc: false,
+        cache_dir_for: Vec::new(),
+        cache_location: rudu::cache::CacheLocation::Xdg,
+        cache_key_file: None,
+        format: None,
+        stream: false,
+        tree: false,
+        color: ColorChoice::Auto,
+        bar: false,
+        columns: None,
+        printf: None,
+        csv_delimiter: None,
+        csv_quote: CsvQuoting::Necessary,
+        csv_header: true,
+        null: false,
+        absolute_paths: false,
+        relative_to: None,
+        block_size: None,
+        kilobytes: false,
+        megabytes: false,
+        si: false,
+        binary: false,
+        top: None,
+        top_files: false,
+        report: None,
+        limit: None,
+        offset: 0,
+        template: None,
+        min_size: None,
+        max_size: None,
+        include: vec![],
+        exclude_from: vec![],
+        exclude_regex: vec![],
+        include_regex: vec![],
+        older_than: None,
+        newer_than: None,
+        group: None,
+        gid: None,
+        files_only: false,
+        dirs_only: false,
+        summarize: false,
+        all: false,
+        human_readable: false,
+        help: None,
+        quiet: false,
+        verbose: 0,
+        reverse: false,
+        threshold: None,
+        estimate: false,
+        exclude_mounts: vec![],
+        time: false,
+        time_style: None,
+        count_only: false,
+        scan_archives: false,
+        budgets: None,
+        alert_threshold: None,
+        alert_growth: None,
+        alert_url: None,
+        alert_email: Vec::new(),
+        smtp_server: "localhost".to_string(),
+        alert_from: None,
+        user_quotas: None,
+        stat_batch: None,
+        cached_attrs: None,
+        no_fs_tuning: false,
+        sparse_report: None,
+        cold_after: None,
+        largest_files: None,
+        snapshot: false,
+        delete_selected: None,
+        dry_run: false,
+        deterministic: false,
+        extended_metadata: false,
+        yes: false,
     }
 }
 
@@ -104,6 +200,9 @@ fn test_csv_renderer_handles_none_owner_and_inodes() {
             owner: None,
             inodes: None,
             entry_type: EntryType::File,
+            previous_size: None,
+            mtime: None,
+            stat: None,
         },
         FileEntry {
             path: PathBuf::from("/test/dir-no-meta"),
@@ -111,6 +210,9 @@ fn test_csv_renderer_handles_none_owner_and_inodes() {
             owner: None,
             inodes: None,
             entry_type: EntryType::Dir,
+            previous_size: None,
+            mtime: None,
+            stat: None,
         },
     ];
 
@@ -167,10 +269,672 @@ fn test_terminal_renderer_works() {
     let args = make_args(root.clone());
 
     // terminal::render writes to stdout; verify it doesn't error
-    let result = terminal::render(&entries, &args, &root);
+    let result = terminal::render(&entries, &args, &make_meta(root));
     assert!(
         result.is_ok(),
         "terminal::render returned an error: {:?}",
         result
     );
 }
+
+#[test]
+fn test_terminal_format_delta() {
+    assert_eq!(terminal::format_delta(None, 1_000, SizeFormat::Decimal), "");
+    assert_eq!(
+        terminal::format_delta(Some(1_000), 1_000, SizeFormat::Decimal),
+        ""
+    );
+    assert_eq!(
+        terminal::format_delta(Some(1_000), 13_300, SizeFormat::Decimal),
+        "+12.30 kB"
+    );
+    assert_eq!(
+        terminal::format_delta(Some(5_000_000), 1_000_000, SizeFormat::Decimal),
+        "-4 MB"
+    );
+}
+
+#[test]
+fn test_terminal_renderer_with_previous_sizes() {
+    let mut entries = make_test_entries();
+    entries[0].previous_size = Some(512);
+    let root = PathBuf::from("/test");
+    let args = make_args(root.clone());
+
+    assert!(terminal::render(&entries, &args, &make_meta(root)).is_ok());
+}
+
+#[test]
+fn test_terminal_render_to_preserves_order_across_chunks() {
+    let root = PathBuf::from("/test");
+    let entries: Vec<FileEntry> = (0..10_000)
+        .map(|i| FileEntry {
+            path: root.join(format!("f{i:05}")),
+            size: i,
+            owner: None,
+            inodes: None,
+            entry_type: EntryType::File,
+            previous_size: None,
+            mtime: None,
+            stat: None,
+        })
+        .collect();
+    let mut args = make_args(root.clone());
+    args.show_owner = false;
+
+    let mut out = Vec::new();
+    terminal::render_to(&mut out, &entries, &args, &make_meta(root)).unwrap();
+    let out = String::from_utf8(out).unwrap();
+
+    let paths: Vec<&str> = out
+        .lines()
+        .map(|line| line.rsplit(' ').next().unwrap())
+        .collect();
+    assert_eq!(paths.len(), entries.len());
+    assert_eq!(paths[0], "f00000");
+    assert!(paths.windows(2).all(|w| w[0] < w[1]));
+}
+
+#[test]
+fn test_ndjson_renderer_writes_one_object_per_line() {
+    let entries = make_test_entries();
+    let tmp = NamedTempFile::new().expect("Failed to create temp file");
+    let mut args = make_args(PathBuf::from("/test"));
+    args.output = Some(tmp.path().to_string_lossy().into_owned());
+
+    ndjson::render(&entries, &args).expect("ndjson::render failed");
+
+    let content = std::fs::read_to_string(tmp.path()).unwrap();
+    let lines: Vec<serde_json::Value> = content
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("each line is a JSON object"))
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["path"], "/test/dir1");
+    assert_eq!(lines[0]["size"], 1024);
+    assert_eq!(lines[0]["inodes"], 5);
+    assert_eq!(lines[1]["owner"], "testuser");
+    assert!(lines[1]["inodes"].is_null());
+    assert!(content.ends_with('\n'));
+}
+
+#[test]
+fn test_ndjson_write_entry_streams_single_records() {
+    let entries = make_test_entries();
+    let mut out = Vec::new();
+    for entry in &entries {
+        ndjson::write_entry(&mut out, entry).unwrap();
+    }
+
+    let mut rendered = Vec::new();
+    ndjson::render_to(&mut rendered, &entries).unwrap();
+    assert_eq!(out, rendered);
+}
+
+#[test]
+fn test_serialized_records_read_back_into_file_entries() {
+    let entries = make_test_entries();
+
+    let mut out = Vec::new();
+    ndjson::render_to(&mut out, &entries).unwrap();
+    let parsed: Vec<FileEntry> = String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(parsed, entries);
+
+    let mut out = Vec::new();
+    yaml::render_to(&mut out, &entries).unwrap();
+    assert_eq!(
+        serde_yaml::from_slice::<Vec<FileEntry>>(&out).unwrap(),
+        entries
+    );
+
+    let mut out = Vec::new();
+    msgpack::render_to(&mut out, &entries).unwrap();
+    assert_eq!(
+        rmp_serde::from_slice::<Vec<FileEntry>>(&out).unwrap(),
+        entries
+    );
+}
+
+#[test]
+fn test_ncdu_export_rebuilds_tree_with_own_sizes() {
+    let dir = |path: &str, size| FileEntry {
+        path: PathBuf::from(path),
+        size,
+        owner: None,
+        inodes: None,
+        entry_type: EntryType::Dir,
+        previous_size: None,
+        mtime: None,
+        stat: None,
+    };
+    let file = |path: &str, size| FileEntry {
+        entry_type: EntryType::File,
+        ..dir(path, size)
+    };
+    // /test/a/deep is implied by its file but was not listed itself
+    let entries = vec![
+        dir("/test", 1000),
+        dir("/test/a", 700),
+        file("/test/a/deep/f", 300),
+        file("/test/b.txt", 200),
+    ];
+
+    let mut out = Vec::new();
+    ncdu::render_to(&mut out, &entries, &make_meta(PathBuf::from("/test"))).unwrap();
+    let export: serde_json::Value = serde_json::from_slice(&out).unwrap();
+
+    assert_eq!(export[0], 1);
+    assert_eq!(export[1], 0);
+    assert_eq!(export[2]["progname"], "rudu");
+    let root = &export[3];
+    assert_eq!(root[0]["name"], "/test");
+    assert_eq!(root[0]["dsize"], 100);
+
+    let a = &root[1];
+    assert_eq!(a[0]["name"], "a");
+    assert_eq!(a[0]["dsize"], 400);
+    assert_eq!(a[1][0]["name"], "deep");
+    assert_eq!(a[1][0]["dsize"], 0);
+    assert_eq!(a[1][1], serde_json::json!({"name": "f", "dsize": 300}));
+    assert_eq!(root[2], serde_json::json!({"name": "b.txt", "dsize": 200}));
+}
+
+#[test]
+fn test_terminal_tree_lines_draw_connectors() {
+    let entry = |path: &str, entry_type| FileEntry {
+        path: PathBuf::from(path),
+        size: 1,
+        owner: None,
+        inodes: None,
+        entry_type,
+        previous_size: None,
+        mtime: None,
+        stat: None,
+    };
+    // Sorted by size: siblings are not in path order, and /test/b/c is
+    // listed without /test/b
+    let entries = vec![
+        entry("/test", EntryType::Dir),
+        entry("/test/z", EntryType::Dir),
+        entry("/test/a", EntryType::Dir),
+        entry("/test/z/f.txt", EntryType::File),
+        entry("/test/b/c", EntryType::Dir),
+        entry("/test/a/g.txt", EntryType::File),
+    ];
+
+    let labels: Vec<String> = terminal::tree_lines(&entries, Path::new("/test"))
+        .into_iter()
+        .map(|(_, connectors, name)| connectors + &name)
+        .collect();
+    assert_eq!(
+        labels,
+        vec![
+            "/test",
+            "├── z",
+            "│   └── f.txt",
+            "├── a",
+            "│   └── g.txt",
+            "└── b/c",
+        ]
+    );
+}
+
+#[test]
+fn test_terminal_tree_render() {
+    let entries = make_test_entries();
+    let root = PathBuf::from("/test");
+    let mut args = make_args(root.clone());
+    args.tree = true;
+    args.show_owner = false;
+    args.show_inodes = false;
+
+    let mut out = Vec::new();
+    terminal::render_to(&mut out, &entries, &args, &make_meta(root)).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = out.lines().collect();
+    assert!(lines[0].ends_with(" dir1"));
+    assert!(lines[1].ends_with(" file1.txt"));
+}
+
+#[test]
+fn test_terminal_palette_resolution_and_buckets() {
+    assert_eq!(
+        terminal::Palette::resolve(ColorChoice::Never, true),
+        terminal::Palette::PLAIN
+    );
+    assert_eq!(
+        terminal::Palette::resolve(ColorChoice::Always, false),
+        terminal::Palette::DEFAULT
+    );
+    assert_eq!(
+        terminal::Palette::resolve(ColorChoice::Auto, false),
+        terminal::Palette::PLAIN
+    );
+
+    let palette = terminal::Palette::DEFAULT;
+    assert_eq!(palette.size(2_000_000_000_000), "\x1b[31m");
+    assert_eq!(palette.size(5_000_000_000), "\x1b[33m");
+    assert_eq!(palette.size(1_000_000), "\x1b[32m");
+    assert_eq!(palette.size(999), "");
+}
+
+#[test]
+fn test_terminal_color_keeps_columns_aligned() {
+    let entries = make_test_entries();
+    let root = PathBuf::from("/test");
+    let args = make_args(root.clone());
+    let meta = make_meta(root);
+
+    let mut plain = Vec::new();
+    terminal::render_styled(
+        &mut plain,
+        &entries,
+        &args,
+        &meta,
+        &terminal::Palette::PLAIN,
+    )
+    .unwrap();
+    let mut colored = Vec::new();
+    terminal::render_styled(
+        &mut colored,
+        &entries,
+        &args,
+        &meta,
+        &terminal::Palette::DEFAULT,
+    )
+    .unwrap();
+
+    let colored = String::from_utf8(colored).unwrap();
+    assert!(colored.contains("\x1b[1;34m[DIR]\x1b[0m"));
+    assert!(colored.contains("\x1b[36mtestuser  \x1b[0m"));
+
+    // Stripping the escapes gives back the plain output
+    let stripped = colored
+        .split('\x1b')
+        .enumerate()
+        .map(|(i, part)| {
+            if i == 0 {
+                part
+            } else {
+                &part[part.find('m').unwrap() + 1..]
+            }
+        })
+        .collect::<String>();
+    assert_eq!(stripped, String::from_utf8(plain).unwrap());
+}
+
+#[test]
+fn test_terminal_usage_bar() {
+    assert_eq!(terminal::usage_bar(10, 10), "▇▇▇▇▇▇▇▇▇▇");
+    assert_eq!(terminal::usage_bar(3, 10), "▇▇▇▁▁▁▁▁▁▁");
+    assert_eq!(terminal::usage_bar(1, 1_000_000), "▇▁▁▁▁▁▁▁▁▁");
+    assert_eq!(terminal::usage_bar(0, 10), "▁▁▁▁▁▁▁▁▁▁");
+    assert_eq!(terminal::usage_bar(0, 0), "▁▁▁▁▁▁▁▁▁▁");
+}
+
+#[test]
+fn test_terminal_bar_scales_to_largest_sibling() {
+    let dir = |path: &str, size| FileEntry {
+        path: PathBuf::from(path),
+        size,
+        owner: None,
+        inodes: None,
+        entry_type: EntryType::Dir,
+        previous_size: None,
+        mtime: None,
+        stat: None,
+    };
+    let entries = vec![
+        dir("/test/big", 1000),
+        dir("/test/big/small", 100),
+        dir("/test/half", 500),
+    ];
+    let root = PathBuf::from("/test");
+    let mut args = make_args(root.clone());
+    args.bar = true;
+    args.show_owner = false;
+    args.show_inodes = false;
+
+    let mut out = Vec::new();
+    terminal::render_to(&mut out, &entries, &args, &make_meta(root)).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = out.lines().collect();
+    // The only child of big fills its bar; half is scaled against big
+    assert!(lines[0].contains("▇▇▇▇▇▇▇▇▇▇"));
+    assert!(lines[1].contains("▇▇▇▇▇▇▇▇▇▇"));
+    assert!(lines[2].contains("▇▇▇▇▇▁▁▁▁▁"));
+}
+
+#[test]
+fn test_terminal_columns_choose_order() {
+    let mut entries = make_test_entries();
+    entries[0].mtime = Some(1_700_000_000);
+    let root = PathBuf::from("/test");
+    let mut args = make_args(root.clone());
+    args.color = ColorChoice::Never;
+    args.columns = Some("path,mtime,owner".parse().unwrap());
+
+    let mut out = Vec::new();
+    terminal::render_to(&mut out, &entries, &args, &make_meta(root)).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(
+        lines[0],
+        format!(
+            "dir1 {} testuser  ",
+            terminal::format_mtime(Some(1_700_000_000))
+        )
+    );
+    assert_eq!(lines[1], format!("file1.txt {:<16} testuser  ", "-"));
+}
+
+#[test]
+fn test_time_adds_mtime_column() {
+    let mut entries = make_test_entries();
+    entries[0].mtime = Some(1_700_000_000);
+    let root = PathBuf::from("/test");
+    let mut args = make_args(root.clone());
+    args.color = ColorChoice::Never;
+    args.show_owner = false;
+    args.show_inodes = false;
+    args.time = true;
+    args.time_style = Some("iso".parse().unwrap());
+
+    let mut out = Vec::new();
+    terminal::render_to(&mut out, &entries, &args, &make_meta(root)).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = out.lines().collect();
+    let day = TimeStyle::Iso.format(Some(1_700_000_000));
+    assert!(lines[0].ends_with(&format!("{} dir1", day)));
+    assert!(lines[1].ends_with(&format!("{:<10} file1.txt", "-")));
+
+    args.time_style = None;
+    let mut out = Vec::new();
+    csv::render_to(
+        &mut out,
+        &entries,
+        &ColumnSpec::for_csv(&args),
+        csv::Dialect::from_args(&args),
+        SizeFormat::Decimal,
+        &TimeStyle::Rfc3339,
+    )
+    .unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.starts_with("entry_type,size_bytes,size_human,owner,path,inodes,mtime\n"));
+    assert!(out.contains("/test/dir1,5,2023-11-14T22:13:20Z\n"));
+    assert!(out.ends_with("/test/file1.txt,,\n"));
+}
+
+#[test]
+fn test_csv_columns_choose_order() {
+    let mut entries = make_test_entries();
+    entries[0].mtime = Some(1_700_000_000);
+    let tmp = NamedTempFile::new().expect("Failed to create temp file");
+    let mut args = make_args(PathBuf::from("/test"));
+    args.output = Some(tmp.path().to_string_lossy().into_owned());
+    args.columns = Some("path,size,mtime".parse().unwrap());
+
+    csv::render(&entries, &args).unwrap();
+    let buf = std::fs::read_to_string(tmp.path()).unwrap();
+    let lines: Vec<&str> = buf.lines().collect();
+    assert_eq!(lines[0], "path,size_bytes,size_human,mtime");
+    assert_eq!(lines[1], "/test/dir1,1024,1.02 kB,2023-11-14T22:13:20Z");
+    assert_eq!(lines[2], "/test/file1.txt,512,512 B,");
+}
+
+#[test]
+fn test_printf_renderer_writes_custom_records() {
+    let entries = make_test_entries();
+    let root = PathBuf::from("/test");
+    let format: printf::PrintfFormat = "%y\\t%s\\t%P\\n".parse().unwrap();
+
+    let mut out = Vec::new();
+    printf::render_to(&mut out, &entries, &format, &root, SizeFormat::Decimal).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "d\t1024\tdir1\nf\t512\tfile1.txt\n"
+    );
+}
+
+#[test]
+fn test_csv_dialect_options() {
+    let entries = make_test_entries();
+    let mut args = make_args(PathBuf::from("/test"));
+    args.columns = Some("type,path,inodes".parse().unwrap());
+    let columns = ColumnSpec::for_csv(&args);
+
+    args.format = Some(OutputFormat::Tsv);
+    let mut out = Vec::new();
+    csv::render_to(
+        &mut out,
+        &entries,
+        &columns,
+        csv::Dialect::from_args(&args),
+        SizeFormat::Decimal,
+        &TimeStyle::Rfc3339,
+    )
+    .unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "entry_type\tpath\tinodes\nDIR\t/test/dir1\t5\nFILE\t/test/file1.txt\t\n"
+    );
+
+    args.format = None;
+    args.csv_delimiter = Some(b';');
+    args.csv_quote = CsvQuoting::NonNumeric;
+    args.csv_header = false;
+    let mut out = Vec::new();
+    csv::render_to(
+        &mut out,
+        &entries,
+        &columns,
+        csv::Dialect::from_args(&args),
+        SizeFormat::Decimal,
+        &TimeStyle::Rfc3339,
+    )
+    .unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "\"DIR\";\"/test/dir1\";5\n\"FILE\";\"/test/file1.txt\";\"\"\n"
+    );
+}
+
+#[test]
+fn test_html_report_is_self_contained() {
+    let mut entries = make_test_entries();
+    entries[0].path = PathBuf::from("/test/<dir1>");
+    let root = PathBuf::from("/test");
+    let args = make_args(root.clone());
+
+    let mut out = Vec::new();
+    html::render_to(&mut out, &entries, &args, &make_meta(root)).unwrap();
+    let out = String::from_utf8(out).unwrap();
+
+    assert!(out.starts_with("<!DOCTYPE html>"));
+    assert!(out.trim_end().ends_with("</html>"));
+    assert!(!out.contains("src=\"http"));
+    assert!(out.contains("<svg"));
+    assert!(out.contains("<th>Owner</th>"));
+    assert!(out.contains("<td>&lt;dir1&gt;</td>"));
+    assert!(!out.contains("<dir1>"));
+    assert_eq!(out.matches("<tr data-path=").count(), 2);
+}
+
+#[test]
+fn test_yaml_renderer_mirrors_json_records() {
+    let entries = make_test_entries();
+    let mut out = Vec::new();
+    yaml::render_to(&mut out, &entries).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.starts_with("- path: /test/dir1\n"));
+
+    // Same records as the JSON formatters, entry for entry
+    let parsed: Vec<serde_json::Value> = serde_yaml::from_str(&out).unwrap();
+    let expected: Vec<serde_json::Value> = entries
+        .iter()
+        .map(|e| serde_json::to_value(e).unwrap())
+        .collect();
+    assert_eq!(parsed, expected);
+
+    let mut out = Vec::new();
+    yaml::render_to(&mut out, &[]).unwrap();
+    let parsed: Vec<serde_json::Value> = serde_yaml::from_slice(&out).unwrap();
+    assert!(parsed.is_empty());
+}
+
+#[test]
+fn test_msgpack_renderer_mirrors_json_records() {
+    let entries = make_test_entries();
+    let mut out = Vec::new();
+    msgpack::render_to(&mut out, &entries).unwrap();
+
+    // A two-element fixarray of maps keyed by field name
+    assert_eq!(out[0], 0x92);
+    let parsed: Vec<serde_json::Value> = rmp_serde::from_slice(&out).unwrap();
+    let expected: Vec<serde_json::Value> = entries
+        .iter()
+        .map(|e| serde_json::to_value(e).unwrap())
+        .collect();
+    assert_eq!(parsed, expected);
+}
+
+#[test]
+fn test_folded_stacks_weight_own_bytes() {
+    let entry = |path: &str, size, entry_type| FileEntry {
+        path: PathBuf::from(path),
+        size,
+        owner: None,
+        inodes: None,
+        entry_type,
+        previous_size: None,
+        mtime: None,
+        stat: None,
+    };
+    let entries = vec![
+        entry("/data/a", 1000, EntryType::Dir),
+        entry("/data/a/b", 600, EntryType::Dir),
+        entry("/data/a/b/c;d.txt", 600, EntryType::File),
+        entry("/data/a/e.txt", 100, EntryType::File),
+        // Its parent directory was filtered out of the listing
+        entry("/data/x/y", 50, EntryType::Dir),
+    ];
+
+    let mut out = Vec::new();
+    folded::render_to(&mut out, &entries, Path::new("/data")).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "/data;a 300\n/data;a;b;c_d.txt 600\n/data;a;e.txt 100\n/data;x;y 50\n"
+    );
+}
+
+#[test]
+fn test_terminal_null_terminated_lines() {
+    let mut entries = make_test_entries();
+    entries[1].path = PathBuf::from("/test/new\nline.txt");
+    let root = PathBuf::from("/test");
+    let mut args = make_args(root.clone());
+    args.null = true;
+    args.columns = Some("path".parse().unwrap());
+
+    let mut out = Vec::new();
+    terminal::render_to(&mut out, &entries, &args, &make_meta(root)).unwrap();
+    assert_eq!(out, b"dir1\0new\nline.txt\0");
+}
+
+#[test]
+fn test_terminal_and_csv_use_selected_units() {
+    let entries = make_test_entries();
+    let root = PathBuf::from("/test");
+    let mut args = make_args(root.clone());
+    args.kilobytes = true;
+    args.columns = Some("size,path".parse().unwrap());
+
+    let mut out = Vec::new();
+    terminal::render_to(&mut out, &entries, &args, &make_meta(root)).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.starts_with(&format!("{:<12} dir1\n", "1")));
+
+    let mut out = Vec::new();
+    let dialect = csv::Dialect::from_args(&args);
+    csv::render_to(
+        &mut out,
+        &entries,
+        &ColumnSpec::for_csv(&args),
+        dialect,
+        args.size_format(),
+        &TimeStyle::Rfc3339,
+    )
+    .unwrap();
+    assert!(
+        String::from_utf8(out)
+            .unwrap()
+            .contains("\n1024,1,/test/dir1\n")
+    );
+}
+
+#[test]
+fn test_terminal_summarize_labels_root() {
+    let root = PathBuf::from("/test");
+    let entries = vec![FileEntry {
+        path: root.clone(),
+        size: 3072,
+        owner: None,
+        inodes: None,
+        entry_type: EntryType::Dir,
+        previous_size: None,
+        mtime: None,
+        stat: None,
+    }];
+    let mut args = make_args(root.clone());
+    args.summarize = true;
+    args.columns = Some("size,path".parse().unwrap());
+    args.kilobytes = true;
+
+    let mut out = Vec::new();
+    terminal::render_to(&mut out, &entries, &args, &make_meta(root)).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        format!("{:<12} /test\n", "3")
+    );
+}
+
+#[test]
+fn test_path_style_forces_absolute_or_relative_paths() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path().canonicalize().unwrap().join("data");
+    let entries = vec![FileEntry {
+        path: root.join("dir1"),
+        size: 1024,
+        owner: None,
+        inodes: None,
+        entry_type: EntryType::Dir,
+        previous_size: None,
+        mtime: None,
+        stat: None,
+    }];
+    let mut args = make_args(root.clone());
+    args.columns = Some("path".parse().unwrap());
+
+    args.relative_to = Some(tmp.path().to_path_buf());
+    let mut out = Vec::new();
+    terminal::render_to(&mut out, &entries, &args, &make_meta(root.clone())).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "data/dir1\n");
+
+    args.relative_to = None;
+    args.absolute_paths = true;
+    let mut out = Vec::new();
+    terminal::render_to(&mut out, &entries, &args, &make_meta(root.clone())).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        format!("{}\n", root.join("dir1").display())
+    );
+
+    let style = PathStyle::relative_to(&root, &root);
+    let restyled = PathStyle::restyle(Some(&style), &entries);
+    assert_eq!(restyled[0].path, Path::new("dir1"));
+    assert_eq!(restyled[0].size, 1024);
+}
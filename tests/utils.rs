@@ -1,10 +1,13 @@
-use rudu::cli::SortKey;
-use rudu::data::{EntryType, FileEntry};
+use rudu::data::{EntryStat, EntryType, FileEntry, SortKey};
 use rudu::utils::{
-    build_exclude_matcher, disk_usage, expand_exclude_patterns, get_dir_metadata, path_depth,
-    path_hash, sort_entries,
+    CACHEDIR_TAG, RUDUIGNORE_MARKER, build_exclude_matcher, containing_fs_type_in_mount_table,
+    containing_mount_in_table, disk_usage, disk_usage_and_cached_stat, disk_usage_and_mtime,
+    disk_usage_and_stat, expand_exclude_patterns, filter_by_gid, filter_by_mtime, filter_included,
+    format_count, fs_type_in_mount_table, get_dir_metadata, group_id, group_name,
+    has_exclude_marker, paginate, path_depth, path_hash, read_pattern_file, sort_entries,
+    top_entries, user_name,
 };
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tempfile::TempDir;
 
 #[test]
@@ -18,6 +21,276 @@ fn test_path_depth() {
     assert_eq!(path_depth(&root, &root), 0);
 }
 
+#[test]
+fn test_top_entries() {
+    let entry = |name: &str, size, entry_type| FileEntry {
+        path: PathBuf::from(format!("/data/{}", name)),
+        size,
+        owner: None,
+        inodes: None,
+        entry_type,
+        previous_size: None,
+        mtime: None,
+        stat: None,
+    };
+    let entries = vec![
+        entry("a", 300, EntryType::Dir),
+        entry("b", 100, EntryType::Dir),
+        entry("big.iso", 900, EntryType::File),
+        entry("c", 500, EntryType::Dir),
+        entry("d", 300, EntryType::Dir),
+        entry("e", 200, EntryType::Dir),
+    ];
+    let names = |selected: Vec<FileEntry>| -> Vec<String> {
+        selected
+            .iter()
+            .map(|e| e.path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect()
+    };
+
+    // Ties keep their input order
+    let dirs = top_entries(entries.clone(), 3, |e| e.entry_type == EntryType::Dir);
+    assert_eq!(names(dirs), ["c", "a", "d"]);
+
+    let all = top_entries(entries.clone(), 2, |_| true);
+    assert_eq!(names(all), ["big.iso", "c"]);
+
+    assert_eq!(top_entries(entries.clone(), 100, |_| true).len(), 6);
+    assert!(top_entries(entries, 0, |_| true).is_empty());
+}
+
+#[test]
+fn test_filter_included() {
+    let entry = |path: &str, size, entry_type| FileEntry {
+        path: PathBuf::from(path),
+        size,
+        owner: None,
+        inodes: match entry_type {
+            EntryType::Dir => Some(99),
+            _ => None,
+        },
+        entry_type,
+        previous_size: None,
+        mtime: None,
+        stat: None,
+    };
+    let entries = vec![
+        entry("/p", 1111, EntryType::Dir),
+        entry("/p/a", 1011, EntryType::Dir),
+        entry("/p/a/checkpoints", 1010, EntryType::Dir),
+        entry("/p/a/checkpoints/1.ckpt", 1000, EntryType::File),
+        entry("/p/a/checkpoints/2.ckpt", 10, EntryType::File),
+        entry("/p/a/log.txt", 1, EntryType::File),
+        entry("/p/b", 100, EntryType::Dir),
+        entry("/p/b/data.nc", 100, EntryType::File),
+        entry("/p/c", 0, EntryType::Dir),
+        entry("/p/c/checkpoints", 0, EntryType::Dir),
+    ];
+    let include =
+        build_exclude_matcher(&expand_exclude_patterns(&["checkpoints".to_string()])).unwrap();
+    let kept = filter_included(entries, &PathBuf::from("/p"), &include);
+
+    let summary: Vec<(&str, u64, Option<u64>)> = kept
+        .iter()
+        .map(|e| (e.path.to_str().unwrap(), e.size, e.inodes))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("/p", 1010, Some(2)),
+            ("/p/a", 1010, Some(1)),
+            ("/p/a/checkpoints", 1010, Some(2)),
+            ("/p/a/checkpoints/1.ckpt", 1000, None),
+            ("/p/a/checkpoints/2.ckpt", 10, None),
+            ("/p/c", 0, Some(1)),
+            ("/p/c/checkpoints", 0, Some(0)),
+        ]
+    );
+}
+
+#[test]
+fn test_filter_by_mtime() {
+    let entry = |path: &str, size, mtime: Option<u64>| FileEntry {
+        path: PathBuf::from(path),
+        size,
+        owner: None,
+        inodes: None,
+        entry_type: if path.ends_with(".nc") {
+            EntryType::File
+        } else {
+            EntryType::Dir
+        },
+        previous_size: None,
+        mtime,
+        stat: None,
+    };
+    let entries = vec![
+        entry("/p", 1111, Some(500)),
+        entry("/p/old", 1100, Some(500)),
+        entry("/p/old/a.nc", 1000, Some(100)),
+        entry("/p/old/b.nc", 100, Some(300)),
+        entry("/p/new", 11, Some(500)),
+        entry("/p/new/c.nc", 10, Some(400)),
+        entry("/p/new/d.nc", 1, None),
+    ];
+    let summary = |kept: Vec<FileEntry>| -> Vec<(String, u64)> {
+        kept.into_iter()
+            .map(|e| (e.path.display().to_string(), e.size))
+            .collect()
+    };
+    let root = PathBuf::from("/p");
+
+    assert_eq!(
+        summary(filter_by_mtime(entries.clone(), &root, Some(300), None)),
+        [
+            ("/p".to_string(), 1000),
+            ("/p/old".to_string(), 1000),
+            ("/p/old/a.nc".to_string(), 1000),
+        ]
+    );
+    assert_eq!(
+        summary(filter_by_mtime(entries, &root, Some(400), Some(300))),
+        [
+            ("/p".to_string(), 100),
+            ("/p/old".to_string(), 100),
+            ("/p/old/b.nc".to_string(), 100),
+        ]
+    );
+}
+
+#[test]
+fn test_filter_by_gid() {
+    let entry = |path: &str, size, gid: Option<u32>| FileEntry {
+        path: PathBuf::from(path),
+        size,
+        owner: None,
+        inodes: None,
+        entry_type: if path.ends_with(".nc") {
+            EntryType::File
+        } else {
+            EntryType::Dir
+        },
+        previous_size: None,
+        mtime: None,
+        stat: gid.map(|gid| {
+            Box::new(EntryStat {
+                atime: 0,
+                nlink: 1,
+                uid: 0,
+                gid,
+                mode: 0o100644,
+                dev: 0,
+                ino: 0,
+                apparent_size: size,
+            })
+        }),
+    };
+    let entries = vec![
+        entry("/p", 1111, Some(100)),
+        entry("/p/lab", 1100, Some(200)),
+        entry("/p/lab/a.nc", 1000, Some(100)),
+        entry("/p/lab/b.nc", 100, Some(200)),
+        entry("/p/other", 11, Some(100)),
+        entry("/p/other/c.nc", 11, None),
+    ];
+    let kept: Vec<(String, u64)> = filter_by_gid(entries, Path::new("/p"), 200)
+        .into_iter()
+        .map(|e| (e.path.display().to_string(), e.size))
+        .collect();
+    assert_eq!(
+        kept,
+        [
+            ("/p".to_string(), 100),
+            ("/p/lab".to_string(), 100),
+            ("/p/lab/b.nc".to_string(), 100),
+        ]
+    );
+}
+
+#[test]
+fn test_group_id() {
+    assert_eq!(group_id("root"), Some(0));
+    assert_eq!(group_id("no-such-group-rudu"), None);
+}
+
+#[test]
+fn test_path_matcher_regexes() {
+    let matcher = build_exclude_matcher(&expand_exclude_patterns(&["*.tmp".to_string()]))
+        .unwrap()
+        .with_regexes(&[r"run_\d{8}_\d{6}$".to_string()])
+        .unwrap();
+
+    assert!(matcher.is_match(Path::new("/scratch/run_20240101_120000")));
+    assert!(matcher.is_match(Path::new("scratch.tmp")));
+    assert!(!matcher.is_match(Path::new("/scratch/run_20240101_120000/out.nc")));
+    assert!(!matcher.is_match(Path::new("/scratch/run_latest")));
+
+    let err = build_exclude_matcher(&[])
+        .unwrap()
+        .with_regexes(&["run_(".to_string()])
+        .unwrap_err();
+    assert!(err.to_string().contains("run_("));
+}
+
+#[test]
+fn test_read_pattern_file() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("excludes.txt");
+    std::fs::write(
+        &path,
+        "# site-wide exclusions\n.git\n\n  node_modules  \n**/*.tmp\n   # indented comment\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+        read_pattern_file(&path).unwrap(),
+        [".git", "node_modules", "**/*.tmp"]
+    );
+    assert!(read_pattern_file(&dir.path().join("missing.txt")).is_err());
+}
+
+#[test]
+fn test_paginate() {
+    let entries: Vec<FileEntry> = (0..10)
+        .map(|i| FileEntry {
+            path: PathBuf::from(format!("/data/{}", i)),
+            size: i,
+            owner: None,
+            inodes: None,
+            entry_type: EntryType::File,
+            previous_size: None,
+            mtime: None,
+            stat: None,
+        })
+        .collect();
+    let sizes = |page: &[FileEntry]| page.iter().map(|e| e.size).collect::<Vec<_>>();
+
+    let mut page = entries.clone();
+    assert_eq!(paginate(&mut page, 0, Some(3)), 7);
+    assert_eq!(sizes(&page), [0, 1, 2]);
+
+    let mut page = entries.clone();
+    assert_eq!(paginate(&mut page, 4, Some(3)), 3);
+    assert_eq!(sizes(&page), [4, 5, 6]);
+
+    let mut page = entries.clone();
+    assert_eq!(paginate(&mut page, 8, None), 0);
+    assert_eq!(sizes(&page), [8, 9]);
+
+    let mut page = entries;
+    assert_eq!(paginate(&mut page, 20, Some(5)), 0);
+    assert!(page.is_empty());
+}
+
+#[test]
+fn test_format_count() {
+    assert_eq!(format_count(0), "0");
+    assert_eq!(format_count(999), "999");
+    assert_eq!(format_count(1000), "1,000");
+    assert_eq!(format_count(1_234_567), "1,234,567");
+    assert_eq!(format_count(123_456_789), "123,456,789");
+}
+
 #[test]
 fn test_sort_entries() {
     let entries = vec![
@@ -27,6 +300,9 @@ fn test_sort_entries() {
             owner: Some("user".to_string()),
             inodes: None,
             entry_type: EntryType::File,
+            previous_size: None,
+            mtime: None,
+            stat: None,
         },
         FileEntry {
             path: PathBuf::from("/home/user/a.txt"),
@@ -34,6 +310,9 @@ fn test_sort_entries() {
             owner: Some("user".to_string()),
             inodes: None,
             entry_type: EntryType::File,
+            previous_size: None,
+            mtime: None,
+            stat: None,
         },
         FileEntry {
             path: PathBuf::from("/home/user/c.txt"),
@@ -41,19 +320,22 @@ fn test_sort_entries() {
             owner: Some("user".to_string()),
             inodes: None,
             entry_type: EntryType::File,
+            previous_size: None,
+            mtime: None,
+            stat: None,
         },
     ];
 
     // Test sorting by name
     let mut name_sorted = entries.clone();
-    sort_entries(&mut name_sorted, SortKey::Name);
+    sort_entries(&mut name_sorted, &[SortKey::Name], false);
     assert_eq!(name_sorted[0].path, PathBuf::from("/home/user/a.txt"));
     assert_eq!(name_sorted[1].path, PathBuf::from("/home/user/b.txt"));
     assert_eq!(name_sorted[2].path, PathBuf::from("/home/user/c.txt"));
 
     // Test sorting by size (largest first)
     let mut size_sorted = entries.clone();
-    sort_entries(&mut size_sorted, SortKey::Size);
+    sort_entries(&mut size_sorted, &[SortKey::Size], false);
     assert_eq!(size_sorted[0].size, 2048);
     assert_eq!(size_sorted[1].size, 1024);
     assert_eq!(size_sorted[2].size, 512);
@@ -100,7 +382,10 @@ fn test_build_exclude_matcher_invalid_pattern() {
     ];
 
     let matcher = build_exclude_matcher(&patterns);
-    assert!(matcher.is_err());
+    assert!(matches!(
+        matcher,
+        Err(rudu::Error::InvalidPattern { ref pattern, .. }) if pattern == "[invalid"
+    ));
 }
 
 // ── disk_usage ────────────────────────────────────────────────────────────────
@@ -124,6 +409,58 @@ fn test_disk_usage_zero_for_missing_path() {
     assert_eq!(usage, 0, "disk_usage should return 0 for a missing path");
 }
 
+#[test]
+fn test_disk_usage_and_mtime_match_metadata() {
+    use std::os::unix::fs::MetadataExt;
+
+    let tmp = TempDir::new().unwrap();
+    let file_path = tmp.path().join("sample.txt");
+    std::fs::write(&file_path, "x".repeat(4096)).unwrap();
+    let metadata = std::fs::metadata(&file_path).unwrap();
+
+    let (usage, mtime) = disk_usage_and_mtime(&file_path);
+    assert_eq!(usage, disk_usage(&file_path));
+    assert_eq!(mtime, Some(metadata.mtime() as u64));
+    assert_eq!(
+        disk_usage_and_mtime(std::path::Path::new("/nonexistent/path")),
+        (0, None)
+    );
+}
+
+#[test]
+fn test_disk_usage_and_stat_match_metadata() {
+    let tmp = TempDir::new().unwrap();
+    let file_path = tmp.path().join("sample.txt");
+    std::fs::write(&file_path, "x".repeat(4096)).unwrap();
+    let metadata = std::fs::metadata(&file_path).unwrap();
+
+    let (usage, mtime, stat) = disk_usage_and_stat(&file_path);
+    assert_eq!((usage, mtime), disk_usage_and_mtime(&file_path));
+    assert_eq!(stat, Some(EntryStat::from_metadata(&metadata)));
+    assert_eq!(stat.unwrap().nlink, 1);
+    assert_eq!(
+        disk_usage_and_stat(std::path::Path::new("/nonexistent/path")),
+        (0, None, None)
+    );
+}
+
+#[test]
+fn test_cached_stat_matches_lstat() {
+    let tmp = TempDir::new().unwrap();
+    let file_path = tmp.path().join("sample.bin");
+    std::fs::write(&file_path, vec![1u8; 10_000]).unwrap();
+    let link_path = tmp.path().join("link");
+    std::os::unix::fs::symlink(&file_path, &link_path).unwrap();
+
+    for path in [&file_path, &link_path] {
+        assert_eq!(disk_usage_and_cached_stat(path), disk_usage_and_stat(path));
+    }
+    assert_eq!(
+        disk_usage_and_cached_stat(std::path::Path::new("/nonexistent/path")),
+        (0, None, None)
+    );
+}
+
 // ── path_hash ─────────────────────────────────────────────────────────────────
 
 #[test]
@@ -195,6 +532,9 @@ fn test_sort_entries_size_ties_are_stable_by_relative_order() {
             owner: None,
             inodes: None,
             entry_type: EntryType::File,
+            previous_size: None,
+            mtime: None,
+            stat: None,
         },
         FileEntry {
             path: PathBuf::from("/second"),
@@ -202,9 +542,12 @@ fn test_sort_entries_size_ties_are_stable_by_relative_order() {
             owner: None,
             inodes: None,
             entry_type: EntryType::File,
+            previous_size: None,
+            mtime: None,
+            stat: None,
         },
     ];
-    sort_entries(&mut entries, SortKey::Size);
+    sort_entries(&mut entries, &[SortKey::Size], false);
     // Both have the same size; stability means /first stays before /second
     assert_eq!(entries[0].path, PathBuf::from("/first"));
     assert_eq!(entries[1].path, PathBuf::from("/second"));
@@ -213,8 +556,8 @@ fn test_sort_entries_size_ties_are_stable_by_relative_order() {
 #[test]
 fn test_sort_entries_empty_slice_does_not_panic() {
     let mut entries: Vec<FileEntry> = vec![];
-    sort_entries(&mut entries, SortKey::Name);
-    sort_entries(&mut entries, SortKey::Size);
+    sort_entries(&mut entries, &[SortKey::Name], false);
+    sort_entries(&mut entries, &[SortKey::Size], false);
     // No assertions needed — reaching here without panic is the goal
 }
 
@@ -226,7 +569,178 @@ fn test_sort_entries_single_entry_unchanged() {
         owner: None,
         inodes: None,
         entry_type: EntryType::Dir,
+        previous_size: None,
+        mtime: None,
+        stat: None,
     }];
-    sort_entries(&mut entries, SortKey::Size);
+    sort_entries(&mut entries, &[SortKey::Size], false);
     assert_eq!(entries[0].path, PathBuf::from("/only"));
 }
+
+#[test]
+fn test_has_exclude_marker() {
+    let temp = TempDir::new().unwrap();
+    let dir = temp.path();
+    assert!(!has_exclude_marker(dir));
+
+    // CACHEDIR.TAG without the standard signature is ignored
+    std::fs::write(dir.join(CACHEDIR_TAG), "not a real tag").unwrap();
+    assert!(!has_exclude_marker(dir));
+
+    std::fs::write(
+        dir.join(CACHEDIR_TAG),
+        "Signature: 8a477f597d28d172789f06886806bc55\n# created by some tool\n",
+    )
+    .unwrap();
+    assert!(has_exclude_marker(dir));
+
+    std::fs::remove_file(dir.join(CACHEDIR_TAG)).unwrap();
+    std::fs::write(dir.join(RUDUIGNORE_MARKER), "").unwrap();
+    assert!(has_exclude_marker(dir));
+}
+
+#[test]
+fn test_sort_entries_compound_keys_and_reverse() {
+    let entry = |path: &str, size| FileEntry {
+        path: PathBuf::from(path),
+        size,
+        owner: None,
+        inodes: None,
+        entry_type: EntryType::File,
+        previous_size: None,
+        mtime: None,
+        stat: None,
+    };
+    let entries = vec![
+        entry("/b", 512),
+        entry("/c", 1024),
+        entry("/a", 512),
+        entry("/d", 256),
+    ];
+    let paths = |entries: &[FileEntry]| -> Vec<String> {
+        entries
+            .iter()
+            .map(|e| e.path.display().to_string())
+            .collect()
+    };
+
+    // Size ties are broken by name
+    let mut sorted = entries.clone();
+    sort_entries(&mut sorted, &[SortKey::Size, SortKey::Name], false);
+    assert_eq!(paths(&sorted), ["/c", "/a", "/b", "/d"]);
+
+    // Reversed: smallest first, ties by descending name
+    sort_entries(&mut sorted, &[SortKey::Size, SortKey::Name], true);
+    assert_eq!(paths(&sorted), ["/d", "/b", "/a", "/c"]);
+
+    let mut sorted = entries;
+    sort_entries(&mut sorted, &[SortKey::Name], true);
+    assert_eq!(paths(&sorted), ["/d", "/c", "/b", "/a"]);
+}
+
+#[test]
+fn test_sort_entries_by_mtime_owner_and_inodes() {
+    let entry = |path: &str, mtime, owner: Option<&str>, inodes| FileEntry {
+        path: PathBuf::from(path),
+        size: 0,
+        owner: owner.map(str::to_string),
+        inodes,
+        entry_type: EntryType::Dir,
+        previous_size: None,
+        mtime,
+        stat: None,
+    };
+    let mut entries = vec![
+        entry("/a", Some(100), Some("carol"), Some(5)),
+        entry("/b", None, None, None),
+        entry("/c", Some(300), Some("alice"), Some(50)),
+        entry("/d", Some(200), Some("bob"), Some(5)),
+    ];
+    let paths = |entries: &[FileEntry]| -> Vec<String> {
+        entries
+            .iter()
+            .map(|e| e.path.display().to_string())
+            .collect()
+    };
+
+    sort_entries(&mut entries, &[SortKey::Mtime], false);
+    assert_eq!(paths(&entries), ["/c", "/d", "/a", "/b"]);
+
+    sort_entries(&mut entries, &[SortKey::Owner], false);
+    assert_eq!(paths(&entries), ["/c", "/d", "/a", "/b"]);
+
+    sort_entries(&mut entries, &[SortKey::Inodes, SortKey::Name], false);
+    assert_eq!(paths(&entries), ["/c", "/a", "/d", "/b"]);
+}
+
+#[test]
+fn test_fs_type_in_mount_table() {
+    let table = "\
+/dev/sda1 / ext4 rw,relatime 0 0
+filer:/vol/data /data nfs4 rw,vers=4.1 0 0
+filer:/vol/data/.snapshot /data/.snapshot nfs4 ro 0 0
+/dev/sdb1 /mnt/my\\040disk xfs rw 0 0
+tmpfs /data/scratch tmpfs rw 0 0
+/dev/sdc1 /data/scratch ext4 rw 0 0
+";
+
+    assert_eq!(
+        fs_type_in_mount_table(table, Path::new("/data/.snapshot")).as_deref(),
+        Some("nfs4")
+    );
+    assert_eq!(
+        fs_type_in_mount_table(table, Path::new("/mnt/my disk")).as_deref(),
+        Some("xfs")
+    );
+    // The last filesystem mounted on a path is the visible one
+    assert_eq!(
+        fs_type_in_mount_table(table, Path::new("/data/scratch")).as_deref(),
+        Some("ext4")
+    );
+    assert_eq!(fs_type_in_mount_table(table, Path::new("/data/sub")), None);
+}
+
+#[test]
+fn test_containing_fs_type_in_mount_table() {
+    let table = "\
+/dev/sda1 / ext4 rw,relatime 0 0
+10.0.0.1@tcp:/gdata /g/data lustre rw 0 0
+tmpfs /g/data/tmp tmpfs rw 0 0
+";
+
+    let fs_type = |path: &str| containing_fs_type_in_mount_table(table, Path::new(path));
+    assert_eq!(fs_type("/g/data/project/run1").as_deref(), Some("lustre"));
+    assert_eq!(fs_type("/g/data").as_deref(), Some("lustre"));
+    assert_eq!(fs_type("/g/data/tmp/x").as_deref(), Some("tmpfs"));
+    // Prefixes only match whole components
+    assert_eq!(fs_type("/g/database").as_deref(), Some("ext4"));
+    assert_eq!(fs_type("/home/user").as_deref(), Some("ext4"));
+}
+
+#[test]
+fn test_containing_mount_in_table() {
+    let table = "\
+/dev/sda1 / ext4 rw,relatime 0 0
+/dev/sdb1 /archive xfs rw,noatime,attr2,prjquota 0 0
+";
+
+    let mount = containing_mount_in_table(table, Path::new("/archive/2019")).unwrap();
+    assert_eq!(mount.source, Path::new("/dev/sdb1"));
+    assert_eq!(mount.mountpoint, Path::new("/archive"));
+    assert_eq!(mount.fs_type, "xfs");
+    assert!(mount.has_option("noatime"));
+    assert!(mount.has_option("prjquota"));
+    assert!(!mount.has_option("quota"));
+    let root = containing_mount_in_table(table, Path::new("/home/user")).unwrap();
+    assert_eq!(root.options, "rw,relatime");
+    assert_eq!(containing_mount_in_table("", Path::new("/")), None);
+}
+
+#[test]
+fn test_user_and_group_names() {
+    assert_eq!(user_name(0).as_deref(), Some("root"));
+    assert!(group_name(0).is_some());
+    // IDs no passwd or group file hands out
+    assert_eq!(user_name(3_999_999_999), None);
+    assert_eq!(group_name(3_999_999_999), None);
+}